@@ -0,0 +1,35 @@
+//! Benchmarks for encoding a large user list in every wire format the API
+//! serves.
+
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use hex_play_api::test_support::{ResponseFormat, encode_grpc_list_users, encode_http_list_users};
+use hex_play_core::user::User;
+
+const USER_COUNT: u64 = 10_000;
+
+fn users() -> Vec<User> {
+    (1..=USER_COUNT)
+        .map(|id| User::fake_with_age(id, format!("User {id}"), format!("user{id}@example.com"), (id % 100) as i16))
+        .collect()
+}
+
+fn bench_list_users(c: &mut Criterion) {
+    let users = users();
+
+    let mut group = c.benchmark_group("list_users_10k");
+    group.throughput(Throughput::Elements(USER_COUNT));
+    for (name, format) in [
+        ("json", ResponseFormat::Json),
+        ("msgpack", ResponseFormat::MessagePack),
+        ("cbor", ResponseFormat::Cbor),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter_batched(|| users.clone(), |users| encode_http_list_users(format, users), BatchSize::LargeInput)
+        });
+    }
+    group.bench_function("protobuf", |b| b.iter_batched(|| users.clone(), encode_grpc_list_users, BatchSize::LargeInput));
+    group.finish();
+}
+
+criterion_group!(benches, bench_list_users);
+criterion_main!(benches);
@@ -1,6 +1,18 @@
+use std::{env, path::PathBuf};
+
+const PROTOS: &[&str] = &[
+    "proto/hexplay/system/v1/system.proto",
+    "proto/hexplay/user/v1/user.proto",
+    "proto/hexplay/user_info/v1/user_info.proto",
+];
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_prost_build::compile_protos("proto/system.proto")?;
-    tonic_prost_build::compile_protos("proto/user.proto")?;
+    // The descriptor set lets tests compare the API against the committed
+    // snapshot for breaking changes.
+    let descriptor_path = PathBuf::from(env::var("OUT_DIR")?).join("hexplay_descriptor.bin");
+    tonic_prost_build::configure()
+        .file_descriptor_set_path(descriptor_path)
+        .compile_protos(PROTOS, &["proto"])?;
 
     println!("cargo:rerun-if-changed=proto");
 
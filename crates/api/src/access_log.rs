@@ -0,0 +1,153 @@
+//! One structured event per HTTP request served, under the
+//! [`ACCESS_LOG_TARGET`] target, so deployments can send access logs
+//! somewhere of their own.
+//!
+//! Each event has the method, the matched route template rather than the
+//! path, the status, the latency in milliseconds, the size of the response
+//! body when it is known up front, the request id and the user the request
+//! acted for.
+
+use std::time::Instant;
+
+use axum::{
+    body::HttpBody as _,
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::context::REQUEST_ID_HEADER;
+
+/// Target of the access log events.
+pub const ACCESS_LOG_TARGET: &str = "access";
+
+/// Route of requests matching none, so unknown paths are not logged as
+/// they were sent.
+const UNMATCHED: &str = "unmatched";
+
+/// Response extension naming the user a request acted for, added where the
+/// request context is built.
+#[derive(Debug, Clone)]
+pub(crate) struct Caller {
+    pub(crate) user_id: Option<String>,
+}
+
+pub(crate) async fn log_http(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request.extensions().get::<MatchedPath>().map_or(UNMATCHED, MatchedPath::as_str).to_string();
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let started = Instant::now();
+
+    let mut response = next.run(request).await;
+
+    let caller = response.extensions_mut().remove::<Caller>();
+    tracing::info!(
+        target: ACCESS_LOG_TARGET,
+        method,
+        route,
+        status = response.status().as_u16(),
+        latency_ms = started.elapsed().as_millis() as u64,
+        bytes = response.body().size_hint().exact(),
+        request_id,
+        user_id = caller.and_then(|caller| caller.user_id),
+        "Request served"
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, StatusCode},
+        middleware::from_fn,
+        response::{IntoResponse as _, Response},
+        routing::get,
+    };
+    use tower::ServiceExt;
+    use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt as _};
+
+    use super::{Caller, log_http};
+
+    // ===================
+    // Test Helpers
+    // ===================
+    /// Collects what is logged, to read back as JSON lines.
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Captured {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    async fn as_user() -> Response {
+        let mut response = "hello".into_response();
+        response.extensions_mut().insert(Caller {
+            user_id: Some("42".to_string()),
+        });
+        response
+    }
+
+    /// The access log event of one request to `uri`.
+    async fn access_event(uri: &str) -> serde_json::Value {
+        let captured = Captured::default();
+        let subscriber = tracing_subscriber::registry().with(tracing_subscriber::fmt::layer().json().with_writer(captured.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let app = Router::new().route("/users/{id}", get(as_user)).layer(from_fn(log_http));
+
+        app.oneshot(Request::builder().uri(uri).header("x-request-id", "req-1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        let logged = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        serde_json::from_str(logged.lines().next().expect("an access log event")).unwrap()
+    }
+
+    // ===================
+    // Tests: log_http
+    // ===================
+    #[tokio::test]
+    async fn test_log_http_records_request() {
+        let event = access_event("/users/7").await;
+
+        assert_eq!(event["target"], "access");
+        assert_eq!(event["fields"]["method"], "GET");
+        assert_eq!(event["fields"]["route"], "/users/{id}");
+        assert_eq!(event["fields"]["status"], StatusCode::OK.as_u16());
+        assert_eq!(event["fields"]["bytes"], 5);
+        assert_eq!(event["fields"]["request_id"], "req-1");
+        assert_eq!(event["fields"]["user_id"], "42");
+        assert!(event["fields"]["latency_ms"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_log_http_hides_unmatched_paths() {
+        let event = access_event("/secret/path").await;
+
+        assert_eq!(event["fields"]["route"], "unmatched");
+        assert_eq!(event["fields"]["status"], StatusCode::NOT_FOUND.as_u16());
+        assert!(event["fields"].get("user_id").is_none());
+    }
+}
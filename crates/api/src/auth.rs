@@ -0,0 +1,265 @@
+//! Who a request comes from.
+//!
+//! Clients authenticate with one of the configured API keys, sent as
+//! `Authorization: Bearer`. Each key acts as a user, within the tenant the
+//! key is for if any, and an admin's key may also use the admin routes, such
+//! as impersonation. Requests without a key are anonymous. Other bearer
+//! tokens, such as impersonation and SCIM tokens, are left to the routes that
+//! take them.
+//!
+//! The tenant of a request is only ever its key's, or for an impersonation
+//! that of the admin who started it. `x-tenant-id` may name it too, but a
+//! request naming any other tenant is refused, with 403 or
+//! `PERMISSION_DENIED`, rather than acting within it.
+
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{HeaderMap, Request, header::AUTHORIZATION},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use hex_play_core::{context::RequestContext, session::Impersonation, user::UserId};
+use hex_play_utils::secret::Secret;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use sha2::{Digest as _, Sha256};
+
+use crate::http::error::Error;
+
+pub(crate) const TENANT_HEADER: &str = "x-tenant-id";
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct ApiKeyConfig {
+    /// The key clients send as `Authorization: Bearer`, e.g.
+    /// `HPLAY__API_KEYS__OPS__KEY=${OPS_API_KEY}`.
+    pub key: Secret,
+
+    /// Id of the user requests with the key act as, e.g.
+    /// `HPLAY__API_KEYS__OPS__USER_ID=1`.
+    pub user_id: u64,
+
+    /// (optional) Tenant requests with the key act within, e.g.
+    /// `HPLAY__API_KEYS__OPS__TENANT=acme`. Without one they act outside of
+    /// any tenant.
+    #[serde(default)]
+    pub tenant: Option<String>,
+
+    /// (optional) Whether the key may use the admin routes, e.g.
+    /// `HPLAY__API_KEYS__OPS__ADMIN=true`. Defaults to false.
+    #[serde(default)]
+    pub admin: bool,
+}
+
+/// The holder of an API key a request was authenticated with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Principal {
+    pub(crate) user_id: UserId,
+    pub(crate) tenant: Option<String>,
+    pub(crate) admin: bool,
+}
+
+impl Principal {
+    /// Makes `context` act as the key's user, within its tenant.
+    pub(crate) fn apply_to(&self, context: &mut RequestContext) {
+        context.actor = Some(self.user_id.to_string());
+        context.tenant = self.tenant.clone();
+    }
+}
+
+/// The configured API keys, by the digest of the key. Digests are looked up
+/// so a key is never compared with what a client sent byte by byte.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ApiKeys(Arc<HashMap<Vec<u8>, Principal>>);
+
+impl ApiKeys {
+    /// Every key in `configs`, keyed by a name for the operator's benefit.
+    /// Empty keys are left out.
+    pub(crate) fn new(configs: &HashMap<String, ApiKeyConfig>) -> Self {
+        let keys = configs
+            .values()
+            .filter(|config| !config.key.is_empty())
+            .map(|config| {
+                let principal = Principal {
+                    user_id: UserId::new(config.user_id),
+                    tenant: config.tenant.clone(),
+                    admin: config.admin,
+                };
+                (Sha256::digest(config.key.expose()).to_vec(), principal)
+            })
+            .collect();
+        Self(Arc::new(keys))
+    }
+
+    /// The holder of the API key `headers` carry as a bearer token, `None`
+    /// if they carry none or another token.
+    pub(crate) fn authenticate(&self, headers: &HeaderMap) -> Option<Principal> {
+        let presented = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))?;
+        self.0.get(Sha256::digest(presented.trim()).as_slice()).cloned()
+    }
+}
+
+/// Checks that the tenant `headers` name in `x-tenant-id`, if any, is
+/// `tenant`, the one the request is authenticated for. Anonymous requests
+/// act within none.
+pub(crate) fn check_tenant(tenant: Option<&str>, headers: &HeaderMap) -> Result<(), String> {
+    let Some(named) = headers.get(TENANT_HEADER) else {
+        return Ok(());
+    };
+    match tenant {
+        Some(tenant) if named.as_bytes() == tenant.as_bytes() => Ok(()),
+        _ => Err(format!("not authorized for the tenant named in {TENANT_HEADER}")),
+    }
+}
+
+/// Adds the [`Principal`] of a request carrying one of `api_keys` to its
+/// extensions, for the request context and the admin routes to read.
+/// Requests naming a tenant other than their key's, or than their
+/// impersonation's, get 403.
+pub(crate) async fn authenticate_http(State(api_keys): State<ApiKeys>, mut request: Request<Body>, next: Next) -> Response {
+    let principal = api_keys.authenticate(request.headers());
+    let tenant = match (&principal, request.extensions().get::<Impersonation>()) {
+        (Some(principal), _) => principal.tenant.as_deref(),
+        (None, Some(impersonation)) => impersonation.tenant.as_deref(),
+        (None, None) => None,
+    };
+    if let Err(message) = check_tenant(tenant, request.headers()) {
+        return Error::Forbidden(message).into_response();
+    }
+    if let Some(principal) = principal {
+        request.extensions_mut().insert(principal);
+    }
+    next.run(request).await
+}
+
+/// Lets through only requests [`authenticate_http`] found an admin's API key
+/// on. Requests without a key get 401 and those with another key 403.
+pub(crate) async fn require_admin(request: Request<Body>, next: Next) -> Response {
+    match request.extensions().get::<Principal>() {
+        Some(principal) if principal.admin => next.run(request).await,
+        Some(_) => Error::Forbidden("only admins may use the admin routes".to_string()).into_response(),
+        None => Error::Unauthorized("expected an admin's API key as `Authorization: Bearer`".to_string()).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use axum::http::{HeaderMap, HeaderValue};
+    use hex_play_core::{context::RequestContext, user::UserId};
+    use hex_play_utils::secret::Secret;
+
+    use super::{ApiKeyConfig, ApiKeys, Principal, check_tenant};
+
+    // ===================
+    // Tests: ApiKeys
+    // ===================
+    #[test]
+    fn test_authenticates_configured_key() {
+        let api_keys = create_api_keys();
+
+        let principal = api_keys.authenticate(&bearer("admin-key"));
+
+        assert_eq!(
+            principal,
+            Some(Principal {
+                user_id: UserId::new(1),
+                tenant: Some("acme".to_string()),
+                admin: true
+            })
+        );
+    }
+
+    #[test]
+    fn test_other_bearer_tokens_are_anonymous() {
+        let api_keys = create_api_keys();
+
+        assert_eq!(api_keys.authenticate(&bearer("scim-token")), None);
+        assert_eq!(api_keys.authenticate(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_empty_key_is_ignored() {
+        let api_keys = ApiKeys::new(&HashMap::from([("empty".to_string(), api_key("", 1, true))]));
+
+        assert_eq!(api_keys.authenticate(&bearer("")), None);
+    }
+
+    // ===================
+    // Tests: check_tenant
+    // ===================
+    #[test]
+    fn test_authenticated_tenant_may_be_named() {
+        assert_eq!(check_tenant(Some("acme"), &tenant("acme")), Ok(()));
+        assert_eq!(check_tenant(Some("acme"), &HeaderMap::new()), Ok(()));
+    }
+
+    #[test]
+    fn test_other_tenant_is_refused() {
+        assert!(check_tenant(Some("acme"), &tenant("globex")).is_err());
+    }
+
+    #[test]
+    fn test_tenant_is_refused_outside_of_any() {
+        assert!(check_tenant(None, &tenant("acme")).is_err());
+    }
+
+    // ===================
+    // Tests: Principal
+    // ===================
+    #[test]
+    fn test_principal_acts_within_its_tenant() {
+        let principal = create_api_keys().authenticate(&bearer("admin-key")).unwrap();
+        let mut context = RequestContext::internal();
+
+        principal.apply_to(&mut context);
+
+        assert_eq!(context.actor.as_deref(), Some("1"));
+        assert_eq!(context.tenant.as_deref(), Some("acme"));
+    }
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn api_key(key: &str, user_id: u64, admin: bool) -> ApiKeyConfig {
+        ApiKeyConfig {
+            key: Secret::new(key),
+            user_id,
+            tenant: None,
+            admin,
+        }
+    }
+
+    /// `admin-key` for admin 1 of tenant `acme`, `user-key` for user 2 of no
+    /// tenant.
+    fn create_api_keys() -> ApiKeys {
+        ApiKeys::new(&HashMap::from([
+            (
+                "ops".to_string(),
+                ApiKeyConfig {
+                    tenant: Some("acme".to_string()),
+                    ..api_key("admin-key", 1, true)
+                },
+            ),
+            ("app".to_string(), api_key("user-key", 2, false)),
+        ]))
+    }
+
+    fn tenant(tenant: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-tenant-id", HeaderValue::from_str(tenant).unwrap());
+        headers
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_str(&format!("Bearer {token}")).unwrap());
+        headers
+    }
+}
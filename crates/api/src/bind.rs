@@ -0,0 +1,22 @@
+//! Where a server accepts connections.
+
+use std::fmt;
+
+use crate::unix_socket::UnixSocketConfig;
+
+/// One listener of the HTTP or gRPC server. Each runs as its own subsystem.
+#[derive(Debug, Clone)]
+pub(crate) enum Bind {
+    /// A TCP address such as `0.0.0.0:3000` or `[::1]:3000`.
+    Tcp(String),
+    Unix(UnixSocketConfig),
+}
+
+impl fmt::Display for Bind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Bind::Tcp(addr) => write!(f, "{addr}"),
+            Bind::Unix(config) => write!(f, "unix:{}", config.path.display()),
+        }
+    }
+}
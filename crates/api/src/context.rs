@@ -0,0 +1,113 @@
+//! Builds the [`RequestContext`] for an incoming HTTP or gRPC request.
+
+use std::time::{Duration, Instant};
+
+use axum::http::{HeaderMap, header::ACCEPT_LANGUAGE};
+use hex_play_core::{context::RequestContext, i18n::negotiate_locale};
+
+pub(crate) const REQUEST_ID_HEADER: &str = "x-request-id";
+const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+const READ_PRIMARY_HEADER: &str = "x-read-primary";
+
+/// Reads the context from request headers. A request without an
+/// `x-request-id` gets a new one, and one with `x-read-primary: true` reads
+/// from the primary database, see [`RequestContext::require_primary`]. The
+/// actor and tenant are left unset for the caller to set from what the
+/// request is authenticated with, never from a header, see
+/// [`auth`](crate::auth).
+pub(crate) fn from_headers(headers: &HeaderMap) -> RequestContext {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+
+    let mut context = RequestContext::new(header(REQUEST_ID_HEADER).map_or_else(|| uuid::Uuid::new_v4().to_string(), str::to_string));
+    context.deadline = header(GRPC_TIMEOUT_HEADER).and_then(parse_grpc_timeout).map(|timeout| Instant::now() + timeout);
+    context.locale = header(ACCEPT_LANGUAGE.as_str()).and_then(negotiate_locale);
+    if header(READ_PRIMARY_HEADER).is_some_and(|value| value.eq_ignore_ascii_case("true")) {
+        context = context.require_primary();
+    }
+    context
+}
+
+/// Parses a gRPC `grpc-timeout` value: up to 8 digits and a unit.
+fn parse_grpc_timeout(value: &str) -> Option<Duration> {
+    let (digits, unit) = value.split_at_checked(value.len().checked_sub(1)?)?;
+    if digits.is_empty() || digits.len() > 8 {
+        return None;
+    }
+    let amount: u64 = digits.parse().ok()?;
+
+    match unit {
+        "H" => Some(Duration::from_secs(amount * 3600)),
+        "M" => Some(Duration::from_secs(amount * 60)),
+        "S" => Some(Duration::from_secs(amount)),
+        "m" => Some(Duration::from_millis(amount)),
+        "u" => Some(Duration::from_micros(amount)),
+        "n" => Some(Duration::from_nanos(amount)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::http::{HeaderMap, HeaderValue};
+
+    use super::{from_headers, parse_grpc_timeout};
+
+    // ===================
+    // Tests: from_headers
+    // ===================
+
+    #[test]
+    fn test_reads_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", HeaderValue::from_static("req-1"));
+        headers.insert("x-tenant-id", HeaderValue::from_static("acme"));
+        headers.insert("accept-language", HeaderValue::from_static("de-CH, de;q=0.9"));
+        headers.insert("grpc-timeout", HeaderValue::from_static("5S"));
+
+        let context = from_headers(&headers);
+
+        assert_eq!(context.request_id, "req-1");
+        assert_eq!(context.actor, None);
+        assert_eq!(context.tenant, None);
+        assert_eq!(context.locale.as_deref(), Some("de-CH"));
+        assert!(context.remaining().is_some_and(|remaining| remaining <= Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_generates_request_id() {
+        let context = from_headers(&HeaderMap::new());
+
+        assert!(!context.request_id.is_empty());
+        assert_eq!(context.deadline, None);
+        assert!(!context.requires_primary);
+    }
+
+    #[test]
+    fn test_read_primary_header_requires_primary() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-read-primary", HeaderValue::from_static("true"));
+        assert!(from_headers(&headers).requires_primary);
+
+        headers.insert("x-read-primary", HeaderValue::from_static("no"));
+        assert!(!from_headers(&headers).requires_primary);
+    }
+
+    #[test]
+    fn test_parse_grpc_timeout() {
+        assert_eq!(parse_grpc_timeout("250m"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_grpc_timeout("2M"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_grpc_timeout("123456789S"), None);
+        assert_eq!(parse_grpc_timeout("S"), None);
+        assert_eq!(parse_grpc_timeout("5x"), None);
+    }
+
+    #[test]
+    fn test_locale_is_negotiated_against_catalogs() {
+        let mut headers = HeaderMap::new();
+        headers.insert("accept-language", HeaderValue::from_static("fr-FR, en;q=0.5, de;q=0.8"));
+
+        assert_eq!(from_headers(&headers).locale.as_deref(), Some("de"));
+    }
+}
@@ -1,5 +1,7 @@
 //! API-level error types for infrastructure concerns.
 
+use std::any::Any;
+
 use hex_play_core::Error as CoreError;
 
 /// Infrastructure errors specific to the API layer.
@@ -13,6 +15,9 @@ pub enum ApiError {
 
     #[error("Failed to parse address: {0}")]
     AddressParse(String),
+
+    #[error("Failed to install the metrics recorder: {0}")]
+    MetricsRecorder(String),
 }
 
 impl From<ApiError> for CoreError {
@@ -20,3 +25,13 @@ impl From<ApiError> for CoreError {
         CoreError::Infrastructure(err.to_string())
     }
 }
+
+/// The message a handler panicked with, for logging the panic caught by
+/// `CatchPanicLayer`.
+pub(crate) fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload")
+}
@@ -0,0 +1,231 @@
+//! Fault injection for resilience testing: delays, failed responses and
+//! dropped connections on the user-facing HTTP routes and on gRPC, so
+//! clients' timeouts and retries can be exercised against a real server.
+//! Only compiled when the `fault-injection` feature is enabled.
+//!
+//! Faults are injected inside the tracing and metrics middleware, so they
+//! show in the request span and the latency histograms like real ones.
+
+use std::{io, sync::Arc, time::Duration};
+
+use axum::{
+    Router,
+    body::Body,
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::{Next, from_fn_with_state},
+    response::{IntoResponse as _, Response},
+};
+use rand::RngExt as _;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tonic::Status;
+
+use crate::{http::problem::Problem, reporting::Failure};
+
+/// How often requests are delayed, failed or dropped. Each probability is
+/// between 0, never, and 1, every request. The defaults inject nothing.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct FaultInjectionConfig {
+    /// (optional) Share of requests held for `delay_ms` before being
+    /// handled. Defaults to 0.
+    #[serde(default)]
+    pub delay_probability: f64,
+
+    /// (optional) Milliseconds a delayed request is held for. Defaults to
+    /// 1000.
+    #[serde(default)]
+    #[schemars(extend("default" = DEFAULT_DELAY_MS))]
+    pub delay_ms: Option<u64>,
+
+    /// (optional) Share of requests failed without being handled, with a
+    /// `500` over HTTP and `UNAVAILABLE` over gRPC. Defaults to 0.
+    #[serde(default)]
+    pub error_probability: f64,
+
+    /// (optional) Share of requests whose connection is cut after the
+    /// response head is sent, without being handled. Defaults to 0.
+    #[serde(default)]
+    pub drop_probability: f64,
+}
+
+const DEFAULT_DELAY_MS: u64 = 1000;
+
+impl FaultInjectionConfig {
+    fn is_enabled(&self) -> bool {
+        self.delay_probability > 0.0 || self.error_probability > 0.0 || self.drop_probability > 0.0
+    }
+
+    /// Picks the faults of one request: whether to delay it, and the fault,
+    /// if any, to answer with instead of handling it.
+    fn roll(&self) -> (Option<Duration>, Option<Fault>) {
+        let mut rng = rand::rng();
+        let delay = (rng.random::<f64>() < self.delay_probability).then(|| Duration::from_millis(self.delay_ms.unwrap_or(DEFAULT_DELAY_MS)));
+        let fault = if rng.random::<f64>() < self.error_probability {
+            Some(Fault::Error)
+        } else if rng.random::<f64>() < self.drop_probability {
+            Some(Fault::Drop)
+        } else {
+            None
+        };
+        (delay, fault)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fault {
+    Error,
+    Drop,
+}
+
+/// Injects the faults of `config` into every route of `router`, or leaves
+/// it be if there are none to inject.
+pub(crate) fn inject_http(router: Router, config: &FaultInjectionConfig) -> Router {
+    if !config.is_enabled() {
+        return router;
+    }
+    tracing::warn!(?config, "Injecting faults into HTTP requests");
+    router.layer(from_fn_with_state(Arc::new(config.clone()), http_faults))
+}
+
+/// Same as [`inject_http`], for the gRPC services.
+pub(crate) fn inject_grpc(router: Router, config: &FaultInjectionConfig) -> Router {
+    if !config.is_enabled() {
+        return router;
+    }
+    tracing::warn!(?config, "Injecting faults into gRPC calls");
+    router.layer(from_fn_with_state(Arc::new(config.clone()), grpc_faults))
+}
+
+async fn http_faults(State(config): State<Arc<FaultInjectionConfig>>, request: Request, next: Next) -> Response {
+    match apply_delay(&config).await {
+        Some(Fault::Error) => {
+            let mut response = Problem::new(StatusCode::INTERNAL_SERVER_ERROR, "Injected fault").into_response();
+            Failure::mark(&mut response, "Injected fault");
+            response
+        }
+        Some(Fault::Drop) => dropped(),
+        None => next.run(request).await,
+    }
+}
+
+async fn grpc_faults(State(config): State<Arc<FaultInjectionConfig>>, request: Request, next: Next) -> Response {
+    match apply_delay(&config).await {
+        // The status clients treat as transient and retry on.
+        Some(Fault::Error) => Status::unavailable("Injected fault").into_http(),
+        Some(Fault::Drop) => dropped(),
+        None => next.run(request).await,
+    }
+}
+
+/// Holds the request if it is to be delayed, then returns the fault to
+/// answer with, if any.
+async fn apply_delay(config: &FaultInjectionConfig) -> Option<Fault> {
+    let (delay, fault) = config.roll();
+    if let Some(delay) = delay {
+        tracing::debug!(delay_ms = delay.as_millis() as u64, "Injecting delay");
+        tokio::time::sleep(delay).await;
+    }
+    if let Some(fault) = fault {
+        tracing::debug!(?fault, "Injecting fault");
+    }
+    fault
+}
+
+/// A response whose body fails at once, so the server resets the HTTP/2
+/// stream or closes the HTTP/1 connection after sending the head.
+fn dropped() -> Response {
+    let body = tokio_stream::once(Err::<&'static [u8], _>(io::Error::new(io::ErrorKind::ConnectionReset, "Injected fault")));
+    Response::new(Body::from_stream(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, StatusCode},
+        routing::get,
+    };
+    use tower::ServiceExt as _;
+
+    use super::{FaultInjectionConfig, inject_grpc, inject_http};
+
+    // ===================
+    // Helpers
+    // ===================
+    fn app(config: &FaultInjectionConfig) -> Router {
+        inject_http(Router::new().route("/", get(|| async { "hello" })), config)
+    }
+
+    async fn call(app: Router) -> axum::response::Response {
+        app.oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    // ===================
+    // Tests: inject_http
+    // ===================
+    #[tokio::test]
+    async fn test_inject_http_passes_requests_without_faults() {
+        let response = call(app(&FaultInjectionConfig::default())).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_inject_http_fails_requests() {
+        let config = FaultInjectionConfig {
+            error_probability: 1.0,
+            ..FaultInjectionConfig::default()
+        };
+
+        let response = call(app(&config)).await;
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_inject_http_drops_requests() {
+        let config = FaultInjectionConfig {
+            drop_probability: 1.0,
+            ..FaultInjectionConfig::default()
+        };
+
+        let response = call(app(&config)).await;
+
+        assert!(axum::body::to_bytes(response.into_body(), usize::MAX).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_inject_http_delays_requests() {
+        let config = FaultInjectionConfig {
+            delay_probability: 1.0,
+            delay_ms: Some(50),
+            ..FaultInjectionConfig::default()
+        };
+        let started = Instant::now();
+
+        let response = call(app(&config)).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    // ===================
+    // Tests: inject_grpc
+    // ===================
+    #[tokio::test]
+    async fn test_inject_grpc_fails_calls_as_unavailable() {
+        let config = FaultInjectionConfig {
+            error_probability: 1.0,
+            ..FaultInjectionConfig::default()
+        };
+        let app = inject_grpc(Router::new().route("/", get(|| async { "hello" })), &config);
+
+        let response = call(app).await;
+
+        assert_eq!(response.headers()["grpc-status"], "14");
+    }
+}
@@ -1,53 +1,645 @@
-use std::sync::Arc;
+use std::{any::Any, sync::Arc};
 
-use hex_play_core::{CoreServices, Error};
+use axum::{body::Body, http::HeaderName};
+use hex_play_core::{CoreServices, Error, context::RequestContext, reporting::ErrorReporter};
+use schemars::JsonSchema;
+use serde::Deserialize;
 use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
-use tonic::transport::Server;
+use tokio_stream::wrappers::UnixListenerStream;
+use tonic::{
+    Request, Status,
+    service::Routes,
+    transport::{Server, server::Router},
+};
+use tower::ServiceBuilder;
+use tower_http::{
+    catch_panic::CatchPanicLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
 
-use crate::error::ApiError;
+use crate::{
+    auth::{self, ApiKeys},
+    bind::Bind,
+    context::{self, REQUEST_ID_HEADER},
+    error::{ApiError, panic_message},
+    reporting::{Failure, report_failures},
+    request_metrics, trace_context,
+    unix_socket::{self, UnixSocketConfig},
+};
 
 mod error;
 pub mod system;
 pub mod user;
+pub mod user_info;
+pub(crate) mod web;
+
+#[cfg(test)]
+mod compat;
 
 pub(crate) mod system_proto {
-    tonic::include_proto!("hex_play.system");
+    tonic::include_proto!("hexplay.system.v1");
 }
 
 pub(crate) mod user_proto {
-    tonic::include_proto!("hex_play.user");
+    tonic::include_proto!("hexplay.user.v1");
+}
+
+pub(crate) mod user_info_proto {
+    tonic::include_proto!("hexplay.user_info.v1");
 }
 
+/// Endpoint the client API connects to when no other endpoint is configured.
+pub const DEFAULT_ENDPOINT: &str = "http://localhost:3001";
+
+/// Address the gRPC server listens on unless others are configured.
+pub const LISTEN_ADDR: &str = "0.0.0.0:3001";
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct GrpcConfig {
+    /// (optional) Serve gRPC on the HTTP listeners instead of its own, for
+    /// ingress that can expose only one port. Requests are told apart by
+    /// their `application/grpc` content type. `listen_addrs` and
+    /// `unix_socket` are then unused.
+    #[serde(default)]
+    pub single_port: bool,
+
+    /// (optional) Addresses serving gRPC, comma separated, e.g.
+    /// `HPLAY__GRPC__LISTEN_ADDRS=[::1]:3001,0.0.0.0:3001`. Defaults to
+    /// [`LISTEN_ADDR`].
+    #[serde(default)]
+    #[schemars(extend("default" = [LISTEN_ADDR]))]
+    pub listen_addrs: Vec<String>,
+
+    /// (optional) Also serve on this Unix socket, e.g.
+    /// `HPLAY__GRPC__UNIX_SOCKET__PATH=/run/hex-play/grpc.sock`.
+    #[serde(default)]
+    pub unix_socket: Option<UnixSocketConfig>,
+
+    /// (optional) Also accept gRPC-Web calls, over HTTP/1.1 as well as h2,
+    /// so browsers can use the services directly.
+    #[serde(default)]
+    pub grpc_web: bool,
+
+    /// (optional) Origins allowed to make gRPC-Web calls from another site,
+    /// comma separated, e.g.
+    /// `HPLAY__GRPC__CORS_ALLOWED_ORIGINS=https://app.example.com`. `*`
+    /// allows any origin. Same-origin calls need no entry.
+    #[serde(default)]
+    pub cors_allowed_origins: Vec<String>,
+
+    /// (optional) Delays, failures and dropped connections to inject into
+    /// every call, e.g. `HPLAY__GRPC__FAULTS__ERROR_PROBABILITY=0.05`.
+    /// Defaults to none.
+    #[cfg(feature = "fault-injection")]
+    #[serde(default)]
+    pub faults: crate::FaultInjectionConfig,
+}
+
+impl GrpcConfig {
+    /// The configured addresses, or [`LISTEN_ADDR`] if none are.
+    pub fn listen_addrs(&self) -> Vec<&str> {
+        if self.listen_addrs.is_empty() {
+            vec![LISTEN_ADDR]
+        } else {
+            self.listen_addrs.iter().map(String::as_str).collect()
+        }
+    }
+}
+
+/// Serves every gRPC service on one listener.
 pub(crate) struct GrpcSubsystem {
     core_services: Arc<CoreServices>,
+    config: GrpcConfig,
+    api_keys: ApiKeys,
+    bind: Bind,
 }
 
 impl GrpcSubsystem {
-    pub(crate) fn new(core_services: Arc<CoreServices>) -> Self {
-        Self { core_services }
+    pub(crate) fn new(core_services: Arc<CoreServices>, config: GrpcConfig, api_keys: ApiKeys, bind: Bind) -> Self {
+        Self {
+            core_services,
+            config,
+            api_keys,
+            bind,
+        }
+    }
+
+    /// Builds the tonic router with every gRPC service registered.
+    pub(crate) fn router(&self) -> Router {
+        // Browsers make gRPC-Web calls over HTTP/1.1.
+        Server::builder()
+            .accept_http1(self.config.grpc_web)
+            .add_routes(routes(self.core_services.clone(), &self.config, &self.api_keys))
     }
 }
 
-impl IntoSubsystem<Error> for GrpcSubsystem {
-    async fn run(self, subsys: &mut SubsystemHandle) -> Result<(), Error> {
-        let addr = "0.0.0.0:3001".parse().map_err(|_| Error::from(ApiError::AddressParse("0.0.0.0:3001".into())))?;
+/// Every gRPC service, for serving on a listener of its own or next to the
+/// HTTP API in single-port mode. Calls are authenticated with `api_keys`.
+pub(crate) fn routes(core_services: Arc<CoreServices>, config: &GrpcConfig, api_keys: &ApiKeys) -> Routes {
+    let error_reporter = core_services.error_reporter.clone();
+    let system_service = system::GrpcSystemService::new(core_services.clone());
+    let user_service = user::GrpcUserService::new(core_services.clone());
+    let user_info_service = user_info::GrpcUserInfoService::new(core_services);
+    let interceptor = {
+        let api_keys = api_keys.clone();
+        move |request| context_interceptor(&api_keys, request)
+    };
+
+    let routes = Routes::new(system_proto::system_service_server::SystemServiceServer::new(system_service))
+        .add_service(user_proto::user_service_server::UserServiceServer::with_interceptor(
+            user_service,
+            interceptor.clone(),
+        ))
+        .add_service(user_info_proto::user_info_service_server::UserInfoServiceServer::with_interceptor(
+            user_info_service,
+            interceptor,
+        ));
+    let router = if config.grpc_web {
+        web::layer(routes.into_axum_router(), &config.cors_allowed_origins)
+    } else {
+        routes.into_axum_router()
+    };
+    #[cfg(feature = "fault-injection")]
+    let router = crate::faults::inject_grpc(router, &config.faults);
+    Routes::from(with_request_id(router, error_reporter))
+}
+
+/// Mirrors the HTTP middleware: a call without an `x-request-id` gets one
+/// before [`context_interceptor`] reads it, each call is timed and traced in
+/// a span carrying it and continuing the caller's `traceparent`, it is
+/// echoed in the response metadata, a panicking handler fails the call with
+/// `INTERNAL`, and internal failures are reported.
+fn with_request_id(router: axum::Router, error_reporter: Arc<dyn ErrorReporter>) -> axum::Router {
+    let x_request_id = HeaderName::from_static(REQUEST_ID_HEADER);
+
+    let middleware = ServiceBuilder::new()
+        .layer(SetRequestIdLayer::new(x_request_id.clone(), MakeRequestUuid))
+        .layer(TraceLayer::new_for_grpc().make_span_with(|request: &axum::http::Request<_>| {
+            let request_id = request
+                .headers()
+                .get(REQUEST_ID_HEADER)
+                .map(|v| v.to_str().unwrap_or_default())
+                .unwrap_or_default();
+
+            let span = tracing::info_span!(
+                "grpc",
+                request_id = ?request_id,
+                method = %request.uri().path(),
+                trace_id = tracing::field::Empty,
+            );
+            trace_context::continue_trace(&span, request.headers());
+            span
+        }))
+        .layer(axum::middleware::from_fn(request_metrics::record_grpc))
+        .layer(PropagateRequestIdLayer::new(x_request_id))
+        .layer(axum::middleware::from_fn_with_state(error_reporter, report_failures))
+        .layer(CatchPanicLayer::custom(panicked));
+
+    router.layer(middleware)
+}
+
+fn panicked(panic: Box<dyn Any + Send>) -> axum::http::Response<Body> {
+    let message = panic_message(&*panic);
+    tracing::error!(panic = message, "Handler panicked");
+    let mut response = Status::internal("Internal server error").into_http();
+    Failure::mark_panic(&mut response, message);
+    response
+}
+
+/// Builds the [`RequestContext`] passed to use cases from the request
+/// metadata and the API key it carries, if any. Calls naming a tenant other
+/// than their key's fail with `PERMISSION_DENIED`.
+fn context_interceptor(api_keys: &ApiKeys, mut request: Request<()>) -> Result<Request<()>, Status> {
+    let headers = request.metadata().clone().into_headers();
+    let principal = api_keys.authenticate(&headers);
+    auth::check_tenant(principal.as_ref().and_then(|principal| principal.tenant.as_deref()), &headers).map_err(Status::permission_denied)?;
+    let mut context = context::from_headers(&headers);
+    if let Some(principal) = principal {
+        principal.apply_to(&mut context);
+    }
+    request.extensions_mut().insert(context);
+    Ok(request)
+}
 
-        let system_service = system::GrpcSystemService::new();
-        let user_service = user::GrpcUserService::new(self.core_services.clone());
+/// The context added by [`context_interceptor`], or one read from the
+/// metadata when a service is called without it, as in handler tests.
+fn request_context<T>(request: &Request<T>) -> RequestContext {
+    request
+        .extensions()
+        .get::<RequestContext>()
+        .cloned()
+        .unwrap_or_else(|| context::from_headers(&request.metadata().clone().into_headers()))
+}
 
-        tracing::info!("listening on {}", addr);
-        tokio::select! {
-            _ = subsys.on_shutdown_requested() => {
-                tracing::info!("GrpcSubsystem shutting down...");
+impl IntoSubsystem<Error> for GrpcSubsystem {
+    async fn run(self, subsys: &mut SubsystemHandle) -> Result<(), Error> {
+        let router = self.router();
+        let result = match &self.bind {
+            Bind::Tcp(addr) => {
+                let addr = addr.parse().map_err(|_| Error::from(ApiError::AddressParse(addr.clone())))?;
+                tracing::info!("listening on {}", addr);
+                router.serve_with_shutdown(addr, subsys.on_shutdown_requested()).await
             }
-            _ = Server::builder()
-                .add_service(system_proto::system_service_server::SystemServiceServer::new(system_service))
-                .add_service(user_proto::user_service_server::UserServiceServer::new(user_service))
-                .serve(addr) => {
-                subsys.request_shutdown();
+            Bind::Unix(config) => {
+                let (listener, _socket_file) = unix_socket::bind(config)?;
+                tracing::info!("listening on {}", self.bind);
+                router
+                    .serve_with_incoming_shutdown(UnixListenerStream::new(listener), subsys.on_shutdown_requested())
+                    .await
             }
+        };
+        if let Err(e) = result {
+            tracing::error!("gRPC server error: {}", e);
+            subsys.request_shutdown();
         }
+        tracing::info!("GrpcSubsystem shut down");
 
         Ok(())
     }
 }
+
+/// Contract tests: run the real tonic server in-process and drive it through
+/// the client API, so proto compatibility and status mapping are verified end
+/// to end rather than per handler.
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use hex_play_core::{
+        Error, ErrorKind, RepositoryError,
+        reporting::{ErrorReporter, NoErrorReporter},
+        status::Health,
+        test_support::{MockErrorReporter, MockUserService, create_core_services_with_mock, mock_status_service_up},
+        user::{PartialUserUpdate, User, UserId},
+    };
+    use hex_play_utils::secret::Secret;
+    use mockall::predicate::{always, eq};
+    use tokio::net::TcpListener;
+    use tokio_stream::StreamExt;
+    use tonic::transport::server::TcpIncoming;
+    use tower::ServiceExt;
+
+    use super::{
+        GrpcConfig, GrpcSubsystem, system,
+        system_proto::{StatusRequest, system_service_client::SystemServiceClient},
+        user,
+        user_proto::{GetUserRequest, user_service_client::UserServiceClient},
+        with_request_id,
+    };
+    use crate::{ApiKeyConfig, auth::ApiKeys, bind::Bind};
+
+    // ===================
+    // Test Helpers
+    // ===================
+    /// `acme-key` for user 1 of tenant `acme`.
+    fn create_api_keys() -> ApiKeys {
+        ApiKeys::new(&HashMap::from([(
+            "acme".to_string(),
+            ApiKeyConfig {
+                key: Secret::new("acme-key"),
+                user_id: 1,
+                tenant: Some("acme".to_string()),
+                admin: false,
+            },
+        )]))
+    }
+
+    fn get_user_request(tenant: Option<&str>) -> tonic::Request<GetUserRequest> {
+        let mut request = tonic::Request::new(GetUserRequest { id: 1 });
+        request.metadata_mut().insert("authorization", "Bearer acme-key".parse().unwrap());
+        if let Some(tenant) = tenant {
+            request.metadata_mut().insert("x-tenant-id", tenant.parse().unwrap());
+        }
+        request
+    }
+    async fn start_server(mock: MockUserService) -> String {
+        start_server_reporting_to(mock, Arc::new(NoErrorReporter)).await
+    }
+
+    async fn start_server_reporting_to(mock: MockUserService, error_reporter: Arc<dyn ErrorReporter>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind ephemeral port");
+        let addr = listener.local_addr().expect("listener has local address");
+        let mut core_services = create_core_services_with_mock(mock);
+        core_services.error_reporter = error_reporter;
+        core_services.status_service = Arc::new(mock_status_service_up());
+        let router = GrpcSubsystem::new(Arc::new(core_services), GrpcConfig::default(), create_api_keys(), Bind::Tcp(String::new())).router();
+
+        tokio::spawn(router.serve_with_incoming(TcpIncoming::from(listener)));
+
+        format!("http://{addr}")
+    }
+
+    async fn panicking_handler() -> &'static str {
+        panic!("boom")
+    }
+
+    // ===================
+    // Tests: system::api
+    // ===================
+    #[tokio::test]
+    async fn test_contract_status() {
+        let endpoint = start_server(MockUserService::new()).await;
+
+        let status = system::api::status(&endpoint).await.unwrap();
+
+        assert_eq!(status.health(), Health::Up);
+        assert_eq!(status.components[0].name, "database");
+        assert_eq!(status.components[0].detail.as_deref(), Some("reachable"));
+    }
+
+    // ===================
+    // Tests: request id
+    // ===================
+    #[tokio::test]
+    async fn test_echoes_request_id() {
+        let endpoint = start_server(MockUserService::new()).await;
+        let mut client = SystemServiceClient::connect(endpoint).await.unwrap();
+
+        let mut request = tonic::Request::new(StatusRequest {});
+        request.metadata_mut().insert("x-request-id", "req-1".parse().unwrap());
+        let response = client.status(request).await.unwrap();
+
+        assert_eq!(response.metadata().get("x-request-id").unwrap(), "req-1");
+    }
+
+    #[tokio::test]
+    async fn test_panicking_handler_fails_with_internal() {
+        let mut reporter = MockErrorReporter::new();
+        reporter
+            .expect_report()
+            .withf(|report| report.panic && report.message == "boom" && report.request_id.as_deref() == Some("req-1"))
+            .times(1)
+            .return_const(());
+        let router = with_request_id(axum::Router::new().route("/", axum::routing::post(panicking_handler)), Arc::new(reporter));
+
+        let response = router
+            .oneshot(
+                axum::http::Request::post("/")
+                    .header("content-type", "application/grpc")
+                    .header("x-request-id", "req-1")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.headers()["grpc-status"], "13");
+        assert_eq!(response.headers()["x-request-id"], "req-1");
+    }
+
+    #[tokio::test]
+    async fn test_generates_request_id_on_errors() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(None));
+        let endpoint = start_server(mock).await;
+        let mut client = UserServiceClient::connect(endpoint).await.unwrap();
+
+        let status = client.get(GetUserRequest { id: 1 }).await.unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        assert!(!status.metadata().get("x-request-id").unwrap().is_empty());
+    }
+
+    // ===================
+    // Tests: error reporting
+    // ===================
+    #[tokio::test]
+    async fn test_internal_errors_are_reported() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Err(Error::Infrastructure("connection refused".into())));
+        let mut reporter = MockErrorReporter::new();
+        reporter
+            .expect_report()
+            .withf(|report| !report.panic && report.message == "Infrastructure error: connection refused" && report.request_id.as_deref() == Some("req-1"))
+            .times(1)
+            .return_const(());
+        let endpoint = start_server_reporting_to(mock, Arc::new(reporter)).await;
+        let mut client = UserServiceClient::connect(endpoint).await.unwrap();
+
+        let mut request = tonic::Request::new(GetUserRequest { id: 1 });
+        request.metadata_mut().insert("x-request-id", "req-1".parse().unwrap());
+        let status = client.get(request).await.unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::Internal);
+    }
+
+    #[tokio::test]
+    async fn test_not_found_is_not_reported() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(None));
+        let mut reporter = MockErrorReporter::new();
+        reporter.expect_report().never();
+        let endpoint = start_server_reporting_to(mock, Arc::new(reporter)).await;
+        let mut client = UserServiceClient::connect(endpoint).await.unwrap();
+
+        let status = client.get(GetUserRequest { id: 1 }).await.unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    // ===================
+    // Tests: authentication
+    // ===================
+    #[tokio::test]
+    async fn test_api_key_acts_as_its_user_within_its_tenant() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id()
+            .withf(|context, _| context.actor.as_deref() == Some("1") && context.tenant.as_deref() == Some("acme"))
+            .return_const(Ok(None));
+        let endpoint = start_server(mock).await;
+        let mut client = UserServiceClient::connect(endpoint).await.unwrap();
+
+        let status = client.get(get_user_request(Some("acme"))).await.unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_call_naming_another_tenant_is_permission_denied() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().never();
+        let endpoint = start_server(mock).await;
+        let mut client = UserServiceClient::connect(endpoint).await.unwrap();
+
+        let status = client.get(get_user_request(Some("globex"))).await.unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    }
+
+    // ===================
+    // Tests: user::api
+    // ===================
+    #[tokio::test]
+    async fn test_contract_create_round_trips_all_fields() {
+        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let mut mock = MockUserService::new();
+        mock.expect_add_user().return_const(Ok(user.clone()));
+        let endpoint = start_server(mock).await;
+
+        let created = user::api::create(&endpoint, "John Doe".into(), "john@example.com".into(), 30).await.unwrap();
+
+        assert_eq!(created.id, user.id);
+        assert_eq!(created.token, user.token);
+        assert_eq!(created.name, user.name);
+        assert_eq!(created.email, user.email);
+        assert_eq!(created.age, user.age);
+        assert_eq!(created.version, user.version);
+        assert_eq!(created.created_at, user.created_at);
+        assert_eq!(created.updated_at, user.updated_at);
+    }
+
+    #[tokio::test]
+    async fn test_contract_get_by_token() {
+        let user = User::fake(1, "John Doe", "john@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_token().return_const(Ok(Some(user.clone())));
+        let endpoint = start_server(mock).await;
+
+        let fetched = user::api::get_by_token(&endpoint, user.token).await.unwrap();
+
+        assert_eq!(fetched.id, UserId::new(1));
+        assert_eq!(fetched.token, user.token);
+    }
+
+    #[tokio::test]
+    async fn test_contract_list() {
+        let users = vec![
+            User::fake_with_age(1, "John Doe", "john@example.com", 30),
+            User::fake_with_age(2, "Jane Doe", "jane@example.com", 25),
+        ];
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Ok(users));
+        let endpoint = start_server(mock).await;
+
+        let listed = user::api::list(&endpoint, None, Some(10), false).await.unwrap();
+
+        assert_eq!(listed.users.len(), 2);
+        assert_eq!(listed.users[1].name, "Jane Doe");
+        assert_eq!(listed.users[1].age.value(), 25);
+        assert_eq!(listed.page_size_applied, 10);
+        assert_eq!(listed.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn test_contract_export_streams_users() {
+        let users = vec![User::fake(1, "John Doe", "john@example.com"), User::fake(2, "Jane Doe", "jane@example.com")];
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Ok(users));
+        let endpoint = start_server(mock).await;
+
+        let exported: Vec<_> = user::api::export(&endpoint, None).await.unwrap().collect().await;
+
+        let ids: Vec<_> = exported.into_iter().map(|user| user.unwrap().id.value()).collect();
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_contract_export_error_ends_stream() {
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Err(Error::InvalidId(0)));
+        let endpoint = start_server(mock).await;
+
+        let mut stream = Box::pin(user::api::export(&endpoint, Some(UserId::new(0))).await.unwrap());
+
+        let error = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_contract_delete() {
+        let user = User::fake(1, "John Doe", "john@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_delete_user().return_const(Ok(user));
+        let endpoint = start_server(mock).await;
+
+        let deleted = user::api::delete(&endpoint, UserId::new(1)).await.unwrap();
+
+        assert_eq!(deleted.id, UserId::new(1));
+    }
+
+    #[tokio::test]
+    async fn test_contract_get_not_found() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(None));
+        let endpoint = start_server(mock).await;
+
+        let error = user::api::get(&endpoint, UserId::new(999)).await.unwrap_err();
+
+        assert!(matches!(error, Error::RepositoryError(RepositoryError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_contract_update_conflict() {
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(Some("Updated"), None::<String>, None).unwrap()),
+            )
+            .times(1)
+            .return_const(Err(Error::RepositoryError(RepositoryError::Conflict)));
+        let endpoint = start_server(mock).await;
+
+        let error = user::api::update(&endpoint, UserId::new(1), Some("Updated".into()), None, None, None)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::Conflict);
+    }
+
+    #[tokio::test]
+    async fn test_contract_update_stale_expected_version() {
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(Some("Updated"), None::<String>, None).unwrap().with_expected_version(1)),
+            )
+            .times(1)
+            .return_const(Err(Error::RepositoryError(RepositoryError::Conflict)));
+        let endpoint = start_server(mock).await;
+
+        let error = user::api::update(&endpoint, UserId::new(1), Some("Updated".into()), None, None, Some(1))
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::Conflict);
+    }
+
+    #[tokio::test]
+    async fn test_contract_create_invalid_email() {
+        let endpoint = start_server(MockUserService::new()).await;
+
+        let error = user::api::create(&endpoint, "John Doe".into(), "invalid-email".into(), 30).await.unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert!(error.to_string().contains("Invalid email format"));
+    }
+
+    #[tokio::test]
+    async fn test_contract_internal_error() {
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Err(Error::Infrastructure("db down".into())));
+        let endpoint = start_server(mock).await;
+
+        let error = user::api::list(&endpoint, None, None, false).await.unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::Internal);
+        assert!(error.to_string().contains("db down"));
+    }
+
+    #[tokio::test]
+    async fn test_contract_transport_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let error = user::api::get(&endpoint, UserId::new(1)).await.unwrap_err();
+
+        assert!(matches!(error, Error::Infrastructure(_)));
+    }
+}
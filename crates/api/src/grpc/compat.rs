@@ -0,0 +1,200 @@
+//! Breaking-change check for the gRPC API, in the spirit of `buf breaking`.
+//!
+//! The protos compiled into this build are compared against the descriptor
+//! snapshot committed as `proto/snapshot.binpb`. After an intended change,
+//! such as a new field, accept it with
+//! `UPDATE_PROTO_SNAPSHOT=1 cargo test -p hex-play-api proto_snapshot`.
+//! Changes that break existing clients belong in a new package version
+//! (`hexplay.user.v2`) instead.
+
+use prost_reflect::{DescriptorPool, FieldDescriptor, Kind};
+
+const DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("hexplay_descriptor");
+const SNAPSHOT_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/proto/snapshot.binpb");
+
+/// Describes every change in `new` that breaks a client built against `old`.
+fn breaking_changes(old: &DescriptorPool, new: &DescriptorPool) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    for service in old.services() {
+        let Some(new_service) = new.get_service_by_name(service.full_name()) else {
+            changes.push(format!("service {} removed", service.full_name()));
+            continue;
+        };
+        for method in service.methods() {
+            let name = method.full_name();
+            let Some(new_method) = new_service.methods().find(|new_method| new_method.name() == method.name()) else {
+                changes.push(format!("method {name} removed"));
+                continue;
+            };
+            if new_method.input().full_name() != method.input().full_name() {
+                changes.push(format!(
+                    "method {name} takes {} instead of {}",
+                    new_method.input().full_name(),
+                    method.input().full_name()
+                ));
+            }
+            if new_method.output().full_name() != method.output().full_name() {
+                changes.push(format!(
+                    "method {name} returns {} instead of {}",
+                    new_method.output().full_name(),
+                    method.output().full_name()
+                ));
+            }
+            if new_method.is_client_streaming() != method.is_client_streaming() || new_method.is_server_streaming() != method.is_server_streaming() {
+                changes.push(format!("method {name} changed streaming"));
+            }
+        }
+    }
+
+    for message in old.all_messages() {
+        let Some(new_message) = new.get_message_by_name(message.full_name()) else {
+            changes.push(format!("message {} removed", message.full_name()));
+            continue;
+        };
+        for field in message.fields() {
+            let name = field.full_name();
+            match new_message.get_field(field.number()) {
+                None if new_message.reserved_ranges().any(|range| range.contains(&field.number())) => {}
+                None => changes.push(format!("field {name} = {} removed without reserving its number", field.number())),
+                Some(new_field) => {
+                    // Names matter to JSON clients, even though the wire
+                    // format only uses numbers.
+                    if new_field.name() != field.name() {
+                        changes.push(format!("field {name} = {} renamed to {}", field.number(), new_field.name()));
+                    }
+                    if type_name(&new_field) != type_name(&field) {
+                        changes.push(format!("field {name} changed type from {} to {}", type_name(&field), type_name(&new_field)));
+                    }
+                    if new_field.cardinality() != field.cardinality() || new_field.is_map() != field.is_map() {
+                        changes.push(format!("field {name} changed cardinality"));
+                    }
+                }
+            }
+        }
+    }
+
+    for enumeration in old.all_enums() {
+        let Some(new_enumeration) = new.get_enum_by_name(enumeration.full_name()) else {
+            changes.push(format!("enum {} removed", enumeration.full_name()));
+            continue;
+        };
+        for value in enumeration.values() {
+            let reserved = new_enumeration.reserved_ranges().any(|range| range.contains(&value.number()));
+            if new_enumeration.get_value(value.number()).is_none() && !reserved {
+                changes.push(format!(
+                    "enum value {} = {} removed without reserving its number",
+                    value.full_name(),
+                    value.number()
+                ));
+            }
+        }
+    }
+
+    changes
+}
+
+fn type_name(field: &FieldDescriptor) -> String {
+    match field.kind() {
+        Kind::Message(message) => message.full_name().to_string(),
+        Kind::Enum(enumeration) => enumeration.full_name().to_string(),
+        kind => format!("{kind:?}").to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+    use prost_reflect::DescriptorPool;
+    use prost_types::FileDescriptorSet;
+
+    use super::{DESCRIPTOR_SET, SNAPSHOT_PATH, breaking_changes};
+
+    fn current() -> DescriptorPool {
+        DescriptorPool::decode(DESCRIPTOR_SET).unwrap()
+    }
+
+    /// The current descriptors with `edit` applied to the user proto.
+    fn edited(edit: impl FnOnce(&mut prost_types::FileDescriptorProto)) -> DescriptorPool {
+        let mut set = FileDescriptorSet::decode(DESCRIPTOR_SET).unwrap();
+        let file = set.file.iter_mut().find(|file| file.package() == "hexplay.user.v1").unwrap();
+        edit(file);
+        DescriptorPool::from_file_descriptor_set(set).unwrap()
+    }
+
+    // ===================
+    // Tests: snapshot
+    // ===================
+
+    #[test]
+    fn test_proto_snapshot_has_no_breaking_changes() {
+        if std::env::var_os("UPDATE_PROTO_SNAPSHOT").is_some() {
+            std::fs::write(SNAPSHOT_PATH, DESCRIPTOR_SET).unwrap();
+        }
+        let snapshot = std::fs::read(SNAPSHOT_PATH).expect("proto/snapshot.binpb is committed");
+        let snapshot = DescriptorPool::decode(snapshot.as_slice()).unwrap();
+
+        let changes = breaking_changes(&snapshot, &current());
+
+        assert!(changes.is_empty(), "breaking proto changes:\n{}", changes.join("\n"));
+    }
+
+    // ===================
+    // Tests: breaking_changes
+    // ===================
+
+    #[test]
+    fn test_adding_a_field_is_compatible() {
+        let new = edited(|file| {
+            let user = file.message_type.iter_mut().find(|message| message.name() == "User").unwrap();
+            let mut nickname = user.field[2].clone();
+            nickname.name = Some("nickname".into());
+            nickname.json_name = Some("nickname".into());
+            nickname.number = Some(99);
+            user.field.push(nickname);
+        });
+
+        assert!(breaking_changes(&current(), &new).is_empty());
+    }
+
+    #[test]
+    fn test_detects_removed_field() {
+        let new = edited(|file| {
+            let user = file.message_type.iter_mut().find(|message| message.name() == "User").unwrap();
+            user.field.retain(|field| field.name() != "email");
+        });
+
+        assert_eq!(
+            breaking_changes(&current(), &new),
+            ["field hexplay.user.v1.User.email = 4 removed without reserving its number"]
+        );
+    }
+
+    #[test]
+    fn test_removed_field_with_reserved_number_is_compatible() {
+        let new = edited(|file| {
+            let user = file.message_type.iter_mut().find(|message| message.name() == "User").unwrap();
+            user.field.retain(|field| field.name() != "email");
+            user.reserved_range
+                .push(prost_types::descriptor_proto::ReservedRange { start: Some(4), end: Some(5) });
+        });
+
+        assert!(breaking_changes(&current(), &new).is_empty());
+    }
+
+    #[test]
+    fn test_detects_changed_field_type_and_removed_method() {
+        let new = edited(|file| {
+            let user = file.message_type.iter_mut().find(|message| message.name() == "User").unwrap();
+            let age = user.field.iter_mut().find(|field| field.name() == "age").unwrap();
+            age.set_type(prost_types::field_descriptor_proto::Type::String);
+            file.service[0].method.retain(|method| method.name() != "Delete");
+        });
+
+        let changes = breaking_changes(&current(), &new);
+
+        assert_eq!(changes.len(), 2);
+        assert!(changes[0].starts_with("method hexplay.user.v1.UserService.Delete removed"));
+        assert_eq!(changes[1], "field hexplay.user.v1.User.age changed type from int32 to string");
+    }
+}
@@ -1,26 +1,195 @@
-//! gRPC error mapping from core errors to tonic Status codes.
+//! gRPC error mapping between core errors and tonic Status codes.
+//!
+//! Every error status carries `google.rpc.Status` details: an `ErrorInfo`
+//! in the [`ERROR_DOMAIN`] whose `reason` is stable for clients to match on,
+//! and, when the error concerns one request field, a `BadRequest` field
+//! violation. The encoded details are pinned by the snapshots in
+//! `snapshots/`. Changing a reason breaks clients; accept other intended
+//! changes with `INSTA_UPDATE=always cargo test -p hex-play-api grpc::error`.
 
-use hex_play_core::{Error as CoreError, ErrorKind};
-use tonic::Status;
+use std::collections::HashMap;
 
-/// Maps a core error to the appropriate tonic Status code.
+use hex_play_core::{Error as CoreError, ErrorKind, RepositoryError, i18n::ValidationMessage};
+use prost::Message;
+use tonic::{Code, Status};
+use tonic_types::{BadRequest, ErrorInfo, pb};
+
+use crate::ApiError;
+
+/// The `ErrorInfo` domain of every error this API returns.
+pub const ERROR_DOMAIN: &str = "hexplay";
+
+/// Maps a core error to the appropriate tonic Status code, with details
+/// describing it.
 pub fn map_core_error(error: CoreError) -> Status {
+    let code = match error.kind() {
+        ErrorKind::NotFound => Code::NotFound,
+        ErrorKind::Conflict => Code::AlreadyExists,
+        ErrorKind::InvalidInput | ErrorKind::BadRequest => Code::InvalidArgument,
+        ErrorKind::Internal => Code::Internal,
+        ErrorKind::Unavailable => Code::Unavailable,
+    };
+    let error_info = pb::ErrorInfo {
+        reason: reason(&error).to_string(),
+        domain: ERROR_DOMAIN.to_string(),
+        metadata: metadata(&error),
+    };
+    let mut details = vec![pack(ErrorInfo::TYPE_URL, &error_info)];
+    if let Some(violation) = field_violation(&error) {
+        details.push(pack(
+            BadRequest::TYPE_URL,
+            &pb::BadRequest {
+                field_violations: vec![violation],
+            },
+        ));
+    }
+
     let message = error.to_string();
-    match error.kind() {
-        ErrorKind::NotFound => Status::not_found(message),
-        ErrorKind::Conflict => Status::already_exists(message),
-        ErrorKind::InvalidInput => Status::invalid_argument(message),
-        ErrorKind::BadRequest => Status::invalid_argument(message),
-        ErrorKind::Internal => Status::internal(message),
+    let status = pb::Status {
+        code: code as i32,
+        message: message.clone(),
+        details,
+    };
+    Status::with_details(code, message, status.encode_to_vec().into())
+}
+
+/// Packs a detail message. Details are encoded here rather than through
+/// `tonic_types::ErrorDetails`, which drops `FieldViolation.reason`.
+fn pack(type_url: &str, message: &impl Message) -> prost_types::Any {
+    prost_types::Any {
+        type_url: type_url.to_string(),
+        value: message.encode_to_vec(),
+    }
+}
+
+/// The `ErrorInfo` reason for `error`.
+fn reason(error: &CoreError) -> &'static str {
+    match error {
+        CoreError::InvalidId(_) => "INVALID_ID",
+        CoreError::InvalidPageSize(_) => "INVALID_PAGE_SIZE",
+        CoreError::InvalidBatchSize(_) => "INVALID_BATCH_SIZE",
+        CoreError::Validation(_) => "VALIDATION_FAILED",
+        CoreError::EmptyUpdate => "EMPTY_UPDATE",
+        CoreError::InvalidToken(_) => "INVALID_TOKEN",
+        CoreError::ReadOnlyMode => "READ_ONLY_MODE",
+        CoreError::WebhookReplay => "WEBHOOK_REPLAY",
+        CoreError::SelfImpersonation => "SELF_IMPERSONATION",
+        CoreError::UnknownTenant(_) => "UNKNOWN_TENANT",
+        CoreError::Storage(_) => "STORAGE_ERROR",
+        CoreError::ImageProcessing(_) => "IMAGE_PROCESSING_ERROR",
+        CoreError::InvalidTransactionType | CoreError::Infrastructure(_) | CoreError::Job(_) | CoreError::FrontendError(_) => "INTERNAL_ERROR",
+        CoreError::Remote { .. } => "REMOTE_ERROR",
+        CoreError::RepositoryError(error) => match error {
+            RepositoryError::Constraint(_) => "CONSTRAINT_VIOLATION",
+            RepositoryError::Conflict => "VERSION_CONFLICT",
+            RepositoryError::NotFound => "NOT_FOUND",
+            RepositoryError::QueryCanceled => "QUERY_CANCELED",
+            RepositoryError::ReadOnly | RepositoryError::Database(_) => "DATABASE_ERROR",
+            RepositoryError::Unavailable(_) => "DATABASE_UNAVAILABLE",
+        },
+    }
+}
+
+/// The `ErrorInfo` metadata for `error`: the values a client would
+/// otherwise have to parse out of the message.
+fn metadata(error: &CoreError) -> HashMap<String, String> {
+    let entries: Vec<(&str, String)> = match error {
+        CoreError::InvalidId(id) => vec![("id", id.to_string())],
+        CoreError::InvalidPageSize(size) => vec![("page_size", size.to_string())],
+        CoreError::InvalidBatchSize(size) => vec![("batch_size", size.to_string())],
+        CoreError::Validation(ValidationMessage::AgeOutOfRange { min, max, .. }) => {
+            vec![("min_age", min.to_string()), ("max_age", max.to_string())]
+        }
+        CoreError::Validation(ValidationMessage::AvatarTooLarge { max_bytes }) => vec![("max_bytes", max_bytes.to_string())],
+        _ => Vec::new(),
+    };
+    entries.into_iter().map(|(key, value)| (key.to_string(), value)).collect()
+}
+
+/// The request field `error` is about, if it is about exactly one.
+fn field_violation(error: &CoreError) -> Option<pb::bad_request::FieldViolation> {
+    let (field, reason) = match error {
+        CoreError::InvalidId(_) => ("id", "INVALID_ID"),
+        CoreError::InvalidPageSize(_) => ("page_size", "INVALID_PAGE_SIZE"),
+        CoreError::InvalidToken(_) => ("token", "INVALID_TOKEN"),
+        CoreError::Validation(message) => match message {
+            ValidationMessage::InvalidEmail { .. } => ("email", "INVALID_EMAIL"),
+            ValidationMessage::AgeOutOfRange { .. } => ("age", "AGE_OUT_OF_RANGE"),
+            ValidationMessage::UnsupportedAvatarType { .. } => ("avatar", "UNSUPPORTED_AVATAR_TYPE"),
+            ValidationMessage::AvatarTooLarge { .. } => ("avatar", "AVATAR_TOO_LARGE"),
+            ValidationMessage::AvatarTypeMismatch { .. } => ("avatar", "AVATAR_TYPE_MISMATCH"),
+            ValidationMessage::BlankName => ("name", "BLANK_NAME"),
+            ValidationMessage::Other(_) => return None,
+        },
+        _ => return None,
+    };
+    Some(pb::bad_request::FieldViolation {
+        field: field.to_string(),
+        description: error.to_string(),
+        reason: reason.to_string(),
+        ..Default::default()
+    })
+}
+
+/// Maps a tonic Status received by a client back to a core error.
+///
+/// This is the inverse of [`map_core_error`], so callers of the client API
+/// can match on [`ErrorKind`] the same way server-side code does.
+pub fn map_status(status: Status) -> CoreError {
+    match status.code() {
+        Code::NotFound => CoreError::RepositoryError(RepositoryError::NotFound),
+        Code::AlreadyExists => CoreError::RepositoryError(RepositoryError::Conflict),
+        Code::InvalidArgument => CoreError::Remote {
+            kind: ErrorKind::InvalidInput,
+            message: status.message().to_string(),
+        },
+        Code::Internal => CoreError::Remote {
+            kind: ErrorKind::Internal,
+            message: status.message().to_string(),
+        },
+        Code::Unavailable => CoreError::Remote {
+            kind: ErrorKind::Unavailable,
+            message: status.message().to_string(),
+        },
+        _ => CoreError::from(ApiError::GrpcClient(status.to_string())),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use hex_play_core::{Error, RepositoryError};
-    use tonic::Code;
+    use std::collections::BTreeMap;
+
+    use hex_play_core::{Error, ErrorKind, RepositoryError, i18n::ValidationMessage};
+    use tonic::{Code, Status};
+    use tonic_types::{ErrorDetail, StatusExt};
 
-    use super::map_core_error;
+    use super::{map_core_error, map_status};
+
+    /// Renders the details of `status` as decoded from the wire, with
+    /// metadata sorted so snapshots are stable.
+    fn encoded_details(status: &Status) -> String {
+        let mut rendered = format!("code: {:?}\nmessage: {}\n", status.code(), status.message());
+        for detail in status.check_error_details_vec().expect("details should decode") {
+            match detail {
+                ErrorDetail::ErrorInfo(info) => {
+                    rendered.push_str(&format!("error_info: {}/{}\n", info.domain, info.reason));
+                    for (key, value) in info.metadata.into_iter().collect::<BTreeMap<_, _>>() {
+                        rendered.push_str(&format!("  {key}: {value}\n"));
+                    }
+                }
+                ErrorDetail::BadRequest(bad_request) => {
+                    for violation in bad_request.field_violations {
+                        rendered.push_str(&format!(
+                            "field_violation: {} {}: {}\n",
+                            violation.field, violation.reason, violation.description
+                        ));
+                    }
+                }
+                other => panic!("unexpected detail {other:?}"),
+            }
+        }
+        rendered
+    }
 
     #[test]
     fn test_not_found_maps_to_not_found() {
@@ -87,4 +256,106 @@ mod tests {
 
         assert_eq!(status.code(), Code::Internal);
     }
+
+    #[test]
+    fn test_map_status_not_found() {
+        let error = map_status(Status::not_found("Not found"));
+
+        assert!(matches!(error, Error::RepositoryError(RepositoryError::NotFound)));
+    }
+
+    #[test]
+    fn test_map_status_already_exists_is_conflict() {
+        let error = map_status(Status::already_exists("Conflict Error"));
+
+        assert_eq!(error.kind(), ErrorKind::Conflict);
+    }
+
+    #[test]
+    fn test_map_status_invalid_argument_keeps_message() {
+        let error = map_status(Status::invalid_argument("Validation error: bad email"));
+
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert_eq!(error.to_string(), "Validation error: bad email");
+    }
+
+    #[test]
+    fn test_repository_unavailable_maps_to_unavailable() {
+        let error = Error::RepositoryError(RepositoryError::Unavailable("pool timed out".into()));
+
+        let status = map_core_error(error);
+
+        assert_eq!(status.code(), Code::Unavailable);
+    }
+
+    #[test]
+    fn test_map_status_unavailable_keeps_kind() {
+        let error = map_status(Status::unavailable("connection refused"));
+
+        assert_eq!(error.kind(), ErrorKind::Unavailable);
+        assert_eq!(error.to_string(), "connection refused");
+    }
+
+    #[test]
+    fn test_map_status_unknown_code_is_infrastructure() {
+        let error = map_status(Status::deadline_exceeded("timed out"));
+
+        assert!(matches!(error, Error::Infrastructure(_)));
+        assert_eq!(error.kind(), ErrorKind::Internal);
+    }
+
+    // ===================
+    // Tests: status details
+    // ===================
+    #[test]
+    fn test_details_of_invalid_email() {
+        let error = Error::Validation(ValidationMessage::InvalidEmail { email: "nope".into() });
+
+        insta::assert_snapshot!(encoded_details(&map_core_error(error)));
+    }
+
+    #[test]
+    fn test_details_of_age_out_of_range() {
+        let error = Error::Validation(ValidationMessage::AgeOutOfRange { min: 0, max: 150, age: 200 });
+
+        insta::assert_snapshot!(encoded_details(&map_core_error(error)));
+    }
+
+    #[test]
+    fn test_details_of_free_form_validation() {
+        let error = Error::Validation("name cannot be cleared".into());
+
+        insta::assert_snapshot!(encoded_details(&map_core_error(error)));
+    }
+
+    #[test]
+    fn test_details_of_invalid_page_size() {
+        insta::assert_snapshot!(encoded_details(&map_core_error(Error::InvalidPageSize(5000))));
+    }
+
+    #[test]
+    fn test_details_of_not_found() {
+        let error = Error::RepositoryError(RepositoryError::NotFound);
+
+        insta::assert_snapshot!(encoded_details(&map_core_error(error)));
+    }
+
+    #[test]
+    fn test_details_of_version_conflict() {
+        let error = Error::RepositoryError(RepositoryError::Conflict);
+
+        insta::assert_snapshot!(encoded_details(&map_core_error(error)));
+    }
+
+    #[test]
+    fn test_details_of_read_only_mode() {
+        insta::assert_snapshot!(encoded_details(&map_core_error(Error::ReadOnlyMode)));
+    }
+
+    #[test]
+    fn test_details_of_internal_error() {
+        let error = Error::Infrastructure("connection refused".into());
+
+        insta::assert_snapshot!(encoded_details(&map_core_error(error)));
+    }
 }
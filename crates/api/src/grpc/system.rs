@@ -1,78 +1,127 @@
+use std::sync::Arc;
+
+use hex_play_core::CoreServices;
 use tonic::{Request, Response, Status};
 
-use crate::grpc::{
-    error::map_core_error,
-    system_proto::{StatusRequest, StatusResponse, system_service_server::SystemService},
-};
+use crate::grpc::system_proto::{StatusRequest, StatusResponse, system_service_server::SystemService};
 
 /// gRPC SystemService implementation
-pub(crate) struct GrpcSystemService;
+pub(crate) struct GrpcSystemService {
+    core_services: Arc<CoreServices>,
+}
 
 impl GrpcSystemService {
-    pub(crate) fn new() -> Self {
-        Self
+    pub(crate) fn new(core_services: Arc<CoreServices>) -> Self {
+        Self { core_services }
     }
 }
 
 #[tonic::async_trait]
 impl SystemService for GrpcSystemService {
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn status(&self, request: Request<StatusRequest>) -> Result<Response<StatusResponse>, Status> {
-        let response = handler::status(request.into_inner()).await.map_err(map_core_error)?;
+    async fn status(&self, _request: Request<StatusRequest>) -> Result<Response<StatusResponse>, Status> {
+        let response = handler::status(&self.core_services).await;
         Ok(Response::new(response))
     }
 }
 
 pub(crate) mod handler {
-    use hex_play_core::Error;
+    use hex_play_core::{
+        CoreServices,
+        status::{ComponentStatus, Health},
+    };
 
-    use crate::grpc::system_proto::{StatusRequest, StatusResponse};
+    use crate::grpc::system_proto::{ComponentStatus as ProtoComponentStatus, Health as ProtoHealth, StatusResponse};
 
-    pub(crate) async fn status(request: StatusRequest) -> Result<StatusResponse, Error> {
-        Ok(StatusResponse {
-            answer: format!("{}: Answered", request.question),
-        })
+    fn to_proto_health(health: Health) -> i32 {
+        match health {
+            Health::Up => ProtoHealth::Up,
+            Health::Down => ProtoHealth::Down,
+            Health::Disabled => ProtoHealth::Disabled,
+        }
+        .into()
+    }
+
+    fn to_proto(component: ComponentStatus) -> ProtoComponentStatus {
+        ProtoComponentStatus {
+            name: component.name,
+            health: to_proto_health(component.health),
+            latency: component.latency.and_then(|latency| prost_types::Duration::try_from(latency).ok()),
+            detail: component.detail,
+        }
+    }
+
+    pub(crate) async fn status(core_services: &CoreServices) -> StatusResponse {
+        let status = core_services.status_service.status().await;
+        StatusResponse {
+            health: to_proto_health(status.health()),
+            components: status.components.into_iter().map(to_proto).collect(),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use hex_play_core::{
+        CoreServices,
+        status::{ComponentStatus, Health, MockStatusService, SystemStatus},
+        test_support::{MockUserService, create_core_services_with_mock},
+    };
     use tonic::Request;
 
     use super::{GrpcSystemService, handler};
-    use crate::grpc::system_proto::{StatusRequest, system_service_server::SystemService};
+    use crate::grpc::system_proto::{Health as ProtoHealth, StatusRequest, system_service_server::SystemService};
 
     // ===================
-    // Tests: handler::status
+    // Test Helpers
     // ===================
-    #[tokio::test]
-    async fn test_handler_status_success() {
-        let request = StatusRequest { question: "Hello".into() };
-
-        let result = handler::status(request).await.unwrap();
-
-        assert_eq!(result.answer, "Hello: Answered");
+    fn create_core_services(components: Vec<ComponentStatus>) -> Arc<CoreServices> {
+        let mut status_service = MockStatusService::new();
+        status_service.expect_status().times(1).return_const(SystemStatus { components });
+        Arc::new(CoreServices {
+            status_service: Arc::new(status_service),
+            ..create_core_services_with_mock(MockUserService::new())
+        })
     }
 
+    // ===================
+    // Tests: handler::status
+    // ===================
     #[tokio::test]
-    async fn test_handler_status_empty_question() {
-        let request = StatusRequest { question: String::new() };
-
-        let result = handler::status(request).await.unwrap();
-
-        assert_eq!(result.answer, ": Answered");
+    async fn test_handler_status_reports_components() {
+        let core_services = create_core_services(vec![
+            ComponentStatus {
+                latency: Some(Duration::from_millis(3)),
+                ..ComponentStatus::new("database", Health::Up, "reachable")
+            },
+            ComponentStatus::new("job_queue", Health::Disabled, "background jobs are not configured"),
+        ]);
+
+        let response = handler::status(&core_services).await;
+
+        assert_eq!(response.health, i32::from(ProtoHealth::Up));
+        assert_eq!(response.components.len(), 2);
+        let database = &response.components[0];
+        assert_eq!(database.name, "database");
+        assert_eq!(database.health, i32::from(ProtoHealth::Up));
+        assert_eq!(database.latency, Some(prost_types::Duration { seconds: 0, nanos: 3_000_000 }));
+        assert_eq!(database.detail.as_deref(), Some("reachable"));
+        assert_eq!(response.components[1].health, i32::from(ProtoHealth::Disabled));
+        assert_eq!(response.components[1].latency, None);
     }
 
     #[tokio::test]
-    async fn test_handler_status_long_question() {
-        let long_question = "a".repeat(1000);
-        let request = StatusRequest {
-            question: long_question.clone(),
-        };
+    async fn test_handler_status_is_down_with_a_component_down() {
+        let core_services = create_core_services(vec![
+            ComponentStatus::new("database", Health::Down, "connection refused"),
+            ComponentStatus::new("cache", Health::Up, "in process"),
+        ]);
 
-        let result = handler::status(request).await.unwrap();
+        let response = handler::status(&core_services).await;
 
-        assert_eq!(result.answer, format!("{}: Answered", long_question));
+        assert_eq!(response.health, i32::from(ProtoHealth::Down));
     }
 
     // ===================
@@ -80,54 +129,62 @@ mod tests {
     // ===================
     #[tokio::test]
     async fn test_grpc_service_status() {
-        let service = GrpcSystemService::new();
-
-        let request = Request::new(StatusRequest {
-            question: "Test Question".into(),
-        });
-
-        let response = service.status(request).await.unwrap();
-        let status_response = response.into_inner();
-
-        assert_eq!(status_response.answer, "Test Question: Answered");
-    }
-
-    #[tokio::test]
-    async fn test_grpc_service_status_with_special_characters() {
-        let service = GrpcSystemService::new();
-
-        let request = Request::new(StatusRequest {
-            question: "What's the status? 🚀".into(),
-        });
+        let service = GrpcSystemService::new(create_core_services(vec![ComponentStatus::new("database", Health::Up, "reachable")]));
 
-        let response = service.status(request).await.unwrap();
-        let status_response = response.into_inner();
+        let response = service.status(Request::new(StatusRequest {})).await.unwrap().into_inner();
 
-        assert_eq!(status_response.answer, "What's the status? 🚀: Answered");
+        assert_eq!(response.health, i32::from(ProtoHealth::Up));
+        assert_eq!(response.components[0].name, "database");
     }
 }
 
 pub mod api {
-    use hex_play_core::Error;
+    use std::time::Duration;
+
+    use hex_play_core::{
+        Error,
+        status::{ComponentStatus, Health, SystemStatus},
+    };
 
     use crate::{
         ApiError,
-        grpc::system_proto::{StatusRequest, StatusResponse, system_service_client::SystemServiceClient},
+        grpc::{
+            error::map_status,
+            system_proto::{ComponentStatus as ProtoComponentStatus, Health as ProtoHealth, StatusRequest, system_service_client::SystemServiceClient},
+        },
+        trace_context::traced_request,
     };
 
+    /// Health the server did not set or this client does not know is taken
+    /// as down.
+    fn from_proto_health(health: i32) -> Health {
+        match ProtoHealth::try_from(health) {
+            Ok(ProtoHealth::Up) => Health::Up,
+            Ok(ProtoHealth::Disabled) => Health::Disabled,
+            _ => Health::Down,
+        }
+    }
+
+    fn from_proto(proto: ProtoComponentStatus) -> ComponentStatus {
+        ComponentStatus {
+            name: proto.name,
+            health: from_proto_health(proto.health),
+            latency: proto.latency.and_then(|latency| Duration::try_from(latency).ok()),
+            detail: proto.detail,
+        }
+    }
+
     #[tracing::instrument(level = "trace")]
-    pub async fn status(question: String) -> Result<String, Error> {
-        let mut client = SystemServiceClient::connect("http://localhost:3001")
+    pub async fn status(endpoint: &str) -> Result<SystemStatus, Error> {
+        let mut client = SystemServiceClient::connect(endpoint.to_string())
             .await
             .map_err(|e| Error::from(ApiError::GrpcClient(e.to_string())))?;
 
-        let request = tonic::Request::new(StatusRequest { question });
-        let response: StatusResponse = client
-            .status(request)
-            .await
-            .map_err(|e| Error::from(ApiError::GrpcClient(e.to_string())))?
-            .into_inner();
+        let request = traced_request(StatusRequest {});
+        let response = client.status(request).await.map_err(map_status)?.into_inner();
 
-        Ok(response.answer)
+        Ok(SystemStatus {
+            components: response.components.into_iter().map(from_proto).collect(),
+        })
     }
 }
@@ -1,13 +1,16 @@
 use std::sync::Arc;
 
-use hex_play_core::CoreServices;
+use hex_play_core::{CoreServices, types::RedactedEmail, user::MAX_PAGE_SIZE};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 
 use crate::grpc::{
     error::map_core_error,
+    request_context,
     user_proto::{
-        CreateUserRequest, DeleteUserRequest, GetUserByTokenRequest, GetUserRequest, ListUsersRequest, ListUsersResponse, UpdateUserRequest, User as ProtoUser,
-        user_service_server::UserService,
+        BatchGetUsersRequest, BatchGetUsersResponse, CreateUserRequest, DeleteUserRequest, ExportUsersRequest, GetUserByEmailRequest, GetUserByTokenRequest,
+        GetUserRequest, ListUsersRequest, ListUsersResponse, UpdateUserRequest, UpsertUserRequest, User as ProtoUser, user_service_server::UserService,
     },
 };
 
@@ -24,135 +27,343 @@ impl GrpcUserService {
 
 #[tonic::async_trait]
 impl UserService for GrpcUserService {
-    #[tracing::instrument(level = "trace", skip(self))]
+    #[tracing::instrument(level = "trace", skip(self, request), fields(email = %RedactedEmail::new(&request.get_ref().email)))]
     async fn create(&self, request: Request<CreateUserRequest>) -> Result<Response<ProtoUser>, Status> {
-        let response = handler::create(&self.core_services, request.into_inner()).await.map_err(map_core_error)?;
+        let response = handler::create(&self.core_services, &request_context(&request), request.into_inner())
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(response))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, request), fields(email = %RedactedEmail::new(&request.get_ref().email)))]
+    async fn upsert(&self, request: Request<UpsertUserRequest>) -> Result<Response<ProtoUser>, Status> {
+        let response = handler::upsert(&self.core_services, &request_context(&request), request.into_inner())
+            .await
+            .map_err(map_core_error)?;
         Ok(Response::new(response))
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
     async fn get(&self, request: Request<GetUserRequest>) -> Result<Response<ProtoUser>, Status> {
-        let response = handler::get(&self.core_services, request.into_inner()).await.map_err(map_core_error)?;
+        let response = handler::get(&self.core_services, &request_context(&request), request.into_inner())
+            .await
+            .map_err(map_core_error)?;
         Ok(Response::new(response))
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
     async fn get_by_token(&self, request: Request<GetUserByTokenRequest>) -> Result<Response<ProtoUser>, Status> {
-        let response = handler::get_by_token(&self.core_services, request.into_inner()).await.map_err(map_core_error)?;
+        let response = handler::get_by_token(&self.core_services, &request_context(&request), request.into_inner())
+            .await
+            .map_err(map_core_error)?;
         Ok(Response::new(response))
     }
 
-    #[tracing::instrument(level = "trace", skip(self))]
+    #[tracing::instrument(level = "trace", skip(self, request), fields(email = %RedactedEmail::new(&request.get_ref().email)))]
+    async fn get_by_email(&self, request: Request<GetUserByEmailRequest>) -> Result<Response<ProtoUser>, Status> {
+        let response = handler::get_by_email(&self.core_services, &request_context(&request), request.into_inner())
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(response))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, request), fields(id = request.get_ref().id, email = tracing::field::Empty))]
     async fn update(&self, request: Request<UpdateUserRequest>) -> Result<Response<ProtoUser>, Status> {
-        let response = handler::update(&self.core_services, request.into_inner()).await.map_err(map_core_error)?;
+        if let Some(email) = &request.get_ref().email {
+            tracing::Span::current().record("email", tracing::field::display(RedactedEmail::new(email)));
+        }
+        let response = handler::update(&self.core_services, &request_context(&request), request.into_inner())
+            .await
+            .map_err(map_core_error)?;
         Ok(Response::new(response))
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
     async fn delete(&self, request: Request<DeleteUserRequest>) -> Result<Response<ProtoUser>, Status> {
-        let response = handler::delete(&self.core_services, request.into_inner()).await.map_err(map_core_error)?;
+        let response = handler::delete(&self.core_services, &request_context(&request), request.into_inner())
+            .await
+            .map_err(map_core_error)?;
         Ok(Response::new(response))
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
     async fn list(&self, request: Request<ListUsersRequest>) -> Result<Response<ListUsersResponse>, Status> {
-        let response = handler::list(&self.core_services, request.into_inner()).await.map_err(map_core_error)?;
+        let response = handler::list(&self.core_services, &request_context(&request), request.into_inner())
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(response))
+    }
+
+    type ExportStream = ReceiverStream<Result<ProtoUser, Status>>;
+
+    /// Pages through the users on a background task. The bounded channel
+    /// holds at most one page, so a slow client pauses the export instead of
+    /// letting it buffer.
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn export(&self, request: Request<ExportUsersRequest>) -> Result<Response<Self::ExportStream>, Status> {
+        let (sender, receiver) = mpsc::channel(MAX_PAGE_SIZE as usize);
+        let core_services = self.core_services.clone();
+        let context = request_context(&request);
+        let request = request.into_inner();
+
+        tokio::spawn(async move {
+            if let Err(error) = handler::export(&core_services, &context, request, &sender).await {
+                // The client may already be gone; nothing left to report to.
+                let _ = sender.send(Err(map_core_error(error))).await;
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn batch_get(&self, request: Request<BatchGetUsersRequest>) -> Result<Response<BatchGetUsersResponse>, Status> {
+        let response = handler::batch_get(&self.core_services, &request_context(&request), request.into_inner())
+            .await
+            .map_err(map_core_error)?;
         Ok(Response::new(response))
     }
 }
 
 /// Server-side handlers (business logic)
 pub(crate) mod handler {
+    use chrono::{DateTime, Utc};
     use hex_play_core::{
         CoreServices, Error, RepositoryError,
+        context::RequestContext,
         types::{Age, Email},
-        user::{NewUser, PartialUserUpdate, UserToken},
+        user::{MAX_PAGE_SIZE, NewUser, PartialUserUpdate, UserId, UserToken, after_start_id},
     };
+    use tokio::sync::mpsc;
+    use tonic::Status;
 
     use crate::grpc::user_proto::{
-        CreateUserRequest, DeleteUserRequest, GetUserByTokenRequest, GetUserRequest, ListUsersRequest, ListUsersResponse, UpdateUserRequest, User as ProtoUser,
+        BatchGetUsersRequest, BatchGetUsersResponse, CreateUserRequest, DeleteUserRequest, ExportUsersRequest, GetUserByEmailRequest, GetUserByTokenRequest,
+        GetUserRequest, ListUsersRequest, ListUsersResponse, UpdateUserRequest, UpsertUserRequest, User as ProtoUser,
     };
 
-    fn to_proto(user: hex_play_core::user::User) -> ProtoUser {
+    /// Consumes `user` so its strings move into the message without copying.
+    pub(crate) fn to_proto(user: hex_play_core::user::User) -> ProtoUser {
         ProtoUser {
-            id: user.id,
+            id: user.id.value(),
             token: user.token.to_string(),
             name: user.name,
             email: user.email.into_inner(),
             age: user.age.value() as i32,
             version: user.version,
-            created_at: Some(prost_types::Timestamp {
-                seconds: user.created_at.timestamp(),
-                nanos: user.created_at.timestamp_subsec_nanos() as i32,
-            }),
-            updated_at: Some(prost_types::Timestamp {
-                seconds: user.updated_at.timestamp(),
-                nanos: user.updated_at.timestamp_subsec_nanos() as i32,
-            }),
+            created_at: Some(to_timestamp(user.created_at)),
+            updated_at: Some(to_timestamp(user.updated_at)),
+            last_seen_at: user.last_seen_at.map(to_timestamp),
         }
     }
 
-    pub(crate) async fn create(core_services: &CoreServices, request: CreateUserRequest) -> Result<ProtoUser, Error> {
+    pub(crate) fn to_timestamp(at: DateTime<Utc>) -> prost_types::Timestamp {
+        prost_types::Timestamp {
+            seconds: at.timestamp(),
+            nanos: at.timestamp_subsec_nanos() as i32,
+        }
+    }
+
+    pub(crate) async fn create(core_services: &CoreServices, context: &RequestContext, request: CreateUserRequest) -> Result<ProtoUser, Error> {
+        let new_user = NewUser {
+            name: request.name,
+            email: Email::new(request.email)?,
+            age: Age::new(request.age as i16)?,
+        };
+        let user = core_services.user_service.add_user(context, new_user).await?;
+        Ok(to_proto(user))
+    }
+
+    pub(crate) async fn upsert(core_services: &CoreServices, context: &RequestContext, request: UpsertUserRequest) -> Result<ProtoUser, Error> {
         let new_user = NewUser {
             name: request.name,
             email: Email::new(request.email)?,
             age: Age::new(request.age as i16)?,
         };
-        let user = core_services.user_service.add_user(new_user).await?;
+        let user = core_services.user_service.upsert_by_email(context, new_user).await?;
         Ok(to_proto(user))
     }
 
-    pub(crate) async fn get(core_services: &CoreServices, request: GetUserRequest) -> Result<ProtoUser, Error> {
+    pub(crate) async fn get(core_services: &CoreServices, context: &RequestContext, request: GetUserRequest) -> Result<ProtoUser, Error> {
         let user = core_services
             .user_service
-            .find_by_id(request.id)
+            .find_by_id(context, UserId::new(request.id))
             .await?
             .ok_or(Error::RepositoryError(RepositoryError::NotFound))?;
         Ok(to_proto(user))
     }
 
-    pub(crate) async fn get_by_token(core_services: &CoreServices, request: GetUserByTokenRequest) -> Result<ProtoUser, Error> {
+    pub(crate) async fn get_by_token(core_services: &CoreServices, context: &RequestContext, request: GetUserByTokenRequest) -> Result<ProtoUser, Error> {
         let token = UserToken::parse(&request.token).map_err(|e| Error::InvalidToken(e.to_string()))?;
         let user = core_services
             .user_service
-            .find_by_token(token)
+            .find_by_token(context, token)
             .await?
             .ok_or(Error::RepositoryError(RepositoryError::NotFound))?;
         Ok(to_proto(user))
     }
 
-    pub(crate) async fn update(core_services: &CoreServices, request: UpdateUserRequest) -> Result<ProtoUser, Error> {
-        let mut user = core_services
+    pub(crate) async fn get_by_email(core_services: &CoreServices, context: &RequestContext, request: GetUserByEmailRequest) -> Result<ProtoUser, Error> {
+        let email = Email::new(request.email)?;
+        let user = core_services
             .user_service
-            .find_by_id(request.id)
+            .find_by_email(context, email)
             .await?
             .ok_or(Error::RepositoryError(RepositoryError::NotFound))?;
+        Ok(to_proto(user))
+    }
 
-        let update = PartialUserUpdate {
-            name: request.name,
-            email: request.email.map(Email::new).transpose()?,
-            age: request.age.map(|a| Age::new(a as i16)).transpose()?,
-        };
-        update.apply_to(&mut user);
+    pub(crate) async fn update(core_services: &CoreServices, context: &RequestContext, request: UpdateUserRequest) -> Result<ProtoUser, Error> {
+        let id = UserId::new(request.id);
+        let update = partial_update(request)?;
 
-        let user = core_services.user_service.update_user(user).await?;
+        let user = core_services.user_service.update_user_partial(context, id, update).await?;
         Ok(to_proto(user))
     }
 
-    pub(crate) async fn delete(core_services: &CoreServices, request: DeleteUserRequest) -> Result<ProtoUser, Error> {
-        let user = core_services.user_service.delete_user(request.id).await?;
+    /// Fields an `update_mask` may name.
+    const UPDATE_MASK_PATHS: &[&str] = &["name", "email", "age"];
+
+    /// Builds the update from the set fields or, with a non-empty
+    /// `update_mask`, from exactly the fields it names.
+    pub(super) fn partial_update(mut request: UpdateUserRequest) -> Result<PartialUserUpdate, Error> {
+        let mask = request.update_mask.take().filter(|mask| !mask.paths.is_empty());
+        let mut update = PartialUserUpdate {
+            expected_version: request.expected_version,
+            ..PartialUserUpdate::default()
+        };
+
+        let Some(mask) = mask else {
+            if request.clear_age && request.age.is_some() {
+                return Err(Error::Validation("age and clear_age are mutually exclusive".into()));
+            }
+            update.name = request.name;
+            update.email = request.email.map(Email::new).transpose()?;
+            update.age = if request.clear_age {
+                Some(None)
+            } else {
+                request.age.map(|a| Age::new(a as i16)).transpose()?.map(Some)
+            };
+            return Ok(update);
+        };
+
+        if request.clear_age {
+            return Err(Error::Validation(
+                "clear_age cannot be combined with update_mask; name age in the mask instead".into(),
+            ));
+        }
+        let paths: Vec<&str> = if mask.paths.iter().any(|path| path == "*") {
+            UPDATE_MASK_PATHS.to_vec()
+        } else {
+            mask.paths.iter().map(String::as_str).collect()
+        };
+        for path in paths {
+            match path {
+                "name" => update.name = Some(request.name.take().ok_or_else(|| Error::Validation("name cannot be cleared".into()))?),
+                "email" => {
+                    let email = request.email.take().ok_or_else(|| Error::Validation("email cannot be cleared".into()))?;
+                    update.email = Some(Email::new(email)?);
+                }
+                "age" => update.age = Some(request.age.take().map(|a| Age::new(a as i16)).transpose()?),
+                other => return Err(Error::Validation(format!("unknown update_mask path `{other}`").into())),
+            }
+        }
+
+        Ok(update)
+    }
+
+    pub(crate) async fn delete(core_services: &CoreServices, context: &RequestContext, request: DeleteUserRequest) -> Result<ProtoUser, Error> {
+        let user = core_services.user_service.delete_user(context, UserId::new(request.id)).await?;
         Ok(to_proto(user))
     }
 
-    pub(crate) async fn list(core_services: &CoreServices, request: ListUsersRequest) -> Result<ListUsersResponse, Error> {
+    /// Lists one page of users. A full page gets a `next_cursor`, so a page
+    /// that happens to end at the last user is followed by an empty one.
+    pub(crate) async fn list(core_services: &CoreServices, context: &RequestContext, request: ListUsersRequest) -> Result<ListUsersResponse, Error> {
+        let page_size_applied = core_services.page_size_cap.apply(request.page_size);
+        let active_since = request
+            .active_since
+            .map(|ts| {
+                u32::try_from(ts.nanos)
+                    .ok()
+                    .and_then(|nanos| DateTime::from_timestamp(ts.seconds, nanos))
+                    .ok_or_else(|| Error::Validation("active_since is out of range".into()))
+            })
+            .transpose()?;
         let users = core_services
             .user_service
-            .list_users(request.start_id, request.page_size)
-            .await?
-            .into_iter()
-            .map(to_proto)
-            .collect();
-        Ok(ListUsersResponse { users })
+            .list_users(
+                context,
+                after_start_id(request.start_id.map(UserId::new)),
+                Some(page_size_applied),
+                active_since,
+            )
+            .await?;
+        let next_cursor = match users.last() {
+            Some(last) if users.len() as u64 >= page_size_applied => Some(last.id.value() + 1),
+            _ => None,
+        };
+        let total_size = if request.return_total {
+            Some(core_services.user_service.count_users(context).await?)
+        } else {
+            None
+        };
+
+        Ok(ListUsersResponse {
+            users: users.into_iter().map(to_proto).collect(),
+            next_cursor,
+            total_size,
+            page_size_applied,
+        })
+    }
+
+    /// Sends every user from `request.start_id` onwards to `sender`, one page
+    /// at a time. Stops early, without error, once the receiver is dropped.
+    pub(crate) async fn export(
+        core_services: &CoreServices,
+        context: &RequestContext,
+        request: ExportUsersRequest,
+        sender: &mpsc::Sender<Result<ProtoUser, Status>>,
+    ) -> Result<(), Error> {
+        let mut after_id = after_start_id(request.start_id.map(UserId::new));
+        loop {
+            let page = core_services.user_service.list_users(context, after_id, Some(MAX_PAGE_SIZE), None).await?;
+            let is_last_page = (page.len() as u64) < MAX_PAGE_SIZE;
+            after_id = page.last().map(|user| user.id);
+
+            for user in page {
+                if sender.send(Ok(to_proto(user))).await.is_err() {
+                    return Ok(());
+                }
+            }
+            if is_last_page {
+                return Ok(());
+            }
+        }
+    }
+
+    pub(crate) async fn batch_get(
+        core_services: &CoreServices,
+        context: &RequestContext,
+        request: BatchGetUsersRequest,
+    ) -> Result<BatchGetUsersResponse, Error> {
+        let tokens = request
+            .tokens
+            .iter()
+            .map(|token| UserToken::parse(token).map_err(|e| Error::InvalidToken(e.to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let ids: Vec<UserId> = request.ids.into_iter().map(UserId::new).collect();
+
+        let mut users = core_services.user_service.find_by_ids(context, &ids).await?;
+        users.extend(core_services.user_service.find_by_tokens(context, &tokens).await?);
+        // A user may be requested by both id and token.
+        users.sort_by_key(|user| user.id);
+        users.dedup_by_key(|user| user.id);
+
+        Ok(BatchGetUsersResponse {
+            users: users.into_iter().map(to_proto).collect(),
+        })
     }
 }
 
@@ -160,14 +371,18 @@ pub(crate) mod handler {
 mod tests {
     use hex_play_core::{
         Error, RepositoryError,
+        context::RequestContext,
         test_support::{MockUserService, create_arc_core_services_with_mock, create_core_services_with_mock},
-        user::{User, UserToken},
+        user::{MAX_PAGE_SIZE, PartialUserUpdate, User, UserId, UserToken},
     };
+    use mockall::predicate::{always, eq};
+    use tokio::sync::mpsc;
     use tonic::{Code, Request};
 
     use super::{GrpcUserService, handler};
     use crate::grpc::user_proto::{
-        CreateUserRequest, DeleteUserRequest, GetUserByTokenRequest, GetUserRequest, ListUsersRequest, UpdateUserRequest, user_service_server::UserService,
+        BatchGetUsersRequest, CreateUserRequest, DeleteUserRequest, ExportUsersRequest, GetUserByEmailRequest, GetUserByTokenRequest, GetUserRequest,
+        ListUsersRequest, UpdateUserRequest, UpsertUserRequest, user_service_server::UserService,
     };
 
     // ===================
@@ -183,7 +398,8 @@ mod tests {
     #[tokio::test]
     async fn test_handler_create_success() {
         let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
-        let mock = MockUserService::default().with_add_user_result(Ok(user.clone()));
+        let mut mock = MockUserService::new();
+        mock.expect_add_user().return_const(Ok(user.clone()));
         let core_services = create_core_services_with_mock(mock);
 
         let request = CreateUserRequest {
@@ -192,7 +408,7 @@ mod tests {
             age: 30,
         };
 
-        let result = handler::create(&core_services, request).await.unwrap();
+        let result = handler::create(&core_services, &RequestContext::internal(), request).await.unwrap();
 
         assert_eq!(result.id, 1);
         assert_eq!(result.name, "John Doe");
@@ -206,7 +422,8 @@ mod tests {
     #[tokio::test]
     async fn test_handler_create_with_zero_age() {
         let user = User::fake(1, "John Doe", "john@example.com");
-        let mock = MockUserService::default().with_add_user_result(Ok(user));
+        let mut mock = MockUserService::new();
+        mock.expect_add_user().return_const(Ok(user));
         let core_services = create_core_services_with_mock(mock);
 
         let request = CreateUserRequest {
@@ -215,14 +432,16 @@ mod tests {
             age: 0,
         };
 
-        let result = handler::create(&core_services, request).await.unwrap();
+        let result = handler::create(&core_services, &RequestContext::internal(), request).await.unwrap();
 
         assert_eq!(result.age, 0);
     }
 
     #[tokio::test]
     async fn test_handler_create_constraint_violation() {
-        let mock = MockUserService::default().with_add_user_result(Err(Error::RepositoryError(RepositoryError::Constraint("duplicate email".into()))));
+        let mut mock = MockUserService::new();
+        mock.expect_add_user()
+            .return_const(Err(Error::RepositoryError(RepositoryError::Constraint("duplicate email".into()))));
         let core_services = create_core_services_with_mock(mock);
 
         let request = CreateUserRequest {
@@ -231,23 +450,62 @@ mod tests {
             age: 30,
         };
 
-        let result = handler::create(&core_services, request).await;
+        let result = handler::create(&core_services, &RequestContext::internal(), request).await;
 
         assert!(result.is_err());
     }
 
+    // ===================
+    // Tests: handler::upsert
+    // ===================
+    #[tokio::test]
+    async fn test_handler_upsert_success() {
+        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let mut mock = MockUserService::new();
+        mock.expect_upsert_by_email().return_const(Ok(user));
+        let core_services = create_core_services_with_mock(mock);
+
+        let request = UpsertUserRequest {
+            name: "John Doe".into(),
+            email: "john@example.com".into(),
+            age: 30,
+        };
+
+        let result = handler::upsert(&core_services, &RequestContext::internal(), request).await.unwrap();
+
+        assert_eq!(result.id, 1);
+        assert_eq!(result.email, "john@example.com");
+        assert_eq!(result.age, 30);
+    }
+
+    #[tokio::test]
+    async fn test_handler_upsert_invalid_email() {
+        let core_services = create_core_services_with_mock(MockUserService::new());
+
+        let request = UpsertUserRequest {
+            name: "John Doe".into(),
+            email: "invalid".into(),
+            age: 30,
+        };
+
+        let result = handler::upsert(&core_services, &RequestContext::internal(), request).await;
+
+        assert!(matches!(result.unwrap_err(), Error::Validation(_)));
+    }
+
     // ===================
     // Tests: handler::get
     // ===================
     #[tokio::test]
     async fn test_handler_get_success() {
         let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
-        let mock = MockUserService::default().with_find_by_id_result(Ok(Some(user)));
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(Some(user)));
         let core_services = create_core_services_with_mock(mock);
 
         let request = GetUserRequest { id: 1 };
 
-        let result = handler::get(&core_services, request).await.unwrap();
+        let result = handler::get(&core_services, &RequestContext::internal(), request).await.unwrap();
 
         assert_eq!(result.id, 1);
         assert_eq!(result.name, "John Doe");
@@ -257,12 +515,13 @@ mod tests {
 
     #[tokio::test]
     async fn test_handler_get_not_found() {
-        let mock = MockUserService::default().with_find_by_id_result(Ok(None));
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(None));
         let core_services = create_core_services_with_mock(mock);
 
         let request = GetUserRequest { id: 999 };
 
-        let result = handler::get(&core_services, request).await;
+        let result = handler::get(&core_services, &RequestContext::internal(), request).await;
 
         assert!(result.is_err());
         let err = result.unwrap_err();
@@ -271,12 +530,13 @@ mod tests {
 
     #[tokio::test]
     async fn test_handler_get_invalid_id() {
-        let mock = MockUserService::default().with_find_by_id_result(Err(Error::InvalidId(0)));
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Err(Error::InvalidId(0)));
         let core_services = create_core_services_with_mock(mock);
 
         let request = GetUserRequest { id: 0 };
 
-        let result = handler::get(&core_services, request).await;
+        let result = handler::get(&core_services, &RequestContext::internal(), request).await;
 
         assert!(result.is_err());
     }
@@ -288,12 +548,13 @@ mod tests {
     async fn test_handler_get_by_token_success() {
         let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
         let token = user.token;
-        let mock = MockUserService::default().with_find_by_token_result(Ok(Some(user)));
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_token().return_const(Ok(Some(user)));
         let core_services = create_core_services_with_mock(mock);
 
         let request = GetUserByTokenRequest { token: token.to_string() };
 
-        let result = handler::get_by_token(&core_services, request).await.unwrap();
+        let result = handler::get_by_token(&core_services, &RequestContext::internal(), request).await.unwrap();
 
         assert_eq!(result.id, 1);
         assert_eq!(result.token, token.to_string());
@@ -301,39 +562,91 @@ mod tests {
 
     #[tokio::test]
     async fn test_handler_get_by_token_not_found() {
-        let mock = MockUserService::default().with_find_by_token_result(Ok(None));
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_token().return_const(Ok(None));
         let core_services = create_core_services_with_mock(mock);
 
         let token = UserToken::generate();
         let request = GetUserByTokenRequest { token: token.to_string() };
 
-        let result = handler::get_by_token(&core_services, request).await;
+        let result = handler::get_by_token(&core_services, &RequestContext::internal(), request).await;
 
         assert!(result.is_err());
     }
 
     #[tokio::test]
     async fn test_handler_get_by_token_invalid_uuid() {
-        let mock = MockUserService::default();
+        let mock = MockUserService::new();
         let core_services = create_core_services_with_mock(mock);
 
         let request = GetUserByTokenRequest { token: "not-a-uuid".into() };
 
-        let result = handler::get_by_token(&core_services, request).await;
+        let result = handler::get_by_token(&core_services, &RequestContext::internal(), request).await;
 
         assert!(result.is_err());
     }
 
+    // ===================
+    // Tests: handler::get_by_email
+    // ===================
+    #[tokio::test]
+    async fn test_handler_get_by_email_success() {
+        let user = User::fake(1, "John Doe", "john+tag@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_email().return_const(Ok(Some(user)));
+        let core_services = create_core_services_with_mock(mock);
+
+        let request = GetUserByEmailRequest {
+            email: "john+tag@example.com".into(),
+        };
+
+        let result = handler::get_by_email(&core_services, &RequestContext::internal(), request).await.unwrap();
+
+        assert_eq!(result.id, 1);
+        assert_eq!(result.email, "john+tag@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_handler_get_by_email_not_found() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_email().return_const(Ok(None));
+        let core_services = create_core_services_with_mock(mock);
+
+        let request = GetUserByEmailRequest {
+            email: "none@example.com".into(),
+        };
+
+        let result = handler::get_by_email(&core_services, &RequestContext::internal(), request).await;
+
+        assert!(matches!(result.unwrap_err(), Error::RepositoryError(RepositoryError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_handler_get_by_email_invalid() {
+        let core_services = create_core_services_with_mock(MockUserService::new());
+
+        let request = GetUserByEmailRequest { email: "invalid".into() };
+
+        let result = handler::get_by_email(&core_services, &RequestContext::internal(), request).await;
+
+        assert!(matches!(result.unwrap_err(), Error::Validation(_)));
+    }
+
     // ===================
     // Tests: handler::update
     // ===================
     #[tokio::test]
     async fn test_handler_update_success() {
-        let existing = User::fake_with_age(1, "John Doe", "john@example.com", 30);
         let updated = User::fake_with_age(1, "John Updated", "john@example.com", 30);
-        let mock = MockUserService::default()
-            .with_find_by_id_result(Ok(Some(existing)))
-            .with_update_user_result(Ok(updated));
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(Some("John Updated"), None::<String>, None).unwrap()),
+            )
+            .times(1)
+            .return_const(Ok(updated));
         let core_services = create_core_services_with_mock(mock);
 
         let request = UpdateUserRequest {
@@ -341,9 +654,12 @@ mod tests {
             name: Some("John Updated".into()),
             email: None,
             age: None,
+            clear_age: false,
+            expected_version: None,
+            update_mask: None,
         };
 
-        let result = handler::update(&core_services, request).await.unwrap();
+        let result = handler::update(&core_services, &RequestContext::internal(), request).await.unwrap();
 
         assert_eq!(result.name, "John Updated");
         assert_eq!(result.age, 30);
@@ -351,11 +667,16 @@ mod tests {
 
     #[tokio::test]
     async fn test_handler_update_email_only() {
-        let existing = User::fake_with_age(1, "John Doe", "john@example.com", 25);
         let updated = User::fake_with_age(1, "John Doe", "john.new@example.com", 25);
-        let mock = MockUserService::default()
-            .with_find_by_id_result(Ok(Some(existing)))
-            .with_update_user_result(Ok(updated));
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(None::<String>, Some("john.new@example.com"), None).unwrap()),
+            )
+            .times(1)
+            .return_const(Ok(updated));
         let core_services = create_core_services_with_mock(mock);
 
         let request = UpdateUserRequest {
@@ -363,20 +684,28 @@ mod tests {
             name: None,
             email: Some("john.new@example.com".into()),
             age: None,
+            clear_age: false,
+            expected_version: None,
+            update_mask: None,
         };
 
-        let result = handler::update(&core_services, request).await.unwrap();
+        let result = handler::update(&core_services, &RequestContext::internal(), request).await.unwrap();
 
         assert_eq!(result.email, "john.new@example.com");
     }
 
     #[tokio::test]
     async fn test_handler_update_age_only() {
-        let existing = User::fake_with_age(1, "John Doe", "john@example.com", 30);
         let updated = User::fake_with_age(1, "John Doe", "john@example.com", 31);
-        let mock = MockUserService::default()
-            .with_find_by_id_result(Ok(Some(existing)))
-            .with_update_user_result(Ok(updated));
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(None::<String>, None::<String>, Some(31)).unwrap()),
+            )
+            .times(1)
+            .return_const(Ok(updated));
         let core_services = create_core_services_with_mock(mock);
 
         let request = UpdateUserRequest {
@@ -384,16 +713,27 @@ mod tests {
             name: None,
             email: None,
             age: Some(31),
+            clear_age: false,
+            expected_version: None,
+            update_mask: None,
         };
 
-        let result = handler::update(&core_services, request).await.unwrap();
+        let result = handler::update(&core_services, &RequestContext::internal(), request).await.unwrap();
 
         assert_eq!(result.age, 31);
     }
 
     #[tokio::test]
     async fn test_handler_update_not_found() {
-        let mock = MockUserService::default().with_find_by_id_result(Ok(None));
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(999)),
+                eq(PartialUserUpdate::new(Some("Updated"), None::<String>, None).unwrap()),
+            )
+            .times(1)
+            .return_const(Err(Error::RepositoryError(RepositoryError::NotFound)));
         let core_services = create_core_services_with_mock(mock);
 
         let request = UpdateUserRequest {
@@ -401,19 +741,27 @@ mod tests {
             name: Some("Updated".into()),
             email: None,
             age: None,
+            clear_age: false,
+            expected_version: None,
+            update_mask: None,
         };
 
-        let result = handler::update(&core_services, request).await;
+        let result = handler::update(&core_services, &RequestContext::internal(), request).await;
 
         assert!(result.is_err());
     }
 
     #[tokio::test]
     async fn test_handler_update_conflict() {
-        let existing = User::fake(1, "John Doe", "john@example.com");
-        let mock = MockUserService::default()
-            .with_find_by_id_result(Ok(Some(existing)))
-            .with_update_user_result(Err(Error::RepositoryError(RepositoryError::Conflict)));
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(Some("Updated"), None::<String>, None).unwrap()),
+            )
+            .times(1)
+            .return_const(Err(Error::RepositoryError(RepositoryError::Conflict)));
         let core_services = create_core_services_with_mock(mock);
 
         let request = UpdateUserRequest {
@@ -421,25 +769,192 @@ mod tests {
             name: Some("Updated".into()),
             email: None,
             age: None,
+            clear_age: false,
+            expected_version: None,
+            update_mask: None,
         };
 
-        let result = handler::update(&core_services, request).await;
+        let result = handler::update(&core_services, &RequestContext::internal(), request).await;
 
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_handler_update_expected_version_mismatch() {
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(Some("Updated"), None::<String>, None).unwrap().with_expected_version(4)),
+            )
+            .times(1)
+            .return_const(Err(Error::RepositoryError(RepositoryError::Conflict)));
+        let core_services = create_core_services_with_mock(mock);
+
+        let request = UpdateUserRequest {
+            id: 1,
+            name: Some("Updated".into()),
+            email: None,
+            age: None,
+            clear_age: false,
+            expected_version: Some(4),
+            update_mask: None,
+        };
+
+        let result = handler::update(&core_services, &RequestContext::internal(), request).await;
+
+        assert!(matches!(result.unwrap_err(), Error::RepositoryError(RepositoryError::Conflict)));
+    }
+
+    #[tokio::test]
+    async fn test_handler_update_expected_version_match() {
+        let updated = User::fake(1, "Updated", "john@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(Some("Updated"), None::<String>, None).unwrap().with_expected_version(5)),
+            )
+            .times(1)
+            .return_const(Ok(updated));
+        let core_services = create_core_services_with_mock(mock);
+
+        let request = UpdateUserRequest {
+            id: 1,
+            name: Some("Updated".into()),
+            email: None,
+            age: None,
+            clear_age: false,
+            expected_version: Some(5),
+            update_mask: None,
+        };
+
+        let result = handler::update(&core_services, &RequestContext::internal(), request).await.unwrap();
+
+        assert_eq!(result.name, "Updated");
+    }
+
+    #[tokio::test]
+    async fn test_handler_update_empty() {
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(always(), eq(UserId::new(1)), eq(PartialUserUpdate::default()))
+            .return_const(Err(Error::EmptyUpdate));
+        let core_services = create_core_services_with_mock(mock);
+
+        let request = UpdateUserRequest {
+            id: 1,
+            name: None,
+            email: None,
+            age: None,
+            clear_age: false,
+            expected_version: None,
+            update_mask: None,
+        };
+
+        let result = handler::update(&core_services, &RequestContext::internal(), request).await;
+
+        assert!(matches!(result.unwrap_err(), Error::EmptyUpdate));
+    }
+
+    #[tokio::test]
+    async fn test_handler_update_age_and_clear_age() {
+        let core_services = create_core_services_with_mock(MockUserService::new());
+
+        let request = UpdateUserRequest {
+            id: 1,
+            name: None,
+            email: None,
+            age: Some(30),
+            clear_age: true,
+            expected_version: None,
+            update_mask: None,
+        };
+
+        let result = handler::update(&core_services, &RequestContext::internal(), request).await;
+
+        assert!(matches!(result.unwrap_err(), Error::Validation(_)));
+    }
+
+    // ===================
+    // Tests: handler::partial_update
+    // ===================
+    fn masked_request(paths: &[&str]) -> UpdateUserRequest {
+        UpdateUserRequest {
+            id: 1,
+            name: Some("John Updated".into()),
+            email: Some("john.new@example.com".into()),
+            age: None,
+            clear_age: false,
+            expected_version: None,
+            update_mask: Some(prost_types::FieldMask {
+                paths: paths.iter().map(|path| path.to_string()).collect(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_partial_update_mask_clears_unset_field_and_ignores_unnamed() {
+        let update = handler::partial_update(masked_request(&["name", "age"])).unwrap();
+
+        assert_eq!(update.name.as_deref(), Some("John Updated"));
+        assert!(update.email.is_none());
+        assert_eq!(update.age, Some(None));
+    }
+
+    #[test]
+    fn test_partial_update_wildcard_mask_names_every_field() {
+        let mut request = masked_request(&["*"]);
+        request.age = Some(40);
+
+        let update = handler::partial_update(request).unwrap();
+
+        assert!(update.name.is_some());
+        assert!(update.email.is_some());
+        assert_eq!(update.age.flatten().map(|age| age.value()), Some(40));
+    }
+
+    #[test]
+    fn test_partial_update_empty_mask_updates_set_fields() {
+        let update = handler::partial_update(masked_request(&[])).unwrap();
+
+        assert!(update.name.is_some());
+        assert!(update.email.is_some());
+        assert!(update.age.is_none());
+    }
+
+    #[test]
+    fn test_partial_update_mask_rejects_clearing_required_field() {
+        let mut request = masked_request(&["name"]);
+        request.name = None;
+
+        assert!(matches!(handler::partial_update(request).unwrap_err(), Error::Validation(message) if message == "name cannot be cleared"));
+    }
+
+    #[test]
+    fn test_partial_update_mask_rejects_unknown_path_and_clear_age() {
+        assert!(matches!(handler::partial_update(masked_request(&["token"])).unwrap_err(), Error::Validation(_)));
+
+        let mut request = masked_request(&["age"]);
+        request.clear_age = true;
+        assert!(matches!(handler::partial_update(request).unwrap_err(), Error::Validation(_)));
+    }
+
     // ===================
     // Tests: handler::delete
     // ===================
     #[tokio::test]
     async fn test_handler_delete_success() {
         let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
-        let mock = MockUserService::default().with_delete_user_result(Ok(user));
+        let mut mock = MockUserService::new();
+        mock.expect_delete_user().return_const(Ok(user));
         let core_services = create_core_services_with_mock(mock);
 
         let request = DeleteUserRequest { id: 1 };
 
-        let result = handler::delete(&core_services, request).await.unwrap();
+        let result = handler::delete(&core_services, &RequestContext::internal(), request).await.unwrap();
 
         assert_eq!(result.id, 1);
         assert_eq!(result.name, "John Doe");
@@ -448,12 +963,13 @@ mod tests {
 
     #[tokio::test]
     async fn test_handler_delete_not_found() {
-        let mock = MockUserService::default().with_delete_user_result(Err(Error::RepositoryError(RepositoryError::NotFound)));
+        let mut mock = MockUserService::new();
+        mock.expect_delete_user().return_const(Err(Error::RepositoryError(RepositoryError::NotFound)));
         let core_services = create_core_services_with_mock(mock);
 
         let request = DeleteUserRequest { id: 999 };
 
-        let result = handler::delete(&core_services, request).await;
+        let result = handler::delete(&core_services, &RequestContext::internal(), request).await;
 
         assert!(result.is_err());
     }
@@ -467,15 +983,18 @@ mod tests {
             User::fake_with_age(1, "John Doe", "john@example.com", 30),
             User::fake_with_age(2, "Jane Doe", "jane@example.com", 25),
         ];
-        let mock = MockUserService::default().with_list_users_result(Ok(users));
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Ok(users));
         let core_services = create_core_services_with_mock(mock);
 
         let request = ListUsersRequest {
             start_id: None,
             page_size: None,
+            return_total: false,
+            active_since: None,
         };
 
-        let result = handler::list(&core_services, request).await.unwrap();
+        let result = handler::list(&core_services, &RequestContext::internal(), request).await.unwrap();
 
         assert_eq!(result.users.len(), 2);
         assert_eq!(result.users[0].name, "John Doe");
@@ -484,15 +1003,18 @@ mod tests {
 
     #[tokio::test]
     async fn test_handler_list_empty() {
-        let mock = MockUserService::default().with_list_users_result(Ok(vec![]));
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Ok(vec![]));
         let core_services = create_core_services_with_mock(mock);
 
         let request = ListUsersRequest {
             start_id: None,
             page_size: None,
+            return_total: false,
+            active_since: None,
         };
 
-        let result = handler::list(&core_services, request).await.unwrap();
+        let result = handler::list(&core_services, &RequestContext::internal(), request).await.unwrap();
 
         assert!(result.users.is_empty());
     }
@@ -500,41 +1022,209 @@ mod tests {
     #[tokio::test]
     async fn test_handler_list_with_pagination() {
         let users = vec![User::fake(5, "User Five", "five@example.com")];
-        let mock = MockUserService::default().with_list_users_result(Ok(users));
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Ok(users));
         let core_services = create_core_services_with_mock(mock);
 
         let request = ListUsersRequest {
             start_id: Some(5),
             page_size: Some(10),
+            return_total: false,
+            active_since: None,
         };
 
-        let result = handler::list(&core_services, request).await.unwrap();
+        let result = handler::list(&core_services, &RequestContext::internal(), request).await.unwrap();
 
         assert_eq!(result.users.len(), 1);
+        assert_eq!(result.page_size_applied, 10);
+        assert_eq!(result.next_cursor, None);
+        assert_eq!(result.total_size, None);
+    }
+
+    #[tokio::test]
+    async fn test_handler_list_full_page_has_next_cursor() {
+        let users = vec![User::fake(5, "User Five", "five@example.com"), User::fake(7, "User Seven", "seven@example.com")];
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Ok(users));
+        let core_services = create_core_services_with_mock(mock);
+
+        let request = ListUsersRequest {
+            start_id: Some(5),
+            page_size: Some(2),
+            return_total: false,
+            active_since: None,
+        };
+
+        let result = handler::list(&core_services, &RequestContext::internal(), request).await.unwrap();
+
+        assert_eq!(result.next_cursor, Some(8));
+        assert_eq!(result.page_size_applied, 2);
+    }
+
+    #[tokio::test]
+    async fn test_handler_list_caps_page_size_and_returns_total() {
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Ok(vec![]));
+        mock.expect_count_users().return_const(Ok(3));
+        let core_services = create_core_services_with_mock(mock);
+
+        let request = ListUsersRequest {
+            start_id: None,
+            page_size: Some(MAX_PAGE_SIZE + 1),
+            return_total: true,
+            active_since: None,
+        };
+
+        let result = handler::list(&core_services, &RequestContext::internal(), request).await.unwrap();
+
+        assert_eq!(result.page_size_applied, MAX_PAGE_SIZE);
+        assert_eq!(result.total_size, Some(3));
+        assert_eq!(result.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn test_handler_list_active_since_out_of_range() {
+        let core_services = create_core_services_with_mock(MockUserService::new());
+
+        let request = ListUsersRequest {
+            start_id: None,
+            page_size: None,
+            return_total: false,
+            active_since: Some(prost_types::Timestamp { seconds: 0, nanos: -1 }),
+        };
+
+        let result = handler::list(&core_services, &RequestContext::internal(), request).await;
+
+        assert!(matches!(result, Err(Error::Validation(_))));
     }
 
     #[tokio::test]
     async fn test_handler_list_invalid_start_id() {
-        let mock = MockUserService::default().with_list_users_result(Err(Error::InvalidId(0)));
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Err(Error::InvalidId(0)));
         let core_services = create_core_services_with_mock(mock);
 
         let request = ListUsersRequest {
             start_id: Some(0),
             page_size: None,
+            return_total: false,
+            active_since: None,
         };
 
-        let result = handler::list(&core_services, request).await;
+        let result = handler::list(&core_services, &RequestContext::internal(), request).await;
 
         assert!(result.is_err());
     }
 
+    // ===================
+    // Tests: handler::export
+    // ===================
+    #[tokio::test]
+    async fn test_handler_export_sends_every_user() {
+        let users = vec![User::fake(1, "John Doe", "john@example.com"), User::fake(2, "Jane Doe", "jane@example.com")];
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Ok(users));
+        let core_services = create_core_services_with_mock(mock);
+        let (sender, mut receiver) = mpsc::channel(10);
+
+        handler::export(&core_services, &RequestContext::internal(), ExportUsersRequest { start_id: None }, &sender)
+            .await
+            .unwrap();
+        drop(sender);
+
+        let mut ids = Vec::new();
+        while let Some(user) = receiver.recv().await {
+            ids.push(user.unwrap().id);
+        }
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_handler_export_stops_when_receiver_dropped() {
+        let users = vec![User::fake(1, "John Doe", "john@example.com")];
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Ok(users));
+        let core_services = create_core_services_with_mock(mock);
+        let (sender, receiver) = mpsc::channel(1);
+        drop(receiver);
+
+        let result = handler::export(&core_services, &RequestContext::internal(), ExportUsersRequest { start_id: None }, &sender).await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_handler_export_error() {
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Err(Error::InvalidId(0)));
+        let core_services = create_core_services_with_mock(mock);
+        let (sender, _receiver) = mpsc::channel(1);
+
+        let result = handler::export(&core_services, &RequestContext::internal(), ExportUsersRequest { start_id: Some(0) }, &sender).await;
+
+        assert!(matches!(result.unwrap_err(), Error::InvalidId(0)));
+    }
+
+    // ===================
+    // Tests: handler::batch_get
+    // ===================
+    #[tokio::test]
+    async fn test_handler_batch_get_merges_ids_and_tokens() {
+        let john = User::fake(1, "John Doe", "john@example.com");
+        let jane = User::fake(2, "Jane Doe", "jane@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_ids().return_const(Ok(vec![jane.clone()]));
+        mock.expect_find_by_tokens().return_const(Ok(vec![john.clone(), jane.clone()]));
+        let core_services = create_core_services_with_mock(mock);
+
+        let request = BatchGetUsersRequest {
+            ids: vec![2],
+            tokens: vec![john.token.to_string(), jane.token.to_string()],
+        };
+
+        let result = handler::batch_get(&core_services, &RequestContext::internal(), request).await.unwrap();
+
+        assert_eq!(result.users.iter().map(|user| user.id).collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_handler_batch_get_invalid_token() {
+        let core_services = create_core_services_with_mock(MockUserService::new());
+
+        let request = BatchGetUsersRequest {
+            ids: vec![],
+            tokens: vec!["not-a-token".into()],
+        };
+
+        let result = handler::batch_get(&core_services, &RequestContext::internal(), request).await;
+
+        assert!(matches!(result.unwrap_err(), Error::InvalidToken(_)));
+    }
+
+    #[tokio::test]
+    async fn test_handler_batch_get_too_many() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_ids().return_const(Err(Error::InvalidBatchSize(101)));
+        let core_services = create_core_services_with_mock(mock);
+
+        let request = BatchGetUsersRequest {
+            ids: (1..=101).collect(),
+            tokens: vec![],
+        };
+
+        let result = handler::batch_get(&core_services, &RequestContext::internal(), request).await;
+
+        assert!(matches!(result.unwrap_err(), Error::InvalidBatchSize(101)));
+    }
+
     // ===================
     // Tests: GrpcUserService trait implementation
     // ===================
     #[tokio::test]
     async fn test_grpc_service_create() {
         let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
-        let mock = MockUserService::default().with_add_user_result(Ok(user));
+        let mut mock = MockUserService::new();
+        mock.expect_add_user().return_const(Ok(user));
         let service = create_test_service(mock);
 
         let request = Request::new(CreateUserRequest {
@@ -552,7 +1242,9 @@ mod tests {
 
     #[tokio::test]
     async fn test_grpc_service_create_error_maps_to_status() {
-        let mock = MockUserService::default().with_add_user_result(Err(Error::RepositoryError(RepositoryError::Constraint("duplicate".into()))));
+        let mut mock = MockUserService::new();
+        mock.expect_add_user()
+            .return_const(Err(Error::RepositoryError(RepositoryError::Constraint("duplicate".into()))));
         let service = create_test_service(mock);
 
         let request = Request::new(CreateUserRequest {
@@ -571,7 +1263,8 @@ mod tests {
     #[tokio::test]
     async fn test_grpc_service_get() {
         let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
-        let mock = MockUserService::default().with_find_by_id_result(Ok(Some(user)));
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(Some(user)));
         let service = create_test_service(mock);
 
         let request = Request::new(GetUserRequest { id: 1 });
@@ -584,7 +1277,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_grpc_service_get_not_found_maps_to_status() {
-        let mock = MockUserService::default().with_find_by_id_result(Ok(None));
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(None));
         let service = create_test_service(mock);
 
         let request = Request::new(GetUserRequest { id: 999 });
@@ -600,7 +1294,8 @@ mod tests {
     async fn test_grpc_service_get_by_token() {
         let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
         let token = user.token;
-        let mock = MockUserService::default().with_find_by_token_result(Ok(Some(user)));
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_token().return_const(Ok(Some(user)));
         let service = create_test_service(mock);
 
         let request = Request::new(GetUserByTokenRequest { token: token.to_string() });
@@ -613,11 +1308,16 @@ mod tests {
 
     #[tokio::test]
     async fn test_grpc_service_update() {
-        let existing = User::fake_with_age(1, "John Doe", "john@example.com", 30);
         let updated = User::fake_with_age(1, "John Updated", "john@example.com", 30);
-        let mock = MockUserService::default()
-            .with_find_by_id_result(Ok(Some(existing)))
-            .with_update_user_result(Ok(updated));
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(Some("John Updated"), None::<String>, None).unwrap()),
+            )
+            .times(1)
+            .return_const(Ok(updated));
         let service = create_test_service(mock);
 
         let request = Request::new(UpdateUserRequest {
@@ -625,6 +1325,9 @@ mod tests {
             name: Some("John Updated".into()),
             email: None,
             age: None,
+            clear_age: false,
+            expected_version: None,
+            update_mask: None,
         });
 
         let response = service.update(request).await.unwrap();
@@ -635,10 +1338,15 @@ mod tests {
 
     #[tokio::test]
     async fn test_grpc_service_update_conflict_maps_to_status() {
-        let existing = User::fake(1, "John Doe", "john@example.com");
-        let mock = MockUserService::default()
-            .with_find_by_id_result(Ok(Some(existing)))
-            .with_update_user_result(Err(Error::RepositoryError(RepositoryError::Conflict)));
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(Some("Updated"), None::<String>, None).unwrap()),
+            )
+            .times(1)
+            .return_const(Err(Error::RepositoryError(RepositoryError::Conflict)));
         let service = create_test_service(mock);
 
         let request = Request::new(UpdateUserRequest {
@@ -646,6 +1354,9 @@ mod tests {
             name: Some("Updated".into()),
             email: None,
             age: None,
+            clear_age: false,
+            expected_version: None,
+            update_mask: None,
         });
 
         let result = service.update(request).await;
@@ -658,7 +1369,8 @@ mod tests {
     #[tokio::test]
     async fn test_grpc_service_delete() {
         let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
-        let mock = MockUserService::default().with_delete_user_result(Ok(user));
+        let mut mock = MockUserService::new();
+        mock.expect_delete_user().return_const(Ok(user));
         let service = create_test_service(mock);
 
         let request = Request::new(DeleteUserRequest { id: 1 });
@@ -671,7 +1383,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_grpc_service_delete_not_found_maps_to_status() {
-        let mock = MockUserService::default().with_delete_user_result(Err(Error::RepositoryError(RepositoryError::NotFound)));
+        let mut mock = MockUserService::new();
+        mock.expect_delete_user().return_const(Err(Error::RepositoryError(RepositoryError::NotFound)));
         let service = create_test_service(mock);
 
         let request = Request::new(DeleteUserRequest { id: 999 });
@@ -689,12 +1402,15 @@ mod tests {
             User::fake_with_age(1, "John Doe", "john@example.com", 30),
             User::fake_with_age(2, "Jane Doe", "jane@example.com", 25),
         ];
-        let mock = MockUserService::default().with_list_users_result(Ok(users));
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Ok(users));
         let service = create_test_service(mock);
 
         let request = Request::new(ListUsersRequest {
             start_id: None,
             page_size: None,
+            return_total: false,
+            active_since: None,
         });
 
         let response = service.list(request).await.unwrap();
@@ -706,32 +1422,42 @@ mod tests {
 
 /// Client-side API (returns core domain types)
 pub mod api {
-    use chrono::DateTime;
+    use chrono::{DateTime, Utc};
     use hex_play_core::{
         Error,
-        types::{Age, Email},
+        types::{Age, Email, RedactedEmail},
         user::{User, UserId, UserToken},
     };
+    use tokio_stream::{Stream, StreamExt};
+    use tonic::transport::Channel;
 
     use crate::{
         ApiError,
-        grpc::user_proto::{
-            CreateUserRequest, DeleteUserRequest, GetUserByTokenRequest, GetUserRequest, ListUsersRequest, UpdateUserRequest, User as ProtoUser,
-            user_service_client::UserServiceClient,
+        grpc::{
+            error::map_status,
+            user_proto::{
+                BatchGetUsersRequest, CreateUserRequest, DeleteUserRequest, ExportUsersRequest, GetUserByEmailRequest, GetUserByTokenRequest, GetUserRequest,
+                ListUsersRequest, UpdateUserRequest, UpsertUserRequest, User as ProtoUser, user_service_client::UserServiceClient,
+            },
         },
+        trace_context::traced_request,
     };
 
+    /// Converts a decoded list in place of `collect`, which cannot size the
+    /// output up front when the items are fallible.
+    fn from_proto_list(protos: Vec<ProtoUser>) -> Result<Vec<User>, Error> {
+        let mut users = Vec::with_capacity(protos.len());
+        for proto in protos {
+            users.push(from_proto(proto)?);
+        }
+        Ok(users)
+    }
+
     fn from_proto(proto: ProtoUser) -> Result<User, Error> {
-        let created_at = proto
-            .created_at
-            .and_then(|ts| DateTime::from_timestamp(ts.seconds, ts.nanos as u32))
-            .unwrap_or_default();
-        let updated_at = proto
-            .updated_at
-            .and_then(|ts| DateTime::from_timestamp(ts.seconds, ts.nanos as u32))
-            .unwrap_or_default();
+        let created_at = from_timestamp(proto.created_at, "created_at")?;
+        let updated_at = from_timestamp(proto.updated_at, "updated_at")?;
         Ok(User {
-            id: proto.id,
+            id: UserId::new(proto.id),
             version: proto.version,
             token: UserToken::parse(&proto.token).map_err(|e| Error::InvalidToken(e.to_string()))?,
             name: proto.name,
@@ -739,95 +1465,147 @@ pub mod api {
             age: Age::new(proto.age as i16)?,
             created_at,
             updated_at,
+            last_seen_at: proto
+                .last_seen_at
+                .and_then(|ts| DateTime::from_timestamp(ts.seconds, u32::try_from(ts.nanos).ok()?)),
         })
     }
 
-    #[tracing::instrument(level = "trace")]
-    pub async fn create(name: String, email: String, age: i16) -> Result<User, Error> {
-        let mut client = UserServiceClient::connect("http://localhost:3001")
-            .await
-            .map_err(|e| Error::from(ApiError::GrpcClient(e.to_string())))?;
-        let request = tonic::Request::new(CreateUserRequest { name, email, age: age as i32 });
-        let response = client
-            .create(request)
+    /// The server always sets both timestamps, so a missing or out-of-range
+    /// one means a malformed response rather than a user created in 1970.
+    fn from_timestamp(timestamp: Option<prost_types::Timestamp>, field: &str) -> Result<DateTime<Utc>, Error> {
+        timestamp
+            .and_then(|ts| DateTime::from_timestamp(ts.seconds, u32::try_from(ts.nanos).ok()?))
+            .ok_or_else(|| Error::from(ApiError::GrpcClient(format!("user without a valid {field}"))))
+    }
+
+    async fn connect(endpoint: &str) -> Result<UserServiceClient<Channel>, Error> {
+        UserServiceClient::connect(endpoint.to_string())
             .await
-            .map_err(|e| Error::from(ApiError::GrpcClient(e.to_string())))?
-            .into_inner();
+            .map_err(|e| Error::from(ApiError::GrpcClient(e.to_string())))
+    }
+
+    #[tracing::instrument(level = "trace", skip(email), fields(email = %RedactedEmail::new(&email)))]
+    pub async fn create(endpoint: &str, name: String, email: String, age: i16) -> Result<User, Error> {
+        let mut client = connect(endpoint).await?;
+        let request = traced_request(CreateUserRequest { name, email, age: age as i32 });
+        let response = client.create(request).await.map_err(map_status)?.into_inner();
         from_proto(response)
     }
 
-    #[tracing::instrument(level = "trace")]
-    pub async fn get(id: UserId) -> Result<User, Error> {
-        let mut client = UserServiceClient::connect("http://localhost:3001")
-            .await
-            .map_err(|e| Error::from(ApiError::GrpcClient(e.to_string())))?;
-        let request = tonic::Request::new(GetUserRequest { id });
-        let response = client
-            .get(request)
-            .await
-            .map_err(|e| Error::from(ApiError::GrpcClient(e.to_string())))?
-            .into_inner();
+    #[tracing::instrument(level = "trace", skip(email), fields(email = %RedactedEmail::new(&email)))]
+    pub async fn upsert(endpoint: &str, name: String, email: String, age: i16) -> Result<User, Error> {
+        let mut client = connect(endpoint).await?;
+        let request = traced_request(UpsertUserRequest { name, email, age: age as i32 });
+        let response = client.upsert(request).await.map_err(map_status)?.into_inner();
         from_proto(response)
     }
 
     #[tracing::instrument(level = "trace")]
-    pub async fn get_by_token(token: UserToken) -> Result<User, Error> {
-        let mut client = UserServiceClient::connect("http://localhost:3001")
-            .await
-            .map_err(|e| Error::from(ApiError::GrpcClient(e.to_string())))?;
-        let request = tonic::Request::new(GetUserByTokenRequest { token: token.to_string() });
-        let response = client
-            .get_by_token(request)
-            .await
-            .map_err(|e| Error::from(ApiError::GrpcClient(e.to_string())))?
-            .into_inner();
+    pub async fn get(endpoint: &str, id: UserId) -> Result<User, Error> {
+        let mut client = connect(endpoint).await?;
+        let request = traced_request(GetUserRequest { id: id.value() });
+        let response = client.get(request).await.map_err(map_status)?.into_inner();
         from_proto(response)
     }
 
     #[tracing::instrument(level = "trace")]
-    pub async fn update(id: UserId, name: Option<String>, email: Option<String>, age: Option<i16>) -> Result<User, Error> {
-        let mut client = UserServiceClient::connect("http://localhost:3001")
-            .await
-            .map_err(|e| Error::from(ApiError::GrpcClient(e.to_string())))?;
-        let request = tonic::Request::new(UpdateUserRequest {
-            id,
+    pub async fn get_by_token(endpoint: &str, token: UserToken) -> Result<User, Error> {
+        let mut client = connect(endpoint).await?;
+        let request = traced_request(GetUserByTokenRequest { token: token.to_string() });
+        let response = client.get_by_token(request).await.map_err(map_status)?.into_inner();
+        from_proto(response)
+    }
+
+    #[tracing::instrument(level = "trace", skip(email), fields(email = %RedactedEmail::new(&email)))]
+    pub async fn get_by_email(endpoint: &str, email: String) -> Result<User, Error> {
+        let mut client = connect(endpoint).await?;
+        let request = traced_request(GetUserByEmailRequest { email });
+        let response = client.get_by_email(request).await.map_err(map_status)?.into_inner();
+        from_proto(response)
+    }
+
+    #[tracing::instrument(level = "trace", skip(email), fields(email = email.as_deref().map(|email| tracing::field::display(RedactedEmail::new(email)))))]
+    pub async fn update(
+        endpoint: &str,
+        id: UserId,
+        name: Option<String>,
+        email: Option<String>,
+        age: Option<i16>,
+        expected_version: Option<u64>,
+    ) -> Result<User, Error> {
+        let mut client = connect(endpoint).await?;
+        let request = traced_request(UpdateUserRequest {
+            id: id.value(),
             name,
             email,
             age: age.map(|a| a as i32),
+            clear_age: false,
+            expected_version,
+            update_mask: None,
         });
-        let response = client
-            .update(request)
-            .await
-            .map_err(|e| Error::from(ApiError::GrpcClient(e.to_string())))?
-            .into_inner();
+        let response = client.update(request).await.map_err(map_status)?.into_inner();
         from_proto(response)
     }
 
     #[tracing::instrument(level = "trace")]
-    pub async fn delete(id: UserId) -> Result<User, Error> {
-        let mut client = UserServiceClient::connect("http://localhost:3001")
-            .await
-            .map_err(|e| Error::from(ApiError::GrpcClient(e.to_string())))?;
-        let request = tonic::Request::new(DeleteUserRequest { id });
-        let response = client
-            .delete(request)
-            .await
-            .map_err(|e| Error::from(ApiError::GrpcClient(e.to_string())))?
-            .into_inner();
+    pub async fn delete(endpoint: &str, id: UserId) -> Result<User, Error> {
+        let mut client = connect(endpoint).await?;
+        let request = traced_request(DeleteUserRequest { id: id.value() });
+        let response = client.delete(request).await.map_err(map_status)?.into_inner();
         from_proto(response)
     }
 
+    /// One page of a user listing.
+    #[derive(Debug)]
+    pub struct UserPage {
+        pub users: Vec<User>,
+        /// Pass as `start_id` to get the next page. `None` on the last page.
+        pub next_cursor: Option<UserId>,
+        /// Every user there is, when asked for with `return_total`.
+        pub total_size: Option<u64>,
+        /// The page size the server used.
+        pub page_size_applied: u64,
+    }
+
     #[tracing::instrument(level = "trace")]
-    pub async fn list(start_id: Option<UserId>, page_size: Option<u64>) -> Result<Vec<User>, Error> {
-        let mut client = UserServiceClient::connect("http://localhost:3001")
-            .await
-            .map_err(|e| Error::from(ApiError::GrpcClient(e.to_string())))?;
-        let request = tonic::Request::new(ListUsersRequest { start_id, page_size });
-        let response = client
-            .list(request)
-            .await
-            .map_err(|e| Error::from(ApiError::GrpcClient(e.to_string())))?
-            .into_inner();
-        response.users.into_iter().map(from_proto).collect()
+    pub async fn list(endpoint: &str, start_id: Option<UserId>, page_size: Option<u64>, return_total: bool) -> Result<UserPage, Error> {
+        let mut client = connect(endpoint).await?;
+        let request = traced_request(ListUsersRequest {
+            start_id: start_id.map(UserId::value),
+            page_size,
+            return_total,
+            active_since: None,
+        });
+        let response = client.list(request).await.map_err(map_status)?.into_inner();
+        Ok(UserPage {
+            users: from_proto_list(response.users)?,
+            next_cursor: response.next_cursor.map(UserId::new),
+            total_size: response.total_size,
+            page_size_applied: response.page_size_applied,
+        })
+    }
+
+    /// Streams every user from `start_id` onwards. Users are decoded as they
+    /// arrive, so callers can process exports larger than memory.
+    #[tracing::instrument(level = "trace")]
+    pub async fn export(endpoint: &str, start_id: Option<UserId>) -> Result<impl Stream<Item = Result<User, Error>>, Error> {
+        let mut client = connect(endpoint).await?;
+        let request = traced_request(ExportUsersRequest {
+            start_id: start_id.map(UserId::value),
+        });
+        let stream = client.export(request).await.map_err(map_status)?.into_inner();
+        Ok(stream.map(|proto| proto.map_err(map_status).and_then(from_proto)))
+    }
+
+    #[tracing::instrument(level = "trace")]
+    pub async fn batch_get(endpoint: &str, ids: Vec<UserId>, tokens: Vec<UserToken>) -> Result<Vec<User>, Error> {
+        let mut client = connect(endpoint).await?;
+        let request = traced_request(BatchGetUsersRequest {
+            ids: ids.into_iter().map(UserId::value).collect(),
+            tokens: tokens.iter().map(ToString::to_string).collect(),
+        });
+        let response = client.batch_get(request).await.map_err(map_status)?.into_inner();
+        from_proto_list(response.users)
     }
 }
@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use hex_play_core::CoreServices;
+use tonic::{Request, Response, Status};
+
+use crate::grpc::{
+    error::map_core_error,
+    request_context,
+    user_info_proto::{GetUserInfoRequest, UpdateUserInfoRequest, UserInfo as ProtoUserInfo, user_info_service_server::UserInfoService},
+};
+
+/// gRPC UserInfoService implementation
+pub(crate) struct GrpcUserInfoService {
+    core_services: Arc<CoreServices>,
+}
+
+impl GrpcUserInfoService {
+    pub(crate) fn new(core_services: Arc<CoreServices>) -> Self {
+        Self { core_services }
+    }
+}
+
+#[tonic::async_trait]
+impl UserInfoService for GrpcUserInfoService {
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get(&self, request: Request<GetUserInfoRequest>) -> Result<Response<ProtoUserInfo>, Status> {
+        let response = handler::get(&self.core_services, &request_context(&request), request.into_inner())
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(response))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn update(&self, request: Request<UpdateUserInfoRequest>) -> Result<Response<ProtoUserInfo>, Status> {
+        let response = handler::update(&self.core_services, &request_context(&request), request.into_inner())
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(response))
+    }
+}
+
+/// Server-side handlers (business logic)
+pub(crate) mod handler {
+    use hex_play_core::{
+        CoreServices, Error, RepositoryError,
+        context::RequestContext,
+        types::Age,
+        user::UserToken,
+        user_info::{UserInfo, UserInfoUpdate},
+    };
+
+    use crate::grpc::{
+        user::handler::to_timestamp,
+        user_info_proto::{GetUserInfoRequest, UpdateUserInfoRequest, UserInfo as ProtoUserInfo},
+    };
+
+    pub(crate) fn to_proto(info: UserInfo) -> ProtoUserInfo {
+        ProtoUserInfo {
+            token: info.token.to_string(),
+            age: info.age.value() as i32,
+            updated_at: Some(to_timestamp(info.updated_at)),
+        }
+    }
+
+    fn parse_token(token: &str) -> Result<UserToken, Error> {
+        UserToken::parse(token).map_err(|e| Error::InvalidToken(e.to_string()))
+    }
+
+    pub(crate) async fn get(core_services: &CoreServices, context: &RequestContext, request: GetUserInfoRequest) -> Result<ProtoUserInfo, Error> {
+        let info = core_services
+            .user_info_service
+            .get_info(context, parse_token(&request.token)?)
+            .await?
+            .ok_or(Error::RepositoryError(RepositoryError::NotFound))?;
+        Ok(to_proto(info))
+    }
+
+    pub(crate) async fn update(core_services: &CoreServices, context: &RequestContext, request: UpdateUserInfoRequest) -> Result<ProtoUserInfo, Error> {
+        if request.clear_age && request.age.is_some() {
+            return Err(Error::Validation("age and clear_age are mutually exclusive".into()));
+        }
+        let token = parse_token(&request.token)?;
+        let update = UserInfoUpdate {
+            age: if request.clear_age {
+                Some(None)
+            } else {
+                request.age.map(|a| Age::new(a as i16)).transpose()?.map(Some)
+            },
+        };
+
+        let info = core_services.user_info_service.update_info(context, token, update).await?;
+        Ok(to_proto(info))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use hex_play_core::{
+        CoreServices, Error, RepositoryError,
+        clock::FixedClock,
+        context::RequestContext,
+        test_support::{MockUserService, create_core_services_with_mock},
+        types::Age,
+        user::{UserId, UserToken},
+        user_info::{MockUserInfoService, UserInfo, UserInfoUpdate},
+    };
+    use mockall::predicate::{always, eq};
+    use tonic::{Code, Request};
+
+    use super::{GrpcUserInfoService, handler};
+    use crate::grpc::user_info_proto::{GetUserInfoRequest, UpdateUserInfoRequest, user_info_service_server::UserInfoService};
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn create_core_services(mock: MockUserInfoService) -> CoreServices {
+        CoreServices {
+            user_info_service: Arc::new(mock),
+            ..create_core_services_with_mock(MockUserService::new())
+        }
+    }
+
+    fn fake_info(id: u64, age: i16) -> UserInfo {
+        UserInfo {
+            token: UserToken::new(UserId::new(id)),
+            age: Age::new(age).unwrap(),
+            updated_at: FixedClock::epoch(),
+        }
+    }
+
+    // ===================
+    // Tests: handler::get
+    // ===================
+    #[tokio::test]
+    async fn test_handler_get_success() {
+        let mut mock = MockUserInfoService::new();
+        mock.expect_get_info()
+            .with(always(), eq(UserToken::new(UserId::new(1))))
+            .return_const(Ok(Some(fake_info(1, 30))));
+        let core_services = create_core_services(mock);
+
+        let request = GetUserInfoRequest {
+            token: UserToken::new(UserId::new(1)).to_string(),
+        };
+        let result = handler::get(&core_services, &RequestContext::internal(), request).await.unwrap();
+
+        assert_eq!(result.token, UserToken::new(UserId::new(1)).to_string());
+        assert_eq!(result.age, 30);
+        assert!(result.updated_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_handler_get_not_found() {
+        let mut mock = MockUserInfoService::new();
+        mock.expect_get_info().return_const(Ok(None));
+        let core_services = create_core_services(mock);
+
+        let request = GetUserInfoRequest {
+            token: UserToken::new(UserId::new(1)).to_string(),
+        };
+        let result = handler::get(&core_services, &RequestContext::internal(), request).await;
+
+        assert!(matches!(result.unwrap_err(), Error::RepositoryError(RepositoryError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_handler_get_invalid_token() {
+        let core_services = create_core_services(MockUserInfoService::new());
+
+        let request = GetUserInfoRequest { token: "not-a-token".into() };
+        let result = handler::get(&core_services, &RequestContext::internal(), request).await;
+
+        assert!(matches!(result.unwrap_err(), Error::InvalidToken(_)));
+    }
+
+    // ===================
+    // Tests: handler::update
+    // ===================
+    #[tokio::test]
+    async fn test_handler_update_sets_age() {
+        let mut mock = MockUserInfoService::new();
+        mock.expect_update_info()
+            .with(
+                always(),
+                eq(UserToken::new(UserId::new(1))),
+                eq(UserInfoUpdate {
+                    age: Some(Some(Age::new(31).unwrap())),
+                }),
+            )
+            .return_const(Ok(fake_info(1, 31)));
+        let core_services = create_core_services(mock);
+
+        let request = UpdateUserInfoRequest {
+            token: UserToken::new(UserId::new(1)).to_string(),
+            age: Some(31),
+            clear_age: false,
+        };
+        let result = handler::update(&core_services, &RequestContext::internal(), request).await.unwrap();
+
+        assert_eq!(result.age, 31);
+    }
+
+    #[tokio::test]
+    async fn test_handler_update_clears_age() {
+        let mut mock = MockUserInfoService::new();
+        mock.expect_update_info()
+            .with(always(), always(), eq(UserInfoUpdate { age: Some(None) }))
+            .return_const(Ok(fake_info(1, 0)));
+        let core_services = create_core_services(mock);
+
+        let request = UpdateUserInfoRequest {
+            token: UserToken::new(UserId::new(1)).to_string(),
+            age: None,
+            clear_age: true,
+        };
+        let result = handler::update(&core_services, &RequestContext::internal(), request).await.unwrap();
+
+        assert_eq!(result.age, 0);
+    }
+
+    #[tokio::test]
+    async fn test_handler_update_age_and_clear_age_conflict() {
+        let core_services = create_core_services(MockUserInfoService::new());
+
+        let request = UpdateUserInfoRequest {
+            token: UserToken::new(UserId::new(1)).to_string(),
+            age: Some(31),
+            clear_age: true,
+        };
+        let result = handler::update(&core_services, &RequestContext::internal(), request).await;
+
+        assert!(matches!(result.unwrap_err(), Error::Validation(_)));
+    }
+
+    // ===================
+    // Tests: GrpcUserInfoService
+    // ===================
+    #[tokio::test]
+    async fn test_grpc_service_get_not_found_maps_to_status() {
+        let mut mock = MockUserInfoService::new();
+        mock.expect_get_info().return_const(Ok(None));
+        let service = GrpcUserInfoService::new(Arc::new(create_core_services(mock)));
+
+        let request = Request::new(GetUserInfoRequest {
+            token: UserToken::new(UserId::new(1)).to_string(),
+        });
+        let status = service.get(request).await.unwrap_err();
+
+        assert_eq!(status.code(), Code::NotFound);
+    }
+}
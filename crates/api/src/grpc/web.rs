@@ -0,0 +1,135 @@
+//! gRPC-Web, so browsers can call the gRPC services without a translating
+//! proxy such as Envoy.
+
+use std::time::Duration;
+
+use axum::http::{
+    HeaderMap, HeaderName, HeaderValue, Method,
+    header::{ACCESS_CONTROL_REQUEST_HEADERS, AUTHORIZATION, CONTENT_TYPE},
+};
+use tonic_web::GrpcWebLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+const GRPC_WEB_HEADER: &str = "x-grpc-web";
+
+/// Translates gRPC-Web calls for the services of `router` and answers the
+/// browser's CORS preflight requests for them.
+pub(crate) fn layer(router: axum::Router, allowed_origins: &[String]) -> axum::Router {
+    router.layer(GrpcWebLayer::new()).layer(cors(allowed_origins))
+}
+
+/// Whether a request is a CORS preflight for a gRPC-Web call. Preflights
+/// carry no content type, so single-port mode needs this to route them.
+pub(crate) fn is_preflight(method: &Method, headers: &HeaderMap) -> bool {
+    method == Method::OPTIONS
+        && headers
+            .get(ACCESS_CONTROL_REQUEST_HEADERS)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|requested| requested.split(',').any(|header| header.trim().eq_ignore_ascii_case(GRPC_WEB_HEADER)))
+}
+
+fn cors(allowed_origins: &[String]) -> CorsLayer {
+    let allow_origin = if allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(allowed_origins.iter().filter_map(|origin| HeaderValue::from_str(origin).ok()))
+    };
+
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::POST])
+        .allow_headers([
+            CONTENT_TYPE,
+            HeaderName::from_static(GRPC_WEB_HEADER),
+            HeaderName::from_static("x-user-agent"),
+            HeaderName::from_static("grpc-timeout"),
+            HeaderName::from_static("x-request-id"),
+            HeaderName::from_static("x-tenant-id"),
+            HeaderName::from_static("x-read-primary"),
+            AUTHORIZATION,
+        ])
+        .expose_headers([
+            HeaderName::from_static("grpc-status"),
+            HeaderName::from_static("grpc-message"),
+            HeaderName::from_static("grpc-status-details-bin"),
+            HeaderName::from_static("x-request-id"),
+        ])
+        .max_age(Duration::from_secs(24 * 60 * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        body::Body,
+        http::{Method, Request, StatusCode},
+    };
+    use hex_play_core::{
+        CoreServices,
+        test_support::{MockUserService, create_core_services_with_mock, mock_status_service_up},
+    };
+    use prost::Message;
+    use tower::ServiceExt;
+
+    use crate::{
+        auth::ApiKeys,
+        grpc::{GrpcConfig, routes, system_proto::StatusRequest},
+    };
+
+    fn app() -> axum::Router {
+        let config = GrpcConfig {
+            grpc_web: true,
+            cors_allowed_origins: vec!["https://app.example.com".to_string()],
+            ..GrpcConfig::default()
+        };
+        let core_services = CoreServices {
+            status_service: Arc::new(mock_status_service_up()),
+            ..create_core_services_with_mock(MockUserService::new())
+        };
+        routes(Arc::new(core_services), &config, &ApiKeys::default()).into_axum_router()
+    }
+
+    // ===================
+    // Tests: layer
+    // ===================
+
+    #[tokio::test]
+    async fn test_answers_grpc_web_call() {
+        let message = StatusRequest {}.encode_to_vec();
+        let mut frame = vec![0];
+        frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&message);
+        let request = Request::post("/hexplay.system.v1.SystemService/Status")
+            .header("content-type", "application/grpc-web+proto")
+            .header("x-grpc-web", "1")
+            .body(Body::from(frame))
+            .unwrap();
+
+        let response = app().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "application/grpc-web+proto");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.windows(b"reachable".len()).any(|window| window == b"reachable"));
+        assert!(body.windows(b"grpc-status:0".len()).any(|window| window == b"grpc-status:0"));
+    }
+
+    #[tokio::test]
+    async fn test_allows_preflight_from_configured_origin() {
+        let request = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/hexplay.system.v1.SystemService/Status")
+            .header("origin", "https://app.example.com")
+            .header("access-control-request-method", "POST")
+            .header("access-control-request-headers", "content-type,x-grpc-web")
+            .body(Body::empty())
+            .unwrap();
+
+        assert!(super::is_preflight(request.method(), request.headers()));
+        let response = app().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["access-control-allow-origin"], "https://app.example.com");
+    }
+}
@@ -1,84 +1,733 @@
-use std::sync::Arc;
+use std::{any::Any, collections::HashMap, sync::Arc, time::Duration};
 
 use axum::{
     Router,
-    http::{HeaderName, Request},
-    response::Html,
+    body::Body,
+    http::{HeaderName, Request, StatusCode, header::CONTENT_TYPE},
+    middleware::{Next, from_fn, from_fn_with_state},
+    response::{Html, IntoResponse, Response},
     routing::get,
+    serve::Listener,
 };
-use hex_play_core::{CoreServices, Error};
+use hex_play_core::{CoreServices, Error, reporting::ErrorReporter, session::Impersonation};
+use hex_play_utils::secret::Secret;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo, TokioTimer},
+    server::{conn::auto, graceful::GracefulShutdown},
+    service::TowerToHyperService,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
 use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
-use tower::ServiceBuilder;
+use tower::{ServiceBuilder, ServiceExt};
 use tower_http::{
+    catch_panic::CatchPanicLayer,
     request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
     trace::TraceLayer,
 };
 
-use crate::error::ApiError;
+use crate::{
+    access_log::{self, Caller},
+    auth::{self, ApiKeys, Principal},
+    bind::Bind,
+    context::{self, REQUEST_ID_HEADER},
+    error::{ApiError, panic_message},
+    grpc,
+    http::problem::Problem,
+    reporting::{Failure, report_failures},
+    request_metrics,
+    slo::{self, SloConfig, SloTracker},
+    trace_context,
+    unix_socket::{self, UnixSocketConfig},
+};
 
-mod error;
+mod admin;
+mod duplicates;
+pub(crate) mod error;
+mod impersonation;
+mod import;
+mod limit;
+mod negotiate;
+pub(crate) mod problem;
+mod scim;
+mod settings;
+mod stats;
+mod system;
 mod user;
+mod user_info;
+mod webhook;
 
-const REQUEST_ID_HEADER: &str = "x-request-id";
+pub use limit::ConcurrencyLimits;
+pub(crate) use limit::RouteLimits;
+#[cfg(feature = "test-support")]
+pub use negotiate::ResponseFormat;
+#[cfg(feature = "test-support")]
+pub(crate) use user::encode_list_users;
 
-pub(crate) struct HttpSubsystem {
+/// Address the HTTP server listens on unless others are configured.
+pub const LISTEN_ADDR: &str = "0.0.0.0:3000";
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct HttpConfig {
+    /// (optional) Addresses serving the API, comma separated, e.g.
+    /// `HPLAY__HTTP__LISTEN_ADDRS=[::1]:3000,0.0.0.0:3000`. Defaults to
+    /// [`LISTEN_ADDR`].
+    #[serde(default)]
+    #[schemars(extend("default" = [LISTEN_ADDR]))]
+    pub listen_addrs: Vec<String>,
+
+    /// (optional) Addresses serving only the admin routes, e.g. a loopback
+    /// `HPLAY__HTTP__ADMIN_LISTEN_ADDRS=127.0.0.1:3002`.
+    #[serde(default)]
+    pub admin_listen_addrs: Vec<String>,
+
+    /// (optional) Requests served at once per route group, e.g.
+    /// `HPLAY__HTTP__CONCURRENCY_LIMITS__WRITES=8`. Further requests get 503.
+    #[serde(default)]
+    pub concurrency_limits: ConcurrencyLimits,
+
+    /// (optional) Keep HTTP/1 connections open between requests. Defaults
+    /// to true.
+    #[serde(default)]
+    #[schemars(extend("default" = true))]
+    pub keep_alive: Option<bool>,
+
+    /// (optional) Seconds a client may take to send the request headers on
+    /// HTTP/1 before the connection is closed. Defaults to 30.
+    #[serde(default)]
+    #[schemars(extend("default" = DEFAULT_HEADER_READ_TIMEOUT_SECS))]
+    pub header_read_timeout_secs: Option<u64>,
+
+    /// (optional) Streams a client may open at once on one HTTP/2
+    /// connection. Defaults to 200.
+    #[serde(default)]
+    #[schemars(extend("default" = DEFAULT_MAX_CONCURRENT_STREAMS))]
+    pub max_concurrent_streams: Option<u32>,
+
+    /// (optional) Seconds between HTTP/2 keep-alive pings. Unset sends none.
+    #[serde(default)]
+    pub http2_keep_alive_interval_secs: Option<u64>,
+
+    /// (optional) Seconds to wait for a ping acknowledgement before closing
+    /// the HTTP/2 connection. Defaults to 20.
+    #[serde(default)]
+    #[schemars(extend("default" = DEFAULT_KEEP_ALIVE_TIMEOUT_SECS))]
+    pub http2_keep_alive_timeout_secs: Option<u64>,
+
+    /// (optional) Also serve on this Unix socket, e.g.
+    /// `HPLAY__HTTP__UNIX_SOCKET__PATH=/run/hex-play/http.sock`.
+    #[serde(default)]
+    pub unix_socket: Option<UnixSocketConfig>,
+
+    /// (optional) Bearer token SCIM clients authenticate with, e.g.
+    /// `HPLAY__HTTP__SCIM_TOKEN_FILE=/run/secrets/scim`. The SCIM routes are
+    /// only served when it is set.
+    #[serde(default)]
+    pub scim_token: Option<Secret>,
+
+    /// (optional) Secret an identity provider signs user webhooks with, e.g.
+    /// `HPLAY__HTTP__WEBHOOK_SECRET_FILE=/run/secrets/webhook`. The webhook
+    /// route is only served when it is set.
+    #[serde(default)]
+    pub webhook_secret: Option<Secret>,
+
+    /// (optional) Service level objectives by name, e.g.
+    /// `HPLAY__HTTP__SLOS__GET_USER__ROUTE=/api/v1/user/{id}` with
+    /// `HPLAY__HTTP__SLOS__GET_USER__AVAILABILITY=0.999`. Their error
+    /// budgets are reported at `GET /admin/v1/slo`.
+    #[serde(default)]
+    pub slos: HashMap<String, SloConfig>,
+
+    /// (optional) Delays, failures and dropped connections to inject into
+    /// every request but the admin routes, e.g.
+    /// `HPLAY__HTTP__FAULTS__ERROR_PROBABILITY=0.05`. Defaults to none.
+    #[cfg(feature = "fault-injection")]
+    #[serde(default)]
+    pub faults: crate::FaultInjectionConfig,
+}
+
+impl HttpConfig {
+    /// The configured API addresses, or [`LISTEN_ADDR`] if none are.
+    pub fn listen_addrs(&self) -> Vec<&str> {
+        if self.listen_addrs.is_empty() {
+            vec![LISTEN_ADDR]
+        } else {
+            self.listen_addrs.iter().map(String::as_str).collect()
+        }
+    }
+
+    /// Connection builder serving HTTP/1 and HTTP/2 (prior knowledge or
+    /// upgrade-free h2c) with the configured keep-alive and timeouts.
+    fn connection_builder(&self) -> auto::Builder<TokioExecutor> {
+        let mut builder = auto::Builder::new(TokioExecutor::new());
+        builder
+            .http1()
+            .timer(TokioTimer::new())
+            .keep_alive(self.keep_alive.unwrap_or(true))
+            .header_read_timeout(Duration::from_secs(self.header_read_timeout_secs.unwrap_or(DEFAULT_HEADER_READ_TIMEOUT_SECS)));
+        builder
+            .http2()
+            .timer(TokioTimer::new())
+            .max_concurrent_streams(self.max_concurrent_streams.unwrap_or(DEFAULT_MAX_CONCURRENT_STREAMS))
+            .keep_alive_interval(self.http2_keep_alive_interval_secs.map(Duration::from_secs))
+            .keep_alive_timeout(Duration::from_secs(
+                self.http2_keep_alive_timeout_secs.unwrap_or(DEFAULT_KEEP_ALIVE_TIMEOUT_SECS),
+            ));
+        builder
+    }
+}
+
+const DEFAULT_HEADER_READ_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_MAX_CONCURRENT_STREAMS: u32 = 200;
+const DEFAULT_KEEP_ALIVE_TIMEOUT_SECS: u64 = 20;
+
+/// Builds the full API: user, user info, stats, import, system, webhook, SCIM
+/// and admin routes and the request ID and tracing middleware. Requests are
+/// authenticated with `api_keys`. The routes are held to `limits`, which the
+/// admin routes may change, and their requests counted against `slos`.
+pub(crate) fn app(
+    core_services: Arc<CoreServices>,
+    config: &HttpConfig,
+    api_keys: &ApiKeys,
+    limits: &RouteLimits,
+    slos: &SloTracker,
+    config_schema: Option<Arc<Value>>,
+) -> Router {
+    let user_routes = user::get_routes(core_services.clone(), limits);
+    let user_info_routes = user_info::get_routes(core_services.clone(), limits);
+    let stats_routes = stats::get_routes(core_services.clone(), limits);
+    let import_routes = import::get_routes(core_services.clone(), limits);
+    let system_routes = system::get_routes(core_services.clone(), limits);
+    let webhook_routes = match config.webhook_secret.clone().filter(|secret| !secret.is_empty()) {
+        Some(secret) => webhook::get_routes(core_services.clone(), secret, limits),
+        None => Router::new(),
+    };
+    let scim_routes = match config.scim_token.clone().filter(|token| !token.is_empty()) {
+        Some(token) => scim::get_routes(core_services.clone(), token, limits),
+        None => Router::new(),
+    };
+    let admin_routes = admin::get_routes(core_services.clone(), limits, slos, config_schema);
+    let error_reporter = core_services.error_reporter.clone();
+    let api_routes = Router::new()
+        .route("/", get(hello_handler))
+        .merge(user_routes)
+        .merge(user_info_routes)
+        .merge(stats_routes)
+        .merge(import_routes)
+        .merge(system_routes)
+        .merge(webhook_routes)
+        .merge(scim_routes);
+    // Operators keep the admin routes to turn the faults off again.
+    #[cfg(feature = "fault-injection")]
+    let api_routes = crate::faults::inject_http(api_routes, &config.faults);
+    with_middleware(
+        api_routes
+            .merge(admin_routes)
+            .layer(from_fn_with_state(api_keys.clone(), auth::authenticate_http))
+            .layer(from_fn_with_state(core_services, impersonation::resolve_impersonation)),
+        error_reporter,
+        slos,
+    )
+}
+
+/// Builds an app serving only the admin routes, for listeners that should
+/// not expose the user API. `api_keys`, `limits` and `slos` are those of
+/// the full API, for the admin routes to change and report.
+pub(crate) fn admin_app(
     core_services: Arc<CoreServices>,
+    api_keys: &ApiKeys,
+    limits: &RouteLimits,
+    slos: &SloTracker,
+    config_schema: Option<Arc<Value>>,
+) -> Router {
+    let error_reporter = core_services.error_reporter.clone();
+    let admin_routes = admin::get_routes(core_services, limits, slos, config_schema).layer(from_fn_with_state(api_keys.clone(), auth::authenticate_http));
+    with_middleware(admin_routes, error_reporter, slos)
+}
+
+/// Sends gRPC and gRPC-Web requests, recognised by their `application/grpc`
+/// content type, to `grpc` and everything else to `http`.
+pub(crate) fn multiplex(http: Router, grpc: Router) -> Router {
+    Router::new().fallback_service(tower::service_fn(move |request: Request<Body>| {
+        let service = if is_grpc(&request) { grpc.clone() } else { http.clone() };
+        service.oneshot(request)
+    }))
+}
+
+fn is_grpc(request: &Request<Body>) -> bool {
+    grpc::web::is_preflight(request.method(), request.headers())
+        || request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("application/grpc"))
+}
+
+fn with_middleware(router: Router, error_reporter: Arc<dyn ErrorReporter>, slos: &SloTracker) -> Router {
+    let x_request_id = HeaderName::from_static(REQUEST_ID_HEADER);
+
+    let middleware = ServiceBuilder::new()
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+        .layer(TraceLayer::new_for_http().make_span_with(|request: &Request<_>| {
+            let request_id = request
+                .headers()
+                .get(REQUEST_ID_HEADER)
+                .map(|v| v.to_str().unwrap_or_default())
+                .unwrap_or_default();
+
+            let span = tracing::info_span!(
+                "http",
+                request_id = ?request_id,
+                impersonator_id = tracing::field::Empty,
+                trace_id = tracing::field::Empty,
+            );
+            trace_context::continue_trace(&span, request.headers());
+            span
+        }))
+        .layer(from_fn(request_metrics::record_http))
+        .layer(from_fn_with_state(slos.clone(), slo::track_http))
+        .layer(from_fn(access_log::log_http))
+        .layer(PropagateRequestIdLayer::new(x_request_id))
+        .layer(from_fn_with_state(error_reporter, report_failures))
+        .layer(CatchPanicLayer::custom(panicked));
+
+    router.layer(middleware)
+}
+
+/// Turns a panicking handler into a 500, logged inside the request span so
+/// the request id is kept, instead of a dropped connection.
+fn panicked(panic: Box<dyn Any + Send>) -> Response {
+    let message = panic_message(&*panic);
+    tracing::error!(panic = message, "Handler panicked");
+    let mut response = Problem::new(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error").into_response();
+    Failure::mark_panic(&mut response, message);
+    response
+}
+
+/// Serves an app on one listener.
+pub(crate) struct HttpSubsystem {
+    app: Router,
+    builder: auto::Builder<TokioExecutor>,
+    bind: Bind,
 }
 
 impl HttpSubsystem {
-    pub(crate) fn new(core_services: Arc<CoreServices>) -> Self {
-        Self { core_services }
+    pub(crate) fn new(app: Router, config: &HttpConfig, bind: Bind) -> Self {
+        Self {
+            app,
+            builder: config.connection_builder(),
+            bind,
+        }
     }
 }
 
 impl IntoSubsystem<Error> for HttpSubsystem {
     async fn run(self, subsys: &mut SubsystemHandle) -> Result<(), Error> {
-        let x_request_id = HeaderName::from_static(REQUEST_ID_HEADER);
-
-        let middleware = ServiceBuilder::new()
-            .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
-            .layer(TraceLayer::new_for_http().make_span_with(|request: &Request<_>| {
-                let request_id = request
-                    .headers()
-                    .get(REQUEST_ID_HEADER)
-                    .map(|v| v.to_str().unwrap_or_default())
-                    .unwrap_or_default();
-
-                tracing::info_span!(
-                    "http",
-                    request_id = ?request_id,
-                )
-            }))
-            .layer(PropagateRequestIdLayer::new(x_request_id));
-
-        let user_routes = user::get_routes(self.core_services.clone());
-        let app = Router::new().route("/", get(hello_handler)).merge(user_routes).layer(middleware);
-
-        let listener = tokio::net::TcpListener::bind("0.0.0.0:3000")
-            .await
-            .map_err(|e| Error::from(ApiError::Network(e.to_string())))?;
-        tracing::info!(
-            "listening on {}",
-            listener.local_addr().map_err(|e| Error::from(ApiError::Network(e.to_string())))?
-        );
-
-        tokio::select! {
-            _ = subsys.on_shutdown_requested() => {
-                tracing::info!("HttpSubsystem shutting down...");
+        match &self.bind {
+            Bind::Tcp(addr) => {
+                let mut listener = tokio::net::TcpListener::bind(addr)
+                    .await
+                    .map_err(|e| Error::from(ApiError::Network(format!("{}: {}", addr, e))))?;
+                tracing::info!(
+                    "listening on {}",
+                    listener.local_addr().map_err(|e| Error::from(ApiError::Network(e.to_string())))?
+                );
+                serve(&mut listener, self.app, &self.builder, subsys).await;
             }
-            result = axum::serve(listener, app) => {
-                if let Err(e) = result {
-                    tracing::error!("HTTP server error: {}", e);
-                }
-                subsys.request_shutdown();
+            Bind::Unix(config) => {
+                let (mut listener, _socket_file) = unix_socket::bind(config)?;
+                tracing::info!("listening on {}", self.bind);
+                serve(&mut listener, self.app, &self.builder, subsys).await;
             }
         }
+        tracing::info!("HttpSubsystem shut down");
 
         Ok(())
     }
 }
 
+/// Accepts connections until shutdown is requested, then waits for the
+/// requests in flight to finish.
+async fn serve<L: Listener>(listener: &mut L, app: Router, builder: &auto::Builder<TokioExecutor>, subsys: &SubsystemHandle) {
+    let graceful = GracefulShutdown::new();
+
+    loop {
+        tokio::select! {
+            _ = subsys.on_shutdown_requested() => break,
+            (stream, _) = listener.accept() => {
+                let connection = builder
+                    .serve_connection_with_upgrades(TokioIo::new(stream), TowerToHyperService::new(app.clone()))
+                    .into_owned();
+                let connection = graceful.watch(connection);
+                tokio::spawn(async move {
+                    if let Err(e) = connection.await {
+                        tracing::debug!("HTTP connection error: {}", e);
+                    }
+                });
+            }
+        }
+    }
+
+    graceful.shutdown().await;
+}
+
+/// Builds the [`RequestContext`](hex_play_core::context::RequestContext)
+/// passed to use cases from the request headers and the API key or
+/// impersonation a request is authenticated with, if any. Handlers take it as
+/// `Extension<RequestContext>`. Problem responses are translated into the
+/// negotiated language on the way out.
+async fn request_context(mut request: Request<Body>, next: Next) -> Response {
+    let mut context = context::from_headers(request.headers());
+    if let Some(principal) = request.extensions().get::<Principal>() {
+        principal.apply_to(&mut context);
+    }
+    if let Some(impersonation) = request.extensions().get::<Impersonation>() {
+        context.actor = Some(impersonation.user_id.to_string());
+        context.impersonator = Some(impersonation.impersonator_id.to_string());
+        context.tenant = impersonation.tenant.clone();
+    }
+    let language = context.language();
+    let actor = context.actor.clone();
+    request.extensions_mut().insert(context);
+    let mut response = problem::localize(next.run(request).await, language);
+    Failure::attribute(&mut response, actor.as_deref());
+    response.extensions_mut().insert(Caller { user_id: actor });
+    response
+}
+
 async fn hello_handler() -> Html<&'static str> {
     tracing::info!("Hello world!");
     Html("<h1>Hello, World!</h1>")
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, StatusCode},
+        routing::get,
+    };
+    use hex_play_core::{
+        CoreServices, Error as CoreError,
+        status::Health,
+        test_support::{MockErrorReporter, MockUserService, create_arc_core_services_with_mock, create_core_services_with_mock, mock_status_service_up},
+        user::User,
+    };
+    use hex_play_utils::secret::Secret;
+    use hyper_util::{
+        rt::{TokioExecutor, TokioIo},
+        service::TowerToHyperService,
+    };
+    use tokio::net::TcpListener;
+    use tower::ServiceExt;
+
+    use super::{HttpConfig, RouteLimits, admin_app, error::Error, multiplex, with_middleware};
+    use crate::{
+        ApiKeyConfig,
+        auth::ApiKeys,
+        grpc::{self, GrpcConfig, system},
+        slo::SloTracker,
+    };
+
+    // ===================
+    // Tests: HttpConfig
+    // ===================
+
+    #[tokio::test]
+    async fn test_serves_http2_prior_knowledge() {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let app = Router::new().route("/", get(|| async { "hello" }));
+        tokio::spawn(async move {
+            HttpConfig::default()
+                .connection_builder()
+                .serve_connection_with_upgrades(TokioIo::new(server_io), TowerToHyperService::new(app))
+                .await
+        });
+
+        let (mut sender, connection) = hyper::client::conn::http2::handshake(TokioExecutor::new(), TokioIo::new(client_io))
+            .await
+            .unwrap();
+        tokio::spawn(connection);
+        let response = sender
+            .send_request(Request::get("http://localhost/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.version(), axum::http::Version::HTTP_2);
+        let body = axum::body::to_bytes(Body::new(response.into_body()), usize::MAX).await.unwrap();
+        assert_eq!(body, "hello");
+    }
+
+    // ===================
+    // Tests: with_middleware
+    // ===================
+
+    async fn panicking_handler() -> &'static str {
+        panic!("boom")
+    }
+
+    async fn failing_handler() -> Result<(), Error> {
+        Err(Error::Core(CoreError::Infrastructure("disk full".into())))
+    }
+
+    async fn missing_handler() -> Result<(), Error> {
+        Err(Error::NotFound)
+    }
+
+    #[tokio::test]
+    async fn test_panicking_handler_returns_500_with_request_id() {
+        let mut reporter = MockErrorReporter::new();
+        reporter
+            .expect_report()
+            .withf(|report| report.panic && report.message == "boom" && report.request_id.as_deref() == Some("req-1"))
+            .times(1)
+            .return_const(());
+        let app = with_middleware(Router::new().route("/", get(panicking_handler)), Arc::new(reporter), &SloTracker::default());
+
+        let response = app
+            .oneshot(Request::get("/").header("x-request-id", "req-1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.headers()["x-request-id"], "req-1");
+        assert_eq!(response.headers()["content-type"], "application/problem+json");
+    }
+
+    #[tokio::test]
+    async fn test_internal_errors_are_reported() {
+        let mut reporter = MockErrorReporter::new();
+        reporter
+            .expect_report()
+            .withf(|report| !report.panic && report.message == "Infrastructure error: disk full")
+            .times(1)
+            .return_const(());
+        let app = with_middleware(Router::new().route("/", get(failing_handler)), Arc::new(reporter), &SloTracker::default());
+
+        let response = app.oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_not_reported() {
+        let mut reporter = MockErrorReporter::new();
+        reporter.expect_report().never();
+        let app = with_middleware(Router::new().route("/", get(missing_handler)), Arc::new(reporter), &SloTracker::default());
+
+        let response = app.oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    // ===================
+    // Tests: app
+    // ===================
+
+    #[tokio::test]
+    async fn test_request_naming_another_tenant_is_forbidden() {
+        let api_keys = ApiKeys::new(&HashMap::from([(
+            "acme".to_string(),
+            ApiKeyConfig {
+                key: Secret::new("acme-key"),
+                user_id: 1,
+                tenant: Some("acme".to_string()),
+                admin: false,
+            },
+        )]));
+        // No user use case may run for either request.
+        let app = super::app(
+            create_arc_core_services_with_mock(MockUserService::new()),
+            &HttpConfig::default(),
+            &api_keys,
+            &RouteLimits::default(),
+            &SloTracker::default(),
+            None,
+        );
+        let list_users = |authorization: Option<&str>| {
+            let builder = Request::get("/api/v1/user").header("x-tenant-id", "globex");
+            let builder = match authorization {
+                Some(authorization) => builder.header("authorization", authorization),
+                None => builder,
+            };
+            builder.body(Body::empty()).unwrap()
+        };
+
+        let other_tenant = app.clone().oneshot(list_users(Some("Bearer acme-key"))).await.unwrap();
+        let anonymous = app.oneshot(list_users(None)).await.unwrap();
+
+        assert_eq!(other_tenant.status(), StatusCode::FORBIDDEN);
+        assert_eq!(anonymous.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_requests_carry_what_pins_their_reads_to_the_primary() {
+        let api_keys = ApiKeys::new(&HashMap::from([(
+            "app".to_string(),
+            ApiKeyConfig {
+                key: Secret::new("app-key"),
+                user_id: 2,
+                tenant: None,
+                admin: false,
+            },
+        )]));
+        let mut mock = MockUserService::new();
+        // The write pins the reads of its actor, which the next read by the
+        // same key acts as.
+        mock.expect_upsert_by_email()
+            .withf(|context, _| context.actor.as_deref() == Some("2"))
+            .times(1)
+            .returning(|_, user| Ok(User::fake(1, user.name, user.email.as_str())));
+        mock.expect_find_by_id()
+            .withf(|context, _| context.actor.as_deref() == Some("2") && !context.requires_primary)
+            .times(1)
+            .returning(|_, _| Ok(Some(User::fake(1, "John Doe", "john@example.com"))));
+        // Anonymous reads can't be pinned, so they ask for the primary.
+        mock.expect_find_by_id()
+            .withf(|context, _| context.actor.is_none() && context.requires_primary)
+            .times(1)
+            .returning(|_, _| Ok(Some(User::fake(1, "John Doe", "john@example.com"))));
+        let app = super::app(
+            create_arc_core_services_with_mock(mock),
+            &HttpConfig::default(),
+            &api_keys,
+            &RouteLimits::default(),
+            &SloTracker::default(),
+            None,
+        );
+
+        let write = Request::put("/api/v1/user/by-email/john@example.com")
+            .header("authorization", "Bearer app-key")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"name":"John Doe","age":30}"#))
+            .unwrap();
+        let read = Request::get("/api/v1/user/1")
+            .header("authorization", "Bearer app-key")
+            .body(Body::empty())
+            .unwrap();
+        let anonymous_read = Request::get("/api/v1/user/1").header("x-read-primary", "true").body(Body::empty()).unwrap();
+
+        assert!(app.clone().oneshot(write).await.unwrap().status().is_success());
+        assert_eq!(app.clone().oneshot(read).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(app.oneshot(anonymous_read).await.unwrap().status(), StatusCode::OK);
+    }
+
+    // ===================
+    // Tests: admin_app
+    // ===================
+
+    #[tokio::test]
+    async fn test_admin_app_serves_only_admin_routes() {
+        let app = admin_app(
+            create_arc_core_services_with_mock(MockUserService::new()),
+            &create_admin_and_user_keys(),
+            &RouteLimits::default(),
+            &SloTracker::default(),
+            None,
+        );
+        let get = |uri: &str, key: &str| Request::get(uri).header("authorization", format!("Bearer {key}")).body(Body::empty()).unwrap();
+
+        let admin = app.clone().oneshot(get("/api/admin/maintenance", "ops-key")).await.unwrap();
+        let users = app.oneshot(get("/api/v1/users", "ops-key")).await.unwrap();
+
+        assert_eq!(admin.status(), StatusCode::OK);
+        assert_eq!(users.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_take_an_admins_key() {
+        let core_services = create_arc_core_services_with_mock(MockUserService::new());
+        let api_keys = create_admin_and_user_keys();
+        let full = super::app(
+            core_services.clone(),
+            &HttpConfig::default(),
+            &api_keys,
+            &RouteLimits::default(),
+            &SloTracker::default(),
+            None,
+        );
+        let admin_only = admin_app(core_services, &api_keys, &RouteLimits::default(), &SloTracker::default(), None);
+        let get = |authorization: Option<&str>| {
+            let builder = Request::get("/api/admin/maintenance");
+            let builder = match authorization {
+                Some(authorization) => builder.header("authorization", authorization),
+                None => builder,
+            };
+            builder.body(Body::empty()).unwrap()
+        };
+
+        for app in [full, admin_only] {
+            let anonymous = app.clone().oneshot(get(None)).await.unwrap();
+            let user = app.clone().oneshot(get(Some("Bearer app-key"))).await.unwrap();
+            let admin = app.oneshot(get(Some("Bearer ops-key"))).await.unwrap();
+
+            assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(user.status(), StatusCode::FORBIDDEN);
+            assert_eq!(admin.status(), StatusCode::OK);
+        }
+    }
+
+    // ===================
+    // Tests: multiplex
+    // ===================
+
+    #[tokio::test]
+    async fn test_multiplex_serves_grpc_and_http_on_one_port() {
+        let core_services = Arc::new(CoreServices {
+            status_service: Arc::new(mock_status_service_up()),
+            ..create_core_services_with_mock(MockUserService::new())
+        });
+        let app = multiplex(
+            super::app(
+                core_services.clone(),
+                &HttpConfig::default(),
+                &ApiKeys::default(),
+                &RouteLimits::default(),
+                &SloTracker::default(),
+                None,
+            ),
+            grpc::routes(core_services, &GrpcConfig::default(), &ApiKeys::default()).into_axum_router(),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let builder = HttpConfig::default().connection_builder();
+        let server_app = app.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let connection = builder
+                    .serve_connection_with_upgrades(TokioIo::new(stream), TowerToHyperService::new(server_app.clone()))
+                    .into_owned();
+                tokio::spawn(connection);
+            }
+        });
+
+        let status = system::api::status(&endpoint).await.unwrap();
+        let hello = app.oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+
+        assert_eq!(status.health(), Health::Up);
+        assert_eq!(hello.status(), StatusCode::OK);
+    }
+
+    // ===================
+    // Test Helpers
+    // ===================
+
+    /// `ops-key` for admin 1 and `app-key` for user 2, neither of a tenant.
+    fn create_admin_and_user_keys() -> ApiKeys {
+        let key = |key: &str, user_id, admin| ApiKeyConfig {
+            key: Secret::new(key),
+            user_id,
+            tenant: None,
+            admin,
+        };
+        ApiKeys::new(&HashMap::from([
+            ("ops".to_string(), key("ops-key", 1, true)),
+            ("app".to_string(), key("app-key", 2, false)),
+        ]))
+    }
+}
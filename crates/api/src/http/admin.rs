@@ -0,0 +1,636 @@
+//! Operational HTTP endpoints.
+//!
+//! `GET /api/admin/maintenance` reports whether the service is in read-only
+//! maintenance mode and `PUT` with `{"enabled": bool}` toggles it.
+//!
+//! `GET /api/admin/feature-flags` lists feature flags. `PUT` with
+//! `{"enabled": bool}` on `/api/admin/feature-flags/{name}` overrides a flag
+//! and `DELETE` reverts it to its configured value.
+//!
+//! `/api/v1/admin/impersonate/{user_id}` starts and ends impersonations, see
+//! [`impersonation`](crate::http::impersonation).
+//!
+//! `GET /api/v1/admin/duplicates` reports users that may be the same person,
+//! see [`duplicates`](crate::http::duplicates).
+//!
+//! `GET /admin/v1/config-schema` returns the JSON schema of the settings the
+//! server reads, with their defaults and environment variables, for checking
+//! deployment manifests before a rollout. It is only served when the server
+//! was given one, see
+//! [`ApiSubsystem::with_config_schema`](crate::ApiSubsystem::with_config_schema).
+//!
+//! `GET /admin/v1/leadership` lists the leases held by the instances sharing
+//! the database, with their fencing tokens, and tells which this instance
+//! holds, e.g. to see which instance leads the signup rollup refresh.
+//!
+//! `GET /admin/v1/metrics` renders the metrics of the process for
+//! Prometheus, see [`prometheus`](crate::prometheus).
+//!
+//! `GET /admin/v1/slo` reports how each service level objective is doing
+//! over the last hour: its error budget left and how fast it burns, see
+//! [`slo`](crate::slo).
+//!
+//! The log filter, page size cap and concurrency limits are changed under
+//! `/admin/v1`, see [`settings`](crate::http::settings).
+//!
+//! All of them but impersonation take an admin's API key, see
+//! [`auth`](crate::auth): requests without a key get 401 and those with
+//! another key 403.
+
+use std::{sync::Arc, time::Instant};
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::{StatusCode, header::CONTENT_TYPE},
+    middleware::from_fn,
+    response::IntoResponse,
+    routing::{get, put},
+};
+use chrono::{DateTime, Utc};
+use hex_play_core::{CoreServices, feature_flags::FeatureFlag, lease::Lease};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    auth,
+    http::{duplicates, error::Error, impersonation, limit::RouteLimits, settings},
+    prometheus::{self, OPENMETRICS_CONTENT_TYPE},
+    slo::{SloReport, SloTracker},
+};
+
+pub(crate) fn get_routes(core_services: Arc<CoreServices>, limits: &RouteLimits, slos: &SloTracker, config_schema: Option<Arc<Value>>) -> Router {
+    let schema_routes = match config_schema {
+        Some(schema) => Router::new().route("/admin/v1/config-schema", get(get_config_schema)).with_state(schema),
+        None => Router::new(),
+    };
+    Router::new()
+        .route("/api/admin/maintenance", get(get_maintenance).put(set_maintenance))
+        .route("/api/admin/feature-flags", get(list_feature_flags))
+        .route(
+            "/api/admin/feature-flags/{name}",
+            put(override_feature_flag).delete(clear_feature_flag_override),
+        )
+        .route("/admin/v1/leadership", get(get_leadership))
+        .route("/admin/v1/metrics", get(get_metrics))
+        .with_state(core_services.clone())
+        .merge(settings::get_routes(core_services.clone(), limits))
+        .merge(duplicates::get_routes(core_services.clone()))
+        .merge(schema_routes)
+        .merge(Router::new().route("/admin/v1/slo", get(get_slo)).with_state(slos.clone()))
+        .route_layer(from_fn(auth::require_admin))
+        // Ending an impersonation is authenticated by its token rather than
+        // an admin's key, so the impersonation routes check for themselves.
+        .merge(impersonation::get_routes(core_services))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct MaintenanceState {
+    enabled: bool,
+}
+
+#[tracing::instrument(level = "trace", skip(core_services))]
+async fn get_maintenance(State(core_services): State<Arc<CoreServices>>) -> Json<MaintenanceState> {
+    Json(MaintenanceState {
+        enabled: core_services.maintenance_mode.is_enabled(),
+    })
+}
+
+#[tracing::instrument(level = "trace", skip(core_services))]
+async fn set_maintenance(State(core_services): State<Arc<CoreServices>>, Json(state): Json<MaintenanceState>) -> Json<MaintenanceState> {
+    core_services.maintenance_mode.set_enabled(state.enabled);
+    Json(state)
+}
+
+#[derive(Serialize, Debug)]
+struct FeatureFlagResponse {
+    name: String,
+    enabled: bool,
+    overridden: bool,
+}
+
+impl From<FeatureFlag> for FeatureFlagResponse {
+    fn from(flag: FeatureFlag) -> Self {
+        Self {
+            name: flag.name,
+            enabled: flag.enabled,
+            overridden: flag.overridden,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct OverrideFeatureFlagRequest {
+    enabled: bool,
+}
+
+#[tracing::instrument(level = "trace", skip(core_services))]
+async fn list_feature_flags(State(core_services): State<Arc<CoreServices>>) -> Json<Vec<FeatureFlagResponse>> {
+    Json(core_services.feature_flags.list().into_iter().map(Into::into).collect())
+}
+
+#[tracing::instrument(level = "trace", skip(core_services))]
+async fn override_feature_flag(
+    State(core_services): State<Arc<CoreServices>>,
+    Path(name): Path<String>,
+    Json(request): Json<OverrideFeatureFlagRequest>,
+) -> Json<FeatureFlagResponse> {
+    core_services.feature_flags.set_override(&name, request.enabled);
+    Json(FeatureFlagResponse {
+        name,
+        enabled: request.enabled,
+        overridden: true,
+    })
+}
+
+#[tracing::instrument(level = "trace", skip(core_services))]
+async fn clear_feature_flag_override(State(core_services): State<Arc<CoreServices>>, Path(name): Path<String>) -> Result<StatusCode, Error> {
+    if core_services.feature_flags.clear_override(&name) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(Error::NotFound)
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct LeadershipResponse {
+    instance: String,
+    leases: Vec<LeaseResponse>,
+}
+
+#[derive(Serialize, Debug)]
+struct LeaseResponse {
+    name: String,
+    holder: String,
+    fencing_token: i64,
+    expires_at: DateTime<Utc>,
+    held_here: bool,
+}
+
+impl LeaseResponse {
+    fn new(lease: Lease, instance: &str) -> Self {
+        Self {
+            held_here: lease.holder == instance,
+            name: lease.name,
+            holder: lease.holder,
+            fencing_token: lease.fencing_token,
+            expires_at: lease.expires_at,
+        }
+    }
+}
+
+#[tracing::instrument(level = "trace", skip(core_services))]
+async fn get_leadership(State(core_services): State<Arc<CoreServices>>) -> Result<Json<LeadershipResponse>, Error> {
+    let instance = core_services.lease_service.holder().to_string();
+    let leases = core_services.lease_service.list().await?;
+    Ok(Json(LeadershipResponse {
+        leases: leases.into_iter().map(|lease| LeaseResponse::new(lease, &instance)).collect(),
+        instance,
+    }))
+}
+
+#[tracing::instrument(level = "trace", skip(schema))]
+async fn get_config_schema(State(schema): State<Arc<Value>>) -> Json<Value> {
+    Json(schema.as_ref().clone())
+}
+
+#[tracing::instrument(level = "trace")]
+async fn get_metrics() -> impl IntoResponse {
+    ([(CONTENT_TYPE, OPENMETRICS_CONTENT_TYPE)], prometheus::render())
+}
+
+#[tracing::instrument(level = "trace", skip(slos))]
+async fn get_slo(State(slos): State<SloTracker>) -> Json<Vec<SloReport>> {
+    Json(slos.report(Instant::now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use axum::{
+        Extension, Router,
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use chrono::TimeDelta;
+    use hex_play_core::{
+        CoreServices,
+        clock::FixedClock,
+        lease::{Lease, LeaseHolder},
+        test_support::{MockLeaseService, MockUserService, create_arc_core_services_with_mock, create_core_services_with_mock},
+        user::UserId,
+    };
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::get_routes;
+    use crate::{
+        auth::Principal,
+        http::limit::RouteLimits,
+        prometheus::RegistryRecorder,
+        slo::{SloConfig, SloTracker},
+    };
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn create_test_app() -> (Arc<CoreServices>, Router) {
+        let core_services = create_arc_core_services_with_mock(MockUserService::new());
+        (
+            core_services.clone(),
+            as_principal(get_routes(core_services, &RouteLimits::default(), &SloTracker::default(), None), true),
+        )
+    }
+
+    /// Has the requests to `app` come with the API key of user 1, an admin's
+    /// if `admin`.
+    fn as_principal(app: Router, admin: bool) -> Router {
+        app.layer(Extension(Principal {
+            user_id: UserId::new(1),
+            tenant: None,
+            admin,
+        }))
+    }
+
+    async fn body_to_string(body: Body) -> String {
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.expect("failed to read response body");
+        String::from_utf8(bytes.to_vec()).expect("response body must be valid UTF-8")
+    }
+
+    // ===================
+    // Tests: admin guard
+    // ===================
+    #[tokio::test]
+    async fn test_admin_routes_refuse_anonymous_requests() {
+        let schema = Some(Arc::new(json!({"type": "object"})));
+        let app = get_routes(
+            create_arc_core_services_with_mock(MockUserService::new()),
+            &RouteLimits::default(),
+            &SloTracker::default(),
+            schema,
+        );
+
+        for uri in [
+            "/api/admin/maintenance",
+            "/api/admin/feature-flags",
+            "/api/v1/admin/duplicates",
+            "/admin/v1/config-schema",
+            "/admin/v1/leadership",
+            "/admin/v1/log-level",
+            "/admin/v1/metrics",
+            "/admin/v1/settings",
+            "/admin/v1/slo",
+        ] {
+            let response = app.clone().oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap()).await.unwrap();
+
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_refuse_non_admin_keys() {
+        let schema = Some(Arc::new(json!({"type": "object"})));
+        let app = as_principal(
+            get_routes(
+                create_arc_core_services_with_mock(MockUserService::new()),
+                &RouteLimits::default(),
+                &SloTracker::default(),
+                schema,
+            ),
+            false,
+        );
+
+        for uri in [
+            "/api/admin/maintenance",
+            "/api/v1/admin/duplicates",
+            "/admin/v1/config-schema",
+            "/admin/v1/leadership",
+            "/admin/v1/metrics",
+            "/admin/v1/slo",
+        ] {
+            let response = app.clone().oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap()).await.unwrap();
+
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{uri}");
+        }
+    }
+
+    // ===================
+    // Tests: /api/admin/maintenance
+    // ===================
+    #[tokio::test]
+    async fn test_get_maintenance_reports_state() {
+        let (core_services, app) = create_test_app();
+        core_services.maintenance_mode.set_enabled(true);
+
+        let response = app
+            .oneshot(Request::builder().uri("/api/admin/maintenance").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_to_string(response.into_body()).await, r#"{"enabled":true}"#);
+    }
+
+    #[tokio::test]
+    async fn test_put_maintenance_toggles_mode() {
+        let (core_services, app) = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/api/admin/maintenance")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"enabled":true}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(core_services.maintenance_mode.is_enabled());
+    }
+
+    #[tokio::test]
+    async fn test_put_maintenance_rejects_invalid_body() {
+        let (core_services, app) = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/api/admin/maintenance")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"enabled":"yes"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(!core_services.maintenance_mode.is_enabled());
+    }
+
+    // ===================
+    // Tests: /api/admin/feature-flags
+    // ===================
+    #[tokio::test]
+    async fn test_list_feature_flags() {
+        let (core_services, app) = create_test_app();
+        core_services.feature_flags.set_override("soft_delete", true);
+
+        let response = app
+            .oneshot(Request::builder().uri("/api/admin/feature-flags").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            body_to_string(response.into_body()).await,
+            r#"[{"name":"soft_delete","enabled":true,"overridden":true}]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_override_feature_flag() {
+        let (core_services, app) = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/api/admin/feature-flags/new_pagination")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"enabled":true}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(core_services.feature_flags.is_enabled("new_pagination"));
+    }
+
+    #[tokio::test]
+    async fn test_feature_flags_are_forbidden_to_non_admins() {
+        let core_services = create_arc_core_services_with_mock(MockUserService::new());
+        let app = as_principal(get_routes(core_services.clone(), &RouteLimits::default(), &SloTracker::default(), None), false);
+
+        let list = app
+            .clone()
+            .oneshot(Request::builder().uri("/api/admin/feature-flags").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let set = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/api/admin/feature-flags/new_pagination")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"enabled":true}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(list.status(), StatusCode::FORBIDDEN);
+        assert_eq!(set.status(), StatusCode::FORBIDDEN);
+        assert!(!core_services.feature_flags.is_enabled("new_pagination"));
+    }
+
+    #[tokio::test]
+    async fn test_clear_feature_flag_override() {
+        let (core_services, app) = create_test_app();
+        core_services.feature_flags.set_override("new_pagination", true);
+
+        let request = || {
+            Request::builder()
+                .method("DELETE")
+                .uri("/api/admin/feature-flags/new_pagination")
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(!core_services.feature_flags.is_enabled("new_pagination"));
+
+        let response = app.oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    // ===================
+    // Tests: /admin/v1/settings and /admin/v1/log-level
+    // ===================
+    #[tokio::test]
+    async fn test_settings_are_forbidden_to_non_admins() {
+        let core_services = create_arc_core_services_with_mock(MockUserService::new());
+        let app = as_principal(get_routes(core_services.clone(), &RouteLimits::default(), &SloTracker::default(), None), false);
+
+        let settings = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("PATCH")
+                    .uri("/admin/v1/settings")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"maintenance_mode":true}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let log_level = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/admin/v1/log-level")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"filter":"trace"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(settings.status(), StatusCode::FORBIDDEN);
+        assert_eq!(log_level.status(), StatusCode::FORBIDDEN);
+        assert!(!core_services.maintenance_mode.is_enabled());
+    }
+
+    // ===================
+    // Tests: /admin/v1/config-schema
+    // ===================
+    #[tokio::test]
+    async fn test_get_config_schema_returns_schema() {
+        let schema = json!({"type": "object", "properties": {"maintenance_mode": {"type": "boolean"}}});
+        let app = as_principal(
+            get_routes(
+                create_arc_core_services_with_mock(MockUserService::new()),
+                &RouteLimits::default(),
+                &SloTracker::default(),
+                Some(Arc::new(schema.clone())),
+            ),
+            true,
+        );
+
+        let response = app
+            .oneshot(Request::builder().uri("/admin/v1/config-schema").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body_to_string(response.into_body()).await).unwrap();
+        assert_eq!(body, schema);
+    }
+
+    #[tokio::test]
+    async fn test_config_schema_is_not_found_without_schema() {
+        let (_, app) = create_test_app();
+
+        let response = app
+            .oneshot(Request::builder().uri("/admin/v1/config-schema").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    // ===================
+    // Tests: /admin/v1/leadership
+    // ===================
+    #[tokio::test]
+    async fn test_get_leadership_marks_leases_held_here() {
+        let instance = LeaseHolder::generate();
+        let lease = |name: &str, holder: &str, fencing_token| Lease {
+            name: name.to_string(),
+            holder: holder.to_string(),
+            fencing_token,
+            expires_at: FixedClock::epoch() + TimeDelta::seconds(30),
+        };
+        let leases = vec![
+            lease("export", "LH_other", 4),
+            lease("scheduler:refresh_signup_rollups", &instance.to_string(), 2),
+        ];
+        let mut lease_service = MockLeaseService::new();
+        lease_service.expect_holder().return_const(instance);
+        lease_service.expect_list().times(1).returning(move || Ok(leases.clone()));
+        let core_services = Arc::new(CoreServices {
+            lease_service: Arc::new(lease_service),
+            ..create_core_services_with_mock(MockUserService::new())
+        });
+        let app = as_principal(get_routes(core_services, &RouteLimits::default(), &SloTracker::default(), None), true);
+
+        let response = app
+            .oneshot(Request::builder().uri("/admin/v1/leadership").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body_to_string(response.into_body()).await).unwrap();
+        assert_eq!(body["instance"], json!(instance.to_string()));
+        assert_eq!(body["leases"][0]["held_here"], json!(false));
+        assert_eq!(body["leases"][1]["held_here"], json!(true));
+        assert_eq!(body["leases"][1]["fencing_token"], json!(2));
+    }
+
+    // ===================
+    // Tests: /admin/v1/slo
+    // ===================
+    #[tokio::test]
+    async fn test_get_slo_reports_each_objective() {
+        let slos = SloTracker::new(&HashMap::from([(
+            "get_user".to_string(),
+            SloConfig {
+                route: "/api/v1/user/{id}".to_string(),
+                availability: Some(0.99),
+                latency: None,
+                latency_threshold_ms: None,
+                alert_burn_rate: None,
+            },
+        )]));
+        slos.record("/api/v1/user/{id}", StatusCode::INTERNAL_SERVER_ERROR, Duration::from_millis(5), Instant::now());
+        let app = as_principal(
+            get_routes(create_arc_core_services_with_mock(MockUserService::new()), &RouteLimits::default(), &slos, None),
+            true,
+        );
+
+        let response = app.oneshot(Request::builder().uri("/admin/v1/slo").body(Body::empty()).unwrap()).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body_to_string(response.into_body()).await).unwrap();
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert_eq!(body[0]["name"], json!("get_user"));
+        assert_eq!(body[0]["objective"], json!("availability"));
+        assert_eq!(body[0]["requests"], json!(1));
+        assert_eq!(body[0]["bad_requests"], json!(1));
+        assert_eq!(body[0]["alerting"], json!(true));
+    }
+
+    // ===================
+    // Tests: /admin/v1/metrics
+    // ===================
+    #[tokio::test]
+    async fn test_get_metrics_reports_recorded_series() {
+        metrics::with_local_recorder(&RegistryRecorder::default(), || {
+            metrics::histogram!("test_admin_op_duration_seconds", "repo" => "user", "op" => "find_by_id").record(0.01);
+        });
+        let (_, app) = create_test_app();
+
+        let response = app
+            .oneshot(Request::builder().uri("/admin/v1/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "application/openmetrics-text; version=1.0.0; charset=utf-8");
+        let body = body_to_string(response.into_body()).await;
+        assert!(body.contains("# TYPE test_admin_op_duration_seconds histogram"), "{body}");
+        assert!(
+            body.contains(r#"test_admin_op_duration_seconds_count{repo="user",op="find_by_id"} 1"#),
+            "{body}"
+        );
+    }
+}
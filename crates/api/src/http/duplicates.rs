@@ -0,0 +1,180 @@
+//! Users that may be the same person.
+//!
+//! `GET /api/v1/admin/duplicates` groups users by normalized email local
+//! part and by similar names, so that an admin can review each group before
+//! merging its users. It reads every user of the admin's tenant, so it is
+//! meant for occasional reports rather than dashboards. Only admins' API
+//! keys may ask for it, see [`admin`](crate::http::admin).
+
+use std::sync::Arc;
+
+use axum::{Extension, Json, Router, extract::State, middleware::from_fn, routing::get};
+use hex_play_core::{
+    CoreServices,
+    context::RequestContext,
+    user::{DuplicateGroup, DuplicateReason, User, UserId},
+};
+use serde::Serialize;
+
+use crate::{
+    auth::Principal,
+    http::{error::Error, request_context},
+};
+
+pub(crate) fn get_routes(core_services: Arc<CoreServices>) -> Router {
+    Router::new()
+        .route("/api/v1/admin/duplicates", get(list_duplicates))
+        .layer(from_fn(request_context))
+        .with_state(core_services)
+}
+
+#[derive(Serialize, Debug)]
+struct DuplicateGroupResponse {
+    reason: &'static str,
+    key: String,
+    users: Vec<DuplicateUserResponse>,
+}
+
+impl From<DuplicateGroup> for DuplicateGroupResponse {
+    fn from(group: DuplicateGroup) -> Self {
+        Self {
+            reason: match group.reason {
+                DuplicateReason::EmailLocalPart => "email_local_part",
+                DuplicateReason::SimilarName => "similar_name",
+            },
+            key: group.key,
+            users: group.users.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct DuplicateUserResponse {
+    id: UserId,
+    token: String,
+    name: String,
+    email: String,
+}
+
+impl From<User> for DuplicateUserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            token: user.token.to_string(),
+            name: user.name,
+            email: user.email.as_str().to_string(),
+        }
+    }
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context, principal))]
+async fn list_duplicates(
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    Extension(principal): Extension<Principal>,
+) -> Result<Json<Vec<DuplicateGroupResponse>>, Error> {
+    // Users of other tenants are never compared, let alone shown.
+    let context = RequestContext {
+        tenant: principal.tenant,
+        ..context
+    };
+    let groups = core_services.user_service.find_potential_duplicates(&context).await.map_err(Error::Core)?;
+    Ok(Json(groups.into_iter().map(Into::into).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Extension, Router,
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use hex_play_core::{
+        Error,
+        test_support::{MockUserService, create_arc_core_services_with_mock},
+        user::{DuplicateGroup, DuplicateReason, User, UserId, UserToken},
+    };
+    use tower::ServiceExt;
+
+    use super::get_routes;
+    use crate::auth::Principal;
+
+    // ===================
+    // Test Helpers
+    // ===================
+    /// The routes, with requests coming from the key of admin 1 of `acme`.
+    fn create_test_app(mock: MockUserService) -> Router {
+        get_routes(create_arc_core_services_with_mock(mock)).layer(Extension(Principal {
+            user_id: UserId::new(1),
+            tenant: Some("acme".to_string()),
+            admin: true,
+        }))
+    }
+
+    async fn body_to_string(body: Body) -> String {
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.expect("failed to read response body");
+        String::from_utf8(bytes.to_vec()).expect("response body must be valid UTF-8")
+    }
+
+    // ===================
+    // Tests: GET /api/v1/admin/duplicates
+    // ===================
+    #[tokio::test]
+    async fn test_list_duplicates() {
+        let group = DuplicateGroup {
+            reason: DuplicateReason::EmailLocalPart,
+            key: "janedoe".to_string(),
+            users: vec![User::fake(1, "Jane", "jane.doe@example.com"), User::fake(3, "J. Doe", "janedoe@example.org")],
+        };
+        let mut mock = MockUserService::new();
+        mock.expect_find_potential_duplicates().return_const(Ok(vec![group]));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().uri("/api/v1/admin/duplicates").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            body_to_string(response.into_body()).await,
+            format!(
+                r#"[{{"reason":"email_local_part","key":"janedoe","users":[{{"id":1,"token":"{}","name":"Jane","email":"jane.doe@example.com"}},{{"id":3,"token":"{}","name":"J. Doe","email":"janedoe@example.org"}}]}}]"#,
+                UserToken::new(UserId::new(1)),
+                UserToken::new(UserId::new(3))
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_duplicates_covers_only_the_keys_tenant() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_potential_duplicates()
+            .withf(|context| context.tenant.as_deref() == Some("acme"))
+            .times(1)
+            .return_const(Ok(vec![]));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().uri("/api/v1/admin/duplicates").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_list_duplicates_propagates_error() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_potential_duplicates()
+            .return_const(Err(Error::Infrastructure("database down".into())));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().uri("/api/v1/admin/duplicates").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert!(response.status().is_server_error());
+    }
+}
@@ -1,9 +1,11 @@
 use axum::{
-    http::StatusCode,
+    http::{HeaderValue, StatusCode, header::RETRY_AFTER},
     response::{IntoResponse, Response},
 };
 use hex_play_core::{Error as CoreError, ErrorKind};
 
+use crate::reporting::Failure;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error(transparent)]
@@ -11,27 +13,71 @@ pub enum Error {
 
     #[error("Not found")]
     NotFound,
+
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+
+    #[error("Invalid signature")]
+    InvalidSignature,
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Payload too large: over {0} bytes")]
+    PayloadTooLarge(usize),
+
+    #[error("Unsupported media type: {0}")]
+    UnsupportedMediaType(String),
 }
 
-fn status_code_from_error_kind(kind: ErrorKind) -> StatusCode {
+/// Seconds clients are asked to wait before retrying a 503.
+const RETRY_AFTER_SECS: &str = "30";
+
+pub(crate) fn status_code_from_error_kind(kind: ErrorKind) -> StatusCode {
     match kind {
         ErrorKind::NotFound => StatusCode::NOT_FOUND,
         ErrorKind::Conflict => StatusCode::CONFLICT,
         ErrorKind::InvalidInput => StatusCode::UNPROCESSABLE_ENTITY,
         ErrorKind::BadRequest => StatusCode::BAD_REQUEST,
         ErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        ErrorKind::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+    }
+}
+
+impl Error {
+    pub(crate) fn status(&self) -> StatusCode {
+        match self {
+            Error::NotFound => StatusCode::NOT_FOUND,
+            Error::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Error::InvalidSignature | Error::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Error::Forbidden(_) => StatusCode::FORBIDDEN,
+            Error::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Error::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Error::Core(core_error) => status_code_from_error_kind(core_error.kind()),
+        }
     }
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
-        let (status, message) = match &self {
-            Error::NotFound => (StatusCode::NOT_FOUND, "Not found".to_string()),
-            Error::Core(core_error) => (status_code_from_error_kind(core_error.kind()), core_error.to_string()),
-        };
+        let (status, message) = (self.status(), self.to_string());
 
         tracing::error!(%status, error = %self, "Request failed");
 
-        (status, message).into_response()
+        let mut response = (status, message.clone()).into_response();
+        add_retry_after(&mut response);
+        Failure::mark(&mut response, message);
+        response
+    }
+}
+
+/// Adds `Retry-After` to 503 responses so clients back off while the service
+/// is in maintenance mode, overloaded, or the database is unreachable.
+pub(crate) fn add_retry_after(response: &mut Response) {
+    if response.status() == StatusCode::SERVICE_UNAVAILABLE {
+        response.headers_mut().insert(RETRY_AFTER, HeaderValue::from_static(RETRY_AFTER_SECS));
     }
 }
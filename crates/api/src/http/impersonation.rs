@@ -0,0 +1,405 @@
+//! Admins acting as another user.
+//!
+//! `POST /api/v1/admin/impersonate/{user_id}` with an admin's API key starts
+//! an impersonation by the key's user and returns its token, good for
+//! [`IMPERSONATION_TTL`](hex_play_core::session::IMPERSONATION_TTL). Other
+//! keys get 403 and requests without one 401. Requests
+//! sending it as `Authorization: Bearer` act as the user, within the admin's
+//! tenant, with the admin as the context's impersonator and
+//! `impersonator_id` on the request span.
+//! `DELETE` on the same path with the token ends it early. Starting and
+//! ending are audited with both identities.
+
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json, Router,
+    body::Body,
+    extract::{Path, State},
+    http::{HeaderMap, Request, StatusCode, header::AUTHORIZATION},
+    middleware::{Next, from_fn},
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use chrono::{DateTime, Utc};
+use hex_play_core::{
+    CoreServices,
+    context::RequestContext,
+    session::{Impersonation, ImpersonationToken},
+    user::UserId,
+};
+use serde::Serialize;
+
+use crate::{
+    auth::Principal,
+    context,
+    http::{error::Error, request_context},
+};
+
+pub(crate) fn get_routes(core_services: Arc<CoreServices>) -> Router {
+    Router::new()
+        .route("/api/v1/admin/impersonate/{user_id}", post(start_impersonation).delete(end_impersonation))
+        .layer(from_fn(request_context))
+        .with_state(core_services)
+}
+
+#[derive(Serialize, Debug)]
+struct ImpersonationResponse {
+    token: String,
+    user_id: UserId,
+    impersonator_id: UserId,
+    expires_at: DateTime<Utc>,
+}
+
+impl From<Impersonation> for ImpersonationResponse {
+    fn from(impersonation: Impersonation) -> Self {
+        Self {
+            token: impersonation.token.to_string(),
+            user_id: impersonation.user_id,
+            impersonator_id: impersonation.impersonator_id,
+            expires_at: impersonation.expires_at,
+        }
+    }
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context, principal))]
+async fn start_impersonation(
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    principal: Option<Extension<Principal>>,
+    Path(user_id): Path<UserId>,
+) -> Result<(StatusCode, Json<ImpersonationResponse>), Error> {
+    let Some(Extension(principal)) = principal else {
+        return Err(Error::Unauthorized("expected an admin's API key as `Authorization: Bearer`".to_string()));
+    };
+    if !principal.admin {
+        return Err(Error::Forbidden("only admins may impersonate users".to_string()));
+    }
+    let impersonation = core_services
+        .session_service
+        .start_impersonation(&context, principal.user_id, user_id)
+        .await
+        .map_err(Error::Core)?;
+
+    Ok((StatusCode::CREATED, Json(impersonation.into())))
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context, headers))]
+async fn end_impersonation(
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    Path(user_id): Path<UserId>,
+    headers: HeaderMap,
+) -> Result<StatusCode, Error> {
+    let token = bearer_token(&headers).ok_or_else(|| Error::BadRequest("expected the impersonation token as `Authorization: Bearer`".to_string()))?;
+    // Only the impersonation of the user named in the path is ended.
+    let impersonation = core_services
+        .session_service
+        .find_impersonation(&context, token)
+        .await
+        .map_err(Error::Core)?
+        .filter(|impersonation| impersonation.user_id == user_id)
+        .ok_or(Error::NotFound)?;
+    core_services
+        .session_service
+        .end_impersonation(&context, impersonation.token)
+        .await
+        .map_err(Error::Core)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The impersonation token a request carries, if its bearer token is one.
+fn bearer_token(headers: &HeaderMap) -> Option<ImpersonationToken> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .and_then(|token| ImpersonationToken::parse(token.trim()).ok())
+}
+
+/// Makes a request carrying an impersonation token act as the impersonated
+/// user. An ended or expired token gets 401; other bearer tokens, such as
+/// SCIM's, pass through untouched.
+pub(crate) async fn resolve_impersonation(State(core_services): State<Arc<CoreServices>>, mut request: Request<Body>, next: Next) -> Response {
+    let Some(token) = bearer_token(request.headers()) else {
+        return next.run(request).await;
+    };
+    let context = context::from_headers(request.headers());
+    match core_services.session_service.find_impersonation(&context, token).await {
+        Ok(Some(impersonation)) => {
+            tracing::Span::current().record("impersonator_id", tracing::field::display(impersonation.impersonator_id));
+            request.extensions_mut().insert(impersonation);
+            next.run(request).await
+        }
+        Ok(None) => Error::Unauthorized("impersonation has ended or expired".to_string()).into_response(),
+        Err(e) => Error::Core(e).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use axum::{
+        Extension, Router,
+        body::Body,
+        http::{Request, StatusCode},
+        middleware::{from_fn, from_fn_with_state},
+        routing::get,
+    };
+    use chrono::{DateTime, Utc};
+    use hex_play_core::{
+        CoreServices, Error,
+        context::RequestContext,
+        session::{Impersonation, ImpersonationToken},
+        test_support::{MockSessionService, MockUserService, create_core_services_with_mock},
+        user::UserId,
+    };
+    use hex_play_utils::secret::Secret;
+    use tower::ServiceExt;
+
+    use super::{get_routes, resolve_impersonation};
+    use crate::{
+        ApiKeyConfig,
+        auth::{ApiKeys, authenticate_http},
+        http::request_context,
+    };
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn create_core_services(mock: MockSessionService) -> Arc<CoreServices> {
+        let mut core_services = create_core_services_with_mock(MockUserService::new());
+        core_services.session_service = Arc::new(mock);
+        Arc::new(core_services)
+    }
+
+    /// Authenticates `admin-key` as admin 1 and `user-key` as user 3, who is
+    /// not an admin.
+    fn create_api_keys() -> ApiKeys {
+        ApiKeys::new(&HashMap::from([
+            ("ops".to_string(), api_key("admin-key", 1, true)),
+            ("app".to_string(), api_key("user-key", 3, false)),
+        ]))
+    }
+
+    fn create_test_app(mock: MockSessionService) -> Router {
+        get_routes(create_core_services(mock)).layer(from_fn_with_state(create_api_keys(), authenticate_http))
+    }
+
+    fn api_key(key: &str, user_id: u64, admin: bool) -> ApiKeyConfig {
+        ApiKeyConfig {
+            key: Secret::new(key),
+            user_id,
+            tenant: None,
+            admin,
+        }
+    }
+
+    fn start_request(user_id: u64, api_key: Option<&str>) -> Request<Body> {
+        let builder = Request::builder().method("POST").uri(format!("/api/v1/admin/impersonate/{user_id}"));
+        let builder = match api_key {
+            Some(api_key) => builder.header("authorization", format!("Bearer {api_key}")),
+            None => builder,
+        };
+        builder.body(Body::empty()).unwrap()
+    }
+
+    /// A route answering with the actor and impersonator of its context.
+    fn create_whoami_app(mock: MockSessionService) -> Router {
+        Router::new()
+            .route(
+                "/whoami",
+                get(|Extension(context): Extension<RequestContext>| async move {
+                    format!("{:?} {:?} {:?}", context.actor, context.impersonator, context.tenant)
+                }),
+            )
+            .layer(from_fn(request_context))
+            .layer(from_fn_with_state(create_api_keys(), authenticate_http))
+            .layer(from_fn_with_state(create_core_services(mock), resolve_impersonation))
+    }
+
+    fn whoami_request(authorization: &str) -> Request<Body> {
+        Request::builder()
+            .uri("/whoami")
+            .header("authorization", authorization)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn fake_impersonation(user_id: u64) -> Impersonation {
+        Impersonation {
+            token: ImpersonationToken::new(42),
+            user_id: UserId::new(user_id),
+            impersonator_id: UserId::new(1),
+            tenant: Some("acme".to_string()),
+            expires_at: DateTime::<Utc>::from_timestamp(1735689600, 0).unwrap(),
+        }
+    }
+
+    async fn body_to_string(body: Body) -> String {
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.expect("failed to read response body");
+        String::from_utf8(bytes.to_vec()).expect("response body must be valid UTF-8")
+    }
+
+    fn end_request(user_id: u64) -> Request<Body> {
+        Request::builder()
+            .method("DELETE")
+            .uri(format!("/api/v1/admin/impersonate/{user_id}"))
+            .header("authorization", format!("Bearer {}", ImpersonationToken::new(42)))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    // ===================
+    // Tests: POST /api/v1/admin/impersonate/{user_id}
+    // ===================
+    #[tokio::test]
+    async fn test_start_impersonation_returns_token() {
+        let mut mock = MockSessionService::new();
+        mock.expect_start_impersonation()
+            .withf(|_, impersonator_id, user_id| *impersonator_id == UserId::new(1) && *user_id == UserId::new(2))
+            .return_const(Ok(fake_impersonation(2)));
+        let app = create_test_app(mock);
+
+        let response = app.oneshot(start_request(2, Some("admin-key"))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(
+            body_to_string(response.into_body()).await,
+            format!(
+                r#"{{"token":"{}","user_id":2,"impersonator_id":1,"expires_at":"2025-01-01T00:00:00Z"}}"#,
+                ImpersonationToken::new(42)
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_impersonation_of_self_is_bad_request() {
+        let mut mock = MockSessionService::new();
+        mock.expect_start_impersonation().return_const(Err(Error::SelfImpersonation));
+        let app = create_test_app(mock);
+
+        let response = app.oneshot(start_request(1, Some("admin-key"))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_start_impersonation_by_non_admin_is_forbidden() {
+        let mut mock = MockSessionService::new();
+        mock.expect_start_impersonation().never();
+        let app = create_test_app(mock);
+
+        let response = app.oneshot(start_request(2, Some("user-key"))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_start_impersonation_without_api_key_is_unauthorized() {
+        let mut mock = MockSessionService::new();
+        mock.expect_start_impersonation().never();
+        let app = create_test_app(mock);
+
+        let unauthenticated = app.clone().oneshot(start_request(2, None)).await.unwrap();
+        let unknown_key = app.oneshot(start_request(2, Some("guessed-key"))).await.unwrap();
+
+        assert_eq!(unauthenticated.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(unknown_key.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    // ===================
+    // Tests: DELETE /api/v1/admin/impersonate/{user_id}
+    // ===================
+    #[tokio::test]
+    async fn test_end_impersonation() {
+        let mut mock = MockSessionService::new();
+        mock.expect_find_impersonation().return_const(Ok(Some(fake_impersonation(2))));
+        mock.expect_end_impersonation().return_const(Ok(fake_impersonation(2)));
+        let app = create_test_app(mock);
+
+        let response = app.oneshot(end_request(2)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_end_impersonation_of_other_user_is_not_found() {
+        let mut mock = MockSessionService::new();
+        mock.expect_find_impersonation().return_const(Ok(Some(fake_impersonation(2))));
+        let app = create_test_app(mock);
+
+        let response = app.oneshot(end_request(3)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    // ===================
+    // Tests: resolve_impersonation
+    // ===================
+    #[tokio::test]
+    async fn test_impersonation_token_acts_as_user() {
+        let mut mock = MockSessionService::new();
+        mock.expect_find_impersonation().return_const(Ok(Some(fake_impersonation(2))));
+        let app = create_whoami_app(mock);
+
+        let response = app.oneshot(whoami_request(&format!("Bearer {}", ImpersonationToken::new(42)))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_to_string(response.into_body()).await, r#"Some("2") Some("1") Some("acme")"#);
+    }
+
+    #[tokio::test]
+    async fn test_impersonation_may_name_only_its_tenant() {
+        let mut mock = MockSessionService::new();
+        mock.expect_find_impersonation().return_const(Ok(Some(fake_impersonation(2))));
+        let app = create_whoami_app(mock);
+        let request = |tenant: &str| {
+            Request::builder()
+                .uri("/whoami")
+                .header("authorization", format!("Bearer {}", ImpersonationToken::new(42)))
+                .header("x-tenant-id", tenant)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let own_tenant = app.clone().oneshot(request("acme")).await.unwrap();
+        let other_tenant = app.oneshot(request("globex")).await.unwrap();
+
+        assert_eq!(own_tenant.status(), StatusCode::OK);
+        assert_eq!(other_tenant.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_ended_impersonation_token_is_unauthorized() {
+        let mut mock = MockSessionService::new();
+        mock.expect_find_impersonation().return_const(Ok(None));
+        let app = create_whoami_app(mock);
+
+        let response = app.oneshot(whoami_request(&format!("Bearer {}", ImpersonationToken::new(42)))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_acts_as_its_user() {
+        let app = create_whoami_app(MockSessionService::new());
+
+        let response = app.oneshot(whoami_request("Bearer user-key")).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_to_string(response.into_body()).await, r#"Some("3") None None"#);
+    }
+
+    #[tokio::test]
+    async fn test_other_bearer_tokens_pass_through() {
+        let app = create_whoami_app(MockSessionService::new());
+
+        let response = app.oneshot(whoami_request("Bearer scim-token")).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_to_string(response.into_body()).await, "None None None");
+    }
+}
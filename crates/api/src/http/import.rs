@@ -0,0 +1,345 @@
+//! Bulk import of users.
+//!
+//! `POST /api/v1/user/import` takes one user per line as NDJSON, each line
+//! like the body of a create. The body may be sent with
+//! `Content-Encoding: gzip`, and is decoded as it arrives: lines are
+//! parsed one by one and written [`IMPORT_BATCH_SIZE`] at a time, so a load
+//! of millions of users never sits whole in memory or on disk. A user
+//! breaking a business rule, such as a blank name, fails the batch it is
+//! in. Users are matched by email, so an import cut short by a bad line can
+//! be fixed and sent again.
+
+use std::{io::Write as _, sync::Arc};
+
+use axum::{
+    Extension, Json, Router,
+    body::Body,
+    extract::State,
+    http::{HeaderMap, header::CONTENT_ENCODING},
+    middleware::from_fn,
+    routing::post,
+};
+use flate2::write::MultiGzDecoder;
+use hex_play_core::{
+    CoreServices,
+    context::RequestContext,
+    user::{IMPORT_BATCH_SIZE, NewUser},
+};
+use serde::Serialize;
+use tokio_stream::StreamExt as _;
+
+use crate::http::{error::Error, limit::RouteLimits, request_context, user::CreateUserRequest};
+
+/// Most bytes an import may decode to, however well it compresses.
+const MAX_IMPORT_BYTES: usize = 1024 * 1024 * 1024;
+/// Compressed bytes decoded at a time, which bounds how far past
+/// [`MAX_IMPORT_BYTES`] a body that compresses very well gets before it is
+/// refused.
+const GZIP_SLICE_BYTES: usize = 4 * 1024;
+
+pub(crate) fn get_routes(core_services: Arc<CoreServices>, limits: &RouteLimits) -> Router {
+    Router::new()
+        .route("/api/v1/user/import", limits.exports(post(import_users)))
+        .layer(from_fn(request_context))
+        .with_state(core_services)
+}
+
+#[derive(Serialize, Debug)]
+struct ImportUsersResponse {
+    imported: u64,
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context, headers, body))]
+async fn import_users(
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Json<ImportUsersResponse>, Error> {
+    let mut lines = Lines::new(is_gzip(&headers)?, MAX_IMPORT_BYTES);
+    let mut body = body.into_data_stream();
+    let mut batch = Vec::with_capacity(IMPORT_BATCH_SIZE);
+    let mut line_number = 0;
+    let mut imported = 0;
+    let mut done = false;
+
+    while !done {
+        let last = match body.next().await {
+            Some(chunk) => {
+                lines.push(&chunk.map_err(|error| Error::BadRequest(format!("Couldn't read body: {error}")))?)?;
+                None
+            }
+            None => {
+                done = true;
+                lines.finish()?
+            }
+        };
+        for line in std::iter::from_fn(|| lines.next_line()).chain(last) {
+            line_number += 1;
+            let line = line.trim_ascii();
+            if line.is_empty() {
+                continue;
+            }
+            let user = parse_user(line).map_err(|message| Error::BadRequest(format!("Line {line_number}: {message} ({imported} users imported before it)")))?;
+            batch.push(user);
+            if batch.len() == IMPORT_BATCH_SIZE {
+                imported += import_batch(&core_services, &context, &mut batch).await?;
+            }
+        }
+    }
+    if !batch.is_empty() {
+        imported += import_batch(&core_services, &context, &mut batch).await?;
+    }
+
+    tracing::info!(imported, "Imported users");
+    Ok(Json(ImportUsersResponse { imported }))
+}
+
+async fn import_batch(core_services: &CoreServices, context: &RequestContext, batch: &mut Vec<NewUser>) -> Result<u64, Error> {
+    core_services
+        .user_service
+        .import_users(context, std::mem::take(batch))
+        .await
+        .map_err(Error::Core)
+}
+
+/// Whether the body is gzipped, refusing any other encoding.
+fn is_gzip(headers: &HeaderMap) -> Result<bool, Error> {
+    match headers.get(CONTENT_ENCODING).map(|value| value.to_str().unwrap_or_default().trim()) {
+        None | Some("identity") => Ok(false),
+        Some(encoding) if encoding.eq_ignore_ascii_case("gzip") || encoding.eq_ignore_ascii_case("x-gzip") => Ok(true),
+        Some(encoding) => Err(Error::UnsupportedMediaType(format!("Content-Encoding {encoding:?}"))),
+    }
+}
+
+/// A user as a line describes them.
+fn parse_user(line: &[u8]) -> Result<NewUser, String> {
+    serde_json::from_slice::<CreateUserRequest>(line)
+        .map(Into::into)
+        .map_err(|error| error.to_string())
+}
+
+/// The lines of a body that arrives in chunks, gunzipped first if need be,
+/// including bodies of several gzip members as `pigz` writes.
+struct Lines {
+    gzip: Option<MultiGzDecoder<Vec<u8>>>,
+    /// Decoded bytes, of which those before `start` were handed out.
+    buffer: Vec<u8>,
+    start: usize,
+    decoded: usize,
+    max_bytes: usize,
+}
+
+impl Lines {
+    fn new(gzip: bool, max_bytes: usize) -> Self {
+        Self {
+            gzip: gzip.then(|| MultiGzDecoder::new(Vec::new())),
+            buffer: Vec::new(),
+            start: 0,
+            decoded: 0,
+            max_bytes,
+        }
+    }
+
+    /// Decodes `chunk`, failing once more than `max_bytes` were decoded.
+    fn push(&mut self, chunk: &[u8]) -> Result<(), Error> {
+        if self.gzip.is_none() {
+            return self.append(chunk);
+        }
+        for slice in chunk.chunks(GZIP_SLICE_BYTES) {
+            let gzip = self.gzip.as_mut().expect("gzip decoder is there until finished");
+            gzip.write_all(slice).and_then(|()| gzip.flush()).map_err(invalid_gzip)?;
+            let decoded = std::mem::take(gzip.get_mut());
+            self.append(&decoded)?;
+        }
+        Ok(())
+    }
+
+    /// Ends the body, returning its last line if no newline ends it.
+    fn finish(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        if let Some(gzip) = self.gzip.take() {
+            let decoded = gzip.finish().map_err(invalid_gzip)?;
+            self.append(&decoded)?;
+        }
+        let rest = self.buffer.split_off(self.start);
+        self.start = self.buffer.len();
+        Ok((!rest.is_empty()).then_some(rest))
+    }
+
+    /// The next whole line decoded so far, without its newline.
+    fn next_line(&mut self) -> Option<Vec<u8>> {
+        let end = self.start + self.buffer[self.start..].iter().position(|&byte| byte == b'\n')?;
+        let line = self.buffer[self.start..end].to_vec();
+        self.start = end + 1;
+        Some(line)
+    }
+
+    fn append(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.decoded += bytes.len();
+        if self.decoded > self.max_bytes {
+            return Err(Error::PayloadTooLarge(self.max_bytes));
+        }
+        self.buffer.drain(..self.start);
+        self.start = 0;
+        self.buffer.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+fn invalid_gzip(error: std::io::Error) -> Error {
+    Error::BadRequest(format!("Invalid gzip body: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use flate2::{Compression, write::GzEncoder};
+    use hex_play_core::{
+        Error,
+        test_support::{MockUserService, create_arc_core_services_with_mock},
+        user::IMPORT_BATCH_SIZE,
+    };
+    use tower::ServiceExt;
+
+    use super::{Lines, get_routes};
+    use crate::http::{error::Error as HttpError, limit::RouteLimits};
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn create_test_app(mock: MockUserService) -> Router {
+        get_routes(create_arc_core_services_with_mock(mock), &RouteLimits::default())
+    }
+
+    fn import_request(body: impl Into<Body>, encoding: Option<&str>) -> Request<Body> {
+        let mut request = Request::builder().method("POST").uri("/api/v1/user/import");
+        if let Some(encoding) = encoding {
+            request = request.header("content-encoding", encoding);
+        }
+        request.body(body.into()).unwrap()
+    }
+
+    fn ndjson(users: usize) -> String {
+        (0..users)
+            .map(|n| format!("{{\"name\":\"User {n}\",\"email\":\"user{n}@example.com\",\"age\":30}}\n"))
+            .collect()
+    }
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    async fn body_to_string(body: Body) -> String {
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.expect("failed to read response body");
+        String::from_utf8(bytes.to_vec()).expect("response body must be valid UTF-8")
+    }
+
+    // ===================
+    // Tests: POST /api/v1/user/import
+    // ===================
+    #[tokio::test]
+    async fn test_import_users_commits_in_batches() {
+        let mut mock = MockUserService::new();
+        mock.expect_import_users().times(3).returning(|_, users| Ok(users.len() as u64));
+        let app = create_test_app(mock);
+
+        let response = app.oneshot(import_request(ndjson(2 * IMPORT_BATCH_SIZE + 1), None)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            body_to_string(response.into_body()).await,
+            format!(r#"{{"imported":{}}}"#, 2 * IMPORT_BATCH_SIZE + 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_users_gzip() {
+        let mut mock = MockUserService::new();
+        mock.expect_import_users().times(1).returning(|_, users| {
+            assert_eq!(users[2].email.as_str(), "user2@example.com");
+            Ok(users.len() as u64)
+        });
+        let app = create_test_app(mock);
+
+        let body = gzip(ndjson(3).trim_end().as_bytes());
+        let response = app.oneshot(import_request(body, Some("gzip"))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_to_string(response.into_body()).await, r#"{"imported":3}"#);
+    }
+
+    #[tokio::test]
+    async fn test_import_users_reports_bad_line() {
+        let mut mock = MockUserService::new();
+        mock.expect_import_users().never();
+        let app = create_test_app(mock);
+
+        let body = format!("{}\n{{\"name\":\"Jane Doe\",\"email\":\"not an email\"}}\n", ndjson(1));
+        let response = app.oneshot(import_request(body, None)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(body_to_string(response.into_body()).await.contains("Line 3:"));
+    }
+
+    #[tokio::test]
+    async fn test_import_users_invalid_gzip() {
+        let app = create_test_app(MockUserService::new());
+
+        let response = app.oneshot(import_request(ndjson(1), Some("gzip"))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_import_users_unsupported_encoding() {
+        let app = create_test_app(MockUserService::new());
+
+        let response = app.oneshot(import_request(ndjson(1), Some("br"))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn test_import_users_propagates_error() {
+        let mut mock = MockUserService::new();
+        mock.expect_import_users().return_const(Err(Error::ReadOnlyMode));
+        let app = create_test_app(mock);
+
+        let response = app.oneshot(import_request(ndjson(1), None)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    // ===================
+    // Tests: Lines
+    // ===================
+    #[test]
+    fn test_lines_split_across_chunks() {
+        let mut lines = Lines::new(false, 1024);
+
+        lines.push(b"ab").unwrap();
+        assert_eq!(lines.next_line(), None);
+        lines.push(b"c\nde").unwrap();
+
+        assert_eq!(lines.next_line(), Some(b"abc".to_vec()));
+        assert_eq!(lines.next_line(), None);
+        assert_eq!(lines.finish().unwrap(), Some(b"de".to_vec()));
+    }
+
+    #[test]
+    fn test_lines_refuse_more_than_max_bytes_decoded() {
+        let mut lines = Lines::new(true, 1024);
+
+        let error = lines.push(&gzip(&[b'\n'; 1024 * 1024])).unwrap_err();
+
+        assert!(matches!(error, HttpError::PayloadTooLarge(1024)));
+    }
+}
@@ -0,0 +1,240 @@
+//! Concurrency limits per route group, so a burst of one kind of request
+//! cannot take every database connection. Requests over the limit are shed
+//! with 503 rather than queued.
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    BoxError,
+    error_handling::HandleErrorLayer,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::MethodRouter,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+use tower::{ServiceBuilder, limit::GlobalConcurrencyLimitLayer};
+
+use crate::http::error::add_retry_after;
+
+/// Maximum number of requests in flight per route group.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema)]
+#[serde(default)]
+pub struct ConcurrencyLimits {
+    /// Lookups of a single user.
+    pub reads: usize,
+    /// Creates, updates and deletes.
+    pub writes: usize,
+    /// Listings and batch lookups, which hold a connection longest.
+    pub exports: usize,
+}
+
+impl Default for ConcurrencyLimits {
+    fn default() -> Self {
+        Self {
+            reads: 64,
+            writes: 16,
+            exports: 4,
+        }
+    }
+}
+
+/// One shared limit per route group, applied to the routes of that group.
+/// Clones share the limits, so resizing one resizes them everywhere.
+#[derive(Clone)]
+pub(crate) struct RouteLimits {
+    reads: Limit,
+    writes: Limit,
+    exports: Limit,
+    current: Arc<Mutex<ConcurrencyLimits>>,
+}
+
+impl RouteLimits {
+    pub(crate) fn new(limits: &ConcurrencyLimits) -> Self {
+        Self {
+            reads: Limit::new(limits.reads),
+            writes: Limit::new(limits.writes),
+            exports: Limit::new(limits.exports),
+            current: Arc::new(Mutex::new(*limits)),
+        }
+    }
+
+    pub(crate) fn current(&self) -> ConcurrencyLimits {
+        *self.current.lock().unwrap()
+    }
+
+    /// Changes the limits of every route group. A lowered limit takes
+    /// effect as requests in flight finish; none is cut short.
+    pub(crate) fn resize(&self, limits: &ConcurrencyLimits) {
+        let mut current = self.current.lock().unwrap();
+        self.reads.resize(current.reads, limits.reads);
+        self.writes.resize(current.writes, limits.writes);
+        self.exports.resize(current.exports, limits.exports);
+        *current = *limits;
+        tracing::warn!(
+            reads = limits.reads,
+            writes = limits.writes,
+            exports = limits.exports,
+            "Concurrency limits changed"
+        );
+    }
+
+    pub(crate) fn reads<S: Clone + Send + Sync + 'static>(&self, route: MethodRouter<S>) -> MethodRouter<S> {
+        shed_over(route, self.reads.layer())
+    }
+
+    pub(crate) fn writes<S: Clone + Send + Sync + 'static>(&self, route: MethodRouter<S>) -> MethodRouter<S> {
+        shed_over(route, self.writes.layer())
+    }
+
+    pub(crate) fn exports<S: Clone + Send + Sync + 'static>(&self, route: MethodRouter<S>) -> MethodRouter<S> {
+        shed_over(route, self.exports.layer())
+    }
+}
+
+/// The permits of one route group. Requests hold a permit while in flight.
+#[derive(Clone)]
+struct Limit(Arc<Semaphore>);
+
+impl Limit {
+    fn new(permits: usize) -> Self {
+        Self(Arc::new(Semaphore::new(permits)))
+    }
+
+    fn layer(&self) -> GlobalConcurrencyLimitLayer {
+        GlobalConcurrencyLimitLayer::with_semaphore(self.0.clone())
+    }
+
+    fn resize(&self, from: usize, to: usize) {
+        if to > from {
+            self.0.add_permits(to - from);
+            return;
+        }
+        // Permits held by requests in flight are taken back as they are
+        // returned.
+        let excess = from - to;
+        let held = excess - self.0.forget_permits(excess);
+        if held > 0 {
+            let semaphore = self.0.clone();
+            tokio::spawn(async move {
+                if let Ok(permits) = semaphore.acquire_many(held as u32).await {
+                    permits.forget();
+                }
+            });
+        }
+    }
+}
+
+impl Default for RouteLimits {
+    fn default() -> Self {
+        Self::new(&ConcurrencyLimits::default())
+    }
+}
+
+fn shed_over<S: Clone + Send + Sync + 'static>(route: MethodRouter<S>, limit: GlobalConcurrencyLimitLayer) -> MethodRouter<S> {
+    route.layer(ServiceBuilder::new().layer(HandleErrorLayer::new(overloaded)).load_shed().layer(limit))
+}
+
+async fn overloaded(_: BoxError) -> Response {
+    let mut response = (StatusCode::SERVICE_UNAVAILABLE, "Server is busy").into_response();
+    add_retry_after(&mut response);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, StatusCode},
+        routing::get,
+    };
+    use tokio::sync::Notify;
+    use tower::ServiceExt;
+
+    use super::{ConcurrencyLimits, RouteLimits};
+
+    // ===================
+    // Tests: RouteLimits
+    // ===================
+    #[tokio::test]
+    async fn test_sheds_requests_over_the_limit() {
+        let entered = Arc::new(Notify::new());
+        let release = Arc::new(Notify::new());
+        let limits = RouteLimits::new(&ConcurrencyLimits {
+            reads: 1,
+            ..ConcurrencyLimits::default()
+        });
+        let (handler_entered, handler_release) = (entered.clone(), release.clone());
+        let app = Router::new().route(
+            "/",
+            limits.reads(get(move || async move {
+                handler_entered.notify_one();
+                handler_release.notified().await;
+            })),
+        );
+
+        let first = tokio::spawn(app.clone().oneshot(Request::get("/").body(Body::empty()).unwrap()));
+        entered.notified().await;
+        let second = app.clone().oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+        release.notify_one();
+
+        assert_eq!(second.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(second.headers()["retry-after"], "30");
+        assert_eq!(first.await.unwrap().unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_resize_applies_to_existing_routes() {
+        let limits = RouteLimits::new(&ConcurrencyLimits {
+            reads: 0,
+            ..ConcurrencyLimits::default()
+        });
+        let app = Router::new().route("/", limits.reads(get(|| async {})));
+
+        let shed = app.clone().oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+        limits.resize(&ConcurrencyLimits {
+            reads: 1,
+            ..ConcurrencyLimits::default()
+        });
+        let served = app.oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+
+        assert_eq!(shed.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(served.status(), StatusCode::OK);
+        assert_eq!(limits.current().reads, 1);
+    }
+
+    #[tokio::test]
+    async fn test_lowered_limit_waits_for_requests_in_flight() {
+        let entered = Arc::new(Notify::new());
+        let release = Arc::new(Notify::new());
+        let limits = RouteLimits::new(&ConcurrencyLimits {
+            reads: 2,
+            ..ConcurrencyLimits::default()
+        });
+        let (handler_entered, handler_release) = (entered.clone(), release.clone());
+        let app = Router::new().route(
+            "/",
+            limits.reads(get(move || async move {
+                handler_entered.notify_one();
+                handler_release.notified().await;
+            })),
+        );
+
+        let first = tokio::spawn(app.clone().oneshot(Request::get("/").body(Body::empty()).unwrap()));
+        entered.notified().await;
+        limits.resize(&ConcurrencyLimits {
+            reads: 0,
+            ..ConcurrencyLimits::default()
+        });
+        release.notify_one();
+        assert_eq!(first.await.unwrap().unwrap().status(), StatusCode::OK);
+        tokio::task::yield_now().await;
+        let second = app.oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+
+        assert_eq!(second.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
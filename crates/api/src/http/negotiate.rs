@@ -0,0 +1,195 @@
+//! `Accept`-driven response serialization.
+//!
+//! Handlers take a [`ResponseFormat`] extractor and wrap their DTO in
+//! [`Negotiated`], which encodes it as JSON, MessagePack or CBOR. Anything
+//! the client did not ask for explicitly is served as JSON.
+
+use std::convert::Infallible;
+
+use axum::{
+    extract::FromRequestParts,
+    http::{
+        HeaderValue, StatusCode,
+        header::{ACCEPT, CONTENT_TYPE, VARY},
+        request::Parts,
+    },
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+const JSON: &str = "application/json";
+const MSGPACK: &str = "application/msgpack";
+const CBOR: &str = "application/cbor";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResponseFormat {
+    #[default]
+    Json,
+    MessagePack,
+    Cbor,
+}
+
+impl ResponseFormat {
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type {
+            "application/json" | "application/*" | "*/*" => Some(Self::Json),
+            MSGPACK | "application/x-msgpack" => Some(Self::MessagePack),
+            CBOR => Some(Self::Cbor),
+            _ => None,
+        }
+    }
+
+    /// Picks the supported format with the highest `q` value, preferring
+    /// earlier entries on ties.
+    pub(crate) fn from_accept(accept: &str) -> Self {
+        let mut best: Option<(Self, f32)> = None;
+        for entry in accept.split(',') {
+            let mut parts = entry.split(';').map(str::trim);
+            let Some(format) = parts.next().and_then(|media_type| Self::from_media_type(&media_type.to_ascii_lowercase())) else {
+                continue;
+            };
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if quality > 0.0 && best.is_none_or(|(_, best_quality)| quality > best_quality) {
+                best = Some((format, quality));
+            }
+        }
+
+        best.map(|(format, _)| format).unwrap_or_default()
+    }
+
+    /// Serializes `body`, returning the content type alongside the bytes.
+    pub(crate) fn encode<T: Serialize>(self, body: &T) -> Result<(&'static str, Vec<u8>), String> {
+        match self {
+            Self::Json => serde_json::to_vec(body).map_err(|e| e.to_string()).map(|bytes| (JSON, bytes)),
+            Self::MessagePack => rmp_serde::to_vec_named(body).map_err(|e| e.to_string()).map(|bytes| (MSGPACK, bytes)),
+            Self::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(body, &mut bytes).map_err(|e| e.to_string()).map(|()| (CBOR, bytes))
+            }
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ResponseFormat {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .headers
+            .get(ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .map(Self::from_accept)
+            .unwrap_or_default())
+    }
+}
+
+/// A serde DTO rendered in the format the client negotiated.
+pub(crate) struct Negotiated<T>(pub ResponseFormat, pub T);
+
+impl<T: Serialize> IntoResponse for Negotiated<T> {
+    fn into_response(self) -> Response {
+        let Negotiated(format, body) = self;
+
+        match format.encode(&body) {
+            Ok((content_type, bytes)) => (
+                [
+                    (CONTENT_TYPE, HeaderValue::from_static(content_type)),
+                    (VARY, HeaderValue::from_static("accept")),
+                ],
+                bytes,
+            )
+                .into_response(),
+            Err(error) => {
+                tracing::error!(%error, ?format, "Failed to encode response");
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::StatusCode, response::IntoResponse};
+    use serde::{Deserialize, Serialize};
+
+    use super::{Negotiated, ResponseFormat};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Sample {
+        name: String,
+        age: i16,
+    }
+
+    fn sample() -> Sample {
+        Sample {
+            name: "John Doe".into(),
+            age: 30,
+        }
+    }
+
+    async fn body_bytes(response: axum::response::Response) -> Vec<u8> {
+        axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec()
+    }
+
+    // ===================
+    // Tests: from_accept
+    // ===================
+    #[test]
+    fn test_from_accept_defaults_to_json() {
+        assert_eq!(ResponseFormat::from_accept("*/*"), ResponseFormat::Json);
+        assert_eq!(ResponseFormat::from_accept("text/html"), ResponseFormat::Json);
+        assert_eq!(ResponseFormat::from_accept(""), ResponseFormat::Json);
+    }
+
+    #[test]
+    fn test_from_accept_picks_binary_formats() {
+        assert_eq!(ResponseFormat::from_accept("application/msgpack"), ResponseFormat::MessagePack);
+        assert_eq!(ResponseFormat::from_accept("application/x-msgpack"), ResponseFormat::MessagePack);
+        assert_eq!(ResponseFormat::from_accept("application/cbor"), ResponseFormat::Cbor);
+    }
+
+    #[test]
+    fn test_from_accept_honours_quality() {
+        assert_eq!(ResponseFormat::from_accept("application/json;q=0.5, application/cbor"), ResponseFormat::Cbor);
+        assert_eq!(
+            ResponseFormat::from_accept("application/cbor;q=0.2, application/msgpack;q=0.8"),
+            ResponseFormat::MessagePack
+        );
+        assert_eq!(ResponseFormat::from_accept("application/cbor;q=0, */*"), ResponseFormat::Json);
+    }
+
+    // ===================
+    // Tests: Negotiated
+    // ===================
+    #[tokio::test]
+    async fn test_negotiated_msgpack_round_trips() {
+        let response = Negotiated(ResponseFormat::MessagePack, sample()).into_response();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "application/msgpack");
+        assert_eq!(response.headers()["vary"], "accept");
+        let decoded: Sample = rmp_serde::from_slice(&body_bytes(response).await).unwrap();
+        assert_eq!(decoded, sample());
+    }
+
+    #[tokio::test]
+    async fn test_negotiated_cbor_round_trips() {
+        let response = Negotiated(ResponseFormat::Cbor, sample()).into_response();
+
+        assert_eq!(response.headers()["content-type"], "application/cbor");
+        let decoded: Sample = ciborium::from_reader(body_bytes(response).await.as_slice()).unwrap();
+        assert_eq!(decoded, sample());
+    }
+
+    #[tokio::test]
+    async fn test_negotiated_json() {
+        let response = Negotiated(ResponseFormat::Json, sample()).into_response();
+
+        assert_eq!(response.headers()["content-type"], "application/json");
+        let decoded: Sample = serde_json::from_slice(&body_bytes(response).await).unwrap();
+        assert_eq!(decoded, sample());
+    }
+}
@@ -0,0 +1,195 @@
+//! RFC 9457 problem details (`application/problem+json`) error responses.
+//!
+//! The detail of a validation error is written in English and translated
+//! by [`localize`] once the caller's language is known.
+
+use axum::{
+    Json,
+    extract::rejection::{JsonRejection, QueryRejection},
+    http::{
+        HeaderValue, StatusCode,
+        header::{CONTENT_LANGUAGE, CONTENT_TYPE},
+    },
+    response::{IntoResponse, Response},
+};
+use hex_play_core::{Error as CoreError, i18n::Language};
+use serde::Serialize;
+
+use crate::{
+    http::error::{Error, add_retry_after},
+    reporting::Failure,
+};
+
+const PROBLEM_JSON: &str = "application/problem+json";
+
+#[derive(Debug)]
+pub(crate) struct Problem {
+    status: StatusCode,
+    detail: String,
+    /// The error behind the detail, when it has translations.
+    localizable: Option<CoreError>,
+}
+
+/// Response extension carrying the error a problem detail can be
+/// translated from.
+#[derive(Clone)]
+struct Localizable(CoreError);
+
+#[derive(Serialize)]
+struct ProblemBody<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    title: &'static str,
+    status: u16,
+    detail: &'a str,
+}
+
+impl Problem {
+    pub(crate) fn new(status: StatusCode, detail: impl Into<String>) -> Self {
+        Self {
+            status,
+            detail: detail.into(),
+            localizable: None,
+        }
+    }
+}
+
+impl From<Error> for Problem {
+    fn from(error: Error) -> Self {
+        let mut problem = Self::new(error.status(), error.to_string());
+        if let Error::Core(core_error @ CoreError::Validation(_)) = error {
+            problem.localizable = Some(core_error);
+        }
+        problem
+    }
+}
+
+impl From<hex_play_core::Error> for Problem {
+    fn from(error: hex_play_core::Error) -> Self {
+        Error::Core(error).into()
+    }
+}
+
+impl From<JsonRejection> for Problem {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(rejection.status(), rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for Problem {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::new(rejection.status(), rejection.body_text())
+    }
+}
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> Response {
+        tracing::error!(status = %self.status, detail = %self.detail, "Request failed");
+
+        let mut response = problem_response(self.status, &self.detail);
+        Failure::mark(&mut response, self.detail);
+        if let Some(error) = self.localizable {
+            response.extensions_mut().insert(Localizable(error));
+        }
+        response
+    }
+}
+
+fn problem_response(status: StatusCode, detail: &str) -> Response {
+    let body = ProblemBody {
+        kind: "about:blank",
+        title: status.canonical_reason().unwrap_or("Unknown"),
+        status: status.as_u16(),
+        detail,
+    };
+    let mut response = (status, Json(body)).into_response();
+    response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+    add_retry_after(&mut response);
+    response
+}
+
+/// Rewrites the detail of a translatable problem response in `language`
+/// and marks it with `Content-Language`. Other responses pass through.
+pub(crate) fn localize(mut response: Response, language: Language) -> Response {
+    let Some(Localizable(error)) = response.extensions_mut().remove::<Localizable>() else {
+        return response;
+    };
+    if language == Language::default() {
+        return response;
+    }
+
+    let mut localized = problem_response(response.status(), &error.localize(language));
+    localized.headers_mut().insert(CONTENT_LANGUAGE, HeaderValue::from_static(language.code()));
+    localized
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::StatusCode, response::IntoResponse};
+    use hex_play_core::{Error as CoreError, RepositoryError, i18n::Language, types::Age};
+
+    use super::{Problem, localize};
+    use crate::http::error::Error;
+
+    // ===================
+    // Tests: Problem
+    // ===================
+    #[tokio::test]
+    async fn test_problem_response_shape() {
+        let response = Problem::from(Error::Core(CoreError::RepositoryError(RepositoryError::Conflict))).into_response();
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(response.headers()["content-type"], "application/problem+json");
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["type"], "about:blank");
+        assert_eq!(body["title"], "Conflict");
+        assert_eq!(body["status"], 409);
+        assert_eq!(body["detail"], "Conflict Error");
+    }
+
+    #[test]
+    fn test_problem_not_found() {
+        let response = Problem::from(Error::NotFound).into_response();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_problem_database_unavailable() {
+        let response = Problem::from(Error::Core(CoreError::RepositoryError(RepositoryError::Unavailable("pool timed out".into())))).into_response();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()["retry-after"], "30");
+    }
+
+    #[test]
+    fn test_problem_read_only_mode() {
+        let response = Problem::from(CoreError::ReadOnlyMode).into_response();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()["retry-after"], "30");
+    }
+
+    #[tokio::test]
+    async fn test_localize_validation_problem() {
+        let response = Problem::from(Age::new(200).unwrap_err()).into_response();
+
+        let response = localize(response, Language::German);
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.headers()["content-language"], "de");
+        assert_eq!(response.headers()["content-type"], "application/problem+json");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["detail"], "Validierungsfehler: Das Alter muss zwischen 0 und 150 liegen, erhalten: 200");
+    }
+
+    #[test]
+    fn test_localize_leaves_untranslatable_problems() {
+        let response = localize(Problem::from(CoreError::ReadOnlyMode).into_response(), Language::German);
+
+        assert!(!response.headers().contains_key("content-language"));
+    }
+}
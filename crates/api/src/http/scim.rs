@@ -0,0 +1,725 @@
+//! Minimal SCIM 2.0 (RFC 7643/7644) user provisioning, so identity providers
+//! such as Okta or Azure AD can manage users.
+//!
+//! `GET /scim/v2/Users` lists users, optionally filtered by
+//! `userName eq "..."` and paged with `startIndex` and `count`. `POST`
+//! creates a user, and `GET`, `PATCH` and `DELETE` on `/scim/v2/Users/{id}`
+//! read, change and remove one. The SCIM `id` is the user token and
+//! `userName` the email. Clients send the configured token as
+//! `Authorization: Bearer`.
+//!
+//! Users here are never inactive, so a PATCH setting `active` to false
+//! deletes the user.
+
+use std::{fmt, sync::Arc};
+
+use axum::{
+    Extension, Router,
+    body::Bytes,
+    extract::{Path, Query, Request, State},
+    http::{
+        HeaderValue, StatusCode,
+        header::{AUTHORIZATION, CONTENT_TYPE, LOCATION},
+    },
+    middleware::{Next, from_fn, from_fn_with_state},
+    response::{IntoResponse, Response},
+    routing::{get, patch, post},
+};
+use chrono::{DateTime, Utc};
+use hex_play_core::{
+    CoreServices, Error as CoreError, ErrorKind, RepositoryError,
+    context::RequestContext,
+    types::{Age, Email, RedactedEmail},
+    user::{MAX_PAGE_SIZE, NewUser, PartialUserUpdate, User, UserToken},
+};
+use hex_play_utils::{secret::Secret, sensitive::Sensitive};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    http::{error::status_code_from_error_kind, limit::RouteLimits, request_context},
+    reporting::Failure,
+};
+
+const USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+const LIST_RESPONSE_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+const ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+const SCIM_CONTENT_TYPE: &str = "application/scim+json";
+
+pub(crate) fn get_routes(core_services: Arc<CoreServices>, token: Secret, limits: &RouteLimits) -> Router {
+    Router::new()
+        .route("/scim/v2/Users", limits.exports(get(list_users)).merge(limits.writes(post(create_user))))
+        .route(
+            "/scim/v2/Users/{id}",
+            limits.reads(get(get_user)).merge(limits.writes(patch(patch_user).delete(delete_user))),
+        )
+        .layer(from_fn(request_context))
+        .layer(from_fn_with_state(Arc::new(token), require_bearer_token))
+        .with_state(core_services)
+}
+
+/// Rejects requests without the configured bearer token. Digests are
+/// compared so the comparison takes the same time however much matches.
+async fn require_bearer_token(State(token): State<Arc<Secret>>, request: Request, next: Next) -> Response {
+    let presented = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match presented {
+        Some(presented) if Sha256::digest(presented) == Sha256::digest(token.expose()) => next.run(request).await,
+        _ => ScimError::new(StatusCode::UNAUTHORIZED, None, "Missing or invalid bearer token").into_response(),
+    }
+}
+
+// ===================
+// Resources
+// ===================
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ScimUser {
+    schemas: [&'static str; 1],
+    id: String,
+    user_name: Email,
+    display_name: String,
+    name: ScimName,
+    emails: [ScimEmail; 1],
+    active: bool,
+    meta: ScimMeta,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct ScimName {
+    formatted: Option<String>,
+    #[serde(default, skip_serializing)]
+    given_name: Option<String>,
+    #[serde(default, skip_serializing)]
+    family_name: Option<String>,
+}
+
+impl ScimName {
+    /// The formatted name, or else the given and family names joined.
+    fn full_name(self) -> Option<String> {
+        self.formatted.or_else(|| {
+            let parts: Vec<_> = [self.given_name, self.family_name].into_iter().flatten().collect();
+            (!parts.is_empty()).then(|| parts.join(" "))
+        })
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct ScimEmail {
+    value: Email,
+    primary: bool,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ScimMeta {
+    resource_type: &'static str,
+    created: DateTime<Utc>,
+    last_modified: DateTime<Utc>,
+    version: String,
+    location: String,
+}
+
+impl From<User> for ScimUser {
+    fn from(user: User) -> Self {
+        let id = user.token.to_string();
+        Self {
+            schemas: [USER_SCHEMA],
+            meta: ScimMeta {
+                resource_type: "User",
+                created: user.created_at,
+                last_modified: user.updated_at,
+                version: format!("W/\"{}\"", user.version),
+                location: format!("/scim/v2/Users/{id}"),
+            },
+            id,
+            user_name: user.email.clone(),
+            display_name: user.name.clone(),
+            name: ScimName {
+                formatted: Some(user.name),
+                ..ScimName::default()
+            },
+            emails: [ScimEmail {
+                value: user.email,
+                primary: true,
+            }],
+            active: true,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ListResponse {
+    schemas: [&'static str; 1],
+    total_results: u64,
+    start_index: u64,
+    items_per_page: usize,
+    #[serde(rename = "Resources")]
+    resources: Vec<ScimUser>,
+}
+
+/// Responds with `body` as `application/scim+json`.
+fn scim_response(status: StatusCode, body: &impl Serialize) -> Response {
+    let body = serde_json::to_vec(body).expect("SCIM resources serialize to JSON");
+    (status, [(CONTENT_TYPE, HeaderValue::from_static(SCIM_CONTENT_TYPE))], body).into_response()
+}
+
+// ===================
+// Errors
+// ===================
+
+/// A SCIM error response (RFC 7644, section 3.12).
+#[derive(Debug)]
+struct ScimError {
+    status: StatusCode,
+    scim_type: Option<&'static str>,
+    detail: String,
+}
+
+impl ScimError {
+    fn new(status: StatusCode, scim_type: Option<&'static str>, detail: impl Into<String>) -> Self {
+        Self {
+            status,
+            scim_type,
+            detail: detail.into(),
+        }
+    }
+
+    fn invalid_value(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, Some("invalidValue"), detail)
+    }
+
+    fn not_found() -> Self {
+        Self::new(StatusCode::NOT_FOUND, None, "User not found")
+    }
+}
+
+impl From<CoreError> for ScimError {
+    fn from(error: CoreError) -> Self {
+        match &error {
+            // The only unique column a client can collide on is the email.
+            CoreError::RepositoryError(RepositoryError::Constraint(_)) => Self::new(StatusCode::CONFLICT, Some("uniqueness"), error.to_string()),
+            _ if error.kind() == ErrorKind::InvalidInput => Self::invalid_value(error.to_string()),
+            _ => Self::new(status_code_from_error_kind(error.kind()), None, error.to_string()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScimErrorBody<'a> {
+    schemas: [&'static str; 1],
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scim_type: Option<&'static str>,
+    detail: &'a str,
+}
+
+impl IntoResponse for ScimError {
+    fn into_response(self) -> Response {
+        tracing::error!(status = %self.status, detail = %self.detail, "SCIM request failed");
+        let mut response = scim_response(
+            self.status,
+            &ScimErrorBody {
+                schemas: [ERROR_SCHEMA],
+                status: self.status.as_u16().to_string(),
+                scim_type: self.scim_type,
+                detail: &self.detail,
+            },
+        );
+        Failure::mark(&mut response, self.detail);
+        response
+    }
+}
+
+/// Parses a JSON body. Clients send `application/scim+json`, which axum's
+/// `Json` extractor would refuse.
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, ScimError> {
+    serde_json::from_slice(body).map_err(|e| ScimError::new(StatusCode::BAD_REQUEST, Some("invalidSyntax"), e.to_string()))
+}
+
+// ===================
+// Handlers
+// ===================
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListQuery {
+    filter: Option<String>,
+    start_index: Option<u64>,
+    count: Option<u64>,
+}
+
+/// The span of `list_users` records the query, so the email a filter names
+/// is masked, and a filter that can't be parsed is left out altogether.
+impl fmt::Debug for ListQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let filter = self.filter.as_deref().map(|filter| match parse_user_name_filter(filter) {
+            Some(user_name) => format!("userName eq \"{}\"", RedactedEmail::new(user_name)),
+            None => format!("{:?}", Sensitive::new(filter)),
+        });
+        f.debug_struct("ListQuery")
+            .field("filter", &filter)
+            .field("start_index", &self.start_index)
+            .field("count", &self.count)
+            .finish()
+    }
+}
+
+/// The email in a `userName eq "..."` filter, the only one supported.
+fn parse_user_name_filter(filter: &str) -> Option<&str> {
+    let mut parts = filter.trim().splitn(3, ' ');
+    let (attribute, operator, value) = (parts.next()?, parts.next()?, parts.next()?);
+    if !attribute.eq_ignore_ascii_case("userName") || !operator.eq_ignore_ascii_case("eq") {
+        return None;
+    }
+    value.strip_prefix('"')?.strip_suffix('"')
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context))]
+async fn list_users(
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    Query(query): Query<ListQuery>,
+) -> Result<Response, ScimError> {
+    let start_index = query.start_index.unwrap_or(1).max(1);
+    let max_count = core_services.page_size_cap.get();
+    let count = query.count.unwrap_or(max_count).min(max_count);
+
+    let (total_results, users) = match &query.filter {
+        Some(filter) => {
+            let user_name = parse_user_name_filter(filter)
+                .ok_or_else(|| ScimError::new(StatusCode::BAD_REQUEST, Some("invalidFilter"), "Only `userName eq \"...\"` is supported"))?;
+            let users: Vec<User> = match Email::new(user_name) {
+                Ok(email) => core_services.user_service.find_by_email(&context, email).await?.into_iter().collect(),
+                // No user has a userName that is not an email.
+                Err(_) => Vec::new(),
+            };
+            let users: Vec<User> = users.into_iter().skip((start_index - 1) as usize).take(count as usize).collect();
+            (users.len() as u64, users)
+        }
+        None => (
+            core_services.user_service.count_users(&context).await?,
+            page_of_users(&core_services, &context, start_index - 1, count).await?,
+        ),
+    };
+
+    Ok(scim_response(
+        StatusCode::OK,
+        &ListResponse {
+            schemas: [LIST_RESPONSE_SCHEMA],
+            total_results,
+            start_index,
+            items_per_page: users.len(),
+            resources: users.into_iter().map(Into::into).collect(),
+        },
+    ))
+}
+
+/// Up to `count` users after skipping `offset`, in id order. SCIM pages by
+/// offset while users are listed by id cursor, so the skipped users are
+/// walked past a page at a time.
+async fn page_of_users(core_services: &CoreServices, context: &RequestContext, mut offset: u64, count: u64) -> Result<Vec<User>, CoreError> {
+    let mut users = Vec::new();
+    let mut cursor = None;
+    while (users.len() as u64) < count {
+        let page = core_services.user_service.list_users(context, cursor, Some(MAX_PAGE_SIZE), None).await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        cursor = Some(last.id);
+        let full = page.len() as u64 == MAX_PAGE_SIZE;
+
+        let skipped = offset.min(page.len() as u64);
+        offset -= skipped;
+        let wanted = (count as usize).saturating_sub(users.len());
+        users.extend(page.into_iter().skip(skipped as usize).take(wanted));
+        if !full {
+            break;
+        }
+    }
+    Ok(users)
+}
+
+async fn find_user(core_services: &CoreServices, context: &RequestContext, id: &str) -> Result<User, ScimError> {
+    // Ids that are not tokens cannot name a user.
+    let token: UserToken = id.parse().map_err(|_| ScimError::not_found())?;
+    core_services.user_service.find_by_token(context, token).await?.ok_or_else(ScimError::not_found)
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context))]
+async fn get_user(
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    Path(id): Path<String>,
+) -> Result<Response, ScimError> {
+    let user = find_user(&core_services, &context, &id).await?;
+    Ok(scim_response(StatusCode::OK, &ScimUser::from(user)))
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CreateUserRequest {
+    user_name: Email,
+    display_name: Option<String>,
+    #[serde(default)]
+    name: ScimName,
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context, body))]
+async fn create_user(
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    body: Bytes,
+) -> Result<Response, ScimError> {
+    let request: CreateUserRequest = parse_body(&body)?;
+    let name = request
+        .display_name
+        .or_else(|| request.name.full_name())
+        .unwrap_or_else(|| request.user_name.to_string());
+    let new_user = NewUser {
+        name,
+        email: request.user_name,
+        age: Age::default(),
+    };
+
+    let user = ScimUser::from(core_services.user_service.add_user(&context, new_user).await?);
+    let location = HeaderValue::from_str(&user.meta.location).expect("user location is a valid header value");
+    let mut response = scim_response(StatusCode::CREATED, &user);
+    response.headers_mut().insert(LOCATION, location);
+    Ok(response)
+}
+
+#[derive(Deserialize, Debug)]
+struct PatchRequest {
+    #[serde(rename = "Operations")]
+    operations: Vec<PatchOperation>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PatchOperation {
+    op: String,
+    path: Option<String>,
+    #[serde(default)]
+    value: Value,
+}
+
+/// What a PATCH asks for: field changes, and whether to deactivate.
+#[derive(Debug, Default)]
+struct PatchChanges {
+    update: PartialUserUpdate,
+    deactivate: bool,
+}
+
+impl PatchChanges {
+    fn apply(&mut self, operation: PatchOperation) -> Result<(), ScimError> {
+        if !operation.op.eq_ignore_ascii_case("add") && !operation.op.eq_ignore_ascii_case("replace") {
+            return Err(ScimError::new(
+                StatusCode::BAD_REQUEST,
+                Some("invalidPath"),
+                format!("Unsupported operation `{}`", operation.op),
+            ));
+        }
+        match operation.path {
+            Some(path) => self.set(&path, operation.value),
+            None => match operation.value {
+                Value::Object(attributes) => attributes.into_iter().try_for_each(|(path, value)| self.set(&path, value)),
+                _ => Err(ScimError::invalid_value("Operations without a path need an object value")),
+            },
+        }
+    }
+
+    fn set(&mut self, path: &str, value: Value) -> Result<(), ScimError> {
+        let text = |value: Value| match value {
+            Value::String(text) => Ok(text),
+            _ => Err(ScimError::invalid_value(format!("`{path}` must be a string"))),
+        };
+        match path {
+            "userName" => self.update.email = Some(Email::new(text(value)?)?),
+            "displayName" | "name.formatted" => self.update.name = Some(text(value)?),
+            "name" => {
+                let name: ScimName = serde_json::from_value(value).map_err(|e| ScimError::invalid_value(e.to_string()))?;
+                if let Some(name) = name.full_name() {
+                    self.update.name = Some(name);
+                }
+            }
+            "active" => match value {
+                Value::Bool(active) => self.deactivate = !active,
+                _ => return Err(ScimError::invalid_value("`active` must be a boolean")),
+            },
+            _ => {
+                return Err(ScimError::new(
+                    StatusCode::BAD_REQUEST,
+                    Some("invalidPath"),
+                    format!("Unsupported path `{path}`"),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context, body))]
+async fn patch_user(
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    Path(id): Path<String>,
+    body: Bytes,
+) -> Result<Response, ScimError> {
+    let request: PatchRequest = parse_body(&body)?;
+    let mut changes = PatchChanges::default();
+    for operation in request.operations {
+        changes.apply(operation)?;
+    }
+
+    let user = find_user(&core_services, &context, &id).await?;
+    if changes.deactivate {
+        core_services.user_service.delete_user(&context, user.id).await?;
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+    let user = if changes.update.is_empty() {
+        user
+    } else {
+        core_services.user_service.update_user_partial(&context, user.id, changes.update).await?
+    };
+    Ok(scim_response(StatusCode::OK, &ScimUser::from(user)))
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context))]
+async fn delete_user(
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ScimError> {
+    let user = find_user(&core_services, &context, &id).await?;
+    core_services.user_service.delete_user(&context, user.id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, StatusCode, header::CONTENT_TYPE},
+    };
+    use hex_play_core::{
+        Error, RepositoryError,
+        test_support::{MockUserService, create_arc_core_services_with_mock},
+        user::User,
+    };
+    use hex_play_utils::secret::Secret;
+    use tower::ServiceExt;
+
+    use super::{ListQuery, get_routes, parse_user_name_filter};
+    use crate::http::limit::RouteLimits;
+
+    const TOKEN: &str = "scim-token";
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn create_test_app(mock: MockUserService) -> Router {
+        get_routes(create_arc_core_services_with_mock(mock), Secret::new(TOKEN), &RouteLimits::default())
+    }
+
+    fn request(method: &str, uri: &str, body: Option<&str>) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("authorization", format!("Bearer {TOKEN}"))
+            .header(CONTENT_TYPE, "application/scim+json")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap()
+    }
+
+    async fn body_to_json(body: Body) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.expect("failed to read response body");
+        serde_json::from_slice(&bytes).expect("response body must be JSON")
+    }
+
+    // ===================
+    // Tests: authentication
+    // ===================
+    #[tokio::test]
+    async fn test_missing_token_is_unauthorized() {
+        let app = create_test_app(MockUserService::new());
+
+        let response = app
+            .oneshot(Request::builder().uri("/scim/v2/Users").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(body_to_json(response.into_body()).await["status"], "401");
+    }
+
+    // ===================
+    // Tests: GET /scim/v2/Users
+    // ===================
+    #[tokio::test]
+    async fn test_list_users_filtered_by_user_name() {
+        let user = User::fake(1, "Jane", "jane@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_email().return_const(Ok(Some(user)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(request("GET", "/scim/v2/Users?filter=userName%20eq%20%22jane@example.com%22", None))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/scim+json");
+        let json = body_to_json(response.into_body()).await;
+        assert_eq!(json["totalResults"], 1);
+        assert_eq!(json["Resources"][0]["userName"], "jane@example.com");
+        assert_eq!(json["Resources"][0]["displayName"], "Jane");
+    }
+
+    #[tokio::test]
+    async fn test_list_users_pages_by_start_index() {
+        let users: Vec<_> = (1..=3)
+            .map(|id| User::fake(id, format!("User {id}"), format!("user{id}@example.com")))
+            .collect();
+        let mut mock = MockUserService::new();
+        mock.expect_count_users().return_const(Ok(3));
+        mock.expect_list_users().return_const(Ok(users));
+        let app = create_test_app(mock);
+
+        let response = app.oneshot(request("GET", "/scim/v2/Users?startIndex=2&count=1", None)).await.unwrap();
+
+        let json = body_to_json(response.into_body()).await;
+        assert_eq!(json["totalResults"], 3);
+        assert_eq!(json["startIndex"], 2);
+        assert_eq!(json["itemsPerPage"], 1);
+        assert_eq!(json["Resources"][0]["userName"], "user2@example.com");
+    }
+
+    #[test]
+    fn test_parse_user_name_filter() {
+        assert_eq!(parse_user_name_filter(r#"userName eq "jane@example.com""#), Some("jane@example.com"));
+        assert_eq!(parse_user_name_filter(r#"USERNAME EQ "jane@example.com""#), Some("jane@example.com"));
+        assert_eq!(parse_user_name_filter(r#"displayName eq "Jane""#), None);
+        assert_eq!(parse_user_name_filter(r#"userName sw "jane""#), None);
+    }
+
+    #[test]
+    fn test_list_query_debug_masks_user_name() {
+        let query = |filter: &str| ListQuery {
+            filter: Some(filter.to_string()),
+            start_index: Some(1),
+            count: None,
+        };
+
+        assert_eq!(
+            format!("{:?}", query(r#"userName eq "jane@example.com""#)),
+            r#"ListQuery { filter: Some("userName eq \"j***@example.com\""), start_index: Some(1), count: None }"#
+        );
+        assert!(!format!("{:?}", query(r#"emails eq "jane@example.com""#)).contains("jane"));
+    }
+
+    // ===================
+    // Tests: POST /scim/v2/Users
+    // ===================
+    #[tokio::test]
+    async fn test_create_user() {
+        let user = User::fake(1, "Jane Doe", "jane@example.com");
+        let token = user.token.to_string();
+        let mut mock = MockUserService::new();
+        mock.expect_add_user().return_const(Ok(user));
+        let app = create_test_app(mock);
+        let body = r#"{"schemas":["urn:ietf:params:scim:schemas:core:2.0:User"],"userName":"jane@example.com","name":{"givenName":"Jane","familyName":"Doe"}}"#;
+
+        let response = app.oneshot(request("POST", "/scim/v2/Users", Some(body))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()["location"], format!("/scim/v2/Users/{token}"));
+        assert_eq!(body_to_json(response.into_body()).await["id"], token);
+    }
+
+    #[tokio::test]
+    async fn test_create_duplicate_user_is_uniqueness_conflict() {
+        let error = Error::RepositoryError(RepositoryError::Constraint("duplicate email".into()));
+        let mut mock = MockUserService::new();
+        mock.expect_add_user().return_const(Err(error));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(request("POST", "/scim/v2/Users", Some(r#"{"userName":"jane@example.com"}"#)))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(body_to_json(response.into_body()).await["scimType"], "uniqueness");
+    }
+
+    // ===================
+    // Tests: PATCH /scim/v2/Users/{id}
+    // ===================
+    #[tokio::test]
+    async fn test_patch_replaces_display_name() {
+        let user = User::fake(1, "Jane", "jane@example.com");
+        let token = user.token.to_string();
+        let updated = User::fake(1, "Janet", "jane@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_token().return_const(Ok(Some(user)));
+        mock.expect_update_user_partial().return_const(Ok(updated));
+        let app = create_test_app(mock);
+        let body = r#"{"schemas":["urn:ietf:params:scim:api:messages:2.0:PatchOp"],"Operations":[{"op":"Replace","value":{"displayName":"Janet"}}]}"#;
+
+        let response = app.oneshot(request("PATCH", &format!("/scim/v2/Users/{token}"), Some(body))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_to_json(response.into_body()).await["displayName"], "Janet");
+    }
+
+    #[tokio::test]
+    async fn test_patch_deactivating_deletes_user() {
+        let user = User::fake(1, "Jane", "jane@example.com");
+        let token = user.token.to_string();
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_token().return_const(Ok(Some(user.clone())));
+        mock.expect_delete_user().return_const(Ok(user));
+        let app = create_test_app(mock);
+        let body = r#"{"Operations":[{"op":"replace","path":"active","value":false}]}"#;
+
+        let response = app.oneshot(request("PATCH", &format!("/scim/v2/Users/{token}"), Some(body))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_patch_unsupported_path_is_bad_request() {
+        let app = create_test_app(MockUserService::new());
+        let body = r#"{"Operations":[{"op":"replace","path":"title","value":"Boss"}]}"#;
+
+        let response = app.oneshot(request("PATCH", "/scim/v2/Users/anything", Some(body))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(body_to_json(response.into_body()).await["scimType"], "invalidPath");
+    }
+
+    // ===================
+    // Tests: GET and DELETE /scim/v2/Users/{id}
+    // ===================
+    #[tokio::test]
+    async fn test_unknown_id_is_not_found() {
+        let app = create_test_app(MockUserService::new());
+
+        let response = app.oneshot(request("DELETE", "/scim/v2/Users/not-a-token", None)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            body_to_json(response.into_body()).await["schemas"][0],
+            "urn:ietf:params:scim:api:messages:2.0:Error"
+        );
+    }
+}
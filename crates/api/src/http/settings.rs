@@ -0,0 +1,280 @@
+//! Settings changed while the server runs, without a restart.
+//!
+//! `GET /admin/v1/log-level` returns the log filter in effect as
+//! `{"filter": "info,hex_play_api=debug"}`, in `RUST_LOG` syntax; `PUT` with
+//! the same body replaces it.
+//!
+//! `GET /admin/v1/settings` returns the settings that may change at runtime:
+//! maintenance mode, the page size cap of the listings and the concurrency
+//! limits. `PATCH` with some of them changes those and leaves the rest;
+//! anything else is rejected, as it only takes effect on restart.
+
+use std::sync::Arc;
+
+use axum::{Json, Router, extract::State, routing::get};
+use hex_play_core::CoreServices;
+use serde::{Deserialize, Serialize};
+
+use crate::http::{
+    error::Error,
+    limit::{ConcurrencyLimits, RouteLimits},
+};
+
+#[derive(Clone)]
+struct SettingsState {
+    core_services: Arc<CoreServices>,
+    limits: RouteLimits,
+}
+
+pub(crate) fn get_routes(core_services: Arc<CoreServices>, limits: &RouteLimits) -> Router {
+    Router::new()
+        .route("/admin/v1/log-level", get(get_log_level).put(set_log_level))
+        .route("/admin/v1/settings", get(get_settings).patch(update_settings))
+        .with_state(SettingsState {
+            core_services,
+            limits: limits.clone(),
+        })
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct LogLevel {
+    filter: String,
+}
+
+#[tracing::instrument(level = "trace", skip(state))]
+async fn get_log_level(State(state): State<SettingsState>) -> Json<LogLevel> {
+    Json(LogLevel {
+        filter: state.core_services.log_filter.current(),
+    })
+}
+
+#[tracing::instrument(level = "trace", skip(state))]
+async fn set_log_level(State(state): State<SettingsState>, Json(request): Json<LogLevel>) -> Result<Json<LogLevel>, Error> {
+    state.core_services.log_filter.set(&request.filter)?;
+    Ok(Json(request))
+}
+
+#[derive(Serialize, Debug)]
+struct Settings {
+    maintenance_mode: bool,
+    max_page_size: u64,
+    concurrency_limits: ConcurrencyLimits,
+}
+
+impl Settings {
+    fn current(state: &SettingsState) -> Self {
+        Self {
+            maintenance_mode: state.core_services.maintenance_mode.is_enabled(),
+            max_page_size: state.core_services.page_size_cap.get(),
+            concurrency_limits: state.limits.current(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct SettingsUpdate {
+    maintenance_mode: Option<bool>,
+    max_page_size: Option<u64>,
+    concurrency_limits: Option<ConcurrencyLimitsUpdate>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ConcurrencyLimitsUpdate {
+    reads: Option<usize>,
+    writes: Option<usize>,
+    exports: Option<usize>,
+}
+
+#[tracing::instrument(level = "trace", skip(state))]
+async fn get_settings(State(state): State<SettingsState>) -> Json<Settings> {
+    Json(Settings::current(&state))
+}
+
+#[tracing::instrument(level = "trace", skip(state))]
+async fn update_settings(State(state): State<SettingsState>, Json(update): Json<SettingsUpdate>) -> Result<Json<Settings>, Error> {
+    // The only setting that can be refused goes first, so a refused update
+    // changes nothing.
+    if let Some(max_page_size) = update.max_page_size {
+        state.core_services.page_size_cap.set(max_page_size)?;
+    }
+    if let Some(enabled) = update.maintenance_mode {
+        state.core_services.maintenance_mode.set_enabled(enabled);
+    }
+    if let Some(limits) = update.concurrency_limits {
+        let current = state.limits.current();
+        state.limits.resize(&ConcurrencyLimits {
+            reads: limits.reads.unwrap_or(current.reads),
+            writes: limits.writes.unwrap_or(current.writes),
+            exports: limits.exports.unwrap_or(current.exports),
+        });
+    }
+
+    Ok(Json(Settings::current(&state)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use hex_play_core::{
+        CoreServices, Error,
+        test_support::{MockLogFilter, MockUserService, create_core_services_with_mock},
+        user::MAX_PAGE_SIZE,
+    };
+    use mockall::predicate::eq;
+    use serde_json::{Value, json};
+    use tower::ServiceExt;
+
+    use super::get_routes;
+    use crate::http::limit::RouteLimits;
+
+    // ===================
+    // Helpers
+    // ===================
+    fn create_test_app(log_filter: MockLogFilter) -> (Arc<CoreServices>, RouteLimits, Router) {
+        let core_services = Arc::new(CoreServices {
+            log_filter: Arc::new(log_filter),
+            ..create_core_services_with_mock(MockUserService::new())
+        });
+        let limits = RouteLimits::default();
+        (core_services.clone(), limits.clone(), get_routes(core_services, &limits))
+    }
+
+    fn json_request(method: &str, uri: &str, body: Value) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    async fn body_to_json(body: Body) -> Value {
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    // ===================
+    // Tests: /admin/v1/log-level
+    // ===================
+    #[tokio::test]
+    async fn test_get_log_level() {
+        let mut log_filter = MockLogFilter::new();
+        log_filter.expect_current().return_const("info".to_string());
+        let (_, _, app) = create_test_app(log_filter);
+
+        let response = app
+            .oneshot(Request::builder().uri("/admin/v1/log-level").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_to_json(response.into_body()).await, json!({"filter": "info"}));
+    }
+
+    #[tokio::test]
+    async fn test_set_log_level() {
+        let mut log_filter = MockLogFilter::new();
+        log_filter.expect_set().with(eq("info,hex_play_api=debug")).times(1).returning(|_| Ok(()));
+        let (_, _, app) = create_test_app(log_filter);
+
+        let response = app
+            .oneshot(json_request("PUT", "/admin/v1/log-level", json!({"filter": "info,hex_play_api=debug"})))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_to_json(response.into_body()).await, json!({"filter": "info,hex_play_api=debug"}));
+    }
+
+    #[tokio::test]
+    async fn test_set_log_level_rejects_invalid_filter() {
+        let mut log_filter = MockLogFilter::new();
+        log_filter.expect_set().returning(|_| Err(Error::Validation("invalid log filter".into())));
+        let (_, _, app) = create_test_app(log_filter);
+
+        let response = app.oneshot(json_request("PUT", "/admin/v1/log-level", json!({"filter": "=="}))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    // ===================
+    // Tests: /admin/v1/settings
+    // ===================
+    #[tokio::test]
+    async fn test_get_settings() {
+        let (_, _, app) = create_test_app(MockLogFilter::new());
+
+        let response = app
+            .oneshot(Request::builder().uri("/admin/v1/settings").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            body_to_json(response.into_body()).await,
+            json!({
+                "maintenance_mode": false,
+                "max_page_size": MAX_PAGE_SIZE,
+                "concurrency_limits": {"reads": 64, "writes": 16, "exports": 4},
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_settings_changes_only_given_settings() {
+        let (core_services, limits, app) = create_test_app(MockLogFilter::new());
+
+        let response = app
+            .oneshot(json_request(
+                "PATCH",
+                "/admin/v1/settings",
+                json!({"maintenance_mode": true, "max_page_size": 10, "concurrency_limits": {"exports": 2}}),
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(core_services.maintenance_mode.is_enabled());
+        assert_eq!(core_services.page_size_cap.get(), 10);
+        assert_eq!(limits.current().exports, 2);
+        assert_eq!(limits.current().reads, 64);
+    }
+
+    #[tokio::test]
+    async fn test_update_settings_rejects_invalid_page_size_without_changes() {
+        let (core_services, _, app) = create_test_app(MockLogFilter::new());
+
+        let response = app
+            .oneshot(json_request(
+                "PATCH",
+                "/admin/v1/settings",
+                json!({"maintenance_mode": true, "max_page_size": 0}),
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(!core_services.maintenance_mode.is_enabled());
+        assert_eq!(core_services.page_size_cap.get(), MAX_PAGE_SIZE);
+    }
+
+    #[tokio::test]
+    async fn test_update_settings_rejects_settings_needing_restart() {
+        let (_, _, app) = create_test_app(MockLogFilter::new());
+
+        let response = app
+            .oneshot(json_request("PATCH", "/admin/v1/settings", json!({"listen_addrs": ["0.0.0.0:3000"]})))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
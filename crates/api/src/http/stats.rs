@@ -0,0 +1,185 @@
+//! Aggregate figures for dashboards.
+//!
+//! `GET /api/v1/stats/users` counts users by activity status, signups on
+//! each of the last 30 days and users per age bucket. Signups come from
+//! daily rollups refreshed in the background, so the latest may lag by a
+//! few minutes.
+
+use std::sync::Arc;
+
+use axum::{Extension, Json, Router, extract::State, middleware::from_fn, routing::get};
+use chrono::NaiveDate;
+use hex_play_core::{
+    CoreServices,
+    context::RequestContext,
+    user::{AgeBucketCount, DailySignups, StatusCounts, UserStats},
+};
+use serde::Serialize;
+
+use crate::http::{error::Error, limit::RouteLimits, request_context};
+
+pub(crate) fn get_routes(core_services: Arc<CoreServices>, limits: &RouteLimits) -> Router {
+    Router::new()
+        .route("/api/v1/stats/users", limits.reads(get(get_user_stats)))
+        .layer(from_fn(request_context))
+        .with_state(core_services)
+}
+
+#[derive(Serialize, Debug)]
+struct UserStatsResponse {
+    total: u64,
+    by_status: StatusCountsResponse,
+    signups_per_day: Vec<DailySignupsResponse>,
+    age_distribution: Vec<AgeBucketResponse>,
+}
+
+impl From<UserStats> for UserStatsResponse {
+    fn from(stats: UserStats) -> Self {
+        Self {
+            total: stats.total(),
+            by_status: stats.by_status.into(),
+            signups_per_day: stats.signups_per_day.into_iter().map(Into::into).collect(),
+            age_distribution: stats.by_age.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct StatusCountsResponse {
+    active: u64,
+    inactive: u64,
+    never_seen: u64,
+}
+
+impl From<StatusCounts> for StatusCountsResponse {
+    fn from(counts: StatusCounts) -> Self {
+        Self {
+            active: counts.active,
+            inactive: counts.inactive,
+            never_seen: counts.never_seen,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct DailySignupsResponse {
+    date: NaiveDate,
+    count: u64,
+}
+
+impl From<DailySignups> for DailySignupsResponse {
+    fn from(day: DailySignups) -> Self {
+        Self {
+            date: day.date,
+            count: day.count,
+        }
+    }
+}
+
+/// Users aged `min` to `max`, both included.
+#[derive(Serialize, Debug)]
+struct AgeBucketResponse {
+    min: i16,
+    max: i16,
+    count: u64,
+}
+
+impl From<AgeBucketCount> for AgeBucketResponse {
+    fn from(bucket: AgeBucketCount) -> Self {
+        Self {
+            min: *bucket.ages.start(),
+            max: *bucket.ages.end(),
+            count: bucket.count,
+        }
+    }
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context))]
+async fn get_user_stats(
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+) -> Result<Json<UserStatsResponse>, Error> {
+    let stats = core_services.user_service.stats(&context).await.map_err(Error::Core)?;
+    Ok(Json(stats.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use chrono::NaiveDate;
+    use hex_play_core::{
+        Error,
+        test_support::{MockUserService, create_arc_core_services_with_mock},
+        user::{AGE_BUCKETS, AgeBucketCount, DailySignups, StatusCounts, UserStats},
+    };
+    use tower::ServiceExt;
+
+    use super::get_routes;
+    use crate::http::limit::RouteLimits;
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn create_test_app(mock: MockUserService) -> Router {
+        get_routes(create_arc_core_services_with_mock(mock), &RouteLimits::default())
+    }
+
+    async fn body_to_string(body: Body) -> String {
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.expect("failed to read response body");
+        String::from_utf8(bytes.to_vec()).expect("response body must be valid UTF-8")
+    }
+
+    // ===================
+    // Tests: GET /api/v1/stats/users
+    // ===================
+    #[tokio::test]
+    async fn test_get_user_stats() {
+        let stats = UserStats {
+            by_status: StatusCounts {
+                active: 2,
+                inactive: 1,
+                never_seen: 0,
+            },
+            signups_per_day: vec![DailySignups {
+                date: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+                count: 3,
+            }],
+            by_age: vec![AgeBucketCount {
+                ages: AGE_BUCKETS[0].clone(),
+                count: 3,
+            }],
+        };
+        let mut mock = MockUserService::new();
+        mock.expect_stats().return_const(Ok(stats));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().uri("/api/v1/stats/users").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            body_to_string(response.into_body()).await,
+            r#"{"total":3,"by_status":{"active":2,"inactive":1,"never_seen":0},"signups_per_day":[{"date":"2025-01-01","count":3}],"age_distribution":[{"min":0,"max":17,"count":3}]}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_user_stats_propagates_error() {
+        let mut mock = MockUserService::new();
+        mock.expect_stats().return_const(Err(Error::Infrastructure("database down".into())));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().uri("/api/v1/stats/users").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert!(response.status().is_server_error());
+    }
+}
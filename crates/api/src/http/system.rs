@@ -0,0 +1,158 @@
+//! How the service and what it depends on are doing.
+//!
+//! `GET /api/v1/system/status` checks the database and its schema, the
+//! cache, the job queue and the server subsystems, the same report as the
+//! gRPC `SystemService.Status`. It answers 503 while any component is down,
+//! so load balancers can take the instance out of rotation.
+
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use hex_play_core::{
+    CoreServices,
+    status::{ComponentStatus, Health, SystemStatus},
+};
+use serde::Serialize;
+
+use crate::http::limit::RouteLimits;
+
+pub(crate) fn get_routes(core_services: Arc<CoreServices>, limits: &RouteLimits) -> Router {
+    Router::new()
+        .route("/api/v1/system/status", limits.reads(get(get_status)))
+        .with_state(core_services)
+}
+
+#[derive(Serialize, Debug)]
+struct StatusResponse {
+    health: &'static str,
+    components: Vec<ComponentResponse>,
+}
+
+impl From<SystemStatus> for StatusResponse {
+    fn from(status: SystemStatus) -> Self {
+        Self {
+            health: status.health().as_str(),
+            components: status.components.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct ComponentResponse {
+    name: String,
+    health: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+impl From<ComponentStatus> for ComponentResponse {
+    fn from(component: ComponentStatus) -> Self {
+        Self {
+            name: component.name,
+            health: component.health.as_str(),
+            latency_ms: component.latency.map(|latency| latency.as_micros() as f64 / 1000.0),
+            detail: component.detail,
+        }
+    }
+}
+
+#[tracing::instrument(level = "trace", skip(core_services))]
+async fn get_status(State(core_services): State<Arc<CoreServices>>) -> Response {
+    let status = core_services.status_service.status().await;
+    let code = match status.health() {
+        Health::Down => StatusCode::SERVICE_UNAVAILABLE,
+        Health::Up | Health::Disabled => StatusCode::OK,
+    };
+    (code, Json(StatusResponse::from(status))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use hex_play_core::{
+        CoreServices,
+        status::{ComponentStatus, Health, MockStatusService, SystemStatus},
+        test_support::{MockUserService, create_core_services_with_mock},
+    };
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::get_routes;
+    use crate::http::limit::RouteLimits;
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn create_test_app(components: Vec<ComponentStatus>) -> Router {
+        let mut status_service = MockStatusService::new();
+        status_service.expect_status().times(1).return_const(SystemStatus { components });
+        let core_services = Arc::new(CoreServices {
+            status_service: Arc::new(status_service),
+            ..create_core_services_with_mock(MockUserService::new())
+        });
+        get_routes(core_services, &RouteLimits::default())
+    }
+
+    async fn get_status(app: Router) -> (StatusCode, serde_json::Value) {
+        let response = app
+            .oneshot(Request::builder().uri("/api/v1/system/status").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    // ===================
+    // Tests: GET /api/v1/system/status
+    // ===================
+    #[tokio::test]
+    async fn test_get_status_reports_components() {
+        let app = create_test_app(vec![
+            ComponentStatus {
+                latency: Some(Duration::from_micros(1500)),
+                ..ComponentStatus::new("database", Health::Up, "reachable")
+            },
+            ComponentStatus::new("job_queue", Health::Disabled, "background jobs are not configured"),
+        ]);
+
+        let (status, body) = get_status(app).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            json!({
+                "health": "up",
+                "components": [
+                    {"name": "database", "health": "up", "latency_ms": 1.5, "detail": "reachable"},
+                    {"name": "job_queue", "health": "disabled", "detail": "background jobs are not configured"},
+                ],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_status_is_unavailable_with_a_component_down() {
+        let app = create_test_app(vec![ComponentStatus::new("database", Health::Down, "connection refused")]);
+
+        let (status, body) = get_status(app).await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["health"], "down");
+        assert_eq!(body["components"][0]["detail"], "connection refused");
+    }
+}
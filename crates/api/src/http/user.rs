@@ -1,35 +1,52 @@
-use std::sync::Arc;
+//! User HTTP API.
+//!
+//! Each API version lives in its own module with its own response DTOs and
+//! routes; request parsing and response plumbing shared between versions
+//! lives here.
+
+use std::{fmt::Debug, sync::Arc};
 
 use axum::{
-    Json, Router,
-    extract::{Path, Query, State},
-    http::StatusCode,
-    routing::{get, post},
+    Router,
+    http::{
+        HeaderMap, HeaderValue, StatusCode,
+        header::{ALLOW, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    },
+    response::{IntoResponse, Response},
 };
-use chrono::{DateTime, Utc};
+use chrono::DateTime;
 use hex_play_core::{
     CoreServices,
     types::{Age, Email},
-    user::{NewUser, PartialUserUpdate, User, UserId, UserToken},
+    user::{NewUser, PartialUserUpdate, User, UserListStamp},
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::http::{
+    limit::RouteLimits,
+    negotiate::{Negotiated, ResponseFormat},
 };
-use serde::{Deserialize, Serialize};
 
-use crate::http::error::Error;
+mod patch;
+mod v1;
+mod v2;
 
-pub(crate) fn get_routes(core_services: Arc<CoreServices>) -> Router {
-    Router::new()
-        .nest(
-            "/api/v1/user",
-            Router::new()
-                .route("/", post(create_user).get(list_users))
-                .route("/token/{token}", get(get_user_by_token))
-                .route("/{id}", get(get_user).patch(update_user).delete(delete_user)),
-        )
-        .with_state(core_services)
+#[cfg(feature = "test-support")]
+pub(crate) use v1::encode_list_users;
+
+pub(crate) fn get_routes(core_services: Arc<CoreServices>, limits: &RouteLimits) -> Router {
+    v1::get_routes(core_services.clone(), limits).merge(v2::get_routes(core_services, limits))
+}
+
+/// Answers an OPTIONS request by advertising the methods a route supports.
+/// HEAD is served by axum from the GET handler with the body stripped.
+async fn allow(methods: &'static str) -> impl IntoResponse {
+    (StatusCode::NO_CONTENT, [(ALLOW, methods)])
 }
 
 #[derive(Deserialize, Debug)]
-struct CreateUserRequest {
+pub(super) struct CreateUserRequest {
     name: String,
     email: Email,
     #[serde(default)]
@@ -46,89 +63,23 @@ impl From<CreateUserRequest> for NewUser {
     }
 }
 
-#[derive(Serialize, Debug)]
-struct UserResponse {
-    id: u64,
-    token: String,
-    name: String,
-    email: Email,
-    age: Age,
-    version: u64,
-    created_at: DateTime<Utc>,
-    updated_at: DateTime<Utc>,
-}
-
-impl From<User> for UserResponse {
-    fn from(user: User) -> Self {
-        Self {
-            id: user.id,
-            token: user.token.to_string(),
-            name: user.name,
-            email: user.email,
-            age: user.age,
-            version: user.version,
-            created_at: user.created_at,
-            updated_at: user.updated_at,
-        }
-    }
-}
-
-#[tracing::instrument(level = "trace", skip(core_services))]
-async fn create_user(
-    State(core_services): State<Arc<CoreServices>>,
-    Json(request): Json<CreateUserRequest>,
-) -> Result<(StatusCode, Json<UserResponse>), Error> {
-    let user = core_services.user_service.add_user(request.into()).await.map_err(Error::Core)?;
-    Ok((StatusCode::CREATED, Json(user.into())))
-}
-
-#[derive(Deserialize, Debug, Default)]
-pub struct FilterOptions {
-    pub start_id: Option<UserId>,
-    pub page_size: Option<u64>,
-}
-
-#[derive(Serialize, Debug)]
-pub struct ListUsersResponse {
-    users: Vec<UserResponse>,
-}
-
-#[tracing::instrument(level = "trace", skip(core_services))]
-async fn list_users(Query(opts): Query<FilterOptions>, State(core_services): State<Arc<CoreServices>>) -> Result<Json<ListUsersResponse>, Error> {
-    let users = core_services
-        .user_service
-        .list_users(opts.start_id, opts.page_size)
-        .await
-        .map_err(Error::Core)?
-        .into_iter()
-        .map(Into::into)
-        .collect();
-
-    Ok(Json(ListUsersResponse { users }))
-}
-
-#[tracing::instrument(level = "trace", skip(core_services))]
-async fn get_user(Path(id): Path<UserId>, State(core_services): State<Arc<CoreServices>>) -> Result<Json<UserResponse>, Error> {
-    let user = core_services.user_service.find_by_id(id).await.map_err(Error::Core)?.ok_or(Error::NotFound)?;
-    Ok(Json(user.into()))
-}
-
-#[tracing::instrument(level = "trace", skip(core_services))]
-async fn get_user_by_token(Path(token): Path<UserToken>, State(core_services): State<Arc<CoreServices>>) -> Result<Json<UserResponse>, Error> {
-    let user = core_services
-        .user_service
-        .find_by_token(token)
-        .await
-        .map_err(Error::Core)?
-        .ok_or(Error::NotFound)?;
-    Ok(Json(user.into()))
+/// Distinguishes an explicit `null` (`Some(None)`) from an absent field
+/// (`None`, via `#[serde(default)]`).
+pub(super) fn deserialize_nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
 #[derive(Deserialize, Debug)]
 struct UpdateUserRequest {
     name: Option<String>,
     email: Option<Email>,
-    age: Option<Age>,
+    /// `null` clears the age back to its default.
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    age: Option<Option<Age>>,
 }
 
 impl From<UpdateUserRequest> for PartialUserUpdate {
@@ -137,548 +88,56 @@ impl From<UpdateUserRequest> for PartialUserUpdate {
             name: req.name,
             email: req.email,
             age: req.age,
+            expected_version: None,
         }
     }
 }
 
-#[tracing::instrument(level = "trace", skip(core_services))]
-async fn update_user(
-    Path(id): Path<UserId>,
-    State(core_services): State<Arc<CoreServices>>,
-    Json(request): Json<UpdateUserRequest>,
-) -> Result<Json<UserResponse>, Error> {
-    let mut user = core_services.user_service.find_by_id(id).await.map_err(Error::Core)?.ok_or(Error::NotFound)?;
-
-    let update: PartialUserUpdate = request.into();
-    update.apply_to(&mut user);
+/// Format used for `Last-Modified`/`If-Modified-Since` (RFC 9110 IMF-fixdate).
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Builds a GET response for `user` rendered as `T` in `format`, honouring
+/// `If-Modified-Since`.
+///
+/// HTTP dates only have second precision, so the comparison truncates
+/// `updated_at` to whole seconds. An unparseable header is ignored.
+/// The `ETag` is derived from the user's version.
+fn conditional_user_response<T>(headers: &HeaderMap, format: ResponseFormat, user: User) -> Response
+where
+    T: From<User> + Serialize,
+{
+    let last_modified = HeaderValue::from_str(&user.updated_at.format(HTTP_DATE_FORMAT).to_string()).expect("HTTP date is a valid header value");
+    let etag = HeaderValue::from_str(&format!("\"{}\"", user.version)).expect("version is a valid header value");
+
+    let if_modified_since = headers
+        .get(IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok());
+    if let Some(since) = if_modified_since {
+        if user.updated_at.timestamp() <= since.timestamp() {
+            return (StatusCode::NOT_MODIFIED, [(LAST_MODIFIED, last_modified), (ETAG, etag)]).into_response();
+        }
+    }
 
-    let user = core_services.user_service.update_user(user).await.map_err(Error::Core)?;
-    Ok(Json(user.into()))
+    ([(LAST_MODIFIED, last_modified), (ETAG, etag)], Negotiated(format, T::from(user))).into_response()
 }
 
-#[tracing::instrument(level = "trace", skip(core_services))]
-async fn delete_user(Path(id): Path<UserId>, State(core_services): State<Arc<CoreServices>>) -> Result<Json<UserResponse>, Error> {
-    let user = core_services.user_service.delete_user(id).await.map_err(Error::Core)?;
-    Ok(Json(user.into()))
+/// A weak `ETag` for a listing of users as of `stamp`. `variant` tells the
+/// listings apart, e.g. by page and format, so each has its own.
+fn list_etag(stamp: &UserListStamp, variant: &impl Debug) -> HeaderValue {
+    let digest = Sha256::digest(format!("{stamp:?}/{variant:?}"));
+    HeaderValue::from_str(&format!("W/\"{}\"", hex::encode(&digest[..16]))).expect("hex digest is a valid header value")
 }
 
-#[cfg(test)]
-mod tests {
-    use axum::{
-        Router,
-        body::Body,
-        http::{Request, StatusCode},
-    };
-    use hex_play_core::{
-        Error, RepositoryError,
-        test_support::{MockUserService, create_arc_core_services_with_mock},
-        user::{User, UserToken},
-    };
-    use tower::ServiceExt;
-
-    use super::get_routes;
-
-    // ===================
-    // Test Helpers
-    // ===================
-    fn create_test_app(mock: MockUserService) -> Router {
-        get_routes(create_arc_core_services_with_mock(mock))
-    }
-
-    async fn body_to_string(body: Body) -> String {
-        let bytes = axum::body::to_bytes(body, usize::MAX).await.expect("failed to read response body");
-        String::from_utf8(bytes.to_vec()).expect("response body must be valid UTF-8")
-    }
-
-    // ===================
-    // Tests: POST /api/v1/user (create_user)
-    // ===================
-    #[tokio::test]
-    async fn test_create_user_success() {
-        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
-        let mock = MockUserService::default().with_add_user_result(Ok(user));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/v1/user")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"name":"John Doe","email":"john@example.com","age":30}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::CREATED);
-
-        let body = body_to_string(response.into_body()).await;
-        assert!(body.contains(r#""id":1"#));
-        assert!(body.contains(r#""name":"John Doe""#));
-        assert!(body.contains(r#""email":"john@example.com""#));
-        assert!(body.contains(r#""age":30"#));
-        assert!(body.contains(r#""version":0"#));
-        assert!(body.contains(r#""created_at":"#));
-        assert!(body.contains(r#""updated_at":"#));
-    }
-
-    #[tokio::test]
-    async fn test_create_user_without_age_defaults_to_zero() {
-        let user = User::fake(1, "John Doe", "john@example.com");
-        let mock = MockUserService::default().with_add_user_result(Ok(user));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/v1/user")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"name":"John Doe","email":"john@example.com"}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::CREATED);
-
-        let body = body_to_string(response.into_body()).await;
-        assert!(body.contains(r#""age":0"#));
-    }
-
-    #[tokio::test]
-    async fn test_create_user_invalid_json() {
-        let mock = MockUserService::default();
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/v1/user")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"name":"John Doe""#)) // Invalid JSON
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-
-        // Axum returns 400 for malformed JSON
-        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
-    }
-
-    #[tokio::test]
-    async fn test_create_user_missing_fields() {
-        let mock = MockUserService::default();
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/v1/user")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"name":"John Doe"}"#)) // Missing email
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
-    }
-
-    #[tokio::test]
-    async fn test_create_user_constraint_violation() {
-        let mock = MockUserService::default().with_add_user_result(Err(Error::RepositoryError(RepositoryError::Constraint("duplicate email".into()))));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/v1/user")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"name":"John Doe","email":"john@example.com"}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
-    }
-
-    // ===================
-    // Tests: GET /api/v1/user (list_users)
-    // ===================
-    #[tokio::test]
-    async fn test_list_users_success() {
-        let users = vec![
-            User::fake_with_age(1, "John Doe", "john@example.com", 30),
-            User::fake_with_age(2, "Jane Doe", "jane@example.com", 25),
-        ];
-        let mock = MockUserService::default().with_list_users_result(Ok(users));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(Request::builder().method("GET").uri("/api/v1/user").body(Body::empty()).unwrap())
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::OK);
-
-        let body = body_to_string(response.into_body()).await;
-        assert!(body.contains(r#""users""#));
-        assert!(body.contains(r#""name":"John Doe""#));
-        assert!(body.contains(r#""name":"Jane Doe""#));
-        assert!(body.contains(r#""age":30"#));
-        assert!(body.contains(r#""age":25"#));
-    }
-
-    #[tokio::test]
-    async fn test_list_users_empty() {
-        let mock = MockUserService::default().with_list_users_result(Ok(vec![]));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(Request::builder().method("GET").uri("/api/v1/user").body(Body::empty()).unwrap())
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::OK);
-
-        let body = body_to_string(response.into_body()).await;
-        assert!(body.contains(r#""users":[]"#));
-    }
-
-    #[tokio::test]
-    async fn test_list_users_with_pagination() {
-        let users = vec![User::fake(5, "User Five", "five@example.com")];
-        let mock = MockUserService::default().with_list_users_result(Ok(users));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("GET")
-                    .uri("/api/v1/user?start_id=5&page_size=10")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::OK);
-    }
-
-    #[tokio::test]
-    async fn test_list_users_invalid_start_id() {
-        let mock = MockUserService::default().with_list_users_result(Err(Error::InvalidId(0)));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(Request::builder().method("GET").uri("/api/v1/user?start_id=-1").body(Body::empty()).unwrap())
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
-    }
-
-    #[tokio::test]
-    async fn test_list_users_invalid_page_size() {
-        let mock = MockUserService::default().with_list_users_result(Err(Error::InvalidPageSize(0)));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(Request::builder().method("GET").uri("/api/v1/user?page_size=0").body(Body::empty()).unwrap())
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
-    }
-
-    // ===================
-    // Tests: GET /api/v1/user/{id} (get_user)
-    // ===================
-    #[tokio::test]
-    async fn test_get_user_success() {
-        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
-        let mock = MockUserService::default().with_find_by_id_result(Ok(Some(user)));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(Request::builder().method("GET").uri("/api/v1/user/1").body(Body::empty()).unwrap())
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::OK);
-
-        let body = body_to_string(response.into_body()).await;
-        assert!(body.contains(r#""id":1"#));
-        assert!(body.contains(r#""name":"John Doe""#));
-        assert!(body.contains(r#""age":30"#));
-        assert!(body.contains(r#""version":0"#));
-        assert!(body.contains(r#""created_at":"#));
-        assert!(body.contains(r#""updated_at":"#));
-    }
-
-    #[tokio::test]
-    async fn test_get_user_not_found() {
-        let mock = MockUserService::default().with_find_by_id_result(Ok(None));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(Request::builder().method("GET").uri("/api/v1/user/999").body(Body::empty()).unwrap())
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::NOT_FOUND);
-    }
-
-    #[tokio::test]
-    async fn test_get_user_invalid_id() {
-        let mock = MockUserService::default().with_find_by_id_result(Err(Error::InvalidId(0)));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(Request::builder().method("GET").uri("/api/v1/user/-1").body(Body::empty()).unwrap())
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
-    }
-
-    // ===================
-    // Tests: PATCH /api/v1/user/{id} (update_user)
-    // ===================
-    #[tokio::test]
-    async fn test_update_user_success() {
-        let existing = User::fake_with_age(1, "John Doe", "john@example.com", 30);
-        let updated = User::fake_with_age(1, "John Updated", "john@example.com", 30);
-        let mock = MockUserService::default()
-            .with_find_by_id_result(Ok(Some(existing)))
-            .with_update_user_result(Ok(updated));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("PATCH")
-                    .uri("/api/v1/user/1")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"name":"John Updated"}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::OK);
-
-        let body = body_to_string(response.into_body()).await;
-        assert!(body.contains(r#""name":"John Updated""#));
-        assert!(body.contains(r#""age":30"#));
-    }
-
-    #[tokio::test]
-    async fn test_update_user_partial_email() {
-        let existing = User::fake_with_age(1, "John Doe", "john@example.com", 25);
-        let updated = User::fake_with_age(1, "John Doe", "john.new@example.com", 25);
-        let mock = MockUserService::default()
-            .with_find_by_id_result(Ok(Some(existing)))
-            .with_update_user_result(Ok(updated));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("PATCH")
-                    .uri("/api/v1/user/1")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"email":"john.new@example.com"}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::OK);
-
-        let body = body_to_string(response.into_body()).await;
-        assert!(body.contains(r#""email":"john.new@example.com""#));
-        assert!(body.contains(r#""age":25"#));
-    }
-
-    #[tokio::test]
-    async fn test_update_user_age() {
-        let existing = User::fake_with_age(1, "John Doe", "john@example.com", 30);
-        let updated = User::fake_with_age(1, "John Doe", "john@example.com", 31);
-        let mock = MockUserService::default()
-            .with_find_by_id_result(Ok(Some(existing)))
-            .with_update_user_result(Ok(updated));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("PATCH")
-                    .uri("/api/v1/user/1")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"age":31}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::OK);
-
-        let body = body_to_string(response.into_body()).await;
-        assert!(body.contains(r#""name":"John Doe""#));
-        assert!(body.contains(r#""age":31"#));
-    }
-
-    #[tokio::test]
-    async fn test_update_user_not_found() {
-        let mock = MockUserService::default().with_find_by_id_result(Ok(None));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("PATCH")
-                    .uri("/api/v1/user/999")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"name":"Updated"}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::NOT_FOUND);
-    }
-
-    #[tokio::test]
-    async fn test_update_user_conflict() {
-        let existing = User::fake(1, "John Doe", "john@example.com");
-        let mock = MockUserService::default()
-            .with_find_by_id_result(Ok(Some(existing)))
-            .with_update_user_result(Err(Error::RepositoryError(RepositoryError::Conflict)));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("PATCH")
-                    .uri("/api/v1/user/1")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"name":"Updated"}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::CONFLICT);
-    }
-
-    // ===================
-    // Tests: DELETE /api/v1/user/{id} (delete_user)
-    // ===================
-    #[tokio::test]
-    async fn test_delete_user_success() {
-        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
-        let mock = MockUserService::default().with_delete_user_result(Ok(user));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(Request::builder().method("DELETE").uri("/api/v1/user/1").body(Body::empty()).unwrap())
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::OK);
-
-        let body = body_to_string(response.into_body()).await;
-        assert!(body.contains(r#""id":1"#));
-        assert!(body.contains(r#""name":"John Doe""#));
-        assert!(body.contains(r#""age":30"#));
-    }
-
-    #[tokio::test]
-    async fn test_delete_user_not_found() {
-        let mock = MockUserService::default().with_delete_user_result(Err(Error::RepositoryError(RepositoryError::NotFound)));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(Request::builder().method("DELETE").uri("/api/v1/user/999").body(Body::empty()).unwrap())
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::NOT_FOUND);
-    }
-
-    // ===================
-    // Tests: GET /api/v1/user/token/{token} (get_user_by_token)
-    // ===================
-    #[tokio::test]
-    async fn test_get_user_by_token_success() {
-        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
-        let token = user.token;
-        let mock = MockUserService::default().with_find_by_token_result(Ok(Some(user)));
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("GET")
-                    .uri(format!("/api/v1/user/token/{token}"))
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::OK);
-
-        let body = body_to_string(response.into_body()).await;
-        assert!(body.contains(r#""id":1"#));
-        assert!(body.contains(r#""name":"John Doe""#));
-        assert!(body.contains(&format!(r#""token":"{token}""#)));
-        assert!(body.contains(r#""age":30"#));
-    }
-
-    #[tokio::test]
-    async fn test_get_user_by_token_not_found() {
-        let mock = MockUserService::default().with_find_by_token_result(Ok(None));
-        let app = create_test_app(mock);
-
-        let token = UserToken::generate();
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("GET")
-                    .uri(format!("/api/v1/user/token/{token}"))
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::NOT_FOUND);
-    }
-
-    #[tokio::test]
-    async fn test_get_user_by_token_invalid_uuid() {
-        let mock = MockUserService::default();
-        let app = create_test_app(mock);
-
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("GET")
-                    .uri("/api/v1/user/token/not-a-uuid")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-
-        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
-    }
+/// Whether `If-None-Match` is `*` or lists `etag`, compared weakly as
+/// RFC 9110 has for GET and HEAD.
+fn if_none_match(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = etag.to_str().map(opaque).unwrap_or_default();
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == etag)
 }
@@ -0,0 +1,138 @@
+//! The bodies `PATCH /api/v1/user/{id}` takes, told apart by `Content-Type`:
+//!
+//! - `application/json`: the fields to change, where a `null` age clears it.
+//! - `application/merge-patch+json` (RFC 7396): the same, except that `null`
+//!   clears any field. The name and email can't be cleared, and fields the
+//!   user doesn't have are rejected rather than added.
+//! - `application/json-patch+json` (RFC 6902): `add`, `replace` and `remove`
+//!   operations on `/name`, `/email` and `/age`, applied in order, and `test`
+//!   on `/version`, which makes the update conditional on the stored version.
+//!
+//! Every user has an age, so clearing it, with a `null` or by removing
+//! `/age`, resets it to the default of 0 rather than leaving it unset.
+
+use axum::{
+    http::{HeaderMap, HeaderName, header::CONTENT_TYPE},
+    response::IntoResponse,
+};
+use hex_play_core::{
+    Error as CoreError,
+    types::{Age, Email},
+    user::PartialUserUpdate,
+};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Value, error::Category};
+
+use super::{UpdateUserRequest, allow, deserialize_nullable};
+use crate::http::error::Error;
+
+const JSON: &str = "application/json";
+const MERGE_PATCH: &str = "application/merge-patch+json";
+const JSON_PATCH: &str = "application/json-patch+json";
+
+const ACCEPT_PATCH: &str = "application/json, application/merge-patch+json, application/json-patch+json";
+
+/// Answers an OPTIONS request like [`allow`], also advertising the bodies a
+/// PATCH takes in `Accept-Patch` (RFC 5789).
+pub(super) async fn allow_patch(methods: &'static str) -> impl IntoResponse {
+    ([(HeaderName::from_static("accept-patch"), ACCEPT_PATCH)], allow(methods).await)
+}
+
+/// Parses `body` as the kind of patch its `Content-Type` names.
+pub(super) fn parse_update(headers: &HeaderMap, body: &[u8]) -> Result<PartialUserUpdate, Error> {
+    let content_type = headers.get(CONTENT_TYPE).and_then(|value| value.to_str().ok()).unwrap_or_default();
+    let media_type = content_type.split(';').next().unwrap_or_default().trim();
+    if media_type.eq_ignore_ascii_case(JSON) {
+        Ok(parse::<UpdateUserRequest>(body)?.into())
+    } else if media_type.eq_ignore_ascii_case(MERGE_PATCH) {
+        Ok(parse::<MergePatch>(body)?.try_into()?)
+    } else if media_type.eq_ignore_ascii_case(JSON_PATCH) {
+        let mut update = PartialUserUpdate::default();
+        for operation in parse::<Vec<PatchOperation>>(body)? {
+            operation.apply(&mut update)?;
+        }
+        Ok(update)
+    } else {
+        Err(Error::UnsupportedMediaType(format!(
+            "expected {JSON}, {MERGE_PATCH} or {JSON_PATCH}, got {content_type:?}"
+        )))
+    }
+}
+
+/// Malformed JSON is a bad request; well-formed JSON that doesn't describe
+/// an update fails validation, as with the `Json` extractor.
+fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, Error> {
+    serde_json::from_slice(body).map_err(|error| match error.classify() {
+        Category::Data => Error::Core(CoreError::Validation(error.to_string().into())),
+        Category::Io | Category::Syntax | Category::Eof => Error::BadRequest(format!("Invalid JSON: {error}")),
+    })
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct MergePatch {
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    email: Option<Option<Email>>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    age: Option<Option<Age>>,
+}
+
+impl TryFrom<MergePatch> for PartialUserUpdate {
+    type Error = CoreError;
+
+    fn try_from(patch: MergePatch) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: patch.name.map(|name| name.ok_or_else(|| cannot_clear("name"))).transpose()?,
+            email: patch.email.map(|email| email.ok_or_else(|| cannot_clear("email"))).transpose()?,
+            age: patch.age,
+            expected_version: None,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum PatchOperation {
+    Add { path: String, value: Value },
+    Replace { path: String, value: Value },
+    Remove { path: String },
+    Test { path: String, value: Value },
+}
+
+impl PatchOperation {
+    fn apply(self, update: &mut PartialUserUpdate) -> Result<(), CoreError> {
+        match self {
+            PatchOperation::Add { path, value } | PatchOperation::Replace { path, value } => match path.as_str() {
+                "/name" => update.name = Some(value_at(&path, value)?),
+                "/email" => update.email = Some(value_at(&path, value)?),
+                "/age" => update.age = Some(value_at(&path, value)?),
+                _ => return Err(unsupported_path(&path)),
+            },
+            PatchOperation::Remove { path } => match path.as_str() {
+                "/age" => update.age = Some(None),
+                "/name" => return Err(cannot_clear("name")),
+                "/email" => return Err(cannot_clear("email")),
+                _ => return Err(unsupported_path(&path)),
+            },
+            PatchOperation::Test { path, value } => match path.as_str() {
+                "/version" => update.expected_version = Some(value_at(&path, value)?),
+                _ => return Err(CoreError::Validation(format!("Only /version can be tested, not {path}").into())),
+            },
+        }
+        Ok(())
+    }
+}
+
+fn value_at<T: DeserializeOwned>(path: &str, value: Value) -> Result<T, CoreError> {
+    serde_json::from_value(value).map_err(|error| CoreError::Validation(format!("Invalid value for {path}: {error}").into()))
+}
+
+fn unsupported_path(path: &str) -> CoreError {
+    CoreError::Validation(format!("Unsupported path: {path}").into())
+}
+
+fn cannot_clear(field: &str) -> CoreError {
+    CoreError::Validation(format!("The {field} can't be cleared").into())
+}
@@ -0,0 +1,2006 @@
+//! Version 1 of the user API, addressed by numeric id.
+//!
+//! Deprecated in favour of [`super::v2`]; every response carries
+//! `Deprecation`, `Sunset` and successor `Link` headers.
+
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json, Router,
+    body::Bytes,
+    extract::{
+        DefaultBodyLimit, Multipart, Path, Query, State,
+        multipart::{Field, MultipartError},
+    },
+    http::{
+        HeaderMap, HeaderName, HeaderValue, StatusCode,
+        header::{ETAG, LINK},
+    },
+    middleware::{from_fn, map_response},
+    response::{IntoResponse as _, Redirect, Response},
+    routing::{get, patch, post, put},
+};
+use chrono::{DateTime, Utc};
+use hex_play_core::{
+    CoreServices, Error as CoreError,
+    avatar::{AvatarVariant, MAX_AVATAR_BYTES},
+    context::RequestContext,
+    i18n::ValidationMessage,
+    types::{Age, Email},
+    user::{NewUser, User, UserId, UserToken, after_start_id},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    CreateUserRequest, allow, conditional_user_response, if_none_match, list_etag,
+    patch::{allow_patch, parse_update},
+};
+use crate::http::{
+    error::Error,
+    limit::RouteLimits,
+    negotiate::{Negotiated, ResponseFormat},
+    request_context,
+};
+
+/// When v1 was deprecated, as an RFC 9745 structured date (2026-10-01).
+const DEPRECATION: &str = "@1790812800";
+/// When v1 will stop being served (RFC 8594).
+const SUNSET: &str = "Thu, 01 Apr 2027 00:00:00 GMT";
+const SUCCESSOR_LINK: &str = "</api/v2/user>; rel=\"successor-version\"";
+
+pub(super) fn get_routes(core_services: Arc<CoreServices>, limits: &RouteLimits) -> Router {
+    Router::new()
+        .nest(
+            "/api/v1/user",
+            Router::new()
+                .route(
+                    "/",
+                    limits
+                        .writes(post(create_user))
+                        .merge(limits.exports(get(list_users)))
+                        .options(|| allow("GET, HEAD, POST, OPTIONS")),
+                )
+                .route("/token/{token}", limits.reads(get(get_user_by_token)).options(|| allow("GET, HEAD, OPTIONS")))
+                .route("/email/{email}", limits.reads(get(get_user_by_email)).options(|| allow("GET, HEAD, OPTIONS")))
+                .route("/by-email/{email}", limits.writes(put(upsert_user)).options(|| allow("PUT, OPTIONS")))
+                .route("/batch-get", limits.exports(post(batch_get_users)).options(|| allow("POST, OPTIONS")))
+                .route(
+                    "/{id}",
+                    limits
+                        .reads(get(get_user))
+                        .merge(limits.writes(patch(update_user).delete(delete_user)))
+                        .options(|| allow_patch("GET, HEAD, PATCH, DELETE, OPTIONS")),
+                )
+                .route(
+                    "/{id}/avatar",
+                    limits
+                        .reads(get(get_avatar))
+                        .merge(limits.writes(post(upload_avatar).layer(DefaultBodyLimit::max(AVATAR_BODY_LIMIT))))
+                        .options(|| allow("GET, HEAD, POST, OPTIONS")),
+                )
+                .layer(map_response(add_deprecation_headers))
+                .layer(from_fn(request_context)),
+        )
+        .with_state(core_services)
+}
+
+async fn add_deprecation_headers(mut response: Response) -> Response {
+    let headers = response.headers_mut();
+    headers.insert(HeaderName::from_static("deprecation"), HeaderValue::from_static(DEPRECATION));
+    headers.insert(HeaderName::from_static("sunset"), HeaderValue::from_static(SUNSET));
+    headers.insert(LINK, HeaderValue::from_static(SUCCESSOR_LINK));
+    response
+}
+
+#[derive(Serialize, Debug)]
+struct UserResponse {
+    id: UserId,
+    token: String,
+    name: String,
+    email: Email,
+    age: Age,
+    version: u64,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    last_seen_at: Option<DateTime<Utc>>,
+    avatar: AvatarLinks,
+}
+
+/// Where to fetch each variant of a user's avatar. The links redirect to
+/// presigned URLs and answer 404 while the user has no avatar; they never
+/// change, so responses stay cacheable.
+#[derive(Serialize, Debug)]
+struct AvatarLinks {
+    original: String,
+    thumbnail: String,
+    medium: String,
+}
+
+impl AvatarLinks {
+    fn new(id: UserId) -> Self {
+        let link = |variant: AvatarVariant| format!("/api/v1/user/{id}/avatar?variant={}", variant.as_str());
+        Self {
+            original: format!("/api/v1/user/{id}/avatar"),
+            thumbnail: link(AvatarVariant::Thumbnail),
+            medium: link(AvatarVariant::Medium),
+        }
+    }
+}
+
+impl From<User> for UserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            avatar: AvatarLinks::new(user.id),
+            id: user.id,
+            token: user.token.to_string(),
+            name: user.name,
+            email: user.email,
+            age: user.age,
+            version: user.version,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+            last_seen_at: user.last_seen_at,
+        }
+    }
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context))]
+async fn create_user(
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    format: ResponseFormat,
+    Json(request): Json<CreateUserRequest>,
+) -> Result<(StatusCode, Negotiated<UserResponse>), Error> {
+    let user = core_services.user_service.add_user(&context, request.into()).await.map_err(Error::Core)?;
+    Ok((StatusCode::CREATED, Negotiated(format, user.into())))
+}
+
+#[derive(Deserialize, Debug)]
+struct UpsertUserRequest {
+    name: String,
+    #[serde(default)]
+    age: Age,
+}
+
+/// Creates or updates the user with `email`. Responds 201 when the user was
+/// created and 200 when an existing user was updated.
+#[tracing::instrument(level = "trace", skip(core_services, context, email), fields(email = %email.redacted()))]
+async fn upsert_user(
+    Path(email): Path<Email>,
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    format: ResponseFormat,
+    Json(request): Json<UpsertUserRequest>,
+) -> Result<(StatusCode, Negotiated<UserResponse>), Error> {
+    let new_user = NewUser {
+        name: request.name,
+        email,
+        age: request.age,
+    };
+    let user = core_services.user_service.upsert_by_email(&context, new_user).await.map_err(Error::Core)?;
+
+    // Inserted rows start at version 1; the conflict path always bumps it.
+    let status = if user.version == 1 { StatusCode::CREATED } else { StatusCode::OK };
+    Ok((status, Negotiated(format, user.into())))
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct FilterOptions {
+    /// The first id to list, inclusive.
+    pub start_id: Option<UserId>,
+    pub page_size: Option<u64>,
+    /// Only users seen at or after this RFC 3339 time.
+    pub active_since: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ListUsersResponse {
+    users: Vec<UserResponse>,
+}
+
+/// Encodes `users` as the `list_users` response body, for benchmarks.
+#[cfg(feature = "test-support")]
+pub(crate) fn encode_list_users(format: ResponseFormat, users: Vec<User>) -> Vec<u8> {
+    let response = ListUsersResponse {
+        users: users.into_iter().map(Into::into).collect(),
+    };
+    format.encode(&response).expect("user list is serializable").1
+}
+
+/// Answers `304 Not Modified` to an `If-None-Match` naming the weak `ETag`
+/// of the page, which changes whenever the users do, without reading the
+/// page.
+#[tracing::instrument(level = "trace", skip(core_services, context, headers))]
+async fn list_users(
+    Query(opts): Query<FilterOptions>,
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    format: ResponseFormat,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let after_id = after_start_id(opts.start_id);
+    let page_size = Some(core_services.page_size_cap.apply(opts.page_size));
+    let stamp = core_services.user_service.list_stamp(&context).await.map_err(Error::Core)?;
+    let etag = list_etag(&stamp, &(after_id, page_size, opts.active_since, format));
+    if if_none_match(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response());
+    }
+
+    let users = core_services
+        .user_service
+        .list_users(&context, after_id, page_size, opts.active_since)
+        .await
+        .map_err(Error::Core)?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(([(ETAG, etag)], Negotiated(format, ListUsersResponse { users })).into_response())
+}
+
+#[derive(Deserialize, Debug)]
+struct BatchGetUsersRequest {
+    #[serde(default)]
+    ids: Vec<UserId>,
+    #[serde(default)]
+    tokens: Vec<UserToken>,
+}
+
+/// Resolves users by id and/or token in one request. Users are returned once,
+/// ordered by id; unknown ids and tokens are skipped.
+#[tracing::instrument(level = "trace", skip(core_services, context))]
+async fn batch_get_users(
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    format: ResponseFormat,
+    Json(request): Json<BatchGetUsersRequest>,
+) -> Result<Negotiated<ListUsersResponse>, Error> {
+    let mut users = core_services.user_service.find_by_ids(&context, &request.ids).await.map_err(Error::Core)?;
+    users.extend(
+        core_services
+            .user_service
+            .find_by_tokens(&context, &request.tokens)
+            .await
+            .map_err(Error::Core)?,
+    );
+    users.sort_by_key(|user| user.id);
+    users.dedup_by_key(|user| user.id);
+
+    Ok(Negotiated(
+        format,
+        ListUsersResponse {
+            users: users.into_iter().map(Into::into).collect(),
+        },
+    ))
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context, headers))]
+async fn get_user(
+    Path(id): Path<UserId>,
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    format: ResponseFormat,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let user = core_services
+        .user_service
+        .find_by_id(&context, id)
+        .await
+        .map_err(Error::Core)?
+        .ok_or(Error::NotFound)?;
+    Ok(conditional_user_response::<UserResponse>(&headers, format, user))
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context, headers))]
+async fn get_user_by_token(
+    Path(token): Path<UserToken>,
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    format: ResponseFormat,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let user = core_services
+        .user_service
+        .find_by_token(&context, token)
+        .await
+        .map_err(Error::Core)?
+        .ok_or(Error::NotFound)?;
+    Ok(conditional_user_response::<UserResponse>(&headers, format, user))
+}
+
+/// Looks a user up by email. The path segment is percent-decoded by axum, so
+/// clients should encode reserved characters such as `+` (`%2B`).
+#[tracing::instrument(level = "trace", skip(core_services, context, headers, email), fields(email = %email.redacted()))]
+async fn get_user_by_email(
+    Path(email): Path<Email>,
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    format: ResponseFormat,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let user = core_services
+        .user_service
+        .find_by_email(&context, email)
+        .await
+        .map_err(Error::Core)?
+        .ok_or(Error::NotFound)?;
+    Ok(conditional_user_response::<UserResponse>(&headers, format, user))
+}
+
+/// Takes a JSON body, a JSON Merge Patch or a JSON Patch, see [`super::patch`].
+#[tracing::instrument(level = "trace", skip(core_services, context, headers, body))]
+async fn update_user(
+    Path(id): Path<UserId>,
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    format: ResponseFormat,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Negotiated<UserResponse>, Error> {
+    let update = parse_update(&headers, &body)?;
+    let user = core_services
+        .user_service
+        .update_user_partial(&context, id, update)
+        .await
+        .map_err(Error::Core)?;
+    Ok(Negotiated(format, user.into()))
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context))]
+async fn delete_user(
+    Path(id): Path<UserId>,
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    format: ResponseFormat,
+) -> Result<Negotiated<UserResponse>, Error> {
+    let user = core_services.user_service.delete_user(&context, id).await.map_err(Error::Core)?;
+    Ok(Negotiated(format, user.into()))
+}
+
+/// Multipart field carrying the image in an avatar upload.
+const AVATAR_FIELD: &str = "avatar";
+/// Largest avatar upload body: the image plus room for the multipart
+/// framing and small fields.
+const AVATAR_BODY_LIMIT: usize = MAX_AVATAR_BYTES + 64 * 1024;
+/// Declared content type clients send when they don't know the real one.
+const OCTET_STREAM: &str = "application/octet-stream";
+
+#[derive(Serialize, Debug)]
+struct AvatarResponse {
+    key: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct AvatarQuery {
+    #[serde(default)]
+    variant: AvatarVariant,
+}
+
+fn avatar_too_large() -> Error {
+    Error::Core(CoreError::Validation(ValidationMessage::AvatarTooLarge { max_bytes: MAX_AVATAR_BYTES }))
+}
+
+fn multipart_error(error: MultipartError) -> Error {
+    if error.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        return avatar_too_large();
+    }
+    Error::Core(CoreError::Validation(error.body_text().into()))
+}
+
+/// Reads an avatar field, failing as soon as it exceeds
+/// [`MAX_AVATAR_BYTES`] rather than buffering all of it.
+async fn read_avatar(mut field: Field<'_>) -> Result<Vec<u8>, Error> {
+    let mut image = Vec::new();
+    while let Some(chunk) = field.chunk().await.map_err(multipart_error)? {
+        if image.len() + chunk.len() > MAX_AVATAR_BYTES {
+            return Err(avatar_too_large());
+        }
+        image.extend_from_slice(&chunk);
+    }
+    Ok(image)
+}
+
+/// The image type `image` holds, from its leading bytes.
+fn sniff_image_type(image: &[u8]) -> Option<&'static str> {
+    if image.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if image.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if image.starts_with(b"GIF87a") || image.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if image.len() >= 12 && image.starts_with(b"RIFF") && &image[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+/// The content type of an uploaded avatar, detected from its bytes. A
+/// declared type other than `application/octet-stream` must agree.
+fn avatar_content_type(declared: Option<&str>, image: &[u8]) -> Result<&'static str, Error> {
+    let declared = declared.filter(|declared| !declared.eq_ignore_ascii_case(OCTET_STREAM));
+    let detected = sniff_image_type(image).ok_or_else(|| {
+        Error::Core(CoreError::Validation(ValidationMessage::UnsupportedAvatarType {
+            content_type: declared.unwrap_or(OCTET_STREAM).to_string(),
+        }))
+    })?;
+    match declared {
+        Some(declared) if !declared.eq_ignore_ascii_case(detected) => Err(Error::Core(CoreError::Validation(ValidationMessage::AvatarTypeMismatch {
+            declared: declared.to_string(),
+            detected: detected.to_string(),
+        }))),
+        _ => Ok(detected),
+    }
+}
+
+/// Stores the `avatar` file of a `multipart/form-data` body as the user's
+/// avatar, replacing any previous one. Its type is detected from its
+/// contents; resized variants follow once a background job has run.
+#[tracing::instrument(level = "trace", skip(core_services, context, multipart))]
+async fn upload_avatar(
+    Path(id): Path<UserId>,
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    format: ResponseFormat,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Negotiated<AvatarResponse>), Error> {
+    while let Some(field) = multipart.next_field().await.map_err(multipart_error)? {
+        if field.name() != Some(AVATAR_FIELD) {
+            continue;
+        }
+        let declared = field.content_type().map(str::to_string);
+        let image = read_avatar(field).await?;
+        let content_type = avatar_content_type(declared.as_deref(), &image)?;
+        let key = core_services
+            .avatar_service
+            .upload_avatar(&context, id, content_type, image)
+            .await
+            .map_err(Error::Core)?;
+        return Ok((StatusCode::CREATED, Negotiated(format, AvatarResponse { key })));
+    }
+
+    Err(Error::Core(CoreError::Validation(format!("missing `{AVATAR_FIELD}` file field").into())))
+}
+
+/// Redirects to a short-lived presigned URL of the user's avatar, so the
+/// image itself never passes through the API. `?variant=thumbnail` or
+/// `?variant=medium` selects a resized variant.
+#[tracing::instrument(level = "trace", skip(core_services, context))]
+async fn get_avatar(
+    Path(id): Path<UserId>,
+    Query(query): Query<AvatarQuery>,
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+) -> Result<Redirect, Error> {
+    let url = core_services
+        .avatar_service
+        .avatar_url(&context, id, query.variant)
+        .await
+        .map_err(Error::Core)?
+        .ok_or(Error::NotFound)?;
+    Ok(Redirect::temporary(&url))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        Router,
+        body::Body,
+        http::{HeaderMap, HeaderValue, Request, StatusCode, header::CONTENT_TYPE},
+    };
+    use hex_play_core::{
+        Error, RepositoryError,
+        avatar::MAX_AVATAR_BYTES,
+        test_support::{MockAvatarService, MockUserService, create_arc_core_services_with_mock, create_core_services_with_mock},
+        types::Age,
+        user::{PartialUserUpdate, User, UserId, UserListStamp, UserToken},
+    };
+    use mockall::predicate::{always, eq};
+    use tower::ServiceExt;
+
+    use super::{AVATAR_BODY_LIMIT, get_routes};
+    use crate::http::{limit::RouteLimits, user::patch::parse_update};
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn create_test_app(mock: MockUserService) -> Router {
+        get_routes(create_arc_core_services_with_mock(mock), &RouteLimits::default())
+    }
+
+    fn create_avatar_test_app(mock: MockAvatarService) -> Router {
+        let mut core_services = create_core_services_with_mock(MockUserService::new());
+        core_services.avatar_service = Arc::new(mock);
+        get_routes(Arc::new(core_services), &RouteLimits::default())
+    }
+
+    /// Leading bytes of a PNG file, enough for sniffing.
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    fn multipart_request(uri: &str, field: &str) -> Request<Body> {
+        multipart_request_with(uri, field, "image/png", PNG)
+    }
+
+    fn multipart_request_with(uri: &str, field: &str, content_type: &str, image: &[u8]) -> Request<Body> {
+        let mut body =
+            format!("--boundary\r\nContent-Disposition: form-data; name=\"{field}\"; filename=\"a.png\"\r\nContent-Type: {content_type}\r\n\r\n").into_bytes();
+        body.extend_from_slice(image);
+        body.extend_from_slice(b"\r\n--boundary--\r\n");
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("content-type", "multipart/form-data; boundary=boundary")
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    fn patch_request(uri: &str, content_type: &str, body: &'static str) -> Request<Body> {
+        Request::builder()
+            .method("PATCH")
+            .uri(uri)
+            .header("content-type", content_type)
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    async fn body_to_string(body: Body) -> String {
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.expect("failed to read response body");
+        String::from_utf8(bytes.to_vec()).expect("response body must be valid UTF-8")
+    }
+
+    // ===================
+    // Tests: POST /api/v1/user (create_user)
+    // ===================
+    #[tokio::test]
+    async fn test_create_user_success() {
+        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let mut mock = MockUserService::new();
+        mock.expect_add_user().return_const(Ok(user));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/user")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name":"John Doe","email":"john@example.com","age":30}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let body = body_to_string(response.into_body()).await;
+        assert!(body.contains(r#""id":1"#));
+        assert!(body.contains(r#""name":"John Doe""#));
+        assert!(body.contains(r#""email":"john@example.com""#));
+        assert!(body.contains(r#""age":30"#));
+        assert!(body.contains(r#""version":0"#));
+        assert!(body.contains(r#""created_at":"#));
+        assert!(body.contains(r#""updated_at":"#));
+    }
+
+    #[tokio::test]
+    async fn test_create_user_without_age_defaults_to_zero() {
+        let user = User::fake(1, "John Doe", "john@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_add_user().return_const(Ok(user));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/user")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name":"John Doe","email":"john@example.com"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let body = body_to_string(response.into_body()).await;
+        assert!(body.contains(r#""age":0"#));
+    }
+
+    #[tokio::test]
+    async fn test_create_user_invalid_json() {
+        let mock = MockUserService::new();
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/user")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name":"John Doe""#)) // Invalid JSON
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Axum returns 400 for malformed JSON
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_create_user_missing_fields() {
+        let mock = MockUserService::new();
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/user")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name":"John Doe"}"#)) // Missing email
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_create_user_constraint_violation() {
+        let mut mock = MockUserService::new();
+        mock.expect_add_user()
+            .return_const(Err(Error::RepositoryError(RepositoryError::Constraint("duplicate email".into()))));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/user")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name":"John Doe","email":"john@example.com"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    // ===================
+    // Tests: PUT /api/v1/user/by-email/{email} (upsert_user)
+    // ===================
+    #[tokio::test]
+    async fn test_upsert_user_created() {
+        let mut user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        user.version = 1;
+        let mut mock = MockUserService::new();
+        mock.expect_upsert_by_email().return_const(Ok(user));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/api/v1/user/by-email/john@example.com")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name":"John Doe","age":30}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = body_to_string(response.into_body()).await;
+        assert!(body.contains(r#""email":"john@example.com""#));
+    }
+
+    #[tokio::test]
+    async fn test_upsert_user_updated() {
+        let mut user = User::fake_with_age(1, "John Updated", "john@example.com", 31);
+        user.version = 2;
+        let mut mock = MockUserService::new();
+        mock.expect_upsert_by_email().return_const(Ok(user));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/api/v1/user/by-email/john@example.com")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name":"John Updated","age":31}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body_to_string(response.into_body()).await;
+        assert!(body.contains(r#""name":"John Updated""#));
+    }
+
+    #[tokio::test]
+    async fn test_upsert_user_invalid_email() {
+        let app = create_test_app(MockUserService::new());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/api/v1/user/by-email/not-an-email")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name":"John Doe"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // ===================
+    // Tests: GET /api/v1/user (list_users)
+    // ===================
+    #[tokio::test]
+    async fn test_list_users_success() {
+        let users = vec![
+            User::fake_with_age(1, "John Doe", "john@example.com", 30),
+            User::fake_with_age(2, "Jane Doe", "jane@example.com", 25),
+        ];
+        let mut mock = MockUserService::new();
+        mock.expect_list_stamp().return_const(Ok(UserListStamp::default()));
+        mock.expect_list_users().return_const(Ok(users));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().method("GET").uri("/api/v1/user").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_to_string(response.into_body()).await;
+        assert!(body.contains(r#""users""#));
+        assert!(body.contains(r#""name":"John Doe""#));
+        assert!(body.contains(r#""name":"Jane Doe""#));
+        assert!(body.contains(r#""age":30"#));
+        assert!(body.contains(r#""age":25"#));
+    }
+
+    #[tokio::test]
+    async fn test_list_users_empty() {
+        let mut mock = MockUserService::new();
+        mock.expect_list_stamp().return_const(Ok(UserListStamp::default()));
+        mock.expect_list_users().return_const(Ok(vec![]));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().method("GET").uri("/api/v1/user").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_to_string(response.into_body()).await;
+        assert!(body.contains(r#""users":[]"#));
+    }
+
+    #[tokio::test]
+    async fn test_list_users_with_pagination() {
+        let users = vec![User::fake(5, "User Five", "five@example.com")];
+        let mut mock = MockUserService::new();
+        mock.expect_list_stamp().return_const(Ok(UserListStamp::default()));
+        mock.expect_list_users().return_const(Ok(users));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/api/v1/user?start_id=5&page_size=10")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_list_users_invalid_start_id() {
+        let mut mock = MockUserService::new();
+        mock.expect_list_stamp().return_const(Ok(UserListStamp::default()));
+        mock.expect_list_users().return_const(Err(Error::InvalidId(0)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().method("GET").uri("/api/v1/user?start_id=-1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_list_users_invalid_page_size() {
+        let mut mock = MockUserService::new();
+        mock.expect_list_stamp().return_const(Ok(UserListStamp::default()));
+        mock.expect_list_users().return_const(Err(Error::InvalidPageSize(0)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().method("GET").uri("/api/v1/user?page_size=0").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_list_users_sets_weak_etag() {
+        let mut mock = MockUserService::new();
+        mock.expect_list_stamp().return_const(Ok(UserListStamp::default()));
+        mock.expect_list_users().return_const(Ok(vec![]));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().method("GET").uri("/api/v1/user").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers()["etag"].to_str().unwrap().starts_with("W/\""));
+    }
+
+    #[tokio::test]
+    async fn test_list_users_not_modified() {
+        let mut mock = MockUserService::new();
+        mock.expect_list_stamp().return_const(Ok(UserListStamp {
+            count: 2,
+            ..UserListStamp::default()
+        }));
+        mock.expect_list_users().times(1).return_const(Ok(vec![]));
+        let app = create_test_app(mock);
+        let response = app
+            .clone()
+            .oneshot(Request::builder().method("GET").uri("/api/v1/user").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let etag = response.headers()["etag"].clone();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/api/v1/user")
+                    .header("if-none-match", etag.clone())
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()["etag"], etag);
+        assert!(body_to_string(response.into_body()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_users_modified_when_users_change() {
+        let mut mock = MockUserService::new();
+        let mut count = 0;
+        mock.expect_list_stamp().returning(move |_| {
+            count += 1;
+            Ok(UserListStamp {
+                count,
+                ..UserListStamp::default()
+            })
+        });
+        mock.expect_list_users().times(2).return_const(Ok(vec![]));
+        let app = create_test_app(mock);
+        let response = app
+            .clone()
+            .oneshot(Request::builder().method("GET").uri("/api/v1/user").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let etag = response.headers()["etag"].clone();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/api/v1/user")
+                    .header("if-none-match", etag.clone())
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()["etag"], etag);
+    }
+
+    #[tokio::test]
+    async fn test_list_users_etag_differs_by_page() {
+        let mut mock = MockUserService::new();
+        mock.expect_list_stamp().return_const(Ok(UserListStamp::default()));
+        mock.expect_list_users().return_const(Ok(vec![]));
+        let app = create_test_app(mock);
+        let etag = async |uri: &str| {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().method("GET").uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            response.headers()["etag"].clone()
+        };
+
+        assert_ne!(etag("/api/v1/user").await, etag("/api/v1/user?start_id=5").await);
+    }
+
+    // ===================
+    // Tests: POST /api/v1/user/batch-get (batch_get_users)
+    // ===================
+    #[tokio::test]
+    async fn test_batch_get_users_merges_ids_and_tokens() {
+        let john = User::fake(1, "John Doe", "john@example.com");
+        let jane = User::fake(2, "Jane Doe", "jane@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_ids().return_const(Ok(vec![john.clone(), jane.clone()]));
+        mock.expect_find_by_tokens().return_const(Ok(vec![john.clone()]));
+        let app = create_test_app(mock);
+
+        let body = format!(r#"{{"ids":[1,2],"tokens":["{}"]}}"#, john.token);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/user/batch-get")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body_to_string(response.into_body()).await;
+        assert_eq!(body.matches(r#""name":"John Doe""#).count(), 1);
+        assert!(body.contains(r#""name":"Jane Doe""#));
+    }
+
+    #[tokio::test]
+    async fn test_batch_get_users_too_many() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_ids().return_const(Err(Error::InvalidBatchSize(101)));
+        mock.expect_find_by_tokens().return_const(Ok(vec![]));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/user/batch-get")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"ids":[1]}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_batch_get_users_invalid_token() {
+        let app = create_test_app(MockUserService::new());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/user/batch-get")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"tokens":["not-a-token"]}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    // ===================
+    // Tests: GET /api/v1/user/{id} (get_user)
+    // ===================
+    #[tokio::test]
+    async fn test_get_user_success() {
+        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(Some(user)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().method("GET").uri("/api/v1/user/1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_to_string(response.into_body()).await;
+        assert!(body.contains(r#""id":1"#));
+        assert!(body.contains(r#""name":"John Doe""#));
+        assert!(body.contains(r#""age":30"#));
+        assert!(body.contains(r#""version":0"#));
+        assert!(body.contains(r#""created_at":"#));
+        assert!(body.contains(r#""updated_at":"#));
+        assert!(body.contains(r#""thumbnail":"/api/v1/user/1/avatar?variant=thumbnail""#));
+    }
+
+    #[tokio::test]
+    async fn test_get_user_sets_last_modified() {
+        let user = User::fake(1, "John Doe", "john@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(Some(user)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().method("GET").uri("/api/v1/user/1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["last-modified"], "Wed, 01 Jan 2025 00:00:00 GMT");
+    }
+
+    #[tokio::test]
+    async fn test_get_user_not_modified_since() {
+        let user = User::fake(1, "John Doe", "john@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(Some(user)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/api/v1/user/1")
+                    .header("if-modified-since", "Wed, 01 Jan 2025 00:00:00 GMT")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert!(body_to_string(response.into_body()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_user_modified_since() {
+        let user = User::fake(1, "John Doe", "john@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(Some(user)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/api/v1/user/1")
+                    .header("if-modified-since", "Tue, 31 Dec 2024 23:59:59 GMT")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_get_user_ignores_invalid_if_modified_since() {
+        let user = User::fake(1, "John Doe", "john@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(Some(user)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/api/v1/user/1")
+                    .header("if-modified-since", "yesterday")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_get_user_not_found() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(None));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().method("GET").uri("/api/v1/user/999").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_user_invalid_id() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Err(Error::InvalidId(0)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().method("GET").uri("/api/v1/user/-1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // ===================
+    // Tests: GET /api/v1/user/email/{email} (get_user_by_email)
+    // ===================
+    #[tokio::test]
+    async fn test_get_user_by_email_percent_encoded() {
+        let user = User::fake(1, "John Doe", "john+tag@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_email().return_const(Ok(Some(user)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/api/v1/user/email/john%2Btag%40example.com")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body_to_string(response.into_body()).await;
+        assert!(body.contains(r#""email":"john+tag@example.com""#));
+    }
+
+    #[tokio::test]
+    async fn test_get_user_by_email_not_found() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_email().return_const(Ok(None));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/api/v1/user/email/none@example.com")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_user_by_email_invalid() {
+        let app = create_test_app(MockUserService::new());
+
+        let response = app
+            .oneshot(Request::builder().method("GET").uri("/api/v1/user/email/invalid").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // ===================
+    // Tests: PATCH /api/v1/user/{id} (update_user)
+    // ===================
+    #[tokio::test]
+    async fn test_update_user_success() {
+        let updated = User::fake_with_age(1, "John Updated", "john@example.com", 30);
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(Some("John Updated"), None::<String>, None).unwrap()),
+            )
+            .times(1)
+            .return_const(Ok(updated));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PATCH")
+                    .uri("/api/v1/user/1")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name":"John Updated"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_to_string(response.into_body()).await;
+        assert!(body.contains(r#""name":"John Updated""#));
+        assert!(body.contains(r#""age":30"#));
+    }
+
+    #[tokio::test]
+    async fn test_update_user_partial_email() {
+        let updated = User::fake_with_age(1, "John Doe", "john.new@example.com", 25);
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(None::<String>, Some("john.new@example.com"), None).unwrap()),
+            )
+            .times(1)
+            .return_const(Ok(updated));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PATCH")
+                    .uri("/api/v1/user/1")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"email":"john.new@example.com"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_to_string(response.into_body()).await;
+        assert!(body.contains(r#""email":"john.new@example.com""#));
+        assert!(body.contains(r#""age":25"#));
+    }
+
+    #[tokio::test]
+    async fn test_update_user_age() {
+        let updated = User::fake_with_age(1, "John Doe", "john@example.com", 31);
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(None::<String>, None::<String>, Some(31)).unwrap()),
+            )
+            .times(1)
+            .return_const(Ok(updated));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PATCH")
+                    .uri("/api/v1/user/1")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"age":31}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_to_string(response.into_body()).await;
+        assert!(body.contains(r#""name":"John Doe""#));
+        assert!(body.contains(r#""age":31"#));
+    }
+
+    #[tokio::test]
+    async fn test_update_user_not_found() {
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(999)),
+                eq(PartialUserUpdate::new(Some("Updated"), None::<String>, None).unwrap()),
+            )
+            .times(1)
+            .return_const(Err(Error::RepositoryError(RepositoryError::NotFound)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PATCH")
+                    .uri("/api/v1/user/999")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name":"Updated"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_update_user_conflict() {
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(Some("Updated"), None::<String>, None).unwrap()),
+            )
+            .times(1)
+            .return_const(Err(Error::RepositoryError(RepositoryError::Conflict)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PATCH")
+                    .uri("/api/v1/user/1")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name":"Updated"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_update_user_empty_body() {
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(always(), eq(UserId::new(1)), eq(PartialUserUpdate::default()))
+            .return_const(Err(Error::EmptyUpdate));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PATCH")
+                    .uri("/api/v1/user/1")
+                    .header("content-type", "application/json")
+                    .body(Body::from("{}"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_update_user_clear_age() {
+        let cleared = User::fake(1, "John Doe", "john@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(always(), eq(UserId::new(1)), eq(PartialUserUpdate::default().clear_age()))
+            .times(1)
+            .return_const(Ok(cleared));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PATCH")
+                    .uri("/api/v1/user/1")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"age":null}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_to_string(response.into_body()).await;
+        assert!(body.contains(r#""age":0"#));
+    }
+
+    #[tokio::test]
+    async fn test_update_user_merge_patch() {
+        let updated = User::fake(1, "John Updated", "john@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(Some("John Updated"), None::<String>, None).unwrap().clear_age()),
+            )
+            .times(1)
+            .return_const(Ok(updated));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(patch_request(
+                "/api/v1/user/1",
+                "application/merge-patch+json",
+                r#"{"name":"John Updated","age":null}"#,
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_update_user_merge_patch_cannot_clear_name() {
+        let app = create_test_app(MockUserService::new());
+
+        let response = app
+            .oneshot(patch_request("/api/v1/user/1", "application/merge-patch+json", r#"{"name":null}"#))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body_to_string(response.into_body()).await.contains("The name can't be cleared"));
+    }
+
+    #[tokio::test]
+    async fn test_update_user_merge_patch_unknown_field() {
+        let app = create_test_app(MockUserService::new());
+
+        let response = app
+            .oneshot(patch_request("/api/v1/user/1", "application/merge-patch+json", r#"{"version":3}"#))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_update_user_json_patch() {
+        let updated = User::fake(1, "John Doe", "john.new@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(None::<String>, Some("john.new@example.com"), None)
+                    .unwrap()
+                    .clear_age()
+                    .with_expected_version(3)),
+            )
+            .times(1)
+            .return_const(Ok(updated));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(patch_request(
+                "/api/v1/user/1",
+                "application/json-patch+json",
+                r#"[
+                    {"op":"test","path":"/version","value":3},
+                    {"op":"replace","path":"/email","value":"john.new@example.com"},
+                    {"op":"add","path":"/age","value":40},
+                    {"op":"remove","path":"/age"}
+                ]"#,
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_clearing_age_by_patch_resets_it_to_default() {
+        let cases = [
+            ("application/json", r#"{"age":null}"#),
+            ("application/merge-patch+json", r#"{"age":null}"#),
+            ("application/json-patch+json", r#"[{"op":"remove","path":"/age"}]"#),
+        ];
+
+        for (content_type, body) in cases {
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+            let mut user = User::fake_with_age(1, "John Doe", "john@example.com", 40);
+
+            parse_update(&headers, body.as_bytes()).unwrap().apply_to(&mut user);
+
+            assert_eq!(user.age, Age::default(), "{content_type}");
+            assert_eq!(user.age.value(), 0, "{content_type}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_user_json_patch_rejects_unsupported_operations() {
+        let cases = [
+            r#"[{"op":"move","from":"/name","path":"/email"}]"#,
+            r#"[{"op":"replace","path":"/id","value":2}]"#,
+            r#"[{"op":"remove","path":"/name"}]"#,
+            r#"[{"op":"test","path":"/name","value":"John"}]"#,
+            r#"[{"op":"replace","path":"/age","value":200}]"#,
+        ];
+
+        for body in cases {
+            let app = create_test_app(MockUserService::new());
+
+            let response = app.oneshot(patch_request("/api/v1/user/1", "application/json-patch+json", body)).await.unwrap();
+
+            assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_user_malformed_json() {
+        let app = create_test_app(MockUserService::new());
+
+        let response = app
+            .oneshot(patch_request("/api/v1/user/1", "application/json-patch+json", r#"[{"op":"#))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_update_user_unsupported_media_type() {
+        let app = create_test_app(MockUserService::new());
+
+        let response = app.oneshot(patch_request("/api/v1/user/1", "text/plain", r#"{"name":"John"}"#)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn test_options_advertises_accept_patch() {
+        let app = create_test_app(MockUserService::new());
+
+        let response = app
+            .oneshot(Request::builder().method("OPTIONS").uri("/api/v1/user/1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.headers()["accept-patch"],
+            "application/json, application/merge-patch+json, application/json-patch+json"
+        );
+    }
+
+    // ===================
+    // Tests: DELETE /api/v1/user/{id} (delete_user)
+    // ===================
+    #[tokio::test]
+    async fn test_delete_user_success() {
+        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let mut mock = MockUserService::new();
+        mock.expect_delete_user().return_const(Ok(user));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().method("DELETE").uri("/api/v1/user/1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_to_string(response.into_body()).await;
+        assert!(body.contains(r#""id":1"#));
+        assert!(body.contains(r#""name":"John Doe""#));
+        assert!(body.contains(r#""age":30"#));
+    }
+
+    #[tokio::test]
+    async fn test_delete_user_not_found() {
+        let mut mock = MockUserService::new();
+        mock.expect_delete_user().return_const(Err(Error::RepositoryError(RepositoryError::NotFound)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().method("DELETE").uri("/api/v1/user/999").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    // ===================
+    // Tests: content negotiation
+    // ===================
+    #[tokio::test]
+    async fn test_get_user_msgpack() {
+        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(Some(user)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/api/v1/user/1")
+                    .header("accept", "application/msgpack")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "application/msgpack");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(body["name"], "John Doe");
+        assert_eq!(body["age"], 30);
+    }
+
+    #[tokio::test]
+    async fn test_list_users_cbor() {
+        let users = vec![User::fake(1, "John Doe", "john@example.com"), User::fake(2, "Jane Doe", "jane@example.com")];
+        let mut mock = MockUserService::new();
+        mock.expect_list_stamp().return_const(Ok(UserListStamp::default()));
+        mock.expect_list_users().return_const(Ok(users));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/api/v1/user")
+                    .header("accept", "application/cbor")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "application/cbor");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = ciborium::from_reader(bytes.as_ref()).unwrap();
+        assert_eq!(body["users"][1]["name"], "Jane Doe");
+    }
+
+    // ===================
+    // Tests: deprecation headers
+    // ===================
+    #[tokio::test]
+    async fn test_v1_responses_carry_deprecation_headers() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(None));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().method("GET").uri("/api/v1/user/999").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()["deprecation"], "@1790812800");
+        assert_eq!(response.headers()["sunset"], "Thu, 01 Apr 2027 00:00:00 GMT");
+        assert_eq!(response.headers()["link"], "</api/v2/user>; rel=\"successor-version\"");
+    }
+
+    // ===================
+    // Tests: HEAD / OPTIONS
+    // ===================
+    #[tokio::test]
+    async fn test_head_user_returns_headers_without_body() {
+        let mut user = User::fake(1, "John Doe", "john@example.com");
+        user.version = 3;
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(Some(user)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().method("HEAD").uri("/api/v1/user/1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["etag"], "\"3\"");
+        assert_eq!(response.headers()["last-modified"], "Wed, 01 Jan 2025 00:00:00 GMT");
+        let content_length: usize = response.headers()["content-length"].to_str().unwrap().parse().unwrap();
+        assert!(content_length > 0);
+        assert!(body_to_string(response.into_body()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_head_user_not_found() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_id().return_const(Ok(None));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().method("HEAD").uri("/api/v1/user/999").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_options_routes_advertise_methods() {
+        let cases = [
+            ("/api/v1/user", "GET, HEAD, POST, OPTIONS"),
+            ("/api/v1/user/1", "GET, HEAD, PATCH, DELETE, OPTIONS"),
+            (&format!("/api/v1/user/token/{}", UserToken::new(UserId::new(1))), "GET, HEAD, OPTIONS"),
+        ];
+
+        for (uri, allowed) in cases {
+            let app = create_test_app(MockUserService::new());
+
+            let response = app
+                .oneshot(Request::builder().method("OPTIONS").uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::NO_CONTENT, "{uri}");
+            assert_eq!(response.headers()["allow"], allowed, "{uri}");
+        }
+    }
+
+    // ===================
+    // Tests: GET /api/v1/user/token/{token} (get_user_by_token)
+    // ===================
+    #[tokio::test]
+    async fn test_get_user_by_token_not_modified_since() {
+        let user = User::fake(1, "John Doe", "john@example.com");
+        let token = user.token;
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_token().return_const(Ok(Some(user)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri(format!("/api/v1/user/token/{token}"))
+                    .header("if-modified-since", "Thu, 02 Jan 2025 00:00:00 GMT")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()["last-modified"], "Wed, 01 Jan 2025 00:00:00 GMT");
+    }
+
+    #[tokio::test]
+    async fn test_get_user_by_token_success() {
+        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let token = user.token;
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_token().return_const(Ok(Some(user)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri(format!("/api/v1/user/token/{token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_to_string(response.into_body()).await;
+        assert!(body.contains(r#""id":1"#));
+        assert!(body.contains(r#""name":"John Doe""#));
+        assert!(body.contains(&format!(r#""token":"{token}""#)));
+        assert!(body.contains(r#""age":30"#));
+    }
+
+    #[tokio::test]
+    async fn test_get_user_by_token_not_found() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_token().return_const(Ok(None));
+        let app = create_test_app(mock);
+
+        let token = UserToken::generate();
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri(format!("/api/v1/user/token/{token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_user_by_token_invalid_uuid() {
+        let mock = MockUserService::new();
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/api/v1/user/token/not-a-uuid")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // ===================
+    // Tests: /api/v1/user/{id}/avatar
+    // ===================
+    #[tokio::test]
+    async fn test_upload_avatar_success() {
+        let mut mock = MockAvatarService::new();
+        mock.expect_upload_avatar().return_const(Ok("avatars/U_1/1.png".into()));
+        let app = create_avatar_test_app(mock);
+
+        let response = app.oneshot(multipart_request("/api/v1/user/1/avatar", "avatar")).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(body_to_string(response.into_body()).await, r#"{"key":"avatars/U_1/1.png"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_upload_avatar_detects_type_of_octet_stream() {
+        let mut mock = MockAvatarService::new();
+        mock.expect_upload_avatar().return_const(Ok("avatars/U_1/1.png".into()));
+        let app = create_avatar_test_app(mock);
+
+        let response = app
+            .oneshot(multipart_request_with("/api/v1/user/1/avatar", "avatar", "application/octet-stream", PNG))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_upload_avatar_type_mismatch() {
+        let app = create_avatar_test_app(MockAvatarService::new());
+
+        let response = app
+            .oneshot(multipart_request_with("/api/v1/user/1/avatar", "avatar", "image/jpeg", PNG))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body_to_string(response.into_body()).await.contains("sent as image/jpeg but contains image/png"));
+    }
+
+    #[tokio::test]
+    async fn test_upload_avatar_unrecognized_image() {
+        let app = create_avatar_test_app(MockAvatarService::new());
+
+        let response = app
+            .oneshot(multipart_request_with("/api/v1/user/1/avatar", "avatar", "image/png", b"<svg/>"))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body_to_string(response.into_body()).await.contains("got image/png"));
+    }
+
+    #[tokio::test]
+    async fn test_upload_avatar_too_large() {
+        let app = create_avatar_test_app(MockAvatarService::new());
+        let mut image = PNG.to_vec();
+        image.resize(MAX_AVATAR_BYTES + 1, 0);
+
+        let response = app
+            .oneshot(multipart_request_with("/api/v1/user/1/avatar", "avatar", "image/png", &image))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body_to_string(response.into_body()).await.contains("at most"));
+    }
+
+    #[tokio::test]
+    async fn test_upload_avatar_body_over_limit() {
+        let app = create_avatar_test_app(MockAvatarService::new());
+
+        let response = app
+            .oneshot(multipart_request_with(
+                "/api/v1/user/1/avatar",
+                "avatar",
+                "image/png",
+                &vec![0; AVATAR_BODY_LIMIT],
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body_to_string(response.into_body()).await.contains("at most"));
+    }
+
+    #[tokio::test]
+    async fn test_upload_avatar_missing_field() {
+        let app = create_avatar_test_app(MockAvatarService::new());
+
+        let response = app.oneshot(multipart_request("/api/v1/user/1/avatar", "picture")).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body_to_string(response.into_body()).await.contains("missing `avatar` file field"));
+    }
+
+    #[tokio::test]
+    async fn test_get_avatar_redirects_to_presigned_url() {
+        let mut mock = MockAvatarService::new();
+        mock.expect_avatar_url().return_const(Ok(Some("https://bucket.example/a.png?sig=1".into())));
+        let app = create_avatar_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().uri("/api/v1/user/1/avatar").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(response.headers()["location"], "https://bucket.example/a.png?sig=1");
+    }
+
+    #[tokio::test]
+    async fn test_get_avatar_variant() {
+        let mut mock = MockAvatarService::new();
+        mock.expect_avatar_url().return_const(Ok(Some("https://bucket.example/a-thumbnail.png".into())));
+        let app = create_avatar_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().uri("/api/v1/user/1/avatar?variant=thumbnail").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    }
+
+    #[tokio::test]
+    async fn test_get_avatar_unknown_variant() {
+        let app = create_avatar_test_app(MockAvatarService::new());
+
+        let response = app
+            .oneshot(Request::builder().uri("/api/v1/user/1/avatar?variant=huge").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_get_avatar_without_avatar() {
+        let mut mock = MockAvatarService::new();
+        mock.expect_avatar_url().return_const(Ok(None));
+        let app = create_avatar_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().uri("/api/v1/user/1/avatar").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,550 @@
+//! Version 2 of the user API.
+//!
+//! Users are addressed by their public token rather than the numeric id,
+//! lists use opaque cursor pagination, and errors are returned as
+//! `application/problem+json`.
+
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json, Router,
+    extract::{
+        Path, Query, State,
+        rejection::{JsonRejection, QueryRejection},
+    },
+    http::{HeaderMap, StatusCode},
+    middleware::from_fn,
+    response::Response,
+    routing::{get, patch, post},
+};
+use chrono::{DateTime, Utc};
+use hex_play_core::{
+    CoreServices, Error as CoreError, RepositoryError,
+    context::RequestContext,
+    types::{Age, Email},
+    user::{User, UserToken},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{CreateUserRequest, UpdateUserRequest, allow, conditional_user_response};
+use crate::http::{
+    limit::RouteLimits,
+    negotiate::{Negotiated, ResponseFormat},
+    problem::Problem,
+    request_context,
+};
+
+pub(super) fn get_routes(core_services: Arc<CoreServices>, limits: &RouteLimits) -> Router {
+    Router::new()
+        .nest(
+            "/api/v2/user",
+            Router::new()
+                .route(
+                    "/",
+                    limits
+                        .writes(post(create_user))
+                        .merge(limits.exports(get(list_users)))
+                        .options(|| allow("GET, HEAD, POST, OPTIONS")),
+                )
+                .route(
+                    "/{token}",
+                    limits
+                        .reads(get(get_user))
+                        .merge(limits.writes(patch(update_user).delete(delete_user)))
+                        .options(|| allow("GET, HEAD, PATCH, DELETE, OPTIONS")),
+                )
+                .layer(from_fn(request_context)),
+        )
+        .with_state(core_services)
+}
+
+#[derive(Serialize, Debug)]
+struct UserResponse {
+    token: String,
+    name: String,
+    email: Email,
+    age: Age,
+    version: u64,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    last_seen_at: Option<DateTime<Utc>>,
+}
+
+impl From<User> for UserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            token: user.token.to_string(),
+            name: user.name,
+            email: user.email,
+            age: user.age,
+            version: user.version,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+            last_seen_at: user.last_seen_at,
+        }
+    }
+}
+
+/// Parses a path or cursor token, reporting failures as a bad request rather
+/// than axum's plain-text path rejection.
+fn parse_token(token: &str) -> Result<UserToken, Problem> {
+    UserToken::parse(token).map_err(|e| CoreError::InvalidToken(e.to_string()).into())
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context))]
+async fn create_user(
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    format: ResponseFormat,
+    request: Result<Json<CreateUserRequest>, JsonRejection>,
+) -> Result<(StatusCode, Negotiated<UserResponse>), Problem> {
+    let Json(request) = request?;
+    let user = core_services.user_service.add_user(&context, request.into()).await?;
+    Ok((StatusCode::CREATED, Negotiated(format, user.into())))
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct ListOptions {
+    /// Token of the last user on the previous page.
+    cursor: Option<String>,
+    limit: Option<u64>,
+    /// Only users seen at or after this RFC 3339 time.
+    active_since: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug)]
+struct ListUsersResponse {
+    users: Vec<UserResponse>,
+    /// Pass back as `cursor` to fetch the next page; absent on the last page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context))]
+async fn list_users(
+    options: Result<Query<ListOptions>, QueryRejection>,
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    format: ResponseFormat,
+) -> Result<Negotiated<ListUsersResponse>, Problem> {
+    let Query(options) = options?;
+    let after_id = options.cursor.as_deref().map(parse_token).transpose()?.map(|token| token.id());
+
+    let page_size = core_services.page_size_cap.apply(options.limit);
+    let users = core_services
+        .user_service
+        .list_users(&context, after_id, Some(page_size), options.active_since)
+        .await?;
+
+    // A full page means there may be more; the client finds out for sure
+    // when the next request comes back short.
+    let next_cursor = if users.len() as u64 == page_size {
+        users.last().map(|user| user.token.to_string())
+    } else {
+        None
+    };
+
+    Ok(Negotiated(
+        format,
+        ListUsersResponse {
+            users: users.into_iter().map(Into::into).collect(),
+            next_cursor,
+        },
+    ))
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context, headers))]
+async fn get_user(
+    Path(token): Path<String>,
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    format: ResponseFormat,
+    headers: HeaderMap,
+) -> Result<Response, Problem> {
+    let token = parse_token(&token)?;
+    let user = core_services
+        .user_service
+        .find_by_token(&context, token)
+        .await?
+        .ok_or(CoreError::RepositoryError(RepositoryError::NotFound))?;
+    Ok(conditional_user_response::<UserResponse>(&headers, format, user))
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context))]
+async fn update_user(
+    Path(token): Path<String>,
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    format: ResponseFormat,
+    request: Result<Json<UpdateUserRequest>, JsonRejection>,
+) -> Result<Negotiated<UserResponse>, Problem> {
+    let token = parse_token(&token)?;
+    let Json(request) = request?;
+    let user = core_services.user_service.update_user_partial(&context, token.id(), request.into()).await?;
+    Ok(Negotiated(format, user.into()))
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context))]
+async fn delete_user(
+    Path(token): Path<String>,
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    format: ResponseFormat,
+) -> Result<Negotiated<UserResponse>, Problem> {
+    let token = parse_token(&token)?;
+    let user = core_services.user_service.delete_user(&context, token.id()).await?;
+    Ok(Negotiated(format, user.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use hex_play_core::{
+        Error, RepositoryError,
+        clock::FixedClock,
+        test_support::{MockUserService, create_arc_core_services_with_mock},
+        types::Age,
+        user::{PartialUserUpdate, User, UserId, UserToken},
+    };
+    use mockall::predicate::{always, eq};
+    use tower::ServiceExt;
+
+    use super::get_routes;
+    use crate::http::limit::RouteLimits;
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn create_test_app(mock: MockUserService) -> Router {
+        get_routes(create_arc_core_services_with_mock(mock), &RouteLimits::default())
+    }
+
+    async fn body_to_json(body: Body) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.expect("failed to read response body");
+        serde_json::from_slice(&bytes).expect("response body must be valid JSON")
+    }
+
+    // ===================
+    // Tests: POST /api/v2/user
+    // ===================
+    #[tokio::test]
+    async fn test_create_user_success() {
+        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let mut mock = MockUserService::new();
+        mock.expect_add_user().return_const(Ok(user));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v2/user")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name":"John Doe","email":"john@example.com","age":30}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = body_to_json(response.into_body()).await;
+        assert_eq!(body["token"], UserToken::new(UserId::new(1)).to_string());
+        assert!(body.get("id").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_create_user_invalid_body_is_problem() {
+        let app = create_test_app(MockUserService::new());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v2/user")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name":"John Doe","email":"not-an-email"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.headers()["content-type"], "application/problem+json");
+    }
+
+    #[tokio::test]
+    async fn test_create_user_validation_problem_is_localized() {
+        let mut mock = MockUserService::new();
+        mock.expect_add_user().return_const(Err(Age::new(200).unwrap_err()));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v2/user")
+                    .header("content-type", "application/json")
+                    .header("accept-language", "fr, de-CH;q=0.9")
+                    .body(Body::from(r#"{"name":"John Doe","email":"john@example.com","age":30}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.headers()["content-language"], "de");
+        let body = body_to_json(response.into_body()).await;
+        assert_eq!(body["detail"], "Validierungsfehler: Das Alter muss zwischen 0 und 150 liegen, erhalten: 200");
+    }
+
+    // ===================
+    // Tests: GET /api/v2/user
+    // ===================
+    #[tokio::test]
+    async fn test_list_users_full_page_has_next_cursor() {
+        let users = vec![User::fake(1, "John Doe", "john@example.com"), User::fake(2, "Jane Doe", "jane@example.com")];
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Ok(users));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(Request::builder().method("GET").uri("/api/v2/user?limit=2").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body_to_json(response.into_body()).await;
+        assert_eq!(body["users"].as_array().unwrap().len(), 2);
+        assert_eq!(body["next_cursor"], UserToken::new(UserId::new(2)).to_string());
+    }
+
+    #[tokio::test]
+    async fn test_list_users_short_page_has_no_cursor() {
+        let users = vec![User::fake(3, "John Doe", "john@example.com")];
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Ok(users));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri(format!("/api/v2/user?limit=2&cursor={}", UserToken::new(UserId::new(2))))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body_to_json(response.into_body()).await;
+        assert!(body.get("next_cursor").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_list_users_invalid_cursor() {
+        let app = create_test_app(MockUserService::new());
+
+        let response = app
+            .oneshot(Request::builder().method("GET").uri("/api/v2/user?cursor=bogus").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()["content-type"], "application/problem+json");
+    }
+
+    #[tokio::test]
+    async fn test_list_users_active_since_reports_last_seen() {
+        let mut user = User::fake(1, "John Doe", "john@example.com");
+        user.last_seen_at = Some(FixedClock::epoch());
+        let mut mock = MockUserService::new();
+        mock.expect_list_users().return_const(Ok(vec![user]));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/api/v2/user?active_since=2024-12-31T00:00:00Z")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body_to_json(response.into_body()).await;
+        assert_eq!(body["users"][0]["last_seen_at"], "2025-01-01T00:00:00Z");
+    }
+
+    #[tokio::test]
+    async fn test_list_users_invalid_active_since() {
+        let app = create_test_app(MockUserService::new());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/api/v2/user?active_since=yesterday")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()["content-type"], "application/problem+json");
+    }
+
+    // ===================
+    // Tests: GET /api/v2/user/{token}
+    // ===================
+    #[tokio::test]
+    async fn test_get_user_success() {
+        let user = User::fake(1, "John Doe", "john@example.com");
+        let token = user.token;
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_token().return_const(Ok(Some(user)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri(format!("/api/v2/user/{token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("deprecation").is_none());
+        let body = body_to_json(response.into_body()).await;
+        assert_eq!(body["name"], "John Doe");
+    }
+
+    #[tokio::test]
+    async fn test_get_user_msgpack() {
+        let user = User::fake(1, "John Doe", "john@example.com");
+        let token = user.token;
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_token().return_const(Ok(Some(user)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri(format!("/api/v2/user/{token}"))
+                    .header("accept", "application/msgpack, application/json;q=0.5")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.headers()["content-type"], "application/msgpack");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(body["token"], token.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_get_user_not_found_is_problem() {
+        let mut mock = MockUserService::new();
+        mock.expect_find_by_token().return_const(Ok(None));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri(format!("/api/v2/user/{}", UserToken::new(UserId::new(9))))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()["content-type"], "application/problem+json");
+        let body = body_to_json(response.into_body()).await;
+        assert_eq!(body["status"], 404);
+        assert_eq!(body["title"], "Not Found");
+    }
+
+    #[tokio::test]
+    async fn test_get_user_invalid_token() {
+        let app = create_test_app(MockUserService::new());
+
+        let response = app
+            .oneshot(Request::builder().method("GET").uri("/api/v2/user/not-a-token").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()["content-type"], "application/problem+json");
+    }
+
+    // ===================
+    // Tests: PATCH /api/v2/user/{token}
+    // ===================
+    #[tokio::test]
+    async fn test_update_user_conflict_is_problem() {
+        let mut mock = MockUserService::new();
+        mock.expect_update_user_partial()
+            .with(
+                always(),
+                eq(UserId::new(1)),
+                eq(PartialUserUpdate::new(Some("Updated"), None::<String>, None).unwrap()),
+            )
+            .times(1)
+            .return_const(Err(Error::RepositoryError(RepositoryError::Conflict)));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PATCH")
+                    .uri(format!("/api/v2/user/{}", UserToken::new(UserId::new(1))))
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name":"Updated"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(response.headers()["content-type"], "application/problem+json");
+    }
+
+    // ===================
+    // Tests: DELETE /api/v2/user/{token}
+    // ===================
+    #[tokio::test]
+    async fn test_delete_user_success() {
+        let user = User::fake(1, "John Doe", "john@example.com");
+        let mut mock = MockUserService::new();
+        mock.expect_delete_user().return_const(Ok(user));
+        let app = create_test_app(mock);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri(format!("/api/v2/user/{}", UserToken::new(UserId::new(1))))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,234 @@
+//! The profile of users by token, without the rest of the user.
+//!
+//! `GET /api/v1/user-info/{token}` reads it and `PATCH` changes the fields
+//! in the body, `null` clearing a field back to its default. Meant for
+//! consumers such as analytics that have no use for names and emails.
+
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json, Router,
+    extract::{Path, State},
+    middleware::from_fn,
+    routing::{get, patch},
+};
+use chrono::{DateTime, Utc};
+use hex_play_core::{
+    CoreServices,
+    context::RequestContext,
+    types::Age,
+    user::UserToken,
+    user_info::{UserInfo, UserInfoUpdate},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::http::{error::Error, limit::RouteLimits, request_context, user::deserialize_nullable};
+
+pub(crate) fn get_routes(core_services: Arc<CoreServices>, limits: &RouteLimits) -> Router {
+    Router::new()
+        .route(
+            "/api/v1/user-info/{token}",
+            limits.reads(get(get_user_info)).merge(limits.writes(patch(update_user_info))),
+        )
+        .layer(from_fn(request_context))
+        .with_state(core_services)
+}
+
+#[derive(Serialize, Debug)]
+struct UserInfoResponse {
+    token: String,
+    age: Age,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<UserInfo> for UserInfoResponse {
+    fn from(info: UserInfo) -> Self {
+        Self {
+            token: info.token.to_string(),
+            age: info.age,
+            updated_at: info.updated_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct UpdateUserInfoRequest {
+    /// `null` clears the age back to its default.
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    age: Option<Option<Age>>,
+}
+
+impl From<UpdateUserInfoRequest> for UserInfoUpdate {
+    fn from(request: UpdateUserInfoRequest) -> Self {
+        Self { age: request.age }
+    }
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context))]
+async fn get_user_info(
+    Path(token): Path<UserToken>,
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+) -> Result<Json<UserInfoResponse>, Error> {
+    let info = core_services
+        .user_info_service
+        .get_info(&context, token)
+        .await
+        .map_err(Error::Core)?
+        .ok_or(Error::NotFound)?;
+    Ok(Json(info.into()))
+}
+
+#[tracing::instrument(level = "trace", skip(core_services, context))]
+async fn update_user_info(
+    Path(token): Path<UserToken>,
+    State(core_services): State<Arc<CoreServices>>,
+    Extension(context): Extension<RequestContext>,
+    Json(request): Json<UpdateUserInfoRequest>,
+) -> Result<Json<UserInfoResponse>, Error> {
+    let info = core_services
+        .user_info_service
+        .update_info(&context, token, request.into())
+        .await
+        .map_err(Error::Core)?;
+    Ok(Json(info.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use hex_play_core::{
+        CoreServices, Error,
+        clock::FixedClock,
+        test_support::{MockUserService, create_core_services_with_mock},
+        types::Age,
+        user::{UserId, UserToken},
+        user_info::{MockUserInfoService, UserInfo, UserInfoUpdate},
+    };
+    use mockall::predicate::{always, eq};
+    use tower::ServiceExt;
+
+    use super::get_routes;
+    use crate::http::limit::RouteLimits;
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn create_test_app(mock: MockUserInfoService) -> Router {
+        let core_services = Arc::new(CoreServices {
+            user_info_service: Arc::new(mock),
+            ..create_core_services_with_mock(MockUserService::new())
+        });
+        get_routes(core_services, &RouteLimits::default())
+    }
+
+    fn fake_info(age: i16) -> UserInfo {
+        UserInfo {
+            token: UserToken::new(UserId::new(1)),
+            age: Age::new(age).unwrap(),
+            updated_at: FixedClock::epoch(),
+        }
+    }
+
+    fn uri() -> String {
+        format!("/api/v1/user-info/{}", UserToken::new(UserId::new(1)))
+    }
+
+    fn patch_request(body: &str) -> Request<Body> {
+        Request::builder()
+            .method("PATCH")
+            .uri(uri())
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    async fn body_to_json(body: Body) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    // ===================
+    // Tests: GET /api/v1/user-info/{token}
+    // ===================
+    #[tokio::test]
+    async fn test_get_user_info() {
+        let mut mock = MockUserInfoService::new();
+        mock.expect_get_info()
+            .with(always(), eq(UserToken::new(UserId::new(1))))
+            .return_const(Ok(Some(fake_info(30))));
+        let app = create_test_app(mock);
+
+        let response = app.oneshot(Request::builder().uri(uri()).body(Body::empty()).unwrap()).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body_to_json(response.into_body()).await;
+        assert_eq!(body["token"], UserToken::new(UserId::new(1)).to_string());
+        assert_eq!(body["age"], 30);
+        assert!(body.get("name").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_user_info_not_found() {
+        let mut mock = MockUserInfoService::new();
+        mock.expect_get_info().return_const(Ok(None));
+        let app = create_test_app(mock);
+
+        let response = app.oneshot(Request::builder().uri(uri()).body(Body::empty()).unwrap()).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    // ===================
+    // Tests: PATCH /api/v1/user-info/{token}
+    // ===================
+    #[tokio::test]
+    async fn test_update_user_info_sets_age() {
+        let mut mock = MockUserInfoService::new();
+        mock.expect_update_info()
+            .with(
+                always(),
+                eq(UserToken::new(UserId::new(1))),
+                eq(UserInfoUpdate {
+                    age: Some(Some(Age::new(31).unwrap())),
+                }),
+            )
+            .return_const(Ok(fake_info(31)));
+        let app = create_test_app(mock);
+
+        let response = app.oneshot(patch_request(r#"{"age":31}"#)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_to_json(response.into_body()).await["age"], 31);
+    }
+
+    #[tokio::test]
+    async fn test_update_user_info_null_clears_age() {
+        let mut mock = MockUserInfoService::new();
+        mock.expect_update_info()
+            .with(always(), always(), eq(UserInfoUpdate { age: Some(None) }))
+            .return_const(Ok(fake_info(0)));
+        let app = create_test_app(mock);
+
+        let response = app.oneshot(patch_request(r#"{"age":null}"#)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_update_user_info_propagates_error() {
+        let mut mock = MockUserInfoService::new();
+        mock.expect_update_info().return_const(Err(Error::EmptyUpdate));
+        let app = create_test_app(mock);
+
+        let response = app.oneshot(patch_request("{}")).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,266 @@
+//! Inbound webhooks.
+//!
+//! `POST /api/v1/webhooks/users` receives user changes from an external
+//! identity provider. Each delivery carries `X-Webhook-Timestamp` (Unix
+//! seconds), a unique `X-Webhook-Nonce` and `X-Webhook-Signature`, which is
+//! `sha256=` followed by the hex HMAC-SHA256 of `{timestamp}.{nonce}.{body}`
+//! keyed with the shared secret. A bad signature gets 401, a replayed or
+//! expired delivery 409 and an applied one 204.
+
+use std::sync::Arc;
+
+use axum::{
+    Extension, Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    middleware::from_fn,
+    routing::post,
+};
+use chrono::DateTime;
+use hex_play_core::{
+    CoreServices,
+    context::RequestContext,
+    types::{Age, Email},
+    user::{NewUser, PartialUserUpdate},
+    webhook::{Delivery, UserSyncEvent},
+};
+use hex_play_utils::secret::Secret;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use crate::http::{error::Error, limit::RouteLimits, request_context};
+
+const TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+const NONCE_HEADER: &str = "x-webhook-nonce";
+const SIGNATURE_HEADER: &str = "x-webhook-signature";
+/// Longest nonce accepted, as nonces are stored until they expire.
+const MAX_NONCE_LEN: usize = 128;
+
+struct WebhookState {
+    core_services: Arc<CoreServices>,
+    secret: Secret,
+}
+
+pub(crate) fn get_routes(core_services: Arc<CoreServices>, secret: Secret, limits: &RouteLimits) -> Router {
+    Router::new()
+        .route("/api/v1/webhooks/users", limits.writes(post(receive_user_notification)))
+        .layer(from_fn(request_context))
+        .with_state(Arc::new(WebhookState { core_services, secret }))
+}
+
+/// A user change as sent by the identity provider, which knows users by
+/// their email.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type")]
+enum UserNotification {
+    #[serde(rename = "user.created")]
+    Created {
+        name: String,
+        email: Email,
+        #[serde(default)]
+        age: Age,
+    },
+    #[serde(rename = "user.updated")]
+    Updated { email: Email, changes: UserChanges },
+    #[serde(rename = "user.deleted")]
+    Deleted { email: Email },
+}
+
+#[derive(Deserialize, Debug)]
+struct UserChanges {
+    name: Option<String>,
+    email: Option<Email>,
+    age: Option<Age>,
+}
+
+impl From<UserNotification> for UserSyncEvent {
+    fn from(notification: UserNotification) -> Self {
+        match notification {
+            UserNotification::Created { name, email, age } => UserSyncEvent::Created(NewUser { name, email, age }),
+            UserNotification::Updated { email, changes } => UserSyncEvent::Updated {
+                email,
+                update: PartialUserUpdate {
+                    name: changes.name,
+                    email: changes.email,
+                    age: changes.age.map(Some),
+                    expected_version: None,
+                },
+            },
+            UserNotification::Deleted { email } => UserSyncEvent::Deleted { email },
+        }
+    }
+}
+
+#[tracing::instrument(level = "trace", skip(state, context, headers, body))]
+async fn receive_user_notification(
+    State(state): State<Arc<WebhookState>>,
+    Extension(context): Extension<RequestContext>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, Error> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| Error::BadRequest(format!("missing {name} header")))
+    };
+    let (timestamp, nonce, signature) = (header(TIMESTAMP_HEADER)?, header(NONCE_HEADER)?, header(SIGNATURE_HEADER)?);
+    if !verify_signature(&state.secret, timestamp, nonce, &body, signature) {
+        return Err(Error::InvalidSignature);
+    }
+    if nonce.is_empty() || nonce.len() > MAX_NONCE_LEN {
+        return Err(Error::BadRequest(format!("{NONCE_HEADER} must be 1 to {MAX_NONCE_LEN} characters")));
+    }
+    let sent_at = timestamp
+        .parse()
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .ok_or_else(|| Error::BadRequest(format!("{TIMESTAMP_HEADER} must be Unix seconds")))?;
+    let notification: UserNotification = serde_json::from_slice(&body).map_err(|e| Error::BadRequest(e.to_string()))?;
+
+    let delivery = Delivery {
+        nonce: nonce.to_string(),
+        sent_at,
+    };
+    state
+        .core_services
+        .webhook_service
+        .receive_user_event(&context, delivery, notification.into())
+        .await
+        .map_err(Error::Core)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Checks `signature` against the HMAC of the delivery in constant time.
+fn verify_signature(secret: &Secret, timestamp: &str, nonce: &str, body: &[u8], signature: &str) -> bool {
+    let Some(signature) = signature.strip_prefix("sha256=").and_then(|hex| hex::decode(hex).ok()) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose().as_bytes()).expect("HMAC takes keys of any length");
+    for part in [timestamp.as_bytes(), b".", nonce.as_bytes(), b".", body] {
+        mac.update(part);
+    }
+    mac.verify_slice(&signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use hex_play_core::{
+        Error,
+        test_support::{MockUserService, MockWebhookService, create_core_services_with_mock},
+        webhook::UserSyncEvent,
+    };
+    use hex_play_utils::secret::Secret;
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+    use tower::ServiceExt;
+
+    use super::get_routes;
+    use crate::http::limit::RouteLimits;
+
+    const SECRET: &str = "webhook-secret";
+    const CREATED: &str = r#"{"type":"user.created","name":"Jane","email":"jane@example.com","age":30}"#;
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn create_test_app(mock: MockWebhookService) -> Router {
+        let mut core_services = create_core_services_with_mock(MockUserService::new());
+        core_services.webhook_service = Arc::new(mock);
+        get_routes(Arc::new(core_services), Secret::new(SECRET), &RouteLimits::default())
+    }
+
+    fn sign(timestamp: &str, nonce: &str, body: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).unwrap();
+        mac.update(format!("{timestamp}.{nonce}.{body}").as_bytes());
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    fn delivery(body: &'static str, signature: &str) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri("/api/v1/webhooks/users")
+            .header("x-webhook-timestamp", "1735689600")
+            .header("x-webhook-nonce", "nonce-1")
+            .header("x-webhook-signature", signature)
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    // ===================
+    // Tests: POST /api/v1/webhooks/users
+    // ===================
+    #[tokio::test]
+    async fn test_signed_delivery_is_applied() {
+        let mut mock = MockWebhookService::new();
+        mock.expect_receive_user_event()
+            .withf(|_, delivery, event| {
+                delivery.nonce == "nonce-1"
+                    && delivery.sent_at.timestamp() == 1735689600
+                    && matches!(event, UserSyncEvent::Created(user) if user.name == "Jane")
+            })
+            .times(1)
+            .return_const(Ok(None));
+        let app = create_test_app(mock);
+
+        let response = app.oneshot(delivery(CREATED, &sign("1735689600", "nonce-1", CREATED))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_bad_signature_is_unauthorized() {
+        let mut mock = MockWebhookService::new();
+        mock.expect_receive_user_event().never();
+        let app = create_test_app(mock);
+
+        let response = app.oneshot(delivery(CREATED, &sign("1735689600", "nonce-2", CREATED))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_missing_headers_is_bad_request() {
+        let app = create_test_app(MockWebhookService::new());
+        let request = Request::builder()
+            .method("POST")
+            .uri("/api/v1/webhooks/users")
+            .body(Body::from(CREATED))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_notification_type_is_bad_request() {
+        let body = r#"{"type":"user.renamed","email":"jane@example.com"}"#;
+        let app = create_test_app(MockWebhookService::new());
+
+        let response = app.oneshot(delivery(body, &sign("1735689600", "nonce-1", body))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_replayed_delivery_is_conflict() {
+        let mut mock = MockWebhookService::new();
+        mock.expect_receive_user_event().return_const(Err(Error::WebhookReplay));
+        let app = create_test_app(mock);
+
+        let response = app.oneshot(delivery(CREATED, &sign("1735689600", "nonce-1", CREATED))).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+}
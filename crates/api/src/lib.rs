@@ -1,30 +1,122 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use hex_play_core::{CoreServices, Error};
+use serde_json::Value;
 use tokio_graceful_shutdown::{IntoSubsystem, SubsystemBuilder, SubsystemHandle};
 
-use crate::{grpc::GrpcSubsystem, http::HttpSubsystem};
+use crate::{auth::ApiKeys, bind::Bind, grpc::GrpcSubsystem, http::HttpSubsystem};
 
+mod access_log;
+mod auth;
+mod bind;
+mod context;
 mod error;
+#[cfg(feature = "fault-injection")]
+mod faults;
 pub mod grpc;
 mod http;
+mod prometheus;
+mod reporting;
+mod request_metrics;
+mod slo;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+mod trace_context;
+mod unix_socket;
 
+pub use access_log::ACCESS_LOG_TARGET;
+pub use auth::ApiKeyConfig;
 pub use error::ApiError;
+#[cfg(feature = "fault-injection")]
+pub use faults::FaultInjectionConfig;
+pub use grpc::GrpcConfig;
+pub use http::{ConcurrencyLimits, HttpConfig, LISTEN_ADDR as HTTP_LISTEN_ADDR};
+pub use prometheus::install_metrics_recorder;
+pub use slo::SloConfig;
+pub use unix_socket::{UnixSocketConfig, parse_mode as parse_socket_mode};
 
+/// Serves the HTTP API and gRPC, each of which may be turned off so a
+/// deployment can run them in separate processes.
 pub struct ApiSubsystem {
     core_services: Arc<CoreServices>,
+    http_config: HttpConfig,
+    grpc_config: GrpcConfig,
+    api_keys: ApiKeys,
+    config_schema: Option<Arc<Value>>,
+    serve_http: bool,
+    serve_grpc: bool,
+}
+
+impl ApiSubsystem {
+    /// Authenticates requests carrying one of `api_keys`, keyed by name.
+    /// Without any, every request is anonymous.
+    pub fn with_api_keys(mut self, api_keys: &HashMap<String, ApiKeyConfig>) -> Self {
+        self.api_keys = ApiKeys::new(api_keys);
+        self
+    }
+
+    /// Serves `schema`, the JSON schema of the server's settings, at
+    /// `GET /admin/v1/config-schema`.
+    pub fn with_config_schema(mut self, schema: Value) -> Self {
+        self.config_schema = Some(Arc::new(schema));
+        self
+    }
+
+    /// Starts no HTTP listeners, admin listeners included. gRPC then gets
+    /// listeners of its own even if it was to share the HTTP port.
+    pub fn without_http(mut self) -> Self {
+        self.serve_http = false;
+        self
+    }
+
+    /// Starts no gRPC listeners, nor serves gRPC on the HTTP port.
+    pub fn without_grpc(mut self) -> Self {
+        self.serve_grpc = false;
+        self
+    }
 }
 
 impl IntoSubsystem<Error> for ApiSubsystem {
     async fn run(self, subsys: &mut SubsystemHandle) -> Result<(), Error> {
         tracing::info!("ApiSubsystem starting...");
-        let http_subsystem = HttpSubsystem::new(self.core_services.clone());
-        let grpc_subsystem = GrpcSubsystem::new(self.core_services.clone());
-
-        subsys.start(SubsystemBuilder::new("Http", http_subsystem.into_subsystem()));
-        subsys.start(SubsystemBuilder::new("Grpc", grpc_subsystem.into_subsystem()));
+        let grpc_on_http_port = self.serve_http && self.serve_grpc && self.grpc_config.single_port;
+        if self.serve_http {
+            // Shared by both apps, so the admin listeners can change the
+            // limits of the API and report its objectives.
+            let limits = http::RouteLimits::new(&self.http_config.concurrency_limits);
+            let slos = slo::SloTracker::new(&self.http_config.slos);
+            let mut app = http::app(
+                self.core_services.clone(),
+                &self.http_config,
+                &self.api_keys,
+                &limits,
+                &slos,
+                self.config_schema.clone(),
+            );
+            if grpc_on_http_port {
+                app = http::multiplex(
+                    app,
+                    grpc::routes(self.core_services.clone(), &self.grpc_config, &self.api_keys).into_axum_router(),
+                );
+            }
+            for bind in binds(self.http_config.listen_addrs(), &self.http_config.unix_socket) {
+                let http_subsystem = HttpSubsystem::new(app.clone(), &self.http_config, bind.clone());
+                subsys.start(SubsystemBuilder::new(format!("Http {bind}"), http_subsystem.into_subsystem()));
+            }
+            let admin_app = http::admin_app(self.core_services.clone(), &self.api_keys, &limits, &slos, self.config_schema.clone());
+            for addr in &self.http_config.admin_listen_addrs {
+                let admin_subsystem = HttpSubsystem::new(admin_app.clone(), &self.http_config, Bind::Tcp(addr.clone()));
+                subsys.start(SubsystemBuilder::new(format!("HttpAdmin {addr}"), admin_subsystem.into_subsystem()));
+            }
+        }
+        if self.serve_grpc && !grpc_on_http_port {
+            for bind in binds(self.grpc_config.listen_addrs(), &self.grpc_config.unix_socket) {
+                let grpc_subsystem = GrpcSubsystem::new(self.core_services.clone(), self.grpc_config.clone(), self.api_keys.clone(), bind.clone());
+                subsys.start(SubsystemBuilder::new(format!("Grpc {bind}"), grpc_subsystem.into_subsystem()));
+            }
+        }
 
-        tracing::info!("ApiSubsystem started");
+        tracing::info!(http = self.serve_http, grpc = self.serve_grpc, "ApiSubsystem started");
 
         subsys.on_shutdown_requested().await;
         tracing::info!("ApiSubsystem shutting down");
@@ -33,6 +125,29 @@ impl IntoSubsystem<Error> for ApiSubsystem {
     }
 }
 
+/// Every TCP address plus the Unix socket, if one is configured.
+fn binds(addrs: Vec<&str>, unix_socket: &Option<UnixSocketConfig>) -> Vec<Bind> {
+    addrs
+        .into_iter()
+        .map(|addr| Bind::Tcp(addr.to_string()))
+        .chain(unix_socket.clone().map(Bind::Unix))
+        .collect()
+}
+
 pub fn create_api_subsystem(core_services: Arc<CoreServices>) -> ApiSubsystem {
-    ApiSubsystem { core_services }
+    create_api_subsystem_with_config(core_services, HttpConfig::default(), GrpcConfig::default())
+}
+
+/// Same as [`create_api_subsystem`], but with the given HTTP and gRPC
+/// settings.
+pub fn create_api_subsystem_with_config(core_services: Arc<CoreServices>, http_config: HttpConfig, grpc_config: GrpcConfig) -> ApiSubsystem {
+    ApiSubsystem {
+        core_services,
+        http_config,
+        grpc_config,
+        api_keys: ApiKeys::default(),
+        config_schema: None,
+        serve_http: true,
+        serve_grpc: true,
+    }
 }
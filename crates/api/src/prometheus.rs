@@ -0,0 +1,240 @@
+//! Publishing the metrics of the process for Prometheus to scrape.
+//!
+//! Everything recorded through the `metrics` facade, by the repository, the
+//! caches and the request middleware, lands in one registry once
+//! [`install_metrics_recorder`] has run. `GET /admin/v1/metrics` renders it
+//! in the OpenMetrics text format. Each metric is registered the first time
+//! it is recorded, histograms with latency buckets if their name ends in
+//! `_seconds` and with count buckets otherwise.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, LazyLock, Mutex, MutexGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use metrics::{Counter, CounterFn, Gauge, GaugeFn, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+use prometheus_client::{
+    encoding::text::encode,
+    metrics::{
+        counter::Counter as RegistryCounter,
+        family::Family,
+        gauge::Gauge as RegistryGauge,
+        histogram::{Histogram as RegistryHistogram, exponential_buckets},
+    },
+    registry::Registry,
+};
+
+use crate::ApiError;
+
+/// Content type of [`render`]ed metrics.
+pub(crate) const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Label names and values of a series.
+pub(crate) type Labels = Vec<(String, String)>;
+
+type CounterFamily = Family<Labels, RegistryCounter>;
+type GaugeFamily = Family<Labels, RegistryGauge<f64, AtomicU64>>;
+type HistogramFamily = Family<Labels, RegistryHistogram, fn() -> RegistryHistogram>;
+
+/// The registry every metric of the process is published from.
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(Mutex::default);
+
+/// Registers `metric` under `name` to be rendered.
+pub(crate) fn register(name: &str, help: &str, metric: impl prometheus_client::registry::Metric) {
+    registry().register(name, help, metric);
+}
+
+/// Every registered metric in the OpenMetrics text format.
+pub(crate) fn render() -> String {
+    let mut body = String::new();
+    encode(&mut body, &registry()).expect("writing to a String cannot fail");
+    body
+}
+
+fn registry() -> MutexGuard<'static, Registry> {
+    // A panic while registering leaves the registry usable.
+    REGISTRY.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Buckets of histograms of durations, from half a millisecond to 16 s.
+pub(crate) fn latency_buckets() -> impl Iterator<Item = f64> {
+    exponential_buckets(0.0005, 2.0, 16)
+}
+
+fn latency_histogram() -> RegistryHistogram {
+    RegistryHistogram::new(latency_buckets())
+}
+
+/// Buckets of histograms of counts, from 1 to 2048.
+fn count_histogram() -> RegistryHistogram {
+    RegistryHistogram::new(exponential_buckets(1.0, 2.0, 12))
+}
+
+/// Makes the `metrics` facade record into the registry served at
+/// `GET /admin/v1/metrics`. Until it runs, what is recorded through the
+/// facade is dropped.
+///
+/// # Errors
+///
+/// Fails if a recorder is already installed.
+pub fn install_metrics_recorder() -> Result<(), ApiError> {
+    metrics::set_global_recorder(RegistryRecorder::default()).map_err(|e| ApiError::MetricsRecorder(e.to_string()))
+}
+
+/// Records into the families of [`REGISTRY`], one per metric name.
+#[derive(Default)]
+pub(crate) struct RegistryRecorder {
+    descriptions: Mutex<HashMap<String, SharedString>>,
+    counters: Mutex<HashMap<String, CounterFamily>>,
+    gauges: Mutex<HashMap<String, GaugeFamily>>,
+    histograms: Mutex<HashMap<String, HistogramFamily>>,
+}
+
+impl RegistryRecorder {
+    /// The family of metrics named `name`, registered on first use.
+    /// Counters are registered without their `_total` suffix, which the
+    /// OpenMetrics encoding adds back.
+    fn family<F: prometheus_client::registry::Metric + Clone>(&self, families: &Mutex<HashMap<String, F>>, name: &str, new: impl FnOnce() -> F) -> F {
+        let mut families = families.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        families
+            .entry(name.to_string())
+            .or_insert_with(|| {
+                let family = new();
+                let help = self.description(name);
+                register(name.strip_suffix("_total").unwrap_or(name), &help, family.clone());
+                family
+            })
+            .clone()
+    }
+
+    fn description(&self, name: &str) -> String {
+        let descriptions = self.descriptions.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        descriptions.get(name).map(ToString::to_string).unwrap_or_default()
+    }
+
+    fn describe(&self, key: KeyName, description: SharedString) {
+        let mut descriptions = self.descriptions.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        descriptions.insert(key.as_str().to_string(), description);
+    }
+}
+
+fn labels(key: &Key) -> Labels {
+    key.labels().map(|label| (label.key().to_string(), label.value().to_string())).collect()
+}
+
+impl Recorder for RegistryRecorder {
+    fn describe_counter(&self, key: KeyName, _unit: Option<Unit>, description: SharedString) {
+        self.describe(key, description);
+    }
+
+    fn describe_gauge(&self, key: KeyName, _unit: Option<Unit>, description: SharedString) {
+        self.describe(key, description);
+    }
+
+    fn describe_histogram(&self, key: KeyName, _unit: Option<Unit>, description: SharedString) {
+        self.describe(key, description);
+    }
+
+    fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+        let family = self.family(&self.counters, key.name(), CounterFamily::default);
+        let counter = family.get_or_create(&labels(key)).clone();
+        Counter::from_arc(Arc::new(CounterHandle(counter)))
+    }
+
+    fn register_gauge(&self, key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+        let family = self.family(&self.gauges, key.name(), GaugeFamily::default);
+        let gauge = family.get_or_create(&labels(key)).clone();
+        Gauge::from_arc(Arc::new(GaugeHandle(gauge)))
+    }
+
+    fn register_histogram(&self, key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+        let constructor: fn() -> RegistryHistogram = if key.name().ends_with("_seconds") {
+            latency_histogram
+        } else {
+            count_histogram
+        };
+        let family = self.family(&self.histograms, key.name(), || HistogramFamily::new_with_constructor(constructor));
+        let histogram = family.get_or_create(&labels(key)).clone();
+        Histogram::from_arc(Arc::new(HistogramHandle(histogram)))
+    }
+}
+
+struct CounterHandle(RegistryCounter);
+
+impl CounterFn for CounterHandle {
+    fn increment(&self, value: u64) {
+        self.0.inc_by(value);
+    }
+
+    fn absolute(&self, value: u64) {
+        self.0.inner().fetch_max(value, Ordering::Relaxed);
+    }
+}
+
+struct GaugeHandle(RegistryGauge<f64, AtomicU64>);
+
+impl GaugeFn for GaugeHandle {
+    fn increment(&self, value: f64) {
+        self.0.inc_by(value);
+    }
+
+    fn decrement(&self, value: f64) {
+        self.0.dec_by(value);
+    }
+
+    fn set(&self, value: f64) {
+        self.0.set(value);
+    }
+}
+
+struct HistogramHandle(RegistryHistogram);
+
+impl HistogramFn for HistogramHandle {
+    fn record(&self, value: f64) {
+        self.0.observe(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RegistryRecorder, render};
+
+    // ===================
+    // Tests: RegistryRecorder
+    // ===================
+    #[test]
+    fn test_recorded_metrics_are_rendered() {
+        let recorder = RegistryRecorder::default();
+
+        metrics::with_local_recorder(&recorder, || {
+            metrics::describe_histogram!("test_render_duration_seconds", "Time taken to render");
+            metrics::histogram!("test_render_duration_seconds", "op" => "find").record(0.003);
+            metrics::counter!("test_render_calls_total", "op" => "find").increment(2);
+            metrics::gauge!("test_render_in_flight").set(3.0);
+        });
+
+        let rendered = render();
+        assert!(rendered.contains("# HELP test_render_duration_seconds Time taken to render."), "{rendered}");
+        assert!(
+            rendered.contains(r#"test_render_duration_seconds_bucket{le="0.004",op="find"} 1"#),
+            "{rendered}"
+        );
+        assert!(rendered.contains(r#"test_render_duration_seconds_count{op="find"} 1"#), "{rendered}");
+        assert!(rendered.contains(r#"test_render_calls_total{op="find"} 2"#), "{rendered}");
+        assert!(rendered.contains("test_render_in_flight{} 3.0"), "{rendered}");
+        assert!(rendered.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn test_histograms_of_counts_get_count_buckets() {
+        let recorder = RegistryRecorder::default();
+
+        metrics::with_local_recorder(&recorder, || metrics::histogram!("test_render_statements").record(5.0));
+
+        let rendered = render();
+        assert!(rendered.contains(r#"test_render_statements_bucket{le="8.0"} 1"#), "{rendered}");
+    }
+}
@@ -0,0 +1,176 @@
+//! Sends internal failures of HTTP and gRPC requests to the
+//! [`ErrorReporter`].
+//!
+//! Error mappers and panic handlers mark a failed response with a
+//! [`Failure`] saying what went wrong, and [`report_failures`], running
+//! outside them, reports it with the request id. A `500` or gRPC `INTERNAL`
+//! response without a mark is reported too, by its status.
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use hex_play_core::reporting::{ErrorReport, ErrorReporter};
+use tonic::{Code, Status};
+
+use crate::context::REQUEST_ID_HEADER;
+
+/// Response extension describing an internal failure.
+#[derive(Debug, Clone)]
+pub(crate) struct Failure {
+    message: String,
+    panic: bool,
+    user_id: Option<String>,
+}
+
+impl Failure {
+    /// Marks `response` as failed with `message` if it is a `500`.
+    pub(crate) fn mark(response: &mut Response, message: impl Into<String>) {
+        if response.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            response.extensions_mut().insert(Failure {
+                message: message.into(),
+                panic: false,
+                user_id: None,
+            });
+        }
+    }
+
+    /// Marks `response` as the result of a handler panicking with `message`.
+    pub(crate) fn mark_panic<B>(response: &mut axum::http::Response<B>, message: impl Into<String>) {
+        response.extensions_mut().insert(Failure {
+            message: message.into(),
+            panic: true,
+            user_id: None,
+        });
+    }
+
+    /// Adds the user the request acted for to the failure `response` is
+    /// marked with, if any.
+    pub(crate) fn attribute(response: &mut Response, user_id: Option<&str>) {
+        if let Some(failure) = response.extensions_mut().get_mut::<Failure>() {
+            failure.user_id = user_id.map(str::to_string);
+        }
+    }
+}
+
+/// Reports the failure a response is marked with, or an unmarked internal
+/// failure by its status.
+pub(crate) async fn report_failures(State(reporter): State<Arc<dyn ErrorReporter>>, request: Request<Body>, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let mut response = next.run(request).await;
+
+    let failure = response.extensions_mut().remove::<Failure>().or_else(|| unmarked_failure(&response));
+    if let Some(failure) = failure {
+        reporter.report(ErrorReport {
+            message: failure.message,
+            panic: failure.panic,
+            request_id,
+            user_id: failure.user_id,
+        });
+    }
+    response
+}
+
+/// An HTTP `500` or a gRPC call that failed with `INTERNAL` in its headers,
+/// as unary calls do. Errors a gRPC stream ends with are in its trailers and
+/// are not seen here.
+fn unmarked_failure(response: &Response) -> Option<Failure> {
+    let message = match Status::from_header_map(response.headers()) {
+        Some(status) if status.code() == Code::Internal => status.message().to_string(),
+        Some(_) => return None,
+        None if response.status() == StatusCode::INTERNAL_SERVER_ERROR => "Internal server error".to_string(),
+        None => return None,
+    };
+    Some(Failure {
+        message,
+        panic: false,
+        user_id: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        Router,
+        body::Body,
+        extract::Request,
+        http::StatusCode,
+        middleware::{Next, from_fn, from_fn_with_state},
+        response::{IntoResponse, Response},
+        routing::get,
+    };
+    use hex_play_core::test_support::MockErrorReporter;
+    use tower::ServiceExt;
+
+    use super::{Failure, report_failures};
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn create_test_app(reporter: MockErrorReporter, router: Router) -> Router {
+        router.layer(from_fn_with_state(Arc::new(reporter) as _, report_failures))
+    }
+
+    async fn as_user_42(request: Request, next: Next) -> Response {
+        let mut response = next.run(request).await;
+        Failure::attribute(&mut response, Some("42"));
+        response
+    }
+
+    async fn failing_handler() -> Response {
+        let mut response = StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        Failure::mark(&mut response, "disk full");
+        response
+    }
+
+    // ===================
+    // Tests: report_failures
+    // ===================
+    #[tokio::test]
+    async fn test_failure_is_reported_with_its_user() {
+        let mut reporter = MockErrorReporter::new();
+        reporter
+            .expect_report()
+            .withf(|report| report.message == "disk full" && report.user_id.as_deref() == Some("42"))
+            .times(1)
+            .return_const(());
+        let app = create_test_app(reporter, Router::new().route("/", get(failing_handler)).layer(from_fn(as_user_42)));
+
+        let response = app.oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_unmarked_server_error_is_reported_by_status() {
+        let mut reporter = MockErrorReporter::new();
+        reporter
+            .expect_report()
+            .withf(|report| report.message == "Internal server error" && report.user_id.is_none())
+            .times(1)
+            .return_const(());
+        let app = create_test_app(reporter, Router::new().route("/", get(|| async { StatusCode::INTERNAL_SERVER_ERROR })));
+
+        app.oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_is_not_reported() {
+        let mut reporter = MockErrorReporter::new();
+        reporter.expect_report().never();
+        let app = create_test_app(reporter, Router::new().route("/", get(|| async { StatusCode::SERVICE_UNAVAILABLE })));
+
+        app.oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+    }
+}
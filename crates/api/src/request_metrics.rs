@@ -0,0 +1,147 @@
+//! Latency histograms of the requests served, in seconds:
+//! `http_request_duration_seconds` by method, matched route and status, and
+//! `grpc_request_duration_seconds` by method.
+//!
+//! They are recorded inside the request span, and each bucket keeps the
+//! trace id of the last request counted in it as an exemplar, so a slow
+//! bucket leads to a trace of a slow request. The `metrics` facade has no
+//! way to pass exemplars, so the histograms are registered with the
+//! [`prometheus`](crate::prometheus) registry directly.
+
+use std::{sync::LazyLock, time::Instant};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use prometheus_client::metrics::{exemplar::HistogramWithExemplars, family::Family};
+
+use crate::{
+    prometheus::{self, Labels},
+    trace_context,
+};
+
+type LatencyFamily = Family<Labels, HistogramWithExemplars<Labels>, fn() -> HistogramWithExemplars<Labels>>;
+
+static HTTP_REQUEST_DURATION: LazyLock<LatencyFamily> = LazyLock::new(|| latency_family("http_request_duration_seconds", "Time taken to serve HTTP requests"));
+
+static GRPC_REQUEST_DURATION: LazyLock<LatencyFamily> = LazyLock::new(|| latency_family("grpc_request_duration_seconds", "Time taken to answer gRPC calls"));
+
+fn latency_family(name: &str, help: &str) -> LatencyFamily {
+    let family = LatencyFamily::new_with_constructor(|| HistogramWithExemplars::new(prometheus::latency_buckets()));
+    prometheus::register(name, help, family.clone());
+    family
+}
+
+/// Records `seconds` in the series of `labels`, with the trace of the
+/// current span as the exemplar if it belongs to one.
+fn observe(family: &LatencyFamily, labels: Labels, seconds: f64) {
+    let exemplar = trace_context::current_trace_id().map(|trace_id| vec![("trace_id".to_string(), trace_id)]);
+    family.get_or_create(&labels).observe(seconds, exemplar);
+}
+
+/// Route or method label of requests matching none, so unknown paths
+/// cannot grow the number of series.
+const UNMATCHED: &str = "unmatched";
+
+/// `grpc-status` of calls to a method no service has.
+const UNIMPLEMENTED: &str = "12";
+
+pub(crate) async fn record_http(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request.extensions().get::<MatchedPath>().map_or(UNMATCHED, MatchedPath::as_str).to_string();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    let labels = vec![
+        ("method".to_string(), method),
+        ("route".to_string(), route),
+        ("status".to_string(), response.status().as_str().to_string()),
+    ];
+    observe(&HTTP_REQUEST_DURATION, labels, started.elapsed().as_secs_f64());
+    response
+}
+
+/// Times until the response head is sent; for streaming calls that is
+/// before the last message.
+pub(crate) async fn record_grpc(request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_string();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    let method = if response.headers().get("grpc-status").is_some_and(|status| status == UNIMPLEMENTED) {
+        UNMATCHED.to_string()
+    } else {
+        path
+    };
+    observe(&GRPC_REQUEST_DURATION, vec![("method".to_string(), method)], started.elapsed().as_secs_f64());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderMap, HeaderValue};
+    use opentelemetry::{global, trace::TracerProvider as _};
+    use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider};
+    use tracing_subscriber::{Registry, layer::SubscriberExt as _};
+
+    use super::{HTTP_REQUEST_DURATION, observe};
+    use crate::{prometheus::render, trace_context::continue_trace};
+
+    // ===================
+    // Tests: observe
+    // ===================
+    #[test]
+    fn test_observation_in_trace_is_its_buckets_exemplar() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let tracer = SdkTracerProvider::builder().build().tracer("test");
+        let subscriber = Registry::default().with(tracing_opentelemetry::layer().with_tracer(tracer));
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("http", trace_id = tracing::field::Empty);
+            continue_trace(&span, &headers);
+            let _entered = span.enter();
+
+            observe(&HTTP_REQUEST_DURATION, labels("/test/traced"), 0.003);
+        });
+
+        let rendered = render();
+        assert!(
+            rendered.contains(
+                r#"http_request_duration_seconds_bucket{le="0.004",method="GET",route="/test/traced",status="200"} 1 # {trace_id="4bf92f3577b34da6a3ce929d0e0e4736"} 0.003"#
+            ),
+            "{rendered}"
+        );
+    }
+
+    #[test]
+    fn test_observation_outside_trace_has_no_exemplar() {
+        observe(&HTTP_REQUEST_DURATION, labels("/test/untraced"), 0.003);
+
+        let rendered = render();
+        assert!(
+            rendered.contains(r#"http_request_duration_seconds_bucket{le="0.004",method="GET",route="/test/untraced",status="200"} 1"#),
+            "{rendered}"
+        );
+        assert!(!rendered.contains(r#"route="/test/untraced",status="200"} 1 #"#), "{rendered}");
+    }
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn labels(route: &str) -> Vec<(String, String)> {
+        vec![
+            ("method".to_string(), "GET".to_string()),
+            ("route".to_string(), route.to_string()),
+            ("status".to_string(), "200".to_string()),
+        ]
+    }
+}
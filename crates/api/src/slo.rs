@@ -0,0 +1,401 @@
+//! Service level objectives per route, tracked over the requests this
+//! instance serves.
+//!
+//! An objective sets the share of requests to a route that should be
+//! answered without a server error, or within a latency threshold. Requests
+//! are counted per minute over the last hour, which the error budget rolls
+//! with. The burn rate is how fast the budget is spent, 1 spending it
+//! exactly over the hour. A warning is logged, at most once a minute, while
+//! both the last hour and the last five minutes burn faster than the alert
+//! threshold, so a short spike alone does not warn and neither does an
+//! hour that has recovered.
+//!
+//! The counts are those of this instance: the `metrics` facade has no
+//! registry to read the request histograms back from.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Milliseconds under which requests meet a latency objective unless
+/// configured otherwise.
+pub const DEFAULT_LATENCY_THRESHOLD_MS: u64 = 500;
+
+/// Burn rate warned about unless configured otherwise, the usual threshold
+/// for paging on a 30 day objective.
+pub const DEFAULT_ALERT_BURN_RATE: f64 = 14.4;
+
+/// Minutes the error budget rolls over.
+const LONG_WINDOW_MINUTES: u64 = 60;
+
+/// Minutes that must burn too, so an hour that has recovered does not warn.
+const SHORT_WINDOW_MINUTES: u64 = 5;
+
+/// Objectives of one route.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct SloConfig {
+    /// Route template as matched, e.g. `/api/v1/user/{id}`.
+    pub route: String,
+
+    /// (optional) Share of requests to answer without a server error, e.g.
+    /// `0.999`.
+    #[serde(default)]
+    pub availability: Option<f64>,
+
+    /// (optional) Share of requests to answer within
+    /// `latency_threshold_ms`, e.g. `0.99`.
+    #[serde(default)]
+    pub latency: Option<f64>,
+
+    /// (optional) Milliseconds under which a request meets the latency
+    /// objective. Defaults to 500.
+    #[serde(default)]
+    #[schemars(extend("default" = DEFAULT_LATENCY_THRESHOLD_MS))]
+    pub latency_threshold_ms: Option<u64>,
+
+    /// (optional) Burn rate over both windows above which a warning is
+    /// logged. Defaults to 14.4.
+    #[serde(default)]
+    #[schemars(extend("default" = DEFAULT_ALERT_BURN_RATE))]
+    pub alert_burn_rate: Option<f64>,
+}
+
+/// Counts the requests of every objective. Clones share the counts.
+#[derive(Clone)]
+pub(crate) struct SloTracker {
+    objectives: Arc<Vec<Objective>>,
+    started: Instant,
+}
+
+struct Objective {
+    name: String,
+    route: String,
+    kind: Kind,
+    target: f64,
+    alert_burn_rate: f64,
+    minutes: Mutex<VecDeque<Minute>>,
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Availability,
+    Latency(Duration),
+}
+
+/// Requests counted in one minute since the tracker started.
+#[derive(Clone, Copy)]
+struct Minute {
+    minute: u64,
+    total: u64,
+    bad: u64,
+}
+
+/// How an objective is doing, as `GET /admin/v1/slo` reports it.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct SloReport {
+    pub(crate) name: String,
+    pub(crate) route: String,
+    /// `availability` or `latency`.
+    pub(crate) objective: String,
+    pub(crate) target: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) latency_threshold_ms: Option<u64>,
+    /// Requests over the last hour.
+    pub(crate) requests: u64,
+    /// Requests over the last hour that failed the objective.
+    pub(crate) bad_requests: u64,
+    /// Share of the error budget of the last hour left, negative once
+    /// overspent.
+    pub(crate) budget_remaining: f64,
+    pub(crate) burn_rate_1h: f64,
+    pub(crate) burn_rate_5m: f64,
+    /// Whether both burn rates are above the alert threshold.
+    pub(crate) alerting: bool,
+}
+
+impl SloTracker {
+    /// Tracks the objectives of `slos`, named by their keys.
+    pub(crate) fn new(slos: &HashMap<String, SloConfig>) -> Self {
+        let mut objectives = Vec::new();
+        for (name, slo) in slos {
+            let latency_threshold = Duration::from_millis(slo.latency_threshold_ms.unwrap_or(DEFAULT_LATENCY_THRESHOLD_MS));
+            let kinds = [
+                slo.availability.map(|target| (Kind::Availability, target)),
+                slo.latency.map(|target| (Kind::Latency(latency_threshold), target)),
+            ];
+            objectives.extend(kinds.into_iter().flatten().map(|(kind, target)| Objective {
+                name: name.clone(),
+                route: slo.route.clone(),
+                kind,
+                target,
+                alert_burn_rate: slo.alert_burn_rate.unwrap_or(DEFAULT_ALERT_BURN_RATE),
+                minutes: Mutex::new(VecDeque::new()),
+            }));
+        }
+        objectives.sort_by(|a, b| (&a.name, a.kind.label()).cmp(&(&b.name, b.kind.label())));
+        Self {
+            objectives: Arc::new(objectives),
+            started: Instant::now(),
+        }
+    }
+
+    /// Counts a request to `route` answered with `status` after `latency`,
+    /// at `now`.
+    pub(crate) fn record(&self, route: &str, status: StatusCode, latency: Duration, now: Instant) {
+        let minute = self.minute(now);
+        for objective in self.objectives.iter().filter(|objective| objective.route == route) {
+            let bad = match objective.kind {
+                Kind::Availability => status.is_server_error(),
+                Kind::Latency(threshold) => latency > threshold,
+            };
+            if objective.record(minute, bad) {
+                objective.warn_if_burning(minute);
+            }
+        }
+    }
+
+    /// How every objective is doing at `now`, by name.
+    pub(crate) fn report(&self, now: Instant) -> Vec<SloReport> {
+        let minute = self.minute(now);
+        self.objectives.iter().map(|objective| objective.report(minute)).collect()
+    }
+
+    fn minute(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.started).as_secs() / 60
+    }
+}
+
+impl Default for SloTracker {
+    /// Tracks no objectives.
+    fn default() -> Self {
+        Self::new(&HashMap::new())
+    }
+}
+
+impl Kind {
+    fn label(&self) -> &'static str {
+        match self {
+            Kind::Availability => "availability",
+            Kind::Latency(_) => "latency",
+        }
+    }
+}
+
+impl Objective {
+    /// Counts a request in `minute`, returning whether it is the first of
+    /// the minute.
+    fn record(&self, minute: u64, bad: bool) -> bool {
+        let mut minutes = self.lock();
+        // A request timed just before another that was counted first is
+        // counted in the later minute.
+        let first = minutes.back().is_none_or(|last| last.minute < minute);
+        if first {
+            while minutes.front().is_some_and(|oldest| oldest.minute + LONG_WINDOW_MINUTES <= minute) {
+                minutes.pop_front();
+            }
+            minutes.push_back(Minute { minute, total: 0, bad: 0 });
+        }
+        let last = minutes.back_mut().expect("the current minute was just added");
+        last.total += 1;
+        last.bad += u64::from(bad);
+        first
+    }
+
+    /// Requests and bad requests over the `window` minutes up to `minute`.
+    fn counts(&self, minute: u64, window: u64) -> (u64, u64) {
+        self.lock()
+            .iter()
+            .filter(|counted| counted.minute + window > minute)
+            .fold((0, 0), |(total, bad), counted| (total + counted.total, bad + counted.bad))
+    }
+
+    /// How many times faster than the budget allows the `window` minutes up
+    /// to `minute` failed.
+    fn burn_rate(&self, minute: u64, window: u64) -> f64 {
+        let (total, bad) = self.counts(minute, window);
+        if total == 0 {
+            return 0.0;
+        }
+        (bad as f64 / total as f64) / self.budget()
+    }
+
+    /// Share of requests allowed to fail.
+    fn budget(&self) -> f64 {
+        (1.0 - self.target).max(f64::EPSILON)
+    }
+
+    fn warn_if_burning(&self, minute: u64) {
+        let burn_rate_1h = self.burn_rate(minute, LONG_WINDOW_MINUTES);
+        let burn_rate_5m = self.burn_rate(minute, SHORT_WINDOW_MINUTES);
+        if burn_rate_1h > self.alert_burn_rate && burn_rate_5m > self.alert_burn_rate {
+            tracing::warn!(
+                slo = self.name,
+                route = self.route,
+                objective = self.kind.label(),
+                target = self.target,
+                burn_rate_1h,
+                burn_rate_5m,
+                threshold = self.alert_burn_rate,
+                "Error budget burning too fast"
+            );
+        }
+    }
+
+    fn report(&self, minute: u64) -> SloReport {
+        let (requests, bad_requests) = self.counts(minute, LONG_WINDOW_MINUTES);
+        let allowed = requests as f64 * self.budget();
+        let burn_rate_1h = self.burn_rate(minute, LONG_WINDOW_MINUTES);
+        let burn_rate_5m = self.burn_rate(minute, SHORT_WINDOW_MINUTES);
+        SloReport {
+            name: self.name.clone(),
+            route: self.route.clone(),
+            objective: self.kind.label().to_string(),
+            target: self.target,
+            latency_threshold_ms: match self.kind {
+                Kind::Availability => None,
+                Kind::Latency(threshold) => Some(threshold.as_millis() as u64),
+            },
+            requests,
+            bad_requests,
+            budget_remaining: if requests == 0 { 1.0 } else { 1.0 - bad_requests as f64 / allowed },
+            burn_rate_1h,
+            burn_rate_5m,
+            alerting: burn_rate_1h > self.alert_burn_rate && burn_rate_5m > self.alert_burn_rate,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<Minute>> {
+        // The counts stay consistent even if a holder panicked.
+        self.minutes.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+pub(crate) async fn track_http(State(slos): State<SloTracker>, request: Request, next: Next) -> Response {
+    let route = request.extensions().get::<MatchedPath>().map(|path| path.as_str().to_string());
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    if let Some(route) = route {
+        slos.record(&route, response.status(), started.elapsed(), Instant::now());
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        time::{Duration, Instant},
+    };
+
+    use axum::http::StatusCode;
+
+    use super::{SloConfig, SloTracker};
+
+    // ===================
+    // Test Helpers
+    // ===================
+    const ROUTE: &str = "/api/v1/user/{id}";
+
+    fn tracker() -> SloTracker {
+        SloTracker::new(&HashMap::from([(
+            "get_user".to_string(),
+            SloConfig {
+                route: ROUTE.to_string(),
+                availability: Some(0.99),
+                latency: Some(0.9),
+                latency_threshold_ms: Some(100),
+                alert_burn_rate: None,
+            },
+        )]))
+    }
+
+    fn minutes(count: u64) -> Duration {
+        Duration::from_secs(count * 60)
+    }
+
+    // ===================
+    // Tests: report
+    // ===================
+    #[test]
+    fn test_report_without_requests() {
+        let tracker = tracker();
+
+        let reports = tracker.report(Instant::now());
+
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].objective, "availability");
+        assert_eq!(reports[0].latency_threshold_ms, None);
+        assert_eq!(reports[1].objective, "latency");
+        assert_eq!(reports[1].latency_threshold_ms, Some(100));
+        assert!(
+            reports
+                .iter()
+                .all(|report| report.requests == 0 && report.budget_remaining == 1.0 && !report.alerting)
+        );
+    }
+
+    #[test]
+    fn test_report_counts_failures_per_objective() {
+        let tracker = tracker();
+        let now = Instant::now();
+        for _ in 0..198 {
+            tracker.record(ROUTE, StatusCode::OK, Duration::from_millis(10), now);
+        }
+        tracker.record(ROUTE, StatusCode::INTERNAL_SERVER_ERROR, Duration::from_millis(10), now);
+        tracker.record(ROUTE, StatusCode::OK, Duration::from_millis(150), now);
+        tracker.record("/api/v1/user", StatusCode::INTERNAL_SERVER_ERROR, Duration::from_millis(10), now);
+
+        let reports = tracker.report(now);
+
+        assert_eq!((reports[0].requests, reports[0].bad_requests), (200, 1));
+        assert!((reports[0].budget_remaining - 0.5).abs() < 1e-9);
+        assert!((reports[0].burn_rate_1h - 0.5).abs() < 1e-9);
+        assert_eq!((reports[1].requests, reports[1].bad_requests), (200, 1));
+        assert!((reports[1].budget_remaining - 0.95).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_report_alerts_only_while_both_windows_burn() {
+        let tracker = tracker();
+        let start = Instant::now();
+        for _ in 0..10 {
+            tracker.record(ROUTE, StatusCode::SERVICE_UNAVAILABLE, Duration::from_millis(10), start);
+        }
+
+        assert!(tracker.report(start).iter().find(|report| report.objective == "availability").unwrap().alerting);
+
+        let later = start + minutes(10);
+        for _ in 0..10 {
+            tracker.record(ROUTE, StatusCode::OK, Duration::from_millis(10), later);
+        }
+        let report = tracker.report(later).into_iter().find(|report| report.objective == "availability").unwrap();
+        assert!(report.burn_rate_1h > 14.4);
+        assert_eq!(report.burn_rate_5m, 0.0);
+        assert!(!report.alerting);
+    }
+
+    #[test]
+    fn test_report_forgets_requests_older_than_an_hour() {
+        let tracker = tracker();
+        let start = Instant::now();
+        tracker.record(ROUTE, StatusCode::INTERNAL_SERVER_ERROR, Duration::from_millis(10), start);
+        tracker.record(ROUTE, StatusCode::OK, Duration::from_millis(10), start + minutes(30));
+
+        assert_eq!(tracker.report(start + minutes(59))[0].requests, 2);
+        assert_eq!(tracker.report(start + minutes(60))[0].requests, 1);
+        assert_eq!(tracker.report(start + minutes(90))[0].requests, 0);
+    }
+}
@@ -0,0 +1,23 @@
+//! Encoding hooks for benchmarks.
+//! Only compiled when the `test-support` feature is enabled.
+
+use hex_play_core::user::User;
+use prost::Message;
+
+use crate::grpc::{user::handler, user_proto::ListUsersResponse};
+pub use crate::http::ResponseFormat;
+
+/// Encodes `users` in `format` exactly as `GET /api/v1/user` would.
+pub fn encode_http_list_users(format: ResponseFormat, users: Vec<User>) -> Vec<u8> {
+    crate::http::encode_list_users(format, users)
+}
+
+/// Encodes `users` as the protobuf `ListUsersResponse` returned by the `List`
+/// RPC.
+pub fn encode_grpc_list_users(users: Vec<User>) -> Vec<u8> {
+    ListUsersResponse {
+        users: users.into_iter().map(handler::to_proto).collect(),
+        ..ListUsersResponse::default()
+    }
+    .encode_to_vec()
+}
@@ -0,0 +1,144 @@
+//! W3C trace context propagation, so the spans of a client call and those of
+//! the server handling it, down to the database, belong to one trace.
+//!
+//! The gRPC client functions send the `traceparent` of the current span in
+//! the request metadata, see [`traced_request`]; the server makes the span
+//! of each gRPC call and HTTP request a child of the `traceparent` it
+//! receives, see [`continue_trace`]. Both go through the global OpenTelemetry propagator,
+//! so nothing is sent or read unless the process set one up.
+
+use axum::http::{HeaderMap, HeaderName};
+use opentelemetry::{
+    Context, global,
+    propagation::{Extractor, Injector},
+    trace::TraceContextExt as _,
+};
+use tonic::metadata::{Ascii, MetadataKey, MetadataMap, MetadataValue};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+
+/// `message` as a request carrying the trace context of the current span.
+pub(crate) fn traced_request<T>(message: T) -> tonic::Request<T> {
+    let mut request = tonic::Request::new(message);
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut MetadataInjector(request.metadata_mut())));
+    request
+}
+
+/// Makes `span` part of the trace the caller sent in `headers`, or of a new
+/// one if it sent none, and records the trace id on it so log lines can be
+/// matched with the trace. Does nothing without the OpenTelemetry layer, as
+/// in tests.
+pub(crate) fn continue_trace(span: &Span, headers: &HeaderMap) {
+    if span.set_parent(extract(headers)).is_ok() {
+        span.record("trace_id", tracing::field::display(span.context().span().span_context().trace_id()));
+    }
+}
+
+/// Id of the trace the current span belongs to, `None` outside of one or
+/// without the OpenTelemetry layer.
+pub(crate) fn current_trace_id() -> Option<String> {
+    let context = Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+    span_context.is_valid().then(|| span_context.trace_id().to_string())
+}
+
+/// The trace context sent by the caller, empty if it sent none.
+fn extract(headers: &HeaderMap) -> Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)))
+}
+
+struct MetadataInjector<'a>(&'a mut MetadataMap);
+
+impl Injector for MetadataInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        // The propagator only produces valid keys and values.
+        if let (Ok(key), Ok(value)) = (MetadataKey::<Ascii>::from_bytes(key.as_bytes()), MetadataValue::try_from(value)) {
+            self.0.insert(key, value);
+        }
+    }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderMap, HeaderValue};
+    use opentelemetry::{global, trace::TracerProvider as _};
+    use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider};
+    use tracing_subscriber::{Registry, layer::SubscriberExt as _};
+
+    use super::{continue_trace, traced_request};
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    // ===================
+    // Helpers
+    // ===================
+    /// Runs `f` with OpenTelemetry set up as the server and CLI do.
+    fn with_tracing(f: impl FnOnce()) {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let tracer = SdkTracerProvider::builder().build().tracer("test");
+        let subscriber = Registry::default().with(tracing_opentelemetry::layer().with_tracer(tracer));
+        tracing::subscriber::with_default(subscriber, f);
+    }
+
+    fn caller_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("traceparent", HeaderValue::from_static(TRACEPARENT));
+        headers
+    }
+
+    // ===================
+    // Tests: continue_trace
+    // ===================
+    #[test]
+    fn test_continue_trace_joins_callers_trace() {
+        with_tracing(|| {
+            let span = tracing::info_span!("grpc", trace_id = tracing::field::Empty);
+            continue_trace(&span, &caller_headers());
+            let _entered = span.enter();
+
+            let request = traced_request(());
+
+            let traceparent = request.metadata().get("traceparent").unwrap().to_str().unwrap();
+            assert!(traceparent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+            assert!(!traceparent.contains("00f067aa0ba902b7"));
+        });
+    }
+
+    // ===================
+    // Tests: traced_request
+    // ===================
+    #[test]
+    fn test_traced_request_starts_trace_in_new_span() {
+        with_tracing(|| {
+            let span = tracing::info_span!("cli");
+            let _entered = span.enter();
+
+            let request = traced_request(());
+
+            assert!(request.metadata().get("traceparent").is_some());
+        });
+    }
+
+    #[test]
+    fn test_traced_request_outside_span_sends_nothing() {
+        with_tracing(|| {
+            let request = traced_request(());
+
+            assert!(request.metadata().get("traceparent").is_none());
+        });
+    }
+}
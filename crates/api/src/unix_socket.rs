@@ -0,0 +1,163 @@
+//! Listening on a Unix domain socket as well as TCP, for sidecar deployments
+//! where only a local proxy should reach the API.
+
+use std::{
+    fs::{self, Permissions},
+    io,
+    os::unix::{
+        fs::{FileTypeExt, PermissionsExt},
+        net::UnixStream,
+    },
+    path::{Path, PathBuf},
+};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tokio::net::UnixListener;
+
+use crate::error::ApiError;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct UnixSocketConfig {
+    /// (required) Path of the socket file, e.g.
+    /// `HPLAY__HTTP__UNIX_SOCKET__PATH=/run/hex-play/http.sock`.
+    pub path: PathBuf,
+
+    /// (optional) Permissions of the socket file in octal, e.g. `660` so
+    /// only the owner and group can connect. Defaults to what the umask
+    /// allows.
+    #[serde(default)]
+    pub mode: Option<String>,
+}
+
+/// Removes the socket file when dropped, so a clean shutdown leaves nothing
+/// behind.
+#[derive(Debug)]
+pub(crate) struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.0) {
+            tracing::warn!("couldn't remove socket {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+/// Binds the socket, replacing one left behind by an earlier run, and
+/// applies the configured permissions.
+pub(crate) fn bind(config: &UnixSocketConfig) -> Result<(UnixListener, SocketFile), ApiError> {
+    let path = &config.path;
+    let error = |e: io::Error| ApiError::Network(format!("{}: {}", path.display(), e));
+
+    remove_stale(path)?;
+    let listener = UnixListener::bind(path).map_err(error)?;
+    let file = SocketFile(path.clone());
+    if let Some(mode) = &config.mode {
+        let mode = parse_mode(mode).ok_or_else(|| ApiError::Network(format!("{}: invalid mode `{}`", path.display(), mode)))?;
+        fs::set_permissions(path, Permissions::from_mode(mode)).map_err(error)?;
+    }
+
+    Ok((listener, file))
+}
+
+/// Parses permission bits written in octal, such as `660` or `0600`.
+pub fn parse_mode(mode: &str) -> Option<u32> {
+    u32::from_str_radix(mode, 8).ok().filter(|mode| *mode <= 0o777)
+}
+
+/// Removes a socket nothing accepts on any more. Anything else at the path,
+/// including a socket still in use, is left alone and reported.
+fn remove_stale(path: &Path) -> Result<(), ApiError> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(ApiError::Network(format!("{}: {}", path.display(), e))),
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(ApiError::Network(format!("{}: exists and is not a socket", path.display())));
+    }
+    if UnixStream::connect(path).is_ok() {
+        return Err(ApiError::Network(format!("{}: already in use", path.display())));
+    }
+
+    tracing::info!("removing stale socket {}", path.display());
+    fs::remove_file(path).map_err(|e| ApiError::Network(format!("{}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::PermissionsExt, path::PathBuf};
+
+    use super::{UnixSocketConfig, bind, parse_mode};
+
+    fn socket_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("hex-play-{}-{}.sock", name, std::process::id()))
+    }
+
+    // ===================
+    // Tests: bind
+    // ===================
+
+    #[tokio::test]
+    async fn test_bind_sets_mode_and_cleans_up() {
+        let path = socket_path("mode");
+        let config = UnixSocketConfig {
+            path: path.clone(),
+            mode: Some("600".to_string()),
+        };
+
+        let (listener, file) = bind(&config).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        drop((listener, file));
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_bind_replaces_stale_socket() {
+        let path = socket_path("stale");
+        let config = UnixSocketConfig {
+            path: path.clone(),
+            mode: None,
+        };
+        // Dropping only the listener leaves the file behind, as a crash would.
+        let (listener, file) = bind(&config).unwrap();
+        drop(listener);
+        std::mem::forget(file);
+
+        let (_listener, _file) = bind(&config).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bind_refuses_socket_in_use() {
+        let config = UnixSocketConfig {
+            path: socket_path("in-use"),
+            mode: None,
+        };
+        let (_listener, _file) = bind(&config).unwrap();
+
+        assert!(bind(&config).unwrap_err().to_string().contains("already in use"));
+    }
+
+    #[test]
+    fn test_bind_refuses_regular_file() {
+        let path = socket_path("regular");
+        fs::write(&path, "").unwrap();
+
+        let result = bind(&UnixSocketConfig {
+            path: path.clone(),
+            mode: None,
+        });
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.unwrap_err().to_string().contains("not a socket"));
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("660"), Some(0o660));
+        assert_eq!(parse_mode("0600"), Some(0o600));
+        assert_eq!(parse_mode("1777"), None);
+        assert_eq!(parse_mode("rw"), None);
+    }
+}
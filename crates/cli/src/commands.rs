@@ -1,7 +1,35 @@
+mod dedupe;
+mod doctor;
+#[cfg(feature = "dev-tools")]
+mod gen_entities;
+mod list_view;
+mod loadtest;
+mod rollups;
 mod server;
+mod status;
+mod user;
 
-use hex_play_core::user::UserId;
+use anyhow::Context;
+use chrono::NaiveDate;
+pub use dedupe::*;
+pub use doctor::*;
+#[cfg(feature = "dev-tools")]
+pub use gen_entities::*;
+use hex_play_api::grpc::DEFAULT_ENDPOINT;
+use hex_play_core::user::{UserId, UserToken};
+pub use list_view::*;
+pub use loadtest::*;
+pub use rollups::*;
 pub use server::*;
+pub use status::*;
+use tracing::Instrument as _;
+pub use user::*;
+
+use crate::{
+    config::Config,
+    error::CommandError,
+    logging::{init_client_tracing, init_logging},
+};
 
 #[derive(Debug, clap::Parser)]
 #[command(
@@ -22,17 +50,74 @@ AUTHORS:
 #[command(about, long_about = None)]
 #[command(propagate_version = true, arg_required_else_help = true)]
 pub struct CommandLine {
+    /// Print nothing; report the outcome through the exit status only
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Output format for command results
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub format: OutputFormat,
+
     #[clap(subcommand)]
     pub command: Commands,
 }
 
+impl CommandLine {
+    pub fn output(&self) -> Output {
+        match (self.quiet, self.format) {
+            (true, _) => Output::Quiet,
+            (false, OutputFormat::Pretty) => Output::Pretty,
+            (false, OutputFormat::Json) => Output::Json,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable summary
+    #[default]
+    Pretty,
+    /// One JSON object per user
+    Json,
+}
+
+/// How command results are written to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    Quiet,
+    Pretty,
+    Json,
+}
+
 #[derive(Debug, clap::Subcommand)]
 pub enum Commands {
     #[command(about = "Start server", display_order = 10)]
-    Server,
+    Server {
+        #[command(flatten)]
+        options: ServerOptions,
+    },
+
+    #[command(about = "Check configuration, database and ports without starting the server", display_order = 11)]
+    Doctor,
+
+    #[command(about = "Rebuild the daily signup rollups from the users table", display_order = 12)]
+    BackfillSignupRollups {
+        /// Only rebuild days from this date (YYYY-MM-DD, UTC) on
+        #[arg(long, value_name = "date")]
+        since: Option<NaiveDate>,
+    },
+
+    #[command(about = "Find users that may be the same person", display_order = 13)]
+    Dedupe {
+        #[command(subcommand)]
+        command: DedupeCommand,
+    },
 
-    #[command(about = "Server status check", display_order = 20)]
-    Status { question: String },
+    #[command(about = "Rebuild the user listings from the users table", display_order = 14)]
+    RebuildUserListView,
+
+    #[command(about = "Report how the server and its dependencies are doing", display_order = 20)]
+    Status,
 
     #[command(about = "Add user", display_order = 30)]
     AddUser { name: String, email: String, age: i16 },
@@ -49,8 +134,124 @@ pub enum Commands {
         email: Option<String>,
         #[arg(value_name = "age")]
         age: Option<i16>,
+        /// Only update if the user is still at this version
+        #[arg(long, value_name = "version")]
+        expected_version: Option<u64>,
     },
 
     #[command(about = "Get users", display_order = 33)]
-    GetUsers {},
+    GetUsers {
+        /// List users starting at this id
+        #[arg(long, visible_alias = "cursor", value_name = "id")]
+        start_id: Option<UserId>,
+        /// Users per page (server default when omitted)
+        #[arg(long, value_name = "count", conflicts_with = "all")]
+        page_size: Option<u64>,
+        /// Follow the cursor for this many pages
+        #[arg(long, value_name = "count", default_value_t = 1, conflicts_with = "all")]
+        pages: u64,
+        /// Also print how many users there are in total
+        #[arg(long, conflicts_with = "all")]
+        total: bool,
+        /// Stream every user instead of a single page
+        #[arg(long)]
+        all: bool,
+    },
+
+    #[command(about = "Get user by id", display_order = 34)]
+    GetUser { id: UserId },
+
+    #[command(about = "Get user by token", display_order = 35)]
+    GetUserByToken { token: UserToken },
+
+    #[command(about = "Send a mix of user requests at a steady rate and report latencies", display_order = 40)]
+    Loadtest {
+        #[command(flatten)]
+        options: LoadtestOptions,
+    },
+
+    #[cfg(feature = "dev-tools")]
+    #[command(about = "Regenerate the database entities from the schema of a live database", display_order = 50)]
+    GenEntities {
+        #[command(flatten)]
+        options: GenEntitiesOptions,
+    },
+}
+
+impl Commands {
+    /// Whether the command calls a running server, rather than
+    /// running the server or working on the database itself.
+    fn calls_server(&self) -> bool {
+        #[cfg(feature = "dev-tools")]
+        if matches!(self, Self::GenEntities { .. }) {
+            return false;
+        }
+        !matches!(
+            self,
+            Self::Server { .. } | Self::Doctor | Self::BackfillSignupRollups { .. } | Self::Dedupe { .. } | Self::RebuildUserListView
+        )
+    }
+}
+
+/// Runs the parsed command. Failures carry the exit status the process
+/// should end with; see [`CommandError::exit_code`].
+pub async fn run_command(cli: CommandLine) -> Result<(), CommandError> {
+    let output = cli.output();
+    // Commands calling the server send it their trace, so the server's spans
+    // join it.
+    if cli.command.calls_server() {
+        init_client_tracing()?;
+    }
+    let span = tracing::info_span!("command");
+
+    async move {
+        match cli.command {
+            Commands::Server { options } => {
+                let config = Config::load().await.context("Cannot load configuration")?;
+                let log_filter = init_logging(config.access_log_file.as_deref())?;
+                run_server_command(&config, &options, log_filter).await.context("Couldn't start server")?;
+            }
+            Commands::Doctor => run_doctor_command(Config::load().await, output).await?,
+            Commands::BackfillSignupRollups { since } => {
+                let config = Config::load().await.context("Cannot load configuration")?;
+                run_backfill_signup_rollups_command(&config, since, output).await?;
+            }
+            Commands::Dedupe {
+                command: DedupeCommand::Report,
+            } => {
+                let config = Config::load().await.context("Cannot load configuration")?;
+                run_dedupe_report_command(&config, output).await?;
+            }
+            Commands::RebuildUserListView => {
+                let config = Config::load().await.context("Cannot load configuration")?;
+                run_rebuild_user_list_view_command(&config, output).await?;
+            }
+            Commands::Status => run_status_command(DEFAULT_ENDPOINT, output).await?,
+            Commands::AddUser { name, email, age } => run_add_user_command(DEFAULT_ENDPOINT, name, email, age, output).await?,
+            Commands::DeleteUser { id } => run_delete_user_command(DEFAULT_ENDPOINT, id, output).await?,
+            Commands::UpdateUser {
+                id,
+                name,
+                email,
+                age,
+                expected_version,
+            } => run_update_user_command(DEFAULT_ENDPOINT, id, name, email, age, expected_version, output).await?,
+            Commands::GetUsers {
+                start_id,
+                page_size,
+                pages,
+                total,
+                all,
+            } => run_get_users_command(DEFAULT_ENDPOINT, start_id, page_size, pages, total, all, output).await?,
+            Commands::GetUser { id } => run_get_user_command(DEFAULT_ENDPOINT, id, output).await?,
+            Commands::GetUserByToken { token } => run_get_user_by_token_command(DEFAULT_ENDPOINT, token, output).await?,
+            Commands::Loadtest { options } => run_loadtest_command(&options, output).await?,
+            #[cfg(feature = "dev-tools")]
+            Commands::GenEntities { options } => run_gen_entities_command(&options, output).await?,
+        }
+
+        Ok::<_, CommandError>(())
+    }
+    .instrument(span)
+    .await
 }
@@ -0,0 +1,73 @@
+//! `hex-play dedupe report`: lists users that may be the same person,
+//! straight from the database, without a running server.
+
+use anyhow::Context;
+use hex_play_core::{
+    context::RequestContext,
+    create_services,
+    user::{DuplicateGroup, DuplicateReason},
+};
+use hex_play_database::{create_repository_service_with_config, open_database};
+
+use crate::{commands::Output, config::Config};
+
+#[derive(Debug, clap::Subcommand)]
+pub enum DedupeCommand {
+    /// List groups of users sharing an email local part or a similar name
+    Report,
+}
+
+/// Groups every user by normalized email local part and by similar names,
+/// then closes the database. Each group's ids are the users to merge.
+pub async fn run_dedupe_report_command(config: &Config, output: Output) -> anyhow::Result<()> {
+    let database = open_database(&config.database).await.context("Couldn't create database connection")?;
+    let repository_service = create_repository_service_with_config(database, &config.database)
+        .await
+        .context("Couldn't create database connection")?;
+    let services = create_services(repository_service.clone()).context("Couldn't create core services")?;
+
+    let result = services.user_service.find_potential_duplicates(&RequestContext::internal()).await;
+    repository_service.repository().close().await.context("Couldn't close database")?;
+    let groups = result.context("Couldn't find potential duplicates")?;
+
+    match output {
+        Output::Quiet => {}
+        Output::Pretty if groups.is_empty() => println!("No potential duplicates"),
+        Output::Pretty => {
+            for group in &groups {
+                println!("{} `{}`:", reason_label(group.reason), group.key);
+                for user in &group.users {
+                    println!("  {} {} <{}>", user.id, user.name, user.email.as_str());
+                }
+            }
+        }
+        Output::Json => {
+            for group in &groups {
+                println!("{}", group_json(group));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn reason_label(reason: DuplicateReason) -> &'static str {
+    match reason {
+        DuplicateReason::EmailLocalPart => "Same email local part",
+        DuplicateReason::SimilarName => "Similar name",
+    }
+}
+
+fn group_json(group: &DuplicateGroup) -> serde_json::Value {
+    serde_json::json!({
+        "reason": match group.reason {
+            DuplicateReason::EmailLocalPart => "email_local_part",
+            DuplicateReason::SimilarName => "similar_name",
+        },
+        "key": group.key,
+        "users": group
+            .users
+            .iter()
+            .map(|user| serde_json::json!({ "id": user.id, "token": user.token.to_string(), "name": user.name, "email": user.email.as_str() }))
+            .collect::<Vec<_>>(),
+    })
+}
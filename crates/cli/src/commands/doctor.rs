@@ -0,0 +1,149 @@
+//! `hex-play doctor`: runs the checks the server depends on at startup and
+//! prints a pass/fail report, without launching the server.
+
+use std::fmt;
+
+use hex_play_database::{DatabaseConfig, check_schema, open_database};
+use tokio::net::TcpListener;
+
+use crate::{commands::Output, config::Config, error::Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Pass,
+    Warn,
+    Fail,
+    Skip,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Outcome::Pass => "PASS",
+            Outcome::Warn => "WARN",
+            Outcome::Fail => "FAIL",
+            Outcome::Skip => "SKIP",
+        })
+    }
+}
+
+struct Check {
+    name: &'static str,
+    outcome: Outcome,
+    detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, outcome: Outcome, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            outcome,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Runs every check and prints the report, failing if any check failed.
+pub async fn run_doctor_command(config: Result<Config, Error>, output: Output) -> anyhow::Result<()> {
+    let mut checks = Vec::new();
+
+    match config {
+        Ok(config) => {
+            checks.push(Check::new("Configuration", Outcome::Pass, "loaded from HPLAY__* environment"));
+            checks.push(check_feature_flags(&config));
+            checks.extend(check_database(&config.database).await);
+            for addr in config.http.listen_addrs() {
+                checks.push(check_port("HTTP port", addr).await);
+            }
+            for addr in &config.http.admin_listen_addrs {
+                checks.push(check_port("HTTP admin port", addr).await);
+            }
+            if config.grpc.single_port {
+                checks.push(Check::new("gRPC port", Outcome::Skip, "served on the HTTP ports"));
+            } else {
+                for addr in config.grpc.listen_addrs() {
+                    checks.push(check_port("gRPC port", addr).await);
+                }
+            }
+            let frontend_addr = format!("{}:{}", config.frontend.listen_ip, config.frontend.listen_port);
+            checks.push(check_port("Frontend port", &frontend_addr).await);
+        }
+        Err(error) => checks.push(Check::new("Configuration", Outcome::Fail, error.to_string())),
+    }
+    checks.push(Check::new("TLS", Outcome::Skip, "not configured; HTTP and gRPC are served in plaintext"));
+
+    if output != Output::Quiet {
+        print_report(&checks);
+    }
+
+    let failed = checks.iter().filter(|check| check.outcome == Outcome::Fail).count();
+    if failed > 0 {
+        anyhow::bail!("{failed} check(s) failed");
+    }
+
+    Ok(())
+}
+
+fn check_feature_flags(config: &Config) -> Check {
+    match config.load_feature_flags() {
+        Ok(flags) => Check::new("Feature flags", Outcome::Pass, format!("{} configured", flags.len())),
+        Err(error) => Check::new("Feature flags", Outcome::Fail, error.to_string()),
+    }
+}
+
+async fn check_database(config: &DatabaseConfig) -> Vec<Check> {
+    // Report an unreachable database straight away rather than waiting out
+    // the retry window the server allows itself at startup.
+    let config = DatabaseConfig {
+        connect_max_wait_secs: Some(0),
+        ..config.clone()
+    };
+
+    let database = match open_database(&config).await {
+        Ok(database) => database,
+        Err(error) => {
+            return vec![
+                Check::new("Database connection", Outcome::Fail, error.to_string()),
+                Check::new("Database schema", Outcome::Skip, "database unreachable"),
+            ];
+        }
+    };
+
+    let schema = match check_schema(&database, config.pool_mode).await {
+        Ok(stale) if stale.is_empty() => Check::new("Database schema", Outcome::Pass, "up to date"),
+        Ok(stale) => Check::new(
+            "Database schema",
+            Outcome::Warn,
+            format!("{} missing or out of date; synced when the server starts", stale.join(", ")),
+        ),
+        Err(error) => Check::new("Database schema", Outcome::Fail, error.to_string()),
+    };
+    let _ = database.close().await;
+
+    vec![Check::new("Database connection", Outcome::Pass, "connected"), schema]
+}
+
+async fn check_port(name: &'static str, addr: &str) -> Check {
+    match TcpListener::bind(addr).await {
+        Ok(_) => Check::new(name, Outcome::Pass, format!("{addr} is free")),
+        Err(error) => Check::new(name, Outcome::Fail, format!("{addr}: {error}")),
+    }
+}
+
+fn print_report(checks: &[Check]) {
+    println!("HexPlay doctor {}", clap::crate_version!());
+    println!();
+    for check in checks {
+        println!("[{}] {:<20} {}", check.outcome, check.name, check.detail);
+    }
+
+    let count = |outcome| checks.iter().filter(|check| check.outcome == outcome).count();
+    println!();
+    println!(
+        "{} passed, {} warned, {} failed, {} skipped",
+        count(Outcome::Pass),
+        count(Outcome::Warn),
+        count(Outcome::Fail),
+        count(Outcome::Skip)
+    );
+}
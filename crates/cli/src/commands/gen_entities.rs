@@ -0,0 +1,428 @@
+//! `hex-play gen-entities`: regenerates the `SeaORM` entities of the
+//! database crate from the schema of a live database, the way
+//! `sea-orm-cli generate entity` does, then fits them to the crate.
+//!
+//! Generation only replaces the `Model` structs, so what was written by hand
+//! around them survives: imports, doc comments of the model and its fields,
+//! and everything after the struct such as `ActiveModelBehavior` impls. The
+//! modules are kept crate-private, and each model of a table backing a
+//! domain type gets its `From<Model>` conversion, regenerated on every run.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use clap::Parser as _;
+
+use crate::{commands::Output, config::Config};
+
+/// Where the database crate keeps its entities, from the workspace root.
+pub const DEFAULT_ENTITIES_DIR: &str = "crates/database/src/entities";
+
+/// Starts the generated conversion at the end of an entity file. Everything
+/// from it on is replaced when the entities are generated again.
+const CONVERSION_MARKER: &str = "// Generated by `hex-play gen-entities`; edits below this line are overwritten.";
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct GenEntitiesOptions {
+    /// Database to read the schema from (the configured database when
+    /// omitted)
+    #[arg(long, value_name = "url")]
+    pub database_url: Option<String>,
+
+    /// Directory to write the entities to
+    #[arg(long, value_name = "dir", default_value = DEFAULT_ENTITIES_DIR)]
+    pub output_dir: PathBuf,
+}
+
+/// The domain type the model of a table converts into. Fields are converted
+/// by their type in the model, or by the expression in `overrides`, reading
+/// the model as `model`.
+struct Conversion {
+    table: &'static str,
+    target: &'static str,
+    overrides: &'static [(&'static str, &'static str)],
+}
+
+/// How the columns of a user convert, in `users` and in the listings
+/// denormalized from it.
+const USER_OVERRIDES: &[(&str, &str)] = &[
+    ("id", "crate::ids::user_id(model.id)"),
+    ("token", "hex_play_core::user::UserToken::parse(&model.token).unwrap()"),
+    (
+        "email",
+        r#"hex_play_core::types::Email::new(model.email).expect("database email should be valid")"#,
+    ),
+    ("age", r#"hex_play_core::types::Age::new(model.age).expect("database age should be valid")"#),
+    ("version", "model.version as u64"),
+];
+
+const CONVERSIONS: &[Conversion] = &[
+    Conversion {
+        table: "leases",
+        target: "hex_play_core::lease::Lease",
+        overrides: &[],
+    },
+    Conversion {
+        table: "sessions",
+        target: "hex_play_core::session::Session",
+        overrides: &[("id", "hex_play_core::session::SessionId::new(model.id)")],
+    },
+    Conversion {
+        table: "user_list_view",
+        target: "hex_play_core::user::User",
+        overrides: USER_OVERRIDES,
+    },
+    Conversion {
+        table: "users",
+        target: "hex_play_core::user::User",
+        overrides: USER_OVERRIDES,
+    },
+];
+
+/// Generates the entities into a scratch directory, then merges each into
+/// `output_dir`, leaving entities of tables no longer in the schema alone.
+pub async fn run_gen_entities_command(options: &GenEntitiesOptions, output: Output) -> anyhow::Result<()> {
+    let database_url = match &options.database_url {
+        Some(url) => url.clone(),
+        None => {
+            let config = Config::load().await.context("Cannot load configuration")?;
+            config.database.database_url.expose().to_string()
+        }
+    };
+    let scratch = std::env::temp_dir().join(format!("hex-play-entities-{}", std::process::id()));
+
+    let result = generate(&database_url, &scratch).await.and_then(|()| merge_into(&scratch, &options.output_dir));
+    let _ = fs::remove_dir_all(&scratch);
+    let written = result?;
+
+    match output {
+        Output::Quiet => {}
+        Output::Pretty => {
+            for file in &written {
+                println!("Wrote {}", file.display());
+            }
+        }
+        Output::Json => println!("{}", serde_json::json!({ "files": written })),
+    }
+    Ok(())
+}
+
+/// Runs the generator of `sea-orm-cli` with the options our entities are
+/// written with.
+async fn generate(database_url: &str, dir: &Path) -> anyhow::Result<()> {
+    let dir = dir.to_str().context("Scratch directory is not valid UTF-8")?;
+    let cli = sea_orm_cli::Cli::try_parse_from([
+        "sea-orm-cli",
+        "generate",
+        "entity",
+        "--database-url",
+        database_url,
+        "--output-dir",
+        dir,
+        "--entity-format",
+        "dense",
+        "--with-serde",
+        "both",
+        "--date-time-crate",
+        "chrono",
+    ])?;
+    let sea_orm_cli::Commands::Generate { command } = cli.command else {
+        unreachable!("parsed a generate command");
+    };
+    // The error is not `Send`, so only its message is kept.
+    sea_orm_cli::run_generate_command(command, false)
+        .await
+        .map_err(|error| anyhow::anyhow!("Couldn't generate entities: {error}"))
+}
+
+/// Merges every generated file in `generated` into `output_dir`, returning
+/// the paths written.
+fn merge_into(generated: &Path, output_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut names: Vec<_> = fs::read_dir(generated)
+        .context("Couldn't read generated entities")?
+        .map(|entry| entry.map(|entry| entry.file_name()))
+        .collect::<Result<_, _>>()?;
+    names.sort();
+    fs::create_dir_all(output_dir).with_context(|| format!("Couldn't create {}", output_dir.display()))?;
+
+    let mut written = Vec::with_capacity(names.len());
+    for name in names {
+        let source = fs::read_to_string(generated.join(&name))?;
+        let path = output_dir.join(&name);
+        let merged = match name.to_str() {
+            Some("mod.rs" | "prelude.rs") => crate_private(&source),
+            Some(file) => {
+                let table = file.trim_end_matches(".rs");
+                let existing = fs::read_to_string(&path).ok();
+                let conversion = CONVERSIONS.iter().find(|conversion| conversion.table == table);
+                merge_entity(&source, existing.as_deref(), conversion).with_context(|| format!("Couldn't merge {}", path.display()))?
+            }
+            None => continue,
+        };
+        fs::write(&path, merged).with_context(|| format!("Couldn't write {}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Narrows the `pub` modules and re-exports of `mod.rs` and `prelude.rs` to
+/// the crate, as the entities are an implementation detail of the adapters.
+fn crate_private(source: &str) -> String {
+    let lines: Vec<_> = source
+        .lines()
+        .map(|line| match line.strip_prefix("pub ") {
+            Some(rest) if rest.starts_with("mod ") || rest.starts_with("use ") => format!("pub(crate) {rest}"),
+            _ => line.to_string(),
+        })
+        .collect();
+    lines.join("\n") + "\n"
+}
+
+/// An entity file cut around its `Model` struct.
+struct EntityFile<'a> {
+    /// The header and imports.
+    preamble: &'a [&'a str],
+    /// The doc comment of the model.
+    docs: &'a [&'a str],
+    /// The model, from its attributes to its closing brace.
+    model: &'a [&'a str],
+    /// What follows the model, up to a generated conversion.
+    tail: &'a [&'a str],
+}
+
+impl<'a> EntityFile<'a> {
+    fn parse(lines: &'a [&'a str]) -> Option<Self> {
+        let name = lines.iter().position(|line| line.starts_with("pub struct Model"))?;
+        let end = name + lines[name..].iter().position(|line| *line == "}")?;
+        let start = lines[..name].iter().rposition(|line| !line.starts_with("#[")).map_or(0, |index| index + 1);
+        let docs = lines[..start].iter().rposition(|line| !line.starts_with("///")).map_or(0, |index| index + 1);
+        let tail_end = lines.iter().position(|line| *line == CONVERSION_MARKER).unwrap_or(lines.len());
+        Some(Self {
+            preamble: &lines[..docs],
+            docs: &lines[docs..start],
+            model: &lines[start..=end],
+            tail: &lines[end + 1..tail_end.max(end + 1)],
+        })
+    }
+
+    /// The doc comment of the field `name`.
+    fn field_docs(&self, name: &str) -> Vec<&'a str> {
+        let mut docs = Vec::new();
+        for line in self.model {
+            match field(line) {
+                Some((field, _)) if field == name => return docs,
+                Some(_) => docs.clear(),
+                None if line.trim_start().starts_with("///") => docs.push(*line),
+                None => {}
+            }
+        }
+        Vec::new()
+    }
+}
+
+/// The name and type of the field declared by `line`, if it declares one.
+fn field(line: &str) -> Option<(&str, &str)> {
+    let (name, ty) = line.trim().strip_prefix("pub ")?.strip_suffix(',')?.split_once(':')?;
+    Some((name.trim(), ty.trim()))
+}
+
+/// Merges the `generated` entity with the `existing` one, keeping what was
+/// written by hand, and appends the conversion of the model, if any.
+fn merge_entity(generated: &str, existing: Option<&str>, conversion: Option<&Conversion>) -> anyhow::Result<String> {
+    let generated_lines: Vec<_> = generated.lines().collect();
+    let generated = EntityFile::parse(&generated_lines).context("Generated entity has no model")?;
+    let existing_lines: Vec<_> = existing.map(|existing| existing.lines().collect()).unwrap_or_default();
+    let existing = existing
+        .map(|_| EntityFile::parse(&existing_lines).context("Existing entity has no model"))
+        .transpose()?;
+
+    let mut lines: Vec<String> = Vec::new();
+    let kept = existing.as_ref().unwrap_or(&generated);
+    push_section(&mut lines, kept.preamble);
+    lines.extend(kept.docs.iter().map(ToString::to_string));
+    let mut attributes = Vec::new();
+    for line in generated.model {
+        match (field(line), &existing) {
+            (Some((name, _)), Some(existing)) => {
+                lines.extend(existing.field_docs(name).into_iter().map(str::to_string));
+                lines.append(&mut attributes);
+                lines.push(line.to_string());
+            }
+            (Some(_), None) => {
+                lines.append(&mut attributes);
+                lines.push(line.to_string());
+            }
+            (None, _) if line.trim_start().starts_with("#[") && line.starts_with("    ") => attributes.push(line.to_string()),
+            (None, _) => {
+                lines.append(&mut attributes);
+                lines.push(line.to_string());
+            }
+        }
+    }
+    lines.push(String::new());
+    push_section(&mut lines, &with_async_trait(kept.tail));
+    if let Some(conversion) = conversion {
+        lines.push(CONVERSION_MARKER.to_string());
+        lines.extend(conversion_impl(conversion, generated.model));
+    }
+
+    while lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Appends `section` without its surrounding blank lines, followed by one.
+fn push_section(lines: &mut Vec<String>, section: &[&str]) {
+    let Some(first) = section.iter().position(|line| !line.is_empty()) else {
+        return;
+    };
+    let last = section.iter().rposition(|line| !line.is_empty()).unwrap_or(first);
+    lines.extend(section[first..=last].iter().map(ToString::to_string));
+    lines.push(String::new());
+}
+
+/// `tail` with `ActiveModelBehavior` impls marked `#[async_trait]`, as the
+/// hand-written ones are.
+fn with_async_trait<'a>(tail: &[&'a str]) -> Vec<&'a str> {
+    let mut lines: Vec<&str> = Vec::with_capacity(tail.len() + 1);
+    for line in tail {
+        if line.starts_with("impl ActiveModelBehavior") && lines.last().is_none_or(|previous| !previous.starts_with("#[async_trait")) {
+            lines.push("#[async_trait::async_trait]");
+        }
+        lines.push(*line);
+    }
+    lines
+}
+
+/// `impl From<Model>` for the target of `conversion`, converting each
+/// column of `model`. Relations, which have no column, are left out.
+fn conversion_impl(conversion: &Conversion, model: &[&str]) -> Vec<String> {
+    let mut lines = vec![
+        format!("impl From<Model> for {} {{", conversion.target),
+        "    fn from(model: Model) -> Self {".to_string(),
+        "        Self {".to_string(),
+    ];
+    for (name, ty) in model.iter().filter_map(|line| field(line)) {
+        if ty.starts_with("HasMany<") || ty.starts_with("HasOne<") || ty.starts_with("BelongsTo<") {
+            continue;
+        }
+        let value = match conversion.overrides.iter().find(|(field, _)| *field == name) {
+            Some((_, value)) => (*value).to_string(),
+            None if ty == "DateTimeWithTimeZone" => format!("model.{name}.with_timezone(&chrono::Utc)"),
+            None if ty == "Option<DateTimeWithTimeZone>" => format!("model.{name}.map(|at| at.with_timezone(&chrono::Utc))"),
+            None => format!("model.{name}"),
+        };
+        lines.push(format!("            {name}: {value},"));
+    }
+    lines.extend(["        }".to_string(), "    }".to_string(), "}".to_string()]);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CONVERSION_MARKER, CONVERSIONS, crate_private, merge_entity};
+
+    const GENERATED: &str = r#"//! `SeaORM` Entity, @generated by sea-orm-codegen 2.0
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "leases")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub name: String,
+    pub holder: String,
+    pub fencing_token: i64,
+    pub expires_at: DateTimeWithTimeZone,
+    pub released_at: Option<DateTimeWithTimeZone>,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+"#;
+
+    fn leases() -> Option<&'static super::Conversion> {
+        CONVERSIONS.iter().find(|conversion| conversion.table == "leases")
+    }
+
+    // ===================
+    // Tests: merge_entity
+    // ===================
+    #[test]
+    fn test_merge_entity_keeps_hand_written_code() {
+        let existing = r#"use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Leases taken by server instances.
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "leases")]
+pub struct Model {
+    /// Name of the lease.
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub name: String,
+    pub holder: String,
+    /// Goes up with every new holder.
+    pub fencing_token: i64,
+    pub expires_at: DateTimeWithTimeZone,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {}
+
+impl ActiveModel {
+    pub(crate) fn release(&mut self) {}
+}
+"#;
+
+        let merged = merge_entity(GENERATED, Some(existing), None).unwrap();
+
+        assert!(!merged.contains("@generated"));
+        assert!(merged.contains("/// Leases taken by server instances.\n#[sea_orm::model]"));
+        assert!(merged.contains("    /// Name of the lease.\n    #[sea_orm(primary_key, auto_increment = false)]\n    pub name: String,"));
+        assert!(merged.contains("    /// Goes up with every new holder.\n    pub fencing_token: i64,"));
+        assert!(merged.contains("    pub released_at: Option<DateTimeWithTimeZone>,"));
+        assert!(merged.ends_with("impl ActiveModel {\n    pub(crate) fn release(&mut self) {}\n}\n"));
+        assert_eq!(merged.matches("impl ActiveModelBehavior").count(), 1);
+    }
+
+    #[test]
+    fn test_merge_entity_adds_conversion() {
+        let merged = merge_entity(GENERATED, None, leases()).unwrap();
+
+        let conversion = merged.split_once(CONVERSION_MARKER).unwrap().1;
+        assert!(conversion.contains("impl From<Model> for hex_play_core::lease::Lease {"));
+        assert!(conversion.contains("            name: model.name,"));
+        assert!(conversion.contains("            expires_at: model.expires_at.with_timezone(&chrono::Utc),"));
+        assert!(conversion.contains("            released_at: model.released_at.map(|at| at.with_timezone(&chrono::Utc)),"));
+        assert!(merged.contains("#[async_trait::async_trait]\nimpl ActiveModelBehavior for ActiveModel {}"));
+    }
+
+    #[test]
+    fn test_merge_entity_replaces_previous_conversion() {
+        let first = merge_entity(GENERATED, None, leases()).unwrap();
+
+        let second = merge_entity(GENERATED, Some(&first), leases()).unwrap();
+
+        assert_eq!(second, first);
+    }
+
+    // ===================
+    // Tests: crate_private
+    // ===================
+    #[test]
+    fn test_crate_private_narrows_modules_and_reexports() {
+        let source = "pub mod prelude;\n\npub mod users;\n";
+
+        assert_eq!(crate_private(source), "pub(crate) mod prelude;\n\npub(crate) mod users;\n");
+        assert_eq!(
+            crate_private("pub use super::users::Entity as Users;"),
+            "pub(crate) use super::users::Entity as Users;\n"
+        );
+    }
+}
@@ -0,0 +1,28 @@
+//! `hex-play rebuild-user-list-view`: rebuilds the listings of users
+//! straight from the database, without a running server.
+
+use anyhow::Context;
+use hex_play_core::{context::RequestContext, create_services};
+use hex_play_database::{create_repository_service_with_config, open_database};
+
+use crate::{commands::Output, config::Config};
+
+/// Rebuilds the listings from the users, then closes the database.
+pub async fn run_rebuild_user_list_view_command(config: &Config, output: Output) -> anyhow::Result<()> {
+    let database = open_database(&config.database).await.context("Couldn't create database connection")?;
+    let repository_service = create_repository_service_with_config(database, &config.database)
+        .await
+        .context("Couldn't create database connection")?;
+    let services = create_services(repository_service.clone()).context("Couldn't create core services")?;
+
+    let result = services.user_service.rebuild_list_view(&RequestContext::internal()).await;
+    repository_service.repository().close().await.context("Couldn't close database")?;
+    let users = result.context("Couldn't rebuild user listings")?;
+
+    match output {
+        Output::Quiet => {}
+        Output::Pretty => println!("Rebuilt user listings with {users} users"),
+        Output::Json => println!("{}", serde_json::json!({ "users": users })),
+    }
+    Ok(())
+}
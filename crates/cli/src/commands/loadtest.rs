@@ -0,0 +1,461 @@
+//! `hex-play loadtest`: drives a running server with a steady rate of user
+//! requests and reports latency percentiles and error rates per operation,
+//! to compare the server before and after a change.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context as _;
+use hex_play_api::grpc::{DEFAULT_ENDPOINT, user::api};
+use hex_play_core::user::UserToken;
+use tokio::{task::JoinSet, time::MissedTickBehavior};
+
+use crate::{commands::Output, error::CommandError};
+
+/// Base URL of the HTTP API on the default listen address.
+pub const DEFAULT_HTTP_ENDPOINT: &str = "http://localhost:3000";
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct LoadtestOptions {
+    /// Requests to start per second, whether or not earlier ones have
+    /// finished
+    #[arg(long, value_name = "count", default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    pub rps: u32,
+
+    /// How long to send requests for, e.g. 60s, 5m or 500ms
+    #[arg(long, value_name = "duration", default_value = "60s", value_parser = parse_duration)]
+    pub duration: Duration,
+
+    /// Operations to send with their relative weights
+    #[arg(long, value_name = "op:weight,...", default_value = "create:1,get:8,list:1")]
+    pub mix: TrafficMix,
+
+    /// API to send the requests to
+    #[arg(long, value_enum, default_value_t)]
+    pub target: Target,
+
+    /// Server to send the requests to (the local listener of the target when
+    /// omitted)
+    #[arg(long, value_name = "url")]
+    pub endpoint: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Target {
+    /// The gRPC user service
+    #[default]
+    Grpc,
+    /// The `/api/v2/user` routes
+    Http,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Operation {
+    /// Adds a new user.
+    Create,
+    /// Reads one of the users added so far.
+    Get,
+    /// Reads the first page of users.
+    List,
+}
+
+impl Operation {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Get => "get",
+            Self::List => "list",
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Operation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "create" => Ok(Self::Create),
+            "get" => Ok(Self::Get),
+            "list" => Ok(Self::List),
+            _ => Err(format!("unknown operation '{s}', expected create, get or list")),
+        }
+    }
+}
+
+/// Weighted operations, e.g. `create:1,get:8,list:1` for one create and
+/// one list for every eight gets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrafficMix {
+    weights: Vec<(Operation, u32)>,
+    total: u32,
+}
+
+impl TrafficMix {
+    /// The operation of the `n`th request. Every run sends the same
+    /// sequence, so runs compare like with like.
+    pub fn pick(&self, n: u64) -> Operation {
+        let mut slot = n % u64::from(self.total);
+        for &(operation, weight) in &self.weights {
+            if slot < u64::from(weight) {
+                return operation;
+            }
+            slot -= u64::from(weight);
+        }
+        unreachable!("slot is below the total weight")
+    }
+}
+
+impl FromStr for TrafficMix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights: Vec<(Operation, u32)> = Vec::new();
+        for entry in s.split(',') {
+            let (operation, weight) = entry.split_once(':').ok_or_else(|| format!("expected op:weight, got '{entry}'"))?;
+            let operation: Operation = operation.trim().parse()?;
+            let weight: u32 = weight.trim().parse().map_err(|_| format!("invalid weight '{weight}' for {operation}"))?;
+            if weights.iter().any(|&(seen, _)| seen == operation) {
+                return Err(format!("{operation} is given more than once"));
+            }
+            weights.push((operation, weight));
+        }
+
+        let total = weights.iter().try_fold(0u32, |total, &(_, weight)| total.checked_add(weight));
+        match total {
+            Some(0) => Err("at least one operation needs a weight above 0".to_string()),
+            Some(total) => Ok(Self { weights, total }),
+            None => Err("weights add up to too much".to_string()),
+        }
+    }
+}
+
+/// Parses durations like `60s`, `5m`, `1h` or `500ms`.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (amount, unit) = s.split_at(split);
+    let amount: u64 = amount.parse().map_err(|_| format!("expected a duration like 60s, got '{s}'"))?;
+    let duration = match unit {
+        "ms" => Duration::from_millis(amount),
+        "s" => Duration::from_secs(amount),
+        "m" => Duration::from_secs(amount * 60),
+        "h" => Duration::from_secs(amount * 3600),
+        _ => return Err(format!("unknown unit '{unit}' in '{s}', expected ms, s, m or h")),
+    };
+    if duration.is_zero() {
+        return Err("duration must be above 0".to_string());
+    }
+    Ok(duration)
+}
+
+/// Sends the mix at `rps` for `duration`, then waits for the requests still
+/// running and prints what each operation took.
+///
+/// Requests are started on schedule rather than after the previous one
+/// finished, so a slow server shows as higher latencies instead of fewer
+/// requests. Each gRPC request connects anew, as the other commands do.
+pub async fn run_loadtest_command(options: &LoadtestOptions, output: Output) -> Result<(), CommandError> {
+    let client = Arc::new(Client::new(options.target, options.endpoint.clone()).context("Couldn't create HTTP client")?);
+    // Emails are unique, so every run adds users of its own.
+    let run = chrono::Utc::now().timestamp_millis();
+
+    // Gets need a user to read before the first create finishes.
+    let seed = client.create(run, 0).await.context("Couldn't add a user to read")?;
+    let users = Arc::new(Mutex::new(vec![seed]));
+
+    let mut interval = tokio::time::interval(Duration::from_secs(1) / options.rps);
+    // Catch up after a stall, so the run sends `rps` on average.
+    interval.set_missed_tick_behavior(MissedTickBehavior::Burst);
+    let mut requests = JoinSet::new();
+    let started = Instant::now();
+    for n in 1.. {
+        interval.tick().await;
+        if started.elapsed() >= options.duration {
+            break;
+        }
+
+        let operation = options.mix.pick(n - 1);
+        let (client, users) = (client.clone(), users.clone());
+        requests.spawn(async move {
+            let request_started = Instant::now();
+            let result = match operation {
+                Operation::Create => client.create(run, n).await.map(|user| users.lock().unwrap().push(user)),
+                Operation::Get => {
+                    let user = {
+                        let users = users.lock().unwrap();
+                        users[n as usize % users.len()]
+                    };
+                    client.get(user).await
+                }
+                Operation::List => client.list().await,
+            };
+            Sample {
+                operation,
+                latency: request_started.elapsed(),
+                error: result.err().map(|error| format!("{error:#}")),
+            }
+        });
+    }
+    let sending = started.elapsed();
+
+    let mut report = Report::default();
+    while let Some(sample) = requests.join_next().await {
+        report.add(sample.context("Request task failed")?);
+    }
+    print_report(&report, sending, output);
+
+    Ok(())
+}
+
+/// The internal client of the chosen API.
+enum Client {
+    Grpc { endpoint: String },
+    Http { client: reqwest::Client, endpoint: String },
+}
+
+impl Client {
+    fn new(target: Target, endpoint: Option<String>) -> anyhow::Result<Self> {
+        Ok(match target {
+            Target::Grpc => Self::Grpc {
+                endpoint: endpoint.unwrap_or_else(|| DEFAULT_ENDPOINT.to_string()),
+            },
+            Target::Http => Self::Http {
+                client: reqwest::Client::builder().build()?,
+                endpoint: endpoint.unwrap_or_else(|| DEFAULT_HTTP_ENDPOINT.to_string()).trim_end_matches('/').to_string(),
+            },
+        })
+    }
+
+    async fn create(&self, run: i64, n: u64) -> anyhow::Result<UserToken> {
+        let name = format!("Load Test {n}");
+        let email = format!("loadtest-{run}-{n}@example.com");
+        match self {
+            Self::Grpc { endpoint } => Ok(api::create(endpoint, name, email, 30).await?.token),
+            Self::Http { client, endpoint } => {
+                #[derive(serde::Deserialize)]
+                struct Created {
+                    token: UserToken,
+                }
+
+                let created: Created = client
+                    .post(format!("{endpoint}/api/v2/user"))
+                    .json(&serde_json::json!({ "name": name, "email": email, "age": 30 }))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                Ok(created.token)
+            }
+        }
+    }
+
+    async fn get(&self, token: UserToken) -> anyhow::Result<()> {
+        match self {
+            Self::Grpc { endpoint } => {
+                api::get_by_token(endpoint, token).await?;
+            }
+            Self::Http { client, endpoint } => {
+                client.get(format!("{endpoint}/api/v2/user/{token}")).send().await?.error_for_status()?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn list(&self) -> anyhow::Result<()> {
+        match self {
+            Self::Grpc { endpoint } => {
+                api::list(endpoint, None, None, false).await?;
+            }
+            Self::Http { client, endpoint } => {
+                client.get(format!("{endpoint}/api/v2/user")).send().await?.error_for_status()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of one request.
+struct Sample {
+    operation: Operation,
+    latency: Duration,
+    error: Option<String>,
+}
+
+#[derive(Default)]
+struct Report {
+    operations: BTreeMap<Operation, OperationReport>,
+}
+
+#[derive(Default)]
+struct OperationReport {
+    latencies: Vec<Duration>,
+    /// Failures by message, to tell a few kinds of error from many.
+    errors: BTreeMap<String, u64>,
+}
+
+impl Report {
+    fn add(&mut self, sample: Sample) {
+        let operation = self.operations.entry(sample.operation).or_default();
+        operation.latencies.push(sample.latency);
+        if let Some(error) = sample.error {
+            *operation.errors.entry(error).or_default() += 1;
+        }
+    }
+
+    fn requests(&self) -> usize {
+        self.operations.values().map(|operation| operation.latencies.len()).sum()
+    }
+}
+
+impl OperationReport {
+    fn error_count(&self) -> u64 {
+        self.errors.values().sum()
+    }
+
+    fn error_rate(&self) -> f64 {
+        self.error_count() as f64 / self.latencies.len() as f64
+    }
+
+    /// Latency percentiles in milliseconds: p50, p90, p99 and the maximum.
+    fn percentiles(&self) -> [f64; 4] {
+        let mut latencies = self.latencies.clone();
+        latencies.sort_unstable();
+        [50.0, 90.0, 99.0, 100.0].map(|p| percentile(&latencies, p).as_secs_f64() * 1000.0)
+    }
+}
+
+/// The nearest-rank `p`th percentile of the sorted, non-empty `latencies`.
+fn percentile(latencies: &[Duration], p: f64) -> Duration {
+    let rank = (p / 100.0 * latencies.len() as f64).ceil() as usize;
+    latencies[rank.clamp(1, latencies.len()) - 1]
+}
+
+fn print_report(report: &Report, sending: Duration, output: Output) {
+    let rate = report.requests() as f64 / sending.as_secs_f64();
+    match output {
+        Output::Quiet => {}
+        Output::Pretty => {
+            println!("Sent {} requests in {:.1}s ({rate:.1}/s)", report.requests(), sending.as_secs_f64());
+            println!("operation\trequests\terrors\tp50 ms\tp90 ms\tp99 ms\tmax ms");
+            for (operation, operation_report) in &report.operations {
+                let [p50, p90, p99, max] = operation_report.percentiles();
+                println!(
+                    "{operation}\t{}\t{:.2}%\t{p50:.1}\t{p90:.1}\t{p99:.1}\t{max:.1}",
+                    operation_report.latencies.len(),
+                    operation_report.error_rate() * 100.0
+                );
+            }
+            for (operation, operation_report) in &report.operations {
+                for (error, count) in &operation_report.errors {
+                    println!("  {operation} failed {count} times: {error}");
+                }
+            }
+        }
+        Output::Json => {
+            let operations: Vec<_> = report
+                .operations
+                .iter()
+                .map(|(operation, operation_report)| {
+                    let [p50, p90, p99, max] = operation_report.percentiles();
+                    serde_json::json!({
+                        "operation": operation.as_str(),
+                        "requests": operation_report.latencies.len(),
+                        "errors": operation_report.error_count(),
+                        "error_rate": operation_report.error_rate(),
+                        "latency_ms": { "p50": p50, "p90": p90, "p99": p99, "max": max },
+                        "error_messages": operation_report.errors,
+                    })
+                })
+                .collect();
+            println!(
+                "{}",
+                serde_json::json!({
+                    "requests": report.requests(),
+                    "seconds": sending.as_secs_f64(),
+                    "rate": rate,
+                    "operations": operations,
+                })
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Operation, TrafficMix, parse_duration, percentile};
+
+    // ===================
+    // Tests: TrafficMix
+    // ===================
+    #[test]
+    fn test_mix_picks_operations_by_weight() {
+        let mix: TrafficMix = "create:1,get:8,list:1".parse().unwrap();
+
+        let picked: Vec<_> = (0..20).map(|n| mix.pick(n)).collect();
+
+        assert_eq!(picked.iter().filter(|&&operation| operation == Operation::Create).count(), 2);
+        assert_eq!(picked.iter().filter(|&&operation| operation == Operation::Get).count(), 16);
+        assert_eq!(picked.iter().filter(|&&operation| operation == Operation::List).count(), 2);
+    }
+
+    #[test]
+    fn test_mix_skips_operations_weighted_zero() {
+        let mix: TrafficMix = "create:0,get:1".parse().unwrap();
+
+        assert!((0..5).all(|n| mix.pick(n) == Operation::Get));
+    }
+
+    #[test]
+    fn test_mix_rejects_invalid_entries() {
+        assert!("get".parse::<TrafficMix>().is_err());
+        assert!("delete:1".parse::<TrafficMix>().is_err());
+        assert!("get:many".parse::<TrafficMix>().is_err());
+        assert!("get:1,get:2".parse::<TrafficMix>().is_err());
+        assert!("get:0".parse::<TrafficMix>().is_err());
+    }
+
+    // ===================
+    // Tests: parse_duration
+    // ===================
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("60s"), Ok(Duration::from_secs(60)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn test_parse_duration_rejects_invalid() {
+        assert!(parse_duration("60").is_err());
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("1d").is_err());
+    }
+
+    // ===================
+    // Tests: percentile
+    // ===================
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let latencies: Vec<_> = (1..=100).map(Duration::from_millis).collect();
+
+        assert_eq!(percentile(&latencies, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&latencies, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&latencies, 100.0), Duration::from_millis(100));
+        assert_eq!(percentile(&latencies[..1], 50.0), Duration::from_millis(1));
+    }
+}
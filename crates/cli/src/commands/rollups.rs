@@ -0,0 +1,30 @@
+//! `hex-play backfill-signup-rollups`: rebuilds the daily signup rollups
+//! straight from the database, without a running server.
+
+use anyhow::Context;
+use chrono::NaiveDate;
+use hex_play_core::{context::RequestContext, create_services};
+use hex_play_database::{create_repository_service_with_config, open_database};
+
+use crate::{commands::Output, config::Config};
+
+/// Recomputes the rollups of every day from `since` on, or of every day
+/// without it, then closes the database.
+pub async fn run_backfill_signup_rollups_command(config: &Config, since: Option<NaiveDate>, output: Output) -> anyhow::Result<()> {
+    let database = open_database(&config.database).await.context("Couldn't create database connection")?;
+    let repository_service = create_repository_service_with_config(database, &config.database)
+        .await
+        .context("Couldn't create database connection")?;
+    let services = create_services(repository_service.clone()).context("Couldn't create core services")?;
+
+    let result = services.user_service.refresh_signup_rollups(&RequestContext::internal(), since).await;
+    repository_service.repository().close().await.context("Couldn't close database")?;
+    let days = result.context("Couldn't refresh signup rollups")?;
+
+    match output {
+        Output::Quiet => {}
+        Output::Pretty => println!("Refreshed signup rollups for {days} days with signups"),
+        Output::Json => println!("{}", serde_json::json!({ "days": days, "since": since })),
+    }
+    Ok(())
+}
@@ -1,41 +1,389 @@
+use std::sync::Arc;
+
 use anyhow::Context;
-use hex_play_api::create_api_subsystem;
-use hex_play_core::create_services;
-use hex_play_database::{create_repository_service, open_database};
-use hex_play_frontend::server::launch_server_frontend;
-use tokio::time::Duration;
-use tokio_graceful_shutdown::{IntoSubsystem, SubsystemBuilder, SubsystemHandle, Toplevel};
+use chrono::TimeDelta;
+use hex_play_api::{ApiSubsystem, create_api_subsystem_with_config, install_metrics_recorder};
+use hex_play_core::{
+    CoreAdapters, CoreServices, Error,
+    activity::ActivityService,
+    create_services_with_adapters,
+    feature_flags::InMemoryFeatureFlags,
+    jobs::{Job, JobQueue, JobReceiver, job_channel},
+    lease::{LeaderElection, LeaseService},
+    log_filter::LogFilter,
+    repository::RepositoryService,
+    status::{SubsystemState, Subsystems},
+};
+use hex_play_database::{create_repository_service_with_config, open_database};
+use hex_play_directory::create_user_directory;
+use hex_play_frontend::server::{FrontendSubsystem, create_frontend_subsystem};
+use hex_play_media::RasterImageProcessor;
+use hex_play_storage::create_object_storage;
+use tokio::time::{Duration, timeout};
+use tokio_graceful_shutdown::{IntoSubsystem, NestedSubsystem, SubsystemBuilder, SubsystemHandle, Toplevel};
+
+use crate::{
+    config::{Config, ShutdownConfig},
+    reporting::create_error_reporter,
+};
+
+/// Which parts of the server run, so one binary can serve API-only or
+/// frontend-only deployments. Background jobs run whatever is selected.
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct ServerOptions {
+    /// Don't serve the HTTP API, admin routes included
+    #[arg(long)]
+    pub no_http: bool,
 
-use crate::config::Config;
+    /// Don't serve gRPC
+    #[arg(long)]
+    pub no_grpc: bool,
 
-pub async fn run_server_command(config: &Config) -> anyhow::Result<()> {
+    /// Don't serve the frontend
+    #[arg(long)]
+    pub no_frontend: bool,
+
+    /// Serve the HTTP API on this port of every configured address
+    #[arg(long, value_name = "port", conflicts_with = "no_http")]
+    pub http_port: Option<u16>,
+
+    /// Serve gRPC on this port of every configured address, unless it
+    /// shares the HTTP port
+    #[arg(long, value_name = "port", conflicts_with = "no_grpc")]
+    pub grpc_port: Option<u16>,
+}
+
+/// Runs the server until it is told to stop. `log_filter` lets the admin
+/// routes change the log filter while it runs.
+pub async fn run_server_command(config: &Config, options: &ServerOptions, log_filter: Arc<dyn LogFilter>) -> anyhow::Result<()> {
     let crate_version = clap::crate_version!();
 
     tracing::info!("HexPlay {}", crate_version);
 
     let span = tracing::span!(tracing::Level::TRACE, "CreateServer").entered();
 
+    install_metrics_recorder().context("Couldn't install the metrics recorder")?;
+
     let database = open_database(&config.database).await.context("Couldn't create database connection")?;
-    let repository_service = create_repository_service(database).await.context("Couldn't create database connection")?;
+    let repository_service = create_repository_service_with_config(database, &config.database)
+        .await
+        .context("Couldn't create database connection")?;
 
     let server = {
-        let services = create_services(repository_service.clone()).context("Couldn't create core services")?;
-        let api_subsystem = create_api_subsystem(services.clone());
+        let feature_flags = config.load_feature_flags().context("Couldn't load feature flags")?;
+        let (job_queue, job_receiver) = job_channel();
+        let job_queue: Arc<dyn JobQueue> = Arc::new(job_queue);
+        let adapters = CoreAdapters {
+            object_storage: create_object_storage(&config.storage).context("Couldn't create object storage")?,
+            image_processor: Arc::new(RasterImageProcessor),
+            job_queue: job_queue.clone(),
+            error_reporter: create_error_reporter(&config.sentry).context("Couldn't set up error reporting")?,
+            log_filter,
+            user_directory: create_user_directory(&config.directory).context("Couldn't create user directory")?,
+        };
+        let services = create_services_with_adapters(repository_service.clone(), Arc::new(InMemoryFeatureFlags::new(feature_flags)), adapters)
+            .context("Couldn't create core services")?;
+        services.maintenance_mode.set_enabled(config.maintenance_mode);
+        services.user_list_cache.set_ttl(config.user_list_cache_ttl());
+        let mut http_config = config.http.clone();
+        if let Some(port) = options.http_port {
+            http_config.listen_addrs = with_port(http_config.listen_addrs(), port);
+        }
+        let mut grpc_config = config.grpc.clone();
+        if let Some(port) = options.grpc_port {
+            grpc_config.listen_addrs = with_port(grpc_config.listen_addrs(), port);
+        }
+        let http_addrs = if options.no_http {
+            OFF.to_string()
+        } else {
+            http_config.listen_addrs().join(",")
+        };
+        let grpc_addrs = match (options.no_grpc, grpc_config.single_port && !options.no_http) {
+            (true, _) => OFF.to_string(),
+            (false, true) => http_addrs.clone(),
+            (false, false) => grpc_config.listen_addrs().join(","),
+        };
+        let mut api_subsystem = create_api_subsystem_with_config(services.clone(), http_config, grpc_config)
+            .with_api_keys(&config.api_keys)
+            .with_config_schema(Config::schema());
+        if options.no_http {
+            api_subsystem = api_subsystem.without_http();
+        }
+        if options.no_grpc {
+            api_subsystem = api_subsystem.without_grpc();
+        }
+
+        let frontend_subsystem = if options.no_frontend {
+            None
+        } else {
+            Some(create_frontend_subsystem(&config.frontend, &config.auth, services.clone()).context("Couldn't set up the frontend")?)
+        };
+        let frontend_addr = if options.no_frontend {
+            OFF.to_string()
+        } else {
+            format!("{}:{}", config.frontend.listen_ip, config.frontend.listen_port)
+        };
 
-        launch_server_frontend(&config.frontend, services.clone());
+        tracing::info!(
+            version = crate_version,
+            http = %http_addrs,
+            grpc = %grpc_addrs,
+            frontend = %frontend_addr,
+            maintenance_mode = services.maintenance_mode.is_enabled(),
+            feature_flags = services.feature_flags.list().len(),
+            "HexPlay ready"
+        );
 
+        let server_subsystem = ServerSubsystem {
+            api: api_subsystem,
+            frontend: frontend_subsystem,
+            jobs: JobsSubsystem {
+                receiver: job_receiver,
+                services: services.clone(),
+            },
+            signup_rollups: SchedulerSubsystem::new(
+                job_queue,
+                services.lease_service.clone(),
+                Job::RefreshSignupRollups,
+                config.signup_rollup_interval(),
+            ),
+            activity: ActivitySubsystem {
+                activity_service: services.activity_service.clone(),
+                interval: config.activity_flush_interval(),
+            },
+            repository_service,
+            subsystems: services.subsystems.clone(),
+            timeouts: config.shutdown.clone(),
+        };
+        let total_timeout = config.shutdown.total_timeout();
         Toplevel::new(async |s: &mut SubsystemHandle| {
-            s.start(SubsystemBuilder::new("Api", api_subsystem.into_subsystem()));
+            s.start(SubsystemBuilder::new("Server", server_subsystem.into_subsystem()));
         })
         .catch_signals()
-        .handle_shutdown_requests(Duration::from_millis(1000))
+        .handle_shutdown_requests(total_timeout)
     };
 
     span.exit();
 
     server.await?;
 
-    repository_service.repository().close().await.context("Couldn't close database")?;
-
     Ok(())
 }
+
+/// Logged in place of the addresses of a part of the server turned off.
+const OFF: &str = "off";
+
+/// `addrs` with their port replaced by `port`.
+fn with_port(addrs: Vec<&str>, port: u16) -> Vec<String> {
+    addrs
+        .into_iter()
+        .map(|addr| match addr.rsplit_once(':') {
+            Some((host, _)) => format!("{host}:{port}"),
+            None => format!("{addr}:{port}"),
+        })
+        .collect()
+}
+
+/// Runs the API, frontend and background jobs, and on shutdown closes the
+/// database pool only once all have drained, so no request or job in flight
+/// loses its connection. Records in `subsystems` which are running, for the
+/// status to report.
+struct ServerSubsystem {
+    api: ApiSubsystem,
+    frontend: Option<FrontendSubsystem>,
+    jobs: JobsSubsystem,
+    signup_rollups: SchedulerSubsystem,
+    activity: ActivitySubsystem,
+    repository_service: Arc<RepositoryService>,
+    subsystems: Subsystems,
+    timeouts: ShutdownConfig,
+}
+
+impl IntoSubsystem<Error> for ServerSubsystem {
+    async fn run(self, subsys: &mut SubsystemHandle) -> Result<(), Error> {
+        let api = subsys.start(SubsystemBuilder::new("Api", self.api.into_subsystem()));
+        let frontend = self
+            .frontend
+            .map(|frontend| subsys.start(SubsystemBuilder::new("Frontend", frontend.into_subsystem())));
+        let jobs = subsys.start(SubsystemBuilder::new("Jobs", self.jobs.into_subsystem()));
+        subsys.start(SubsystemBuilder::new("SignupRollups", self.signup_rollups.into_subsystem()));
+        let activity_service = self.activity.activity_service.clone();
+        subsys.start(SubsystemBuilder::new("Activity", self.activity.into_subsystem()));
+        let started = ["Api", "Jobs", "SignupRollups", "Activity"]
+            .into_iter()
+            .chain(frontend.is_some().then_some("Frontend"));
+        for name in started {
+            self.subsystems.set(name, SubsystemState::Running);
+        }
+
+        // Every listener stops accepting as soon as shutdown is requested.
+        subsys.on_shutdown_requested().await;
+        self.subsystems.set_all(SubsystemState::Stopping);
+        tokio::join!(drain("Api", &api, self.timeouts.api_timeout()), async {
+            if let Some(frontend) = &frontend {
+                drain("Frontend", frontend, self.timeouts.frontend_timeout()).await;
+            }
+        });
+        drain("Jobs", &jobs, self.timeouts.jobs_timeout()).await;
+
+        // Sightings recorded while draining are written before the pool
+        // closes.
+        let database_timeout = self.timeouts.database_timeout();
+        let close = async {
+            if let Err(error) = activity_service.flush().await {
+                tracing::warn!(%error, "Couldn't write user activity");
+            }
+            self.repository_service.repository().close().await
+        };
+        match timeout(database_timeout, close).await {
+            Ok(result) => result?,
+            Err(_) => tracing::warn!("Database pool did not close within {:?}", database_timeout),
+        }
+        tracing::info!("ServerSubsystem shut down");
+
+        Ok(())
+    }
+}
+
+/// Runs background jobs one at a time, in the order they were queued. On
+/// shutdown the job in progress finishes and those still queued are
+/// dropped; every job tolerates never running.
+struct JobsSubsystem {
+    receiver: JobReceiver,
+    services: Arc<CoreServices>,
+}
+
+impl IntoSubsystem<Error> for JobsSubsystem {
+    async fn run(mut self, subsys: &mut SubsystemHandle) -> Result<(), Error> {
+        loop {
+            // Only waiting for the next job is cancelled, never a job
+            // itself.
+            let job = tokio::select! {
+                _ = subsys.on_shutdown_requested() => break,
+                job = self.receiver.recv() => match job {
+                    Some(job) => job,
+                    None => break,
+                },
+            };
+            let name = job.name();
+            if let Err(error) = self.services.run_job(job).await {
+                tracing::warn!(job = name, %error, "Background job failed");
+            }
+        }
+        tracing::info!("JobsSubsystem shut down");
+
+        Ok(())
+    }
+}
+
+/// Enqueues `job` on startup and then every `period` until shutdown. A
+/// job still queued from its last turn is queued again, so scheduled jobs
+/// must tolerate running twice.
+///
+/// With several instances sharing the database, only the elected leader
+/// enqueues it. The leader renews its lease on every turn and resigns on
+/// shutdown; if it crashes, another instance takes over once the lease
+/// expires, two periods after the last renewal.
+struct SchedulerSubsystem {
+    job_queue: Arc<dyn JobQueue>,
+    leader: LeaderElection,
+    job: Job,
+    period: Duration,
+}
+
+impl SchedulerSubsystem {
+    fn new(job_queue: Arc<dyn JobQueue>, lease_service: Arc<dyn LeaseService>, job: Job, period: Duration) -> Self {
+        // Outlasting one period keeps a late turn of the leader from losing
+        // the lease to another instance.
+        let ttl = TimeDelta::from_std(period * 2).unwrap_or(TimeDelta::MAX);
+        Self {
+            job_queue,
+            leader: LeaderElection::new(lease_service, format!("scheduler:{}", job.name()), ttl),
+            job,
+            period,
+        }
+    }
+}
+
+impl IntoSubsystem<Error> for SchedulerSubsystem {
+    async fn run(self, subsys: &mut SubsystemHandle) -> Result<(), Error> {
+        let mut interval = tokio::time::interval(self.period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = subsys.on_shutdown_requested() => break,
+                _ = interval.tick() => {}
+            }
+            if self.leader.heartbeat().await.is_none() {
+                continue;
+            }
+            if let Err(error) = self.job_queue.enqueue(self.job.clone()).await {
+                tracing::warn!(job = self.job.name(), %error, "Couldn't enqueue scheduled job");
+            }
+        }
+        self.leader.resign().await;
+        tracing::info!(job = self.job.name(), "SchedulerSubsystem shut down");
+
+        Ok(())
+    }
+}
+
+/// Writes buffered user activity every `interval` until shutdown. The
+/// final flush happens in [`ServerSubsystem`], once requests have drained.
+struct ActivitySubsystem {
+    activity_service: Arc<dyn ActivityService>,
+    interval: Duration,
+}
+
+impl IntoSubsystem<Error> for ActivitySubsystem {
+    async fn run(self, subsys: &mut SubsystemHandle) -> Result<(), Error> {
+        let mut interval = tokio::time::interval(self.interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // The first tick completes at once and there is nothing to write yet.
+        interval.tick().await;
+        loop {
+            tokio::select! {
+                _ = subsys.on_shutdown_requested() => break,
+                _ = interval.tick() => {}
+            }
+            if let Err(error) = self.activity_service.flush().await {
+                tracing::warn!(%error, "Couldn't write user activity");
+            }
+        }
+        tracing::info!("ActivitySubsystem shut down");
+
+        Ok(())
+    }
+}
+
+/// Waits for `subsystem` to finish the requests in flight, aborting it
+/// once `limit` has passed.
+async fn drain(name: &str, subsystem: &NestedSubsystem, limit: Duration) {
+    // Failures are forwarded to the toplevel, which reports them, so
+    // joining only tells whether the subsystem finished.
+    if timeout(limit, subsystem.join()).await.is_err() {
+        tracing::warn!("{} did not drain within {:?}, aborting", name, limit);
+        subsystem.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::with_port;
+
+    // ===================
+    // Tests: with_port
+    // ===================
+    #[test]
+    fn test_with_port_replaces_port_of_every_address() {
+        assert_eq!(
+            with_port(vec!["0.0.0.0:3000", "[::1]:3000", "localhost:3000"], 4000),
+            vec!["0.0.0.0:4000", "[::1]:4000", "localhost:4000"]
+        );
+    }
+
+    #[test]
+    fn test_with_port_adds_missing_port() {
+        assert_eq!(with_port(vec!["127.0.0.1"], 4000), vec!["127.0.0.1:4000"]);
+    }
+}
@@ -0,0 +1,62 @@
+//! `hex-play status`: asks the server how it and what it depends on are
+//! doing, and prints the report.
+
+use hex_play_api::grpc::system;
+use hex_play_core::status::{ComponentStatus, Health, SystemStatus};
+
+use crate::{commands::Output, error::CommandError};
+
+/// Prints the status of the server at `endpoint`, failing if any component
+/// is down.
+pub async fn run_status_command(endpoint: &str, output: Output) -> Result<(), CommandError> {
+    let status = system::api::status(endpoint).await?;
+    match output {
+        Output::Quiet => {}
+        Output::Pretty => print_report(&status),
+        Output::Json => println!("{}", to_json(&status)),
+    }
+
+    if status.health() == Health::Down {
+        let down: Vec<_> = status
+            .components
+            .iter()
+            .filter(|component| component.health == Health::Down)
+            .map(|component| component.name.as_str())
+            .collect();
+        return Err(anyhow::anyhow!("down: {}", down.join(", ")).into());
+    }
+    Ok(())
+}
+
+fn print_report(status: &SystemStatus) {
+    println!("Status: {}", status.health().as_str());
+    for component in &status.components {
+        let latency = component
+            .latency
+            .map(|latency| format!("{:.1}ms", latency.as_secs_f64() * 1000.0))
+            .unwrap_or_default();
+        println!(
+            "  {:<20} {:<8} {:>8}  {}",
+            component.name,
+            component.health.as_str(),
+            latency,
+            component.detail.as_deref().unwrap_or_default()
+        );
+    }
+}
+
+fn to_json(status: &SystemStatus) -> serde_json::Value {
+    serde_json::json!({
+        "health": status.health().as_str(),
+        "components": status.components.iter().map(component_to_json).collect::<Vec<_>>(),
+    })
+}
+
+fn component_to_json(component: &ComponentStatus) -> serde_json::Value {
+    serde_json::json!({
+        "name": component.name,
+        "health": component.health.as_str(),
+        "latency_ms": component.latency.map(|latency| latency.as_micros() as f64 / 1000.0),
+        "detail": component.detail,
+    })
+}
@@ -0,0 +1,156 @@
+//! User commands against a running server over gRPC.
+
+use std::pin::pin;
+
+use hex_play_api::grpc::user::api;
+use hex_play_core::{
+    Error,
+    user::{User, UserId, UserToken},
+};
+use tokio_stream::StreamExt;
+
+use crate::{commands::Output, error::CommandError};
+
+pub async fn run_add_user_command(endpoint: &str, name: String, email: String, age: i16, output: Output) -> Result<(), CommandError> {
+    let user = api::create(endpoint, name, email, age).await?;
+    print_user(&user, output);
+    Ok(())
+}
+
+pub async fn run_delete_user_command(endpoint: &str, id: UserId, output: Output) -> Result<(), CommandError> {
+    let user = api::delete(endpoint, id).await?;
+    print_user(&user, output);
+    Ok(())
+}
+
+pub async fn run_update_user_command(
+    endpoint: &str,
+    id: UserId,
+    name: Option<String>,
+    email: Option<String>,
+    age: Option<i16>,
+    expected_version: Option<u64>,
+    output: Output,
+) -> Result<(), CommandError> {
+    let user = api::update(endpoint, id, name, email, age, expected_version).await?;
+    print_user(&user, output);
+    Ok(())
+}
+
+pub async fn run_get_user_command(endpoint: &str, id: UserId, output: Output) -> Result<(), CommandError> {
+    let user = api::get(endpoint, id).await?;
+    print_user(&user, output);
+    Ok(())
+}
+
+pub async fn run_get_user_by_token_command(endpoint: &str, token: UserToken, output: Output) -> Result<(), CommandError> {
+    let user = api::get_by_token(endpoint, token).await?;
+    print_user(&user, output);
+    Ok(())
+}
+
+/// Prints up to `pages` pages of users starting at `start_id`, following
+/// the cursor the server returns, or with `all`, every user from `start_id`
+/// onwards. Users are printed one per line as they arrive rather than after
+/// the whole listing has been received.
+pub async fn run_get_users_command(
+    endpoint: &str,
+    start_id: Option<UserId>,
+    page_size: Option<u64>,
+    pages: u64,
+    total: bool,
+    all: bool,
+    output: Output,
+) -> Result<(), CommandError> {
+    if all {
+        stream_users(endpoint, start_id, output).await?;
+    } else {
+        page_users(endpoint, start_id, page_size, pages, total, output).await?;
+    }
+
+    Ok(())
+}
+
+/// Pages through users with `List`, stopping after `pages` pages or on the
+/// last one. The cursor to continue from goes to stderr so piped output
+/// stays one user per line.
+async fn page_users(endpoint: &str, start_id: Option<UserId>, page_size: Option<u64>, pages: u64, total: bool, output: Output) -> Result<(), Error> {
+    let mut cursor = start_id;
+    for page_number in 0..pages {
+        // Counting scans the table, so only the first page asks.
+        let page = api::list(endpoint, cursor, page_size, total && page_number == 0).await?;
+        for user in &page.users {
+            print_row(user, output);
+        }
+        if let (Some(total_size), Output::Pretty) = (page.total_size, output) {
+            eprintln!("{total_size} users in total");
+        }
+
+        cursor = page.next_cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+
+    if let (Some(cursor), Output::Pretty) = (cursor, output) {
+        eprintln!("More users: --cursor {cursor}");
+    }
+
+    Ok(())
+}
+
+/// Pages through every user with the server-side streaming export.
+async fn stream_users(endpoint: &str, start_id: Option<UserId>, output: Output) -> Result<(), Error> {
+    let mut users = pin!(api::export(endpoint, start_id).await?);
+    while let Some(user) = users.next().await {
+        print_row(&user?, output);
+    }
+
+    Ok(())
+}
+
+fn print_user(user: &User, output: Output) {
+    match output {
+        Output::Quiet => {}
+        Output::Pretty => {
+            println!("User {} (version {})", user.id, user.version);
+            println!("  Token:   {}", user.token);
+            println!("  Name:    {}", user.name);
+            println!("  Email:   {}", user.email);
+            println!("  Age:     {}", user.age);
+            println!("  Created: {}", user.created_at.to_rfc3339());
+            println!("  Updated: {}", user.updated_at.to_rfc3339());
+        }
+        Output::Json => println!("{}", to_json(user)),
+    }
+}
+
+fn print_row(user: &User, output: Output) {
+    match output {
+        Output::Quiet => {}
+        Output::Pretty => println!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            user.id,
+            user.version,
+            user.token,
+            user.name,
+            user.email,
+            user.age,
+            user.updated_at.to_rfc3339()
+        ),
+        Output::Json => println!("{}", to_json(user)),
+    }
+}
+
+fn to_json(user: &User) -> serde_json::Value {
+    serde_json::json!({
+        "id": user.id,
+        "version": user.version,
+        "token": user.token,
+        "name": user.name,
+        "email": user.email,
+        "age": user.age,
+        "created_at": user.created_at.to_rfc3339(),
+        "updated_at": user.updated_at.to_rfc3339(),
+    })
+}
@@ -1,23 +1,1134 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use hex_play_api::{ApiKeyConfig, GrpcConfig, HttpConfig, parse_socket_mode};
+use hex_play_core::activity::DEFAULT_ACTIVITY_FLUSH_INTERVAL;
 use hex_play_database::DatabaseConfig;
-use hex_play_frontend::FrontendConfig;
+use hex_play_directory::DirectoryConfig;
+use hex_play_frontend::{AuthConfig, FrontendConfig};
+use hex_play_storage::StorageConfig;
+use hex_play_utils::secret::Secret;
+use schemars::{JsonSchema, generate::SchemaSettings};
 use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    error::Error,
+    reporting::SentryConfig,
+    secrets::{FileSecretsProvider, SecretsProvider},
+};
+
+const ENV_PREFIX: &str = "HPLAY";
+const ENV_SEPARATOR: &str = "__";
+const LIST_SEPARATOR: &str = ",";
+
+/// Settings read as comma separated lists.
+const LIST_KEYS: &[&str] = &[
+    "http.listen_addrs",
+    "http.admin_listen_addrs",
+    "grpc.listen_addrs",
+    "grpc.cors_allowed_origins",
+    "auth.oidc.scopes",
+    "database.user_repository_decorators",
+];
+
+/// Names the Vault KV entry (`<mount>/<entry>`) to read secrets from.
+const VAULT_PATH_VAR: &str = "HPLAY__SECRETS__VAULT_PATH";
+
+/// A setting that may come from a secrets provider instead of the
+/// environment.
+struct SecretSetting {
+    /// Name the secret is stored under in a provider.
+    name: &'static str,
+    /// Variable that sets it directly; `<var>_FILE` names a file holding it.
+    var: &'static str,
+    apply: fn(&mut Config, Secret),
+}
 
-use crate::error::Error;
+const SECRETS: &[SecretSetting] = &[
+    SecretSetting {
+        name: "database_url",
+        var: "HPLAY__DATABASE__DATABASE_URL",
+        apply: |config, secret| config.database.database_url = secret,
+    },
+    SecretSetting {
+        name: "scim_token",
+        var: "HPLAY__HTTP__SCIM_TOKEN",
+        apply: |config, secret| config.http.scim_token = Some(secret),
+    },
+    SecretSetting {
+        name: "webhook_secret",
+        var: "HPLAY__HTTP__WEBHOOK_SECRET",
+        apply: |config, secret| config.http.webhook_secret = Some(secret),
+    },
+    SecretSetting {
+        name: "directory_token",
+        var: "HPLAY__DIRECTORY__TOKEN",
+        apply: |config, secret| config.directory.token = Some(secret),
+    },
+    SecretSetting {
+        name: "sentry_dsn",
+        var: "HPLAY__SENTRY__DSN",
+        apply: |config, secret| config.sentry.dsn = Some(secret),
+    },
+    SecretSetting {
+        name: "oidc_client_secret",
+        var: "HPLAY__AUTH__OIDC__CLIENT_SECRET",
+        apply: |config, secret| {
+            if let Some(oidc) = config.auth.oidc.as_mut() {
+                oidc.client_secret = Some(secret);
+            }
+        },
+    },
+];
 
-#[derive(Debug, Deserialize)]
+/// Settings `[auth.oidc]` cannot do without.
+const OIDC_REQUIRED: &[&str] = &[
+    "HPLAY__AUTH__OIDC__ISSUER_URL",
+    "HPLAY__AUTH__OIDC__CLIENT_ID",
+    "HPLAY__AUTH__OIDC__REDIRECT_URL",
+];
+
+const DATABASE_URL_SCHEMES: &[&str] = &["postgres://", "postgresql://", "mysql://", "sqlite:"];
+
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct Config {
+    // Defaulted so a database URL from a secrets provider alone suffices;
+    // `validate` reports it missing otherwise.
+    #[serde(default)]
     pub database: DatabaseConfig,
+
+    #[serde(default)]
     pub frontend: FrontendConfig,
+
+    #[serde(default)]
+    pub auth: AuthConfig,
+
+    #[serde(default)]
+    pub http: HttpConfig,
+
+    #[serde(default)]
+    pub grpc: GrpcConfig,
+
+    /// (optional) API keys HTTP and gRPC clients authenticate with, by
+    /// name, e.g. `HPLAY__API_KEYS__OPS__KEY=${OPS_API_KEY}` with
+    /// `HPLAY__API_KEYS__OPS__USER_ID=1`. Without any, every request is
+    /// anonymous.
+    #[serde(default)]
+    pub api_keys: HashMap<String, ApiKeyConfig>,
+
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+
+    #[serde(default)]
+    pub storage: StorageConfig,
+
+    #[serde(default)]
+    pub directory: DirectoryConfig,
+
+    #[serde(default)]
+    pub sentry: SentryConfig,
+
+    /// (optional) Start in read-only maintenance mode. Can be toggled at
+    /// runtime through `/api/admin/maintenance`.
+    #[serde(default)]
+    pub maintenance_mode: bool,
+
+    /// (optional) Path to a TOML, YAML or JSON file of `flag_name = bool`
+    /// feature flag defaults.
+    #[serde(default)]
+    pub feature_flags_file: Option<String>,
+
+    /// (optional) Feature flag defaults applied over `feature_flags_file`,
+    /// e.g. `HPLAY__FEATURE_FLAGS__SOFT_DELETE=true`.
+    #[serde(default)]
+    pub feature_flags: HashMap<String, bool>,
+
+    /// (optional) Seconds between writes of buffered user activity, e.g.
+    /// `HPLAY__ACTIVITY_FLUSH_INTERVAL_SECS=60`. Defaults to 30.
+    #[serde(default)]
+    #[schemars(extend("default" = DEFAULT_ACTIVITY_FLUSH_INTERVAL.as_secs()))]
+    pub activity_flush_interval_secs: Option<u64>,
+
+    /// (optional) Seconds between refreshes of the daily signup rollups the
+    /// statistics read, e.g. `HPLAY__SIGNUP_ROLLUP_INTERVAL_SECS=60`.
+    /// Defaults to 300.
+    #[serde(default)]
+    #[schemars(extend("default" = DEFAULT_SIGNUP_ROLLUP_INTERVAL_SECS))]
+    pub signup_rollup_interval_secs: Option<u64>,
+
+    /// (optional) Milliseconds pages of users are served from memory before
+    /// being read again, e.g. `HPLAY__USER_LIST_CACHE_TTL_MS=2000`. Writes
+    /// through this server drop them sooner. Defaults to 0, not caching.
+    #[serde(default)]
+    pub user_list_cache_ttl_ms: u64,
+
+    /// (optional) File to append the access log to, one JSON object per HTTP
+    /// request, e.g. `HPLAY__ACCESS_LOG_FILE=/var/log/hex-play/access.log`.
+    /// Without it, access events are logged with everything else under the
+    /// `access` target. `RUST_LOG` applies to them either way.
+    #[serde(default)]
+    pub access_log_file: Option<String>,
 }
 
 impl Config {
-    pub fn load() -> Result<Config, Error> {
+    /// Loads the configuration from `HPLAY__*` environment variables.
+    ///
+    /// Values may reference other variables as `${VAR}`. Secrets such as the
+    /// database URL may instead be read from the file named by
+    /// `<VAR>_FILE`, or from the Vault entry named by
+    /// `HPLAY__SECRETS__VAULT_PATH` using `VAULT_ADDR` and `VAULT_TOKEN`.
+    /// Every missing or invalid setting is reported at once, by the variable
+    /// that sets it.
+    pub async fn load() -> Result<Config, Error> {
+        let prefix = format!("{ENV_PREFIX}{ENV_SEPARATOR}");
+        let vars = std::env::vars().filter(|(name, _)| name.starts_with(&prefix)).collect();
+
+        Self::from_vars(vars, |name| std::env::var(name).ok()).await
+    }
+
+    async fn from_vars(vars: HashMap<String, String>, lookup: impl Fn(&str) -> Option<String>) -> Result<Config, Error> {
+        let mut issues = Vec::new();
+        let mut vars = interpolate(vars, &lookup, &mut issues);
+        let providers = secrets_providers(&mut vars, &lookup, &mut issues);
+        let mut secrets = resolve_secrets(&vars, &providers, &mut issues).await;
+        validate(&vars, &secrets, &mut issues);
+        if !issues.is_empty() {
+            issues.sort();
+            return Err(Error::Invalid(issues));
+        }
+
+        let environment = LIST_KEYS.iter().fold(
+            config::Environment::with_prefix(ENV_PREFIX)
+                .try_parsing(true)
+                .separator(ENV_SEPARATOR)
+                .list_separator(LIST_SEPARATOR),
+            |environment, key| environment.with_list_parse_key(key),
+        );
         let config = config::Config::builder()
-            .add_source(config::Environment::with_prefix("HPLAY").try_parsing(true).separator("__"))
+            .add_source(environment.source(Some(vars.into_iter().collect())))
             .build()?;
 
-        let config: Config = config.try_deserialize()?;
+        let mut config: Config = config.try_deserialize()?;
+        for setting in SECRETS {
+            if let Some(secret) = secrets.remove(setting.var) {
+                (setting.apply)(&mut config, secret);
+            }
+        }
 
         Ok(config)
     }
+
+    /// Resolves feature flag defaults from `feature_flags_file`, then
+    /// `feature_flags`.
+    pub fn load_feature_flags(&self) -> Result<HashMap<String, bool>, Error> {
+        let mut flags: HashMap<String, bool> = match &self.feature_flags_file {
+            Some(path) => config::Config::builder().add_source(config::File::with_name(path)).build()?.try_deserialize()?,
+            None => HashMap::new(),
+        };
+        flags.extend(self.feature_flags.iter().map(|(name, enabled)| (name.clone(), *enabled)));
+
+        Ok(flags)
+    }
+
+    pub fn activity_flush_interval(&self) -> Duration {
+        self.activity_flush_interval_secs.map_or(DEFAULT_ACTIVITY_FLUSH_INTERVAL, Duration::from_secs)
+    }
+
+    pub fn signup_rollup_interval(&self) -> Duration {
+        Duration::from_secs(self.signup_rollup_interval_secs.unwrap_or(DEFAULT_SIGNUP_ROLLUP_INTERVAL_SECS))
+    }
+
+    pub fn user_list_cache_ttl(&self) -> Duration {
+        Duration::from_millis(self.user_list_cache_ttl_ms)
+    }
+
+    /// The JSON schema of the settings, for deployment tooling to check
+    /// manifests against. Each setting carries its default, if it has one,
+    /// and the variable that sets it as `x-env-var`; secrets also name the
+    /// variable pointing at a file holding them as `x-env-var-file`.
+    pub fn schema() -> Value {
+        let generator = SchemaSettings::draft2020_12()
+            .with(|settings| settings.inline_subschemas = true)
+            .into_generator();
+        let mut schema = generator.into_root_schema_for::<Config>().to_value();
+        annotate_env_vars(&mut schema, ENV_PREFIX);
+
+        schema
+    }
+}
+
+const DEFAULT_SIGNUP_ROLLUP_INTERVAL_SECS: u64 = 300;
+
+/// Names the variable setting each property of `schema`, recursing into
+/// nested sections. Maps are set one entry at a time, by a variable ending
+/// in the key.
+fn annotate_env_vars(schema: &mut Value, prefix: &str) {
+    let Some(properties) = schema.get_mut("properties").and_then(Value::as_object_mut) else {
+        return;
+    };
+    for (name, property) in properties {
+        let var = format!("{prefix}{ENV_SEPARATOR}{}", name.to_uppercase());
+        if property.get("properties").is_some() {
+            annotate_env_vars(property, &var);
+            continue;
+        }
+        let Some(property) = property.as_object_mut() else {
+            continue;
+        };
+        if property.contains_key("additionalProperties") {
+            property.insert("x-env-var".to_string(), format!("{var}{ENV_SEPARATOR}<KEY>").into());
+        } else {
+            property.insert("x-env-var".to_string(), var.clone().into());
+        }
+        if SECRETS.iter().any(|setting| setting.var == var) {
+            property.insert("x-env-var-file".to_string(), format!("{var}_FILE").into());
+        }
+    }
+}
+
+/// How long each part of the server gets to shut down. Shutdown runs in
+/// order: listeners stop accepting, the API and frontend drain the requests
+/// in flight, the background job in progress finishes, then the database
+/// pool is closed.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct ShutdownConfig {
+    /// (optional) Seconds the API gets to finish requests in flight, e.g.
+    /// `HPLAY__SHUTDOWN__API_TIMEOUT_SECS=30`. Defaults to 10.
+    #[serde(default)]
+    #[schemars(extend("default" = 10))]
+    pub api_timeout_secs: Option<u64>,
+
+    /// (optional) Seconds the frontend gets to finish requests in flight.
+    /// Defaults to 5.
+    #[serde(default)]
+    #[schemars(extend("default" = 5))]
+    pub frontend_timeout_secs: Option<u64>,
+
+    /// (optional) Seconds the background job in progress gets to finish.
+    /// Defaults to 10.
+    #[serde(default)]
+    #[schemars(extend("default" = 10))]
+    pub jobs_timeout_secs: Option<u64>,
+
+    /// (optional) Seconds closing the database pool may take. Defaults to 5.
+    #[serde(default)]
+    #[schemars(extend("default" = 5))]
+    pub database_timeout_secs: Option<u64>,
+}
+
+impl ShutdownConfig {
+    pub fn api_timeout(&self) -> Duration {
+        Duration::from_secs(self.api_timeout_secs.unwrap_or(10))
+    }
+
+    pub fn frontend_timeout(&self) -> Duration {
+        Duration::from_secs(self.frontend_timeout_secs.unwrap_or(5))
+    }
+
+    pub fn jobs_timeout(&self) -> Duration {
+        Duration::from_secs(self.jobs_timeout_secs.unwrap_or(10))
+    }
+
+    pub fn database_timeout(&self) -> Duration {
+        Duration::from_secs(self.database_timeout_secs.unwrap_or(5))
+    }
+
+    /// Time the whole shutdown may take: the slower drain, then the
+    /// background job, then closing the database, with a second to spare.
+    pub fn total_timeout(&self) -> Duration {
+        self.api_timeout().max(self.frontend_timeout()) + self.jobs_timeout() + self.database_timeout() + Duration::from_secs(1)
+    }
+}
+
+/// Expands `${VAR}` references in every value.
+fn interpolate(vars: HashMap<String, String>, lookup: &impl Fn(&str) -> Option<String>, issues: &mut Vec<String>) -> HashMap<String, String> {
+    vars.into_iter()
+        .map(|(name, value)| {
+            let mut expanded = String::with_capacity(value.len());
+            let mut rest = value.as_str();
+            while let Some(start) = rest.find("${") {
+                expanded.push_str(&rest[..start]);
+                let Some(end) = rest[start..].find('}') else {
+                    issues.push(format!("{name}: unterminated `${{` reference"));
+                    rest = "";
+                    break;
+                };
+                let reference = &rest[start + 2..start + end];
+                match lookup(reference) {
+                    Some(resolved) => expanded.push_str(&resolved),
+                    None => issues.push(format!("{name}: references unset variable `{reference}`")),
+                }
+                rest = &rest[start + end + 1..];
+            }
+            expanded.push_str(rest);
+            (name, expanded)
+        })
+        .collect()
+}
+
+/// Builds the providers asked for secrets not set directly: `<VAR>_FILE`
+/// files first, then Vault if `HPLAY__SECRETS__VAULT_PATH` is set.
+fn secrets_providers(vars: &mut HashMap<String, String>, lookup: &impl Fn(&str) -> Option<String>, issues: &mut Vec<String>) -> Vec<Box<dyn SecretsProvider>> {
+    let mut paths = HashMap::new();
+    for setting in SECRETS {
+        let file_var = format!("{}_FILE", setting.var);
+        let Some(path) = vars.remove(&file_var) else {
+            continue;
+        };
+        if vars.contains_key(setting.var) {
+            issues.push(format!("{file_var}: conflicts with {}; set only one", setting.var));
+            continue;
+        }
+        paths.insert(setting.name.to_string(), path);
+    }
+
+    let mut providers: Vec<Box<dyn SecretsProvider>> = vec![Box::new(FileSecretsProvider::new(paths))];
+    if let Some(path) = vars.remove(VAULT_PATH_VAR) {
+        match vault_provider(&path, lookup) {
+            Ok(provider) => providers.push(provider),
+            Err(problem) => issues.push(format!("{VAULT_PATH_VAR}: {problem}")),
+        }
+    }
+
+    providers
+}
+
+#[cfg(feature = "vault")]
+fn vault_provider(path: &str, lookup: &impl Fn(&str) -> Option<String>) -> Result<Box<dyn SecretsProvider>, String> {
+    let (Some(address), Some(token)) = (lookup("VAULT_ADDR"), lookup("VAULT_TOKEN")) else {
+        return Err("requires VAULT_ADDR and VAULT_TOKEN".to_string());
+    };
+    let provider = crate::secrets::VaultSecretsProvider::new(&address, Secret::from(token), path).map_err(|error| error.to_string())?;
+
+    Ok(Box::new(provider))
+}
+
+#[cfg(not(feature = "vault"))]
+fn vault_provider(_path: &str, _lookup: &impl Fn(&str) -> Option<String>) -> Result<Box<dyn SecretsProvider>, String> {
+    Err("this build does not include Vault support".to_string())
+}
+
+/// Asks each provider in turn for every secret not set directly.
+async fn resolve_secrets(vars: &HashMap<String, String>, providers: &[Box<dyn SecretsProvider>], issues: &mut Vec<String>) -> HashMap<&'static str, Secret> {
+    let mut secrets = HashMap::new();
+    for setting in SECRETS.iter().filter(|setting| !vars.contains_key(setting.var)) {
+        for provider in providers {
+            match provider.get(setting.name).await {
+                Ok(Some(secret)) => {
+                    secrets.insert(setting.var, secret);
+                    break;
+                }
+                Ok(None) => {}
+                Err(error) => {
+                    issues.push(format!("{}: {} secrets provider: {error}", setting.var, provider.name()));
+                    break;
+                }
+            }
+        }
+    }
+
+    secrets
+}
+
+fn validate(vars: &HashMap<String, String>, secrets: &HashMap<&'static str, Secret>, issues: &mut Vec<String>) {
+    let database_url = vars
+        .get("HPLAY__DATABASE__DATABASE_URL")
+        .map(String::as_str)
+        .or_else(|| secrets.get("HPLAY__DATABASE__DATABASE_URL").map(Secret::expose));
+    match database_url {
+        // Already reported by the provider that failed to supply it.
+        None if issues.iter().any(|issue| issue.starts_with("HPLAY__DATABASE__DATABASE_URL:")) => {}
+        None => issues.push("HPLAY__DATABASE__DATABASE_URL: missing (or set HPLAY__DATABASE__DATABASE_URL_FILE)".to_string()),
+        Some(url) if !DATABASE_URL_SCHEMES.iter().any(|scheme| url.starts_with(scheme)) => issues.push(format!(
+            "HPLAY__DATABASE__DATABASE_URL: unsupported scheme, expected one of {}",
+            DATABASE_URL_SCHEMES.join(", ")
+        )),
+        Some(_) => {}
+    }
+
+    if vars.keys().any(|name| name.starts_with("HPLAY__AUTH__OIDC__")) {
+        for name in OIDC_REQUIRED.iter().filter(|name| !vars.contains_key(**name)) {
+            issues.push(format!("{name}: missing, required to sign in through OIDC"));
+        }
+    }
+
+    if !cfg!(feature = "sentry") && (vars.contains_key("HPLAY__SENTRY__DSN") || secrets.contains_key("HPLAY__SENTRY__DSN")) {
+        issues.push("HPLAY__SENTRY__DSN: this build does not include Sentry support".to_string());
+    }
+
+    for (name, value) in vars {
+        let problem = match name.as_str() {
+            "HPLAY__DATABASE__STATEMENT_TIMEOUT_MS"
+            | "HPLAY__DATABASE__SLOW_QUERY_THRESHOLD_MS"
+            | "HPLAY__DATABASE__CONNECT_MAX_WAIT_SECS"
+            | "HPLAY__DATABASE__READ_YOUR_WRITES_WINDOW_MS" => value.parse::<u64>().is_err().then_some("expected a non-negative integer"),
+            name if name.starts_with("HPLAY__DATABASE__LATENCY_BUDGETS_MS__") => value.parse::<u64>().is_err().then_some("expected a non-negative integer"),
+            name if name.starts_with("HPLAY__HTTP__SLOS__") && (name.ends_with("__AVAILABILITY") || name.ends_with("__LATENCY")) => value
+                .parse::<f64>()
+                .ok()
+                .filter(|target| *target > 0.0 && *target < 1.0)
+                .is_none()
+                .then_some("expected a share between 0 and 1 exclusive, such as 0.999"),
+            name if name.starts_with("HPLAY__HTTP__SLOS__") && name.ends_with("__LATENCY_THRESHOLD_MS") => {
+                value.parse::<u64>().is_err().then_some("expected a non-negative integer")
+            }
+            name if name.starts_with("HPLAY__HTTP__SLOS__") && name.ends_with("__ALERT_BURN_RATE") => value
+                .parse::<f64>()
+                .ok()
+                .filter(|rate| *rate > 0.0)
+                .is_none()
+                .then_some("expected a positive number"),
+            name if name.starts_with("HPLAY__HTTP__SLOS__") && name.ends_with("__ROUTE") => {
+                (!value.starts_with('/')).then_some("expected a route such as `/api/v1/user/{id}`")
+            }
+            name if name.starts_with("HPLAY__HTTP__CONCURRENCY_LIMITS__") => {
+                value.parse::<usize>().map_or(true, |limit| limit == 0).then_some("expected a positive integer")
+            }
+            "HPLAY__ACTIVITY_FLUSH_INTERVAL_SECS" | "HPLAY__SIGNUP_ROLLUP_INTERVAL_SECS" => {
+                value.parse::<u64>().map_or(true, |secs| secs == 0).then_some("expected a positive integer")
+            }
+            "HPLAY__HTTP__HEADER_READ_TIMEOUT_SECS" | "HPLAY__HTTP__HTTP2_KEEP_ALIVE_INTERVAL_SECS" | "HPLAY__HTTP__HTTP2_KEEP_ALIVE_TIMEOUT_SECS" => {
+                value.parse::<u64>().is_err().then_some("expected a non-negative integer")
+            }
+            "HPLAY__SHUTDOWN__API_TIMEOUT_SECS" | "HPLAY__SHUTDOWN__FRONTEND_TIMEOUT_SECS" | "HPLAY__SHUTDOWN__DATABASE_TIMEOUT_SECS" => {
+                value.parse::<u64>().is_err().then_some("expected a non-negative integer")
+            }
+            "HPLAY__HTTP__MAX_CONCURRENT_STREAMS" => value
+                .parse::<u32>()
+                .map_or(true, |streams| streams == 0)
+                .then_some("expected a positive integer"),
+            "HPLAY__HTTP__KEEP_ALIVE" => value.to_ascii_lowercase().parse::<bool>().is_err().then_some("expected `true` or `false`"),
+            "HPLAY__HTTP__LISTEN_ADDRS" | "HPLAY__HTTP__ADMIN_LISTEN_ADDRS" | "HPLAY__GRPC__LISTEN_ADDRS" => value
+                .split(LIST_SEPARATOR)
+                .any(|addr| addr.trim().parse::<SocketAddr>().is_err())
+                .then_some("expected comma separated addresses such as `0.0.0.0:3000,[::1]:3000`"),
+            "HPLAY__HTTP__UNIX_SOCKET__MODE" | "HPLAY__GRPC__UNIX_SOCKET__MODE" if !vars.contains_key(&name.replace("__MODE", "__PATH")) => {
+                Some("requires the matching UNIX_SOCKET__PATH")
+            }
+            "HPLAY__HTTP__UNIX_SOCKET__MODE" | "HPLAY__GRPC__UNIX_SOCKET__MODE" => {
+                parse_socket_mode(value).is_none().then_some("expected octal permissions such as 660")
+            }
+            "HPLAY__STORAGE__BACKEND" if value == "local" && !vars.contains_key("HPLAY__STORAGE__LOCAL_DIR") => {
+                Some("`local` requires HPLAY__STORAGE__LOCAL_DIR")
+            }
+            "HPLAY__STORAGE__BACKEND" if value == "s3" && !vars.contains_key("HPLAY__STORAGE__BUCKET") => Some("`s3` requires HPLAY__STORAGE__BUCKET"),
+            "HPLAY__STORAGE__BACKEND" => (value != "local" && value != "s3").then_some("expected `local` or `s3`"),
+            "HPLAY__FRONTEND__LISTEN_PORT" => value.parse::<u16>().is_err().then_some("expected a port number"),
+            "HPLAY__FRONTEND__LISTEN_IP" => value.parse::<IpAddr>().is_err().then_some("expected an IP address"),
+            "HPLAY__FRONTEND__BASE_PATH" => (!value.starts_with('/')).then_some("expected a path such as `/app`"),
+            "HPLAY__AUTH__OIDC__ISSUER_URL" | "HPLAY__AUTH__OIDC__REDIRECT_URL" => {
+                (!value.starts_with("https://") && !value.starts_with("http://")).then_some("expected a URL such as `https://accounts.example.com`")
+            }
+            "HPLAY__GRPC__CORS_ALLOWED_ORIGINS" => value
+                .split(LIST_SEPARATOR)
+                .map(str::trim)
+                .any(|origin| origin != "*" && !origin.starts_with("http://") && !origin.starts_with("https://"))
+                .then_some("expected comma separated origins such as `https://app.example.com`, or `*`"),
+            "HPLAY__MAINTENANCE_MODE" | "HPLAY__GRPC__SINGLE_PORT" | "HPLAY__GRPC__GRPC_WEB" => {
+                value.to_ascii_lowercase().parse::<bool>().is_err().then_some("expected `true` or `false`")
+            }
+            name if name.starts_with("HPLAY__FEATURE_FLAGS__") => value.to_ascii_lowercase().parse::<bool>().is_err().then_some("expected `true` or `false`"),
+            _ => None,
+        };
+        if let Some(problem) = problem {
+            issues.push(format!("{name}: {problem}, got `{value}`"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use hex_play_storage::StorageBackend;
+
+    use super::Config;
+    use crate::error::Error;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    fn issues(result: Result<Config, Error>) -> Vec<String> {
+        match result {
+            Err(Error::Invalid(issues)) => issues,
+            other => panic!("expected validation issues, got {other:?}"),
+        }
+    }
+
+    // ===================
+    // Tests: from_vars
+    // ===================
+
+    #[tokio::test]
+    async fn test_minimal_config() {
+        let config = Config::from_vars(vars(&[("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:")]), |_| None)
+            .await
+            .unwrap();
+
+        assert_eq!(config.database.database_url.expose(), "sqlite::memory:");
+        assert_eq!(config.frontend.listen_port, 8080);
+    }
+
+    #[tokio::test]
+    async fn test_reports_every_issue_by_variable() {
+        let result = Config::from_vars(
+            vars(&[
+                ("HPLAY__DATABASE__STATEMENT_TIMEOUT_MS", "soon"),
+                ("HPLAY__FRONTEND__LISTEN_PORT", "70000"),
+                ("HPLAY__FEATURE_FLAGS__SOFT_DELETE", "yes"),
+            ]),
+            |_| None,
+        )
+        .await;
+
+        let issues = issues(result);
+        assert_eq!(issues.len(), 4);
+        assert!(issues[0].starts_with("HPLAY__DATABASE__DATABASE_URL: missing"));
+        assert!(issues[1].starts_with("HPLAY__DATABASE__STATEMENT_TIMEOUT_MS:"));
+        assert!(issues[2].starts_with("HPLAY__FEATURE_FLAGS__SOFT_DELETE:"));
+        assert!(issues[3].starts_with("HPLAY__FRONTEND__LISTEN_PORT:"));
+    }
+
+    #[tokio::test]
+    async fn test_http_concurrency_limits() {
+        let config = Config::from_vars(
+            vars(&[
+                ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                ("HPLAY__HTTP__CONCURRENCY_LIMITS__WRITES", "8"),
+            ]),
+            |_| None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(config.http.concurrency_limits.writes, 8);
+        assert_eq!(config.http.concurrency_limits.reads, 64);
+
+        let issues = issues(
+            Config::from_vars(
+                vars(&[
+                    ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                    ("HPLAY__HTTP__CONCURRENCY_LIMITS__READS", "0"),
+                ]),
+                |_| None,
+            )
+            .await,
+        );
+        assert!(issues[0].starts_with("HPLAY__HTTP__CONCURRENCY_LIMITS__READS: expected a positive integer"));
+    }
+
+    #[tokio::test]
+    async fn test_http_slos() {
+        let config = Config::from_vars(
+            vars(&[
+                ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                ("HPLAY__HTTP__SLOS__GET_USER__ROUTE", "/api/v1/user/{id}"),
+                ("HPLAY__HTTP__SLOS__GET_USER__AVAILABILITY", "0.999"),
+                ("HPLAY__HTTP__SLOS__GET_USER__LATENCY_THRESHOLD_MS", "250"),
+            ]),
+            |_| None,
+        )
+        .await
+        .unwrap();
+
+        let slo = &config.http.slos["get_user"];
+        assert_eq!(slo.route, "/api/v1/user/{id}");
+        assert_eq!(slo.availability, Some(0.999));
+        assert_eq!(slo.latency, None);
+        assert_eq!(slo.latency_threshold_ms, Some(250));
+
+        let issues = issues(
+            Config::from_vars(
+                vars(&[
+                    ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                    ("HPLAY__HTTP__SLOS__GET_USER__ROUTE", "/api/v1/user/{id}"),
+                    ("HPLAY__HTTP__SLOS__GET_USER__AVAILABILITY", "99.9"),
+                ]),
+                |_| None,
+            )
+            .await,
+        );
+        assert!(issues[0].starts_with("HPLAY__HTTP__SLOS__GET_USER__AVAILABILITY: expected a share between 0 and 1"));
+    }
+
+    #[tokio::test]
+    async fn test_api_keys() {
+        let config = Config::from_vars(
+            vars(&[
+                ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                ("HPLAY__API_KEYS__OPS__KEY", "ops-key"),
+                ("HPLAY__API_KEYS__OPS__USER_ID", "1"),
+                ("HPLAY__API_KEYS__OPS__TENANT", "acme"),
+                ("HPLAY__API_KEYS__OPS__ADMIN", "true"),
+            ]),
+            |_| None,
+        )
+        .await
+        .unwrap();
+
+        let api_key = &config.api_keys["ops"];
+        assert_eq!(api_key.key.expose(), "ops-key");
+        assert_eq!(api_key.user_id, 1);
+        assert_eq!(api_key.tenant.as_deref(), Some("acme"));
+        assert!(api_key.admin);
+        assert!(!format!("{config:?}").contains("ops-key"));
+    }
+
+    #[tokio::test]
+    async fn test_http_connection_tuning() {
+        let config = Config::from_vars(
+            vars(&[
+                ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                ("HPLAY__HTTP__KEEP_ALIVE", "false"),
+                ("HPLAY__HTTP__MAX_CONCURRENT_STREAMS", "50"),
+                ("HPLAY__HTTP__HTTP2_KEEP_ALIVE_INTERVAL_SECS", "15"),
+            ]),
+            |_| None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(config.http.keep_alive, Some(false));
+        assert_eq!(config.http.max_concurrent_streams, Some(50));
+        assert_eq!(config.http.http2_keep_alive_interval_secs, Some(15));
+        assert_eq!(config.http.header_read_timeout_secs, None);
+
+        let issues = issues(
+            Config::from_vars(
+                vars(&[
+                    ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                    ("HPLAY__HTTP__HEADER_READ_TIMEOUT_SECS", "-1"),
+                ]),
+                |_| None,
+            )
+            .await,
+        );
+        assert!(issues[0].starts_with("HPLAY__HTTP__HEADER_READ_TIMEOUT_SECS: expected a non-negative integer"));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_timeouts() {
+        let config = Config::from_vars(
+            vars(&[
+                ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                ("HPLAY__SHUTDOWN__API_TIMEOUT_SECS", "30"),
+            ]),
+            |_| None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(config.shutdown.api_timeout(), Duration::from_secs(30));
+        assert_eq!(config.shutdown.frontend_timeout(), Duration::from_secs(5));
+        assert_eq!(config.shutdown.jobs_timeout(), Duration::from_secs(10));
+        assert_eq!(config.shutdown.total_timeout(), Duration::from_secs(46));
+
+        let issues = issues(
+            Config::from_vars(
+                vars(&[
+                    ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                    ("HPLAY__SHUTDOWN__DATABASE_TIMEOUT_SECS", "soon"),
+                ]),
+                |_| None,
+            )
+            .await,
+        );
+        assert!(issues[0].starts_with("HPLAY__SHUTDOWN__DATABASE_TIMEOUT_SECS: expected a non-negative integer"));
+    }
+
+    #[tokio::test]
+    async fn test_background_intervals() {
+        let config = Config::from_vars(vars(&[("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:")]), |_| None)
+            .await
+            .unwrap();
+        assert_eq!(config.activity_flush_interval(), Duration::from_secs(30));
+        assert_eq!(config.signup_rollup_interval(), Duration::from_secs(300));
+
+        let config = Config::from_vars(
+            vars(&[
+                ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                ("HPLAY__ACTIVITY_FLUSH_INTERVAL_SECS", "60"),
+            ]),
+            |_| None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(config.activity_flush_interval(), Duration::from_secs(60));
+
+        let issues = issues(
+            Config::from_vars(
+                vars(&[
+                    ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                    ("HPLAY__ACTIVITY_FLUSH_INTERVAL_SECS", "0"),
+                ]),
+                |_| None,
+            )
+            .await,
+        );
+        assert!(issues[0].starts_with("HPLAY__ACTIVITY_FLUSH_INTERVAL_SECS: expected a positive integer"));
+    }
+
+    #[tokio::test]
+    async fn test_frontend_base_path() {
+        let config = Config::from_vars(
+            vars(&[("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"), ("HPLAY__FRONTEND__BASE_PATH", "/app")]),
+            |_| None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(config.frontend.base_path.as_deref(), Some("/app"));
+        assert_eq!(config.frontend.assets_dir, None);
+
+        let issues = issues(
+            Config::from_vars(
+                vars(&[("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"), ("HPLAY__FRONTEND__BASE_PATH", "app")]),
+                |_| None,
+            )
+            .await,
+        );
+        assert!(issues[0].starts_with("HPLAY__FRONTEND__BASE_PATH: expected a path such as `/app`"));
+    }
+
+    #[tokio::test]
+    async fn test_storage_backend() {
+        let config = Config::from_vars(
+            vars(&[
+                ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                ("HPLAY__STORAGE__BACKEND", "local"),
+                ("HPLAY__STORAGE__LOCAL_DIR", "/tmp/objects"),
+            ]),
+            |_| None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(config.storage.backend, Some(StorageBackend::Local));
+        assert_eq!(config.storage.local_dir.as_deref(), Some("/tmp/objects"));
+
+        let issues = issues(
+            Config::from_vars(
+                vars(&[("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"), ("HPLAY__STORAGE__BACKEND", "s3")]),
+                |_| None,
+            )
+            .await,
+        );
+        assert!(issues[0].starts_with("HPLAY__STORAGE__BACKEND: `s3` requires HPLAY__STORAGE__BUCKET"));
+    }
+
+    #[tokio::test]
+    async fn test_listen_addrs() {
+        let config = Config::from_vars(
+            vars(&[
+                ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                ("HPLAY__HTTP__LISTEN_ADDRS", "[::1]:3000,0.0.0.0:3000"),
+                ("HPLAY__HTTP__ADMIN_LISTEN_ADDRS", "127.0.0.1:3002"),
+            ]),
+            |_| None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(config.http.listen_addrs(), ["[::1]:3000", "0.0.0.0:3000"]);
+        assert_eq!(config.http.admin_listen_addrs, ["127.0.0.1:3002"]);
+        assert_eq!(config.grpc.listen_addrs(), [hex_play_api::grpc::LISTEN_ADDR]);
+
+        let issues = issues(
+            Config::from_vars(
+                vars(&[
+                    ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                    ("HPLAY__GRPC__LISTEN_ADDRS", "0.0.0.0:3001,localhost"),
+                ]),
+                |_| None,
+            )
+            .await,
+        );
+        assert!(issues[0].starts_with("HPLAY__GRPC__LISTEN_ADDRS: expected comma separated addresses"));
+    }
+
+    #[tokio::test]
+    async fn test_grpc_web() {
+        let config = Config::from_vars(
+            vars(&[
+                ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                ("HPLAY__GRPC__GRPC_WEB", "true"),
+                ("HPLAY__GRPC__CORS_ALLOWED_ORIGINS", "https://app.example.com,http://localhost:8080"),
+            ]),
+            |_| None,
+        )
+        .await
+        .unwrap();
+
+        assert!(config.grpc.grpc_web);
+        assert_eq!(config.grpc.cors_allowed_origins, ["https://app.example.com", "http://localhost:8080"]);
+
+        let issues = issues(
+            Config::from_vars(
+                vars(&[
+                    ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                    ("HPLAY__GRPC__CORS_ALLOWED_ORIGINS", "app.example.com"),
+                ]),
+                |_| None,
+            )
+            .await,
+        );
+        assert!(issues[0].starts_with("HPLAY__GRPC__CORS_ALLOWED_ORIGINS: expected comma separated origins"));
+    }
+
+    #[tokio::test]
+    async fn test_unix_sockets() {
+        let config = Config::from_vars(
+            vars(&[
+                ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                ("HPLAY__GRPC__UNIX_SOCKET__PATH", "/run/hex-play/grpc.sock"),
+                ("HPLAY__GRPC__UNIX_SOCKET__MODE", "0660"),
+            ]),
+            |_| None,
+        )
+        .await
+        .unwrap();
+
+        let unix_socket = config.grpc.unix_socket.unwrap();
+        assert_eq!(unix_socket.path.to_str(), Some("/run/hex-play/grpc.sock"));
+        assert_eq!(unix_socket.mode.as_deref().and_then(hex_play_api::parse_socket_mode), Some(0o660));
+        assert!(config.http.unix_socket.is_none());
+
+        let issues = issues(
+            Config::from_vars(
+                vars(&[
+                    ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                    ("HPLAY__GRPC__UNIX_SOCKET__PATH", "/run/hex-play/grpc.sock"),
+                    ("HPLAY__GRPC__UNIX_SOCKET__MODE", "rw-rw----"),
+                    ("HPLAY__HTTP__UNIX_SOCKET__MODE", "660"),
+                ]),
+                |_| None,
+            )
+            .await,
+        );
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].starts_with("HPLAY__GRPC__UNIX_SOCKET__MODE: expected octal permissions"));
+        assert!(issues[1].starts_with("HPLAY__HTTP__UNIX_SOCKET__MODE: requires the matching UNIX_SOCKET__PATH"));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unknown_database_scheme() {
+        let issues = issues(Config::from_vars(vars(&[("HPLAY__DATABASE__DATABASE_URL", "postgress://db")]), |_| None).await);
+
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].contains("unsupported scheme"));
+    }
+
+    #[tokio::test]
+    async fn test_interpolates_variables() {
+        let lookup = |name: &str| (name == "DB_PASSWORD").then(|| "s3cret".to_string());
+
+        let config = Config::from_vars(vars(&[("HPLAY__DATABASE__DATABASE_URL", "postgres://app:${DB_PASSWORD}@db/app")]), lookup)
+            .await
+            .unwrap();
+
+        assert_eq!(config.database.database_url.expose(), "postgres://app:s3cret@db/app");
+    }
+
+    #[tokio::test]
+    async fn test_reports_unset_interpolated_variable() {
+        let issues = issues(Config::from_vars(vars(&[("HPLAY__DATABASE__DATABASE_URL", "postgres://${DB_HOST}/app")]), |_| None).await);
+
+        assert_eq!(issues, vec!["HPLAY__DATABASE__DATABASE_URL: references unset variable `DB_HOST`".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_reads_database_url_from_file() {
+        let path = std::env::temp_dir().join(format!("hex-play-config-test-{}", std::process::id()));
+        std::fs::write(&path, "sqlite::memory:\n").unwrap();
+
+        let result = Config::from_vars(vars(&[("HPLAY__DATABASE__DATABASE_URL_FILE", path.to_str().unwrap())]), |_| None).await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.unwrap().database.database_url.expose(), "sqlite::memory:");
+    }
+
+    #[tokio::test]
+    async fn test_reads_webhook_secret_from_file() {
+        let path = std::env::temp_dir().join(format!("hex-play-webhook-secret-test-{}", std::process::id()));
+        std::fs::write(&path, "s3cret\n").unwrap();
+
+        let result = Config::from_vars(
+            vars(&[
+                ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                ("HPLAY__HTTP__WEBHOOK_SECRET_FILE", path.to_str().unwrap()),
+            ]),
+            |_| None,
+        )
+        .await;
+        std::fs::remove_file(&path).unwrap();
+
+        let config = result.unwrap();
+        assert_eq!(config.http.webhook_secret.as_ref().map(|secret| secret.expose()), Some("s3cret"));
+        assert!(!format!("{config:?}").contains("s3cret"));
+    }
+
+    #[tokio::test]
+    async fn test_oidc_config() {
+        let config = Config::from_vars(
+            vars(&[
+                ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                ("HPLAY__AUTH__OIDC__ISSUER_URL", "https://accounts.example.com"),
+                ("HPLAY__AUTH__OIDC__CLIENT_ID", "hex-play"),
+                ("HPLAY__AUTH__OIDC__CLIENT_SECRET", "s3cret"),
+                ("HPLAY__AUTH__OIDC__REDIRECT_URL", "https://hex-play.example.com/auth/oidc/callback"),
+                ("HPLAY__AUTH__OIDC__SCOPES", "email,groups"),
+            ]),
+            |_| None,
+        )
+        .await
+        .unwrap();
+
+        let oidc = config.auth.oidc.as_ref().unwrap();
+        assert_eq!(oidc.issuer_url, "https://accounts.example.com");
+        assert_eq!(oidc.client_secret.as_ref().map(|secret| secret.expose()), Some("s3cret"));
+        assert_eq!(oidc.scopes, vec!["email", "groups"]);
+        assert!(!format!("{config:?}").contains("s3cret"));
+    }
+
+    #[tokio::test]
+    async fn test_oidc_config_is_optional() {
+        let config = Config::from_vars(vars(&[("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:")]), |_| None)
+            .await
+            .unwrap();
+
+        assert!(config.auth.oidc.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_incomplete_oidc_config() {
+        let issues = issues(
+            Config::from_vars(
+                vars(&[
+                    ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                    ("HPLAY__AUTH__OIDC__ISSUER_URL", "accounts.example.com"),
+                ]),
+                |_| None,
+            )
+            .await,
+        );
+
+        assert_eq!(
+            issues,
+            vec![
+                "HPLAY__AUTH__OIDC__CLIENT_ID: missing, required to sign in through OIDC".to_string(),
+                "HPLAY__AUTH__OIDC__ISSUER_URL: expected a URL such as `https://accounts.example.com`, got `accounts.example.com`".to_string(),
+                "HPLAY__AUTH__OIDC__REDIRECT_URL: missing, required to sign in through OIDC".to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_database_url_file_conflicts_with_database_url() {
+        let issues = issues(
+            Config::from_vars(
+                vars(&[
+                    ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                    ("HPLAY__DATABASE__DATABASE_URL_FILE", "/run/secrets/database_url"),
+                ]),
+                |_| None,
+            )
+            .await,
+        );
+
+        assert!(issues[0].contains("conflicts with HPLAY__DATABASE__DATABASE_URL"));
+    }
+
+    #[tokio::test]
+    async fn test_reports_unreadable_database_url_file() {
+        let issues = issues(Config::from_vars(vars(&[("HPLAY__DATABASE__DATABASE_URL_FILE", "/nonexistent/database_url")]), |_| None).await);
+
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].starts_with("HPLAY__DATABASE__DATABASE_URL: file secrets provider: cannot read"));
+    }
+
+    #[tokio::test]
+    async fn test_reports_unusable_vault_path() {
+        let issues = issues(
+            Config::from_vars(
+                vars(&[
+                    ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                    ("HPLAY__SECRETS__VAULT_PATH", "secret/hex-play"),
+                ]),
+                |_| None,
+            )
+            .await,
+        );
+
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].starts_with("HPLAY__SECRETS__VAULT_PATH:"));
+    }
+
+    #[cfg(not(feature = "sentry"))]
+    #[tokio::test]
+    async fn test_reports_sentry_dsn_without_sentry_support() {
+        let issues = issues(
+            Config::from_vars(
+                vars(&[
+                    ("HPLAY__DATABASE__DATABASE_URL", "sqlite::memory:"),
+                    ("HPLAY__SENTRY__DSN", "https://key@o0.ingest.sentry.io/0"),
+                ]),
+                |_| None,
+            )
+            .await,
+        );
+
+        assert_eq!(issues, ["HPLAY__SENTRY__DSN: this build does not include Sentry support"]);
+    }
+
+    #[tokio::test]
+    async fn test_debug_redacts_database_url() {
+        let config = Config::from_vars(vars(&[("HPLAY__DATABASE__DATABASE_URL", "postgres://app:s3cret@db/app")]), |_| None)
+            .await
+            .unwrap();
+
+        assert!(!format!("{config:?}").contains("s3cret"));
+    }
+
+    // ===================
+    // Tests: schema
+    // ===================
+    #[test]
+    fn test_schema_names_env_vars() {
+        let schema = Config::schema();
+        let properties = &schema["properties"];
+
+        assert_eq!(properties["maintenance_mode"]["x-env-var"], "HPLAY__MAINTENANCE_MODE");
+        assert_eq!(
+            properties["http"]["properties"]["unix_socket"]["properties"]["path"]["x-env-var"],
+            "HPLAY__HTTP__UNIX_SOCKET__PATH"
+        );
+        assert_eq!(properties["feature_flags"]["x-env-var"], "HPLAY__FEATURE_FLAGS__<KEY>");
+    }
+
+    #[test]
+    fn test_schema_names_secret_files() {
+        let schema = Config::schema();
+        let database_url = &schema["properties"]["database"]["properties"]["database_url"];
+
+        assert_eq!(database_url["x-env-var-file"], "HPLAY__DATABASE__DATABASE_URL_FILE");
+        assert_eq!(database_url["writeOnly"], true);
+        assert!(schema["properties"]["maintenance_mode"].get("x-env-var-file").is_none());
+    }
+
+    #[test]
+    fn test_schema_includes_defaults() {
+        let schema = Config::schema();
+        let properties = &schema["properties"];
+
+        assert_eq!(properties["frontend"]["properties"]["listen_port"]["default"], 8080);
+        assert_eq!(properties["http"]["properties"]["header_read_timeout_secs"]["default"], 30);
+        assert_eq!(properties["http"]["properties"]["concurrency_limits"]["properties"]["writes"]["default"], 16);
+        assert_eq!(properties["shutdown"]["properties"]["api_timeout_secs"]["default"], 10);
+        assert_eq!(properties["activity_flush_interval_secs"]["default"], 30);
+    }
 }
@@ -1,4 +1,7 @@
+use std::process::ExitCode;
+
 use config::ConfigError;
+use hex_play_core::{Error as CoreError, ErrorKind};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -7,4 +10,101 @@ pub enum Error {
 
     #[error(transparent)]
     ConfigError(#[from] ConfigError),
+
+    #[error("Invalid configuration:\n  {}", .0.join("\n  "))]
+    Invalid(Vec<String>),
+
+    #[error("{0}")]
+    Secrets(String),
+}
+
+/// Exit status for any failure without a more specific code.
+pub const EXIT_FAILURE: u8 = 1;
+/// Exit status when the request was rejected as invalid. Clap uses the same
+/// code for command line usage errors.
+pub const EXIT_VALIDATION: u8 = 2;
+/// Exit status when the requested resource does not exist.
+pub const EXIT_NOT_FOUND: u8 = 3;
+/// Exit status when the request conflicted with the current state, e.g. a
+/// stale `--expected-version`.
+pub const EXIT_CONFLICT: u8 = 4;
+/// Exit status when the server could not be reached or is unavailable.
+pub const EXIT_TRANSPORT: u8 = 10;
+
+/// Failure of a CLI command, mapped to a distinct process exit status.
+#[derive(Debug, thiserror::Error)]
+pub enum CommandError {
+    #[error(transparent)]
+    Core(#[from] CoreError),
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl CommandError {
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(self.exit_status())
+    }
+
+    fn exit_status(&self) -> u8 {
+        let CommandError::Core(error) = self else {
+            return EXIT_FAILURE;
+        };
+
+        match error.kind() {
+            ErrorKind::InvalidInput | ErrorKind::BadRequest => EXIT_VALIDATION,
+            ErrorKind::NotFound => EXIT_NOT_FOUND,
+            ErrorKind::Conflict => EXIT_CONFLICT,
+            ErrorKind::Unavailable => EXIT_TRANSPORT,
+            // The gRPC client reports connection failures as infrastructure errors.
+            ErrorKind::Internal if matches!(error, CoreError::Infrastructure(_)) => EXIT_TRANSPORT,
+            ErrorKind::Internal => EXIT_FAILURE,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_play_core::{Error as CoreError, ErrorKind, RepositoryError};
+
+    use super::{CommandError, EXIT_CONFLICT, EXIT_FAILURE, EXIT_NOT_FOUND, EXIT_TRANSPORT, EXIT_VALIDATION};
+
+    fn remote(kind: ErrorKind) -> CommandError {
+        CommandError::Core(CoreError::Remote {
+            kind,
+            message: "remote".into(),
+        })
+    }
+
+    // ===================
+    // Tests: exit_status
+    // ===================
+
+    #[test]
+    fn test_exit_status_by_kind() {
+        assert_eq!(remote(ErrorKind::InvalidInput).exit_status(), EXIT_VALIDATION);
+        assert_eq!(CommandError::Core(CoreError::InvalidId(0)).exit_status(), EXIT_VALIDATION);
+        assert_eq!(
+            CommandError::Core(CoreError::RepositoryError(RepositoryError::NotFound)).exit_status(),
+            EXIT_NOT_FOUND
+        );
+        assert_eq!(
+            CommandError::Core(CoreError::RepositoryError(RepositoryError::Conflict)).exit_status(),
+            EXIT_CONFLICT
+        );
+        assert_eq!(remote(ErrorKind::Unavailable).exit_status(), EXIT_TRANSPORT);
+        assert_eq!(remote(ErrorKind::Internal).exit_status(), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn test_transport_failure_exit_status() {
+        let error = CommandError::Core(CoreError::Infrastructure("gRPC client error: transport error".into()));
+
+        assert_eq!(error.exit_status(), EXIT_TRANSPORT);
+    }
+
+    #[test]
+    fn test_other_errors_exit_with_failure() {
+        assert_eq!(CommandError::Other(anyhow::anyhow!("boom")).exit_status(), EXIT_FAILURE);
+    }
 }
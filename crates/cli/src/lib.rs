@@ -6,3 +6,7 @@ pub mod config;
 pub mod error;
 #[cfg(feature = "server")]
 pub mod logging;
+#[cfg(feature = "server")]
+pub mod reporting;
+#[cfg(feature = "server")]
+pub mod secrets;
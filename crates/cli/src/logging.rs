@@ -1,35 +1,170 @@
+use std::{
+    fs::OpenOptions,
+    sync::{Arc, Mutex},
+};
+
 use anyhow::{Context, Result};
+use hex_play_api::ACCESS_LOG_TARGET;
+use hex_play_core::{Error, log_filter::LogFilter};
+use opentelemetry::{global, trace::TracerProvider as _};
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    trace::{SdkTracer, SdkTracerProvider},
+};
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::{
+    EnvFilter, Layer as _, Registry,
+    filter::{ParseError, filter_fn},
+    registry::LookupSpan,
+    reload::{self, Handle},
+};
+
+/// Targets too chatty to log, whatever the filter in effect says.
+const SILENCED: &[&str] = &[
+    "sqlx::postgres::notice",
+    "sqlx::query",
+    "sea_orm",
+    "dioxus_core",
+    "dioxus_signals",
+    "hyper::proto",
+    "hyper::client",
+    "h2",
+    "rustls",
+    "tokio_util",
+    "tower_http",
+    "reqwest",
+    "simple_crypt",
+];
 
-pub fn init_logging() -> Result<()> {
+/// Sets up logging with the filter from `RUST_LOG`, or `info` if it is unset
+/// or invalid. The returned [`LogFilter`] changes the filter while the
+/// server runs.
+///
+/// With `access_log_file`, the access log is appended to it as JSON lines
+/// instead of being logged with everything else.
+///
+/// Spans also get OpenTelemetry trace ids, and gRPC calls carrying a W3C
+/// `traceparent` continue the caller's trace.
+pub fn init_logging(access_log_file: Option<&str>) -> Result<Arc<dyn LogFilter>> {
     use tracing::subscriber::set_global_default;
     use tracing_log::LogTracer;
-    use tracing_subscriber::{EnvFilter, Registry, fmt::format::FmtSpan, prelude::__tracing_subscriber_SubscriberExt};
+    use tracing_subscriber::{fmt::format::FmtSpan, prelude::__tracing_subscriber_SubscriberExt};
 
     LogTracer::init_with_filter(log::LevelFilter::Off).context("Unable to setup log tracer")?;
 
-    let env_filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new("info"))
-        .add_directive("sqlx::postgres::notice=off".parse()?)
-        .add_directive("sqlx::query=off".parse()?)
-        .add_directive("sea_orm=off".parse()?)
-        .add_directive("dioxus_core=off".parse()?)
-        .add_directive("dioxus_signals=off".parse()?)
-        .add_directive("hyper::proto=off".parse()?)
-        .add_directive("hyper::client=off".parse()?)
-        .add_directive("h2=off".parse()?)
-        .add_directive("rustls=off".parse()?)
-        .add_directive("tokio_util=off".parse()?)
-        .add_directive("tower_http=off".parse()?)
-        .add_directive("reqwest=off".parse()?)
-        .add_directive("simple_crypt=off".parse()?);
+    let (directives, filter) = match std::env::var(EnvFilter::DEFAULT_ENV).map(|directives| (env_filter(&directives), directives)) {
+        Ok((Ok(filter), directives)) => (directives, filter),
+        _ => ("info".to_string(), env_filter("info")?),
+    };
+    let (env_filter, handle) = reload::Layer::new(filter);
 
+    let separate_access_log = access_log_file.is_some();
     let formatting_layer = tracing_subscriber::fmt::layer()
         .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE)
-        .with_ansi(false);
+        .with_ansi(false)
+        .with_filter(filter_fn(move |metadata| !(separate_access_log && metadata.target() == ACCESS_LOG_TARGET)));
+    let access_log_layer = match access_log_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Unable to open access log {path}"))?;
+            let layer = tracing_subscriber::fmt::layer()
+                .json()
+                .with_current_span(false)
+                .with_span_list(false)
+                .with_writer(Mutex::new(file))
+                .with_filter(filter_fn(|metadata| metadata.target() == ACCESS_LOG_TARGET));
+            Some(layer)
+        }
+        None => None,
+    };
 
-    let subscriber = Registry::default().with(env_filter).with(formatting_layer);
+    let subscriber = Registry::default()
+        .with(env_filter)
+        .with(formatting_layer)
+        .with(access_log_layer)
+        .with(trace_layer());
 
     set_global_default(subscriber).context("Failed to set tracing subscriber xxx")?;
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    Ok(Arc::new(ReloadableLogFilter {
+        handle,
+        directives: Mutex::new(directives),
+    }))
+}
+
+/// Sets up tracing for commands calling the server. Nothing is logged, but
+/// spans get OpenTelemetry trace ids, which the calls send along as W3C
+/// `traceparent` so the server's spans join the command's trace.
+pub fn init_client_tracing() -> Result<()> {
+    use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+
+    tracing::subscriber::set_global_default(Registry::default().with(trace_layer())).context("Failed to set tracing subscriber")?;
+    global::set_text_map_propagator(TraceContextPropagator::new());
 
     Ok(())
 }
+
+/// Gives spans OpenTelemetry ids. No exporter is set up, so the spans are
+/// only seen through the trace ids recorded on them.
+fn trace_layer<S>() -> OpenTelemetryLayer<S, SdkTracer>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(SdkTracerProvider::builder().build().tracer("hex-play"))
+}
+
+/// `directives` with every [`SILENCED`] target turned off.
+fn env_filter(directives: &str) -> Result<EnvFilter, ParseError> {
+    SILENCED.iter().try_fold(EnvFilter::try_new(directives)?, |filter, target| {
+        Ok(filter.add_directive(format!("{target}=off").parse()?))
+    })
+}
+
+/// Swaps the filter of the global subscriber set up by [`init_logging`].
+struct ReloadableLogFilter {
+    handle: Handle<EnvFilter, Registry>,
+    directives: Mutex<String>,
+}
+
+impl LogFilter for ReloadableLogFilter {
+    fn current(&self) -> String {
+        self.directives.lock().unwrap().clone()
+    }
+
+    fn set(&self, directives: &str) -> Result<(), Error> {
+        let filter = env_filter(directives).map_err(|error| Error::Validation(format!("Invalid log filter: {error}").into()))?;
+        let mut current = self.directives.lock().unwrap();
+        self.handle.reload(filter).map_err(|error| Error::Infrastructure(error.to_string()))?;
+        *current = directives.to_string();
+        tracing::warn!(filter = directives, "Log filter changed");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::env_filter;
+
+    // ===================
+    // Tests: env_filter
+    // ===================
+    #[test]
+    fn test_env_filter_silences_chatty_targets() {
+        let filter = env_filter("debug").unwrap().to_string();
+
+        assert!(filter.contains("debug"));
+        assert!(filter.contains("sea_orm=off"));
+        assert!(filter.contains("h2=off"));
+    }
+
+    #[test]
+    fn test_env_filter_rejects_invalid_directives() {
+        assert!(env_filter("hex_play=loud").is_err());
+    }
+}
@@ -0,0 +1,95 @@
+//! Adapters for the [`ErrorReporter`] port: Sentry, with the `sentry`
+//! feature, or none.
+
+use std::sync::Arc;
+
+use hex_play_core::reporting::{ErrorReporter, NoErrorReporter};
+use hex_play_utils::secret::Secret;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::error::Error;
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct SentryConfig {
+    /// (optional) DSN of the Sentry project internal failures are reported
+    /// to, e.g. `HPLAY__SENTRY__DSN=https://key@o0.ingest.sentry.io/0`. Unset
+    /// leaves error reporting off. Needs a build with the `sentry` feature.
+    #[serde(default)]
+    pub dsn: Option<Secret>,
+
+    /// (optional) Environment reports are filed under, e.g.
+    /// `HPLAY__SENTRY__ENVIRONMENT=staging`.
+    #[serde(default)]
+    pub environment: Option<String>,
+}
+
+/// Creates the reporter `config` selects, or [`NoErrorReporter`] without a
+/// DSN.
+#[cfg(feature = "sentry")]
+pub fn create_error_reporter(config: &SentryConfig) -> Result<Arc<dyn ErrorReporter>, Error> {
+    match config.dsn.as_ref().filter(|dsn| !dsn.is_empty()) {
+        Some(dsn) => Ok(Arc::new(sentry::SentryErrorReporter::new(dsn, config.environment.clone())?)),
+        None => Ok(Arc::new(NoErrorReporter)),
+    }
+}
+
+/// Creates the reporter `config` selects, always [`NoErrorReporter`] in a
+/// build without Sentry; `Config::load` rejects a DSN there.
+#[cfg(not(feature = "sentry"))]
+pub fn create_error_reporter(_config: &SentryConfig) -> Result<Arc<dyn ErrorReporter>, Error> {
+    Ok(Arc::new(NoErrorReporter))
+}
+
+#[cfg(feature = "sentry")]
+mod sentry {
+    use hex_play_core::reporting::{ErrorReport, ErrorReporter};
+    use hex_play_utils::secret::Secret;
+
+    use crate::error::Error;
+
+    /// Files each report as a Sentry event tagged with its request and user.
+    /// Events are sent from a background thread, and those still queued are
+    /// flushed when the reporter is dropped.
+    pub struct SentryErrorReporter {
+        _guard: ::sentry::ClientInitGuard,
+    }
+
+    impl SentryErrorReporter {
+        pub fn new(dsn: &Secret, environment: Option<String>) -> Result<Self, Error> {
+            let dsn = dsn
+                .expose()
+                .parse()
+                .map_err(|error| Error::Invalid(vec![format!("HPLAY__SENTRY__DSN: {error}")]))?;
+            let guard = ::sentry::init(::sentry::ClientOptions {
+                dsn: Some(dsn),
+                environment: environment.map(Into::into),
+                release: ::sentry::release_name!(),
+                ..Default::default()
+            });
+
+            Ok(Self { _guard: guard })
+        }
+    }
+
+    impl ErrorReporter for SentryErrorReporter {
+        fn report(&self, report: ErrorReport) {
+            ::sentry::with_scope(
+                |scope| {
+                    scope.set_tag("panic", report.panic);
+                    if let Some(request_id) = &report.request_id {
+                        scope.set_tag("request_id", request_id);
+                    }
+                    if let Some(user_id) = &report.user_id {
+                        scope.set_tag("user_id", user_id);
+                        scope.set_user(Some(::sentry::User {
+                            id: Some(user_id.clone()),
+                            ..Default::default()
+                        }));
+                    }
+                },
+                || ::sentry::capture_message(&report.message, ::sentry::Level::Error),
+            );
+        }
+    }
+}
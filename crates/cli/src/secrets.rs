@@ -0,0 +1,191 @@
+//! Sources for secret settings such as database credentials.
+//!
+//! Secrets are looked up by name (e.g. `database_url`) in each provider in
+//! turn. A value set directly in the environment always takes precedence.
+
+use std::collections::HashMap;
+
+use hex_play_utils::secret::Secret;
+
+use crate::error::Error;
+
+#[async_trait::async_trait]
+pub trait SecretsProvider: Send + Sync {
+    /// Short name used in diagnostics.
+    fn name(&self) -> &'static str;
+
+    /// Returns the named secret, or `None` if this provider does not hold it.
+    async fn get(&self, name: &str) -> Result<Option<Secret>, Error>;
+}
+
+/// Reads secrets from files, e.g. mounted Docker or Kubernetes secrets.
+/// Trailing newlines are stripped.
+pub struct FileSecretsProvider {
+    paths: HashMap<String, String>,
+}
+
+impl FileSecretsProvider {
+    /// `paths` maps secret names to the files holding them.
+    pub fn new(paths: HashMap<String, String>) -> Self {
+        Self { paths }
+    }
+}
+
+#[async_trait::async_trait]
+impl SecretsProvider for FileSecretsProvider {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    async fn get(&self, name: &str) -> Result<Option<Secret>, Error> {
+        let Some(path) = self.paths.get(name) else {
+            return Ok(None);
+        };
+        let mut contents = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|error| Error::Secrets(format!("cannot read `{path}`: {error}")))?;
+        contents.truncate(contents.trim_end_matches(['\r', '\n']).len());
+
+        Ok(Some(Secret::from(contents)))
+    }
+}
+
+#[cfg(feature = "vault")]
+pub use vault::VaultSecretsProvider;
+
+#[cfg(feature = "vault")]
+mod vault {
+    use std::collections::HashMap;
+
+    use hex_play_utils::secret::Secret;
+    use serde::Deserialize;
+    use tokio::sync::OnceCell;
+
+    use super::SecretsProvider;
+    use crate::error::Error;
+
+    /// Reads secrets from one HashiCorp Vault KV version 2 entry, fetched on
+    /// first use. Each field of the entry is a secret.
+    pub struct VaultSecretsProvider {
+        url: String,
+        token: Secret,
+        client: reqwest::Client,
+        entry: OnceCell<HashMap<String, Secret>>,
+    }
+
+    #[derive(Deserialize)]
+    struct KvResponse {
+        data: KvData,
+    }
+
+    #[derive(Deserialize)]
+    struct KvData {
+        data: HashMap<String, Secret>,
+    }
+
+    impl VaultSecretsProvider {
+        /// `path` is `<mount>/<entry>`, e.g. `secret/hex-play`.
+        pub fn new(address: &str, token: Secret, path: &str) -> Result<Self, Error> {
+            let (mount, entry) = path
+                .split_once('/')
+                .ok_or_else(|| Error::Secrets(format!("Vault path `{path}` must be <mount>/<entry>")))?;
+
+            Ok(Self {
+                url: format!("{}/v1/{mount}/data/{entry}", address.trim_end_matches('/')),
+                token,
+                client: reqwest::Client::new(),
+                entry: OnceCell::new(),
+            })
+        }
+
+        async fn fetch(&self) -> Result<HashMap<String, Secret>, Error> {
+            let response = self
+                .client
+                .get(&self.url)
+                .header("X-Vault-Token", self.token.expose())
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status)
+                .map_err(|error| Error::Secrets(format!("Vault request failed: {error}")))?;
+            let body: KvResponse = response
+                .json()
+                .await
+                .map_err(|error| Error::Secrets(format!("unexpected Vault response: {error}")))?;
+
+            Ok(body.data.data)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl SecretsProvider for VaultSecretsProvider {
+        fn name(&self) -> &'static str {
+            "vault"
+        }
+
+        async fn get(&self, name: &str) -> Result<Option<Secret>, Error> {
+            let entry = self.entry.get_or_try_init(|| self.fetch()).await?;
+            Ok(entry.get(name).cloned())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use hex_play_utils::secret::Secret;
+
+        use super::{KvResponse, VaultSecretsProvider};
+
+        // ===================
+        // Tests: VaultSecretsProvider
+        // ===================
+
+        #[test]
+        fn test_kv_v2_url() {
+            let provider = VaultSecretsProvider::new("https://vault:8200/", Secret::new("token"), "secret/hex-play").unwrap();
+
+            assert_eq!(provider.url, "https://vault:8200/v1/secret/data/hex-play");
+        }
+
+        #[test]
+        fn test_rejects_path_without_mount() {
+            assert!(VaultSecretsProvider::new("https://vault:8200", Secret::new("token"), "hex-play").is_err());
+        }
+
+        #[test]
+        fn test_parses_kv_v2_response() {
+            let body: KvResponse = serde_json::from_str(r#"{"data":{"data":{"database_url":"postgres://db/app"},"metadata":{"version":3}}}"#).unwrap();
+
+            assert_eq!(body.data.data["database_url"].expose(), "postgres://db/app");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{FileSecretsProvider, SecretsProvider};
+
+    // ===================
+    // Tests: FileSecretsProvider
+    // ===================
+
+    #[tokio::test]
+    async fn test_reads_secret_file() {
+        let path = std::env::temp_dir().join(format!("hex-play-secret-test-{}", std::process::id()));
+        std::fs::write(&path, "s3cret\r\n").unwrap();
+        let provider = FileSecretsProvider::new(HashMap::from([("token".to_string(), path.to_str().unwrap().to_string())]));
+
+        let secret = provider.get("token").await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(secret.unwrap().unwrap().expose(), "s3cret");
+        assert!(provider.get("other").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_secret_file() {
+        let provider = FileSecretsProvider::new(HashMap::from([("token".to_string(), "/nonexistent/secret".to_string())]));
+
+        assert!(provider.get("token").await.is_err());
+    }
+}
@@ -0,0 +1,27 @@
+//! Benchmarks for parsing the shared domain newtypes.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use hex_play_core::types::{Age, Email};
+
+fn bench_email(c: &mut Criterion) {
+    let mut group = c.benchmark_group("email");
+    group.bench_function("new/valid", |b| b.iter(|| Email::new(black_box("john.doe+tag@example.com"))));
+    group.bench_function("new/invalid", |b| b.iter(|| Email::new(black_box("john.doe.example.com"))));
+    group.bench_function("deserialize", |b| {
+        b.iter(|| serde_json::from_str::<Email>(black_box(r#""john.doe+tag@example.com""#)))
+    });
+    group.finish();
+}
+
+fn bench_age(c: &mut Criterion) {
+    let mut group = c.benchmark_group("age");
+    group.bench_function("new/valid", |b| b.iter(|| Age::new(black_box(42))));
+    group.bench_function("new/invalid", |b| b.iter(|| Age::new(black_box(-1))));
+    group.bench_function("deserialize", |b| b.iter(|| serde_json::from_str::<Age>(black_box("42"))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_email, bench_age);
+criterion_main!(benches);
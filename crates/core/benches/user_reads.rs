@@ -0,0 +1,43 @@
+//! Benchmarks for reading a user through `CoreServices`, dispatching on
+//! trait objects, against `UserReader`, generic over the repositories. Both
+//! read from the same mocks, so the difference is the dispatch.
+
+use std::{hint::black_box, sync::Arc};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use hex_play_core::{
+    context::RequestContext,
+    create_services,
+    test_support::{MockRepository, MockSessionRepository, MockUserQueryRepository, MockUserRepository, MockWebhookRepository, mock_repository_service},
+    user::{User, UserId, UserReader},
+};
+
+fn user_repository() -> MockUserRepository {
+    let mut users = MockUserRepository::new();
+    users
+        .expect_find_by_id()
+        .returning(|_, id| Ok(Some(User::fake(id.value(), "John Doe", "john@example.com"))));
+    users
+}
+
+fn bench_find_by_id(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let context = RequestContext::internal();
+    let repository_service = mock_repository_service(user_repository(), MockSessionRepository::new(), MockWebhookRepository::new())
+        .build()
+        .unwrap();
+    let core_services = create_services(Arc::new(repository_service)).unwrap();
+    let reader = UserReader::new(Arc::new(MockRepository), Arc::new(user_repository()), Arc::new(MockUserQueryRepository::new()));
+
+    let mut group = c.benchmark_group("find_by_id");
+    group.bench_function("core_services", |b| {
+        b.iter(|| runtime.block_on(core_services.user_service.find_by_id(&context, black_box(UserId::new(42)))))
+    });
+    group.bench_function("user_reader", |b| {
+        b.iter(|| runtime.block_on(reader.find_by_id(&context, black_box(UserId::new(42)))))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_find_by_id);
+criterion_main!(benches);
@@ -0,0 +1,6 @@
+pub mod service;
+
+pub(crate) use service::ActivityServiceImpl;
+#[cfg(any(test, feature = "test-support"))]
+pub use service::MockActivityService;
+pub use service::{ActivityService, DEFAULT_ACTIVITY_FLUSH_INTERVAL};
@@ -0,0 +1,156 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{Error, cache::USERS, context::RequestContext, repository::RepositoryService, user::UserId};
+
+/// How often buffered activity is written when no interval is configured.
+pub const DEFAULT_ACTIVITY_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Tracks when users were last seen.
+///
+/// Sightings are buffered in memory and written in batches by a background
+/// writer calling [`flush`](ActivityService::flush), so a busy user costs
+/// one write per flush instead of one per request.
+#[cfg_attr(any(test, feature = "test-support"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait ActivityService: Send + Sync {
+    /// Notes that user `id` was seen just now. Never blocks on the database.
+    fn record_activity(&self, id: UserId);
+    /// Writes every buffered sighting in one transaction, returning how many
+    /// users it covered. On failure they stay buffered for the next flush.
+    async fn flush(&self) -> Result<usize, Error>;
+}
+
+pub(crate) struct ActivityServiceImpl {
+    repository_service: Arc<RepositoryService>,
+    /// Latest sighting per user since the last successful flush.
+    pending: Mutex<HashMap<UserId, DateTime<Utc>>>,
+}
+
+impl ActivityServiceImpl {
+    pub(crate) fn new(repository_service: Arc<RepositoryService>) -> Self {
+        Self {
+            repository_service,
+            pending: Mutex::default(),
+        }
+    }
+
+    /// Puts sightings from a failed flush back, keeping any newer ones
+    /// recorded meanwhile.
+    fn restore(&self, seen: Vec<(UserId, DateTime<Utc>)>) {
+        let mut pending = self.pending.lock().unwrap();
+        for (id, at) in seen {
+            pending.entry(id).and_modify(|latest| *latest = (*latest).max(at)).or_insert(at);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ActivityService for ActivityServiceImpl {
+    fn record_activity(&self, id: UserId) {
+        let now = self.repository_service.clock().now();
+        self.pending.lock().unwrap().insert(id, now);
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn flush(&self) -> Result<usize, Error> {
+        let mut seen: Vec<_> = std::mem::take(&mut *self.pending.lock().unwrap()).into_iter().collect();
+        if seen.is_empty() {
+            return Ok(0);
+        }
+        // A fixed order keeps concurrent writers from deadlocking on rows.
+        seen.sort_unstable_by_key(|(id, _)| *id);
+
+        let context = RequestContext::internal();
+        let result = self
+            .repository_service
+            .execute(&context, async |uow| uow.users().record_last_seen(uow.tx(), &seen).await)
+            .await;
+        match result {
+            Ok(()) => {
+                self.repository_service.user_list_cache().invalidate(USERS);
+                Ok(seen.len())
+            }
+            Err(error) => {
+                self.restore(seen);
+                Err(error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::Duration;
+
+    use super::{ActivityService, ActivityServiceImpl};
+    use crate::{
+        Error, RepositoryError,
+        clock::{Clock, FixedClock},
+        test_support::{MockSessionRepository, MockUserRepository, MockWebhookRepository, mock_repository_service},
+        user::UserId,
+    };
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn create_service(user_repository: MockUserRepository, clock: Arc<FixedClock>) -> ActivityServiceImpl {
+        let repository_service = mock_repository_service(user_repository, MockSessionRepository::new(), MockWebhookRepository::new())
+            .clock(clock as Arc<dyn Clock>)
+            .build()
+            .expect("All required fields provided");
+        ActivityServiceImpl::new(Arc::new(repository_service))
+    }
+
+    // ===================
+    // Tests: flush
+    // ===================
+    #[tokio::test]
+    async fn test_flush_batches_latest_sighting_per_user() {
+        let start = FixedClock::epoch();
+        let mut repository = MockUserRepository::new();
+        repository
+            .expect_record_last_seen()
+            .withf(move |_, seen| seen == [(UserId::new(1), start), (UserId::new(2), start + Duration::seconds(5))])
+            .times(1)
+            .return_const(Ok(()));
+        let clock = Arc::new(FixedClock::default());
+        let service = create_service(repository, clock.clone());
+
+        service.record_activity(UserId::new(2));
+        service.record_activity(UserId::new(1));
+        clock.advance(Duration::seconds(5));
+        service.record_activity(UserId::new(2));
+
+        assert_eq!(service.flush().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_flush_without_activity_writes_nothing() {
+        let mut repository = MockUserRepository::new();
+        repository.expect_record_last_seen().never();
+        let service = create_service(repository, Arc::default());
+
+        assert_eq!(service.flush().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_flush_keeps_sightings() {
+        let mut repository = MockUserRepository::new();
+        repository
+            .expect_record_last_seen()
+            .return_const(Err(Error::RepositoryError(RepositoryError::Conflict)));
+        let service = create_service(repository, Arc::default());
+        service.record_activity(UserId::new(1));
+
+        assert!(service.flush().await.is_err());
+        assert_eq!(service.pending.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,8 @@
+pub mod model;
+pub mod service;
+
+pub use model::{AvatarVariant, StoredAvatar};
+pub(crate) use service::AvatarServiceImpl;
+#[cfg(any(test, feature = "test-support"))]
+pub use service::MockAvatarService;
+pub use service::{AVATAR_CONTENT_TYPES, AVATAR_URL_TTL, AvatarService, MAX_AVATAR_BYTES};
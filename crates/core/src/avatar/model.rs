@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+/// A rendition of a user's avatar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AvatarVariant {
+    /// The image as uploaded.
+    #[default]
+    Original,
+    /// Scaled down to fit 64x64.
+    Thumbnail,
+    /// Scaled down to fit 256x256.
+    Medium,
+}
+
+impl AvatarVariant {
+    /// The variants generated in the background after an upload.
+    pub const RESIZED: [Self; 2] = [Self::Thumbnail, Self::Medium];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Original => "original",
+            Self::Thumbnail => "thumbnail",
+            Self::Medium => "medium",
+        }
+    }
+
+    /// Largest width and height of the variant, `None` for the original.
+    pub fn max_dimension(self) -> Option<u32> {
+        match self {
+            Self::Original => None,
+            Self::Thumbnail => Some(64),
+            Self::Medium => Some(256),
+        }
+    }
+
+    /// Object key of this variant of the avatar stored under `key`. Resized
+    /// variants are always PNG, e.g. `avatars/x/1.jpg` has its thumbnail at
+    /// `avatars/x/1-thumbnail.png`.
+    pub fn key(self, key: &str) -> String {
+        match self {
+            Self::Original => key.to_string(),
+            variant => {
+                let stem = key.rsplit_once('.').map_or(key, |(stem, _)| stem);
+                format!("{stem}-{}.png", variant.as_str())
+            }
+        }
+    }
+}
+
+/// The avatar recorded for a user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredAvatar {
+    /// Object key of the original image.
+    pub key: String,
+    /// Whether the resized variants of `key` have been generated.
+    pub variants_ready: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AvatarVariant;
+
+    // ===================
+    // Tests: AvatarVariant::key
+    // ===================
+    #[test]
+    fn test_variant_key() {
+        assert_eq!(AvatarVariant::Original.key("avatars/x/1.jpg"), "avatars/x/1.jpg");
+        assert_eq!(AvatarVariant::Thumbnail.key("avatars/x/1.jpg"), "avatars/x/1-thumbnail.png");
+        assert_eq!(AvatarVariant::Medium.key("avatars/x/1"), "avatars/x/1-medium.png");
+    }
+}
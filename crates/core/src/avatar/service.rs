@@ -0,0 +1,506 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    Error, RepositoryError,
+    avatar::AvatarVariant,
+    context::RequestContext,
+    i18n::ValidationMessage,
+    image::ImageProcessor,
+    jobs::{Job, JobQueue},
+    repository::RepositoryService,
+    storage::ObjectStorage,
+    user::UserId,
+};
+
+/// Largest avatar accepted, in bytes.
+pub const MAX_AVATAR_BYTES: usize = 1024 * 1024;
+/// Image types accepted as avatars, with the file extension used in keys.
+pub const AVATAR_CONTENT_TYPES: [(&str, &str); 4] = [("image/png", "png"), ("image/jpeg", "jpg"), ("image/gif", "gif"), ("image/webp", "webp")];
+/// How long a URL returned by `avatar_url` stays valid.
+pub const AVATAR_URL_TTL: Duration = Duration::from_secs(15 * 60);
+
+#[cfg_attr(any(test, feature = "test-support"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait AvatarService: Send + Sync {
+    /// Stores `image` as the avatar of user `id`, replacing and removing any
+    /// previous one, and queues a job generating its resized variants.
+    /// Returns the object key it was stored under.
+    async fn upload_avatar(&self, context: &RequestContext, id: UserId, content_type: &str, image: Vec<u8>) -> Result<String, Error>;
+    /// A presigned URL for `variant` of the avatar of user `id`, valid for
+    /// [`AVATAR_URL_TTL`]. Until the resized variants have been generated,
+    /// they resolve to the original. `None` if the user has no avatar.
+    async fn avatar_url(&self, context: &RequestContext, id: UserId, variant: AvatarVariant) -> Result<Option<String>, Error>;
+    /// Generates and stores the resized variants of the avatar under `key`.
+    /// Run by [`Job::ProcessAvatar`]; does nothing once the avatar has been
+    /// replaced.
+    async fn process_avatar(&self, context: &RequestContext, id: UserId, key: &str) -> Result<(), Error>;
+}
+
+pub(crate) struct AvatarServiceImpl {
+    repository_service: Arc<RepositoryService>,
+    object_storage: Arc<dyn ObjectStorage>,
+    image_processor: Arc<dyn ImageProcessor>,
+    job_queue: Arc<dyn JobQueue>,
+}
+
+impl AvatarServiceImpl {
+    pub(crate) fn new(
+        repository_service: Arc<RepositoryService>,
+        object_storage: Arc<dyn ObjectStorage>,
+        image_processor: Arc<dyn ImageProcessor>,
+        job_queue: Arc<dyn JobQueue>,
+    ) -> Self {
+        Self {
+            repository_service,
+            object_storage,
+            image_processor,
+            job_queue,
+        }
+    }
+
+    /// Removes every stored object of the avatar under `key`, logging rather
+    /// than failing, since a leftover object only costs space.
+    async fn delete_avatar_objects(&self, key: &str, reason: &str) {
+        for variant in [AvatarVariant::Original].into_iter().chain(AvatarVariant::RESIZED) {
+            let key = variant.key(key);
+            if let Err(error) = self.object_storage.delete(&key).await {
+                tracing::warn!(%key, %error, "Failed to remove {}", reason);
+            }
+        }
+    }
+
+    async fn resize(&self, image: Arc<Vec<u8>>, max_dimension: u32) -> Result<Vec<u8>, Error> {
+        let image_processor = self.image_processor.clone();
+        tokio::task::spawn_blocking(move || image_processor.resize(&image, max_dimension))
+            .await
+            .map_err(|error| Error::ImageProcessing(error.to_string()))?
+    }
+}
+
+fn extension_for(content_type: &str) -> Result<&'static str, Error> {
+    AVATAR_CONTENT_TYPES
+        .iter()
+        .find(|(accepted, _)| accepted.eq_ignore_ascii_case(content_type))
+        .map(|(_, extension)| *extension)
+        .ok_or_else(|| {
+            Error::Validation(ValidationMessage::UnsupportedAvatarType {
+                content_type: content_type.to_string(),
+            })
+        })
+}
+
+#[async_trait::async_trait]
+impl AvatarService for AvatarServiceImpl {
+    #[tracing::instrument(level = "trace", skip(self, context, image), fields(request_id = %context.request_id, size = image.len()))]
+    async fn upload_avatar(&self, context: &RequestContext, id: UserId, content_type: &str, image: Vec<u8>) -> Result<String, Error> {
+        let extension = extension_for(content_type)?;
+        if image.len() > MAX_AVATAR_BYTES {
+            return Err(Error::Validation(ValidationMessage::AvatarTooLarge { max_bytes: MAX_AVATAR_BYTES }));
+        }
+        // Checked up front so nothing is uploaded that cannot be recorded.
+        if self.repository_service.maintenance_mode().is_enabled() {
+            return Err(Error::ReadOnlyMode);
+        }
+
+        let user = self
+            .repository_service
+            .execute_read_only(context, async |uow| uow.users().find_by_id(uow.tx(), id).await)
+            .await?
+            .ok_or(Error::RepositoryError(RepositoryError::NotFound))?;
+        // A new key per upload, so presigned URLs handed out for the old
+        // avatar never show the new one and caches need no invalidation.
+        let key = format!(
+            "avatars/{}/{}.{extension}",
+            user.token,
+            self.repository_service.clock().now().timestamp_millis()
+        );
+        self.object_storage.put(&key, content_type, image).await?;
+
+        let previous = self
+            .repository_service
+            .execute(context, async |uow| uow.users().set_avatar_key(uow.tx(), id, Some(key.clone())).await)
+            .await;
+        let previous = match previous {
+            Ok(previous) => previous,
+            Err(error) => {
+                if let Err(cleanup) = self.object_storage.delete(&key).await {
+                    tracing::warn!(%key, error = %cleanup, "Failed to remove unrecorded avatar");
+                }
+                return Err(error);
+            }
+        };
+
+        if let Some(previous) = previous.filter(|previous| *previous != key) {
+            self.delete_avatar_objects(&previous, "replaced avatar").await;
+        }
+
+        // The original serves every variant until the job has run, so a
+        // job that is lost only costs bandwidth.
+        let job = Job::ProcessAvatar { user_id: id, key: key.clone() };
+        if let Err(error) = self.job_queue.enqueue(job).await {
+            tracing::warn!(%key, %error, "Failed to queue avatar processing");
+        }
+
+        Ok(key)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn avatar_url(&self, context: &RequestContext, id: UserId, variant: AvatarVariant) -> Result<Option<String>, Error> {
+        let avatar = self
+            .repository_service
+            .execute_read_only(context, async |uow| {
+                if uow.users().find_by_id(uow.tx(), id).await?.is_none() {
+                    return Err(Error::RepositoryError(RepositoryError::NotFound));
+                }
+                uow.users().find_avatar(uow.tx(), id).await
+            })
+            .await?;
+
+        match avatar {
+            Some(avatar) => {
+                let variant = if avatar.variants_ready { variant } else { AvatarVariant::Original };
+                let key = variant.key(&avatar.key);
+                Ok(Some(self.object_storage.presigned_url(&key, AVATAR_URL_TTL).await?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn process_avatar(&self, context: &RequestContext, id: UserId, key: &str) -> Result<(), Error> {
+        let Some(original) = self.object_storage.get(key).await? else {
+            tracing::debug!(%key, "Avatar was removed before processing");
+            return Ok(());
+        };
+
+        let image = Arc::new(original.bytes);
+        for variant in AvatarVariant::RESIZED {
+            let max_dimension = variant.max_dimension().expect("resized variants have a size");
+            let resized = self.resize(image.clone(), max_dimension).await?;
+            self.object_storage.put(&variant.key(key), "image/png", resized).await?;
+        }
+
+        let current = self
+            .repository_service
+            .execute(context, async |uow| uow.users().mark_avatar_variants_ready(uow.tx(), id, key).await)
+            .await?;
+        if !current {
+            // Replaced while processing; the upload that replaced it removed
+            // the original but not the variants written since.
+            self.delete_avatar_objects(key, "variants of replaced avatar").await;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{AvatarService, AvatarServiceImpl, MAX_AVATAR_BYTES};
+    use crate::{
+        Error, RepositoryError,
+        avatar::{AvatarVariant, StoredAvatar},
+        clock::FixedClock,
+        context::RequestContext,
+        image::ImageProcessor,
+        jobs::{Job, RecordingJobQueue},
+        maintenance::MaintenanceMode,
+        storage::{InMemoryObjectStorage, ObjectStorage},
+        test_support::{MockSessionRepository, MockUserRepository, MockWebhookRepository, mock_repository_service},
+        user::model::{User, UserId, UserToken},
+    };
+
+    // ===================
+    // Fake ImageProcessor
+    // ===================
+    /// "Resizes" every image to the decimal text of `max_dimension`.
+    struct FakeImageProcessor;
+
+    impl ImageProcessor for FakeImageProcessor {
+        fn resize(&self, image: &[u8], max_dimension: u32) -> Result<Vec<u8>, Error> {
+            if image.is_empty() {
+                return Err(Error::ImageProcessing("empty image".into()));
+            }
+            Ok(max_dimension.to_string().into_bytes())
+        }
+    }
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn create_service(user_repository: MockUserRepository, storage: Arc<InMemoryObjectStorage>, maintenance_mode: MaintenanceMode) -> AvatarServiceImpl {
+        create_service_with_jobs(user_repository, storage, maintenance_mode, Arc::default())
+    }
+
+    fn create_service_with_jobs(
+        user_repository: MockUserRepository,
+        storage: Arc<InMemoryObjectStorage>,
+        maintenance_mode: MaintenanceMode,
+        jobs: Arc<RecordingJobQueue>,
+    ) -> AvatarServiceImpl {
+        let repository_service = mock_repository_service(user_repository, MockSessionRepository::new(), MockWebhookRepository::new())
+            .clock(Arc::new(FixedClock::default()))
+            .maintenance_mode(maintenance_mode)
+            .build()
+            .expect("All required fields provided");
+        AvatarServiceImpl::new(Arc::new(repository_service), storage, Arc::new(FakeImageProcessor), jobs)
+    }
+
+    /// A repository that knows only user 1.
+    fn with_known_user() -> MockUserRepository {
+        let mut repository = MockUserRepository::new();
+        repository
+            .expect_find_by_id()
+            .returning(|_, id| Ok(Some(User::fake(1, "John Doe", "john@example.com")).filter(|user| user.id == id)));
+        repository
+    }
+
+    /// A repository without users.
+    fn without_users() -> MockUserRepository {
+        let mut repository = MockUserRepository::new();
+        repository.expect_find_by_id().return_const(Ok(None));
+        repository
+    }
+
+    fn with_user(avatar_key: Option<&str>) -> MockUserRepository {
+        with_avatar(avatar_key, false)
+    }
+
+    /// A repository holding user 1 and its avatar, which `set_avatar_key`
+    /// and `mark_avatar_variants_ready` update.
+    fn with_avatar(avatar_key: Option<&str>, variants_ready: bool) -> MockUserRepository {
+        let avatar = Arc::new(Mutex::new(avatar_key.map(|key| StoredAvatar {
+            key: key.to_string(),
+            variants_ready,
+        })));
+        let mut repository = with_known_user();
+        let current = avatar.clone();
+        repository.expect_find_avatar().returning(move |_, _| Ok(current.lock().unwrap().clone()));
+        let current = avatar.clone();
+        repository.expect_set_avatar_key().returning(move |_, _, key| {
+            let avatar = key.map(|key| StoredAvatar { key, variants_ready: false });
+            Ok(std::mem::replace(&mut *current.lock().unwrap(), avatar).map(|previous| previous.key))
+        });
+        repository
+            .expect_mark_avatar_variants_ready()
+            .returning(move |_, _, key| match &mut *avatar.lock().unwrap() {
+                Some(avatar) if avatar.key == key => {
+                    avatar.variants_ready = true;
+                    Ok(true)
+                }
+                _ => Ok(false),
+            });
+        repository
+    }
+
+    // ===================
+    // Tests: upload_avatar
+    // ===================
+    #[tokio::test]
+    async fn test_upload_avatar_replaces_previous() {
+        let storage = Arc::new(InMemoryObjectStorage::default());
+        storage.put("avatars/old.png", "image/png", vec![0]).await.unwrap();
+        storage.put("avatars/old-thumbnail.png", "image/png", vec![0]).await.unwrap();
+        let service = create_service(with_user(Some("avatars/old.png")), storage.clone(), MaintenanceMode::default());
+
+        let key = service
+            .upload_avatar(&RequestContext::internal(), UserId::new(1), "image/png", vec![1, 2, 3])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            key,
+            format!("avatars/{}/{}.png", UserToken::new(UserId::new(1)), FixedClock::epoch().timestamp_millis())
+        );
+        assert_eq!(storage.get(&key).await.unwrap().unwrap().bytes, [1, 2, 3]);
+        assert_eq!(storage.keys(), [key]);
+    }
+
+    #[tokio::test]
+    async fn test_upload_avatar_queues_processing() {
+        let jobs = Arc::new(RecordingJobQueue::default());
+        let service = create_service_with_jobs(with_user(None), Arc::default(), MaintenanceMode::default(), jobs.clone());
+
+        let key = service
+            .upload_avatar(&RequestContext::internal(), UserId::new(1), "image/png", vec![1])
+            .await
+            .unwrap();
+
+        assert_eq!(jobs.jobs(), [Job::ProcessAvatar { user_id: UserId::new(1), key }]);
+    }
+
+    #[tokio::test]
+    async fn test_upload_avatar_rejects_unsupported_type() {
+        let storage = Arc::new(InMemoryObjectStorage::default());
+        let service = create_service(with_user(None), storage.clone(), MaintenanceMode::default());
+
+        let result = service.upload_avatar(&RequestContext::internal(), UserId::new(1), "text/plain", vec![1]).await;
+
+        assert!(matches!(result, Err(Error::Validation(_))));
+        assert!(storage.keys().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_upload_avatar_rejects_large_image() {
+        let service = create_service(with_user(None), Arc::default(), MaintenanceMode::default());
+
+        let result = service
+            .upload_avatar(&RequestContext::internal(), UserId::new(1), "image/png", vec![0; MAX_AVATAR_BYTES + 1])
+            .await;
+
+        assert!(matches!(result, Err(Error::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_upload_avatar_unknown_user() {
+        let storage = Arc::new(InMemoryObjectStorage::default());
+        let service = create_service(without_users(), storage.clone(), MaintenanceMode::default());
+
+        let result = service.upload_avatar(&RequestContext::internal(), UserId::new(1), "image/png", vec![1]).await;
+
+        assert!(matches!(result, Err(Error::RepositoryError(RepositoryError::NotFound))));
+        assert!(storage.keys().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_upload_avatar_removes_object_when_not_recorded() {
+        let storage = Arc::new(InMemoryObjectStorage::default());
+        let mut repository = with_known_user();
+        repository
+            .expect_set_avatar_key()
+            .return_const(Err(Error::RepositoryError(RepositoryError::Conflict)));
+        let service = create_service(repository, storage.clone(), MaintenanceMode::default());
+
+        let result = service.upload_avatar(&RequestContext::internal(), UserId::new(1), "image/png", vec![1]).await;
+
+        assert!(matches!(result, Err(Error::RepositoryError(RepositoryError::Conflict))));
+        assert!(storage.keys().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_upload_avatar_in_maintenance_mode() {
+        let storage = Arc::new(InMemoryObjectStorage::default());
+        let mut repository = with_known_user();
+        repository.expect_set_avatar_key().never();
+        let service = create_service(repository, storage.clone(), MaintenanceMode::new(true));
+
+        let result = service.upload_avatar(&RequestContext::internal(), UserId::new(1), "image/png", vec![1]).await;
+
+        assert!(matches!(result, Err(Error::ReadOnlyMode)));
+        assert!(storage.keys().is_empty());
+    }
+
+    // ===================
+    // Tests: avatar_url
+    // ===================
+    #[tokio::test]
+    async fn test_avatar_url_is_presigned() {
+        let service = create_service(with_user(Some("avatars/a.png")), Arc::default(), MaintenanceMode::default());
+
+        let url = service
+            .avatar_url(&RequestContext::internal(), UserId::new(1), AvatarVariant::Original)
+            .await
+            .unwrap();
+
+        assert_eq!(url.as_deref(), Some("memory://avatars/a.png?expires_in=900"));
+    }
+
+    #[tokio::test]
+    async fn test_avatar_url_variant_when_ready() {
+        let service = create_service(with_avatar(Some("avatars/a.png"), true), Arc::default(), MaintenanceMode::default());
+
+        let url = service
+            .avatar_url(&RequestContext::internal(), UserId::new(1), AvatarVariant::Thumbnail)
+            .await
+            .unwrap();
+
+        assert_eq!(url.as_deref(), Some("memory://avatars/a-thumbnail.png?expires_in=900"));
+    }
+
+    #[tokio::test]
+    async fn test_avatar_url_variant_falls_back_to_original() {
+        let service = create_service(with_avatar(Some("avatars/a.png"), false), Arc::default(), MaintenanceMode::default());
+
+        let url = service
+            .avatar_url(&RequestContext::internal(), UserId::new(1), AvatarVariant::Medium)
+            .await
+            .unwrap();
+
+        assert_eq!(url.as_deref(), Some("memory://avatars/a.png?expires_in=900"));
+    }
+
+    #[tokio::test]
+    async fn test_avatar_url_without_avatar() {
+        let service = create_service(with_user(None), Arc::default(), MaintenanceMode::default());
+
+        assert_eq!(
+            service
+                .avatar_url(&RequestContext::internal(), UserId::new(1), AvatarVariant::Original)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_avatar_url_unknown_user() {
+        let service = create_service(without_users(), Arc::default(), MaintenanceMode::default());
+
+        let result = service.avatar_url(&RequestContext::internal(), UserId::new(1), AvatarVariant::Original).await;
+
+        assert!(matches!(result, Err(Error::RepositoryError(RepositoryError::NotFound))));
+    }
+
+    // ===================
+    // Tests: process_avatar
+    // ===================
+    #[tokio::test]
+    async fn test_process_avatar_stores_variants() {
+        let storage = Arc::new(InMemoryObjectStorage::default());
+        storage.put("avatars/a.jpg", "image/jpeg", vec![1]).await.unwrap();
+        let service = create_service(with_user(Some("avatars/a.jpg")), storage.clone(), MaintenanceMode::default());
+
+        service
+            .process_avatar(&RequestContext::internal(), UserId::new(1), "avatars/a.jpg")
+            .await
+            .unwrap();
+
+        let thumbnail = storage.get("avatars/a-thumbnail.png").await.unwrap().unwrap();
+        assert_eq!(thumbnail.content_type, "image/png");
+        assert_eq!(thumbnail.bytes, b"64");
+        assert_eq!(storage.get("avatars/a-medium.png").await.unwrap().unwrap().bytes, b"256");
+        let url = service
+            .avatar_url(&RequestContext::internal(), UserId::new(1), AvatarVariant::Thumbnail)
+            .await
+            .unwrap();
+        assert_eq!(url.as_deref(), Some("memory://avatars/a-thumbnail.png?expires_in=900"));
+    }
+
+    #[tokio::test]
+    async fn test_process_avatar_after_replacement_removes_variants() {
+        let storage = Arc::new(InMemoryObjectStorage::default());
+        storage.put("avatars/old.png", "image/png", vec![1]).await.unwrap();
+        let service = create_service(with_user(Some("avatars/new.png")), storage.clone(), MaintenanceMode::default());
+
+        service
+            .process_avatar(&RequestContext::internal(), UserId::new(1), "avatars/old.png")
+            .await
+            .unwrap();
+
+        assert!(storage.keys().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_process_avatar_undecodable_image() {
+        let storage = Arc::new(InMemoryObjectStorage::default());
+        storage.put("avatars/a.png", "image/png", vec![]).await.unwrap();
+        let service = create_service(with_user(Some("avatars/a.png")), storage.clone(), MaintenanceMode::default());
+
+        let result = service.process_avatar(&RequestContext::internal(), UserId::new(1), "avatars/a.png").await;
+
+        assert!(matches!(result, Err(Error::ImageProcessing(_))));
+        assert_eq!(storage.keys(), ["avatars/a.png"]);
+    }
+}
@@ -0,0 +1,246 @@
+//! In-process cache of query results, such as the pages of users dashboards
+//! keep polling, so repeated reads skip the database.
+//!
+//! Each entry is tagged with what it was read from, e.g. [`USERS`], and
+//! services invalidate a tag once a write to it has committed. A read that
+//! was under way while a tag was invalidated is not cached, as it may have
+//! read what was there before. Entries also expire after the time to live,
+//! which bounds how stale a write outside of the services leaves them.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::Error;
+
+/// Tag of results read from the users.
+pub const USERS: &str = "users";
+
+/// Entries kept at most; results are not cached while it is full of
+/// entries that have not expired.
+const MAX_ENTRIES: usize = 1024;
+
+/// Shared cache of query results of type `V`. Clones use the same entries
+/// and time to live. Caches nothing until given a time to live.
+#[derive(Debug, Clone)]
+pub struct QueryCache<V> {
+    name: &'static str,
+    ttl_ms: Arc<AtomicU64>,
+    state: Arc<Mutex<State<V>>>,
+}
+
+#[derive(Debug)]
+struct State<V> {
+    entries: HashMap<String, Entry<V>>,
+    /// Bumped each time a tag is invalidated.
+    generations: HashMap<&'static str, u64>,
+}
+
+#[derive(Debug)]
+struct Entry<V> {
+    value: V,
+    tags: &'static [&'static str],
+    expires_at: Instant,
+}
+
+impl<V> State<V> {
+    fn generations(&self, tags: &[&'static str]) -> Vec<u64> {
+        tags.iter().map(|tag| self.generations.get(tag).copied().unwrap_or_default()).collect()
+    }
+}
+
+impl<V: Clone> QueryCache<V> {
+    /// A disabled cache, reported in metrics as `name`.
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            ttl_ms: Arc::new(AtomicU64::new(0)),
+            state: Arc::new(Mutex::new(State {
+                entries: HashMap::new(),
+                generations: HashMap::new(),
+            })),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        Duration::from_millis(self.ttl_ms.load(Ordering::Relaxed))
+    }
+
+    /// Changes how long results are kept, dropping those cached so far. A
+    /// time to live of zero disables the cache.
+    pub fn set_ttl(&self, ttl: Duration) {
+        let previous = self.ttl_ms.swap(ttl.as_millis() as u64, Ordering::Relaxed);
+        self.lock().entries.clear();
+        if previous != ttl.as_millis() as u64 {
+            tracing::info!(cache = self.name, ttl_ms = ttl.as_millis() as u64, "Query cache time to live changed");
+        }
+    }
+
+    /// The result cached under `key`, or else the one `load` reads, cached
+    /// with `tags` if none of them is invalidated while it reads.
+    pub async fn get_or_load<F>(&self, key: String, tags: &'static [&'static str], load: F) -> Result<V, Error>
+    where
+        F: AsyncFnOnce() -> Result<V, Error>,
+    {
+        let ttl = self.ttl();
+        if ttl.is_zero() {
+            return load().await;
+        }
+
+        let generations = {
+            let state = self.lock();
+            if let Some(entry) = state.entries.get(&key).filter(|entry| entry.expires_at > Instant::now()) {
+                metrics::counter!("query_cache_lookups_total", "cache" => self.name, "outcome" => "hit").increment(1);
+                return Ok(entry.value.clone());
+            }
+            state.generations(tags)
+        };
+        metrics::counter!("query_cache_lookups_total", "cache" => self.name, "outcome" => "miss").increment(1);
+
+        let value = load().await?;
+        self.insert(key, tags, generations, value.clone(), Instant::now() + ttl);
+        Ok(value)
+    }
+
+    /// Drops every result tagged with `tag`, and keeps results being read
+    /// meanwhile from being cached.
+    pub fn invalidate(&self, tag: &'static str) {
+        let mut state = self.lock();
+        *state.generations.entry(tag).or_default() += 1;
+        state.entries.retain(|_, entry| !entry.tags.contains(&tag));
+        metrics::counter!("query_cache_invalidations_total", "cache" => self.name, "tag" => tag).increment(1);
+    }
+
+    fn insert(&self, key: String, tags: &'static [&'static str], generations: Vec<u64>, value: V, expires_at: Instant) {
+        let mut state = self.lock();
+        if state.generations(tags) != generations {
+            return;
+        }
+        if state.entries.len() >= MAX_ENTRIES {
+            let now = Instant::now();
+            state.entries.retain(|_, entry| entry.expires_at > now);
+            if state.entries.len() >= MAX_ENTRIES {
+                return;
+            }
+        }
+        state.entries.insert(key, Entry { value, tags, expires_at });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State<V>> {
+        // The state stays consistent even if a holder panicked.
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::{Duration, Instant},
+    };
+
+    use super::{QueryCache, USERS};
+    use crate::Error;
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn cache() -> QueryCache<u64> {
+        let cache = QueryCache::new("test");
+        cache.set_ttl(Duration::from_secs(60));
+        cache
+    }
+
+    /// Reads through `cache`, counting the loads in `loads`.
+    async fn read(cache: &QueryCache<u64>, key: &str, loads: &AtomicUsize) -> u64 {
+        cache
+            .get_or_load(key.to_string(), &[USERS], async || Ok(loads.fetch_add(1, Ordering::Relaxed) as u64))
+            .await
+            .unwrap()
+    }
+
+    // ===================
+    // Tests: get_or_load
+    // ===================
+    #[tokio::test]
+    async fn test_get_or_load_caches_by_key() {
+        let cache = cache();
+        let loads = AtomicUsize::new(0);
+
+        assert_eq!(read(&cache, "a", &loads).await, 0);
+        assert_eq!(read(&cache, "a", &loads).await, 0);
+        assert_eq!(read(&cache, "b", &loads).await, 1);
+        assert_eq!(loads.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_or_load_without_ttl_always_loads() {
+        let cache = QueryCache::new("test");
+        let loads = AtomicUsize::new(0);
+
+        read(&cache, "a", &loads).await;
+        read(&cache, "a", &loads).await;
+
+        assert_eq!(loads.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_or_load_does_not_cache_errors() {
+        let cache = cache();
+        let loads = AtomicUsize::new(0);
+
+        let error = cache
+            .get_or_load("a".to_string(), &[USERS], async || Err(Error::Infrastructure("db down".into())))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, Error::Infrastructure(_)));
+        assert_eq!(read(&cache, "a", &loads).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_or_load_skips_results_read_across_invalidation() {
+        let cache = cache();
+        let loads = AtomicUsize::new(0);
+
+        cache
+            .get_or_load("a".to_string(), &[USERS], async || {
+                cache.invalidate(USERS);
+                Ok(42)
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(read(&cache, "a", &loads).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_are_reloaded() {
+        let cache = cache();
+        let loads = AtomicUsize::new(0);
+        cache.insert("a".to_string(), &[USERS], vec![0], 42, Instant::now() - Duration::from_secs(1));
+
+        assert_eq!(read(&cache, "a", &loads).await, 0);
+    }
+
+    // ===================
+    // Tests: invalidate
+    // ===================
+    #[tokio::test]
+    async fn test_invalidate_drops_tagged_entries_only() {
+        let cache = cache();
+        let loads = AtomicUsize::new(0);
+        read(&cache, "a", &loads).await;
+        cache.get_or_load("b".to_string(), &[], async || Ok(7)).await.unwrap();
+
+        cache.invalidate(USERS);
+
+        assert_eq!(read(&cache, "a", &loads).await, 1);
+        assert_eq!(cache.get_or_load("b".to_string(), &[], async || Ok(8)).await.unwrap(), 7);
+    }
+}
@@ -0,0 +1,107 @@
+//! Time source used for stamping `created_at`/`updated_at`.
+//!
+//! Production code uses [`SystemClock`]; tests inject a [`FixedClock`] so
+//! timestamps are deterministic.
+
+use chrono::{DateTime, Utc};
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Clock backed by the system wall clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+pub use fixed::FixedClock;
+
+#[cfg(any(test, feature = "test-support"))]
+mod fixed {
+    use std::sync::Mutex;
+
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    use super::Clock;
+
+    /// Clock frozen at a given instant until explicitly moved.
+    /// Only available in test builds.
+    #[derive(Debug)]
+    pub struct FixedClock {
+        now: Mutex<DateTime<Utc>>,
+    }
+
+    impl FixedClock {
+        /// The instant a default `FixedClock` is frozen at.
+        pub fn epoch() -> DateTime<Utc> {
+            Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()
+        }
+
+        pub fn new(now: DateTime<Utc>) -> Self {
+            Self { now: Mutex::new(now) }
+        }
+
+        pub fn set(&self, now: DateTime<Utc>) {
+            *self.now.lock().unwrap() = now;
+        }
+
+        pub fn advance(&self, by: Duration) {
+            *self.now.lock().unwrap() += by;
+        }
+    }
+
+    impl Default for FixedClock {
+        fn default() -> Self {
+            Self::new(Self::epoch())
+        }
+    }
+
+    impl Clock for FixedClock {
+        fn now(&self) -> DateTime<Utc> {
+            *self.now.lock().unwrap()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::{Clock, FixedClock};
+
+    // ===================
+    // Tests: FixedClock
+    // ===================
+    #[test]
+    fn test_fixed_clock_is_frozen() {
+        let clock = FixedClock::default();
+
+        assert_eq!(clock.now(), FixedClock::epoch());
+        assert_eq!(clock.now(), clock.now());
+    }
+
+    #[test]
+    fn test_fixed_clock_advance() {
+        let clock = FixedClock::default();
+
+        clock.advance(Duration::minutes(5));
+
+        assert_eq!(clock.now(), FixedClock::epoch() + Duration::minutes(5));
+    }
+
+    #[test]
+    fn test_fixed_clock_set() {
+        let clock = FixedClock::default();
+        let later = FixedClock::epoch() + Duration::days(1);
+
+        clock.set(later);
+
+        assert_eq!(clock.now(), later);
+    }
+}
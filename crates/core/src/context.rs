@@ -0,0 +1,96 @@
+//! Per-request information passed to every use case.
+
+use std::time::{Duration, Instant};
+
+use crate::i18n::Language;
+
+/// Who a request comes from and how long it may take. Built by the inbound
+/// adapters for each request and passed as the first argument to use cases,
+/// so auditing, authorization and deadlines don't depend on tracing spans.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestContext {
+    /// Correlates the logs and audit records of one request.
+    pub request_id: String,
+    /// Authenticated caller; `None` for anonymous requests.
+    pub actor: Option<String>,
+    /// Admin acting as `actor` through an impersonation, if any.
+    pub impersonator: Option<String>,
+    /// Tenant the request acts within, if any.
+    pub tenant: Option<String>,
+    /// When the caller stops waiting for a result.
+    pub deadline: Option<Instant>,
+    /// Preferred language for user-facing text, e.g. `en-US`.
+    pub locale: Option<String>,
+    /// Whether reads must see every committed write, so run against the
+    /// primary database even where reads are routed to a replica.
+    pub requires_primary: bool,
+}
+
+impl RequestContext {
+    pub fn new(request_id: impl Into<String>) -> Self {
+        Self {
+            request_id: request_id.into(),
+            actor: None,
+            impersonator: None,
+            tenant: None,
+            deadline: None,
+            locale: None,
+            requires_primary: false,
+        }
+    }
+
+    /// Context for work not started by an API client, such as the web
+    /// frontend's own calls and tests.
+    pub fn internal() -> Self {
+        Self::new("internal")
+    }
+
+    /// Reads against the primary database rather than a replica, which may
+    /// lag behind, e.g. to read what an earlier request has just written.
+    pub fn require_primary(mut self) -> Self {
+        self.requires_primary = true;
+        self
+    }
+
+    /// Time left until the deadline, zero once it has passed. `None` without
+    /// a deadline.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Catalog language for the locale, English when there is no locale or
+    /// no catalog for it.
+    pub fn language(&self) -> Language {
+        self.locale.as_deref().and_then(Language::from_tag).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::RequestContext;
+
+    // ===================
+    // Tests: RequestContext
+    // ===================
+
+    #[test]
+    fn test_remaining_without_deadline() {
+        assert_eq!(RequestContext::internal().remaining(), None);
+    }
+
+    #[test]
+    fn test_remaining_after_deadline_is_zero() {
+        let mut context = RequestContext::new("req-1");
+        context.deadline = Some(Instant::now() - Duration::from_secs(1));
+
+        assert_eq!(context.remaining(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_require_primary() {
+        assert!(!RequestContext::internal().requires_primary);
+        assert!(RequestContext::internal().require_primary().requires_primary);
+    }
+}
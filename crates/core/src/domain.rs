@@ -0,0 +1,65 @@
+//! Business rules of the aggregates, checked by the
+//! [`CheckedUserRepository`](crate::user::CheckedUserRepository) units of
+//! work hand out rather than by each service or handler, so every write
+//! enforces the same rules. Rules about a single value, such as the range of an age,
+//! belong to its type in [`types`](crate::types) instead.
+
+use crate::{
+    Error,
+    i18n::ValidationMessage,
+    user::{NewUser, User},
+};
+
+/// Rules an aggregate holds to whenever it is persisted.
+pub trait Invariants {
+    /// Fails with `Error::Validation` for the first rule that does not hold.
+    fn check_invariants(&self) -> Result<(), Error>;
+}
+
+/// Every user has a name to be shown by.
+fn check_name(name: &str) -> Result<(), Error> {
+    if name.trim().is_empty() {
+        return Err(Error::Validation(ValidationMessage::BlankName));
+    }
+    Ok(())
+}
+
+impl Invariants for User {
+    fn check_invariants(&self) -> Result<(), Error> {
+        check_name(&self.name)
+    }
+}
+
+impl Invariants for NewUser {
+    fn check_invariants(&self) -> Result<(), Error> {
+        check_name(&self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Invariants as _;
+    use crate::{
+        Error,
+        i18n::ValidationMessage,
+        user::{NewUser, User},
+    };
+
+    // ===================
+    // Tests: Invariants
+    // ===================
+    #[test]
+    fn test_user_with_name_holds() {
+        assert!(User::fake(1, "John Doe", "john@example.com").check_invariants().is_ok());
+        assert!(NewUser::new("John Doe", "john@example.com", 30).unwrap().check_invariants().is_ok());
+    }
+
+    #[test]
+    fn test_blank_name_is_refused() {
+        let error = User::fake(1, " \t", "john@example.com").check_invariants().unwrap_err();
+        assert!(matches!(error, Error::Validation(ValidationMessage::BlankName)));
+
+        let error = NewUser::new("", "john@example.com", 30).unwrap().check_invariants().unwrap_err();
+        assert!(matches!(error, Error::Validation(ValidationMessage::BlankName)));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::i18n::{Language, ValidationMessage, validation_error_prefix};
+
 /// Categorizes errors for response mapping in adapters.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ErrorKind {
@@ -11,6 +13,8 @@ pub enum ErrorKind {
     BadRequest,
     /// Internal or infrastructure error.
     Internal,
+    /// A dependency, e.g. the database, is temporarily unreachable.
+    Unavailable,
 }
 
 #[derive(Debug, Clone, thiserror::Error)]
@@ -21,8 +25,14 @@ pub enum Error {
     #[error("Invalid page size: {0}")]
     InvalidPageSize(u64),
 
+    #[error("Invalid batch size: {0}")]
+    InvalidBatchSize(usize),
+
     #[error("Validation error: {0}")]
-    Validation(String),
+    Validation(ValidationMessage),
+
+    #[error("Update contains no fields")]
+    EmptyUpdate,
 
     #[error("Invalid transaction type")]
     InvalidTransactionType,
@@ -30,31 +40,74 @@ pub enum Error {
     #[error("Invalid token: {0}")]
     InvalidToken(String),
 
+    #[error("Service is in read-only maintenance mode")]
+    ReadOnlyMode,
+
+    /// A webhook delivery whose nonce was already received or whose
+    /// timestamp is too far from now.
+    #[error("Webhook delivery was already received or has expired")]
+    WebhookReplay,
+
+    /// An admin asked to impersonate themselves.
+    #[error("Cannot impersonate yourself")]
+    SelfImpersonation,
+
+    /// A request for a tenant without a schema of its own where tenants
+    /// are kept in schemas.
+    #[error("Unknown tenant: {0}")]
+    UnknownTenant(String),
+
     #[error("Infrastructure error: {0}")]
     Infrastructure(String),
 
+    #[error("Object storage error: {0}")]
+    Storage(String),
+
+    #[error("Image processing error: {0}")]
+    ImageProcessing(String),
+
+    #[error("Background job error: {0}")]
+    Job(String),
+
     #[error("Frontend error: {0}")]
     FrontendError(String),
 
+    /// An error reported by a remote service, carrying the kind it was
+    /// mapped from so callers can still distinguish failures.
+    #[error("{message}")]
+    Remote { kind: ErrorKind, message: String },
+
     #[error(transparent)]
     RepositoryError(#[from] RepositoryError),
-
-    #[cfg(any(test, feature = "test-support"))]
-    #[error("Mock not configured: {0}")]
-    MockNotConfigured(&'static str),
 }
 
 impl Error {
     /// Returns the error kind for response mapping in adapters.
     pub fn kind(&self) -> ErrorKind {
         match self {
-            Error::InvalidId(_) | Error::InvalidPageSize(_) | Error::InvalidToken(_) => ErrorKind::BadRequest,
+            Error::InvalidId(_)
+            | Error::InvalidPageSize(_)
+            | Error::InvalidBatchSize(_)
+            | Error::InvalidToken(_)
+            | Error::EmptyUpdate
+            | Error::SelfImpersonation
+            | Error::UnknownTenant(_) => ErrorKind::BadRequest,
             Error::Validation(_) => ErrorKind::InvalidInput,
-            Error::InvalidTransactionType | Error::Infrastructure(_) => ErrorKind::Internal,
+            Error::InvalidTransactionType | Error::Infrastructure(_) | Error::Storage(_) | Error::ImageProcessing(_) | Error::Job(_) => ErrorKind::Internal,
             Error::RepositoryError(e) => e.kind(),
             Error::FrontendError(_) => ErrorKind::Internal,
-            #[cfg(any(test, feature = "test-support"))]
-            Error::MockNotConfigured(_) => ErrorKind::Internal,
+            Error::Remote { kind, .. } => *kind,
+            Error::ReadOnlyMode => ErrorKind::Unavailable,
+            Error::WebhookReplay => ErrorKind::Conflict,
+        }
+    }
+
+    /// The message in `language`. Only validation errors are translated;
+    /// the rest are the same as `Display`.
+    pub fn localize(&self, language: Language) -> String {
+        match self {
+            Error::Validation(message) => format!("{}: {}", validation_error_prefix(language), message.localize(language)),
+            error => error.to_string(),
         }
     }
 }
@@ -78,6 +131,9 @@ pub enum RepositoryError {
 
     #[error("Query canceled")]
     QueryCanceled,
+
+    #[error("Database unavailable: {0}")]
+    Unavailable(String),
 }
 
 impl RepositoryError {
@@ -88,6 +144,7 @@ impl RepositoryError {
             RepositoryError::Conflict => ErrorKind::Conflict,
             RepositoryError::Constraint(_) => ErrorKind::InvalidInput,
             RepositoryError::ReadOnly | RepositoryError::Database(_) | RepositoryError::QueryCanceled => ErrorKind::Internal,
+            RepositoryError::Unavailable(_) => ErrorKind::Unavailable,
         }
     }
 }
@@ -0,0 +1,160 @@
+//! Fault injection for resilience testing.
+//! Only compiled when the `fault-injection` feature is enabled.
+//!
+//! [`FaultyRepository`] wraps a [`Repository`] and fails a share of its
+//! transactions the way a struggling database does, so the handling of
+//! transient errors can be exercised without one.
+
+use std::{any::Any, sync::Arc, time::Instant};
+
+use rand::RngExt as _;
+
+use crate::{
+    Error, RepositoryError,
+    context::RequestContext,
+    repository::{Repository, Transaction},
+};
+
+/// Fails each begin and commit of a transaction with `error_probability`,
+/// with either [`RepositoryError::Unavailable`] or
+/// [`RepositoryError::Conflict`], the errors a caller may retry. A
+/// transaction failing to commit is rolled back, so nothing it wrote is
+/// kept.
+pub struct FaultyRepository {
+    inner: Arc<dyn Repository>,
+    error_probability: f64,
+}
+
+impl FaultyRepository {
+    /// `error_probability` is between 0, never failing, and 1, always
+    /// failing.
+    pub fn new(inner: Arc<dyn Repository>, error_probability: f64) -> Self {
+        Self { inner, error_probability }
+    }
+
+    /// Begins a transaction with `begin` unless the begin is failed, and
+    /// wraps it so its commit may fail too.
+    async fn begin_faulty(&self, begin: impl Future<Output = Result<Box<dyn Transaction>, Error>> + Send) -> Result<Box<dyn Transaction>, Error> {
+        if let Some(error) = injected_error(self.error_probability) {
+            return Err(error);
+        }
+        Ok(Box::new(FaultyTransaction {
+            inner: begin.await?,
+            error_probability: self.error_probability,
+        }))
+    }
+}
+
+/// A transient error the caller may retry, with `error_probability`, for
+/// injecting faults elsewhere too.
+pub fn injected_error(error_probability: f64) -> Option<Error> {
+    let mut rng = rand::rng();
+    if rng.random::<f64>() >= error_probability {
+        return None;
+    }
+    let error = if rng.random() {
+        RepositoryError::Unavailable("Injected fault".to_string())
+    } else {
+        RepositoryError::Conflict
+    };
+    tracing::debug!(%error, "Injecting repository fault");
+    Some(Error::RepositoryError(error))
+}
+
+#[async_trait::async_trait]
+impl Repository for FaultyRepository {
+    async fn begin_with_deadline(&self, deadline: Option<Instant>) -> Result<Box<dyn Transaction>, Error> {
+        self.begin_faulty(self.inner.begin_with_deadline(deadline)).await
+    }
+
+    async fn begin_read_only_with_deadline(&self, deadline: Option<Instant>) -> Result<Box<dyn Transaction>, Error> {
+        self.begin_faulty(self.inner.begin_read_only_with_deadline(deadline)).await
+    }
+
+    async fn begin_for_request(&self, context: &RequestContext) -> Result<Box<dyn Transaction>, Error> {
+        self.begin_faulty(self.inner.begin_for_request(context)).await
+    }
+
+    async fn begin_read_only_for_request(&self, context: &RequestContext) -> Result<Box<dyn Transaction>, Error> {
+        self.begin_faulty(self.inner.begin_read_only_for_request(context)).await
+    }
+
+    async fn stale_tables(&self) -> Result<Vec<String>, Error> {
+        self.inner.stale_tables().await
+    }
+
+    async fn close(&self) -> Result<(), Error> {
+        self.inner.close().await
+    }
+}
+
+struct FaultyTransaction {
+    inner: Box<dyn Transaction>,
+    error_probability: f64,
+}
+
+#[async_trait::async_trait]
+impl Transaction for FaultyTransaction {
+    /// The wrapped transaction, which is what the repository adapters
+    /// downcast to.
+    fn as_any(&self) -> &dyn Any {
+        self.inner.as_any()
+    }
+
+    async fn commit(self: Box<Self>) -> Result<(), Error> {
+        if let Some(error) = injected_error(self.error_probability) {
+            let _ = self.inner.rollback().await;
+            return Err(error);
+        }
+        self.inner.commit().await
+    }
+
+    async fn rollback(self: Box<Self>) -> Result<(), Error> {
+        self.inner.rollback().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::FaultyRepository;
+    use crate::{
+        Error, ErrorKind,
+        context::RequestContext,
+        repository::Repository,
+        test_support::{MockRepository, MockSessionRepository, MockUserRepository, MockWebhookRepository, mock_repository_service},
+    };
+
+    // ===================
+    // Helpers
+    // ===================
+    /// Runs a transaction that does nothing through a repository failing
+    /// with `error_probability`.
+    async fn run_transaction(error_probability: f64) -> Result<(), Error> {
+        let repository = FaultyRepository::new(Arc::new(MockRepository), error_probability);
+        let repository_service = mock_repository_service(MockUserRepository::new(), MockSessionRepository::new(), MockWebhookRepository::new())
+            .repository(Arc::new(repository) as Arc<dyn Repository>)
+            .build()
+            .unwrap();
+
+        repository_service.execute(&RequestContext::internal(), async |_| Ok(())).await
+    }
+
+    // ===================
+    // Tests: FaultyRepository
+    // ===================
+    #[tokio::test]
+    async fn test_faulty_repository_passes_through_without_faults() {
+        assert!(run_transaction(0.0).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_faulty_repository_fails_with_transient_errors() {
+        for _ in 0..10 {
+            let error = run_transaction(1.0).await.unwrap_err();
+
+            assert!(matches!(error.kind(), ErrorKind::Unavailable | ErrorKind::Conflict), "{error:?}");
+        }
+    }
+}
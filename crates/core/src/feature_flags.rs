@@ -0,0 +1,169 @@
+//! Feature flags for rolling out behaviour gradually.
+//!
+//! Flags are named in `snake_case`. A flag that has never been configured is
+//! off.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::RwLock,
+};
+
+/// A flag's effective state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeatureFlag {
+    pub name: String,
+    pub enabled: bool,
+    /// True when the value comes from a runtime override rather than
+    /// configuration.
+    pub overridden: bool,
+}
+
+pub trait FeatureFlags: Send + Sync {
+    fn is_enabled(&self, flag: &str) -> bool;
+
+    /// Lists every configured or overridden flag, ordered by name.
+    fn list(&self) -> Vec<FeatureFlag>;
+
+    /// Forces `flag` on or off until the override is cleared.
+    fn set_override(&self, flag: &str, enabled: bool);
+
+    /// Reverts `flag` to its configured value, returning whether an override
+    /// was removed.
+    fn clear_override(&self, flag: &str) -> bool;
+}
+
+/// Flags seeded from configuration, with runtime overrides kept in memory.
+/// Overrides are lost on restart.
+#[derive(Debug, Default)]
+pub struct InMemoryFeatureFlags {
+    defaults: HashMap<String, bool>,
+    overrides: RwLock<HashMap<String, bool>>,
+}
+
+impl InMemoryFeatureFlags {
+    pub fn new(defaults: HashMap<String, bool>) -> Self {
+        Self {
+            defaults,
+            overrides: RwLock::default(),
+        }
+    }
+}
+
+impl FeatureFlags for InMemoryFeatureFlags {
+    fn is_enabled(&self, flag: &str) -> bool {
+        let overrides = self.overrides.read().unwrap_or_else(|e| e.into_inner());
+        overrides.get(flag).or_else(|| self.defaults.get(flag)).copied().unwrap_or(false)
+    }
+
+    fn list(&self) -> Vec<FeatureFlag> {
+        let overrides = self.overrides.read().unwrap_or_else(|e| e.into_inner());
+        let mut flags: BTreeMap<&str, FeatureFlag> = BTreeMap::new();
+        for (name, enabled) in &self.defaults {
+            flags.insert(
+                name,
+                FeatureFlag {
+                    name: name.clone(),
+                    enabled: *enabled,
+                    overridden: false,
+                },
+            );
+        }
+        for (name, enabled) in overrides.iter() {
+            flags.insert(
+                name,
+                FeatureFlag {
+                    name: name.clone(),
+                    enabled: *enabled,
+                    overridden: true,
+                },
+            );
+        }
+
+        flags.into_values().collect()
+    }
+
+    fn set_override(&self, flag: &str, enabled: bool) {
+        tracing::info!(flag, enabled, "Feature flag overridden");
+        self.overrides.write().unwrap_or_else(|e| e.into_inner()).insert(flag.to_string(), enabled);
+    }
+
+    fn clear_override(&self, flag: &str) -> bool {
+        let removed = self.overrides.write().unwrap_or_else(|e| e.into_inner()).remove(flag).is_some();
+        if removed {
+            tracing::info!(flag, "Feature flag override cleared");
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{FeatureFlag, FeatureFlags, InMemoryFeatureFlags};
+
+    fn flags() -> InMemoryFeatureFlags {
+        InMemoryFeatureFlags::new(HashMap::from([("soft_delete".to_string(), true), ("new_pagination".to_string(), false)]))
+    }
+
+    // ===================
+    // Tests: is_enabled
+    // ===================
+
+    #[test]
+    fn test_configured_flags() {
+        let flags = flags();
+
+        assert!(flags.is_enabled("soft_delete"));
+        assert!(!flags.is_enabled("new_pagination"));
+    }
+
+    #[test]
+    fn test_unknown_flag_is_disabled() {
+        assert!(!flags().is_enabled("unknown"));
+    }
+
+    // ===================
+    // Tests: overrides
+    // ===================
+
+    #[test]
+    fn test_override_wins_until_cleared() {
+        let flags = flags();
+
+        flags.set_override("soft_delete", false);
+        assert!(!flags.is_enabled("soft_delete"));
+
+        assert!(flags.clear_override("soft_delete"));
+        assert!(flags.is_enabled("soft_delete"));
+        assert!(!flags.clear_override("soft_delete"));
+    }
+
+    #[test]
+    fn test_list_is_sorted_and_marks_overrides() {
+        let flags = flags();
+        flags.set_override("beta", true);
+        flags.set_override("new_pagination", true);
+
+        assert_eq!(
+            flags.list(),
+            vec![
+                FeatureFlag {
+                    name: "beta".into(),
+                    enabled: true,
+                    overridden: true,
+                },
+                FeatureFlag {
+                    name: "new_pagination".into(),
+                    enabled: true,
+                    overridden: true,
+                },
+                FeatureFlag {
+                    name: "soft_delete".into(),
+                    enabled: true,
+                    overridden: false,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Message catalogs for user-facing validation errors.
+//!
+//! Validation failures carry a [`ValidationMessage`] rather than finished
+//! text, so adapters can render them in the caller's language. `Display`
+//! always gives English, which is what logs and traces use.
+
+use std::fmt;
+
+/// Languages with a message catalog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+}
+
+impl Language {
+    /// Every language with a catalog, English first.
+    pub const ALL: [Language; 2] = [Language::English, Language::German];
+
+    /// ISO 639-1 code, as used in `Content-Language`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+        }
+    }
+
+    /// Matches a BCP 47 tag such as `de-CH` by its primary language subtag.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.split(['-', '_']).next()?;
+        Self::ALL.into_iter().find(|language| language.code().eq_ignore_ascii_case(primary))
+    }
+}
+
+/// Picks the tag from an `Accept-Language` value with the highest weight
+/// that has a catalog. Tags with equal weight keep their order, and `q=0`
+/// excludes a tag. `None` if nothing matches, so callers fall back to
+/// English.
+pub fn negotiate_locale(accept_language: &str) -> Option<String> {
+    let mut ranges: Vec<(&str, f32)> = accept_language
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let tag = parts.next()?.trim();
+            let weight = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (!tag.is_empty() && weight > 0.0).then_some((tag, weight))
+        })
+        .collect();
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    ranges
+        .into_iter()
+        .map(|(tag, _)| tag)
+        .find(|tag| Language::from_tag(tag).is_some())
+        .map(str::to_string)
+}
+
+/// Why a value failed validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationMessage {
+    InvalidEmail {
+        email: String,
+    },
+    AgeOutOfRange {
+        min: i16,
+        max: i16,
+        age: i16,
+    },
+    UnsupportedAvatarType {
+        content_type: String,
+    },
+    AvatarTooLarge {
+        max_bytes: usize,
+    },
+    /// The declared content type does not match the image's contents.
+    AvatarTypeMismatch {
+        declared: String,
+        detected: String,
+    },
+    BlankName,
+    /// Free-form text from an adapter, which has no translations.
+    Other(String),
+}
+
+impl ValidationMessage {
+    /// Renders the message from the catalog of `language`.
+    pub fn localize(&self, language: Language) -> String {
+        match (self, language) {
+            (ValidationMessage::InvalidEmail { email }, Language::English) => format!("Invalid email format: {email}"),
+            (ValidationMessage::InvalidEmail { email }, Language::German) => format!("Ungültiges E-Mail-Format: {email}"),
+            (ValidationMessage::AgeOutOfRange { min, max, age }, Language::English) => {
+                format!("Age must be between {min} and {max}, got {age}")
+            }
+            (ValidationMessage::AgeOutOfRange { min, max, age }, Language::German) => {
+                format!("Das Alter muss zwischen {min} und {max} liegen, erhalten: {age}")
+            }
+            (ValidationMessage::UnsupportedAvatarType { content_type }, Language::English) => {
+                format!("Avatars must be PNG, JPEG, GIF or WebP images, got {content_type}")
+            }
+            (ValidationMessage::UnsupportedAvatarType { content_type }, Language::German) => {
+                format!("Avatare müssen PNG-, JPEG-, GIF- oder WebP-Bilder sein, erhalten: {content_type}")
+            }
+            (ValidationMessage::AvatarTooLarge { max_bytes }, Language::English) => format!("Avatars may be at most {max_bytes} bytes"),
+            (ValidationMessage::AvatarTooLarge { max_bytes }, Language::German) => format!("Avatare dürfen höchstens {max_bytes} Bytes groß sein"),
+            (ValidationMessage::AvatarTypeMismatch { declared, detected }, Language::English) => {
+                format!("Avatar was sent as {declared} but contains {detected}")
+            }
+            (ValidationMessage::AvatarTypeMismatch { declared, detected }, Language::German) => {
+                format!("Avatar wurde als {declared} gesendet, enthält aber {detected}")
+            }
+            (ValidationMessage::BlankName, Language::English) => "Name must not be blank".to_string(),
+            (ValidationMessage::BlankName, Language::German) => "Der Name darf nicht leer sein".to_string(),
+            (ValidationMessage::Other(message), _) => message.clone(),
+        }
+    }
+}
+
+/// Prefix of a rendered validation error, e.g. `Validation error: ...`.
+pub(crate) fn validation_error_prefix(language: Language) -> &'static str {
+    match language {
+        Language::English => "Validation error",
+        Language::German => "Validierungsfehler",
+    }
+}
+
+impl fmt::Display for ValidationMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.localize(Language::English))
+    }
+}
+
+impl From<String> for ValidationMessage {
+    fn from(message: String) -> Self {
+        ValidationMessage::Other(message)
+    }
+}
+
+impl From<&str> for ValidationMessage {
+    fn from(message: &str) -> Self {
+        ValidationMessage::Other(message.to_string())
+    }
+}
+
+impl PartialEq<&str> for ValidationMessage {
+    fn eq(&self, other: &&str) -> bool {
+        self.localize(Language::English) == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Language, ValidationMessage, negotiate_locale};
+    use crate::Error;
+
+    // ===================
+    // Tests: Language
+    // ===================
+
+    #[test]
+    fn test_from_tag_matches_primary_subtag() {
+        assert_eq!(Language::from_tag("de-CH"), Some(Language::German));
+        assert_eq!(Language::from_tag("EN_us"), Some(Language::English));
+        assert_eq!(Language::from_tag("fr"), None);
+    }
+
+    // ===================
+    // Tests: negotiate_locale
+    // ===================
+
+    #[test]
+    fn test_negotiate_locale_prefers_highest_weight() {
+        assert_eq!(negotiate_locale("en;q=0.5, de-CH").as_deref(), Some("de-CH"));
+        assert_eq!(negotiate_locale("en-US,en;q=0.5").as_deref(), Some("en-US"));
+    }
+
+    #[test]
+    fn test_negotiate_locale_skips_unsupported_and_excluded() {
+        assert_eq!(negotiate_locale("fr-FR, de;q=0.8").as_deref(), Some("de"));
+        assert_eq!(negotiate_locale("de;q=0, fr"), None);
+        assert_eq!(negotiate_locale("*"), None);
+    }
+
+    // ===================
+    // Tests: ValidationMessage
+    // ===================
+
+    #[test]
+    fn test_localize() {
+        let message = ValidationMessage::AgeOutOfRange { min: 0, max: 150, age: 200 };
+
+        assert_eq!(message.to_string(), "Age must be between 0 and 150, got 200");
+        assert_eq!(message.localize(Language::German), "Das Alter muss zwischen 0 und 150 liegen, erhalten: 200");
+    }
+
+    #[test]
+    fn test_localize_validation_error() {
+        let error = Error::Validation(ValidationMessage::InvalidEmail { email: "nobody".into() });
+
+        assert_eq!(error.to_string(), "Validation error: Invalid email format: nobody");
+        assert_eq!(error.localize(Language::German), "Validierungsfehler: Ungültiges E-Mail-Format: nobody");
+    }
+
+    #[test]
+    fn test_other_is_not_translated() {
+        let message = ValidationMessage::from("name cannot be cleared");
+
+        assert_eq!(message.localize(Language::German), "name cannot be cleared");
+        assert!(message == "name cannot be cleared");
+    }
+}
@@ -0,0 +1,24 @@
+//! Port for decoding and resizing images, such as avatar variants.
+//!
+//! The adapter lives in `hex-play-media`. Calls are CPU bound, so callers run
+//! them on a blocking thread.
+
+use crate::Error;
+
+pub trait ImageProcessor: Send + Sync {
+    /// Scales `image` down to fit within `max_dimension` on both sides,
+    /// keeping its aspect ratio, and encodes the result as PNG. Images that
+    /// already fit are re-encoded at their size.
+    fn resize(&self, image: &[u8], max_dimension: u32) -> Result<Vec<u8>, Error>;
+}
+
+/// Processor for deployments without one configured. Every call fails with
+/// `Error::ImageProcessing`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoImageProcessor;
+
+impl ImageProcessor for NoImageProcessor {
+    fn resize(&self, _image: &[u8], _max_dimension: u32) -> Result<Vec<u8>, Error> {
+        Err(Error::ImageProcessing("image processing is not configured".into()))
+    }
+}
@@ -0,0 +1,176 @@
+//! Background jobs: work a request hands off so it can respond before the
+//! work is done.
+//!
+//! Services enqueue [`Job`]s through the [`JobQueue`] port and
+//! [`CoreServices::run_job`](crate::CoreServices::run_job) carries them out.
+//! [`job_channel`] provides an in-process queue; the server drains its
+//! [`JobReceiver`] in a subsystem of its own. Jobs still queued when the
+//! process exits are lost, so each job must leave things usable if it never
+//! runs.
+
+use tokio::sync::mpsc;
+
+use crate::{Error, status::Health, user::UserId};
+
+/// A unit of background work.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Job {
+    /// Generates the resized variants of the avatar stored under `key`.
+    ProcessAvatar { user_id: UserId, key: String },
+    /// Recomputes the signup rollups of yesterday and today. Yesterday is
+    /// included so signups just before midnight are counted once its day
+    /// has closed.
+    RefreshSignupRollups,
+}
+
+impl Job {
+    /// Short name for logs and metrics.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Job::ProcessAvatar { .. } => "process_avatar",
+            Job::RefreshSignupRollups => "refresh_signup_rollups",
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait JobQueue: Send + Sync {
+    /// Queues `job` to run later. Returns once it is queued, not run.
+    async fn enqueue(&self, job: Job) -> Result<(), Error>;
+
+    /// Whether jobs queued now would be run. Defaults to up.
+    fn health(&self) -> Health {
+        Health::Up
+    }
+}
+
+/// Queue for deployments without background jobs. Every call fails with
+/// `Error::Job`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoJobQueue;
+
+#[async_trait::async_trait]
+impl JobQueue for NoJobQueue {
+    async fn enqueue(&self, _job: Job) -> Result<(), Error> {
+        Err(Error::Job("background jobs are not configured".into()))
+    }
+
+    fn health(&self) -> Health {
+        Health::Disabled
+    }
+}
+
+/// Creates an in-process queue and the receiver its jobs arrive on.
+pub fn job_channel() -> (ChannelJobQueue, JobReceiver) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    (ChannelJobQueue { sender }, JobReceiver { receiver })
+}
+
+/// The sending half of [`job_channel`].
+#[derive(Debug, Clone)]
+pub struct ChannelJobQueue {
+    sender: mpsc::UnboundedSender<Job>,
+}
+
+#[async_trait::async_trait]
+impl JobQueue for ChannelJobQueue {
+    async fn enqueue(&self, job: Job) -> Result<(), Error> {
+        self.sender.send(job).map_err(|_| Error::Job("job receiver has shut down".into()))
+    }
+
+    fn health(&self) -> Health {
+        if self.sender.is_closed() { Health::Down } else { Health::Up }
+    }
+}
+
+/// The receiving half of [`job_channel`].
+#[derive(Debug)]
+pub struct JobReceiver {
+    receiver: mpsc::UnboundedReceiver<Job>,
+}
+
+impl JobReceiver {
+    /// The next queued job, waiting for one if there is none. `None` once
+    /// every queue has been dropped.
+    pub async fn recv(&mut self) -> Option<Job> {
+        self.receiver.recv().await
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+pub use recording::RecordingJobQueue;
+
+#[cfg(any(test, feature = "test-support"))]
+mod recording {
+    use std::sync::Mutex;
+
+    use super::{Job, JobQueue};
+    use crate::Error;
+
+    /// Queue that keeps every job instead of running it. Only available in
+    /// test builds.
+    #[derive(Debug, Default)]
+    pub struct RecordingJobQueue {
+        jobs: Mutex<Vec<Job>>,
+    }
+
+    impl RecordingJobQueue {
+        /// Jobs enqueued so far, in order.
+        pub fn jobs(&self) -> Vec<Job> {
+            self.jobs.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl JobQueue for RecordingJobQueue {
+        async fn enqueue(&self, job: Job) -> Result<(), Error> {
+            self.jobs.lock().unwrap().push(job);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Job, JobQueue, job_channel};
+    use crate::{Error, user::UserId};
+
+    // ===================
+    // Tests: job_channel
+    // ===================
+    #[tokio::test]
+    async fn test_job_channel_delivers_in_order() {
+        let (queue, mut receiver) = job_channel();
+        let first = Job::ProcessAvatar {
+            user_id: UserId::new(1),
+            key: "avatars/a.png".into(),
+        };
+        let second = Job::ProcessAvatar {
+            user_id: UserId::new(2),
+            key: "avatars/b.png".into(),
+        };
+
+        queue.enqueue(first.clone()).await.unwrap();
+        queue.enqueue(second.clone()).await.unwrap();
+        drop(queue);
+
+        assert_eq!(receiver.recv().await, Some(first));
+        assert_eq!(receiver.recv().await, Some(second));
+        assert_eq!(receiver.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_job_channel_fails_after_receiver_dropped() {
+        let (queue, receiver) = job_channel();
+        drop(receiver);
+
+        let result = queue
+            .enqueue(Job::ProcessAvatar {
+                user_id: UserId::new(1),
+                key: "avatars/a.png".into(),
+            })
+            .await;
+
+        assert!(matches!(result, Err(Error::Job(_))));
+    }
+}
@@ -0,0 +1,194 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::TimeDelta;
+
+use crate::lease::{Lease, LeaseService};
+
+/// Elects one of the instances sharing the database to run a singleton
+/// task, such as refreshing the signup rollups. Every instance heartbeats
+/// the same lease; the one holding it leads until it stops heartbeating for
+/// `ttl`, when another takes over with a higher fencing token.
+pub struct LeaderElection {
+    lease_service: Arc<dyn LeaseService>,
+    name: String,
+    ttl: TimeDelta,
+    leadership: Mutex<Option<Lease>>,
+}
+
+impl LeaderElection {
+    pub fn new(lease_service: Arc<dyn LeaseService>, name: impl Into<String>, ttl: TimeDelta) -> Self {
+        Self {
+            lease_service,
+            name: name.into(),
+            ttl,
+            leadership: Mutex::new(None),
+        }
+    }
+
+    /// Renews the lease, or takes it if it is free. Returns the fencing
+    /// token while this instance leads.
+    ///
+    /// An instance that cannot reach the database steps down, as it cannot
+    /// tell whether its lease is still good.
+    #[tracing::instrument(level = "trace", skip(self), fields(name = %self.name))]
+    pub async fn heartbeat(&self) -> Option<i64> {
+        let lease = match self.lease_service.acquire(&self.name, self.ttl).await {
+            Ok(lease) => lease,
+            Err(error) => {
+                tracing::warn!(name = self.name, %error, "Couldn't renew leadership");
+                None
+            }
+        };
+
+        let mut leadership = self.leadership.lock().unwrap();
+        match (leadership.as_ref(), lease.as_ref()) {
+            (None, Some(lease)) => tracing::info!(name = self.name, fencing_token = lease.fencing_token, "Gained leadership"),
+            (Some(held), None) => tracing::warn!(name = self.name, fencing_token = held.fencing_token, "Lost leadership"),
+            (Some(held), Some(lease)) if held.fencing_token != lease.fencing_token => {
+                tracing::warn!(name = self.name, fencing_token = lease.fencing_token, "Regained leadership after losing it")
+            }
+            _ => {}
+        }
+        *leadership = lease;
+
+        leadership.as_ref().map(|lease| lease.fencing_token)
+    }
+
+    /// Whether this instance led as of the last heartbeat.
+    pub fn is_leader(&self) -> bool {
+        self.leadership.lock().unwrap().is_some()
+    }
+
+    /// Steps down, so another instance may lead from its next heartbeat
+    /// instead of once the lease expires.
+    #[tracing::instrument(level = "trace", skip(self), fields(name = %self.name))]
+    pub async fn resign(&self) {
+        if self.leadership.lock().unwrap().take().is_none() {
+            return;
+        }
+        if let Err(error) = self.lease_service.release(&self.name).await {
+            tracing::warn!(name = self.name, %error, "Couldn't release leadership");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::TimeDelta;
+    use mockall::{Sequence, predicate::eq};
+
+    use super::LeaderElection;
+    use crate::{
+        Error,
+        clock::FixedClock,
+        lease::{Lease, MockLeaseService},
+    };
+
+    // ===================
+    // Helpers
+    // ===================
+    fn lease(fencing_token: i64) -> Lease {
+        Lease {
+            name: "rollups".to_string(),
+            holder: "LH_me".to_string(),
+            fencing_token,
+            expires_at: FixedClock::epoch() + TimeDelta::seconds(30),
+        }
+    }
+
+    fn create_election(lease_service: MockLeaseService) -> LeaderElection {
+        LeaderElection::new(Arc::new(lease_service), "rollups", TimeDelta::seconds(30))
+    }
+
+    // ===================
+    // Tests: heartbeat
+    // ===================
+    #[tokio::test]
+    async fn test_heartbeat_leads_with_fencing_token() {
+        let mut lease_service = MockLeaseService::new();
+        lease_service
+            .expect_acquire()
+            .with(eq("rollups"), eq(TimeDelta::seconds(30)))
+            .times(1)
+            .returning(|_, _| Ok(Some(lease(3))));
+        let election = create_election(lease_service);
+
+        assert_eq!(election.heartbeat().await, Some(3));
+        assert!(election.is_leader());
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_follows_while_another_leads() {
+        let mut lease_service = MockLeaseService::new();
+        lease_service.expect_acquire().returning(|_, _| Ok(None));
+        let election = create_election(lease_service);
+
+        assert_eq!(election.heartbeat().await, None);
+        assert!(!election.is_leader());
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_steps_down_when_lease_is_lost() {
+        let mut seq = Sequence::new();
+        let mut lease_service = MockLeaseService::new();
+        lease_service
+            .expect_acquire()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok(Some(lease(1))));
+        lease_service.expect_acquire().times(1).in_sequence(&mut seq).returning(|_, _| Ok(None));
+        let election = create_election(lease_service);
+
+        election.heartbeat().await;
+        assert_eq!(election.heartbeat().await, None);
+        assert!(!election.is_leader());
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_steps_down_when_database_is_unreachable() {
+        let mut seq = Sequence::new();
+        let mut lease_service = MockLeaseService::new();
+        lease_service
+            .expect_acquire()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok(Some(lease(1))));
+        lease_service
+            .expect_acquire()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Err(Error::Infrastructure("database unreachable".into())));
+        let election = create_election(lease_service);
+
+        election.heartbeat().await;
+        assert_eq!(election.heartbeat().await, None);
+        assert!(!election.is_leader());
+    }
+
+    // ===================
+    // Tests: resign
+    // ===================
+    #[tokio::test]
+    async fn test_resign_releases_lease_when_leading() {
+        let mut lease_service = MockLeaseService::new();
+        lease_service.expect_acquire().returning(|_, _| Ok(Some(lease(1))));
+        lease_service.expect_release().with(eq("rollups")).times(1).returning(|_| Ok(()));
+        let election = create_election(lease_service);
+
+        election.heartbeat().await;
+        election.resign().await;
+
+        assert!(!election.is_leader());
+    }
+
+    #[tokio::test]
+    async fn test_resign_does_nothing_when_following() {
+        let mut lease_service = MockLeaseService::new();
+        lease_service.expect_release().never();
+        let election = create_election(lease_service);
+
+        election.resign().await;
+    }
+}
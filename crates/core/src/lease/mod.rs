@@ -0,0 +1,21 @@
+//! Leases that let one of several server instances at a time do work that
+//! must not be done twice, such as enqueuing a scheduled job.
+//!
+//! A lease is held by one [`LeaseHolder`] until it expires. The holder keeps
+//! it by acquiring it again before then; once it has expired, e.g. because
+//! its holder crashed, any instance may take it over, with a higher
+//! fencing token. [`LeaderElection`] builds on a lease to have one instance
+//! run a singleton task.
+
+pub mod leader;
+pub mod model;
+pub mod repository;
+pub mod service;
+
+pub use leader::LeaderElection;
+pub use model::{Lease, LeaseHolder};
+pub use repository::LeaseRepository;
+pub use service::LeaseService;
+pub(crate) use service::LeaseServiceImpl;
+#[cfg(any(test, feature = "test-support"))]
+pub use {repository::MockLeaseRepository, service::MockLeaseService};
@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use hex_play_utils::{define_token_prefix, token::Token};
+
+define_token_prefix!(LeaseHolderPrefix, "LH_");
+/// Identifies the server instance holding a lease. Each process generates
+/// its own, so a restarted instance does not inherit the leases it held.
+pub type LeaseHolder = Token<LeaseHolderPrefix, u128, { u128::MAX }>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lease {
+    pub name: String,
+    pub holder: String,
+    /// Goes up by one every time the lease changes hands and stays the same
+    /// while the holder renews it, so work stamped with a lower token was
+    /// done by a holder that has since lost the lease.
+    pub fencing_token: i64,
+    pub expires_at: DateTime<Utc>,
+}
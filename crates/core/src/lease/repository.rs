@@ -0,0 +1,25 @@
+use chrono::{DateTime, Utc};
+
+use crate::{Error, lease::Lease, repository::Transaction};
+
+#[cfg_attr(any(test, feature = "test-support"), mockall::automock, allow(unused_parens))]
+#[async_trait::async_trait]
+pub trait LeaseRepository: Send + Sync {
+    /// Makes `holder` the holder of the lease `name` until `expires_at` if
+    /// the lease is free, has expired by `now` or is held by `holder`
+    /// already. Returns the lease if `holder` holds it now.
+    async fn try_acquire(
+        &self,
+        transaction: &(dyn Transaction + 'static),
+        name: &str,
+        holder: &str,
+        now: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<Lease>, Error>;
+    /// Frees the lease `name` if `holder` holds it, by letting it expire at
+    /// `now`; its fencing token is kept for the next holder to go past.
+    /// Returns whether it did.
+    async fn release(&self, transaction: &(dyn Transaction + 'static), name: &str, holder: &str, now: DateTime<Utc>) -> Result<bool, Error>;
+    /// Lists the leases that have not expired by `now`, by name.
+    async fn list_active(&self, transaction: &(dyn Transaction + 'static), now: DateTime<Utc>) -> Result<Vec<Lease>, Error>;
+}
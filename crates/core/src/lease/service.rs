@@ -0,0 +1,196 @@
+use std::sync::Arc;
+
+use chrono::TimeDelta;
+
+use crate::{
+    Error,
+    context::RequestContext,
+    lease::{Lease, LeaseHolder},
+    repository::RepositoryService,
+};
+
+/// Leases taken as this server instance.
+#[cfg_attr(any(test, feature = "test-support"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait LeaseService: Send + Sync {
+    /// The holder this instance takes leases as.
+    fn holder(&self) -> LeaseHolder;
+
+    /// Takes the lease `name` for `ttl`, or extends it by `ttl` from now if
+    /// this instance holds it already. Returns `None` while another
+    /// instance holds it.
+    async fn acquire(&self, name: &str, ttl: TimeDelta) -> Result<Option<Lease>, Error>;
+
+    /// Gives up the lease `name` before it expires, so another instance may
+    /// take it at once. Does nothing unless this instance holds it.
+    async fn release(&self, name: &str) -> Result<(), Error>;
+
+    /// Lists the leases held by any instance, by name.
+    async fn list(&self) -> Result<Vec<Lease>, Error>;
+}
+
+pub(crate) struct LeaseServiceImpl {
+    repository_service: Arc<RepositoryService>,
+    holder: LeaseHolder,
+}
+
+impl LeaseServiceImpl {
+    pub(crate) fn new(repository_service: Arc<RepositoryService>) -> Self {
+        Self {
+            repository_service,
+            holder: LeaseHolder::generate(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LeaseService for LeaseServiceImpl {
+    fn holder(&self) -> LeaseHolder {
+        self.holder
+    }
+
+    #[tracing::instrument(level = "trace", skip(self), fields(holder = %self.holder))]
+    async fn acquire(&self, name: &str, ttl: TimeDelta) -> Result<Option<Lease>, Error> {
+        let now = self.repository_service.clock().now();
+        let holder = self.holder.to_string();
+        self.repository_service
+            .execute(&RequestContext::internal(), async |uow| {
+                uow.leases().try_acquire(uow.tx(), name, &holder, now, now + ttl).await
+            })
+            .await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self), fields(holder = %self.holder))]
+    async fn release(&self, name: &str) -> Result<(), Error> {
+        let now = self.repository_service.clock().now();
+        let holder = self.holder.to_string();
+        self.repository_service
+            .execute(&RequestContext::internal(), async |uow| {
+                uow.leases().release(uow.tx(), name, &holder, now).await.map(|_| ())
+            })
+            .await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn list(&self) -> Result<Vec<Lease>, Error> {
+        let now = self.repository_service.clock().now();
+        self.repository_service
+            .execute(&RequestContext::internal(), async |uow| uow.leases().list_active(uow.tx(), now).await)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::TimeDelta;
+    use mockall::predicate::{always, eq};
+
+    use super::{LeaseService, LeaseServiceImpl};
+    use crate::{
+        clock::{Clock, FixedClock},
+        lease::{Lease, LeaseRepository},
+        test_support::{MockLeaseRepository, MockSessionRepository, MockUserRepository, MockWebhookRepository, mock_repository_service},
+    };
+
+    // ===================
+    // Helpers
+    // ===================
+    fn create_service(lease_repository: MockLeaseRepository) -> LeaseServiceImpl {
+        let repository_service = mock_repository_service(MockUserRepository::new(), MockSessionRepository::new(), MockWebhookRepository::new())
+            .lease_repository(Arc::new(lease_repository) as Arc<dyn LeaseRepository>)
+            .clock(Arc::new(FixedClock::default()) as Arc<dyn Clock>)
+            .build()
+            .unwrap();
+        LeaseServiceImpl::new(Arc::new(repository_service))
+    }
+
+    fn lease(holder: &str) -> Lease {
+        Lease {
+            name: "job".to_string(),
+            holder: holder.to_string(),
+            fencing_token: 1,
+            expires_at: FixedClock::epoch() + TimeDelta::seconds(30),
+        }
+    }
+
+    // ===================
+    // Tests: acquire
+    // ===================
+    #[tokio::test]
+    async fn test_acquire_takes_lease_until_ttl_from_now() {
+        let now = FixedClock::epoch();
+        let mut lease_repository = MockLeaseRepository::new();
+        lease_repository
+            .expect_try_acquire()
+            .with(always(), eq("job"), always(), eq(now), eq(now + TimeDelta::seconds(30)))
+            .times(1)
+            .returning(|_, _, holder, _, _| Ok(Some(lease(holder))));
+        let service = create_service(lease_repository);
+
+        let lease = service.acquire("job", TimeDelta::seconds(30)).await.unwrap().unwrap();
+
+        assert_eq!(lease.holder, service.holder().to_string());
+    }
+
+    #[tokio::test]
+    async fn test_acquire_renews_as_the_same_holder() {
+        let holders = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = holders.clone();
+        let mut lease_repository = MockLeaseRepository::new();
+        lease_repository.expect_try_acquire().times(2).returning(move |_, _, holder, _, _| {
+            seen.lock().unwrap().push(holder.to_string());
+            Ok(Some(lease(holder)))
+        });
+        let service = create_service(lease_repository);
+
+        service.acquire("job", TimeDelta::seconds(30)).await.unwrap();
+        service.acquire("job", TimeDelta::seconds(30)).await.unwrap();
+
+        let holders = holders.lock().unwrap();
+        assert_eq!(holders[0], holders[1]);
+        assert!(holders[0].starts_with("LH_"));
+    }
+
+    #[tokio::test]
+    async fn test_acquire_reports_lease_held_elsewhere() {
+        let mut lease_repository = MockLeaseRepository::new();
+        lease_repository.expect_try_acquire().returning(|_, _, _, _, _| Ok(None));
+        let service = create_service(lease_repository);
+
+        assert_eq!(service.acquire("job", TimeDelta::seconds(30)).await.unwrap(), None);
+    }
+
+    // ===================
+    // Tests: release
+    // ===================
+    #[tokio::test]
+    async fn test_release_frees_lease() {
+        let mut lease_repository = MockLeaseRepository::new();
+        lease_repository
+            .expect_release()
+            .with(always(), eq("job"), always(), eq(FixedClock::epoch()))
+            .times(1)
+            .return_const(Ok(true));
+        let service = create_service(lease_repository);
+
+        service.release("job").await.unwrap();
+    }
+
+    // ===================
+    // Tests: list
+    // ===================
+    #[tokio::test]
+    async fn test_list_returns_leases_active_now() {
+        let mut lease_repository = MockLeaseRepository::new();
+        lease_repository
+            .expect_list_active()
+            .with(always(), eq(FixedClock::epoch()))
+            .times(1)
+            .returning(|_, _| Ok(vec![lease("LH_other")]));
+        let service = create_service(lease_repository);
+
+        assert_eq!(service.list().await.unwrap(), [lease("LH_other")]);
+    }
+}
@@ -1,39 +1,173 @@
+pub mod activity;
+pub mod avatar;
+pub mod cache;
+pub mod clock;
+pub mod context;
+pub mod domain;
 pub mod error;
+#[cfg(feature = "fault-injection")]
+pub mod fault;
+pub mod feature_flags;
+pub mod i18n;
+pub mod image;
+pub mod jobs;
+pub mod lease;
+pub mod log_filter;
+pub mod maintenance;
+pub mod reporting;
 pub mod repository;
 pub mod session;
+pub mod specification;
+pub mod status;
+pub mod storage;
 pub mod types;
 pub mod user;
+pub mod user_info;
+pub mod webhook;
 
-#[cfg(feature = "test-support")]
+#[cfg(any(test, feature = "test-support"))]
 pub mod test_support;
 
 use std::sync::Arc;
 
+use chrono::Days;
 pub use error::{Error, ErrorKind, RepositoryError};
 
 use crate::{
+    activity::{ActivityService, ActivityServiceImpl},
+    avatar::{AvatarService, AvatarServiceImpl},
+    cache::QueryCache,
+    clock::Clock,
+    context::RequestContext,
+    feature_flags::{FeatureFlags, InMemoryFeatureFlags},
+    image::{ImageProcessor, NoImageProcessor},
+    jobs::{Job, JobQueue, NoJobQueue},
+    lease::{LeaseService, LeaseServiceImpl},
+    log_filter::{LogFilter, NoLogFilter},
+    maintenance::MaintenanceMode,
+    reporting::{ErrorReporter, NoErrorReporter},
     repository::RepositoryService,
     session::{SessionService, SessionServiceImpl},
-    user::{UserService, UserServiceImpl},
+    status::{StatusService, StatusServiceImpl, Subsystems},
+    storage::{NoObjectStorage, ObjectStorage},
+    user::{NoUserDirectory, PageSizeCap, User, UserDirectoryPort, UserService, UserServiceImpl},
+    user_info::{UserInfoService, UserInfoServiceImpl},
+    webhook::{WebhookService, WebhookServiceImpl},
 };
 
 pub struct CoreServices {
     pub user_service: Arc<dyn UserService>,
+    pub user_info_service: Arc<dyn UserInfoService>,
     pub session_service: Arc<dyn SessionService>,
+    pub avatar_service: Arc<dyn AvatarService>,
+    pub activity_service: Arc<dyn ActivityService>,
+    pub webhook_service: Arc<dyn WebhookService>,
+    pub lease_service: Arc<dyn LeaseService>,
+    pub status_service: Arc<dyn StatusService>,
+    pub clock: Arc<dyn Clock>,
+    pub maintenance_mode: MaintenanceMode,
+    pub page_size_cap: PageSizeCap,
+    pub user_list_cache: QueryCache<Vec<User>>,
+    pub feature_flags: Arc<dyn FeatureFlags>,
+    pub error_reporter: Arc<dyn ErrorReporter>,
+    pub log_filter: Arc<dyn LogFilter>,
+    /// States of the server subsystems, which the server records and the
+    /// status reports.
+    pub subsystems: Subsystems,
+}
+
+/// Outbound adapters the core services use besides the repositories. Each
+/// defaults to one that fails every call, so features depending on it are
+/// unavailable until it is configured, except the user directory, which
+/// defaults to one knowing no one.
+#[derive(Clone)]
+pub struct CoreAdapters {
+    pub object_storage: Arc<dyn ObjectStorage>,
+    pub image_processor: Arc<dyn ImageProcessor>,
+    pub job_queue: Arc<dyn JobQueue>,
+    pub error_reporter: Arc<dyn ErrorReporter>,
+    pub log_filter: Arc<dyn LogFilter>,
+    pub user_directory: Arc<dyn UserDirectoryPort>,
+}
+
+impl Default for CoreAdapters {
+    fn default() -> Self {
+        Self {
+            object_storage: Arc::new(NoObjectStorage),
+            image_processor: Arc::new(NoImageProcessor),
+            job_queue: Arc::new(NoJobQueue),
+            error_reporter: Arc::new(NoErrorReporter),
+            log_filter: Arc::new(NoLogFilter),
+            user_directory: Arc::new(NoUserDirectory),
+        }
+    }
 }
 
 impl CoreServices {
-    #[tracing::instrument(level = "trace", skip(repository_service))]
-    pub(crate) fn new(repository_service: Arc<RepositoryService>) -> Self {
+    #[tracing::instrument(level = "trace", skip(repository_service, feature_flags, adapters))]
+    pub(crate) fn new(repository_service: Arc<RepositoryService>, feature_flags: Arc<dyn FeatureFlags>, adapters: CoreAdapters) -> Self {
+        let subsystems = Subsystems::default();
         Self {
-            user_service: Arc::new(UserServiceImpl::new(repository_service.clone())),
-            session_service: Arc::new(SessionServiceImpl::new(repository_service)),
+            user_service: Arc::new(UserServiceImpl::new(repository_service.clone(), adapters.user_directory)),
+            user_info_service: Arc::new(UserInfoServiceImpl::new(repository_service.clone())),
+            session_service: Arc::new(SessionServiceImpl::new(repository_service.clone())),
+            avatar_service: Arc::new(AvatarServiceImpl::new(
+                repository_service.clone(),
+                adapters.object_storage,
+                adapters.image_processor,
+                adapters.job_queue.clone(),
+            )),
+            activity_service: Arc::new(ActivityServiceImpl::new(repository_service.clone())),
+            webhook_service: Arc::new(WebhookServiceImpl::new(repository_service.clone())),
+            lease_service: Arc::new(LeaseServiceImpl::new(repository_service.clone())),
+            status_service: Arc::new(StatusServiceImpl::new(repository_service.clone(), adapters.job_queue, subsystems.clone())),
+            clock: repository_service.clock().clone(),
+            maintenance_mode: repository_service.maintenance_mode().clone(),
+            page_size_cap: PageSizeCap::default(),
+            user_list_cache: repository_service.user_list_cache().clone(),
+            feature_flags,
+            error_reporter: adapters.error_reporter,
+            log_filter: adapters.log_filter,
+            subsystems,
+        }
+    }
+
+    /// Carries out a job taken off the [`JobQueue`].
+    #[tracing::instrument(level = "trace", skip(self, job), fields(job = job.name()))]
+    pub async fn run_job(&self, job: Job) -> Result<(), Error> {
+        let context = RequestContext::internal();
+        match job {
+            Job::ProcessAvatar { user_id, key } => self.avatar_service.process_avatar(&context, user_id, &key).await,
+            Job::RefreshSignupRollups => {
+                let yesterday = self.clock.now().date_naive() - Days::new(1);
+                self.user_service.refresh_signup_rollups(&context, Some(yesterday)).await.map(|_| ())
+            }
         }
     }
 }
 
 pub fn create_services(repository_service: Arc<RepositoryService>) -> Result<Arc<CoreServices>, Error> {
-    let core_services = CoreServices::new(repository_service);
+    create_services_with_feature_flags(repository_service, Arc::new(InMemoryFeatureFlags::default()))
+}
+
+/// Same as [`create_services`], but with the given feature flags instead of
+/// an empty set where every flag is off.
+pub fn create_services_with_feature_flags(
+    repository_service: Arc<RepositoryService>,
+    feature_flags: Arc<dyn FeatureFlags>,
+) -> Result<Arc<CoreServices>, Error> {
+    create_services_with_adapters(repository_service, feature_flags, CoreAdapters::default())
+}
+
+/// Same as [`create_services_with_feature_flags`], but with the given
+/// adapters for avatar storage, image processing, background jobs, error
+/// reporting, the log filter and the user directory.
+pub fn create_services_with_adapters(
+    repository_service: Arc<RepositoryService>,
+    feature_flags: Arc<dyn FeatureFlags>,
+    adapters: CoreAdapters,
+) -> Result<Arc<CoreServices>, Error> {
+    let core_services = CoreServices::new(repository_service, feature_flags, adapters);
 
     Ok(Arc::new(core_services))
 }
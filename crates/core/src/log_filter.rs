@@ -0,0 +1,30 @@
+//! Port for changing which log events are recorded while the server runs,
+//! e.g. to turn on debug logging for one module while chasing a problem.
+
+use crate::Error;
+
+#[cfg_attr(any(test, feature = "test-support"), mockall::automock)]
+pub trait LogFilter: Send + Sync {
+    /// The directives in effect, in `RUST_LOG` syntax.
+    fn current(&self) -> String;
+
+    /// Replaces the directives in effect with `directives`, in `RUST_LOG`
+    /// syntax, e.g. `info,hex_play_api=debug`. Fails with
+    /// [`Error::Validation`] if they do not parse, leaving the filter as it
+    /// was.
+    fn set(&self, directives: &str) -> Result<(), Error>;
+}
+
+/// Filter for deployments whose logging cannot be changed at runtime.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoLogFilter;
+
+impl LogFilter for NoLogFilter {
+    fn current(&self) -> String {
+        String::new()
+    }
+
+    fn set(&self, _directives: &str) -> Result<(), Error> {
+        Err(Error::Infrastructure("Log filter cannot be changed at runtime".into()))
+    }
+}
@@ -0,0 +1,59 @@
+//! Runtime switch that puts the service into read-only maintenance mode,
+//! e.g. during migrations and failovers.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// Shared maintenance flag. Clones observe and toggle the same state.
+///
+/// While enabled, every read-write transaction fails with
+/// [`Error::ReadOnlyMode`](crate::Error::ReadOnlyMode); read-only
+/// transactions are unaffected.
+#[derive(Debug, Clone, Default)]
+pub struct MaintenanceMode(Arc<AtomicBool>);
+
+impl MaintenanceMode {
+    pub fn new(enabled: bool) -> Self {
+        Self(Arc::new(AtomicBool::new(enabled)))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Turns maintenance mode on or off, returning the previous state.
+    pub fn set_enabled(&self, enabled: bool) -> bool {
+        let previous = self.0.swap(enabled, Ordering::Relaxed);
+        if previous != enabled {
+            tracing::warn!(enabled, "Maintenance mode changed");
+        }
+        previous
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MaintenanceMode;
+
+    // ===================
+    // Tests: MaintenanceMode
+    // ===================
+
+    #[test]
+    fn test_default_is_disabled() {
+        assert!(!MaintenanceMode::default().is_enabled());
+    }
+
+    #[test]
+    fn test_clones_share_state() {
+        let mode = MaintenanceMode::default();
+        let clone = mode.clone();
+
+        assert!(!clone.set_enabled(true));
+        assert!(mode.is_enabled());
+        assert!(mode.set_enabled(false));
+        assert!(!clone.is_enabled());
+    }
+}
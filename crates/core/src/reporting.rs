@@ -0,0 +1,32 @@
+//! Port for sending internal failures to an error tracker, such as Sentry.
+//!
+//! The inbound adapters report every request that fails with an
+//! [`ErrorKind::Internal`](crate::ErrorKind::Internal) error or a panic,
+//! tagged with the request and user it came from. Validation errors,
+//! conflicts and the like are the caller's problem and are not reported.
+
+/// A failure worth a look from the operators.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorReport {
+    pub message: String,
+    /// Whether a handler panicked rather than returned an error.
+    pub panic: bool,
+    pub request_id: Option<String>,
+    /// The authenticated user, if there was one.
+    pub user_id: Option<String>,
+}
+
+#[cfg_attr(any(test, feature = "test-support"), mockall::automock)]
+pub trait ErrorReporter: Send + Sync {
+    /// Sends `report` on its way. Must not block the request on the tracker.
+    fn report(&self, report: ErrorReport);
+}
+
+/// Reporter for deployments without an error tracker. Reports are dropped,
+/// leaving the failure in the logs only.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoErrorReporter;
+
+impl ErrorReporter for NoErrorReporter {
+    fn report(&self, _report: ErrorReport) {}
+}
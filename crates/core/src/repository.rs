@@ -1,15 +1,34 @@
-use std::{any::Any, future::Future, pin::Pin, sync::Arc};
+use std::{any::Any, sync::Arc, time::Instant};
 
 use derive_builder::Builder;
 
-use crate::{Error, session::SessionRepository, user::UserRepository};
+use crate::{
+    Error, RepositoryError,
+    cache::QueryCache,
+    clock::{Clock, SystemClock},
+    context::RequestContext,
+    lease::LeaseRepository,
+    maintenance::MaintenanceMode,
+    session::SessionRepository,
+    user::{CheckedUserRepository, User, UserQueryRepository, UserRepository},
+    webhook::WebhookRepository,
+};
 
 #[derive(Builder)]
 #[builder(pattern = "owned")]
 pub struct RepositoryService {
     repository: Arc<dyn Repository>,
     user_repository: Arc<dyn UserRepository>,
+    user_query_repository: Arc<dyn UserQueryRepository>,
     session_repository: Arc<dyn SessionRepository>,
+    webhook_repository: Arc<dyn WebhookRepository>,
+    lease_repository: Arc<dyn LeaseRepository>,
+    #[builder(default = "Arc::new(SystemClock)")]
+    clock: Arc<dyn Clock>,
+    #[builder(default)]
+    maintenance_mode: MaintenanceMode,
+    #[builder(default = "QueryCache::new(\"user_list\")")]
+    user_list_cache: QueryCache<Vec<User>>,
 }
 
 impl RepositoryService {
@@ -23,124 +42,193 @@ impl RepositoryService {
         &self.user_repository
     }
 
+    /// Returns a reference to the repository listing and searching users.
+    pub fn user_query_repository(&self) -> &Arc<dyn UserQueryRepository> {
+        &self.user_query_repository
+    }
+
     /// Returns a reference to the session repository.
     pub fn session_repository(&self) -> &Arc<dyn SessionRepository> {
         &self.session_repository
     }
+
+    /// Returns a reference to the webhook nonce repository.
+    pub fn webhook_repository(&self) -> &Arc<dyn WebhookRepository> {
+        &self.webhook_repository
+    }
+
+    /// Returns a reference to the lease repository.
+    pub fn lease_repository(&self) -> &Arc<dyn LeaseRepository> {
+        &self.lease_repository
+    }
+
+    /// Returns the clock used to stamp persisted timestamps.
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    /// Returns the maintenance switch consulted before read-write transactions.
+    pub fn maintenance_mode(&self) -> &MaintenanceMode {
+        &self.maintenance_mode
+    }
+
+    /// Returns the cache of `list_users` pages, which services invalidate
+    /// once they have written to the users.
+    pub fn user_list_cache(&self) -> &QueryCache<Vec<User>> {
+        &self.user_list_cache
+    }
+
+    /// Runs `work` in a read-write transaction, committing if it succeeds and
+    /// rolling back if it fails. Statements must finish before the deadline
+    /// of `context`. Fails with `Error::ReadOnlyMode` without touching the
+    /// database while maintenance mode is enabled.
+    ///
+    /// # Example
+    /// ```ignore
+    /// self.repository_service
+    ///     .execute(context, async |uow| {
+    ///         let user = uow.users().add_user(uow.tx(), user).await?;
+    ///         uow.sessions().store(uow.tx(), session_for(&user)).await
+    ///     })
+    ///     .await
+    /// ```
+    pub async fn execute<T, F>(&self, context: &RequestContext, work: F) -> Result<T, Error>
+    where
+        F: AsyncFnOnce(&UnitOfWork<'_>) -> Result<T, Error>,
+    {
+        if self.maintenance_mode.is_enabled() {
+            return Err(Error::ReadOnlyMode);
+        }
+        check_deadline(context.deadline)?;
+        let uow = UnitOfWork {
+            transaction: self.repository.begin_for_request(context).await?,
+            users: CheckedUserRepository::new(&*self.user_repository),
+            repository_service: self,
+        };
+        match work(&uow).await {
+            Ok(result) => {
+                uow.transaction.commit().await?;
+                Ok(result)
+            }
+            Err(e) => {
+                // Best effort rollback - if it fails, we still return the original error
+                let _ = uow.transaction.rollback().await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Runs `work` in a read-only transaction, bounded by the deadline of
+    /// `context`, then rolls it back as there is nothing to keep.
+    pub async fn execute_read_only<T, F>(&self, context: &RequestContext, work: F) -> Result<T, Error>
+    where
+        F: AsyncFnOnce(&UnitOfWork<'_>) -> Result<T, Error>,
+    {
+        check_deadline(context.deadline)?;
+        let uow = UnitOfWork {
+            transaction: self.repository.begin_read_only_for_request(context).await?,
+            users: CheckedUserRepository::new(&*self.user_repository),
+            repository_service: self,
+        };
+        let result = work(&uow).await;
+        // Best effort, as with a failed read-write transaction.
+        let _ = uow.transaction.rollback().await;
+        result
+    }
 }
 
-#[async_trait::async_trait]
-pub trait Transaction: Any + Send + Sync {
-    fn as_any(&self) -> &dyn Any;
-    async fn commit(self: Box<Self>) -> Result<(), Error>;
-    async fn rollback(self: Box<Self>) -> Result<(), Error>;
+/// A transaction and the repositories that run statements in it, handed to
+/// the work passed to [`RepositoryService::execute`].
+pub struct UnitOfWork<'a> {
+    transaction: Box<dyn Transaction>,
+    users: CheckedUserRepository<'a>,
+    repository_service: &'a RepositoryService,
 }
 
-/// Execute an async operation within a read-write transaction.
-///
-/// Clones one or more repositories, begins a transaction, executes the body,
-/// and commits on success or rolls back on error.
-///
-/// # Examples
-/// ```ignore
-/// // Single repository
-/// with_transaction!(self, user_repository, |tx| {
-///     user_repository.add_user(tx, user).await
-/// })
-///
-/// // Multiple repositories
-/// with_transaction!(self, user_repository, order_repository, |tx| {
-///     let user = user_repository.add_user(tx, user).await?;
-///     order_repository.create_order(tx, user.id, order).await
-/// })
-/// ```
-#[macro_export]
-macro_rules! with_transaction {
-    ($self:expr, $($repo:ident),+ , |$tx:ident| $body:expr) => {{
-        $(let $repo = $self.repository_service.$repo().clone();)+
-        $crate::repository::transaction(&**$self.repository_service.repository(), |$tx| Box::pin(async move { $body })).await
-    }};
+impl UnitOfWork<'_> {
+    /// The transaction to pass to repository operations.
+    pub fn tx(&self) -> &dyn Transaction {
+        &*self.transaction
+    }
+
+    /// The user repository, refusing to write users that break their
+    /// invariants.
+    pub fn users(&self) -> &dyn UserRepository {
+        &self.users
+    }
+
+    pub fn user_queries(&self) -> &dyn UserQueryRepository {
+        &*self.repository_service.user_query_repository
+    }
+
+    pub fn sessions(&self) -> &dyn SessionRepository {
+        &*self.repository_service.session_repository
+    }
+
+    pub fn webhooks(&self) -> &dyn WebhookRepository {
+        &*self.repository_service.webhook_repository
+    }
+
+    pub fn leases(&self) -> &dyn LeaseRepository {
+        &*self.repository_service.lease_repository
+    }
 }
 
-/// Execute an async operation within a read-only transaction.
-///
-/// Clones one or more repositories and executes the body within a read-only
-/// transaction.
-///
-/// # Examples
-/// ```ignore
-/// // Single repository
-/// with_read_only_transaction!(self, user_repository, |tx| {
-///     user_repository.find_by_id(tx, id).await
-/// })
+/// An open transaction, passed to repository methods as
+/// `&(dyn Transaction + 'static)`.
 ///
-/// // Multiple repositories
-/// with_read_only_transaction!(self, user_repository, order_repository, |tx| {
-///     let user = user_repository.find_by_id(tx, id).await?;
-///     let orders = order_repository.find_by_user(tx, user.id).await?;
-///     Ok((user, orders))
-/// })
-/// ```
-#[macro_export]
-macro_rules! with_read_only_transaction {
-    ($self:expr, $($repo:ident),+ , |$tx:ident| $body:expr) => {{
-        $(let $repo = $self.repository_service.$repo().clone();)+
-        $crate::repository::read_only_transaction(&**$self.repository_service.repository(), |$tx| Box::pin(async move { $body })).await
-    }};
+/// The bound is the default anyway, as `Any` is `'static`, but spelling it
+/// out keeps the generated repository mocks in step with their traits. The
+/// parentheses then trip a false `unused_parens` lint in the generated code,
+/// which those traits allow.
+#[async_trait::async_trait]
+pub trait Transaction: Any + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    async fn commit(self: Box<Self>) -> Result<(), Error>;
+    async fn rollback(self: Box<Self>) -> Result<(), Error>;
 }
 
 #[async_trait::async_trait]
 pub trait Repository: Send + Sync {
-    async fn begin(&self) -> Result<Box<dyn Transaction>, Error>;
-    async fn begin_read_only(&self) -> Result<Box<dyn Transaction>, Error>;
-    async fn close(&self) -> Result<(), Error>;
-}
+    async fn begin(&self) -> Result<Box<dyn Transaction>, Error> {
+        self.begin_with_deadline(None).await
+    }
 
-/// Execute a closure within a transaction, automatically committing on success
-/// or rolling back on error.
-///
-/// # Example
-/// ```ignore
-/// let result = transaction(&*repository, |tx| Box::pin(async move {
-///     // do stuff with tx
-///     Ok(result)
-/// })).await?;
-/// ```
-#[tracing::instrument(level = "trace", skip(repository, callback))]
-pub async fn transaction<F, T>(repository: &dyn Repository, callback: F) -> Result<T, Error>
-where
-    F: for<'c> FnOnce(&'c dyn Transaction) -> Pin<Box<dyn Future<Output = Result<T, Error>> + Send + 'c>> + Send,
-    T: Send,
-{
-    let tx = repository.begin().await?;
-    match callback(&*tx).await {
-        Ok(result) => {
-            tx.commit().await?;
-            Ok(result)
-        }
-        Err(e) => {
-            // Best effort rollback - if it fails, we still return the original error
-            let _ = tx.rollback().await;
-            Err(e)
-        }
+    async fn begin_read_only(&self) -> Result<Box<dyn Transaction>, Error> {
+        self.begin_read_only_with_deadline(None).await
     }
+
+    /// Begins a transaction whose statements are abandoned once `deadline`
+    /// passes, where the backend supports it.
+    async fn begin_with_deadline(&self, deadline: Option<Instant>) -> Result<Box<dyn Transaction>, Error>;
+    async fn begin_read_only_with_deadline(&self, deadline: Option<Instant>) -> Result<Box<dyn Transaction>, Error>;
+
+    /// Same as [`begin_with_deadline`](Self::begin_with_deadline) with the
+    /// deadline of `context`, its statements confined to what the tenant
+    /// and actor of `context` may see where the backend supports it.
+    async fn begin_for_request(&self, context: &RequestContext) -> Result<Box<dyn Transaction>, Error> {
+        self.begin_with_deadline(context.deadline).await
+    }
+
+    /// Same as [`begin_for_request`](Self::begin_for_request), read-only.
+    async fn begin_read_only_for_request(&self, context: &RequestContext) -> Result<Box<dyn Transaction>, Error> {
+        self.begin_read_only_with_deadline(context.deadline).await
+    }
+
+    /// Tables missing or out of date, where the backend can tell. Defaults
+    /// to none.
+    async fn stale_tables(&self) -> Result<Vec<String>, Error> {
+        Ok(Vec::new())
+    }
+
+    async fn close(&self) -> Result<(), Error>;
 }
 
-/// Execute a closure within a read-only transaction.
-///
-/// # Example
-/// ```ignore
-/// let result = read_only_transaction(&*repository, |tx| Box::pin(async move {
-///     // do stuff with tx
-///     Ok(result)
-/// })).await?;
-/// ```
-#[tracing::instrument(level = "trace", skip(repository, callback))]
-pub async fn read_only_transaction<F, T>(repository: &dyn Repository, callback: F) -> Result<T, Error>
-where
-    F: for<'c> FnOnce(&'c dyn Transaction) -> Pin<Box<dyn Future<Output = Result<T, Error>> + Send + 'c>> + Send,
-    T: Send,
-{
-    let tx = repository.begin_read_only().await?;
-    callback(&*tx).await
+/// Fails with `RepositoryError::QueryCanceled` once `deadline` has passed, as
+/// the caller has already stopped waiting for the result.
+pub(crate) fn check_deadline(deadline: Option<Instant>) -> Result<(), Error> {
+    match deadline {
+        Some(deadline) if deadline <= Instant::now() => Err(Error::RepositoryError(RepositoryError::QueryCanceled)),
+        _ => Ok(()),
+    }
 }
@@ -2,11 +2,9 @@ pub mod model;
 pub mod repository;
 pub mod service;
 
-#[cfg(feature = "test-support")]
-pub mod test_support;
-pub use model::{NewSession, Session, SessionBuilder};
+pub use model::{Impersonation, ImpersonationToken, NewSession, Session, SessionBuilder, SessionId};
 pub use repository::SessionRepository;
-pub use service::SessionService;
 pub(crate) use service::SessionServiceImpl;
-#[cfg(feature = "test-support")]
-pub use test_support::MockSessionService;
+pub use service::{IMPERSONATION_TTL, SessionService};
+#[cfg(any(test, feature = "test-support"))]
+pub use {repository::MockSessionRepository, service::MockSessionService};
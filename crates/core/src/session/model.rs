@@ -1,30 +1,117 @@
+use std::fmt;
+
 use chrono::{DateTime, Utc};
 use derive_builder::Builder;
+use hex_play_utils::{define_token_prefix, token::Token};
+
+use crate::{
+    Error,
+    clock::{Clock, SystemClock},
+    user::UserId,
+};
+
+define_token_prefix!(ImpersonationPrefix, "IMP_");
+/// Bearer token of an impersonation, also the id of the session storing it.
+pub type ImpersonationToken = Token<ImpersonationPrefix, u128, { u128::MAX }>;
+
+/// Id of a stored session: the cookie id of a web session, or the token of
+/// an impersonation. A type of its own so it can't be passed where the
+/// session data, also a string, is expected.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SessionId(String);
+
+impl SessionId {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    /// Returns the id value.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for SessionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<SessionId> for String {
+    fn from(id: SessionId) -> Self {
+        id.0
+    }
+}
 
-use crate::Error;
+impl From<ImpersonationToken> for SessionId {
+    fn from(token: ImpersonationToken) -> Self {
+        Self(token.to_string())
+    }
+}
 
 #[derive(Debug, Clone, Builder)]
 pub struct Session {
-    pub id: String,
+    pub id: SessionId,
     pub session: String,
     pub expires_at: DateTime<Utc>,
-    #[builder(default = "Utc::now()")]
+    #[builder(default = "SystemClock.now()")]
     pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone)]
 pub struct NewSession {
-    pub id: String,
+    pub id: SessionId,
     pub session: String,
     pub expires_at: DateTime<Utc>,
 }
 
 impl NewSession {
-    pub fn new(id: impl Into<String>, session: impl Into<String>, expires_at: DateTime<Utc>) -> Result<Self, Error> {
+    pub fn new(id: SessionId, session: impl Into<String>, expires_at: DateTime<Utc>) -> Result<Self, Error> {
         Ok(Self {
-            id: id.into(),
+            id,
             session: session.into(),
             expires_at,
         })
     }
 }
+
+/// An admin acting as another user until `expires_at`, within the admin's
+/// tenant if any. Stored with the web sessions, keyed by its token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Impersonation {
+    pub token: ImpersonationToken,
+    pub user_id: UserId,
+    pub impersonator_id: UserId,
+    pub tenant: Option<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Impersonation {
+    pub(crate) fn to_new_session(&self) -> NewSession {
+        let session = match &self.tenant {
+            Some(tenant) => format!("{}:{}:{tenant}", self.impersonator_id, self.user_id),
+            None => format!("{}:{}", self.impersonator_id, self.user_id),
+        };
+        NewSession {
+            id: self.token.into(),
+            session,
+            expires_at: self.expires_at,
+        }
+    }
+
+    /// Reads an impersonation back from its session, `None` if the session
+    /// holds something else.
+    pub(crate) fn from_session(session: &Session) -> Option<Self> {
+        let token = ImpersonationToken::parse(session.id.as_str()).ok()?;
+        let mut parts = session.session.splitn(3, ':');
+        let impersonator_id = parts.next()?.parse().ok()?;
+        let user_id = parts.next()?.parse().ok()?;
+        Some(Self {
+            token,
+            user_id,
+            impersonator_id,
+            tenant: parts.next().map(str::to_string),
+            expires_at: session.expires_at,
+        })
+    }
+}
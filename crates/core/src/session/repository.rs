@@ -1,17 +1,18 @@
 use crate::{
     Error,
     repository::Transaction,
-    session::{NewSession, Session},
+    session::{NewSession, Session, SessionId},
 };
 
+#[cfg_attr(any(test, feature = "test-support"), mockall::automock, allow(unused_parens))]
 #[async_trait::async_trait]
 pub trait SessionRepository: Send + Sync {
-    async fn count(&self, transaction: &dyn Transaction) -> Result<i64, Error>;
-    async fn store(&self, transaction: &dyn Transaction, session: NewSession) -> Result<Session, Error>;
-    async fn load(&self, transaction: &dyn Transaction, id: &str) -> Result<Option<Session>, Error>;
-    async fn delete_by_id(&self, transaction: &dyn Transaction, id: &str) -> Result<(), Error>;
-    async fn exists(&self, transaction: &dyn Transaction, id: &str) -> Result<bool, Error>;
-    async fn delete_by_expiry(&self, transaction: &dyn Transaction) -> Result<Vec<String>, Error>;
-    async fn delete_all(&self, transaction: &dyn Transaction) -> Result<(), Error>;
-    async fn get_ids(&self, transaction: &dyn Transaction) -> Result<Vec<String>, Error>;
+    async fn count(&self, transaction: &(dyn Transaction + 'static)) -> Result<i64, Error>;
+    async fn store(&self, transaction: &(dyn Transaction + 'static), session: NewSession) -> Result<Session, Error>;
+    async fn load(&self, transaction: &(dyn Transaction + 'static), id: &SessionId) -> Result<Option<Session>, Error>;
+    async fn delete_by_id(&self, transaction: &(dyn Transaction + 'static), id: &SessionId) -> Result<(), Error>;
+    async fn exists(&self, transaction: &(dyn Transaction + 'static), id: &SessionId) -> Result<bool, Error>;
+    async fn delete_by_expiry(&self, transaction: &(dyn Transaction + 'static)) -> Result<Vec<SessionId>, Error>;
+    async fn delete_all(&self, transaction: &(dyn Transaction + 'static)) -> Result<(), Error>;
+    async fn get_ids(&self, transaction: &(dyn Transaction + 'static)) -> Result<Vec<SessionId>, Error>;
 }
@@ -1,22 +1,40 @@
 use std::sync::Arc;
 
+use chrono::TimeDelta;
+
 use crate::{
-    Error,
+    Error, RepositoryError,
+    context::RequestContext,
     repository::RepositoryService,
-    session::{NewSession, Session},
-    with_transaction,
+    session::{Impersonation, ImpersonationToken, NewSession, Session, SessionId},
+    user::UserId,
 };
 
+/// How long an impersonation lasts before the admin has to start another.
+pub const IMPERSONATION_TTL: TimeDelta = TimeDelta::minutes(15);
+
+#[cfg_attr(any(test, feature = "test-support"), mockall::automock)]
 #[async_trait::async_trait]
 pub trait SessionService: Send + Sync {
     async fn count(&self) -> Result<i64, Error>;
     async fn store(&self, session: NewSession) -> Result<Session, Error>;
-    async fn load(&self, id: &str) -> Result<Option<Session>, Error>;
-    async fn delete_by_id(&self, id: &str) -> Result<(), Error>;
-    async fn exists(&self, id: &str) -> Result<bool, Error>;
-    async fn delete_by_expiry(&self) -> Result<Vec<String>, Error>;
+    async fn load(&self, id: &SessionId) -> Result<Option<Session>, Error>;
+    async fn delete_by_id(&self, id: &SessionId) -> Result<(), Error>;
+    async fn exists(&self, id: &SessionId) -> Result<bool, Error>;
+    async fn delete_by_expiry(&self) -> Result<Vec<SessionId>, Error>;
     async fn delete_all(&self) -> Result<(), Error>;
-    async fn get_ids(&self) -> Result<Vec<String>, Error>;
+    async fn get_ids(&self) -> Result<Vec<SessionId>, Error>;
+
+    /// Lets `impersonator_id` act as `user_id` for [`IMPERSONATION_TTL`],
+    /// within the tenant of `context`. Both users must exist in it. Audited
+    /// with both identities.
+    async fn start_impersonation(&self, context: &RequestContext, impersonator_id: UserId, user_id: UserId) -> Result<Impersonation, Error>;
+    /// The impersonation `token` stands for, or `None` once it has ended or
+    /// expired.
+    async fn find_impersonation(&self, context: &RequestContext, token: ImpersonationToken) -> Result<Option<Impersonation>, Error>;
+    /// Ends the impersonation `token` stands for. Audited with both
+    /// identities.
+    async fn end_impersonation(&self, context: &RequestContext, token: ImpersonationToken) -> Result<Impersonation, Error>;
 }
 
 pub(crate) struct SessionServiceImpl {
@@ -29,268 +47,192 @@ impl SessionServiceImpl {
     }
 }
 
+// Sessions are stored for the web frontend rather than on behalf of a client
+// request, so they run without a request deadline.
 #[async_trait::async_trait]
 impl SessionService for SessionServiceImpl {
     async fn count(&self) -> Result<i64, Error> {
-        with_transaction!(self, session_repository, |tx| session_repository.count(tx).await)
+        self.repository_service
+            .execute(&RequestContext::internal(), async |uow| uow.sessions().count(uow.tx()).await)
+            .await
     }
 
     async fn store(&self, session: NewSession) -> Result<Session, Error> {
-        with_transaction!(self, session_repository, |tx| session_repository.store(tx, session).await)
+        self.repository_service
+            .execute(&RequestContext::internal(), async |uow| uow.sessions().store(uow.tx(), session).await)
+            .await
     }
 
-    async fn load(&self, id: &str) -> Result<Option<Session>, Error> {
-        let id = id.to_owned();
-        with_transaction!(self, session_repository, |tx| session_repository.load(tx, &id).await)
+    async fn load(&self, id: &SessionId) -> Result<Option<Session>, Error> {
+        self.repository_service
+            .execute(&RequestContext::internal(), async |uow| uow.sessions().load(uow.tx(), id).await)
+            .await
     }
-    async fn delete_by_id(&self, id: &str) -> Result<(), Error> {
-        let id = id.to_owned();
-        with_transaction!(self, session_repository, |tx| session_repository.delete_by_id(tx, &id).await)
+    async fn delete_by_id(&self, id: &SessionId) -> Result<(), Error> {
+        self.repository_service
+            .execute(&RequestContext::internal(), async |uow| uow.sessions().delete_by_id(uow.tx(), id).await)
+            .await
     }
-    async fn exists(&self, id: &str) -> Result<bool, Error> {
-        let id = id.to_owned();
-        with_transaction!(self, session_repository, |tx| session_repository.exists(tx, &id).await)
+    async fn exists(&self, id: &SessionId) -> Result<bool, Error> {
+        self.repository_service
+            .execute(&RequestContext::internal(), async |uow| uow.sessions().exists(uow.tx(), id).await)
+            .await
     }
-    async fn delete_by_expiry(&self) -> Result<Vec<String>, Error> {
-        with_transaction!(self, session_repository, |tx| session_repository.delete_by_expiry(tx).await)
+    async fn delete_by_expiry(&self) -> Result<Vec<SessionId>, Error> {
+        self.repository_service
+            .execute(&RequestContext::internal(), async |uow| uow.sessions().delete_by_expiry(uow.tx()).await)
+            .await
     }
     async fn delete_all(&self) -> Result<(), Error> {
-        with_transaction!(self, session_repository, |tx| session_repository.delete_all(tx).await)
+        self.repository_service
+            .execute(&RequestContext::internal(), async |uow| uow.sessions().delete_all(uow.tx()).await)
+            .await
     }
-    async fn get_ids(&self) -> Result<Vec<String>, Error> {
-        with_transaction!(self, session_repository, |tx| session_repository.get_ids(tx).await)
+    async fn get_ids(&self) -> Result<Vec<SessionId>, Error> {
+        self.repository_service
+            .execute(&RequestContext::internal(), async |uow| uow.sessions().get_ids(uow.tx()).await)
+            .await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn start_impersonation(&self, context: &RequestContext, impersonator_id: UserId, user_id: UserId) -> Result<Impersonation, Error> {
+        if impersonator_id == user_id {
+            return Err(Error::SelfImpersonation);
+        }
+        let impersonation = Impersonation {
+            token: ImpersonationToken::generate(),
+            user_id,
+            impersonator_id,
+            tenant: context.tenant.clone(),
+            expires_at: self.repository_service.clock().now() + IMPERSONATION_TTL,
+        };
+        self.repository_service
+            .execute(context, async |uow| {
+                for id in [impersonator_id, user_id] {
+                    uow.users()
+                        .find_by_id(uow.tx(), id)
+                        .await?
+                        .ok_or(Error::RepositoryError(RepositoryError::NotFound))?;
+                }
+                Ok(())
+            })
+            .await?;
+        // Stored outside of any tenant, as requests presenting the token
+        // learn their tenant from it.
+        let session = impersonation.to_new_session();
+        let outside_tenant = RequestContext {
+            tenant: None,
+            ..context.clone()
+        };
+        self.repository_service
+            .execute(&outside_tenant, async |uow| uow.sessions().store(uow.tx(), session).await)
+            .await?;
+
+        tracing::info!(
+            target: "audit",
+            request_id = %context.request_id,
+            %impersonator_id,
+            %user_id,
+            tenant = ?impersonation.tenant,
+            expires_at = %impersonation.expires_at,
+            "Impersonation started"
+        );
+        Ok(impersonation)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn find_impersonation(&self, context: &RequestContext, token: ImpersonationToken) -> Result<Option<Impersonation>, Error> {
+        let id = SessionId::from(token);
+        let session = self
+            .repository_service
+            .execute_read_only(context, async |uow| uow.sessions().load(uow.tx(), &id).await)
+            .await?;
+        let now = self.repository_service.clock().now();
+
+        Ok(session
+            .as_ref()
+            .and_then(Impersonation::from_session)
+            .filter(|impersonation| impersonation.expires_at > now))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn end_impersonation(&self, context: &RequestContext, token: ImpersonationToken) -> Result<Impersonation, Error> {
+        let id = SessionId::from(token);
+        let impersonation = self
+            .repository_service
+            .execute(context, async |uow| {
+                let impersonation = uow
+                    .sessions()
+                    .load(uow.tx(), &id)
+                    .await?
+                    .as_ref()
+                    .and_then(Impersonation::from_session)
+                    .ok_or(Error::RepositoryError(RepositoryError::NotFound))?;
+                uow.sessions().delete_by_id(uow.tx(), &id).await?;
+                Ok(impersonation)
+            })
+            .await?;
+
+        tracing::info!(
+            target: "audit",
+            request_id = %context.request_id,
+            impersonator_id = %impersonation.impersonator_id,
+            user_id = %impersonation.user_id,
+            "Impersonation ended"
+        );
+        Ok(impersonation)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{
-        any::Any,
-        sync::{Arc, Mutex},
-    };
+    use std::sync::Arc;
 
-    use chrono::{Duration, Utc};
+    use chrono::{DateTime, Duration, Utc};
 
-    use super::{SessionService, SessionServiceImpl};
+    use super::{IMPERSONATION_TTL, SessionService, SessionServiceImpl};
     use crate::{
-        Error,
-        repository::{Repository, RepositoryServiceBuilder, Transaction},
-        session::{
-            model::{NewSession, Session, SessionBuilder},
-            repository::SessionRepository,
-        },
+        Error, ErrorKind,
+        clock::{Clock, FixedClock},
+        context::RequestContext,
+        session::model::{Impersonation, ImpersonationToken, NewSession, Session, SessionBuilder, SessionId},
+        test_support::{MockSessionRepository, MockUserRepository, MockWebhookRepository, mock_repository_service},
         types::Email,
-        user::{
-            model::{NewUser, User, UserId, UserToken},
-            repository::UserRepository,
-        },
+        user::{UserBuilder, UserId},
     };
 
-    // ===================
-    // Mock Transaction
-    // ===================
-    struct MockTransaction;
-
-    #[async_trait::async_trait]
-    impl Transaction for MockTransaction {
-        fn as_any(&self) -> &dyn Any {
-            self
-        }
-
-        async fn commit(self: Box<Self>) -> Result<(), Error> {
-            Ok(())
-        }
-
-        async fn rollback(self: Box<Self>) -> Result<(), Error> {
-            Ok(())
-        }
-    }
-
-    // ===================
-    // Mock Repository
-    // ===================
-    struct MockRepository;
-
-    #[async_trait::async_trait]
-    impl Repository for MockRepository {
-        async fn begin(&self) -> Result<Box<dyn Transaction>, Error> {
-            Ok(Box::new(MockTransaction))
-        }
-
-        async fn begin_read_only(&self) -> Result<Box<dyn Transaction>, Error> {
-            Ok(Box::new(MockTransaction))
-        }
-
-        async fn close(&self) -> Result<(), Error> {
-            Ok(())
-        }
-    }
-
-    // ===================
-    // Mock UserRepository
-    // ===================
-    struct MockUserRepository;
-
-    #[async_trait::async_trait]
-    impl UserRepository for MockUserRepository {
-        async fn add_user(&self, _tx: &dyn Transaction, _user: NewUser) -> Result<User, Error> {
-            unimplemented!()
-        }
-        async fn update_user(&self, _tx: &dyn Transaction, _user: User) -> Result<User, Error> {
-            unimplemented!()
-        }
-        async fn delete_user(&self, _tx: &dyn Transaction, _user: User) -> Result<User, Error> {
-            unimplemented!()
-        }
-        async fn list_users(&self, _tx: &dyn Transaction, _start_id: Option<UserId>, _page_size: Option<u64>) -> Result<Vec<User>, Error> {
-            unimplemented!()
-        }
-        async fn find_by_id(&self, _tx: &dyn Transaction, _id: UserId) -> Result<Option<User>, Error> {
-            unimplemented!()
-        }
-        async fn find_by_email(&self, _tx: &dyn Transaction, _email: &Email) -> Result<Option<User>, Error> {
-            unimplemented!()
-        }
-        async fn find_by_token(&self, _tx: &dyn Transaction, _token: UserToken) -> Result<Option<User>, Error> {
-            unimplemented!()
-        }
-    }
-
-    // ===================
-    // Mock SessionRepository
-    // ===================
-    #[derive(Default)]
-    struct MockSessionRepository {
-        count_result: Mutex<Option<Result<i64, Error>>>,
-        store_result: Mutex<Option<Result<Session, Error>>>,
-        load_result: Mutex<Option<Result<Option<Session>, Error>>>,
-        delete_by_id_result: Mutex<Option<Result<(), Error>>>,
-        exists_result: Mutex<Option<Result<bool, Error>>>,
-        delete_by_expiry_result: Mutex<Option<Result<Vec<String>, Error>>>,
-        delete_all_result: Mutex<Option<Result<(), Error>>>,
-        get_ids_result: Mutex<Option<Result<Vec<String>, Error>>>,
-    }
-
-    impl MockSessionRepository {
-        fn with_count_result(self, result: Result<i64, Error>) -> Self {
-            *self.count_result.lock().unwrap() = Some(result);
-            self
-        }
-
-        fn with_store_result(self, result: Result<Session, Error>) -> Self {
-            *self.store_result.lock().unwrap() = Some(result);
-            self
-        }
-
-        fn with_load_result(self, result: Result<Option<Session>, Error>) -> Self {
-            *self.load_result.lock().unwrap() = Some(result);
-            self
-        }
-
-        fn with_delete_by_id_result(self, result: Result<(), Error>) -> Self {
-            *self.delete_by_id_result.lock().unwrap() = Some(result);
-            self
-        }
-
-        fn with_exists_result(self, result: Result<bool, Error>) -> Self {
-            *self.exists_result.lock().unwrap() = Some(result);
-            self
-        }
-
-        fn with_delete_by_expiry_result(self, result: Result<Vec<String>, Error>) -> Self {
-            *self.delete_by_expiry_result.lock().unwrap() = Some(result);
-            self
-        }
-
-        fn with_delete_all_result(self, result: Result<(), Error>) -> Self {
-            *self.delete_all_result.lock().unwrap() = Some(result);
-            self
-        }
-
-        fn with_get_ids_result(self, result: Result<Vec<String>, Error>) -> Self {
-            *self.get_ids_result.lock().unwrap() = Some(result);
-            self
-        }
-    }
-
-    #[async_trait::async_trait]
-    impl SessionRepository for MockSessionRepository {
-        async fn count(&self, _tx: &dyn Transaction) -> Result<i64, Error> {
-            self.count_result
-                .lock()
-                .unwrap()
-                .clone()
-                .unwrap_or_else(|| Err(Error::MockNotConfigured("count")))
-        }
-        async fn store(&self, _tx: &dyn Transaction, _session: NewSession) -> Result<Session, Error> {
-            self.store_result
-                .lock()
-                .unwrap()
-                .clone()
-                .unwrap_or_else(|| Err(Error::MockNotConfigured("store")))
-        }
-        async fn load(&self, _tx: &dyn Transaction, _id: &str) -> Result<Option<Session>, Error> {
-            self.load_result
-                .lock()
-                .unwrap()
-                .clone()
-                .unwrap_or_else(|| Err(Error::MockNotConfigured("load")))
-        }
-        async fn delete_by_id(&self, _tx: &dyn Transaction, _id: &str) -> Result<(), Error> {
-            self.delete_by_id_result
-                .lock()
-                .unwrap()
-                .clone()
-                .unwrap_or_else(|| Err(Error::MockNotConfigured("delete_by_id")))
-        }
-        async fn exists(&self, _tx: &dyn Transaction, _id: &str) -> Result<bool, Error> {
-            self.exists_result
-                .lock()
-                .unwrap()
-                .clone()
-                .unwrap_or_else(|| Err(Error::MockNotConfigured("exists")))
-        }
-        async fn delete_by_expiry(&self, _tx: &dyn Transaction) -> Result<Vec<String>, Error> {
-            self.delete_by_expiry_result
-                .lock()
-                .unwrap()
-                .clone()
-                .unwrap_or_else(|| Err(Error::MockNotConfigured("delete_by_expiry")))
-        }
-        async fn delete_all(&self, _tx: &dyn Transaction) -> Result<(), Error> {
-            self.delete_all_result
-                .lock()
-                .unwrap()
-                .clone()
-                .unwrap_or_else(|| Err(Error::MockNotConfigured("delete_all")))
-        }
-        async fn get_ids(&self, _tx: &dyn Transaction) -> Result<Vec<String>, Error> {
-            self.get_ids_result
-                .lock()
-                .unwrap()
-                .clone()
-                .unwrap_or_else(|| Err(Error::MockNotConfigured("get_ids")))
-        }
-    }
-
     // ===================
     // Test Helpers
     // ===================
     fn create_use_cases(mock_session_repository: MockSessionRepository) -> SessionServiceImpl {
-        let repository_service = Arc::new(
-            RepositoryServiceBuilder::default()
-                .repository(Arc::new(MockRepository) as Arc<dyn Repository>)
-                .user_repository(Arc::new(MockUserRepository) as Arc<dyn UserRepository>)
-                .session_repository(Arc::new(mock_session_repository) as Arc<dyn SessionRepository>)
-                .build()
-                .expect("All required fields provided"),
-        );
-        SessionServiceImpl::new(repository_service)
+        create_use_cases_with_users(mock_session_repository, &[])
+    }
+
+    fn create_use_cases_with_users(mock_session_repository: MockSessionRepository, existing_ids: &[UserId]) -> SessionServiceImpl {
+        let existing_ids = existing_ids.to_vec();
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_id().returning(move |_, id| {
+            Ok(existing_ids.contains(&id).then(|| {
+                UserBuilder::default()
+                    .id(id)
+                    .name(format!("User {id}"))
+                    .email(Email::new(format!("user{id}@example.com")).unwrap())
+                    .created_at(FixedClock::epoch())
+                    .updated_at(FixedClock::epoch())
+                    .build()
+                    .unwrap()
+            }))
+        });
+        let repository_service = mock_repository_service(mock_user_repository, mock_session_repository, MockWebhookRepository::new())
+            .clock(Arc::new(FixedClock::default()) as Arc<dyn Clock>)
+            .build()
+            .expect("All required fields provided");
+        SessionServiceImpl::new(Arc::new(repository_service))
     }
 
     fn fake_session(id: &str) -> Session {
         SessionBuilder::default()
-            .id(id.to_string())
+            .id(SessionId::new(id))
             .session("session-data".to_string())
             .expires_at(Utc::now() + Duration::hours(1))
             .build()
@@ -302,7 +244,8 @@ mod tests {
     // ===================
     #[tokio::test]
     async fn test_count_success() {
-        let mock = MockSessionRepository::default().with_count_result(Ok(5));
+        let mut mock = MockSessionRepository::new();
+        mock.expect_count().return_const(Ok(5));
         let svc = create_use_cases(mock);
 
         let result = svc.count().await;
@@ -313,7 +256,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_count_zero() {
-        let mock = MockSessionRepository::default().with_count_result(Ok(0));
+        let mut mock = MockSessionRepository::new();
+        mock.expect_count().return_const(Ok(0));
         let svc = create_use_cases(mock);
 
         let result = svc.count().await;
@@ -328,24 +272,26 @@ mod tests {
     #[tokio::test]
     async fn test_store_success() {
         let expected = fake_session("sess-1");
-        let mock = MockSessionRepository::default().with_store_result(Ok(expected.clone()));
+        let mut mock = MockSessionRepository::new();
+        mock.expect_store().return_const(Ok(expected.clone()));
         let svc = create_use_cases(mock);
 
-        let new_session = NewSession::new("sess-1", "session-data", Utc::now() + Duration::hours(1)).unwrap();
+        let new_session = NewSession::new(SessionId::new("sess-1"), "session-data", Utc::now() + Duration::hours(1)).unwrap();
         let result = svc.store(new_session).await;
 
         assert!(result.is_ok());
         let session = result.unwrap();
-        assert_eq!(session.id, "sess-1");
+        assert_eq!(session.id.as_str(), "sess-1");
         assert_eq!(session.session, "session-data");
     }
 
     #[tokio::test]
     async fn test_store_propagates_error() {
-        let mock = MockSessionRepository::default().with_store_result(Err(Error::Infrastructure("db error".into())));
+        let mut mock = MockSessionRepository::new();
+        mock.expect_store().return_const(Err(Error::Infrastructure("db error".into())));
         let svc = create_use_cases(mock);
 
-        let new_session = NewSession::new("sess-1", "session-data", Utc::now() + Duration::hours(1)).unwrap();
+        let new_session = NewSession::new(SessionId::new("sess-1"), "session-data", Utc::now() + Duration::hours(1)).unwrap();
         let result = svc.store(new_session).await;
 
         assert!(result.is_err());
@@ -357,23 +303,27 @@ mod tests {
     #[tokio::test]
     async fn test_load_found() {
         let expected = fake_session("sess-1");
-        let mock = MockSessionRepository::default().with_load_result(Ok(Some(expected.clone())));
+        let mut mock = MockSessionRepository::new();
+        mock.expect_load()
+            .withf(|_, id| id.as_str() == "sess-1")
+            .return_const(Ok(Some(expected.clone())));
         let svc = create_use_cases(mock);
 
-        let result = svc.load("sess-1").await;
+        let result = svc.load(&SessionId::new("sess-1")).await;
 
         assert!(result.is_ok());
         let session = result.unwrap();
         assert!(session.is_some());
-        assert_eq!(session.unwrap().id, "sess-1");
+        assert_eq!(session.unwrap().id.as_str(), "sess-1");
     }
 
     #[tokio::test]
     async fn test_load_not_found() {
-        let mock = MockSessionRepository::default().with_load_result(Ok(None));
+        let mut mock = MockSessionRepository::new();
+        mock.expect_load().return_const(Ok(None));
         let svc = create_use_cases(mock);
 
-        let result = svc.load("nonexistent").await;
+        let result = svc.load(&SessionId::new("nonexistent")).await;
 
         assert!(result.is_ok());
         assert!(result.unwrap().is_none());
@@ -384,20 +334,22 @@ mod tests {
     // ===================
     #[tokio::test]
     async fn test_delete_by_id_success() {
-        let mock = MockSessionRepository::default().with_delete_by_id_result(Ok(()));
+        let mut mock = MockSessionRepository::new();
+        mock.expect_delete_by_id().return_const(Ok(()));
         let svc = create_use_cases(mock);
 
-        let result = svc.delete_by_id("sess-1").await;
+        let result = svc.delete_by_id(&SessionId::new("sess-1")).await;
 
         assert!(result.is_ok());
     }
 
     #[tokio::test]
     async fn test_delete_by_id_propagates_error() {
-        let mock = MockSessionRepository::default().with_delete_by_id_result(Err(Error::Infrastructure("db error".into())));
+        let mut mock = MockSessionRepository::new();
+        mock.expect_delete_by_id().return_const(Err(Error::Infrastructure("db error".into())));
         let svc = create_use_cases(mock);
 
-        let result = svc.delete_by_id("sess-1").await;
+        let result = svc.delete_by_id(&SessionId::new("sess-1")).await;
 
         assert!(result.is_err());
     }
@@ -407,10 +359,11 @@ mod tests {
     // ===================
     #[tokio::test]
     async fn test_exists_true() {
-        let mock = MockSessionRepository::default().with_exists_result(Ok(true));
+        let mut mock = MockSessionRepository::new();
+        mock.expect_exists().return_const(Ok(true));
         let svc = create_use_cases(mock);
 
-        let result = svc.exists("sess-1").await;
+        let result = svc.exists(&SessionId::new("sess-1")).await;
 
         assert!(result.is_ok());
         assert!(result.unwrap());
@@ -418,10 +371,11 @@ mod tests {
 
     #[tokio::test]
     async fn test_exists_false() {
-        let mock = MockSessionRepository::default().with_exists_result(Ok(false));
+        let mut mock = MockSessionRepository::new();
+        mock.expect_exists().return_const(Ok(false));
         let svc = create_use_cases(mock);
 
-        let result = svc.exists("nonexistent").await;
+        let result = svc.exists(&SessionId::new("nonexistent")).await;
 
         assert!(result.is_ok());
         assert!(!result.unwrap());
@@ -432,8 +386,9 @@ mod tests {
     // ===================
     #[tokio::test]
     async fn test_delete_by_expiry_success() {
-        let expired_ids = vec!["sess-1".to_string(), "sess-2".to_string()];
-        let mock = MockSessionRepository::default().with_delete_by_expiry_result(Ok(expired_ids.clone()));
+        let expired_ids = vec![SessionId::new("sess-1"), SessionId::new("sess-2")];
+        let mut mock = MockSessionRepository::new();
+        mock.expect_delete_by_expiry().return_const(Ok(expired_ids.clone()));
         let svc = create_use_cases(mock);
 
         let result = svc.delete_by_expiry().await;
@@ -444,7 +399,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_delete_by_expiry_none_expired() {
-        let mock = MockSessionRepository::default().with_delete_by_expiry_result(Ok(vec![]));
+        let mut mock = MockSessionRepository::new();
+        mock.expect_delete_by_expiry().return_const(Ok(vec![]));
         let svc = create_use_cases(mock);
 
         let result = svc.delete_by_expiry().await;
@@ -458,7 +414,8 @@ mod tests {
     // ===================
     #[tokio::test]
     async fn test_delete_all_success() {
-        let mock = MockSessionRepository::default().with_delete_all_result(Ok(()));
+        let mut mock = MockSessionRepository::new();
+        mock.expect_delete_all().return_const(Ok(()));
         let svc = create_use_cases(mock);
 
         let result = svc.delete_all().await;
@@ -471,8 +428,9 @@ mod tests {
     // ===================
     #[tokio::test]
     async fn test_get_ids_success() {
-        let ids = vec!["sess-1".to_string(), "sess-2".to_string(), "sess-3".to_string()];
-        let mock = MockSessionRepository::default().with_get_ids_result(Ok(ids.clone()));
+        let ids = vec![SessionId::new("sess-1"), SessionId::new("sess-2"), SessionId::new("sess-3")];
+        let mut mock = MockSessionRepository::new();
+        mock.expect_get_ids().return_const(Ok(ids.clone()));
         let svc = create_use_cases(mock);
 
         let result = svc.get_ids().await;
@@ -483,7 +441,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_ids_empty() {
-        let mock = MockSessionRepository::default().with_get_ids_result(Ok(vec![]));
+        let mut mock = MockSessionRepository::new();
+        mock.expect_get_ids().return_const(Ok(vec![]));
         let svc = create_use_cases(mock);
 
         let result = svc.get_ids().await;
@@ -491,4 +450,113 @@ mod tests {
         assert!(result.is_ok());
         assert!(result.unwrap().is_empty());
     }
+
+    // ===================
+    // Tests: impersonation
+    // ===================
+    fn impersonation_session(impersonation: &Impersonation) -> Session {
+        let session = impersonation.to_new_session();
+        SessionBuilder::default()
+            .id(session.id)
+            .session(session.session)
+            .expires_at(session.expires_at)
+            .build()
+            .unwrap()
+    }
+
+    fn fake_impersonation(expires_at: DateTime<Utc>) -> Impersonation {
+        Impersonation {
+            token: ImpersonationToken::generate(),
+            user_id: UserId::new(2),
+            impersonator_id: UserId::new(1),
+            tenant: Some("acme".to_string()),
+            expires_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_start_impersonation_stores_session() {
+        let mut mock = MockSessionRepository::new();
+        mock.expect_store().return_const(Ok(fake_session("stored")));
+        let svc = create_use_cases_with_users(mock, &[UserId::new(1), UserId::new(2)]);
+
+        let mut context = RequestContext::internal();
+        context.tenant = Some("acme".to_string());
+
+        let impersonation = svc.start_impersonation(&context, UserId::new(1), UserId::new(2)).await.unwrap();
+
+        assert_eq!(impersonation.impersonator_id, UserId::new(1));
+        assert_eq!(impersonation.user_id, UserId::new(2));
+        assert_eq!(impersonation.tenant.as_deref(), Some("acme"));
+        assert_eq!(impersonation.expires_at, FixedClock::epoch() + IMPERSONATION_TTL);
+    }
+
+    #[tokio::test]
+    async fn test_start_impersonation_of_self_is_rejected() {
+        let mut mock = MockSessionRepository::new();
+        mock.expect_store().never();
+        let svc = create_use_cases_with_users(mock, &[UserId::new(1)]);
+
+        let result = svc.start_impersonation(&RequestContext::internal(), UserId::new(1), UserId::new(1)).await;
+
+        assert!(matches!(result, Err(Error::SelfImpersonation)));
+    }
+
+    #[tokio::test]
+    async fn test_start_impersonation_of_unknown_user_is_not_found() {
+        let svc = create_use_cases_with_users(MockSessionRepository::new(), &[UserId::new(1)]);
+
+        let result = svc.start_impersonation(&RequestContext::internal(), UserId::new(1), UserId::new(2)).await;
+
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_find_impersonation_reads_session() {
+        let impersonation = fake_impersonation(FixedClock::epoch() + IMPERSONATION_TTL);
+        let mut mock = MockSessionRepository::new();
+        mock.expect_load().return_const(Ok(Some(impersonation_session(&impersonation))));
+        let svc = create_use_cases(mock);
+
+        let found = svc.find_impersonation(&RequestContext::internal(), impersonation.token).await.unwrap();
+
+        assert_eq!(found, Some(impersonation));
+    }
+
+    #[tokio::test]
+    async fn test_find_impersonation_ignores_expired() {
+        let impersonation = fake_impersonation(FixedClock::epoch());
+        let mut mock = MockSessionRepository::new();
+        mock.expect_load().return_const(Ok(Some(impersonation_session(&impersonation))));
+        let svc = create_use_cases(mock);
+
+        let found = svc.find_impersonation(&RequestContext::internal(), impersonation.token).await.unwrap();
+
+        assert_eq!(found, None);
+    }
+
+    #[tokio::test]
+    async fn test_end_impersonation_deletes_session() {
+        let impersonation = fake_impersonation(FixedClock::epoch() + IMPERSONATION_TTL);
+        let mut mock = MockSessionRepository::new();
+        mock.expect_load().return_const(Ok(Some(impersonation_session(&impersonation))));
+        let id = SessionId::from(impersonation.token);
+        mock.expect_delete_by_id().withf(move |_, deleted| *deleted == id).times(1).return_const(Ok(()));
+        let svc = create_use_cases(mock);
+
+        let ended = svc.end_impersonation(&RequestContext::internal(), impersonation.token).await.unwrap();
+
+        assert_eq!(ended, impersonation);
+    }
+
+    #[tokio::test]
+    async fn test_end_unknown_impersonation_is_not_found() {
+        let mut mock = MockSessionRepository::new();
+        mock.expect_load().return_const(Ok(None));
+        let svc = create_use_cases(mock);
+
+        let result = svc.end_impersonation(&RequestContext::internal(), ImpersonationToken::generate()).await;
+
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+    }
 }
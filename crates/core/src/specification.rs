@@ -0,0 +1,130 @@
+//! Filters composed from typed predicates with `and`, `or` and `!`, so use
+//! cases can ask repositories for exactly the records they need without a
+//! method per combination and without knowing how a repository queries.
+//!
+//! Each subject names the predicates it can be filtered by; repository
+//! adapters translate them into their own queries, and
+//! [`is_satisfied_by`](Specification::is_satisfied_by) checks them in
+//! memory, as test doubles do.
+
+use std::{fmt::Debug, ops::Not};
+
+/// A type that specifications can filter.
+pub trait Subject {
+    /// The conditions a single record can be tested for.
+    type Predicate: Debug + Clone + Send + Sync;
+
+    fn satisfies(&self, predicate: &Self::Predicate) -> bool;
+}
+
+/// A condition on `T`, built from its predicates.
+#[derive(Debug, Clone)]
+pub enum Specification<T: Subject> {
+    Is(T::Predicate),
+    /// Every one holds; holds for no specifications at all.
+    All(Vec<Specification<T>>),
+    /// At least one holds; fails for no specifications at all.
+    Any(Vec<Specification<T>>),
+    Not(Box<Specification<T>>),
+}
+
+impl<T: Subject> Specification<T> {
+    pub fn is(predicate: T::Predicate) -> Self {
+        Self::Is(predicate)
+    }
+
+    /// Holds when both `self` and `other` do.
+    pub fn and(self, other: Self) -> Self {
+        match self {
+            Self::All(mut specifications) => {
+                specifications.push(other);
+                Self::All(specifications)
+            }
+            specification => Self::All(vec![specification, other]),
+        }
+    }
+
+    /// Holds when `self`, `other` or both do.
+    pub fn or(self, other: Self) -> Self {
+        match self {
+            Self::Any(mut specifications) => {
+                specifications.push(other);
+                Self::Any(specifications)
+            }
+            specification => Self::Any(vec![specification, other]),
+        }
+    }
+
+    pub fn is_satisfied_by(&self, subject: &T) -> bool {
+        match self {
+            Self::Is(predicate) => subject.satisfies(predicate),
+            Self::All(specifications) => specifications.iter().all(|specification| specification.is_satisfied_by(subject)),
+            Self::Any(specifications) => specifications.iter().any(|specification| specification.is_satisfied_by(subject)),
+            Self::Not(specification) => !specification.is_satisfied_by(subject),
+        }
+    }
+}
+
+impl<T: Subject> Not for Specification<T> {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        match self {
+            Self::Not(specification) => *specification,
+            specification => Self::Not(Box::new(specification)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Specification, Subject};
+
+    impl Subject for i32 {
+        type Predicate = Number;
+
+        fn satisfies(&self, predicate: &Number) -> bool {
+            match predicate {
+                Number::Even => self % 2 == 0,
+                Number::Positive => *self > 0,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub enum Number {
+        Even,
+        Positive,
+    }
+
+    // ===================
+    // Tests: composition
+    // ===================
+    #[test]
+    fn test_and_flattens() {
+        let specification = Specification::<i32>::is(Number::Even)
+            .and(Specification::is(Number::Positive))
+            .and(Specification::is(Number::Even));
+
+        assert!(matches!(&specification, Specification::All(specifications) if specifications.len() == 3));
+        assert!(specification.is_satisfied_by(&2));
+        assert!(!specification.is_satisfied_by(&-2));
+        assert!(!specification.is_satisfied_by(&3));
+    }
+
+    #[test]
+    fn test_or_and_not() {
+        let specification = Specification::<i32>::is(Number::Even).or(!Specification::is(Number::Positive));
+
+        assert!(specification.is_satisfied_by(&2));
+        assert!(specification.is_satisfied_by(&-3));
+        assert!(!specification.is_satisfied_by(&3));
+        assert!(matches!(!!Specification::<i32>::is(Number::Even), Specification::Is(_)));
+    }
+
+    #[test]
+    fn test_empty_compositions() {
+        assert!(Specification::<i32>::All(Vec::new()).is_satisfied_by(&1));
+        assert!(!Specification::<i32>::Any(Vec::new()).is_satisfied_by(&1));
+    }
+}
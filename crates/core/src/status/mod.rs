@@ -0,0 +1,17 @@
+//! How the service and what it depends on are doing, for operators and
+//! load balancers.
+//!
+//! [`StatusService::status`] checks the database and its schema, reports
+//! the query cache and the job queue, and lists the server subsystems as
+//! [`Subsystems`] records them.
+
+pub mod model;
+pub mod service;
+pub mod subsystems;
+
+pub use model::{ComponentStatus, Health, SystemStatus};
+#[cfg(any(test, feature = "test-support"))]
+pub use service::MockStatusService;
+pub use service::StatusService;
+pub(crate) use service::StatusServiceImpl;
+pub use subsystems::{SubsystemState, Subsystems};
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+/// How a component is doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    Up,
+    /// Failing its check, or a subsystem shutting down.
+    Down,
+    /// Not configured in this deployment.
+    Disabled,
+}
+
+impl Health {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Health::Up => "up",
+            Health::Down => "down",
+            Health::Disabled => "disabled",
+        }
+    }
+}
+
+/// How one component is doing, e.g. the database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentStatus {
+    pub name: String,
+    pub health: Health,
+    /// How long the check took, for components checked with a round trip.
+    pub latency: Option<Duration>,
+    /// What the check found, e.g. the error of a failed check.
+    pub detail: Option<String>,
+}
+
+impl ComponentStatus {
+    pub fn new(name: impl Into<String>, health: Health, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            health,
+            latency: None,
+            detail: Some(detail.into()),
+        }
+    }
+}
+
+/// How every component is doing, in the order they were checked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemStatus {
+    pub components: Vec<ComponentStatus>,
+}
+
+impl SystemStatus {
+    /// Down if any component is, and otherwise up.
+    pub fn health(&self) -> Health {
+        if self.components.iter().any(|component| component.health == Health::Down) {
+            Health::Down
+        } else {
+            Health::Up
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ComponentStatus, Health, SystemStatus};
+
+    // ===================
+    // Tests: SystemStatus::health
+    // ===================
+    #[test]
+    fn test_health_is_up_with_disabled_components() {
+        let status = SystemStatus {
+            components: vec![
+                ComponentStatus::new("database", Health::Up, "reachable"),
+                ComponentStatus::new("job_queue", Health::Disabled, "not configured"),
+            ],
+        };
+
+        assert_eq!(status.health(), Health::Up);
+    }
+
+    #[test]
+    fn test_health_is_down_with_any_component_down() {
+        let status = SystemStatus {
+            components: vec![
+                ComponentStatus::new("database", Health::Down, "unreachable"),
+                ComponentStatus::new("cache", Health::Up, "in process"),
+            ],
+        };
+
+        assert_eq!(status.health(), Health::Down);
+    }
+}
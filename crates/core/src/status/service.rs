@@ -0,0 +1,227 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    Error,
+    jobs::JobQueue,
+    repository::RepositoryService,
+    status::{ComponentStatus, Health, SubsystemState, Subsystems, SystemStatus},
+};
+
+/// Time a check that reaches out may take before its component is reported
+/// down, so a hung dependency cannot hang the status.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Reports how the service and what it depends on are doing.
+#[cfg_attr(any(test, feature = "test-support"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait StatusService: Send + Sync {
+    /// Checks every component. Never fails: a component whose check fails
+    /// is reported down.
+    async fn status(&self) -> SystemStatus;
+}
+
+pub(crate) struct StatusServiceImpl {
+    repository_service: Arc<RepositoryService>,
+    job_queue: Arc<dyn JobQueue>,
+    subsystems: Subsystems,
+}
+
+impl StatusServiceImpl {
+    pub(crate) fn new(repository_service: Arc<RepositoryService>, job_queue: Arc<dyn JobQueue>, subsystems: Subsystems) -> Self {
+        Self {
+            repository_service,
+            job_queue,
+            subsystems,
+        }
+    }
+
+    /// Whether a transaction can be begun, and how long that takes.
+    async fn check_database(&self) -> ComponentStatus {
+        let started = Instant::now();
+        let result = checked(async {
+            let transaction = self.repository_service.repository().begin_read_only().await?;
+            transaction.rollback().await
+        })
+        .await;
+        ComponentStatus {
+            latency: Some(started.elapsed()),
+            ..match result {
+                Ok(()) => ComponentStatus::new("database", Health::Up, "reachable"),
+                Err(error) => ComponentStatus::new("database", Health::Down, error.to_string()),
+            }
+        }
+    }
+
+    /// Whether the tables are those the entities expect. The schema is
+    /// synced from the entities rather than migrated, so there is no
+    /// migration version to report.
+    async fn check_schema(&self) -> ComponentStatus {
+        let started = Instant::now();
+        let result = checked(self.repository_service.repository().stale_tables()).await;
+        ComponentStatus {
+            latency: Some(started.elapsed()),
+            ..match result {
+                Ok(stale) if stale.is_empty() => ComponentStatus::new("schema", Health::Up, "up to date"),
+                Ok(stale) => ComponentStatus::new("schema", Health::Down, format!("missing or out of date: {}", stale.join(", "))),
+                Err(error) => ComponentStatus::new("schema", Health::Down, error.to_string()),
+            }
+        }
+    }
+
+    fn check_cache(&self) -> ComponentStatus {
+        let ttl = self.repository_service.user_list_cache().ttl();
+        if ttl.is_zero() {
+            ComponentStatus::new("cache", Health::Disabled, "in process, no time to live")
+        } else {
+            ComponentStatus::new("cache", Health::Up, format!("in process, time to live {}ms", ttl.as_millis()))
+        }
+    }
+
+    fn check_job_queue(&self) -> ComponentStatus {
+        let health = self.job_queue.health();
+        let detail = match health {
+            Health::Up => "accepting jobs",
+            Health::Down => "nothing takes jobs off the queue",
+            Health::Disabled => "background jobs are not configured",
+        };
+        ComponentStatus::new("job_queue", health, detail)
+    }
+}
+
+/// `check`, failing once [`CHECK_TIMEOUT`] has passed.
+async fn checked<T>(check: impl Future<Output = Result<T, Error>>) -> Result<T, Error> {
+    tokio::time::timeout(CHECK_TIMEOUT, check)
+        .await
+        .unwrap_or_else(|_| Err(Error::Infrastructure(format!("no answer within {}s", CHECK_TIMEOUT.as_secs()))))
+}
+
+#[async_trait::async_trait]
+impl StatusService for StatusServiceImpl {
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn status(&self) -> SystemStatus {
+        let (database, schema) = tokio::join!(self.check_database(), self.check_schema());
+        let mut components = vec![database, schema, self.check_cache(), self.check_job_queue()];
+        components.extend(self.subsystems.list().into_iter().map(|(name, state)| {
+            let health = match state {
+                SubsystemState::Running => Health::Up,
+                SubsystemState::Stopping => Health::Down,
+            };
+            ComponentStatus::new(format!("subsystem:{name}"), health, state.as_str())
+        }));
+        SystemStatus { components }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::{StatusService as _, StatusServiceImpl};
+    use crate::{
+        Error,
+        jobs::{JobQueue, NoJobQueue, job_channel},
+        repository::{Repository, RepositoryService, Transaction},
+        status::{Health, SubsystemState, Subsystems, SystemStatus},
+        test_support::{MockSessionRepository, MockUserRepository, MockWebhookRepository, mock_repository_service},
+    };
+
+    // ===================
+    // Test Helpers
+    // ===================
+    /// A repository whose database cannot be reached.
+    struct UnreachableRepository;
+
+    #[async_trait::async_trait]
+    impl Repository for UnreachableRepository {
+        async fn begin_with_deadline(&self, _deadline: Option<std::time::Instant>) -> Result<Box<dyn Transaction>, Error> {
+            Err(Error::Infrastructure("connection refused".into()))
+        }
+
+        async fn begin_read_only_with_deadline(&self, _deadline: Option<std::time::Instant>) -> Result<Box<dyn Transaction>, Error> {
+            Err(Error::Infrastructure("connection refused".into()))
+        }
+
+        async fn stale_tables(&self) -> Result<Vec<String>, Error> {
+            Err(Error::Infrastructure("connection refused".into()))
+        }
+
+        async fn close(&self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    fn repository_service() -> Arc<RepositoryService> {
+        Arc::new(
+            mock_repository_service(MockUserRepository::new(), MockSessionRepository::new(), MockWebhookRepository::new())
+                .build()
+                .unwrap(),
+        )
+    }
+
+    fn component<'a>(status: &'a SystemStatus, name: &str) -> &'a crate::status::ComponentStatus {
+        status.components.iter().find(|component| component.name == name).unwrap()
+    }
+
+    // ===================
+    // Tests: status
+    // ===================
+    #[tokio::test]
+    async fn test_status_reports_every_component() {
+        let repository_service = repository_service();
+        repository_service.user_list_cache().set_ttl(Duration::from_secs(5));
+        let (job_queue, _receiver) = job_channel();
+        let subsystems = Subsystems::default();
+        subsystems.set("Api", SubsystemState::Running);
+        let service = StatusServiceImpl::new(repository_service, Arc::new(job_queue) as Arc<dyn JobQueue>, subsystems);
+
+        let status = service.status().await;
+
+        let names: Vec<_> = status.components.iter().map(|component| component.name.as_str()).collect();
+        assert_eq!(names, vec!["database", "schema", "cache", "job_queue", "subsystem:Api"]);
+        assert!(status.components.iter().all(|component| component.health == Health::Up));
+        assert!(component(&status, "database").latency.is_some());
+        assert_eq!(component(&status, "cache").detail.as_deref(), Some("in process, time to live 5000ms"));
+        assert_eq!(status.health(), Health::Up);
+    }
+
+    #[tokio::test]
+    async fn test_status_reports_unreachable_database_down() {
+        let repository_service = Arc::new(
+            mock_repository_service(MockUserRepository::new(), MockSessionRepository::new(), MockWebhookRepository::new())
+                .repository(Arc::new(UnreachableRepository) as Arc<dyn Repository>)
+                .build()
+                .unwrap(),
+        );
+        let service = StatusServiceImpl::new(repository_service, Arc::new(NoJobQueue), Subsystems::default());
+
+        let status = service.status().await;
+
+        let database = component(&status, "database");
+        assert_eq!(database.health, Health::Down);
+        assert_eq!(database.detail.as_deref(), Some("Infrastructure error: connection refused"));
+        assert_eq!(component(&status, "schema").health, Health::Down);
+        assert_eq!(component(&status, "cache").health, Health::Disabled);
+        assert_eq!(component(&status, "job_queue").health, Health::Disabled);
+        assert_eq!(status.health(), Health::Down);
+    }
+
+    #[tokio::test]
+    async fn test_status_reports_closed_job_queue_and_stopping_subsystems_down() {
+        let (job_queue, receiver) = job_channel();
+        drop(receiver);
+        let subsystems = Subsystems::default();
+        subsystems.set("Jobs", SubsystemState::Running);
+        subsystems.set_all(SubsystemState::Stopping);
+        let service = StatusServiceImpl::new(repository_service(), Arc::new(job_queue), subsystems);
+
+        let status = service.status().await;
+
+        assert_eq!(component(&status, "job_queue").health, Health::Down);
+        let jobs = component(&status, "subsystem:Jobs");
+        assert_eq!(jobs.health, Health::Down);
+        assert_eq!(jobs.detail.as_deref(), Some("stopping"));
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+/// Where a server subsystem is in its life.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubsystemState {
+    Running,
+    /// Shutdown was requested; it is finishing the work in flight.
+    Stopping,
+}
+
+impl SubsystemState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubsystemState::Running => "running",
+            SubsystemState::Stopping => "stopping",
+        }
+    }
+}
+
+/// Shared record of the server subsystems and their states, which the
+/// server keeps up to date as it starts and stops them. Clones share the
+/// record.
+#[derive(Debug, Clone, Default)]
+pub struct Subsystems(Arc<Mutex<BTreeMap<String, SubsystemState>>>);
+
+impl Subsystems {
+    pub fn set(&self, name: &str, state: SubsystemState) {
+        self.lock().insert(name.to_string(), state);
+    }
+
+    /// Moves every subsystem recorded to `state`.
+    pub fn set_all(&self, state: SubsystemState) {
+        self.lock().values_mut().for_each(|current| *current = state);
+    }
+
+    /// Every subsystem recorded, by name.
+    pub fn list(&self) -> Vec<(String, SubsystemState)> {
+        self.lock().iter().map(|(name, state)| (name.clone(), *state)).collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, SubsystemState>> {
+        // The record stays consistent even if a holder panicked.
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SubsystemState, Subsystems};
+
+    // ===================
+    // Tests: Subsystems
+    // ===================
+    #[test]
+    fn test_clones_share_states() {
+        let subsystems = Subsystems::default();
+        let clone = subsystems.clone();
+
+        clone.set("Jobs", SubsystemState::Running);
+        clone.set("Api", SubsystemState::Running);
+
+        assert_eq!(
+            subsystems.list(),
+            vec![("Api".to_string(), SubsystemState::Running), ("Jobs".to_string(), SubsystemState::Running)]
+        );
+    }
+
+    #[test]
+    fn test_set_all_moves_every_subsystem() {
+        let subsystems = Subsystems::default();
+        subsystems.set("Api", SubsystemState::Running);
+        subsystems.set("Jobs", SubsystemState::Running);
+
+        subsystems.set_all(SubsystemState::Stopping);
+
+        assert!(subsystems.list().iter().all(|(_, state)| *state == SubsystemState::Stopping));
+    }
+}
@@ -0,0 +1,106 @@
+//! Port for storing binary objects, such as avatars, outside the database.
+//!
+//! Adapters live in `hex-play-storage`. Clients fetch objects directly from
+//! the store through presigned URLs rather than through the API.
+
+use std::time::Duration;
+
+use crate::Error;
+
+/// An object read back from storage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredObject {
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+}
+
+#[async_trait::async_trait]
+pub trait ObjectStorage: Send + Sync {
+    /// Stores `bytes` under `key`, replacing any object already there.
+    async fn put(&self, key: &str, content_type: &str, bytes: Vec<u8>) -> Result<(), Error>;
+    /// Reads the object under `key`, `None` if there is none.
+    async fn get(&self, key: &str) -> Result<Option<StoredObject>, Error>;
+    /// Removes the object under `key`. Removing a missing object succeeds.
+    async fn delete(&self, key: &str) -> Result<(), Error>;
+    /// A URL anyone can fetch the object under `key` from until `expires_in`
+    /// has passed.
+    async fn presigned_url(&self, key: &str, expires_in: Duration) -> Result<String, Error>;
+}
+
+/// Storage for deployments without one configured. Every call fails with
+/// `Error::Storage`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoObjectStorage;
+
+#[async_trait::async_trait]
+impl ObjectStorage for NoObjectStorage {
+    async fn put(&self, _key: &str, _content_type: &str, _bytes: Vec<u8>) -> Result<(), Error> {
+        Err(not_configured())
+    }
+
+    async fn get(&self, _key: &str) -> Result<Option<StoredObject>, Error> {
+        Err(not_configured())
+    }
+
+    async fn delete(&self, _key: &str) -> Result<(), Error> {
+        Err(not_configured())
+    }
+
+    async fn presigned_url(&self, _key: &str, _expires_in: Duration) -> Result<String, Error> {
+        Err(not_configured())
+    }
+}
+
+fn not_configured() -> Error {
+    Error::Storage("object storage is not configured".into())
+}
+
+#[cfg(any(test, feature = "test-support"))]
+pub use memory::InMemoryObjectStorage;
+
+#[cfg(any(test, feature = "test-support"))]
+mod memory {
+    use std::{collections::BTreeMap, sync::Mutex, time::Duration};
+
+    use super::{ObjectStorage, StoredObject};
+    use crate::Error;
+
+    /// Storage kept in a map, with `memory://` URLs. Only available in test
+    /// builds.
+    #[derive(Debug, Default)]
+    pub struct InMemoryObjectStorage {
+        objects: Mutex<BTreeMap<String, StoredObject>>,
+    }
+
+    impl InMemoryObjectStorage {
+        /// Keys of every stored object, in order.
+        pub fn keys(&self) -> Vec<String> {
+            self.objects.lock().unwrap().keys().cloned().collect()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ObjectStorage for InMemoryObjectStorage {
+        async fn put(&self, key: &str, content_type: &str, bytes: Vec<u8>) -> Result<(), Error> {
+            let object = StoredObject {
+                content_type: content_type.to_string(),
+                bytes,
+            };
+            self.objects.lock().unwrap().insert(key.to_string(), object);
+            Ok(())
+        }
+
+        async fn get(&self, key: &str) -> Result<Option<StoredObject>, Error> {
+            Ok(self.objects.lock().unwrap().get(key).cloned())
+        }
+
+        async fn delete(&self, key: &str) -> Result<(), Error> {
+            self.objects.lock().unwrap().remove(key);
+            Ok(())
+        }
+
+        async fn presigned_url(&self, key: &str, expires_in: Duration) -> Result<String, Error> {
+            Ok(format!("memory://{key}?expires_in={}", expires_in.as_secs()))
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! Fluent builders for the users tests work with, e.g.
+//! `a_user().named("Ana").aged(30).persisted(&*user_service).await`.
+//!
+//! A fixture ends in one of three ways: [`build`](UserFixture::build) gives
+//! the user as a value, [`stubbed`](UserFixture::stubbed) makes a
+//! [`MockUserService`] find it, and [`persisted`](UserFixture::persisted)
+//! adds it through a real [`UserService`].
+//!
+//! Fixtures not given an id take the next one from a per-thread sequence
+//! starting at 1, and derive their name, email and token from it, so a test
+//! sees the same users on every run. The sequence restarts with each test
+//! thread; call [`reset_sequence`] when tests share one.
+
+use std::cell::Cell;
+
+use mockall::predicate::{always, eq};
+
+use crate::{
+    clock::FixedClock,
+    context::RequestContext,
+    types::{Age, Email},
+    user::{MockUserService, NewUser, User, UserBuilder, UserId, UserService, UserToken},
+};
+
+thread_local! {
+    static NEXT_USER_ID: Cell<u64> = const { Cell::new(1) };
+}
+
+/// Starts a user fixture with the next id of this thread's sequence.
+pub fn a_user() -> UserFixture {
+    let id = UserId::new(NEXT_USER_ID.with(|next| next.replace(next.get() + 1)));
+    UserFixture {
+        id,
+        name: None,
+        email: None,
+        age: Age::default(),
+        version: 0,
+    }
+}
+
+/// Restarts this thread's id sequence at 1.
+pub fn reset_sequence() {
+    NEXT_USER_ID.with(|next| next.set(1));
+}
+
+/// A user under construction. See the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct UserFixture {
+    id: UserId,
+    name: Option<String>,
+    email: Option<String>,
+    age: Age,
+    version: u64,
+}
+
+impl UserFixture {
+    /// Uses `id` instead of the next one in the sequence. The token follows
+    /// the id.
+    pub fn with_id(mut self, id: UserId) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn with_email(mut self, email: impl Into<String>) -> Self {
+        self.email = Some(email.into());
+        self
+    }
+
+    pub fn aged(mut self, age: i16) -> Self {
+        self.age = Age::new(age).expect("fixture age should be valid");
+        self
+    }
+
+    pub fn at_version(mut self, version: u64) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// The user, with timestamps frozen at [`FixedClock::epoch`].
+    pub fn build(self) -> User {
+        let new_user = self.to_new_user();
+        UserBuilder::default()
+            .id(self.id)
+            .version(self.version)
+            .token(UserToken::new(self.id))
+            .name(new_user.name)
+            .email(new_user.email)
+            .age(new_user.age)
+            .created_at(FixedClock::epoch())
+            .updated_at(FixedClock::epoch())
+            .build()
+            .expect("fixture user should build")
+    }
+
+    /// The user as it would be submitted for creation.
+    pub fn new_user(self) -> NewUser {
+        self.to_new_user()
+    }
+
+    /// Builds the user and has `mock` return it from `find_by_id`,
+    /// `find_by_token` and `find_by_email`. Several users can be stubbed on
+    /// the same mock.
+    pub fn stubbed(self, mock: &mut MockUserService) -> User {
+        let user = self.build();
+        mock.expect_find_by_id().with(always(), eq(user.id)).return_const(Ok(Some(user.clone())));
+        mock.expect_find_by_token().with(always(), eq(user.token)).return_const(Ok(Some(user.clone())));
+        mock.expect_find_by_email()
+            .with(always(), eq(user.email.clone()))
+            .return_const(Ok(Some(user.clone())));
+        user
+    }
+
+    /// Adds the user through `user_service` and returns it as stored. The
+    /// store assigns the id, token and version, so only the name, email and
+    /// age carry over.
+    pub async fn persisted(self, user_service: &dyn UserService) -> User {
+        user_service
+            .add_user(&RequestContext::internal(), self.new_user())
+            .await
+            .expect("fixture user should persist")
+    }
+
+    fn to_new_user(&self) -> NewUser {
+        let id = self.id;
+        let name = self.name.clone().unwrap_or_else(|| format!("User {id}"));
+        let email = self.email.clone().unwrap_or_else(|| format!("user{id}@example.com"));
+        NewUser {
+            name,
+            email: Email::new(email).expect("fixture email should be valid"),
+            age: self.age,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{a_user, reset_sequence};
+    use crate::{
+        context::RequestContext,
+        test_support::MockUserService,
+        user::{User, UserId, UserService, UserToken},
+    };
+
+    // ===================
+    // Tests: a_user
+    // ===================
+    #[test]
+    fn test_ids_follow_the_sequence() {
+        reset_sequence();
+
+        let first = a_user().build();
+        let second = a_user().build();
+
+        assert_eq!((first.id, second.id), (UserId::new(1), UserId::new(2)));
+        assert_eq!(second.token, UserToken::new(UserId::new(2)));
+    }
+
+    #[test]
+    fn test_defaults_derive_from_the_id() {
+        let user = a_user().with_id(UserId::new(7)).build();
+
+        assert_eq!(user.name, "User 7");
+        assert_eq!(user.email.as_str(), "user7@example.com");
+        assert_eq!(user.age.value(), 0);
+    }
+
+    #[test]
+    fn test_overrides_are_kept() {
+        let user = a_user().named("Ana").with_email("ana@example.com").aged(30).at_version(3).build();
+
+        assert_eq!(user.name, "Ana");
+        assert_eq!(user.email.as_str(), "ana@example.com");
+        assert_eq!(user.age.value(), 30);
+        assert_eq!(user.version, 3);
+    }
+
+    // ===================
+    // Tests: stubbed
+    // ===================
+    #[tokio::test]
+    async fn test_stubbed_users_are_found() {
+        let mut mock = MockUserService::new();
+        let ana = a_user().named("Ana").stubbed(&mut mock);
+        let bob = a_user().named("Bob").stubbed(&mut mock);
+        let context = RequestContext::internal();
+
+        let by_id = mock.find_by_id(&context, ana.id).await.unwrap().unwrap();
+        let by_token = mock.find_by_token(&context, bob.token).await.unwrap().unwrap();
+        let by_email = mock.find_by_email(&context, ana.email.clone()).await.unwrap().unwrap();
+
+        assert_eq!(by_id.name, "Ana");
+        assert_eq!(by_token.name, "Bob");
+        assert_eq!(by_email.id, ana.id);
+    }
+
+    // ===================
+    // Tests: persisted
+    // ===================
+    #[tokio::test]
+    async fn test_persisted_adds_the_user() {
+        let mut mock = MockUserService::new();
+        mock.expect_add_user()
+            .withf(|_, user| user.name == "Ana" && user.age.value() == 30)
+            .times(1)
+            .returning(|_, user| Ok(User::fake(42, user.name, user.email.into_inner())));
+
+        let user = a_user().named("Ana").aged(30).persisted(&mock).await;
+
+        assert_eq!(user.id, UserId::new(42));
+    }
+}
@@ -0,0 +1,139 @@
+//! Test utilities for mocking core services and repositories.
+//! Only compiled for core's own tests and when the `test-support` feature is
+//! enabled.
+//!
+//! The service and repository mocks are generated by `mockall`, so tests
+//! set expectations on them, e.g.
+//! `mock.expect_find_by_id().with(always(), eq(1)).return_const(Ok(None))`.
+//! A call no expectation matches panics.
+//!
+//! [`fixtures`] builds the users those tests work with.
+
+pub mod fixtures;
+
+use std::{any::Any, sync::Arc, time::Instant};
+
+use crate::{
+    CoreServices, Error,
+    cache::QueryCache,
+    clock::SystemClock,
+    feature_flags::InMemoryFeatureFlags,
+    lease::LeaseRepository,
+    log_filter::NoLogFilter,
+    maintenance::MaintenanceMode,
+    reporting::NoErrorReporter,
+    repository::{Repository, RepositoryServiceBuilder, Transaction},
+    session::SessionRepository,
+    status::{ComponentStatus, Health, Subsystems, SystemStatus},
+    user::{PageSizeCap, UserQueryRepository, UserRepository},
+    webhook::WebhookRepository,
+};
+pub use crate::{
+    activity::MockActivityService,
+    avatar::MockAvatarService,
+    lease::{MockLeaseRepository, MockLeaseService},
+    log_filter::MockLogFilter,
+    reporting::MockErrorReporter,
+    session::{MockSessionRepository, MockSessionService},
+    status::MockStatusService,
+    user::{MockUserQueryRepository, MockUserRepository, MockUserService},
+    user_info::MockUserInfoService,
+    webhook::{MockWebhookRepository, MockWebhookService},
+};
+
+/// A transaction that commits and rolls back without doing anything.
+pub struct MockTransaction;
+
+#[async_trait::async_trait]
+impl Transaction for MockTransaction {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    async fn commit(self: Box<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn rollback(self: Box<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// A repository whose transactions are [`MockTransaction`]s.
+pub struct MockRepository;
+
+#[async_trait::async_trait]
+impl Repository for MockRepository {
+    async fn begin_with_deadline(&self, _deadline: Option<Instant>) -> Result<Box<dyn Transaction>, Error> {
+        Ok(Box::new(MockTransaction))
+    }
+
+    async fn begin_read_only_with_deadline(&self, _deadline: Option<Instant>) -> Result<Box<dyn Transaction>, Error> {
+        Ok(Box::new(MockTransaction))
+    }
+
+    async fn close(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Starts a repository service over [`MockRepository`] and the given
+/// repository mocks, leaving the clock and maintenance mode to the caller.
+/// The user query and lease repositories are mocks without expectations;
+/// tests of listings and leases set their own.
+pub fn mock_repository_service(
+    user_repository: MockUserRepository,
+    session_repository: MockSessionRepository,
+    webhook_repository: MockWebhookRepository,
+) -> RepositoryServiceBuilder {
+    RepositoryServiceBuilder::default()
+        .repository(Arc::new(MockRepository) as Arc<dyn Repository>)
+        .user_repository(Arc::new(user_repository) as Arc<dyn UserRepository>)
+        .user_query_repository(Arc::new(MockUserQueryRepository::new()) as Arc<dyn UserQueryRepository>)
+        .session_repository(Arc::new(session_repository) as Arc<dyn SessionRepository>)
+        .webhook_repository(Arc::new(webhook_repository) as Arc<dyn WebhookRepository>)
+        .lease_repository(Arc::new(MockLeaseRepository::new()) as Arc<dyn LeaseRepository>)
+}
+
+/// Creates a CoreServices instance with the given mock UserService.
+///
+/// The other services are mocks without expectations, so any call to them
+/// panics. Error reports are dropped and the log filter cannot be changed.
+pub fn create_core_services_with_mock(mock: MockUserService) -> CoreServices {
+    CoreServices {
+        user_service: Arc::new(mock),
+        user_info_service: Arc::new(MockUserInfoService::new()),
+        session_service: Arc::new(MockSessionService::new()),
+        avatar_service: Arc::new(MockAvatarService::new()),
+        activity_service: Arc::new(MockActivityService::new()),
+        webhook_service: Arc::new(MockWebhookService::new()),
+        lease_service: Arc::new(MockLeaseService::new()),
+        status_service: Arc::new(MockStatusService::new()),
+        clock: Arc::new(SystemClock),
+        maintenance_mode: MaintenanceMode::default(),
+        page_size_cap: PageSizeCap::default(),
+        user_list_cache: QueryCache::new("user_list"),
+        feature_flags: Arc::new(InMemoryFeatureFlags::default()),
+        error_reporter: Arc::new(NoErrorReporter),
+        log_filter: Arc::new(NoLogFilter),
+        subsystems: Subsystems::default(),
+    }
+}
+
+/// A status service reporting the database up, for tests calling the
+/// status as a ping.
+pub fn mock_status_service_up() -> MockStatusService {
+    let mut mock = MockStatusService::new();
+    mock.expect_status().returning(|| SystemStatus {
+        components: vec![ComponentStatus::new("database", Health::Up, "reachable")],
+    });
+    mock
+}
+
+/// Creates an Arc-wrapped CoreServices instance with the given mock
+/// UserService.
+///
+/// This is a convenience function for tests that need an Arc<CoreServices>.
+pub fn create_arc_core_services_with_mock(mock: MockUserService) -> Arc<CoreServices> {
+    Arc::new(create_core_services_with_mock(mock))
+}
@@ -4,12 +4,17 @@
 
 use std::fmt;
 
+use hex_play_utils::sensitive::Sensitive;
 use serde::{Deserialize, Serialize, de};
 
-use crate::Error;
+use crate::{Error, i18n::ValidationMessage};
 
 /// A validated email address that must contain '@'.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+///
+/// `Debug` output is redacted so emails stay out of trace spans and logs;
+/// `Display` and serialization give the address itself, for responses and
+/// exports. Logs that need to tell addresses apart use [`Email::redacted`].
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Email(String);
 
 impl Email {
@@ -21,7 +26,7 @@ impl Email {
     pub fn new(email: impl Into<String>) -> Result<Self, Error> {
         let email = email.into();
         if !email.contains('@') {
-            return Err(Error::Validation(format!("Invalid email format: {}", email)));
+            return Err(Error::Validation(ValidationMessage::InvalidEmail { email }));
         }
         Ok(Self(email))
     }
@@ -35,6 +40,18 @@ impl Email {
     pub fn into_inner(self) -> String {
         self.0
     }
+
+    /// Displays as `j***@example.com`, keeping the domain and the first
+    /// character of the local part, for log fields and audit records.
+    pub fn redacted(&self) -> RedactedEmail<'_> {
+        RedactedEmail(&self.0)
+    }
+}
+
+impl fmt::Debug for Email {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Email").field(&Sensitive::new(&self.0)).finish()
+    }
 }
 
 impl fmt::Display for Email {
@@ -43,6 +60,38 @@ impl fmt::Display for Email {
     }
 }
 
+/// An email address displayed with its local part masked, see
+/// [`Email::redacted`].
+#[derive(Clone, Copy)]
+pub struct RedactedEmail<'a>(&'a str);
+
+impl<'a> RedactedEmail<'a> {
+    /// Masks an address that has not been validated as an [`Email`] yet,
+    /// such as one straight from a request. Without an `@`, all of it is
+    /// masked.
+    pub fn new(email: &'a str) -> Self {
+        Self(email)
+    }
+}
+
+impl fmt::Display for RedactedEmail<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some((local, domain)) = self.0.rsplit_once('@') else {
+            return f.write_str("***");
+        };
+        match local.chars().next() {
+            Some(first) => write!(f, "{first}***@{domain}"),
+            None => write!(f, "***@{domain}"),
+        }
+    }
+}
+
+impl fmt::Debug for RedactedEmail<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 impl AsRef<str> for Email {
     fn as_ref(&self) -> &str {
         &self.0
@@ -85,7 +134,11 @@ impl Age {
     /// Returns `Error::Validation` if age is outside 0-150 range.
     pub fn new(age: i16) -> Result<Self, Error> {
         if !(Self::MIN..=Self::MAX).contains(&age) {
-            return Err(Error::Validation(format!("Age must be between {} and {}, got {}", Self::MIN, Self::MAX, age)));
+            return Err(Error::Validation(ValidationMessage::AgeOutOfRange {
+                min: Self::MIN,
+                max: Self::MAX,
+                age,
+            }));
         }
         Ok(Self(age))
     }
@@ -154,6 +207,25 @@ mod tests {
         assert_eq!(format!("{}", email), "test@example.com");
     }
 
+    #[test]
+    fn test_email_debug_is_redacted() {
+        let email = Email::new("test@example.com").unwrap();
+        assert_eq!(format!("{:?}", email), "Email([REDACTED])");
+    }
+
+    #[test]
+    fn test_email_redacted_keeps_first_character_and_domain() {
+        let email = Email::new("test@example.com").unwrap();
+        assert_eq!(email.redacted().to_string(), "t***@example.com");
+    }
+
+    #[test]
+    fn test_redacted_email_of_unvalidated_address() {
+        assert_eq!(RedactedEmail::new("ünïcode@example.com").to_string(), "ü***@example.com");
+        assert_eq!(RedactedEmail::new("@example.com").to_string(), "***@example.com");
+        assert_eq!(RedactedEmail::new("not-an-email").to_string(), "***");
+    }
+
     #[test]
     fn test_email_into_inner() {
         let email = Email::new("test@example.com").unwrap();
@@ -0,0 +1,140 @@
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::{
+    Error,
+    avatar::StoredAvatar,
+    domain::Invariants as _,
+    repository::Transaction,
+    types::Email,
+    user::{NewUser, User, UserId, UserRepository, UserStats, UserToken},
+};
+
+/// A user repository refusing, with `Error::Validation`, to persist a user
+/// that breaks the [`Invariants`](crate::domain::Invariants). Units of work
+/// hand the user repository out wrapped in one, so no service can write a
+/// user without the check.
+pub struct CheckedUserRepository<'a> {
+    inner: &'a dyn UserRepository,
+}
+
+impl<'a> CheckedUserRepository<'a> {
+    pub fn new(inner: &'a dyn UserRepository) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait::async_trait]
+impl UserRepository for CheckedUserRepository<'_> {
+    async fn add_user(&self, transaction: &(dyn Transaction + 'static), user: NewUser) -> Result<User, Error> {
+        user.check_invariants()?;
+        self.inner.add_user(transaction, user).await
+    }
+
+    async fn upsert_by_email(&self, transaction: &(dyn Transaction + 'static), user: NewUser) -> Result<User, Error> {
+        user.check_invariants()?;
+        self.inner.upsert_by_email(transaction, user).await
+    }
+
+    async fn update_user(&self, transaction: &(dyn Transaction + 'static), user: User) -> Result<User, Error> {
+        user.check_invariants()?;
+        self.inner.update_user(transaction, user).await
+    }
+
+    async fn delete_user(&self, transaction: &(dyn Transaction + 'static), user: User) -> Result<User, Error> {
+        self.inner.delete_user(transaction, user).await
+    }
+
+    async fn count_users(&self, transaction: &(dyn Transaction + 'static)) -> Result<u64, Error> {
+        self.inner.count_users(transaction).await
+    }
+
+    async fn find_by_id(&self, transaction: &(dyn Transaction + 'static), id: UserId) -> Result<Option<User>, Error> {
+        self.inner.find_by_id(transaction, id).await
+    }
+
+    async fn find_by_email(&self, transaction: &(dyn Transaction + 'static), email: &Email) -> Result<Option<User>, Error> {
+        self.inner.find_by_email(transaction, email).await
+    }
+
+    async fn find_by_token(&self, transaction: &(dyn Transaction + 'static), token: UserToken) -> Result<Option<User>, Error> {
+        self.inner.find_by_token(transaction, token).await
+    }
+
+    async fn find_by_ids(&self, transaction: &(dyn Transaction + 'static), ids: &[UserId]) -> Result<Vec<User>, Error> {
+        self.inner.find_by_ids(transaction, ids).await
+    }
+
+    async fn find_by_tokens(&self, transaction: &(dyn Transaction + 'static), tokens: &[UserToken]) -> Result<Vec<User>, Error> {
+        self.inner.find_by_tokens(transaction, tokens).await
+    }
+
+    async fn find_avatar(&self, transaction: &(dyn Transaction + 'static), id: UserId) -> Result<Option<StoredAvatar>, Error> {
+        self.inner.find_avatar(transaction, id).await
+    }
+
+    async fn set_avatar_key(&self, transaction: &(dyn Transaction + 'static), id: UserId, key: Option<String>) -> Result<Option<String>, Error> {
+        self.inner.set_avatar_key(transaction, id, key).await
+    }
+
+    async fn mark_avatar_variants_ready(&self, transaction: &(dyn Transaction + 'static), id: UserId, key: &str) -> Result<bool, Error> {
+        self.inner.mark_avatar_variants_ready(transaction, id, key).await
+    }
+
+    async fn record_last_seen(&self, transaction: &(dyn Transaction + 'static), seen: &[(UserId, DateTime<Utc>)]) -> Result<(), Error> {
+        self.inner.record_last_seen(transaction, seen).await
+    }
+
+    async fn user_stats(&self, transaction: &(dyn Transaction + 'static), active_since: DateTime<Utc>, signups_since: NaiveDate) -> Result<UserStats, Error> {
+        self.inner.user_stats(transaction, active_since, signups_since).await
+    }
+
+    async fn refresh_signup_rollups(&self, transaction: &(dyn Transaction + 'static), since: Option<NaiveDate>) -> Result<u64, Error> {
+        self.inner.refresh_signup_rollups(transaction, since).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CheckedUserRepository;
+    use crate::{
+        Error,
+        i18n::ValidationMessage,
+        test_support::{MockTransaction, MockUserRepository},
+        user::{NewUser, User, UserRepository as _},
+    };
+
+    // ===================
+    // Tests: CheckedUserRepository
+    // ===================
+    #[tokio::test]
+    async fn test_checked_repository_refuses_blank_name_without_writing() {
+        let mut inner = MockUserRepository::new();
+        inner.expect_add_user().never();
+        inner.expect_upsert_by_email().never();
+        inner.expect_update_user().never();
+        let repository = CheckedUserRepository::new(&inner);
+        let blank = || NewUser::new(" ", "john@example.com", 30).unwrap();
+
+        let added = repository.add_user(&MockTransaction, blank()).await;
+        let upserted = repository.upsert_by_email(&MockTransaction, blank()).await;
+        let updated = repository.update_user(&MockTransaction, User::fake(1, "", "john@example.com")).await;
+
+        assert!(matches!(added, Err(Error::Validation(ValidationMessage::BlankName))));
+        assert!(matches!(upserted, Err(Error::Validation(ValidationMessage::BlankName))));
+        assert!(matches!(updated, Err(Error::Validation(ValidationMessage::BlankName))));
+    }
+
+    #[tokio::test]
+    async fn test_checked_repository_writes_valid_user() {
+        let mut inner = MockUserRepository::new();
+        inner.expect_update_user().times(1).returning(|_, user| Ok(user));
+        let repository = CheckedUserRepository::new(&inner);
+
+        let updated = repository
+            .update_user(&MockTransaction, User::fake(1, "John Doe", "john@example.com"))
+            .await
+            .unwrap();
+
+        assert_eq!(updated.name, "John Doe");
+    }
+}
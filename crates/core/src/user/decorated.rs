@@ -0,0 +1,201 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::{
+    Error,
+    avatar::StoredAvatar,
+    repository::Transaction,
+    types::Email,
+    user::{NewUser, User, UserId, UserRepository, UserStats, UserToken},
+};
+
+/// Behaviour run around every operation of a repository, such as tracing or
+/// metrics, added by wrapping the repository in a
+/// [`DecoratedUserRepository`].
+pub trait OperationLayer: Send + Sync + 'static {
+    /// Runs `operation`, named after the repository method it calls, e.g.
+    /// `find_by_id`.
+    fn call<T: Send>(&self, op: &'static str, operation: impl Future<Output = Result<T, Error>> + Send) -> impl Future<Output = Result<T, Error>> + Send;
+}
+
+/// A user repository running each operation of `inner` through `layer`.
+/// Decorations nest, so a repository wrapped in several runs the layer
+/// applied last first.
+pub struct DecoratedUserRepository<L> {
+    layer: L,
+    inner: Arc<dyn UserRepository>,
+}
+
+impl<L: OperationLayer> DecoratedUserRepository<L> {
+    pub fn new(layer: L, inner: Arc<dyn UserRepository>) -> Self {
+        Self { layer, inner }
+    }
+}
+
+#[async_trait::async_trait]
+impl<L: OperationLayer> UserRepository for DecoratedUserRepository<L> {
+    async fn add_user(&self, transaction: &(dyn Transaction + 'static), user: NewUser) -> Result<User, Error> {
+        self.layer.call("add_user", self.inner.add_user(transaction, user)).await
+    }
+
+    async fn upsert_by_email(&self, transaction: &(dyn Transaction + 'static), user: NewUser) -> Result<User, Error> {
+        self.layer.call("upsert_by_email", self.inner.upsert_by_email(transaction, user)).await
+    }
+
+    async fn update_user(&self, transaction: &(dyn Transaction + 'static), user: User) -> Result<User, Error> {
+        self.layer.call("update_user", self.inner.update_user(transaction, user)).await
+    }
+
+    async fn delete_user(&self, transaction: &(dyn Transaction + 'static), user: User) -> Result<User, Error> {
+        self.layer.call("delete_user", self.inner.delete_user(transaction, user)).await
+    }
+
+    async fn count_users(&self, transaction: &(dyn Transaction + 'static)) -> Result<u64, Error> {
+        self.layer.call("count_users", self.inner.count_users(transaction)).await
+    }
+
+    async fn find_by_id(&self, transaction: &(dyn Transaction + 'static), id: UserId) -> Result<Option<User>, Error> {
+        self.layer.call("find_by_id", self.inner.find_by_id(transaction, id)).await
+    }
+
+    async fn find_by_email(&self, transaction: &(dyn Transaction + 'static), email: &Email) -> Result<Option<User>, Error> {
+        self.layer.call("find_by_email", self.inner.find_by_email(transaction, email)).await
+    }
+
+    async fn find_by_token(&self, transaction: &(dyn Transaction + 'static), token: UserToken) -> Result<Option<User>, Error> {
+        self.layer.call("find_by_token", self.inner.find_by_token(transaction, token)).await
+    }
+
+    async fn find_by_ids(&self, transaction: &(dyn Transaction + 'static), ids: &[UserId]) -> Result<Vec<User>, Error> {
+        self.layer.call("find_by_ids", self.inner.find_by_ids(transaction, ids)).await
+    }
+
+    async fn find_by_tokens(&self, transaction: &(dyn Transaction + 'static), tokens: &[UserToken]) -> Result<Vec<User>, Error> {
+        self.layer.call("find_by_tokens", self.inner.find_by_tokens(transaction, tokens)).await
+    }
+
+    async fn find_avatar(&self, transaction: &(dyn Transaction + 'static), id: UserId) -> Result<Option<StoredAvatar>, Error> {
+        self.layer.call("find_avatar", self.inner.find_avatar(transaction, id)).await
+    }
+
+    async fn set_avatar_key(&self, transaction: &(dyn Transaction + 'static), id: UserId, key: Option<String>) -> Result<Option<String>, Error> {
+        self.layer.call("set_avatar_key", self.inner.set_avatar_key(transaction, id, key)).await
+    }
+
+    async fn mark_avatar_variants_ready(&self, transaction: &(dyn Transaction + 'static), id: UserId, key: &str) -> Result<bool, Error> {
+        self.layer
+            .call("mark_avatar_variants_ready", self.inner.mark_avatar_variants_ready(transaction, id, key))
+            .await
+    }
+
+    async fn record_last_seen(&self, transaction: &(dyn Transaction + 'static), seen: &[(UserId, DateTime<Utc>)]) -> Result<(), Error> {
+        self.layer.call("record_last_seen", self.inner.record_last_seen(transaction, seen)).await
+    }
+
+    async fn user_stats(&self, transaction: &(dyn Transaction + 'static), active_since: DateTime<Utc>, signups_since: NaiveDate) -> Result<UserStats, Error> {
+        self.layer
+            .call("user_stats", self.inner.user_stats(transaction, active_since, signups_since))
+            .await
+    }
+
+    async fn refresh_signup_rollups(&self, transaction: &(dyn Transaction + 'static), since: Option<NaiveDate>) -> Result<u64, Error> {
+        self.layer
+            .call("refresh_signup_rollups", self.inner.refresh_signup_rollups(transaction, since))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use mockall::predicate::{always, eq};
+
+    use super::{DecoratedUserRepository, OperationLayer};
+    use crate::{
+        Error, RepositoryError,
+        test_support::{MockTransaction, MockUserRepository},
+        user::{UserId, UserRepository},
+    };
+
+    // ===================
+    // Helpers
+    // ===================
+    /// Records the operations it runs, as `<name>:<op>`.
+    struct RecordingLayer {
+        name: &'static str,
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl OperationLayer for RecordingLayer {
+        async fn call<T: Send>(&self, op: &'static str, operation: impl Future<Output = Result<T, Error>> + Send) -> Result<T, Error> {
+            self.calls.lock().unwrap().push(format!("{}:{op}", self.name));
+            operation.await
+        }
+    }
+
+    /// Fails every operation without running it.
+    struct FailingLayer;
+
+    impl OperationLayer for FailingLayer {
+        async fn call<T: Send>(&self, _op: &'static str, _operation: impl Future<Output = Result<T, Error>> + Send) -> Result<T, Error> {
+            Err(Error::RepositoryError(RepositoryError::Unavailable("layer".to_string())))
+        }
+    }
+
+    // ===================
+    // Tests: DecoratedUserRepository
+    // ===================
+    #[tokio::test]
+    async fn test_decorated_repository_runs_layers_outermost_first() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut inner = MockUserRepository::new();
+        inner.expect_count_users().times(1).returning(|_| Ok(7));
+        let inner_layer = RecordingLayer {
+            name: "inner",
+            calls: calls.clone(),
+        };
+        let outer_layer = RecordingLayer {
+            name: "outer",
+            calls: calls.clone(),
+        };
+        let repository = DecoratedUserRepository::new(inner_layer, Arc::new(inner));
+        let repository = DecoratedUserRepository::new(outer_layer, Arc::new(repository) as Arc<dyn UserRepository>);
+
+        let count = repository.count_users(&MockTransaction).await.unwrap();
+
+        assert_eq!(count, 7);
+        assert_eq!(*calls.lock().unwrap(), ["outer:count_users", "inner:count_users"]);
+    }
+
+    #[tokio::test]
+    async fn test_decorated_repository_passes_arguments_through() {
+        let mut inner = MockUserRepository::new();
+        inner
+            .expect_find_by_id()
+            .with(always(), eq(UserId::new(42)))
+            .times(1)
+            .returning(|_, _| Ok(None));
+        let repository = DecoratedUserRepository::new(
+            RecordingLayer {
+                name: "layer",
+                calls: Arc::default(),
+            },
+            Arc::new(inner),
+        );
+
+        assert!(repository.find_by_id(&MockTransaction, UserId::new(42)).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_decorated_repository_layer_may_skip_operation() {
+        let mut inner = MockUserRepository::new();
+        inner.expect_count_users().never();
+        let repository = DecoratedUserRepository::new(FailingLayer, Arc::new(inner));
+
+        let error = repository.count_users(&MockTransaction).await.unwrap_err();
+
+        assert!(matches!(error, Error::RepositoryError(RepositoryError::Unavailable(_))));
+    }
+}
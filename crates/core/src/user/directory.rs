@@ -0,0 +1,28 @@
+//! Port for an external directory of users, such as a company's identity
+//! provider, for deployments where some users live there rather than here.
+//!
+//! [`UserService::find_in_directory`](crate::user::UserService::find_in_directory)
+//! asks the directory, without adding the person it finds as a local user.
+//! Users are only added where they are provisioned, such as on their first
+//! sign-in. Adapters live in `hex-play-directory`.
+
+use crate::{Error, types::Email, user::NewUser};
+
+#[cfg_attr(any(test, feature = "test-support"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait UserDirectoryPort: Send + Sync {
+    /// The person with `email`, as they would be added locally, `None` if
+    /// the directory has no one with it.
+    async fn find_by_email(&self, email: &Email) -> Result<Option<NewUser>, Error>;
+}
+
+/// The directory of deployments without one configured, which knows no one.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoUserDirectory;
+
+#[async_trait::async_trait]
+impl UserDirectoryPort for NoUserDirectory {
+    async fn find_by_email(&self, _email: &Email) -> Result<Option<NewUser>, Error> {
+        Ok(None)
+    }
+}
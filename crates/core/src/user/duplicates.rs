@@ -0,0 +1,191 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::user::User;
+
+/// Trigram similarity from which two names count as potentially the same
+/// person, as `pg_trgm` computes it: shared trigrams over all trigrams.
+pub const NAME_SIMILARITY_THRESHOLD: f64 = 0.5;
+
+/// Why the users of a [`DuplicateGroup`] may be the same person.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateReason {
+    /// Their email local parts are the same once lowercased and stripped of
+    /// dots and `+` tags, e.g. `Jane.Doe+news@a.com` and `janedoe@b.com`.
+    EmailLocalPart,
+    /// Their names are at least [`NAME_SIMILARITY_THRESHOLD`] similar.
+    SimilarName,
+}
+
+/// Users that may be the same person, for review before merging them.
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    pub reason: DuplicateReason,
+    /// The normalized local part or name the group was found by.
+    pub key: String,
+    /// Two or more users, ordered by id.
+    pub users: Vec<User>,
+}
+
+/// Groups `users` by normalized email local part and by name similarity.
+/// Names are compared through an index from trigram to names, so only names
+/// sharing a trigram are scored. A user may be in one group of each kind.
+pub fn find_duplicate_groups(users: &[User]) -> Vec<DuplicateGroup> {
+    let mut groups = group_by_local_part(users);
+    groups.extend(group_by_similar_name(users));
+    groups
+}
+
+fn group_by_local_part(users: &[User]) -> Vec<DuplicateGroup> {
+    let mut by_local_part: BTreeMap<String, Vec<&User>> = BTreeMap::new();
+    for user in users {
+        by_local_part.entry(normalize_local_part(user.email.as_str())).or_default().push(user);
+    }
+
+    by_local_part
+        .into_iter()
+        .filter(|(key, users)| !key.is_empty() && users.len() > 1)
+        .map(|(key, users)| group(DuplicateReason::EmailLocalPart, key, users))
+        .collect()
+}
+
+fn group_by_similar_name(users: &[User]) -> Vec<DuplicateGroup> {
+    let names: Vec<String> = users.iter().map(|user| normalize_name(&user.name)).collect();
+    let trigrams: Vec<HashSet<String>> = names.iter().map(|name| name_trigrams(name)).collect();
+    let mut index: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, user_trigrams) in trigrams.iter().enumerate() {
+        for trigram in user_trigrams {
+            index.entry(trigram.as_str()).or_default().push(i);
+        }
+    }
+
+    // Connects every pair of similar names, then reports each connected set.
+    let mut parents: Vec<usize> = (0..users.len()).collect();
+    for (i, user_trigrams) in trigrams.iter().enumerate() {
+        let mut shared: HashMap<usize, usize> = HashMap::new();
+        for trigram in user_trigrams {
+            for &j in index[trigram.as_str()].iter().filter(|&&j| j > i) {
+                *shared.entry(j).or_default() += 1;
+            }
+        }
+        for (j, shared) in shared {
+            let all = user_trigrams.len() + trigrams[j].len() - shared;
+            if shared as f64 / all as f64 >= NAME_SIMILARITY_THRESHOLD {
+                let (root_i, root_j) = (find_root(&mut parents, i), find_root(&mut parents, j));
+                parents[root_i.max(root_j)] = root_i.min(root_j);
+            }
+        }
+    }
+
+    let mut by_root: BTreeMap<usize, Vec<&User>> = BTreeMap::new();
+    for (i, user) in users.iter().enumerate() {
+        by_root.entry(find_root(&mut parents, i)).or_default().push(user);
+    }
+    by_root
+        .into_iter()
+        .filter(|(_, users)| users.len() > 1)
+        .map(|(root, users)| group(DuplicateReason::SimilarName, names[root].clone(), users))
+        .collect()
+}
+
+fn group(reason: DuplicateReason, key: String, mut users: Vec<&User>) -> DuplicateGroup {
+    users.sort_by_key(|user| user.id);
+    DuplicateGroup {
+        reason,
+        key,
+        users: users.into_iter().cloned().collect(),
+    }
+}
+
+fn find_root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// The part before `@`, lowercased, without dots or a `+` tag.
+fn normalize_local_part(email: &str) -> String {
+    let local_part = email.split('@').next().unwrap_or_default();
+    let untagged = local_part.split('+').next().unwrap_or_default();
+    untagged.chars().filter(|c| *c != '.').flat_map(char::to_lowercase).collect()
+}
+
+/// Lowercased words of the name, separated by single spaces.
+fn normalize_name(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Trigrams of each word padded with two spaces in front and one behind,
+/// as `pg_trgm` forms them.
+fn name_trigrams(name: &str) -> HashSet<String> {
+    name.split(' ')
+        .filter(|word| !word.is_empty())
+        .flat_map(|word| {
+            let padded: Vec<char> = format!("  {word} ").chars().collect();
+            padded.windows(3).map(|window| window.iter().collect::<String>()).collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DuplicateReason, find_duplicate_groups, normalize_local_part, normalize_name};
+    use crate::user::User;
+
+    fn ids(users: &[User]) -> Vec<u64> {
+        users.iter().map(|user| user.id.value()).collect()
+    }
+
+    // ===================
+    // Tests: find_duplicate_groups
+    // ===================
+    #[test]
+    fn test_groups_by_normalized_local_part() {
+        let users = [
+            User::fake(1, "Jane", "Jane.Doe+news@example.com"),
+            User::fake(2, "Bob", "bob@example.com"),
+            User::fake(3, "J. Doe", "janedoe@other.org"),
+        ];
+
+        let groups = find_duplicate_groups(&users);
+
+        let group = groups.iter().find(|group| group.reason == DuplicateReason::EmailLocalPart).unwrap();
+        assert_eq!(group.key, "janedoe");
+        assert_eq!(ids(&group.users), [1, 3]);
+    }
+
+    #[test]
+    fn test_groups_similar_names() {
+        let users = [
+            User::fake(1, "Jonathan Smith", "jon@example.com"),
+            User::fake(2, "Jonathon Smith", "jsmith@example.com"),
+            User::fake(3, "Maria Garcia", "maria@example.com"),
+            User::fake(4, "jonathan  smith", "js@example.org"),
+        ];
+
+        let groups = find_duplicate_groups(&users);
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].reason, DuplicateReason::SimilarName);
+        assert_eq!(groups[0].key, "jonathan smith");
+        assert_eq!(ids(&groups[0].users), [1, 2, 4]);
+    }
+
+    #[test]
+    fn test_distinct_users_have_no_groups() {
+        let users = [User::fake(1, "Alice", "alice@example.com"), User::fake(2, "Bob", "bob@example.com")];
+
+        assert!(find_duplicate_groups(&users).is_empty());
+    }
+
+    #[test]
+    fn test_normalizes() {
+        assert_eq!(normalize_local_part("A.B+tag@x.com"), "ab");
+        assert_eq!(normalize_name("  Mary-Ann   O'Neil "), "mary ann o neil");
+    }
+}
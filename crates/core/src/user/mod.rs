@@ -1,12 +1,26 @@
+pub mod checked;
+pub mod decorated;
+pub mod directory;
+pub mod duplicates;
 pub mod model;
+pub mod query;
+pub mod reader;
 pub mod repository;
 pub mod service;
+pub mod specification;
+pub mod stats;
 
-#[cfg(feature = "test-support")]
-pub mod test_support;
+pub use checked::CheckedUserRepository;
+pub use decorated::{DecoratedUserRepository, OperationLayer};
+pub use directory::{NoUserDirectory, UserDirectoryPort};
+pub use duplicates::{DuplicateGroup, DuplicateReason, NAME_SIMILARITY_THRESHOLD};
 pub use model::{NewUser, PartialUserUpdate, User, UserBuilder, UserId, UserToken};
-pub use repository::UserRepository;
+pub use query::{UserListStamp, UserQueryRepository};
+pub use reader::UserReader;
+pub use repository::{DEFAULT_PAGE_SIZE, IMPORT_BATCH_SIZE, MAX_BATCH_SIZE, MAX_PAGE_SIZE, PageSizeCap, UserRepository, after_start_id, effective_page_size};
 pub use service::UserService;
 pub(crate) use service::UserServiceImpl;
-#[cfg(feature = "test-support")]
-pub use test_support::MockUserService;
+pub use specification::{UserPredicate, UserSpecification};
+pub use stats::{AGE_BUCKETS, AgeBucketCount, DailySignups, StatusCounts, UserStats};
+#[cfg(any(test, feature = "test-support"))]
+pub use {directory::MockUserDirectoryPort, query::MockUserQueryRepository, repository::MockUserRepository, service::MockUserService};
@@ -1,6 +1,12 @@
+use std::{fmt, num::ParseIntError, str::FromStr};
+
 use chrono::{DateTime, Utc};
 use derive_builder::Builder;
-use hex_play_utils::{define_token_prefix, token::Token};
+use hex_play_utils::{
+    define_token_prefix,
+    token::{Token, TokenError, TokenId},
+};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     Error,
@@ -8,12 +14,71 @@ use crate::{
 };
 
 define_token_prefix!(UserPrefix, "U_");
-pub type UserId = u64;
+
+/// Id of a user, which its [`UserToken`] encodes. A type of its own so it
+/// can't be passed where another number, such as a version or a count, is
+/// expected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UserId(u64);
+
+impl UserId {
+    pub const fn new(id: u64) -> Self {
+        Self(id)
+    }
+
+    /// Returns the id value.
+    pub const fn value(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for UserId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for UserId {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self)
+    }
+}
+
+impl From<UserId> for u64 {
+    fn from(id: UserId) -> Self {
+        id.0
+    }
+}
+
+impl TokenId for UserId {
+    const ZERO: Self = Self(0);
+    const ENCODED_LEN: usize = u64::ENCODED_LEN;
+
+    fn encode(self) -> String {
+        self.0.encode()
+    }
+
+    fn encode_to_buf(self, buf: &mut [u8]) {
+        self.0.encode_to_buf(buf);
+    }
+
+    fn decode(s: &str) -> Result<Self, TokenError> {
+        u64::decode(s).map(Self)
+    }
+
+    fn random_in_range(max: u128) -> Self {
+        Self(u64::random_in_range(max))
+    }
+}
+
 pub type UserToken = Token<UserPrefix, UserId, { i64::MAX as u128 }>;
 
 #[derive(Debug, Clone, Builder)]
 pub struct User {
-    #[builder(default = "0")]
+    #[builder(default)]
     pub id: UserId,
     #[builder(default = "0")]
     pub version: u64,
@@ -23,46 +88,42 @@ pub struct User {
     pub email: Email,
     #[builder(default)]
     pub age: Age,
-    #[builder(default = "Utc::now()")]
     pub created_at: DateTime<Utc>,
-    #[builder(default = "Utc::now()")]
     pub updated_at: DateTime<Utc>,
-}
-
-impl Default for User {
-    fn default() -> Self {
-        Self {
-            id: 0,
-            version: 0,
-            token: UserToken::generate(),
-            name: String::new(),
-            email: Email::new("default@example.com").expect("default email is valid"),
-            age: Age::default(),
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        }
-    }
+    /// When the user last made an authenticated request. Recorded in
+    /// batches without bumping `version` or `updated_at`, so it may lag by
+    /// the activity flush interval.
+    #[builder(default)]
+    pub last_seen_at: Option<DateTime<Utc>>,
 }
 
 impl User {
-    /// Creates a fake user with default timestamps and a generated token.
-    /// Only available in test builds.
+    /// Creates a fake user with id `id` and timestamps frozen at
+    /// [`FixedClock::epoch`]. Only available in test builds.
+    ///
+    /// [`FixedClock::epoch`]: crate::clock::FixedClock::epoch
     #[cfg(any(test, feature = "test-support"))]
-    pub fn fake(id: UserId, name: impl Into<String>, email: impl Into<String>) -> Self {
+    pub fn fake(id: u64, name: impl Into<String>, email: impl Into<String>) -> Self {
+        let id = UserId::new(id);
         UserBuilder::default()
             .id(id)
             .version(0)
             .token(UserToken::new(id))
             .name(name.into())
             .email(Email::new(email).expect("test email should be valid"))
+            .created_at(crate::clock::FixedClock::epoch())
+            .updated_at(crate::clock::FixedClock::epoch())
             .build()
             .expect("test user should build successfully")
     }
 
-    /// Creates a fake user with a specific age, default timestamps and a
-    /// generated token. Only available in test builds.
+    /// Creates a fake user with a specific age and timestamps frozen at
+    /// [`FixedClock::epoch`]. Only available in test builds.
+    ///
+    /// [`FixedClock::epoch`]: crate::clock::FixedClock::epoch
     #[cfg(any(test, feature = "test-support"))]
-    pub fn fake_with_age(id: UserId, name: impl Into<String>, email: impl Into<String>, age: i16) -> Self {
+    pub fn fake_with_age(id: u64, name: impl Into<String>, email: impl Into<String>, age: i16) -> Self {
+        let id = UserId::new(id);
         UserBuilder::default()
             .id(id)
             .version(0)
@@ -70,6 +131,8 @@ impl User {
             .name(name.into())
             .email(Email::new(email).expect("test email should be valid"))
             .age(Age::new(age).expect("test age should be valid"))
+            .created_at(crate::clock::FixedClock::epoch())
+            .updated_at(crate::clock::FixedClock::epoch())
             .build()
             .expect("test user should build successfully")
     }
@@ -111,11 +174,18 @@ impl Default for NewUser {
 ///
 /// Used to consolidate update logic between HTTP and gRPC handlers.
 /// All fields are optional - only provided fields will be updated.
-#[derive(Debug, Default, Clone)]
+///
+/// `age` is clearable: `Some(None)` resets it to the default age, while
+/// `None` leaves it untouched.
+///
+/// `expected_version`, when set, makes the update conditional on the stored
+/// version; it does not count as a field for [`is_empty`](Self::is_empty).
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct PartialUserUpdate {
     pub name: Option<String>,
     pub email: Option<Email>,
-    pub age: Option<Age>,
+    pub age: Option<Option<Age>>,
+    pub expected_version: Option<u64>,
 }
 
 impl PartialUserUpdate {
@@ -128,10 +198,23 @@ impl PartialUserUpdate {
         Ok(Self {
             name: name.map(Into::into),
             email: email.map(Email::new).transpose()?,
-            age: age.map(Age::new).transpose()?,
+            age: age.map(Age::new).transpose()?.map(Some),
+            expected_version: None,
         })
     }
 
+    /// Only apply the update if the stored user is still at `version`.
+    pub fn with_expected_version(mut self, version: u64) -> Self {
+        self.expected_version = Some(version);
+        self
+    }
+
+    /// Marks the age to be cleared back to its default.
+    pub fn clear_age(mut self) -> Self {
+        self.age = Some(None);
+        self
+    }
+
     /// Apply this partial update to an existing user, consuming self.
     ///
     /// Only modifies fields that have `Some` values.
@@ -143,7 +226,7 @@ impl PartialUserUpdate {
             user.email = email;
         }
         if let Some(age) = self.age {
-            user.age = age;
+            user.age = age.unwrap_or_default();
         }
     }
 
@@ -152,3 +235,71 @@ impl PartialUserUpdate {
         self.name.is_none() && self.email.is_none() && self.age.is_none()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{PartialUserUpdate, User, UserId, UserToken};
+
+    // ===================
+    // Tests: UserId
+    // ===================
+    #[test]
+    fn test_user_id_display_round_trips() {
+        let id = UserId::new(42);
+
+        assert_eq!(id.to_string(), "42");
+        assert_eq!("42".parse::<UserId>().unwrap(), id);
+        assert!("-1".parse::<UserId>().is_err());
+    }
+
+    #[test]
+    fn test_user_id_serializes_as_number() {
+        let id = UserId::new(42);
+
+        assert_eq!(serde_json::to_string(&id).unwrap(), "42");
+        assert_eq!(serde_json::from_str::<UserId>("42").unwrap(), id);
+    }
+
+    #[test]
+    fn test_user_token_encodes_user_id() {
+        let token = UserToken::generate();
+
+        assert_eq!(UserToken::parse(&token.to_string()).unwrap().id(), token.id());
+        assert_ne!(token.id(), UserId::default());
+    }
+
+    // ===================
+    // Tests: PartialUserUpdate
+    // ===================
+    #[test]
+    fn test_partial_update_default_is_empty() {
+        assert!(PartialUserUpdate::default().is_empty());
+    }
+
+    #[test]
+    fn test_partial_update_clear_age_is_not_empty() {
+        assert!(!PartialUserUpdate::default().clear_age().is_empty());
+    }
+
+    #[test]
+    fn test_partial_update_apply_sets_fields() {
+        let mut user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+
+        PartialUserUpdate::new(Some("John Updated"), None::<String>, Some(31))
+            .unwrap()
+            .apply_to(&mut user);
+
+        assert_eq!(user.name, "John Updated");
+        assert_eq!(user.email.as_str(), "john@example.com");
+        assert_eq!(user.age.value(), 31);
+    }
+
+    #[test]
+    fn test_partial_update_apply_clears_age() {
+        let mut user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+
+        PartialUserUpdate::default().clear_age().apply_to(&mut user);
+
+        assert_eq!(user.age.value(), 0);
+    }
+}
@@ -0,0 +1,56 @@
+//! The read side of users: listings and searches, served from a read model
+//! kept apart from the tables users are written to, so that paging through
+//! many users does not compete with the transactional writes. The adapter
+//! keeps the read model in step within the transaction of each write, so
+//! listings see a write as soon as it commits.
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    Error,
+    repository::Transaction,
+    user::{User, UserId, UserSpecification},
+};
+
+/// What a listing of users changes with: how many users there are, and
+/// when one last changed or was last seen. Reading it is cheaper than
+/// reading a page, so clients polling a listing can be told it has not
+/// changed without reading it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UserListStamp {
+    pub count: u64,
+    pub last_updated_at: Option<DateTime<Utc>>,
+    pub last_seen_at: Option<DateTime<Utc>>,
+}
+
+#[cfg_attr(any(test, feature = "test-support"), mockall::automock, allow(unused_parens))]
+#[async_trait::async_trait]
+pub trait UserQueryRepository: Send + Sync {
+    /// One page of users ordered by id, after `after_id` if given. The
+    /// cursor is exclusive: passing the last id of a page yields the next
+    /// page without repeating it. With `active_since`, only users last seen
+    /// at or after it.
+    async fn list_users(
+        &self,
+        transaction: &(dyn Transaction + 'static),
+        after_id: Option<UserId>,
+        page_size: Option<u64>,
+        active_since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<User>, Error>;
+    /// One page of the users matching `specification`, ordered by id, after
+    /// the exclusive cursor `after_id` like `list_users`.
+    async fn find_matching(
+        &self,
+        transaction: &(dyn Transaction + 'static),
+        specification: &UserSpecification,
+        after_id: Option<UserId>,
+        page_size: Option<u64>,
+    ) -> Result<Vec<User>, Error>;
+    /// The stamp of the users `list_users` reads from, in one aggregate
+    /// query.
+    async fn list_stamp(&self, transaction: &(dyn Transaction + 'static)) -> Result<UserListStamp, Error>;
+    /// Rebuilds the read model from the users, for users written before it
+    /// existed or by anything other than the user repository. Returns how
+    /// many users it holds.
+    async fn rebuild(&self, transaction: &(dyn Transaction + 'static)) -> Result<u64, Error>;
+}
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    Error,
+    context::RequestContext,
+    repository::{Repository, Transaction, check_deadline},
+    user::{User, UserId, UserQueryRepository, UserRepository, UserToken},
+};
+
+/// The read path of [`UserService`](crate::user::UserService) with the
+/// repositories as type parameters rather than trait objects, for embedders
+/// calling it in a hot loop. It reads the same way, in a read-only
+/// transaction bounded by the deadline of the context, but each call is
+/// dispatched statically. The API crates keep using
+/// [`CoreServices`](crate::CoreServices).
+pub struct UserReader<R, U, Q> {
+    repository: Arc<R>,
+    users: Arc<U>,
+    user_queries: Arc<Q>,
+}
+
+impl<R: Repository, U: UserRepository, Q: UserQueryRepository> UserReader<R, U, Q> {
+    pub fn new(repository: Arc<R>, users: Arc<U>, user_queries: Arc<Q>) -> Self {
+        Self {
+            repository,
+            users,
+            user_queries,
+        }
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    pub async fn find_by_id(&self, context: &RequestContext, id: UserId) -> Result<Option<User>, Error> {
+        self.read_only(context, async |transaction| self.users.find_by_id(transaction, id).await).await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    pub async fn find_by_token(&self, context: &RequestContext, token: UserToken) -> Result<Option<User>, Error> {
+        self.read_only(context, async |transaction| self.users.find_by_token(transaction, token).await)
+            .await
+    }
+
+    /// See [`UserService::list_users`](crate::user::UserService::list_users).
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    pub async fn list_users(
+        &self,
+        context: &RequestContext,
+        after_id: Option<UserId>,
+        page_size: Option<u64>,
+        active_since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<User>, Error> {
+        self.read_only(context, async |transaction| {
+            self.user_queries.list_users(transaction, after_id, page_size, active_since).await
+        })
+        .await
+    }
+
+    /// Same as [`RepositoryService::execute_read_only`](crate::repository::RepositoryService::execute_read_only).
+    async fn read_only<T, F>(&self, context: &RequestContext, work: F) -> Result<T, Error>
+    where
+        F: AsyncFnOnce(&dyn Transaction) -> Result<T, Error>,
+    {
+        check_deadline(context.deadline)?;
+        let transaction = self.repository.begin_read_only_for_request(context).await?;
+        let result = work(&*transaction).await;
+        let _ = transaction.rollback().await;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use mockall::predicate::{always, eq};
+
+    use super::UserReader;
+    use crate::{
+        Error, RepositoryError,
+        context::RequestContext,
+        test_support::{MockRepository, MockUserQueryRepository, MockUserRepository},
+        user::{User, UserId},
+    };
+
+    // ===================
+    // Tests: find_by_id
+    // ===================
+    #[tokio::test]
+    async fn test_find_by_id_reads_user() {
+        let mut users = MockUserRepository::new();
+        users
+            .expect_find_by_id()
+            .with(always(), eq(UserId::new(42)))
+            .times(1)
+            .returning(|_, id| Ok(Some(User::fake(id.value(), "John", "john@example.com"))));
+        let reader = UserReader::new(Arc::new(MockRepository), Arc::new(users), Arc::new(MockUserQueryRepository::new()));
+
+        let user = reader.find_by_id(&RequestContext::internal(), UserId::new(42)).await.unwrap().unwrap();
+
+        assert_eq!(user.id, UserId::new(42));
+    }
+
+    #[tokio::test]
+    async fn test_find_by_id_fails_after_deadline() {
+        let mut users = MockUserRepository::new();
+        users.expect_find_by_id().never();
+        let reader = UserReader::new(Arc::new(MockRepository), Arc::new(users), Arc::new(MockUserQueryRepository::new()));
+        let mut context = RequestContext::internal();
+        context.deadline = Some(Instant::now() - Duration::from_secs(1));
+
+        let error = reader.find_by_id(&context, UserId::new(42)).await.unwrap_err();
+
+        assert!(matches!(error, Error::RepositoryError(RepositoryError::QueryCanceled)));
+    }
+
+    // ===================
+    // Tests: list_users
+    // ===================
+    #[tokio::test]
+    async fn test_list_users_reads_page() {
+        let mut user_queries = MockUserQueryRepository::new();
+        user_queries
+            .expect_list_users()
+            .with(always(), eq(Some(UserId::new(10))), eq(Some(2)), eq(None))
+            .times(1)
+            .returning(|_, _, _, _| Ok(vec![User::fake(11, "John", "john@example.com"), User::fake(12, "Jane", "jane@example.com")]));
+        let reader = UserReader::new(Arc::new(MockRepository), Arc::new(MockUserRepository::new()), Arc::new(user_queries));
+
+        let page = reader
+            .list_users(&RequestContext::internal(), Some(UserId::new(10)), Some(2), None)
+            .await
+            .unwrap();
+
+        assert_eq!(page.iter().map(|user| user.id.value()).collect::<Vec<_>>(), [11, 12]);
+    }
+}
@@ -1,17 +1,161 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+
 use crate::{
     Error,
+    avatar::StoredAvatar,
     repository::Transaction,
     types::Email,
-    user::{NewUser, User, UserId, UserToken},
+    user::{NewUser, User, UserId, UserStats, UserToken},
 };
 
+/// Page size used by `list_users` when none is requested.
+pub const DEFAULT_PAGE_SIZE: u64 = 50;
+/// Limit maximum page size to prevent excessively large responses.
+pub const MAX_PAGE_SIZE: u64 = 50;
+/// Limit the number of ids or tokens resolved by a single batch lookup.
+pub const MAX_BATCH_SIZE: usize = 100;
+/// Limit the number of users an import writes in one transaction.
+pub const IMPORT_BATCH_SIZE: usize = 1000;
+
+/// The page size `list_users` applies for a requested one: the default when
+/// none is requested, capped at [`MAX_PAGE_SIZE`].
+pub fn effective_page_size(page_size: Option<u64>) -> u64 {
+    page_size.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE)
+}
+
+/// Cap on the page size the APIs hand out, at most [`MAX_PAGE_SIZE`]. Clones
+/// observe and change the same cap, so it can be lowered while the server
+/// runs, e.g. to ease the load on the database.
+#[derive(Debug, Clone)]
+pub struct PageSizeCap(Arc<AtomicU64>);
+
+impl PageSizeCap {
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Changes the cap, returning the previous one. Fails with
+    /// [`Error::InvalidPageSize`] unless `cap` is between 1 and
+    /// [`MAX_PAGE_SIZE`].
+    pub fn set(&self, cap: u64) -> Result<u64, Error> {
+        if !(1..=MAX_PAGE_SIZE).contains(&cap) {
+            return Err(Error::InvalidPageSize(cap));
+        }
+        let previous = self.0.swap(cap, Ordering::Relaxed);
+        if previous != cap {
+            tracing::warn!(cap, "Page size cap changed");
+        }
+
+        Ok(previous)
+    }
+
+    /// The page size to ask `list_users` for when a client requests
+    /// `page_size`: the default when none is requested, capped. A page size
+    /// of 0 is passed on, for `list_users` to reject.
+    pub fn apply(&self, page_size: Option<u64>) -> u64 {
+        page_size.unwrap_or(DEFAULT_PAGE_SIZE).min(self.get())
+    }
+}
+
+impl Default for PageSizeCap {
+    fn default() -> Self {
+        Self(Arc::new(AtomicU64::new(MAX_PAGE_SIZE)))
+    }
+}
+
+/// The exclusive `list_users` cursor for an inclusive `start_id`, for APIs
+/// that list from a given id on.
+pub fn after_start_id(start_id: Option<UserId>) -> Option<UserId> {
+    start_id.and_then(|id| id.value().checked_sub(1)).map(UserId::new)
+}
+
+#[cfg_attr(any(test, feature = "test-support"), mockall::automock, allow(unused_parens))]
 #[async_trait::async_trait]
 pub trait UserRepository: Send + Sync {
-    async fn add_user(&self, transaction: &dyn Transaction, user: NewUser) -> Result<User, Error>;
-    async fn update_user(&self, transaction: &dyn Transaction, user: User) -> Result<User, Error>;
-    async fn delete_user(&self, transaction: &dyn Transaction, user: User) -> Result<User, Error>;
-    async fn list_users(&self, transaction: &dyn Transaction, start_id: Option<UserId>, page_size: Option<u64>) -> Result<Vec<User>, Error>;
-    async fn find_by_id(&self, transaction: &dyn Transaction, id: UserId) -> Result<Option<User>, Error>;
-    async fn find_by_email(&self, transaction: &dyn Transaction, email: &Email) -> Result<Option<User>, Error>;
-    async fn find_by_token(&self, transaction: &dyn Transaction, token: UserToken) -> Result<Option<User>, Error>;
+    async fn add_user(&self, transaction: &(dyn Transaction + 'static), user: NewUser) -> Result<User, Error>;
+    /// Inserts `user`, or updates the name and age of the user with the same
+    /// email, as a single statement.
+    async fn upsert_by_email(&self, transaction: &(dyn Transaction + 'static), user: NewUser) -> Result<User, Error>;
+    async fn update_user(&self, transaction: &(dyn Transaction + 'static), user: User) -> Result<User, Error>;
+    async fn delete_user(&self, transaction: &(dyn Transaction + 'static), user: User) -> Result<User, Error>;
+    /// Counts every user
+    /// [`UserQueryRepository::list_users`](crate::user::UserQueryRepository::list_users)
+    /// can return.
+    async fn count_users(&self, transaction: &(dyn Transaction + 'static)) -> Result<u64, Error>;
+    async fn find_by_id(&self, transaction: &(dyn Transaction + 'static), id: UserId) -> Result<Option<User>, Error>;
+    async fn find_by_email(&self, transaction: &(dyn Transaction + 'static), email: &Email) -> Result<Option<User>, Error>;
+    async fn find_by_token(&self, transaction: &(dyn Transaction + 'static), token: UserToken) -> Result<Option<User>, Error>;
+    /// Loads every user whose id is in `ids` with a single query, ordered by
+    /// id. Unknown ids are skipped.
+    async fn find_by_ids(&self, transaction: &(dyn Transaction + 'static), ids: &[UserId]) -> Result<Vec<User>, Error>;
+    /// Loads every user whose token is in `tokens` with a single query,
+    /// ordered by id. Unknown tokens are skipped.
+    async fn find_by_tokens(&self, transaction: &(dyn Transaction + 'static), tokens: &[UserToken]) -> Result<Vec<User>, Error>;
+    /// The avatar of user `id`, `None` without one.
+    async fn find_avatar(&self, transaction: &(dyn Transaction + 'static), id: UserId) -> Result<Option<StoredAvatar>, Error>;
+    /// Records `key` as the avatar of user `id`, with its variants not yet
+    /// generated, returning the key it replaces.
+    async fn set_avatar_key(&self, transaction: &(dyn Transaction + 'static), id: UserId, key: Option<String>) -> Result<Option<String>, Error>;
+    /// Records that the variants of `key` have been generated. Returns
+    /// `false`, changing nothing, if `key` is no longer the avatar of user
+    /// `id`.
+    async fn mark_avatar_variants_ready(&self, transaction: &(dyn Transaction + 'static), id: UserId, key: &str) -> Result<bool, Error>;
+    /// Records when each user in `seen` was last seen, leaving `version`
+    /// and `updated_at` alone. Never moves `last_seen_at` back; unknown
+    /// users are skipped.
+    async fn record_last_seen(&self, transaction: &(dyn Transaction + 'static), seen: &[(UserId, DateTime<Utc>)]) -> Result<(), Error>;
+    /// Aggregates the users: by status, counting users seen at or after
+    /// `active_since` as active, and by
+    /// [`AGE_BUCKETS`](crate::user::AGE_BUCKETS) range, each with one grouped
+    /// query. Signups per day from `signups_since` on come from the signup
+    /// rollups, leaving out days without any.
+    async fn user_stats(&self, transaction: &(dyn Transaction + 'static), active_since: DateTime<Utc>, signups_since: NaiveDate) -> Result<UserStats, Error>;
+    /// Recomputes the signup rollups from the users for every day from
+    /// `since` on, or every day without it. Returns how many days had
+    /// signups.
+    async fn refresh_signup_rollups(&self, transaction: &(dyn Transaction + 'static), since: Option<NaiveDate>) -> Result<u64, Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE, PageSizeCap};
+    use crate::Error;
+
+    // ===================
+    // Tests: PageSizeCap
+    // ===================
+
+    #[test]
+    fn test_default_is_max_page_size() {
+        let cap = PageSizeCap::default();
+
+        assert_eq!(cap.get(), MAX_PAGE_SIZE);
+        assert_eq!(cap.apply(None), DEFAULT_PAGE_SIZE);
+        assert_eq!(cap.apply(Some(MAX_PAGE_SIZE + 1)), MAX_PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_clones_share_cap() {
+        let cap = PageSizeCap::default();
+        let clone = cap.clone();
+
+        assert_eq!(clone.set(10).unwrap(), MAX_PAGE_SIZE);
+        assert_eq!(cap.apply(None), 10);
+        assert_eq!(cap.apply(Some(5)), 5);
+        assert_eq!(cap.apply(Some(0)), 0);
+    }
+
+    #[test]
+    fn test_set_rejects_out_of_range() {
+        let cap = PageSizeCap::default();
+
+        assert!(matches!(cap.set(0), Err(Error::InvalidPageSize(0))));
+        assert!(matches!(cap.set(MAX_PAGE_SIZE + 1), Err(Error::InvalidPageSize(_))));
+        assert_eq!(cap.get(), MAX_PAGE_SIZE);
+    }
 }
@@ -1,280 +1,414 @@
 use std::sync::Arc;
 
+use chrono::{DateTime, Days, NaiveDate, Utc};
+
 use crate::{
     Error, RepositoryError,
+    cache::USERS,
+    context::RequestContext,
     repository::RepositoryService,
-    user::{NewUser, User, UserId, UserToken},
-    with_read_only_transaction, with_transaction,
+    types::Email,
+    user::{
+        IMPORT_BATCH_SIZE, MAX_BATCH_SIZE, MAX_PAGE_SIZE, NewUser, PartialUserUpdate, User, UserDirectoryPort, UserId, UserListStamp, UserSpecification,
+        UserStats, UserToken,
+        duplicates::{DuplicateGroup, find_duplicate_groups},
+        stats::{ACTIVE_DAYS, SIGNUP_DAYS},
+    },
 };
 
+#[cfg_attr(any(test, feature = "test-support"), mockall::automock)]
 #[async_trait::async_trait]
 pub trait UserService: Send + Sync {
-    async fn add_user(&self, user: NewUser) -> Result<User, Error>;
-    async fn update_user(&self, user: User) -> Result<User, Error>;
-    async fn list_users(&self, start_id: Option<UserId>, page_size: Option<u64>) -> Result<Vec<User>, Error>;
-    async fn delete_user(&self, id: UserId) -> Result<User, Error>;
-    async fn find_by_id(&self, id: UserId) -> Result<Option<User>, Error>;
-    async fn find_by_token(&self, token: UserToken) -> Result<Option<User>, Error>;
+    async fn add_user(&self, context: &RequestContext, user: NewUser) -> Result<User, Error>;
+    async fn update_user(&self, context: &RequestContext, user: User) -> Result<User, Error>;
+    /// Creates the user, or updates the existing user with the same email.
+    async fn upsert_by_email(&self, context: &RequestContext, user: NewUser) -> Result<User, Error>;
+    /// Adds up to [`IMPORT_BATCH_SIZE`] users, or updates the existing users
+    /// with the same emails, in one transaction, so an import cut short can
+    /// be run again. Returns how many users were written.
+    async fn import_users(&self, context: &RequestContext, users: Vec<NewUser>) -> Result<u64, Error>;
+    /// Applies `update` to the user with `id` in a single transaction.
+    ///
+    /// Returns `Error::EmptyUpdate` if the update carries no fields, and a
+    /// conflict if `update.expected_version` does not match the stored user.
+    async fn update_user_partial(&self, context: &RequestContext, id: UserId, update: PartialUserUpdate) -> Result<User, Error>;
+    /// One page of users ordered by id, after the exclusive cursor
+    /// `after_id`. With `active_since`, only users last seen at or after it.
+    /// Pages come from the
+    /// [`user_list_cache`](crate::repository::RepositoryService::user_list_cache)
+    /// while it has them.
+    async fn list_users(
+        &self,
+        context: &RequestContext,
+        after_id: Option<UserId>,
+        page_size: Option<u64>,
+        active_since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<User>, Error>;
+    /// The stamp of the users `list_users` pages through, which changes
+    /// whenever any of its pages may have. Read afresh rather than cached.
+    async fn list_stamp(&self, context: &RequestContext) -> Result<UserListStamp, Error>;
+    /// Counts every user `list_users` can return. This scans the table, so
+    /// callers only ask when a client wants the total.
+    async fn count_users(&self, context: &RequestContext) -> Result<u64, Error>;
+    async fn delete_user(&self, context: &RequestContext, id: UserId) -> Result<User, Error>;
+    async fn find_by_id(&self, context: &RequestContext, id: UserId) -> Result<Option<User>, Error>;
+    async fn find_by_token(&self, context: &RequestContext, token: UserToken) -> Result<Option<User>, Error>;
+    /// The local user with `email`.
+    async fn find_by_email(&self, context: &RequestContext, email: Email) -> Result<Option<User>, Error>;
+    /// The person with `email` in the user directory, as they would be
+    /// added locally, `None` if it knows no one with it. Nobody is added;
+    /// callers provisioning users, such as the first sign-in, add them.
+    async fn find_in_directory(&self, context: &RequestContext, email: Email) -> Result<Option<NewUser>, Error>;
+    /// Resolves up to `MAX_BATCH_SIZE` ids in one round trip, ordered by id.
+    /// Unknown ids are left out of the result.
+    async fn find_by_ids(&self, context: &RequestContext, ids: &[UserId]) -> Result<Vec<User>, Error>;
+    /// Resolves up to `MAX_BATCH_SIZE` tokens in one round trip, ordered by
+    /// id. Unknown tokens are left out of the result.
+    async fn find_by_tokens(&self, context: &RequestContext, tokens: &[UserToken]) -> Result<Vec<User>, Error>;
+    /// One page of the users matching `specification`, ordered by id, after
+    /// the exclusive cursor `after_id`.
+    async fn find_matching(
+        &self,
+        context: &RequestContext,
+        specification: &UserSpecification,
+        after_id: Option<UserId>,
+        page_size: Option<u64>,
+    ) -> Result<Vec<User>, Error>;
+    /// Counts users by activity status, signups on each of the last
+    /// [`SIGNUP_DAYS`] days and users per age bucket. Signups come from the
+    /// rollups, so they lag until the next
+    /// [`refresh_signup_rollups`](Self::refresh_signup_rollups).
+    async fn stats(&self, context: &RequestContext) -> Result<UserStats, Error>;
+    /// Recomputes the daily signup rollups for every day from `since` on, or
+    /// every day without it. Returns how many days had signups.
+    async fn refresh_signup_rollups(&self, context: &RequestContext, since: Option<NaiveDate>) -> Result<u64, Error>;
+    /// Rebuilds the read model `list_users` and `find_matching` are served
+    /// from, see
+    /// [`UserQueryRepository::rebuild`](crate::user::UserQueryRepository::rebuild).
+    /// Returns how many users it holds.
+    async fn rebuild_list_view(&self, context: &RequestContext) -> Result<u64, Error>;
+    /// Groups of users that may be the same person, by normalized email
+    /// local part or similar names, for review before merging them. Reads
+    /// every user in one transaction.
+    async fn find_potential_duplicates(&self, context: &RequestContext) -> Result<Vec<DuplicateGroup>, Error>;
 }
 
 pub(crate) struct UserServiceImpl {
     repository_service: Arc<RepositoryService>,
+    user_directory: Arc<dyn UserDirectoryPort>,
 }
 
 impl UserServiceImpl {
-    pub(crate) fn new(repository_service: Arc<RepositoryService>) -> Self {
-        Self { repository_service }
+    pub(crate) fn new(repository_service: Arc<RepositoryService>, user_directory: Arc<dyn UserDirectoryPort>) -> Self {
+        Self {
+            repository_service,
+            user_directory,
+        }
+    }
+
+    /// Drops the cached pages of users once a write to them has committed.
+    fn users_changed(&self) {
+        self.repository_service.user_list_cache().invalidate(USERS);
     }
 }
 
 #[async_trait::async_trait]
 impl UserService for UserServiceImpl {
-    #[tracing::instrument(level = "trace", skip(self, user))]
-    async fn add_user(&self, user: NewUser) -> Result<User, Error> {
-        with_transaction!(self, user_repository, |tx| user_repository.add_user(tx, user).await)
+    #[tracing::instrument(level = "trace", skip(self, context, user), fields(request_id = %context.request_id))]
+    async fn add_user(&self, context: &RequestContext, user: NewUser) -> Result<User, Error> {
+        self.repository_service
+            .execute(context, async |uow| uow.users().add_user(uow.tx(), user).await)
+            .await
+            .inspect(|_| self.users_changed())
     }
 
-    #[tracing::instrument(level = "trace", skip(self, user))]
-    async fn update_user(&self, user: User) -> Result<User, Error> {
-        with_transaction!(self, user_repository, |tx| user_repository.update_user(tx, user).await)
+    #[tracing::instrument(level = "trace", skip(self, context, user), fields(request_id = %context.request_id))]
+    async fn update_user(&self, context: &RequestContext, user: User) -> Result<User, Error> {
+        self.repository_service
+            .execute(context, async |uow| uow.users().update_user(uow.tx(), user).await)
+            .await
+            .inspect(|_| self.users_changed())
     }
 
-    #[tracing::instrument(level = "trace", skip(self))]
-    async fn list_users(&self, start_id: Option<UserId>, page_size: Option<u64>) -> Result<Vec<User>, Error> {
-        with_read_only_transaction!(self, user_repository, |tx| user_repository.list_users(tx, start_id, page_size).await)
+    #[tracing::instrument(level = "trace", skip(self, context, user), fields(request_id = %context.request_id, email = %user.email.redacted()))]
+    async fn upsert_by_email(&self, context: &RequestContext, user: NewUser) -> Result<User, Error> {
+        self.repository_service
+            .execute(context, async |uow| uow.users().upsert_by_email(uow.tx(), user).await)
+            .await
+            .inspect(|_| self.users_changed())
     }
 
-    #[tracing::instrument(level = "trace", skip(self))]
-    async fn delete_user(&self, id: UserId) -> Result<User, Error> {
-        with_transaction!(self, user_repository, |tx| {
-            let user = user_repository
-                .find_by_id(tx, id)
-                .await?
-                .ok_or(Error::RepositoryError(RepositoryError::NotFound))?;
-
-            user_repository.delete_user(tx, user).await
-        })
+    #[tracing::instrument(level = "trace", skip(self, context, users), fields(request_id = %context.request_id, users = users.len()))]
+    async fn import_users(&self, context: &RequestContext, users: Vec<NewUser>) -> Result<u64, Error> {
+        if users.len() > IMPORT_BATCH_SIZE {
+            return Err(Error::InvalidBatchSize(users.len()));
+        }
+        self.repository_service
+            .execute(context, async |uow| {
+                let written = users.len() as u64;
+                for user in users {
+                    uow.users().upsert_by_email(uow.tx(), user).await?;
+                }
+                Ok(written)
+            })
+            .await
+            .inspect(|_| self.users_changed())
     }
 
-    #[tracing::instrument(level = "trace", skip(self))]
-    async fn find_by_id(&self, id: UserId) -> Result<Option<User>, Error> {
-        with_read_only_transaction!(self, user_repository, |tx| user_repository.find_by_id(tx, id).await)
-    }
+    #[tracing::instrument(level = "trace", skip(self, context, update), fields(request_id = %context.request_id))]
+    async fn update_user_partial(&self, context: &RequestContext, id: UserId, update: PartialUserUpdate) -> Result<User, Error> {
+        if update.is_empty() {
+            return Err(Error::EmptyUpdate);
+        }
 
-    #[tracing::instrument(level = "trace", skip(self))]
-    async fn find_by_token(&self, token: UserToken) -> Result<Option<User>, Error> {
-        with_read_only_transaction!(self, user_repository, |tx| user_repository.find_by_token(tx, token).await)
+        self.repository_service
+            .execute(context, async |uow| {
+                let mut user = uow
+                    .users()
+                    .find_by_id(uow.tx(), id)
+                    .await?
+                    .ok_or(Error::RepositoryError(RepositoryError::NotFound))?;
+                if update.expected_version.is_some_and(|version| version != user.version) {
+                    return Err(Error::RepositoryError(RepositoryError::Conflict));
+                }
+
+                update.apply_to(&mut user);
+                uow.users().update_user(uow.tx(), user).await
+            })
+            .await
+            .inspect(|_| self.users_changed())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::{
-        any::Any,
-        sync::{Arc, Mutex},
-    };
-
-    use super::{UserService, UserServiceImpl};
-    use crate::{
-        Error, RepositoryError,
-        repository::{Repository, RepositoryServiceBuilder, Transaction},
-        session::{
-            model::{NewSession, Session},
-            repository::SessionRepository,
-        },
-        types::Email,
-        user::{
-            model::{NewUser, User, UserId, UserToken},
-            repository::UserRepository,
-        },
-    };
-
-    // ===================
-    // Mock Transaction
-    // ===================
-    struct MockTransaction;
-
-    #[async_trait::async_trait]
-    impl Transaction for MockTransaction {
-        fn as_any(&self) -> &dyn Any {
-            self
-        }
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn list_users(
+        &self,
+        context: &RequestContext,
+        after_id: Option<UserId>,
+        page_size: Option<u64>,
+        active_since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<User>, Error> {
+        // Keyed by tenant too, as tenants see different users.
+        let key = format!("{:?}/{after_id:?}/{page_size:?}/{active_since:?}", context.tenant);
+        self.repository_service
+            .user_list_cache()
+            .get_or_load(key, &[USERS], async || {
+                self.repository_service
+                    .execute_read_only(context, async |uow| {
+                        uow.user_queries().list_users(uow.tx(), after_id, page_size, active_since).await
+                    })
+                    .await
+            })
+            .await
+    }
 
-        async fn commit(self: Box<Self>) -> Result<(), Error> {
-            Ok(())
-        }
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn list_stamp(&self, context: &RequestContext) -> Result<UserListStamp, Error> {
+        self.repository_service
+            .execute_read_only(context, async |uow| uow.user_queries().list_stamp(uow.tx()).await)
+            .await
+    }
 
-        async fn rollback(self: Box<Self>) -> Result<(), Error> {
-            Ok(())
-        }
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn count_users(&self, context: &RequestContext) -> Result<u64, Error> {
+        self.repository_service
+            .execute_read_only(context, async |uow| uow.users().count_users(uow.tx()).await)
+            .await
     }
 
-    // ===================
-    // Mock Repository
-    // ===================
-    struct MockRepository;
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn delete_user(&self, context: &RequestContext, id: UserId) -> Result<User, Error> {
+        self.repository_service
+            .execute(context, async |uow| {
+                let user = uow
+                    .users()
+                    .find_by_id(uow.tx(), id)
+                    .await?
+                    .ok_or(Error::RepositoryError(RepositoryError::NotFound))?;
+
+                uow.users().delete_user(uow.tx(), user).await
+            })
+            .await
+            .inspect(|_| self.users_changed())
+    }
 
-    #[async_trait::async_trait]
-    impl Repository for MockRepository {
-        async fn begin(&self) -> Result<Box<dyn Transaction>, Error> {
-            Ok(Box::new(MockTransaction))
-        }
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn find_by_id(&self, context: &RequestContext, id: UserId) -> Result<Option<User>, Error> {
+        self.repository_service
+            .execute_read_only(context, async |uow| uow.users().find_by_id(uow.tx(), id).await)
+            .await
+    }
 
-        async fn begin_read_only(&self) -> Result<Box<dyn Transaction>, Error> {
-            Ok(Box::new(MockTransaction))
-        }
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn find_by_token(&self, context: &RequestContext, token: UserToken) -> Result<Option<User>, Error> {
+        self.repository_service
+            .execute_read_only(context, async |uow| uow.users().find_by_token(uow.tx(), token).await)
+            .await
+    }
 
-        async fn close(&self) -> Result<(), Error> {
-            Ok(())
-        }
+    #[tracing::instrument(level = "trace", skip(self, context, email), fields(request_id = %context.request_id, email = %email.redacted()))]
+    async fn find_by_email(&self, context: &RequestContext, email: Email) -> Result<Option<User>, Error> {
+        self.repository_service
+            .execute_read_only(context, async |uow| uow.users().find_by_email(uow.tx(), &email).await)
+            .await
     }
 
-    // ===================
-    // Mock UserRepository
-    // ===================
-    #[derive(Default)]
-    struct MockUserRepository {
-        add_user_result: Mutex<Option<Result<User, Error>>>,
-        update_user_result: Mutex<Option<Result<User, Error>>>,
-        delete_user_result: Mutex<Option<Result<User, Error>>>,
-        find_by_id_result: Mutex<Option<Result<Option<User>, Error>>>,
-        find_by_token_result: Mutex<Option<Result<Option<User>, Error>>>,
-        list_users_result: Mutex<Option<Result<Vec<User>, Error>>>,
+    #[tracing::instrument(level = "trace", skip(self, context, email), fields(request_id = %context.request_id, email = %email.redacted()))]
+    async fn find_in_directory(&self, context: &RequestContext, email: Email) -> Result<Option<NewUser>, Error> {
+        let user = self.user_directory.find_by_email(&email).await?;
+        // Keyed by the email asked for, so once added it is found locally.
+        Ok(user.map(|user| NewUser { email, ..user }))
     }
 
-    impl MockUserRepository {
-        fn with_add_user_result(self, result: Result<User, Error>) -> Self {
-            *self.add_user_result.lock().unwrap() = Some(result);
-            self
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn find_by_ids(&self, context: &RequestContext, ids: &[UserId]) -> Result<Vec<User>, Error> {
+        if ids.len() > MAX_BATCH_SIZE {
+            return Err(Error::InvalidBatchSize(ids.len()));
         }
-
-        fn with_update_user_result(self, result: Result<User, Error>) -> Self {
-            *self.update_user_result.lock().unwrap() = Some(result);
-            self
+        if ids.is_empty() {
+            return Ok(Vec::new());
         }
 
-        fn with_delete_user_result(self, result: Result<User, Error>) -> Self {
-            *self.delete_user_result.lock().unwrap() = Some(result);
-            self
-        }
+        self.repository_service
+            .execute_read_only(context, async |uow| uow.users().find_by_ids(uow.tx(), ids).await)
+            .await
+    }
 
-        fn with_find_by_id_result(self, result: Result<Option<User>, Error>) -> Self {
-            *self.find_by_id_result.lock().unwrap() = Some(result);
-            self
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn find_by_tokens(&self, context: &RequestContext, tokens: &[UserToken]) -> Result<Vec<User>, Error> {
+        if tokens.len() > MAX_BATCH_SIZE {
+            return Err(Error::InvalidBatchSize(tokens.len()));
         }
-
-        fn with_list_users_result(self, result: Result<Vec<User>, Error>) -> Self {
-            *self.list_users_result.lock().unwrap() = Some(result);
-            self
+        if tokens.is_empty() {
+            return Ok(Vec::new());
         }
 
-        fn with_find_by_token_result(self, result: Result<Option<User>, Error>) -> Self {
-            *self.find_by_token_result.lock().unwrap() = Some(result);
-            self
-        }
+        self.repository_service
+            .execute_read_only(context, async |uow| uow.users().find_by_tokens(uow.tx(), tokens).await)
+            .await
     }
 
-    #[async_trait::async_trait]
-    impl UserRepository for MockUserRepository {
-        async fn add_user(&self, _tx: &dyn Transaction, _user: NewUser) -> Result<User, Error> {
-            self.add_user_result
-                .lock()
-                .unwrap()
-                .clone()
-                .unwrap_or_else(|| Err(Error::MockNotConfigured("add_user")))
-        }
-
-        async fn update_user(&self, _tx: &dyn Transaction, _user: User) -> Result<User, Error> {
-            self.update_user_result
-                .lock()
-                .unwrap()
-                .clone()
-                .unwrap_or_else(|| Err(Error::MockNotConfigured("update_user")))
-        }
-
-        async fn delete_user(&self, _tx: &dyn Transaction, _user: User) -> Result<User, Error> {
-            self.delete_user_result
-                .lock()
-                .unwrap()
-                .clone()
-                .unwrap_or_else(|| Err(Error::MockNotConfigured("delete_user")))
-        }
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn find_matching(
+        &self,
+        context: &RequestContext,
+        specification: &UserSpecification,
+        after_id: Option<UserId>,
+        page_size: Option<u64>,
+    ) -> Result<Vec<User>, Error> {
+        self.repository_service
+            .execute_read_only(context, async |uow| {
+                uow.user_queries().find_matching(uow.tx(), specification, after_id, page_size).await
+            })
+            .await
+    }
 
-        async fn list_users(&self, _tx: &dyn Transaction, _start_id: Option<UserId>, _page_size: Option<u64>) -> Result<Vec<User>, Error> {
-            self.list_users_result
-                .lock()
-                .unwrap()
-                .clone()
-                .unwrap_or_else(|| Err(Error::MockNotConfigured("list_users")))
-        }
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn stats(&self, context: &RequestContext) -> Result<UserStats, Error> {
+        let now = self.repository_service.clock().now();
+        let today = now.date_naive();
+        let first_day = today - Days::new(SIGNUP_DAYS - 1);
+        let active_since = now - Days::new(ACTIVE_DAYS);
+
+        let mut stats = self
+            .repository_service
+            .execute_read_only(context, async |uow| uow.users().user_stats(uow.tx(), active_since, first_day).await)
+            .await?;
+        stats.fill_days(first_day, today);
+        Ok(stats)
+    }
 
-        async fn find_by_id(&self, _tx: &dyn Transaction, _id: UserId) -> Result<Option<User>, Error> {
-            self.find_by_id_result
-                .lock()
-                .unwrap()
-                .clone()
-                .unwrap_or_else(|| Err(Error::MockNotConfigured("find_by_id")))
-        }
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn refresh_signup_rollups(&self, context: &RequestContext, since: Option<NaiveDate>) -> Result<u64, Error> {
+        self.repository_service
+            .execute(context, async |uow| uow.users().refresh_signup_rollups(uow.tx(), since).await)
+            .await
+    }
 
-        async fn find_by_email(&self, _tx: &dyn Transaction, _email: &Email) -> Result<Option<User>, Error> {
-            Err(Error::MockNotConfigured("find_by_email"))
-        }
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn rebuild_list_view(&self, context: &RequestContext) -> Result<u64, Error> {
+        self.repository_service
+            .execute(context, async |uow| uow.user_queries().rebuild(uow.tx()).await)
+            .await
+            .inspect(|_| self.users_changed())
+    }
 
-        async fn find_by_token(&self, _tx: &dyn Transaction, _token: UserToken) -> Result<Option<User>, Error> {
-            self.find_by_token_result
-                .lock()
-                .unwrap()
-                .clone()
-                .unwrap_or_else(|| Err(Error::MockNotConfigured("find_by_token")))
-        }
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn find_potential_duplicates(&self, context: &RequestContext) -> Result<Vec<DuplicateGroup>, Error> {
+        let users = self
+            .repository_service
+            .execute_read_only(context, async |uow| {
+                let mut users = Vec::new();
+                let mut after_id = None;
+                loop {
+                    let page = uow.user_queries().list_users(uow.tx(), after_id, Some(MAX_PAGE_SIZE), None).await?;
+                    let last_page = (page.len() as u64) < MAX_PAGE_SIZE;
+                    after_id = page.last().map(|user| user.id);
+                    users.extend(page);
+                    if last_page {
+                        break;
+                    }
+                }
+                Ok(users)
+            })
+            .await?;
+
+        Ok(find_duplicate_groups(&users))
     }
+}
 
-    // ===================
-    // Mock SessionRepository
-    // ===================
-    struct MockSessionRepository;
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::{Duration as StdDuration, Instant},
+    };
 
-    #[async_trait::async_trait]
-    impl SessionRepository for MockSessionRepository {
-        async fn count(&self, _tx: &dyn Transaction) -> Result<i64, Error> {
-            unimplemented!()
-        }
-        async fn store(&self, _tx: &dyn Transaction, _session: NewSession) -> Result<Session, Error> {
-            unimplemented!()
-        }
-        async fn load(&self, _tx: &dyn Transaction, _id: &str) -> Result<Option<Session>, Error> {
-            unimplemented!()
-        }
-        async fn delete_by_id(&self, _tx: &dyn Transaction, _id: &str) -> Result<(), Error> {
-            unimplemented!()
-        }
-        async fn exists(&self, _tx: &dyn Transaction, _id: &str) -> Result<bool, Error> {
-            unimplemented!()
-        }
-        async fn delete_by_expiry(&self, _tx: &dyn Transaction) -> Result<Vec<String>, Error> {
-            unimplemented!()
-        }
-        async fn delete_all(&self, _tx: &dyn Transaction) -> Result<(), Error> {
-            unimplemented!()
-        }
-        async fn get_ids(&self, _tx: &dyn Transaction) -> Result<Vec<String>, Error> {
-            unimplemented!()
-        }
-    }
+    use chrono::Duration;
+    use mockall::predicate::{always, eq};
+
+    use super::{UserService, UserServiceImpl};
+    use crate::{
+        Error, RepositoryError,
+        clock::{Clock, FixedClock},
+        context::RequestContext,
+        i18n::ValidationMessage,
+        test_support::{MockSessionRepository, MockUserQueryRepository, MockUserRepository, MockWebhookRepository, mock_repository_service},
+        types::Email,
+        user::{
+            IMPORT_BATCH_SIZE, MAX_BATCH_SIZE, MockUserDirectoryPort, NoUserDirectory, UserListStamp, UserPredicate, UserSpecification,
+            model::{NewUser, PartialUserUpdate, User, UserId, UserToken},
+            stats::{DailySignups, StatusCounts, UserStats},
+        },
+    };
 
     // ===================
     // Test Helpers
     // ===================
     fn create_use_cases(mock_user_repository: MockUserRepository) -> UserServiceImpl {
-        let repository_service = Arc::new(
-            RepositoryServiceBuilder::default()
-                .repository(Arc::new(MockRepository) as Arc<dyn Repository>)
-                .user_repository(Arc::new(mock_user_repository) as Arc<dyn UserRepository>)
-                .session_repository(Arc::new(MockSessionRepository) as Arc<dyn SessionRepository>)
-                .build()
-                .expect("All required fields provided"),
-        );
-        UserServiceImpl::new(repository_service)
+        create_use_cases_with_clock(mock_user_repository, FixedClock::default())
+    }
+
+    fn create_use_cases_with_clock(mock_user_repository: MockUserRepository, clock: FixedClock) -> UserServiceImpl {
+        let repository_service = mock_repository_service(mock_user_repository, MockSessionRepository::new(), MockWebhookRepository::new())
+            .clock(Arc::new(clock) as Arc<dyn Clock>)
+            .build()
+            .expect("All required fields provided");
+        UserServiceImpl::new(Arc::new(repository_service), Arc::new(NoUserDirectory))
+    }
+
+    fn create_use_cases_with_queries(mock_user_query_repository: MockUserQueryRepository) -> UserServiceImpl {
+        let repository_service = mock_repository_service(MockUserRepository::new(), MockSessionRepository::new(), MockWebhookRepository::new())
+            .user_query_repository(Arc::new(mock_user_query_repository))
+            .build()
+            .expect("All required fields provided");
+        UserServiceImpl::new(Arc::new(repository_service), Arc::new(NoUserDirectory))
+    }
+
+    fn create_use_cases_with_directory(mock_user_repository: MockUserRepository, user_directory: MockUserDirectoryPort) -> UserServiceImpl {
+        let repository_service = mock_repository_service(mock_user_repository, MockSessionRepository::new(), MockWebhookRepository::new())
+            .build()
+            .expect("All required fields provided");
+        UserServiceImpl::new(Arc::new(repository_service), Arc::new(user_directory))
     }
 
     // ===================
@@ -283,16 +417,17 @@ mod tests {
     #[tokio::test]
     async fn test_add_user_success() {
         let expected_user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
-        let mock_user_repository = MockUserRepository::default().with_add_user_result(Ok(expected_user.clone()));
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_add_user().return_const(Ok(expected_user.clone()));
         let use_cases = create_use_cases(mock_user_repository);
 
         let new_user = NewUser::new("John Doe", "john@example.com", 30).unwrap();
 
-        let result = use_cases.add_user(new_user).await;
+        let result = use_cases.add_user(&RequestContext::internal(), new_user).await;
 
         assert!(result.is_ok());
         let user = result.unwrap();
-        assert_eq!(user.id, 1);
+        assert_eq!(user.id, UserId::new(1));
         assert_eq!(user.name, "John Doe");
         assert_eq!(user.email.as_str(), "john@example.com");
         assert_eq!(user.age.value(), 30);
@@ -300,30 +435,112 @@ mod tests {
 
     #[tokio::test]
     async fn test_add_user_propagates_error() {
-        let mock_repository =
-            MockUserRepository::default().with_add_user_result(Err(Error::RepositoryError(RepositoryError::Constraint("duplicate email".into()))));
+        let mut mock_repository = MockUserRepository::new();
+        mock_repository
+            .expect_add_user()
+            .return_const(Err(Error::RepositoryError(RepositoryError::Constraint("duplicate email".into()))));
         let use_cases = create_use_cases(mock_repository);
 
         let new_user = NewUser::new("John Doe", "john@example.com", 30).unwrap();
 
-        let result = use_cases.add_user(new_user).await;
+        let result = use_cases.add_user(&RequestContext::internal(), new_user).await;
 
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), Error::RepositoryError(RepositoryError::Constraint(_))));
     }
 
+    #[tokio::test]
+    async fn test_add_user_refuses_blank_name() {
+        let use_cases = create_use_cases(MockUserRepository::new());
+
+        let new_user = NewUser::new("", "john@example.com", 30).unwrap();
+
+        let result = use_cases.add_user(&RequestContext::internal(), new_user).await;
+
+        assert!(matches!(result.unwrap_err(), Error::Validation(ValidationMessage::BlankName)));
+    }
+
+    // ===================
+    // Tests: upsert_by_email
+    // ===================
+    #[tokio::test]
+    async fn test_upsert_by_email_success() {
+        let expected_user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_upsert_by_email().return_const(Ok(expected_user));
+        let use_cases = create_use_cases(mock_user_repository);
+
+        let result = use_cases
+            .upsert_by_email(&RequestContext::internal(), NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await;
+
+        let user = result.unwrap();
+        assert_eq!(user.email.as_str(), "john@example.com");
+        assert_eq!(user.age.value(), 30);
+    }
+
+    // ===================
+    // Tests: import_users
+    // ===================
+    #[tokio::test]
+    async fn test_import_users_upserts_each_user() {
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository
+            .expect_upsert_by_email()
+            .times(2)
+            .returning(|_, user| Ok(User::fake(1, &user.name, user.email.as_str())));
+        let use_cases = create_use_cases(mock_user_repository);
+
+        let users = vec![
+            NewUser::new("John Doe", "john@example.com", 30).unwrap(),
+            NewUser::new("Jane Doe", "jane@example.com", 25).unwrap(),
+        ];
+        let result = use_cases.import_users(&RequestContext::internal(), users).await;
+
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_import_users_refuses_blank_name() {
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository
+            .expect_upsert_by_email()
+            .times(1)
+            .returning(|_, user| Ok(User::fake(1, &user.name, user.email.as_str())));
+        let use_cases = create_use_cases(mock_user_repository);
+
+        let users = vec![
+            NewUser::new("John Doe", "john@example.com", 30).unwrap(),
+            NewUser::new("", "jane@example.com", 25).unwrap(),
+        ];
+        let result = use_cases.import_users(&RequestContext::internal(), users).await;
+
+        assert!(matches!(result.unwrap_err(), Error::Validation(ValidationMessage::BlankName)));
+    }
+
+    #[tokio::test]
+    async fn test_import_users_too_many() {
+        let use_cases = create_use_cases(MockUserRepository::new());
+
+        let users = vec![NewUser::new("John Doe", "john@example.com", 30).unwrap(); IMPORT_BATCH_SIZE + 1];
+        let result = use_cases.import_users(&RequestContext::internal(), users).await;
+
+        assert!(matches!(result.unwrap_err(), Error::InvalidBatchSize(n) if n == IMPORT_BATCH_SIZE + 1));
+    }
+
     // ===================
     // Tests: update_user
     // ===================
     #[tokio::test]
     async fn test_update_user_success() {
         let updated_user = User::fake_with_age(1, "John Updated", "john.updated@example.com", 35);
-        let mock_user_repository = MockUserRepository::default().with_update_user_result(Ok(updated_user.clone()));
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_update_user().return_const(Ok(updated_user.clone()));
         let use_cases = create_use_cases(mock_user_repository);
 
         let user = User::fake_with_age(1, "John Doe", "john@example.com", 35);
 
-        let result = use_cases.update_user(user).await;
+        let result = use_cases.update_user(&RequestContext::internal(), user).await;
 
         assert!(result.is_ok());
         let user = result.unwrap();
@@ -334,43 +551,142 @@ mod tests {
 
     #[tokio::test]
     async fn test_update_user_not_found() {
-        let mock_repository = MockUserRepository::default().with_update_user_result(Err(Error::RepositoryError(RepositoryError::NotFound)));
+        let mut mock_repository = MockUserRepository::new();
+        mock_repository
+            .expect_update_user()
+            .return_const(Err(Error::RepositoryError(RepositoryError::NotFound)));
         let use_cases = create_use_cases(mock_repository);
 
         let user = User::fake(999, "Nonexistent", "none@example.com");
 
-        let result = use_cases.update_user(user).await;
+        let result = use_cases.update_user(&RequestContext::internal(), user).await;
 
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), Error::RepositoryError(RepositoryError::NotFound)));
     }
 
+    // ===================
+    // Tests: update_user_partial
+    // ===================
+    #[tokio::test]
+    async fn test_update_user_partial_success() {
+        let existing = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let updated = User::fake_with_age(1, "John Updated", "john@example.com", 30);
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_id().return_const(Ok(Some(existing)));
+        mock_user_repository.expect_update_user().return_const(Ok(updated));
+        let use_cases = create_use_cases(mock_user_repository);
+
+        let update = PartialUserUpdate::new(Some("John Updated"), None::<String>, None).unwrap();
+        let result = use_cases.update_user_partial(&RequestContext::internal(), UserId::new(1), update).await;
+
+        assert_eq!(result.unwrap().name, "John Updated");
+    }
+
+    #[tokio::test]
+    async fn test_update_user_partial_expected_version_matches() {
+        let mut existing = User::fake(1, "John Doe", "john@example.com");
+        existing.version = 3;
+        let updated = User::fake(1, "John Updated", "john@example.com");
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_id().return_const(Ok(Some(existing)));
+        mock_user_repository.expect_update_user().return_const(Ok(updated));
+        let use_cases = create_use_cases(mock_user_repository);
+
+        let update = PartialUserUpdate::new(Some("John Updated"), None::<String>, None)
+            .unwrap()
+            .with_expected_version(3);
+        let result = use_cases.update_user_partial(&RequestContext::internal(), UserId::new(1), update).await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_update_user_partial_expected_version_stale() {
+        let mut existing = User::fake(1, "John Doe", "john@example.com");
+        existing.version = 3;
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_id().return_const(Ok(Some(existing)));
+        let use_cases = create_use_cases(mock_user_repository);
+
+        let update = PartialUserUpdate::new(Some("John Updated"), None::<String>, None)
+            .unwrap()
+            .with_expected_version(2);
+        let result = use_cases.update_user_partial(&RequestContext::internal(), UserId::new(1), update).await;
+
+        assert!(matches!(result.unwrap_err(), Error::RepositoryError(RepositoryError::Conflict)));
+    }
+
+    #[tokio::test]
+    async fn test_update_user_partial_refuses_blank_name() {
+        let existing = User::fake(1, "John Doe", "john@example.com");
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_id().return_const(Ok(Some(existing)));
+        mock_user_repository.expect_update_user().never();
+        let use_cases = create_use_cases(mock_user_repository);
+
+        let update = PartialUserUpdate::new(Some(" "), None::<String>, None).unwrap();
+        let result = use_cases.update_user_partial(&RequestContext::internal(), UserId::new(1), update).await;
+
+        assert!(matches!(result.unwrap_err(), Error::Validation(ValidationMessage::BlankName)));
+    }
+
+    #[tokio::test]
+    async fn test_update_user_partial_empty() {
+        let use_cases = create_use_cases(MockUserRepository::new());
+
+        let result = use_cases
+            .update_user_partial(&RequestContext::internal(), UserId::new(1), PartialUserUpdate::default())
+            .await;
+
+        let error = result.unwrap_err();
+        assert!(matches!(error, Error::EmptyUpdate));
+        assert_eq!(error.kind(), crate::ErrorKind::BadRequest);
+    }
+
+    #[tokio::test]
+    async fn test_update_user_partial_not_found() {
+        let mut mock_repository = MockUserRepository::new();
+        mock_repository.expect_find_by_id().return_const(Ok(None));
+        let use_cases = create_use_cases(mock_repository);
+
+        let update = PartialUserUpdate::new(Some("Updated"), None::<String>, None).unwrap();
+        let result = use_cases.update_user_partial(&RequestContext::internal(), UserId::new(999), update).await;
+
+        assert!(matches!(result.unwrap_err(), Error::RepositoryError(RepositoryError::NotFound)));
+    }
+
     // ===================
     // Tests: find_by_id
     // ===================
     #[tokio::test]
     async fn test_find_by_id_found() {
         let expected_user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
-        let mock_user_repository = MockUserRepository::default().with_find_by_id_result(Ok(Some(expected_user.clone())));
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository
+            .expect_find_by_id()
+            .with(always(), eq(UserId::new(1)))
+            .return_const(Ok(Some(expected_user.clone())));
         let use_cases = create_use_cases(mock_user_repository);
 
-        let result = use_cases.find_by_id(1).await;
+        let result = use_cases.find_by_id(&RequestContext::internal(), UserId::new(1)).await;
 
         assert!(result.is_ok());
         let user = result.unwrap();
         assert!(user.is_some());
         let user = user.unwrap();
-        assert_eq!(user.id, 1);
+        assert_eq!(user.id, UserId::new(1));
         assert_eq!(user.name, "John Doe");
         assert_eq!(user.age.value(), 30);
     }
 
     #[tokio::test]
     async fn test_find_by_id_not_found() {
-        let mock_repository = MockUserRepository::default().with_find_by_id_result(Ok(None));
+        let mut mock_repository = MockUserRepository::new();
+        mock_repository.expect_find_by_id().return_const(Ok(None));
         let use_cases = create_use_cases(mock_repository);
 
-        let result = use_cases.find_by_id(999).await;
+        let result = use_cases.find_by_id(&RequestContext::internal(), UserId::new(999)).await;
 
         assert!(result.is_ok());
         assert!(result.unwrap().is_none());
@@ -381,15 +697,14 @@ mod tests {
     // ===================
     #[tokio::test]
     async fn test_list_users_success() {
-        // The database adapter's list_users fetches ages via JOIN, so the mock
-        // should return users with ages already populated
         let user1 = User::fake_with_age(1, "John Doe", "john@example.com", 30);
         let user2 = User::fake_with_age(2, "Jane Doe", "jane@example.com", 25);
         let users = vec![user1, user2];
-        let mock_user_repository = MockUserRepository::default().with_list_users_result(Ok(users));
-        let use_cases = create_use_cases(mock_user_repository);
+        let mut mock_user_query_repository = MockUserQueryRepository::new();
+        mock_user_query_repository.expect_list_users().return_const(Ok(users));
+        let use_cases = create_use_cases_with_queries(mock_user_query_repository);
 
-        let result = use_cases.list_users(None, None).await;
+        let result = use_cases.list_users(&RequestContext::internal(), None, None, None).await;
 
         assert!(result.is_ok());
         let users = result.unwrap();
@@ -402,41 +717,108 @@ mod tests {
 
     #[tokio::test]
     async fn test_list_users_empty() {
-        let mock_repository = MockUserRepository::default().with_list_users_result(Ok(vec![]));
-        let use_cases = create_use_cases(mock_repository);
+        let mut mock_repository = MockUserQueryRepository::new();
+        mock_repository.expect_list_users().return_const(Ok(vec![]));
+        let use_cases = create_use_cases_with_queries(mock_repository);
 
-        let result = use_cases.list_users(None, None).await;
+        let result = use_cases.list_users(&RequestContext::internal(), None, None, None).await;
 
         assert!(result.is_ok());
         assert!(result.unwrap().is_empty());
     }
 
+    #[tokio::test]
+    async fn test_list_users_cached_until_users_change() {
+        let mut mock_user_query_repository = MockUserQueryRepository::new();
+        mock_user_query_repository
+            .expect_list_users()
+            .times(2)
+            .returning(|_, _, _, _| Ok(vec![User::fake(1, "John Doe", "john@example.com")]));
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository
+            .expect_add_user()
+            .return_const(Ok(User::fake(2, "Jane Doe", "jane@example.com")));
+        let repository_service = mock_repository_service(mock_user_repository, MockSessionRepository::new(), MockWebhookRepository::new())
+            .user_query_repository(Arc::new(mock_user_query_repository))
+            .build()
+            .expect("All required fields provided");
+        repository_service.user_list_cache().set_ttl(StdDuration::from_secs(60));
+        let use_cases = UserServiceImpl::new(Arc::new(repository_service), Arc::new(NoUserDirectory));
+        let context = RequestContext::internal();
+
+        use_cases.list_users(&context, None, None, None).await.unwrap();
+        use_cases.list_users(&context, None, None, None).await.unwrap();
+        use_cases
+            .add_user(&context, NewUser::new("Jane Doe", "jane@example.com", 25).unwrap())
+            .await
+            .unwrap();
+        let users = use_cases.list_users(&context, None, None, None).await.unwrap();
+
+        assert_eq!(users.len(), 1);
+    }
+
+    // ===================
+    // Tests: list_stamp
+    // ===================
+    #[tokio::test]
+    async fn test_list_stamp_is_read_afresh() {
+        let mut mock_user_query_repository = MockUserQueryRepository::new();
+        mock_user_query_repository.expect_list_stamp().times(2).returning(|_| {
+            Ok(UserListStamp {
+                count: 2,
+                ..UserListStamp::default()
+            })
+        });
+        let use_cases = create_use_cases_with_queries(mock_user_query_repository);
+        use_cases.repository_service.user_list_cache().set_ttl(StdDuration::from_secs(60));
+
+        use_cases.list_stamp(&RequestContext::internal()).await.unwrap();
+        let stamp = use_cases.list_stamp(&RequestContext::internal()).await.unwrap();
+
+        assert_eq!(stamp.count, 2);
+    }
+
+    // ===================
+    // Tests: count_users
+    // ===================
+    #[tokio::test]
+    async fn test_count_users() {
+        let mut mock_repository = MockUserRepository::new();
+        mock_repository.expect_count_users().return_const(Ok(42));
+        let use_cases = create_use_cases(mock_repository);
+
+        let result = use_cases.count_users(&RequestContext::internal()).await;
+
+        assert_eq!(result.unwrap(), 42);
+    }
+
     // ===================
     // Tests: delete_user
     // ===================
     #[tokio::test]
     async fn test_delete_user_success() {
         let user_to_delete = User::fake_with_age(1, "John Doe", "john@example.com", 30);
-        let mock_user_repository = MockUserRepository::default()
-            .with_find_by_id_result(Ok(Some(user_to_delete.clone())))
-            .with_delete_user_result(Ok(user_to_delete.clone()));
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_id().return_const(Ok(Some(user_to_delete.clone())));
+        mock_user_repository.expect_delete_user().return_const(Ok(user_to_delete.clone()));
         let use_cases = create_use_cases(mock_user_repository);
 
-        let result = use_cases.delete_user(1).await;
+        let result = use_cases.delete_user(&RequestContext::internal(), UserId::new(1)).await;
 
         assert!(result.is_ok());
         let deleted = result.unwrap();
-        assert_eq!(deleted.id, 1);
+        assert_eq!(deleted.id, UserId::new(1));
         assert_eq!(deleted.name, "John Doe");
         assert_eq!(deleted.age.value(), 30);
     }
 
     #[tokio::test]
     async fn test_delete_user_not_found() {
-        let mock_repository = MockUserRepository::default().with_find_by_id_result(Ok(None));
+        let mut mock_repository = MockUserRepository::new();
+        mock_repository.expect_find_by_id().return_const(Ok(None));
         let use_cases = create_use_cases(mock_repository);
 
-        let result = use_cases.delete_user(999).await;
+        let result = use_cases.delete_user(&RequestContext::internal(), UserId::new(999)).await;
 
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), Error::RepositoryError(RepositoryError::NotFound)));
@@ -448,28 +830,329 @@ mod tests {
     #[tokio::test]
     async fn test_find_by_token_found() {
         let expected_user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
-        let mock_user_repository = MockUserRepository::default().with_find_by_token_result(Ok(Some(expected_user.clone())));
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_token().return_const(Ok(Some(expected_user.clone())));
         let use_cases = create_use_cases(mock_user_repository);
 
-        let result = use_cases.find_by_token(expected_user.token).await;
+        let result = use_cases.find_by_token(&RequestContext::internal(), expected_user.token).await;
 
         assert!(result.is_ok());
         let user = result.unwrap();
         assert!(user.is_some());
         let user = user.unwrap();
-        assert_eq!(user.id, 1);
+        assert_eq!(user.id, UserId::new(1));
         assert_eq!(user.name, "John Doe");
         assert_eq!(user.age.value(), 30);
     }
 
     #[tokio::test]
     async fn test_find_by_token_not_found() {
-        let mock_repository = MockUserRepository::default().with_find_by_token_result(Ok(None));
+        let mut mock_repository = MockUserRepository::new();
+        mock_repository.expect_find_by_token().return_const(Ok(None));
         let use_cases = create_use_cases(mock_repository);
 
-        let result = use_cases.find_by_token(UserToken::generate()).await;
+        let result = use_cases.find_by_token(&RequestContext::internal(), UserToken::generate()).await;
 
         assert!(result.is_ok());
         assert!(result.unwrap().is_none());
     }
+
+    // ===================
+    // Tests: find_by_email
+    // ===================
+    #[tokio::test]
+    async fn test_find_by_email_found() {
+        let expected_user = User::fake(1, "John Doe", "john@example.com");
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_email().return_const(Ok(Some(expected_user)));
+        let use_cases = create_use_cases(mock_user_repository);
+
+        let result = use_cases
+            .find_by_email(&RequestContext::internal(), Email::new("john@example.com").unwrap())
+            .await;
+
+        let user = result.unwrap().unwrap();
+        assert_eq!(user.id, UserId::new(1));
+        assert_eq!(user.email.as_str(), "john@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_find_by_email_not_found() {
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_email().return_const(Ok(None));
+        let use_cases = create_use_cases(mock_user_repository);
+
+        let result = use_cases
+            .find_by_email(&RequestContext::internal(), Email::new("none@example.com").unwrap())
+            .await;
+
+        assert!(result.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_find_by_email_leaves_directory_alone() {
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_email().times(1).return_const(Ok(None));
+        mock_user_repository.expect_upsert_by_email().never();
+        mock_user_repository.expect_add_user().never();
+        let mut mock_user_directory = MockUserDirectoryPort::new();
+        mock_user_directory.expect_find_by_email().never();
+        let use_cases = create_use_cases_with_directory(mock_user_repository, mock_user_directory);
+
+        let result = use_cases
+            .find_by_email(&RequestContext::internal(), Email::new("john@example.com").unwrap())
+            .await;
+
+        assert!(result.unwrap().is_none());
+    }
+
+    // ===================
+    // Tests: find_in_directory
+    // ===================
+    #[tokio::test]
+    async fn test_find_in_directory_returns_person_without_adding_them() {
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_upsert_by_email().never();
+        mock_user_repository.expect_add_user().never();
+        let mut mock_user_directory = MockUserDirectoryPort::new();
+        mock_user_directory
+            .expect_find_by_email()
+            .times(1)
+            .returning(|_| Ok(Some(NewUser::new("John Doe", "JOHN@example.com", 30).unwrap())));
+        let use_cases = create_use_cases_with_directory(mock_user_repository, mock_user_directory);
+
+        let user = use_cases
+            .find_in_directory(&RequestContext::internal(), Email::new("john@example.com").unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(user.name, "John Doe");
+        assert_eq!(user.email.as_str(), "john@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_find_in_directory_not_found() {
+        let mut mock_user_directory = MockUserDirectoryPort::new();
+        mock_user_directory.expect_find_by_email().times(1).returning(|_| Ok(None));
+        let use_cases = create_use_cases_with_directory(MockUserRepository::new(), mock_user_directory);
+
+        let result = use_cases
+            .find_in_directory(&RequestContext::internal(), Email::new("nobody@example.com").unwrap())
+            .await;
+
+        assert!(result.unwrap().is_none());
+    }
+
+    // ===================
+    // Tests: find_by_ids
+    // ===================
+    #[tokio::test]
+    async fn test_find_by_ids_success() {
+        let users = vec![User::fake(1, "John Doe", "john@example.com"), User::fake(2, "Jane Doe", "jane@example.com")];
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_ids().return_const(Ok(users));
+        let use_cases = create_use_cases(mock_user_repository);
+
+        let result = use_cases
+            .find_by_ids(&RequestContext::internal(), &[UserId::new(1), UserId::new(2), UserId::new(3)])
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].id, UserId::new(1));
+        assert_eq!(result[1].id, UserId::new(2));
+    }
+
+    #[tokio::test]
+    async fn test_find_by_ids_empty_skips_repository() {
+        let use_cases = create_use_cases(MockUserRepository::new());
+
+        let result = use_cases.find_by_ids(&RequestContext::internal(), &[]).await;
+
+        assert!(result.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_find_by_ids_too_many() {
+        let use_cases = create_use_cases(MockUserRepository::new());
+        let ids: Vec<UserId> = (1..=MAX_BATCH_SIZE as u64 + 1).map(UserId::new).collect();
+
+        let result = use_cases.find_by_ids(&RequestContext::internal(), &ids).await;
+
+        assert!(matches!(result.unwrap_err(), Error::InvalidBatchSize(n) if n == MAX_BATCH_SIZE + 1));
+    }
+
+    // ===================
+    // Tests: find_by_tokens
+    // ===================
+    #[tokio::test]
+    async fn test_find_by_tokens_success() {
+        let user = User::fake(1, "John Doe", "john@example.com");
+        let token = user.token;
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_tokens().return_const(Ok(vec![user]));
+        let use_cases = create_use_cases(mock_user_repository);
+
+        let result = use_cases
+            .find_by_tokens(&RequestContext::internal(), &[token, UserToken::generate()])
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].token, token);
+    }
+
+    #[tokio::test]
+    async fn test_find_by_tokens_too_many() {
+        let use_cases = create_use_cases(MockUserRepository::new());
+        let tokens: Vec<UserToken> = (0..=MAX_BATCH_SIZE).map(|_| UserToken::generate()).collect();
+
+        let result = use_cases.find_by_tokens(&RequestContext::internal(), &tokens).await;
+
+        assert!(matches!(result.unwrap_err(), Error::InvalidBatchSize(_)));
+    }
+
+    // ===================
+    // Tests: find_matching
+    // ===================
+    #[tokio::test]
+    async fn test_find_matching_passes_specification() {
+        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let mut mock_user_query_repository = MockUserQueryRepository::new();
+        mock_user_query_repository
+            .expect_find_matching()
+            .withf(|_, specification, after_id, page_size| {
+                matches!(specification, UserSpecification::Is(UserPredicate::NeverSeen)) && *after_id == Some(UserId::new(7)) && *page_size == Some(10)
+            })
+            .return_const(Ok(vec![user]));
+        let use_cases = create_use_cases_with_queries(mock_user_query_repository);
+
+        let specification = UserSpecification::is(UserPredicate::NeverSeen);
+        let result = use_cases
+            .find_matching(&RequestContext::internal(), &specification, Some(UserId::new(7)), Some(10))
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 1);
+    }
+
+    // ===================
+    // Tests: stats
+    // ===================
+    #[tokio::test]
+    async fn test_stats_covers_last_thirty_days() {
+        let clock = FixedClock::default();
+        let now = clock.now();
+        let today = now.date_naive();
+        let stats = UserStats {
+            by_status: StatusCounts {
+                active: 2,
+                inactive: 1,
+                never_seen: 0,
+            },
+            signups_per_day: vec![DailySignups { date: today, count: 3 }],
+            by_age: Vec::new(),
+        };
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository
+            .expect_user_stats()
+            .with(always(), eq(now - Duration::days(30)), eq(today - Duration::days(29)))
+            .times(1)
+            .return_const(Ok(stats));
+        let use_cases = create_use_cases_with_clock(mock_user_repository, clock);
+
+        let stats = use_cases.stats(&RequestContext::internal()).await.unwrap();
+
+        assert_eq!(stats.total(), 3);
+        assert_eq!(stats.signups_per_day.len(), 30);
+        assert_eq!(stats.signups_per_day.first().unwrap().count, 0);
+        assert_eq!(*stats.signups_per_day.last().unwrap(), DailySignups { date: today, count: 3 });
+        assert_eq!(stats.signups_per_day[0].date, today - Duration::days(29));
+    }
+
+    // ===================
+    // Tests: rebuild_list_view
+    // ===================
+    #[tokio::test]
+    async fn test_rebuild_list_view_returns_users_held() {
+        let mut mock_user_query_repository = MockUserQueryRepository::new();
+        mock_user_query_repository.expect_rebuild().times(1).return_const(Ok(3));
+        let use_cases = create_use_cases_with_queries(mock_user_query_repository);
+
+        assert_eq!(use_cases.rebuild_list_view(&RequestContext::internal()).await.unwrap(), 3);
+    }
+
+    // ===================
+    // Tests: find_potential_duplicates
+    // ===================
+    #[tokio::test]
+    async fn test_find_potential_duplicates() {
+        let users = vec![
+            User::fake_with_age(1, "John Doe", "john.doe@example.com", 30),
+            User::fake_with_age(2, "Jane Roe", "jane@example.com", 25),
+            User::fake_with_age(3, "Johnny Doe", "johndoe+work@example.org", 31),
+        ];
+        let mut mock = MockUserQueryRepository::new();
+        mock.expect_list_users().return_const(Ok(users));
+        let use_cases = create_use_cases_with_queries(mock);
+
+        let groups = use_cases.find_potential_duplicates(&RequestContext::internal()).await.unwrap();
+
+        assert_eq!(groups.len(), 2);
+        for group in groups {
+            assert_eq!(group.users.iter().map(|user| user.id.value()).collect::<Vec<_>>(), [1, 3]);
+        }
+    }
+
+    // ===================
+    // Tests: maintenance mode
+    // ===================
+    #[tokio::test]
+    async fn test_maintenance_mode_rejects_writes() {
+        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_add_user().never();
+        mock_user_repository.expect_delete_user().never();
+        let use_cases = create_use_cases(mock_user_repository);
+        use_cases.repository_service.maintenance_mode().set_enabled(true);
+
+        let result = use_cases
+            .add_user(&RequestContext::internal(), NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await;
+        assert!(matches!(result.unwrap_err(), Error::ReadOnlyMode));
+
+        let result = use_cases.delete_user(&RequestContext::internal(), user.id).await;
+        assert_eq!(result.unwrap_err().kind(), crate::ErrorKind::Unavailable);
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_mode_allows_reads() {
+        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_id().return_const(Ok(Some(user.clone())));
+        let use_cases = create_use_cases(mock_user_repository);
+        use_cases.repository_service.maintenance_mode().set_enabled(true);
+
+        let result = use_cases.find_by_id(&RequestContext::internal(), user.id).await;
+
+        assert_eq!(result.unwrap().unwrap().id, user.id);
+    }
+
+    // ===================
+    // Tests: deadlines
+    // ===================
+    #[tokio::test]
+    async fn test_expired_deadline_cancels_before_querying() {
+        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_id().never();
+        let use_cases = create_use_cases(mock_user_repository);
+        let mut context = RequestContext::new("req-1");
+        context.deadline = Some(Instant::now());
+
+        let result = use_cases.find_by_id(&context, user.id).await;
+
+        assert!(matches!(result.unwrap_err(), Error::RepositoryError(RepositoryError::QueryCanceled)));
+    }
 }
@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+
+use crate::{
+    specification::{Specification, Subject},
+    types::Age,
+    user::User,
+};
+
+/// A filter on users, see [`UserService::find_matching`](crate::user::UserService::find_matching).
+pub type UserSpecification = Specification<User>;
+
+/// The conditions users can be filtered by. Text is compared ignoring case.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UserPredicate {
+    NameContains(String),
+    /// The part of the email after the `@` is `domain`.
+    EmailDomain(String),
+    AgeAtLeast(Age),
+    AgeAtMost(Age),
+    /// Seen at or after the time.
+    SeenSince(DateTime<Utc>),
+    NeverSeen,
+}
+
+impl Subject for User {
+    type Predicate = UserPredicate;
+
+    fn satisfies(&self, predicate: &UserPredicate) -> bool {
+        match predicate {
+            UserPredicate::NameContains(text) => self.name.to_lowercase().contains(&text.to_lowercase()),
+            UserPredicate::EmailDomain(domain) => self
+                .email
+                .as_str()
+                .rsplit_once('@')
+                .is_some_and(|(_, email_domain)| email_domain.eq_ignore_ascii_case(domain)),
+            UserPredicate::AgeAtLeast(age) => self.age >= *age,
+            UserPredicate::AgeAtMost(age) => self.age <= *age,
+            UserPredicate::SeenSince(since) => self.last_seen_at.is_some_and(|seen| seen >= *since),
+            UserPredicate::NeverSeen => self.last_seen_at.is_none(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{UserPredicate, UserSpecification};
+    use crate::{clock::FixedClock, types::Age, user::User};
+
+    // ===================
+    // Tests: Subject for User
+    // ===================
+    #[test]
+    fn test_user_predicates() {
+        let mut user = User::fake_with_age(1, "John Doe", "john@Example.com", 30);
+        let specification = UserSpecification::All(
+            [
+                UserPredicate::NameContains("doe".into()),
+                UserPredicate::EmailDomain("example.COM".into()),
+                UserPredicate::AgeAtLeast(Age::new(30).unwrap()),
+                UserPredicate::AgeAtMost(Age::new(30).unwrap()),
+                UserPredicate::NeverSeen,
+            ]
+            .into_iter()
+            .map(UserSpecification::is)
+            .collect(),
+        );
+
+        assert!(specification.is_satisfied_by(&user));
+        assert!(!UserSpecification::is(UserPredicate::EmailDomain("ample.com".into())).is_satisfied_by(&user));
+
+        user.last_seen_at = Some(FixedClock::epoch());
+        assert!(UserSpecification::is(UserPredicate::SeenSince(FixedClock::epoch())).is_satisfied_by(&user));
+        assert!(!UserSpecification::is(UserPredicate::NeverSeen).is_satisfied_by(&user));
+    }
+}
@@ -0,0 +1,109 @@
+use std::ops::RangeInclusive;
+
+use chrono::{Days, NaiveDate};
+
+use crate::types::Age;
+
+/// Days of signups reported by [`UserService::stats`], today included.
+///
+/// [`UserService::stats`]: crate::user::UserService::stats
+pub const SIGNUP_DAYS: u64 = 30;
+/// Users seen within this many days count as active.
+pub const ACTIVE_DAYS: u64 = 30;
+/// Age ranges the age distribution is bucketed into, covering every valid
+/// age.
+pub const AGE_BUCKETS: [RangeInclusive<i16>; 7] = [Age::MIN..=17, 18..=24, 25..=34, 35..=44, 45..=54, 55..=64, 65..=Age::MAX];
+
+/// Aggregate figures about the users, for dashboards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserStats {
+    pub by_status: StatusCounts,
+    /// Signups per day, oldest first.
+    pub signups_per_day: Vec<DailySignups>,
+    /// One entry per [`AGE_BUCKETS`] range, in order.
+    pub by_age: Vec<AgeBucketCount>,
+}
+
+impl UserStats {
+    pub fn total(&self) -> u64 {
+        self.by_status.active + self.by_status.inactive + self.by_status.never_seen
+    }
+
+    /// Spreads `signups_per_day` over every day from `first` to `last`,
+    /// counting days without signups as zero.
+    pub fn fill_days(&mut self, first: NaiveDate, last: NaiveDate) {
+        let mut days = Vec::new();
+        let mut date = first;
+        while date <= last {
+            let count = self.signups_per_day.iter().find(|day| day.date == date).map_or(0, |day| day.count);
+            days.push(DailySignups { date, count });
+            date = date + Days::new(1);
+        }
+        self.signups_per_day = days;
+    }
+}
+
+/// Users by how recently they were seen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StatusCounts {
+    /// Seen within the last [`ACTIVE_DAYS`].
+    pub active: u64,
+    /// Seen, but not within the last [`ACTIVE_DAYS`].
+    pub inactive: u64,
+    pub never_seen: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DailySignups {
+    pub date: NaiveDate,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgeBucketCount {
+    pub ages: RangeInclusive<i16>,
+    pub count: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{DailySignups, StatusCounts, UserStats};
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    // ===================
+    // Tests: UserStats
+    // ===================
+    #[test]
+    fn test_fill_days_adds_missing_days() {
+        let mut stats = UserStats {
+            by_status: StatusCounts::default(),
+            signups_per_day: vec![DailySignups { date: day(2), count: 3 }],
+            by_age: Vec::new(),
+        };
+
+        stats.fill_days(day(1), day(3));
+
+        let counts: Vec<_> = stats.signups_per_day.iter().map(|day| (day.date, day.count)).collect();
+        assert_eq!(counts, [(day(1), 0), (day(2), 3), (day(3), 0)]);
+    }
+
+    #[test]
+    fn test_total_sums_statuses() {
+        let stats = UserStats {
+            by_status: StatusCounts {
+                active: 1,
+                inactive: 2,
+                never_seen: 3,
+            },
+            signups_per_day: Vec::new(),
+            by_age: Vec::new(),
+        };
+
+        assert_eq!(stats.total(), 6);
+    }
+}
@@ -0,0 +1,8 @@
+pub mod model;
+pub mod service;
+
+pub use model::{UserInfo, UserInfoUpdate};
+#[cfg(any(test, feature = "test-support"))]
+pub use service::MockUserInfoService;
+pub use service::UserInfoService;
+pub(crate) use service::UserInfoServiceImpl;
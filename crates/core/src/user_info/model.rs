@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+
+use crate::{
+    types::Age,
+    user::{PartialUserUpdate, User, UserToken},
+};
+
+/// The profile of a user without the rest of the aggregate, for consumers
+/// such as analytics that have no use for names and emails.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserInfo {
+    pub token: UserToken,
+    pub age: Age,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<User> for UserInfo {
+    fn from(user: User) -> Self {
+        Self {
+            token: user.token,
+            age: user.age,
+            updated_at: user.updated_at,
+        }
+    }
+}
+
+/// Changes to the profile of a user. Like [`PartialUserUpdate`], `None`
+/// leaves a field untouched and `Some(None)` clears it back to its default.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UserInfoUpdate {
+    pub age: Option<Option<Age>>,
+}
+
+impl UserInfoUpdate {
+    /// Returns true if all fields are None.
+    pub fn is_empty(&self) -> bool {
+        self.age.is_none()
+    }
+}
+
+impl From<UserInfoUpdate> for PartialUserUpdate {
+    fn from(update: UserInfoUpdate) -> Self {
+        Self {
+            age: update.age,
+            ..Self::default()
+        }
+    }
+}
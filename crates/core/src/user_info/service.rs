@@ -0,0 +1,196 @@
+use std::sync::Arc;
+
+use crate::{
+    Error, RepositoryError,
+    cache::USERS,
+    context::RequestContext,
+    repository::RepositoryService,
+    user::{PartialUserUpdate, UserToken},
+    user_info::{UserInfo, UserInfoUpdate},
+};
+
+/// Reads and changes the profile of users by token, leaving the rest of the
+/// user aggregate to [`UserService`](crate::user::UserService).
+#[cfg_attr(any(test, feature = "test-support"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait UserInfoService: Send + Sync {
+    /// The profile of the user with `token`, `None` without one.
+    async fn get_info(&self, context: &RequestContext, token: UserToken) -> Result<Option<UserInfo>, Error>;
+    /// Applies `update` to the profile of the user with `token`. Bumps the
+    /// version of the user like any other update of it.
+    async fn update_info(&self, context: &RequestContext, token: UserToken, update: UserInfoUpdate) -> Result<UserInfo, Error>;
+}
+
+pub(crate) struct UserInfoServiceImpl {
+    repository_service: Arc<RepositoryService>,
+}
+
+impl UserInfoServiceImpl {
+    pub(crate) fn new(repository_service: Arc<RepositoryService>) -> Self {
+        Self { repository_service }
+    }
+}
+
+#[async_trait::async_trait]
+impl UserInfoService for UserInfoServiceImpl {
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn get_info(&self, context: &RequestContext, token: UserToken) -> Result<Option<UserInfo>, Error> {
+        let user = self
+            .repository_service
+            .execute_read_only(context, async |uow| uow.users().find_by_token(uow.tx(), token).await)
+            .await?;
+        Ok(user.map(Into::into))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, context), fields(request_id = %context.request_id))]
+    async fn update_info(&self, context: &RequestContext, token: UserToken, update: UserInfoUpdate) -> Result<UserInfo, Error> {
+        if update.is_empty() {
+            return Err(Error::EmptyUpdate);
+        }
+
+        let user = self
+            .repository_service
+            .execute(context, async |uow| {
+                let mut user = uow
+                    .users()
+                    .find_by_token(uow.tx(), token)
+                    .await?
+                    .ok_or(Error::RepositoryError(RepositoryError::NotFound))?;
+
+                PartialUserUpdate::from(update).apply_to(&mut user);
+                uow.users().update_user(uow.tx(), user).await
+            })
+            .await?;
+        self.repository_service.user_list_cache().invalidate(USERS);
+        Ok(user.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use mockall::predicate::{always, eq};
+
+    use super::{UserInfoService, UserInfoServiceImpl};
+    use crate::{
+        Error, RepositoryError,
+        context::RequestContext,
+        test_support::{MockSessionRepository, MockUserRepository, MockWebhookRepository, mock_repository_service},
+        types::Age,
+        user::{User, UserId, UserToken},
+        user_info::UserInfoUpdate,
+    };
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn create_use_cases(mock_user_repository: MockUserRepository) -> UserInfoServiceImpl {
+        let repository_service = mock_repository_service(mock_user_repository, MockSessionRepository::new(), MockWebhookRepository::new())
+            .build()
+            .expect("All required fields provided");
+        UserInfoServiceImpl::new(Arc::new(repository_service))
+    }
+
+    // ===================
+    // Tests: get_info
+    // ===================
+    #[tokio::test]
+    async fn test_get_info_success() {
+        let user = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository
+            .expect_find_by_token()
+            .with(always(), eq(UserToken::new(UserId::new(1))))
+            .return_const(Ok(Some(user.clone())));
+        let use_cases = create_use_cases(mock_user_repository);
+
+        let info = use_cases
+            .get_info(&RequestContext::internal(), UserToken::new(UserId::new(1)))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(info.token, user.token);
+        assert_eq!(info.age.value(), 30);
+        assert_eq!(info.updated_at, user.updated_at);
+    }
+
+    #[tokio::test]
+    async fn test_get_info_not_found() {
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_token().return_const(Ok(None));
+        let use_cases = create_use_cases(mock_user_repository);
+
+        let result = use_cases.get_info(&RequestContext::internal(), UserToken::new(UserId::new(1))).await;
+
+        assert!(result.unwrap().is_none());
+    }
+
+    // ===================
+    // Tests: update_info
+    // ===================
+    #[tokio::test]
+    async fn test_update_info_changes_only_age() {
+        let existing = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_token().return_const(Ok(Some(existing)));
+        mock_user_repository
+            .expect_update_user()
+            .withf(|_, user| user.name == "John Doe" && user.email.as_str() == "john@example.com" && user.age.value() == 31)
+            .returning(|_, user| Ok(user));
+        let use_cases = create_use_cases(mock_user_repository);
+
+        let update = UserInfoUpdate {
+            age: Some(Some(Age::new(31).unwrap())),
+        };
+        let info = use_cases
+            .update_info(&RequestContext::internal(), UserToken::new(UserId::new(1)), update)
+            .await
+            .unwrap();
+
+        assert_eq!(info.age.value(), 31);
+    }
+
+    #[tokio::test]
+    async fn test_update_info_clears_age() {
+        let existing = User::fake_with_age(1, "John Doe", "john@example.com", 30);
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_token().return_const(Ok(Some(existing)));
+        mock_user_repository.expect_update_user().returning(|_, user| Ok(user));
+        let use_cases = create_use_cases(mock_user_repository);
+
+        let update = UserInfoUpdate { age: Some(None) };
+        let info = use_cases
+            .update_info(&RequestContext::internal(), UserToken::new(UserId::new(1)), update)
+            .await
+            .unwrap();
+
+        assert_eq!(info.age, Age::default());
+    }
+
+    #[tokio::test]
+    async fn test_update_info_empty() {
+        let use_cases = create_use_cases(MockUserRepository::new());
+
+        let result = use_cases
+            .update_info(&RequestContext::internal(), UserToken::new(UserId::new(1)), UserInfoUpdate::default())
+            .await;
+
+        assert!(matches!(result.unwrap_err(), Error::EmptyUpdate));
+    }
+
+    #[tokio::test]
+    async fn test_update_info_not_found() {
+        let mut mock_user_repository = MockUserRepository::new();
+        mock_user_repository.expect_find_by_token().return_const(Ok(None));
+        let use_cases = create_use_cases(mock_user_repository);
+
+        let update = UserInfoUpdate {
+            age: Some(Some(Age::new(31).unwrap())),
+        };
+        let result = use_cases.update_info(&RequestContext::internal(), UserToken::new(UserId::new(1)), update).await;
+
+        assert!(matches!(result.unwrap_err(), Error::RepositoryError(RepositoryError::NotFound)));
+    }
+}
@@ -0,0 +1,10 @@
+pub mod model;
+pub mod repository;
+pub mod service;
+
+pub use model::{Delivery, UserSyncEvent};
+pub use repository::WebhookRepository;
+pub(crate) use service::WebhookServiceImpl;
+pub use service::{REPLAY_WINDOW, WebhookService};
+#[cfg(any(test, feature = "test-support"))]
+pub use {repository::MockWebhookRepository, service::MockWebhookService};
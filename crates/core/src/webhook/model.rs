@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+
+use crate::{
+    types::Email,
+    user::{NewUser, PartialUserUpdate},
+};
+
+/// A change to a user reported by an external identity provider, which
+/// knows users by their email.
+#[derive(Debug, Clone)]
+pub enum UserSyncEvent {
+    /// Adds the user, or updates the one with the same email.
+    Created(NewUser),
+    Updated {
+        email: Email,
+        update: PartialUserUpdate,
+    },
+    /// Removes the user. Unknown emails are ignored.
+    Deleted {
+        email: Email,
+    },
+}
+
+/// Identifies one webhook delivery, so replays can be rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delivery {
+    /// Unique per delivery, chosen by the sender.
+    pub nonce: String,
+    pub sent_at: DateTime<Utc>,
+}
@@ -0,0 +1,13 @@
+use chrono::{DateTime, Utc};
+
+use crate::{Error, repository::Transaction};
+
+#[cfg_attr(any(test, feature = "test-support"), mockall::automock, allow(unused_parens))]
+#[async_trait::async_trait]
+pub trait WebhookRepository: Send + Sync {
+    /// Records `nonce` as received at `received_at`. Returns false if it was
+    /// already recorded.
+    async fn claim_nonce(&self, transaction: &(dyn Transaction + 'static), nonce: &str, received_at: DateTime<Utc>) -> Result<bool, Error>;
+    /// Forgets the nonces received before `before`, returning how many.
+    async fn delete_nonces_before(&self, transaction: &(dyn Transaction + 'static), before: DateTime<Utc>) -> Result<u64, Error>;
+}
@@ -0,0 +1,322 @@
+use std::sync::Arc;
+
+use chrono::TimeDelta;
+
+use crate::{
+    Error, RepositoryError,
+    cache::USERS,
+    context::RequestContext,
+    repository::RepositoryService,
+    user::User,
+    webhook::{Delivery, UserSyncEvent},
+};
+
+/// How far the timestamp of a delivery may be from now, either way. Nonces
+/// are kept for twice as long, so a replay is rejected by its nonce while
+/// its timestamp is still accepted.
+pub const REPLAY_WINDOW: TimeDelta = TimeDelta::minutes(5);
+
+/// Applies user changes pushed by an external identity provider.
+#[cfg_attr(any(test, feature = "test-support"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait WebhookService: Send + Sync {
+    /// Applies `event`, unless `delivery` was received before or its
+    /// timestamp is outside [`REPLAY_WINDOW`]. Returns the user as stored,
+    /// or `None` when the user to delete did not exist.
+    async fn receive_user_event(&self, context: &RequestContext, delivery: Delivery, event: UserSyncEvent) -> Result<Option<User>, Error>;
+}
+
+pub(crate) struct WebhookServiceImpl {
+    repository_service: Arc<RepositoryService>,
+}
+
+impl WebhookServiceImpl {
+    pub(crate) fn new(repository_service: Arc<RepositoryService>) -> Self {
+        Self { repository_service }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebhookService for WebhookServiceImpl {
+    #[tracing::instrument(level = "trace", skip(self, context, event), fields(request_id = %context.request_id))]
+    async fn receive_user_event(&self, context: &RequestContext, delivery: Delivery, event: UserSyncEvent) -> Result<Option<User>, Error> {
+        let now = self.repository_service.clock().now();
+        if (now - delivery.sent_at).abs() > REPLAY_WINDOW {
+            return Err(Error::WebhookReplay);
+        }
+        if let UserSyncEvent::Updated { update, .. } = &event
+            && update.is_empty()
+        {
+            return Err(Error::EmptyUpdate);
+        }
+
+        // The nonce is claimed in the same transaction as the change, so a
+        // delivery that fails can be retried.
+        self.repository_service
+            .execute(context, async |uow| {
+                uow.webhooks().delete_nonces_before(uow.tx(), now - REPLAY_WINDOW * 2).await?;
+                if !uow.webhooks().claim_nonce(uow.tx(), &delivery.nonce, now).await? {
+                    return Err(Error::WebhookReplay);
+                }
+
+                match event {
+                    UserSyncEvent::Created(user) => uow.users().upsert_by_email(uow.tx(), user).await.map(Some),
+                    UserSyncEvent::Updated { email, update } => {
+                        let mut user = uow
+                            .users()
+                            .find_by_email(uow.tx(), &email)
+                            .await?
+                            .ok_or(Error::RepositoryError(RepositoryError::NotFound))?;
+                        update.apply_to(&mut user);
+                        uow.users().update_user(uow.tx(), user).await.map(Some)
+                    }
+                    UserSyncEvent::Deleted { email } => match uow.users().find_by_email(uow.tx(), &email).await? {
+                        Some(user) => uow.users().delete_user(uow.tx(), user).await.map(Some),
+                        None => Ok(None),
+                    },
+                }
+            })
+            .await
+            .inspect(|_| self.repository_service.user_list_cache().invalidate(USERS))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        sync::{Arc, Mutex},
+        time::Duration as StdDuration,
+    };
+
+    use chrono::TimeDelta;
+    use mockall::predicate::{always, eq};
+
+    use super::{REPLAY_WINDOW, WebhookService, WebhookServiceImpl};
+    use crate::{
+        Error, ErrorKind,
+        cache::USERS,
+        clock::{Clock, FixedClock},
+        context::RequestContext,
+        i18n::ValidationMessage,
+        test_support::{MockSessionRepository, MockUserRepository, MockWebhookRepository, mock_repository_service},
+        types::Email,
+        user::{
+            PartialUserUpdate,
+            model::{NewUser, User, UserBuilder, UserId},
+        },
+        webhook::{Delivery, UserSyncEvent},
+    };
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn create_service(user_repository: MockUserRepository, webhook_repository: MockWebhookRepository) -> WebhookServiceImpl {
+        let repository_service = mock_repository_service(user_repository, MockSessionRepository::new(), webhook_repository)
+            .clock(Arc::new(FixedClock::default()) as Arc<dyn Clock>)
+            .build()
+            .expect("All required fields provided");
+        WebhookServiceImpl::new(Arc::new(repository_service))
+    }
+
+    /// A repository holding at most one user, found by any email.
+    fn user_repository(existing: Option<User>) -> MockUserRepository {
+        let mut repository = MockUserRepository::new();
+        repository.expect_find_by_email().return_const(Ok(existing));
+        repository.expect_upsert_by_email().returning(|_, user| {
+            Ok(UserBuilder::default()
+                .name(user.name)
+                .email(user.email)
+                .age(user.age)
+                .created_at(FixedClock::epoch())
+                .updated_at(FixedClock::epoch())
+                .build()
+                .unwrap())
+        });
+        repository.expect_update_user().returning(|_, user| Ok(user));
+        repository
+    }
+
+    /// A repository that remembers the nonces it has claimed.
+    fn webhook_repository() -> MockWebhookRepository {
+        let nonces = Mutex::new(HashSet::new());
+        let mut repository = MockWebhookRepository::new();
+        repository
+            .expect_claim_nonce()
+            .returning(move |_, nonce, _| Ok(nonces.lock().unwrap().insert(nonce.to_string())));
+        repository.expect_delete_nonces_before().return_const(Ok(0));
+        repository
+    }
+
+    fn delivery(nonce: &str) -> Delivery {
+        Delivery {
+            nonce: nonce.to_string(),
+            sent_at: FixedClock::epoch(),
+        }
+    }
+
+    fn existing_user() -> User {
+        UserBuilder::default()
+            .id(UserId::new(7))
+            .name("Old Name".to_string())
+            .email(Email::new("user@example.com").unwrap())
+            .created_at(FixedClock::epoch())
+            .updated_at(FixedClock::epoch())
+            .build()
+            .unwrap()
+    }
+
+    fn email() -> Email {
+        Email::new("user@example.com").unwrap()
+    }
+
+    // ===================
+    // Tests: receive_user_event
+    // ===================
+    #[tokio::test]
+    async fn test_created_upserts_user() {
+        let mut webhook_repository = MockWebhookRepository::new();
+        webhook_repository.expect_claim_nonce().return_const(Ok(true));
+        webhook_repository
+            .expect_delete_nonces_before()
+            .with(always(), eq(FixedClock::epoch() - REPLAY_WINDOW * 2))
+            .times(1)
+            .return_const(Ok(0));
+        let service = create_service(user_repository(None), webhook_repository);
+        let event = UserSyncEvent::Created(NewUser::new("New User", "user@example.com", 30).unwrap());
+
+        let user = service.receive_user_event(&RequestContext::internal(), delivery("n1"), event).await.unwrap();
+
+        assert_eq!(user.unwrap().name, "New User");
+    }
+
+    #[tokio::test]
+    async fn test_updated_applies_changes() {
+        let service = create_service(user_repository(Some(existing_user())), webhook_repository());
+        let event = UserSyncEvent::Updated {
+            email: email(),
+            update: PartialUserUpdate::new(Some("New Name"), None::<String>, None).unwrap(),
+        };
+
+        let user = service.receive_user_event(&RequestContext::internal(), delivery("n1"), event).await.unwrap();
+
+        assert_eq!(user.unwrap().name, "New Name");
+    }
+
+    #[tokio::test]
+    async fn test_created_with_blank_name_is_refused() {
+        let mut repository = user_repository(None);
+        repository.expect_upsert_by_email().never();
+        let service = create_service(repository, webhook_repository());
+        let event = UserSyncEvent::Created(NewUser::new(" ", "user@example.com", 30).unwrap());
+
+        let result = service.receive_user_event(&RequestContext::internal(), delivery("n1"), event).await;
+
+        assert!(matches!(result, Err(Error::Validation(ValidationMessage::BlankName))));
+    }
+
+    #[tokio::test]
+    async fn test_update_blanking_name_is_refused() {
+        let mut repository = MockUserRepository::new();
+        repository.expect_find_by_email().return_const(Ok(Some(existing_user())));
+        repository.expect_update_user().never();
+        let service = create_service(repository, webhook_repository());
+        let event = UserSyncEvent::Updated {
+            email: email(),
+            update: PartialUserUpdate::new(Some(""), None::<String>, None).unwrap(),
+        };
+
+        let result = service.receive_user_event(&RequestContext::internal(), delivery("n1"), event).await;
+
+        assert!(matches!(result, Err(Error::Validation(ValidationMessage::BlankName))));
+    }
+
+    #[tokio::test]
+    async fn test_updated_unknown_user_is_not_found() {
+        let service = create_service(user_repository(None), webhook_repository());
+        let event = UserSyncEvent::Updated {
+            email: email(),
+            update: PartialUserUpdate::new(Some("New Name"), None::<String>, None).unwrap(),
+        };
+
+        let result = service.receive_user_event(&RequestContext::internal(), delivery("n1"), event).await;
+
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_deleted_removes_user_and_ignores_unknown() {
+        let mut repository = user_repository(Some(existing_user()));
+        repository
+            .expect_delete_user()
+            .withf(|_, user| user.id == UserId::new(7))
+            .times(1)
+            .returning(|_, user| Ok(user));
+        let service = create_service(repository, webhook_repository());
+        let deleted = service
+            .receive_user_event(&RequestContext::internal(), delivery("n1"), UserSyncEvent::Deleted { email: email() })
+            .await
+            .unwrap();
+        assert_eq!(deleted.map(|user| user.id), Some(UserId::new(7)));
+
+        let mut repository = user_repository(None);
+        repository.expect_delete_user().never();
+        let service = create_service(repository, webhook_repository());
+        let deleted = service
+            .receive_user_event(&RequestContext::internal(), delivery("n1"), UserSyncEvent::Deleted { email: email() })
+            .await
+            .unwrap();
+        assert!(deleted.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_applied_event_drops_cached_user_pages() {
+        let service = create_service(user_repository(Some(existing_user())), webhook_repository());
+        let cache = service.repository_service.user_list_cache();
+        cache.set_ttl(StdDuration::from_secs(60));
+        cache
+            .get_or_load("page".to_string(), &[USERS], async || Ok(vec![existing_user()]))
+            .await
+            .unwrap();
+        let event = UserSyncEvent::Updated {
+            email: email(),
+            update: PartialUserUpdate::new(Some("New Name"), None::<String>, None).unwrap(),
+        };
+
+        service.receive_user_event(&RequestContext::internal(), delivery("n1"), event).await.unwrap();
+        let users = cache.get_or_load("page".to_string(), &[USERS], async || Ok(vec![])).await.unwrap();
+
+        assert!(users.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_repeated_nonce_is_rejected() {
+        let service = create_service(user_repository(None), webhook_repository());
+        let event = UserSyncEvent::Deleted { email: email() };
+
+        service
+            .receive_user_event(&RequestContext::internal(), delivery("n1"), event.clone())
+            .await
+            .unwrap();
+        let result = service.receive_user_event(&RequestContext::internal(), delivery("n1"), event).await;
+
+        assert!(matches!(result, Err(Error::WebhookReplay)));
+    }
+
+    #[tokio::test]
+    async fn test_stale_delivery_is_rejected() {
+        let mut webhook_repository = MockWebhookRepository::new();
+        webhook_repository.expect_claim_nonce().never();
+        let service = create_service(user_repository(None), webhook_repository);
+        let stale = Delivery {
+            nonce: "n1".to_string(),
+            sent_at: FixedClock::epoch() - REPLAY_WINDOW - TimeDelta::seconds(1),
+        };
+
+        let result = service
+            .receive_user_event(&RequestContext::internal(), stale, UserSyncEvent::Deleted { email: email() })
+            .await;
+
+        assert!(matches!(result, Err(Error::WebhookReplay)));
+    }
+}
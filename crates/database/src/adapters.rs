@@ -1,2 +1,5 @@
+pub(crate) mod lease;
 pub(crate) mod session;
 pub(crate) mod user;
+pub(crate) mod user_query;
+pub(crate) mod webhook;
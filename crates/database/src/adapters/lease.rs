@@ -0,0 +1,312 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use hex_play_core::{
+    Error,
+    lease::{Lease, LeaseRepository},
+    repository::Transaction,
+};
+use sea_orm::{
+    ActiveValue::Set,
+    ColumnTrait, EntityTrait, QueryFilter, QueryOrder,
+    sea_query::{Expr, OnConflict},
+};
+
+use crate::{
+    entities::{leases, prelude},
+    error::handle_dberr,
+    latency::LatencyBudgets,
+    transaction::TransactionImpl,
+};
+
+pub struct LeaseRepositoryAdapter {
+    latency_budgets: Arc<LatencyBudgets>,
+}
+
+impl LeaseRepositoryAdapter {
+    pub(crate) fn new(latency_budgets: Arc<LatencyBudgets>) -> Self {
+        Self { latency_budgets }
+    }
+}
+
+#[async_trait::async_trait]
+impl LeaseRepository for LeaseRepositoryAdapter {
+    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    async fn try_acquire(
+        &self,
+        transaction: &dyn Transaction,
+        name: &str,
+        holder: &str,
+        now: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<Lease>, Error> {
+        // Scoped here: `ExprTrait::eq` would clash with `ColumnTrait::eq`
+        // elsewhere.
+        use sea_orm::sea_query::ExprTrait;
+
+        let _timer = self.latency_budgets.start("lease", "try_acquire");
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+
+        let model = leases::ActiveModel {
+            name: Set(name.to_string()),
+            holder: Set(holder.to_string()),
+            fencing_token: Set(1),
+            expires_at: Set(expires_at.into()),
+        };
+
+        // One statement decides between instances racing for the lease: the
+        // existing row is only taken over if it has expired or is ours, and
+        // otherwise no row is written. A new holder gets the next fencing
+        // token, a renewal keeps its own.
+        let fencing_token: Expr = Expr::case(
+            Expr::col((leases::Entity, leases::Column::Holder)).eq(holder),
+            Expr::col((leases::Entity, leases::Column::FencingToken)),
+        )
+        .finally(Expr::col((leases::Entity, leases::Column::FencingToken)).add(1))
+        .into();
+        let written = prelude::Leases::insert(model)
+            .on_conflict(
+                OnConflict::column(leases::Column::Name)
+                    .update_columns([leases::Column::Holder, leases::Column::ExpiresAt])
+                    .value(leases::Column::FencingToken, fencing_token)
+                    .action_and_where(
+                        Expr::col((leases::Entity, leases::Column::ExpiresAt))
+                            .lte(now.fixed_offset())
+                            .or(Expr::col((leases::Entity, leases::Column::Holder)).eq(holder)),
+                    )
+                    .to_owned(),
+            )
+            .exec_without_returning(transaction)
+            .await
+            .map_err(handle_dberr)?;
+        if written == 0 {
+            return Ok(None);
+        }
+
+        let lease = prelude::Leases::find_by_id(name).one(transaction).await.map_err(handle_dberr)?;
+
+        Ok(lease.map(Lease::from))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    async fn release(&self, transaction: &dyn Transaction, name: &str, holder: &str, now: DateTime<Utc>) -> Result<bool, Error> {
+        let _timer = self.latency_budgets.start("lease", "release");
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+
+        let now = now.fixed_offset();
+        let result = prelude::Leases::update_many()
+            .col_expr(leases::Column::ExpiresAt, Expr::value(now))
+            .filter(leases::Column::Name.eq(name))
+            .filter(leases::Column::Holder.eq(holder))
+            .filter(leases::Column::ExpiresAt.gt(now))
+            .exec(transaction)
+            .await
+            .map_err(handle_dberr)?;
+
+        Ok(result.rows_affected > 0)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    async fn list_active(&self, transaction: &dyn Transaction, now: DateTime<Utc>) -> Result<Vec<Lease>, Error> {
+        let _timer = self.latency_budgets.start("lease", "list_active");
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+
+        let leases = prelude::Leases::find()
+            .filter(leases::Column::ExpiresAt.gt(now.fixed_offset()))
+            .order_by_asc(leases::Column::Name)
+            .all(transaction)
+            .await
+            .map_err(handle_dberr)?;
+
+        Ok(leases.into_iter().map(Lease::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{Duration, Utc};
+    use hex_play_core::repository::RepositoryService;
+    use sea_orm::Database;
+
+    use crate::create_repository_service;
+
+    async fn setup() -> Arc<RepositoryService> {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        create_repository_service(db).await.unwrap()
+    }
+
+    // ===================
+    // Tests: try_acquire
+    // ===================
+    #[tokio::test]
+    async fn test_try_acquire_free_lease() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let now = Utc::now();
+
+        let lease = svc
+            .lease_repository()
+            .try_acquire(&*tx, "job", "a", now, now + Duration::seconds(30))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(lease.holder, "a");
+        assert_eq!(lease.fencing_token, 1);
+    }
+
+    #[tokio::test]
+    async fn test_try_acquire_refuses_lease_held_by_another() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let now = Utc::now();
+        svc.lease_repository()
+            .try_acquire(&*tx, "job", "a", now, now + Duration::seconds(30))
+            .await
+            .unwrap();
+
+        let taken = svc
+            .lease_repository()
+            .try_acquire(&*tx, "job", "b", now + Duration::seconds(10), now + Duration::seconds(40))
+            .await
+            .unwrap();
+
+        assert_eq!(taken, None);
+        assert!(
+            svc.lease_repository()
+                .try_acquire(&*tx, "other", "b", now, now + Duration::seconds(30))
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_try_acquire_renews_own_lease() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let now = Utc::now();
+        svc.lease_repository()
+            .try_acquire(&*tx, "job", "a", now, now + Duration::seconds(30))
+            .await
+            .unwrap();
+
+        let renewed = svc
+            .lease_repository()
+            .try_acquire(&*tx, "job", "a", now + Duration::seconds(20), now + Duration::seconds(50))
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Renewed until 50s, so still held at 40s.
+        assert_eq!(renewed.fencing_token, 1);
+        assert!(
+            svc.lease_repository()
+                .try_acquire(&*tx, "job", "b", now + Duration::seconds(40), now + Duration::seconds(70))
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_try_acquire_takes_over_expired_lease() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let now = Utc::now();
+        // The holder crashed and never renewed.
+        svc.lease_repository()
+            .try_acquire(&*tx, "job", "a", now, now + Duration::seconds(30))
+            .await
+            .unwrap();
+
+        let taken = svc
+            .lease_repository()
+            .try_acquire(&*tx, "job", "b", now + Duration::seconds(31), now + Duration::seconds(61))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(taken.holder, "b");
+        assert_eq!(taken.fencing_token, 2);
+        assert!(
+            svc.lease_repository()
+                .try_acquire(&*tx, "job", "a", now + Duration::seconds(32), now + Duration::seconds(62))
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    // ===================
+    // Tests: release
+    // ===================
+    #[tokio::test]
+    async fn test_release_frees_lease_for_others() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let now = Utc::now();
+        svc.lease_repository()
+            .try_acquire(&*tx, "job", "a", now, now + Duration::seconds(30))
+            .await
+            .unwrap();
+
+        assert!(svc.lease_repository().release(&*tx, "job", "a", now).await.unwrap());
+        let taken = svc
+            .lease_repository()
+            .try_acquire(&*tx, "job", "b", now, now + Duration::seconds(30))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(taken.fencing_token, 2);
+    }
+
+    #[tokio::test]
+    async fn test_release_leaves_lease_of_another() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let now = Utc::now();
+        svc.lease_repository()
+            .try_acquire(&*tx, "job", "a", now, now + Duration::seconds(30))
+            .await
+            .unwrap();
+
+        assert!(!svc.lease_repository().release(&*tx, "job", "b", now).await.unwrap());
+        assert!(
+            svc.lease_repository()
+                .try_acquire(&*tx, "job", "b", now, now + Duration::seconds(30))
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    // ===================
+    // Tests: list_active
+    // ===================
+    #[tokio::test]
+    async fn test_list_active_skips_expired_leases() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let now = Utc::now();
+        svc.lease_repository()
+            .try_acquire(&*tx, "rollups", "a", now, now + Duration::seconds(30))
+            .await
+            .unwrap();
+        svc.lease_repository()
+            .try_acquire(&*tx, "expired", "a", now, now + Duration::seconds(5))
+            .await
+            .unwrap();
+        svc.lease_repository()
+            .try_acquire(&*tx, "export", "b", now, now + Duration::seconds(30))
+            .await
+            .unwrap();
+
+        let leases = svc.lease_repository().list_active(&*tx, now + Duration::seconds(10)).await.unwrap();
+
+        let names: Vec<_> = leases.iter().map(|lease| lease.name.as_str()).collect();
+        assert_eq!(names, ["export", "rollups"]);
+    }
+}
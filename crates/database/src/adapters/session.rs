@@ -1,33 +1,28 @@
-use chrono::Utc;
+use std::sync::Arc;
+
 use hex_play_core::{
     Error, RepositoryError,
+    clock::Clock,
     repository::Transaction,
-    session::{NewSession, Session, SessionRepository},
+    session::{NewSession, Session, SessionId, SessionRepository},
 };
 use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, ModelTrait, PaginatorTrait, QueryFilter, QuerySelect, sea_query::OnConflict};
 
 use crate::{
     entities::{prelude, sessions},
     error::handle_dberr,
+    latency::LatencyBudgets,
     transaction::TransactionImpl,
 };
 
-impl From<sessions::Model> for Session {
-    fn from(model: sessions::Model) -> Self {
-        Self {
-            id: model.id,
-            session: model.session,
-            expires_at: model.expires_at.with_timezone(&Utc),
-            created_at: model.created_at.with_timezone(&Utc),
-        }
-    }
+pub struct SessionRepositoryAdapter {
+    clock: Arc<dyn Clock>,
+    latency_budgets: Arc<LatencyBudgets>,
 }
 
-pub struct SessionRepositoryAdapter;
-
 impl SessionRepositoryAdapter {
-    pub(crate) fn new() -> Self {
-        Self
+    pub(crate) fn new(clock: Arc<dyn Clock>, latency_budgets: Arc<LatencyBudgets>) -> Self {
+        Self { clock, latency_budgets }
     }
 }
 
@@ -35,6 +30,7 @@ impl SessionRepositoryAdapter {
 impl SessionRepository for SessionRepositoryAdapter {
     #[tracing::instrument(level = "trace", skip(self, transaction))]
     async fn count(&self, transaction: &dyn Transaction) -> Result<i64, Error> {
+        let _timer = self.latency_budgets.start("session", "count");
         let transaction = TransactionImpl::get_db_transaction(transaction)?;
 
         let count = prelude::Sessions::find().count(transaction).await.map_err(handle_dberr)?;
@@ -44,13 +40,14 @@ impl SessionRepository for SessionRepositoryAdapter {
 
     #[tracing::instrument(level = "trace", skip(self, transaction))]
     async fn store(&self, transaction: &dyn Transaction, session: NewSession) -> Result<Session, Error> {
+        let _timer = self.latency_budgets.start("session", "store");
         let transaction = TransactionImpl::get_db_transaction(transaction)?;
 
         let model = sessions::ActiveModel {
-            id: Set(session.id.clone()),
+            id: Set(session.id.to_string()),
             session: Set(session.session),
             expires_at: Set(session.expires_at.into()),
-            created_at: Set(Utc::now().into()),
+            created_at: Set(self.clock.now().into()),
         };
 
         let on_conflict = OnConflict::column(sessions::Column::Id)
@@ -64,7 +61,7 @@ impl SessionRepository for SessionRepositoryAdapter {
             .map_err(handle_dberr)?;
 
         // Reload to get the final state (handles both insert and update cases)
-        let stored = prelude::Sessions::find_by_id(&session.id)
+        let stored = prelude::Sessions::find_by_id(session.id.as_str())
             .one(transaction)
             .await
             .map_err(handle_dberr)?
@@ -74,17 +71,23 @@ impl SessionRepository for SessionRepositoryAdapter {
     }
 
     #[tracing::instrument(level = "trace", skip(self, transaction))]
-    async fn load(&self, transaction: &dyn Transaction, id: &str) -> Result<Option<Session>, Error> {
+    async fn load(&self, transaction: &dyn Transaction, id: &SessionId) -> Result<Option<Session>, Error> {
+        let _timer = self.latency_budgets.start("session", "load");
         let transaction = TransactionImpl::get_db_transaction(transaction)?;
 
-        Ok(prelude::Sessions::find_by_id(id).one(transaction).await.map_err(handle_dberr)?.map(Into::into))
+        Ok(prelude::Sessions::find_by_id(id.as_str())
+            .one(transaction)
+            .await
+            .map_err(handle_dberr)?
+            .map(Into::into))
     }
 
     #[tracing::instrument(level = "trace", skip(self, transaction))]
-    async fn delete_by_id(&self, transaction: &dyn Transaction, id: &str) -> Result<(), Error> {
+    async fn delete_by_id(&self, transaction: &dyn Transaction, id: &SessionId) -> Result<(), Error> {
+        let _timer = self.latency_budgets.start("session", "delete_by_id");
         let transaction = TransactionImpl::get_db_transaction(transaction)?;
 
-        let existing = prelude::Sessions::find_by_id(id).one(transaction).await.map_err(handle_dberr)?;
+        let existing = prelude::Sessions::find_by_id(id.as_str()).one(transaction).await.map_err(handle_dberr)?;
 
         if let Some(existing) = existing {
             existing.delete(transaction).await.map_err(handle_dberr)?;
@@ -94,18 +97,20 @@ impl SessionRepository for SessionRepositoryAdapter {
     }
 
     #[tracing::instrument(level = "trace", skip(self, transaction))]
-    async fn exists(&self, transaction: &dyn Transaction, id: &str) -> Result<bool, Error> {
+    async fn exists(&self, transaction: &dyn Transaction, id: &SessionId) -> Result<bool, Error> {
+        let _timer = self.latency_budgets.start("session", "exists");
         let transaction = TransactionImpl::get_db_transaction(transaction)?;
 
-        let count = prelude::Sessions::find_by_id(id).count(transaction).await.map_err(handle_dberr)?;
+        let count = prelude::Sessions::find_by_id(id.as_str()).count(transaction).await.map_err(handle_dberr)?;
 
         Ok(count > 0)
     }
 
     #[tracing::instrument(level = "trace", skip(self, transaction))]
-    async fn delete_by_expiry(&self, transaction: &dyn Transaction) -> Result<Vec<String>, Error> {
+    async fn delete_by_expiry(&self, transaction: &dyn Transaction) -> Result<Vec<SessionId>, Error> {
+        let _timer = self.latency_budgets.start("session", "delete_by_expiry");
         let transaction = TransactionImpl::get_db_transaction(transaction)?;
-        let now = Utc::now();
+        let now = self.clock.now();
 
         // Fetch only the IDs of expired sessions
         let ids: Vec<String> = prelude::Sessions::find()
@@ -124,11 +129,12 @@ impl SessionRepository for SessionRepositoryAdapter {
             .await
             .map_err(handle_dberr)?;
 
-        Ok(ids)
+        Ok(ids.into_iter().map(SessionId::new).collect())
     }
 
     #[tracing::instrument(level = "trace", skip(self, transaction))]
     async fn delete_all(&self, transaction: &dyn Transaction) -> Result<(), Error> {
+        let _timer = self.latency_budgets.start("session", "delete_all");
         let transaction = TransactionImpl::get_db_transaction(transaction)?;
 
         prelude::Sessions::delete_many().exec(transaction).await.map_err(handle_dberr)?;
@@ -137,7 +143,8 @@ impl SessionRepository for SessionRepositoryAdapter {
     }
 
     #[tracing::instrument(level = "trace", skip(self, transaction))]
-    async fn get_ids(&self, transaction: &dyn Transaction) -> Result<Vec<String>, Error> {
+    async fn get_ids(&self, transaction: &dyn Transaction) -> Result<Vec<SessionId>, Error> {
+        let _timer = self.latency_budgets.start("session", "get_ids");
         let transaction = TransactionImpl::get_db_transaction(transaction)?;
 
         let ids: Vec<String> = prelude::Sessions::find()
@@ -148,7 +155,7 @@ impl SessionRepository for SessionRepositoryAdapter {
             .await
             .map_err(handle_dberr)?;
 
-        Ok(ids)
+        Ok(ids.into_iter().map(SessionId::new).collect())
     }
 }
 
@@ -157,16 +164,25 @@ mod tests {
     use std::sync::Arc;
 
     use chrono::{Duration, Utc};
-    use hex_play_core::{repository::RepositoryService, session::NewSession};
+    use hex_play_core::{
+        clock::FixedClock,
+        repository::RepositoryService,
+        session::{NewSession, SessionId},
+    };
     use sea_orm::Database;
 
-    use crate::create_repository_service;
+    use crate::{create_repository_service, create_repository_service_with_clock};
 
     async fn setup() -> Arc<RepositoryService> {
         let db = Database::connect("sqlite::memory:").await.unwrap();
         create_repository_service(db).await.unwrap()
     }
 
+    async fn setup_with_clock(clock: Arc<FixedClock>) -> Arc<RepositoryService> {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        create_repository_service_with_clock(db, clock).await.unwrap()
+    }
+
     // ===================
     // Tests: store
     // ===================
@@ -175,12 +191,12 @@ mod tests {
         let svc = setup().await;
         let tx = svc.repository().begin().await.unwrap();
 
-        let new_session = NewSession::new("sess-1", "session-data", Utc::now() + Duration::hours(1)).unwrap();
+        let new_session = NewSession::new(SessionId::new("sess-1"), "session-data", Utc::now() + Duration::hours(1)).unwrap();
         let result = svc.session_repository().store(&*tx, new_session).await;
 
         assert!(result.is_ok());
         let session = result.unwrap();
-        assert_eq!(session.id, "sess-1");
+        assert_eq!(session.id.as_str(), "sess-1");
         assert_eq!(session.session, "session-data");
     }
 
@@ -189,15 +205,15 @@ mod tests {
         let svc = setup().await;
         let tx = svc.repository().begin().await.unwrap();
 
-        let new_session = NewSession::new("sess-1", "original-data", Utc::now() + Duration::hours(1)).unwrap();
+        let new_session = NewSession::new(SessionId::new("sess-1"), "original-data", Utc::now() + Duration::hours(1)).unwrap();
         svc.session_repository().store(&*tx, new_session).await.unwrap();
 
-        let updated_session = NewSession::new("sess-1", "updated-data", Utc::now() + Duration::hours(2)).unwrap();
+        let updated_session = NewSession::new(SessionId::new("sess-1"), "updated-data", Utc::now() + Duration::hours(2)).unwrap();
         let result = svc.session_repository().store(&*tx, updated_session).await;
 
         assert!(result.is_ok());
         let session = result.unwrap();
-        assert_eq!(session.id, "sess-1");
+        assert_eq!(session.id.as_str(), "sess-1");
         assert_eq!(session.session, "updated-data");
 
         // Only one record exists
@@ -205,6 +221,17 @@ mod tests {
         assert_eq!(count, 1);
     }
 
+    #[tokio::test]
+    async fn test_store_uses_clock() {
+        let svc = setup_with_clock(Arc::new(FixedClock::default())).await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let new_session = NewSession::new(SessionId::new("sess-1"), "session-data", FixedClock::epoch() + Duration::hours(1)).unwrap();
+        let session = svc.session_repository().store(&*tx, new_session).await.unwrap();
+
+        assert_eq!(session.created_at, FixedClock::epoch());
+    }
+
     // ===================
     // Tests: count
     // ===================
@@ -225,11 +252,17 @@ mod tests {
         let tx = svc.repository().begin().await.unwrap();
 
         svc.session_repository()
-            .store(&*tx, NewSession::new("sess-1", "data-1", Utc::now() + Duration::hours(1)).unwrap())
+            .store(
+                &*tx,
+                NewSession::new(SessionId::new("sess-1"), "data-1", Utc::now() + Duration::hours(1)).unwrap(),
+            )
             .await
             .unwrap();
         svc.session_repository()
-            .store(&*tx, NewSession::new("sess-2", "data-2", Utc::now() + Duration::hours(1)).unwrap())
+            .store(
+                &*tx,
+                NewSession::new(SessionId::new("sess-2"), "data-2", Utc::now() + Duration::hours(1)).unwrap(),
+            )
             .await
             .unwrap();
 
@@ -248,17 +281,20 @@ mod tests {
         let tx = svc.repository().begin().await.unwrap();
 
         svc.session_repository()
-            .store(&*tx, NewSession::new("sess-1", "session-data", Utc::now() + Duration::hours(1)).unwrap())
+            .store(
+                &*tx,
+                NewSession::new(SessionId::new("sess-1"), "session-data", Utc::now() + Duration::hours(1)).unwrap(),
+            )
             .await
             .unwrap();
 
-        let result = svc.session_repository().load(&*tx, "sess-1").await;
+        let result = svc.session_repository().load(&*tx, &SessionId::new("sess-1")).await;
 
         assert!(result.is_ok());
         let session = result.unwrap();
         assert!(session.is_some());
         let session = session.unwrap();
-        assert_eq!(session.id, "sess-1");
+        assert_eq!(session.id.as_str(), "sess-1");
         assert_eq!(session.session, "session-data");
     }
 
@@ -267,7 +303,7 @@ mod tests {
         let svc = setup().await;
         let tx = svc.repository().begin().await.unwrap();
 
-        let result = svc.session_repository().load(&*tx, "nonexistent").await;
+        let result = svc.session_repository().load(&*tx, &SessionId::new("nonexistent")).await;
 
         assert!(result.is_ok());
         assert!(result.unwrap().is_none());
@@ -282,11 +318,14 @@ mod tests {
         let tx = svc.repository().begin().await.unwrap();
 
         svc.session_repository()
-            .store(&*tx, NewSession::new("sess-1", "data", Utc::now() + Duration::hours(1)).unwrap())
+            .store(
+                &*tx,
+                NewSession::new(SessionId::new("sess-1"), "data", Utc::now() + Duration::hours(1)).unwrap(),
+            )
             .await
             .unwrap();
 
-        let result = svc.session_repository().exists(&*tx, "sess-1").await;
+        let result = svc.session_repository().exists(&*tx, &SessionId::new("sess-1")).await;
 
         assert!(result.is_ok());
         assert!(result.unwrap());
@@ -297,7 +336,7 @@ mod tests {
         let svc = setup().await;
         let tx = svc.repository().begin().await.unwrap();
 
-        let result = svc.session_repository().exists(&*tx, "nonexistent").await;
+        let result = svc.session_repository().exists(&*tx, &SessionId::new("nonexistent")).await;
 
         assert!(result.is_ok());
         assert!(!result.unwrap());
@@ -312,14 +351,17 @@ mod tests {
         let tx = svc.repository().begin().await.unwrap();
 
         svc.session_repository()
-            .store(&*tx, NewSession::new("sess-1", "data", Utc::now() + Duration::hours(1)).unwrap())
+            .store(
+                &*tx,
+                NewSession::new(SessionId::new("sess-1"), "data", Utc::now() + Duration::hours(1)).unwrap(),
+            )
             .await
             .unwrap();
 
-        let result = svc.session_repository().delete_by_id(&*tx, "sess-1").await;
+        let result = svc.session_repository().delete_by_id(&*tx, &SessionId::new("sess-1")).await;
         assert!(result.is_ok());
 
-        let loaded = svc.session_repository().load(&*tx, "sess-1").await.unwrap();
+        let loaded = svc.session_repository().load(&*tx, &SessionId::new("sess-1")).await.unwrap();
         assert!(loaded.is_none());
     }
 
@@ -328,7 +370,7 @@ mod tests {
         let svc = setup().await;
         let tx = svc.repository().begin().await.unwrap();
 
-        let result = svc.session_repository().delete_by_id(&*tx, "nonexistent").await;
+        let result = svc.session_repository().delete_by_id(&*tx, &SessionId::new("nonexistent")).await;
 
         assert!(result.is_ok());
     }
@@ -342,11 +384,17 @@ mod tests {
         let tx = svc.repository().begin().await.unwrap();
 
         svc.session_repository()
-            .store(&*tx, NewSession::new("sess-1", "data-1", Utc::now() + Duration::hours(1)).unwrap())
+            .store(
+                &*tx,
+                NewSession::new(SessionId::new("sess-1"), "data-1", Utc::now() + Duration::hours(1)).unwrap(),
+            )
             .await
             .unwrap();
         svc.session_repository()
-            .store(&*tx, NewSession::new("sess-2", "data-2", Utc::now() + Duration::hours(1)).unwrap())
+            .store(
+                &*tx,
+                NewSession::new(SessionId::new("sess-2"), "data-2", Utc::now() + Duration::hours(1)).unwrap(),
+            )
             .await
             .unwrap();
 
@@ -377,11 +425,17 @@ mod tests {
         let tx = svc.repository().begin().await.unwrap();
 
         svc.session_repository()
-            .store(&*tx, NewSession::new("sess-1", "data-1", Utc::now() + Duration::hours(1)).unwrap())
+            .store(
+                &*tx,
+                NewSession::new(SessionId::new("sess-1"), "data-1", Utc::now() + Duration::hours(1)).unwrap(),
+            )
             .await
             .unwrap();
         svc.session_repository()
-            .store(&*tx, NewSession::new("sess-2", "data-2", Utc::now() + Duration::hours(1)).unwrap())
+            .store(
+                &*tx,
+                NewSession::new(SessionId::new("sess-2"), "data-2", Utc::now() + Duration::hours(1)).unwrap(),
+            )
             .await
             .unwrap();
 
@@ -390,7 +444,7 @@ mod tests {
         assert!(result.is_ok());
         let mut ids = result.unwrap();
         ids.sort();
-        assert_eq!(ids, vec!["sess-1", "sess-2"]);
+        assert_eq!(ids, [SessionId::new("sess-1"), SessionId::new("sess-2")]);
     }
 
     // ===================
@@ -403,12 +457,15 @@ mod tests {
 
         // Insert an expired session
         svc.session_repository()
-            .store(&*tx, NewSession::new("expired", "data", Utc::now() - Duration::hours(1)).unwrap())
+            .store(
+                &*tx,
+                NewSession::new(SessionId::new("expired"), "data", Utc::now() - Duration::hours(1)).unwrap(),
+            )
             .await
             .unwrap();
         // Insert a valid session
         svc.session_repository()
-            .store(&*tx, NewSession::new("valid", "data", Utc::now() + Duration::hours(1)).unwrap())
+            .store(&*tx, NewSession::new(SessionId::new("valid"), "data", Utc::now() + Duration::hours(1)).unwrap())
             .await
             .unwrap();
 
@@ -416,12 +473,33 @@ mod tests {
 
         assert!(result.is_ok());
         let deleted_ids = result.unwrap();
-        assert_eq!(deleted_ids, vec!["expired"]);
+        assert_eq!(deleted_ids, [SessionId::new("expired")]);
 
         // Valid session still exists
-        assert!(svc.session_repository().exists(&*tx, "valid").await.unwrap());
+        assert!(svc.session_repository().exists(&*tx, &SessionId::new("valid")).await.unwrap());
         // Expired session is gone
-        assert!(!svc.session_repository().exists(&*tx, "expired").await.unwrap());
+        assert!(!svc.session_repository().exists(&*tx, &SessionId::new("expired")).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_delete_by_expiry_uses_clock() {
+        let clock = Arc::new(FixedClock::default());
+        let svc = setup_with_clock(clock.clone()).await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        svc.session_repository()
+            .store(
+                &*tx,
+                NewSession::new(SessionId::new("sess-1"), "data", FixedClock::epoch() + Duration::hours(1)).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert!(svc.session_repository().delete_by_expiry(&*tx).await.unwrap().is_empty());
+
+        clock.advance(Duration::hours(2));
+
+        assert_eq!(svc.session_repository().delete_by_expiry(&*tx).await.unwrap(), [SessionId::new("sess-1")]);
     }
 
     #[tokio::test]
@@ -430,7 +508,7 @@ mod tests {
         let tx = svc.repository().begin().await.unwrap();
 
         svc.session_repository()
-            .store(&*tx, NewSession::new("valid", "data", Utc::now() + Duration::hours(1)).unwrap())
+            .store(&*tx, NewSession::new(SessionId::new("valid"), "data", Utc::now() + Duration::hours(1)).unwrap())
             .await
             .unwrap();
 
@@ -1,39 +1,38 @@
-use chrono::Utc;
+use std::sync::Arc;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use hex_play_core::{
     Error, RepositoryError,
+    avatar::StoredAvatar,
+    clock::Clock,
     repository::Transaction,
-    types::{Age, Email},
-    user::{NewUser, User, UserId, UserRepository, UserToken},
+    types::Email,
+    user::{AGE_BUCKETS, AgeBucketCount, DailySignups, NewUser, StatusCounts, User, UserId, UserRepository, UserStats, UserToken},
+};
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, Condition, ConnectionTrait, EntityTrait, FromQueryResult, ModelTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+    sea_query::{Expr, OnConflict},
 };
-use sea_orm::{ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, ModelTrait, QueryFilter, QueryOrder, QuerySelect};
 
 use crate::{
-    entities::{prelude, users},
+    entities::{prelude, user_info, user_list_view, user_signup_rollups, users},
     error::handle_dberr,
+    ids::user_id_column,
+    latency::LatencyBudgets,
+    raw::RawQuery as _,
     transaction::TransactionImpl,
 };
 
-impl From<users::Model> for User {
-    fn from(model: users::Model) -> Self {
-        let token = UserToken::parse(&model.token).unwrap();
-        Self {
-            id: model.id as u64,
-            version: model.version as u64,
-            token,
-            name: model.name,
-            email: Email::new(model.email).expect("database email should be valid"),
-            age: Age::new(model.age).expect("database age should be valid"),
-            created_at: model.created_at.with_timezone(&Utc),
-            updated_at: model.updated_at.with_timezone(&Utc),
-        }
-    }
+pub struct UserRepositoryAdapter {
+    clock: Arc<dyn Clock>,
+    latency_budgets: Arc<LatencyBudgets>,
 }
 
-pub struct UserRepositoryAdapter;
-
 impl UserRepositoryAdapter {
-    pub(crate) fn new() -> Self {
-        Self
+    pub(crate) fn new(clock: Arc<dyn Clock>, latency_budgets: Arc<LatencyBudgets>) -> Self {
+        Self { clock, latency_budgets }
     }
 }
 
@@ -41,34 +40,84 @@ impl UserRepositoryAdapter {
 impl UserRepository for UserRepositoryAdapter {
     #[tracing::instrument(level = "trace", skip(self, transaction))]
     async fn add_user(&self, transaction: &dyn Transaction, user: NewUser) -> Result<User, Error> {
+        let _timer = self.latency_budgets.start("user", "add_user");
         let transaction = TransactionImpl::get_db_transaction(transaction)?;
+        let now = self.clock.now();
 
         let model = users::ActiveModel {
             name: Set(user.name),
             email: Set(user.email.into_inner()),
             age: Set(user.age.value()),
-            version: Set(0i64),
+            version: Set(1i64),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
             ..Default::default()
         };
 
         let model = model.insert(transaction).await.map_err(handle_dberr)?;
+        sync_list_view(transaction, &model).await?;
 
         Ok(model.into())
     }
 
+    #[tracing::instrument(level = "trace", skip(self, transaction, user), fields(email = %user.email.redacted()))]
+    async fn upsert_by_email(&self, transaction: &dyn Transaction, user: NewUser) -> Result<User, Error> {
+        // Scoped here: `ExprTrait::min`/`max` would shadow `Ord` elsewhere.
+        use sea_orm::sea_query::ExprTrait;
+
+        let _timer = self.latency_budgets.start("user", "upsert_by_email");
+
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+        let now = self.clock.now();
+        let email = user.email.into_inner();
+
+        // The generated id/token only take effect when the row is inserted.
+        let model = users::ActiveModel {
+            name: Set(user.name),
+            email: Set(email.clone()),
+            age: Set(user.age.value()),
+            version: Set(1i64),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            ..ActiveModelBehavior::new()
+        };
+
+        let on_conflict = OnConflict::column(users::Column::Email)
+            .update_columns([users::Column::Name, users::Column::Age, users::Column::UpdatedAt])
+            .value(users::Column::Version, Expr::col(users::Column::Version).add(1))
+            .to_owned();
+
+        prelude::Users::insert(model)
+            .on_conflict(on_conflict)
+            .exec_without_returning(transaction)
+            .await
+            .map_err(handle_dberr)?;
+
+        let stored = prelude::Users::find_by_email(email)
+            .one(transaction)
+            .await
+            .map_err(handle_dberr)?
+            .ok_or(Error::RepositoryError(RepositoryError::NotFound))?;
+        sync_list_view(transaction, &stored).await?;
+
+        Ok(stored.into())
+    }
+
     #[tracing::instrument(level = "trace", skip(self, transaction))]
     async fn update_user(&self, transaction: &dyn Transaction, user: User) -> Result<User, Error> {
-        if user.id == 0 {
-            return Err(Error::InvalidId(user.id));
+        let _timer = self.latency_budgets.start("user", "update_user");
+        if user.id.value() == 0 {
+            return Err(Error::InvalidId(user.id.value()));
         }
         let transaction = TransactionImpl::get_db_transaction(transaction)?;
 
-        let existing = prelude::Users::find_by_id(user.id as i64)
+        let existing = prelude::Users::find_by_id(user_id_column(user.id))
             .one(transaction)
             .await
             .map_err(handle_dberr)?
             .ok_or(Error::RepositoryError(RepositoryError::NotFound))?;
-        if existing.version != user.version as i64 {
+        let expected_version = user.version as i64;
+        if existing.version != expected_version {
             return Err(Error::RepositoryError(RepositoryError::Conflict));
         }
 
@@ -82,20 +131,46 @@ impl UserRepository for UserRepositoryAdapter {
         if existing.age != user.age.value() {
             updater.age = Set(user.age.value());
         }
+        updater.touch(self.clock.now());
+        // Every successful update bumps the version, even when nothing else
+        // changed, so a stale version can never be used twice.
+        updater.version = Set(expected_version + 1);
+
+        // The version filter makes the check-and-write atomic: a concurrent
+        // writer that got in first leaves no row to update.
+        let result = prelude::Users::update_many()
+            .set(updater)
+            .filter(users::Column::Id.eq(user_id_column(user.id)))
+            .filter(users::Column::Version.eq(expected_version))
+            .exec(transaction)
+            .await
+            .map_err(handle_dberr)?;
+        if result.rows_affected == 0 {
+            return Err(Error::RepositoryError(RepositoryError::Conflict));
+        }
 
-        let updated = updater.update(transaction).await.map_err(handle_dberr)?;
+        let updated = prelude::Users::find_by_id(user_id_column(user.id))
+            .one(transaction)
+            .await
+            .map_err(handle_dberr)?
+            .ok_or(Error::RepositoryError(RepositoryError::NotFound))?;
+        sync_list_view(transaction, &updated).await?;
 
         Ok(updated.into())
     }
 
     #[tracing::instrument(level = "trace", skip(self, transaction))]
     async fn delete_user(&self, transaction: &dyn Transaction, user: User) -> Result<User, Error> {
-        if user.id == 0 {
-            return Err(Error::InvalidId(user.id));
+        let _timer = self.latency_budgets.start("user", "delete_user");
+        if user.id.value() == 0 {
+            return Err(Error::InvalidId(user.id.value()));
         }
         let transaction = TransactionImpl::get_db_transaction(transaction)?;
 
-        let existing = prelude::Users::find_by_id(user.id as i64).one(transaction).await.map_err(handle_dberr)?;
+        let existing = prelude::Users::find_by_id(user_id_column(user.id))
+            .one(transaction)
+            .await
+            .map_err(handle_dberr)?;
         let Some(existing) = existing else {
             return Err(Error::RepositoryError(RepositoryError::NotFound));
         };
@@ -104,55 +179,39 @@ impl UserRepository for UserRepositoryAdapter {
         }
 
         let user: User = existing.clone().into();
+        prelude::UserInfo::delete_by_id(existing.id).exec(transaction).await.map_err(handle_dberr)?;
+        prelude::UserListView::delete_by_id(existing.id).exec(transaction).await.map_err(handle_dberr)?;
         existing.delete(transaction).await.map_err(handle_dberr)?;
 
         Ok(user)
     }
 
     #[tracing::instrument(level = "trace", skip(self, transaction))]
-    async fn list_users(&self, transaction: &dyn Transaction, start_id: Option<UserId>, page_size: Option<u64>) -> Result<Vec<User>, Error> {
-        const DEFAULT_PAGE_SIZE: u64 = 50;
-        /// Limit maximum page size to prevent excessively large responses.
-        const MAX_PAGE_SIZE: u64 = 50;
-
-        if let Some(page_size) = page_size {
-            if page_size < 1 {
-                return Err(Error::InvalidPageSize(page_size));
-            }
-        }
-
+    async fn count_users(&self, transaction: &dyn Transaction) -> Result<u64, Error> {
+        let _timer = self.latency_budgets.start("user", "count_users");
         let transaction = TransactionImpl::get_db_transaction(transaction)?;
 
-        let mut query = prelude::Users::find().order_by_asc(users::Column::Id);
-
-        if let Some(start_id) = start_id {
-            query = query.filter(users::Column::Id.gte(start_id as i64));
-        }
-
-        let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
-        query = query.limit(page_size);
-
-        let users = query.all(transaction).await.map_err(handle_dberr)?;
-
-        Ok(users.into_iter().map(Into::into).collect())
+        Ok(prelude::Users::find().count(transaction).await.map_err(handle_dberr)?)
     }
 
     #[tracing::instrument(level = "trace", skip(self, transaction))]
     async fn find_by_id(&self, transaction: &dyn Transaction, id: UserId) -> Result<Option<User>, Error> {
-        if id == 0 {
-            return Err(Error::InvalidId(id));
+        let _timer = self.latency_budgets.start("user", "find_by_id");
+        if id.value() == 0 {
+            return Err(Error::InvalidId(id.value()));
         }
         let transaction = TransactionImpl::get_db_transaction(transaction)?;
 
-        Ok(prelude::Users::find_by_id(id as i64)
+        Ok(prelude::Users::find_by_id(user_id_column(id))
             .one(transaction)
             .await
             .map_err(handle_dberr)?
             .map(Into::into))
     }
 
-    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    #[tracing::instrument(level = "trace", skip(self, transaction, email), fields(email = %email.redacted()))]
     async fn find_by_email(&self, transaction: &dyn Transaction, email: &Email) -> Result<Option<User>, Error> {
+        let _timer = self.latency_budgets.start("user", "find_by_email");
         let transaction = TransactionImpl::get_db_transaction(transaction)?;
 
         Ok(prelude::Users::find_by_email(email.as_str())
@@ -164,6 +223,7 @@ impl UserRepository for UserRepositoryAdapter {
 
     #[tracing::instrument(level = "trace", skip(self, transaction))]
     async fn find_by_token(&self, transaction: &dyn Transaction, token: UserToken) -> Result<Option<User>, Error> {
+        let _timer = self.latency_budgets.start("user", "find_by_token");
         let transaction = TransactionImpl::get_db_transaction(transaction)?;
 
         Ok(prelude::Users::find()
@@ -173,27 +233,297 @@ impl UserRepository for UserRepositoryAdapter {
             .map_err(handle_dberr)?
             .map(Into::into))
     }
+
+    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    async fn find_by_ids(&self, transaction: &dyn Transaction, ids: &[UserId]) -> Result<Vec<User>, Error> {
+        let _timer = self.latency_budgets.start("user", "find_by_ids");
+        if let Some(&id) = ids.iter().find(|&&id| id.value() == 0) {
+            return Err(Error::InvalidId(id.value()));
+        }
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+
+        let users = prelude::Users::find()
+            .filter(users::Column::Id.is_in(ids.iter().map(|&id| user_id_column(id))))
+            .order_by_asc(users::Column::Id)
+            .all(transaction)
+            .await
+            .map_err(handle_dberr)?;
+
+        Ok(users.into_iter().map(Into::into).collect())
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    async fn find_by_tokens(&self, transaction: &dyn Transaction, tokens: &[UserToken]) -> Result<Vec<User>, Error> {
+        let _timer = self.latency_budgets.start("user", "find_by_tokens");
+        if tokens.is_empty() {
+            return Ok(Vec::new());
+        }
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+
+        let users = prelude::Users::find()
+            .filter(users::Column::Token.is_in(tokens.iter().map(ToString::to_string)))
+            .order_by_asc(users::Column::Id)
+            .all(transaction)
+            .await
+            .map_err(handle_dberr)?;
+
+        Ok(users.into_iter().map(Into::into).collect())
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    async fn find_avatar(&self, transaction: &dyn Transaction, id: UserId) -> Result<Option<StoredAvatar>, Error> {
+        let _timer = self.latency_budgets.start("user", "find_avatar");
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+
+        let info = prelude::UserInfo::find_by_id(user_id_column(id)).one(transaction).await.map_err(handle_dberr)?;
+
+        Ok(info.and_then(|info| {
+            info.avatar_key.map(|key| StoredAvatar {
+                key,
+                variants_ready: info.avatar_variants_ready,
+            })
+        }))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    async fn set_avatar_key(&self, transaction: &dyn Transaction, id: UserId, key: Option<String>) -> Result<Option<String>, Error> {
+        let _timer = self.latency_budgets.start("user", "set_avatar_key");
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+
+        let previous = prelude::UserInfo::find_by_id(user_id_column(id)).one(transaction).await.map_err(handle_dberr)?;
+
+        let model = user_info::ActiveModel {
+            user_id: Set(user_id_column(id)),
+            avatar_key: Set(key),
+            avatar_variants_ready: Set(false),
+            updated_at: Set(self.clock.now().into()),
+        };
+        let on_conflict = OnConflict::column(user_info::Column::UserId)
+            .update_columns([
+                user_info::Column::AvatarKey,
+                user_info::Column::AvatarVariantsReady,
+                user_info::Column::UpdatedAt,
+            ])
+            .to_owned();
+        prelude::UserInfo::insert(model)
+            .on_conflict(on_conflict)
+            .exec(transaction)
+            .await
+            .map_err(handle_dberr)?;
+
+        Ok(previous.and_then(|info| info.avatar_key))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    async fn mark_avatar_variants_ready(&self, transaction: &dyn Transaction, id: UserId, key: &str) -> Result<bool, Error> {
+        let _timer = self.latency_budgets.start("user", "mark_avatar_variants_ready");
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+
+        // Matching on the key as well keeps a job for a replaced avatar from
+        // marking the new one.
+        let result = prelude::UserInfo::update_many()
+            .col_expr(user_info::Column::AvatarVariantsReady, Expr::value(true))
+            .col_expr(user_info::Column::UpdatedAt, Expr::value(self.clock.now().fixed_offset()))
+            .filter(user_info::Column::UserId.eq(user_id_column(id)))
+            .filter(user_info::Column::AvatarKey.eq(key))
+            .exec(transaction)
+            .await
+            .map_err(handle_dberr)?;
+
+        Ok(result.rows_affected > 0)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, transaction, seen), fields(users = seen.len()))]
+    async fn record_last_seen(&self, transaction: &dyn Transaction, seen: &[(UserId, DateTime<Utc>)]) -> Result<(), Error> {
+        let _timer = self.latency_budgets.start("user", "record_last_seen");
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+
+        for (id, at) in seen {
+            let at = at.fixed_offset();
+            prelude::Users::update_many()
+                .col_expr(users::Column::LastSeenAt, Expr::value(at))
+                .filter(users::Column::Id.eq(user_id_column(*id)))
+                .filter(Condition::any().add(users::Column::LastSeenAt.is_null()).add(users::Column::LastSeenAt.lt(at)))
+                .exec(transaction)
+                .await
+                .map_err(handle_dberr)?;
+            prelude::UserListView::update_many()
+                .col_expr(user_list_view::Column::LastSeenAt, Expr::value(at))
+                .filter(user_list_view::Column::Id.eq(user_id_column(*id)))
+                .filter(
+                    Condition::any()
+                        .add(user_list_view::Column::LastSeenAt.is_null())
+                        .add(user_list_view::Column::LastSeenAt.lt(at)),
+                )
+                .exec(transaction)
+                .await
+                .map_err(handle_dberr)?;
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    async fn user_stats(&self, transaction: &dyn Transaction, active_since: DateTime<Utc>, signups_since: NaiveDate) -> Result<UserStats, Error> {
+        let _timer = self.latency_budgets.start("user", "user_stats");
+        let by_status = transaction
+            .query_as::<StatusCountsRow>(
+                "SELECT COUNT(*) - COUNT(last_seen_at) AS never_seen, \
+                 COUNT(CASE WHEN last_seen_at >= $1 THEN 1 END) AS active, \
+                 COUNT(CASE WHEN last_seen_at < $1 THEN 1 END) AS inactive \
+                 FROM users",
+                vec![active_since.fixed_offset().into()],
+            )
+            .await?
+            .pop()
+            .map(StatusCounts::from)
+            .unwrap_or_default();
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+
+        let signups = prelude::UserSignupRollups::find()
+            .filter(user_signup_rollups::Column::Day.gte(signups_since))
+            .order_by_asc(user_signup_rollups::Column::Day)
+            .all(transaction)
+            .await
+            .map_err(handle_dberr)?;
+
+        let (first, rest) = AGE_BUCKETS.split_first().expect("there are age buckets");
+        let bucket: Expr = rest
+            .iter()
+            .enumerate()
+            .fold(Expr::case(users::Column::Age.lte(*first.end()), 0_i64), |case, (index, ages)| {
+                case.case(users::Column::Age.lte(*ages.end()), index as i64 + 1)
+            })
+            .into();
+        let ages: Vec<(i64, i64)> = prelude::Users::find()
+            .select_only()
+            .column_as(bucket, "bucket")
+            .column_as(users::Column::Id.count(), "count")
+            .group_by(Expr::col("bucket"))
+            .into_tuple()
+            .all(transaction)
+            .await
+            .map_err(handle_dberr)?;
+
+        Ok(UserStats {
+            by_status,
+            signups_per_day: signups
+                .into_iter()
+                .map(|rollup| DailySignups {
+                    date: rollup.day,
+                    count: rollup.signups as u64,
+                })
+                .collect(),
+            by_age: AGE_BUCKETS
+                .iter()
+                .enumerate()
+                .map(|(index, ages_in_bucket)| AgeBucketCount {
+                    ages: ages_in_bucket.clone(),
+                    count: ages.iter().find(|(bucket, _)| *bucket == index as i64).map_or(0, |(_, count)| *count as u64),
+                })
+                .collect(),
+        })
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    async fn refresh_signup_rollups(&self, transaction: &dyn Transaction, since: Option<NaiveDate>) -> Result<u64, Error> {
+        let _timer = self.latency_budgets.start("user", "refresh_signup_rollups");
+
+        // Days whose users have all been deleted since must not keep their
+        // old count, so every day in range is rewritten.
+        let mut stale = prelude::UserSignupRollups::delete_many();
+        let mut values = vec![self.clock.now().fixed_offset().into()];
+        let mut signups_since = "";
+        if let Some(since) = since {
+            stale = stale.filter(user_signup_rollups::Column::Day.gte(since));
+            values.push(since.and_time(NaiveTime::MIN).and_utc().fixed_offset().into());
+            signups_since = " WHERE created_at >= $2";
+        }
+        stale.exec(TransactionImpl::get_db_transaction(transaction)?).await.map_err(handle_dberr)?;
+
+        transaction
+            .execute_sql(
+                &format!(
+                    "INSERT INTO user_signup_rollups (day, signups, refreshed_at) \
+                     SELECT DATE(created_at), COUNT(id), $1 FROM users{signups_since} GROUP BY DATE(created_at)"
+                ),
+                values,
+            )
+            .await
+    }
+}
+
+/// How many users there are of each status, as counted by `user_stats`.
+#[derive(FromQueryResult)]
+struct StatusCountsRow {
+    never_seen: i64,
+    active: i64,
+    inactive: i64,
+}
+
+impl From<StatusCountsRow> for StatusCounts {
+    fn from(row: StatusCountsRow) -> Self {
+        Self {
+            never_seen: row.never_seen as u64,
+            active: row.active as u64,
+            inactive: row.inactive as u64,
+        }
+    }
+}
+
+/// Writes `user`, as it now is in `users`, to the listings.
+async fn sync_list_view(transaction: &impl ConnectionTrait, user: &users::Model) -> Result<(), Error> {
+    let on_conflict = OnConflict::column(user_list_view::Column::Id)
+        .update_columns([
+            user_list_view::Column::Token,
+            user_list_view::Column::Name,
+            user_list_view::Column::Email,
+            user_list_view::Column::Age,
+            user_list_view::Column::Version,
+            user_list_view::Column::CreatedAt,
+            user_list_view::Column::UpdatedAt,
+            user_list_view::Column::LastSeenAt,
+        ])
+        .to_owned();
+    prelude::UserListView::insert(user_list_view::ActiveModel::from(user.clone()))
+        .on_conflict(on_conflict)
+        .exec_without_returning(transaction)
+        .await
+        .map_err(handle_dberr)?;
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
 
+    use chrono::Duration;
     use hex_play_core::{
         Error, RepositoryError,
+        avatar::StoredAvatar,
+        clock::FixedClock,
         repository::RepositoryService,
         types::Email,
-        user::{NewUser, User, UserToken},
+        user::{DailySignups, NewUser, StatusCounts, User, UserId, UserToken},
     };
     use sea_orm::Database;
 
-    use crate::create_repository_service;
+    use crate::{create_repository_service, create_repository_service_with_clock};
 
     async fn setup() -> Arc<RepositoryService> {
         let db = Database::connect("sqlite::memory:").await.unwrap();
         create_repository_service(db).await.unwrap()
     }
 
+    async fn setup_with_clock(clock: Arc<FixedClock>) -> Arc<RepositoryService> {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        create_repository_service_with_clock(db, clock).await.unwrap()
+    }
+
     // ===================
     // Tests: add_user
     // ===================
@@ -207,9 +537,73 @@ mod tests {
 
         assert!(result.is_ok());
         let user = result.unwrap();
-        assert_ne!(user.id, 0);
+        assert_ne!(user.id.value(), 0);
         assert_eq!(user.name, "John Doe");
         assert_eq!(user.email.as_str(), "john@example.com");
+        assert_eq!(user.version, 1);
+    }
+
+    #[tokio::test]
+    async fn test_add_user_uses_clock() {
+        let clock = Arc::new(FixedClock::default());
+        let svc = setup_with_clock(clock.clone()).await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let user = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(user.created_at, FixedClock::epoch());
+        assert_eq!(user.updated_at, FixedClock::epoch());
+    }
+
+    // ===================
+    // Tests: upsert_by_email
+    // ===================
+    #[tokio::test]
+    async fn test_upsert_by_email_inserts_new_user() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let user = svc
+            .user_repository()
+            .upsert_by_email(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+
+        assert_ne!(user.id.value(), 0);
+        assert_eq!(user.name, "John Doe");
+        assert_eq!(user.version, 1);
+    }
+
+    #[tokio::test]
+    async fn test_upsert_by_email_updates_existing_user() {
+        let clock = Arc::new(FixedClock::default());
+        let svc = setup_with_clock(clock.clone()).await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let inserted = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+        clock.advance(Duration::minutes(1));
+
+        let upserted = svc
+            .user_repository()
+            .upsert_by_email(&*tx, NewUser::new("John Updated", "john@example.com", 31).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(upserted.id, inserted.id);
+        assert_eq!(upserted.token, inserted.token);
+        assert_eq!(upserted.name, "John Updated");
+        assert_eq!(upserted.age.value(), 31);
+        assert_eq!(upserted.version, inserted.version + 1);
+        assert_eq!(upserted.created_at, inserted.created_at);
+        assert_eq!(upserted.updated_at, FixedClock::epoch() + Duration::minutes(1));
     }
 
     // ===================
@@ -241,7 +635,7 @@ mod tests {
         let svc = setup().await;
         let tx = svc.repository().begin().await.unwrap();
 
-        let result = svc.user_repository().find_by_id(&*tx, 999).await;
+        let result = svc.user_repository().find_by_id(&*tx, UserId::new(999)).await;
 
         assert!(result.is_ok());
         assert!(result.unwrap().is_none());
@@ -252,7 +646,7 @@ mod tests {
         let svc = setup().await;
         let tx = svc.repository().begin().await.unwrap();
 
-        let result = svc.user_repository().find_by_id(&*tx, 0).await;
+        let result = svc.user_repository().find_by_id(&*tx, UserId::new(0)).await;
 
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), Error::InvalidId(0)));
@@ -293,65 +687,136 @@ mod tests {
     }
 
     // ===================
-    // Tests: list_users
+    // Tests: user_stats
     // ===================
     #[tokio::test]
-    async fn test_list_users_success() {
-        let svc = setup().await;
+    async fn test_user_stats_groups_users() {
+        let clock = Arc::new(FixedClock::default());
+        let svc = setup_with_clock(clock.clone()).await;
         let tx = svc.repository().begin().await.unwrap();
-
-        svc.user_repository()
-            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+        let now = FixedClock::epoch();
+        let repository = svc.user_repository();
+        let john = repository
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 17).unwrap())
             .await
             .unwrap();
-        svc.user_repository()
-            .add_user(&*tx, NewUser::new("Jane Doe", "jane@example.com", 25).unwrap())
+        clock.advance(Duration::days(1));
+        let jane = repository
+            .add_user(&*tx, NewUser::new("Jane Doe", "jane@example.com", 30).unwrap())
+            .await
+            .unwrap();
+        repository
+            .add_user(&*tx, NewUser::new("Jim Doe", "jim@example.com", 33).unwrap())
+            .await
+            .unwrap();
+        repository
+            .record_last_seen(&*tx, &[(john.id, now - Duration::days(40)), (jane.id, now)])
             .await
             .unwrap();
 
-        let result = svc.user_repository().list_users(&*tx, None, None).await;
+        assert_eq!(repository.refresh_signup_rollups(&*tx, None).await.unwrap(), 2);
 
-        assert!(result.is_ok());
-        let mut users = result.unwrap();
-        assert_eq!(users.len(), 2);
-        users.sort_by(|a, b| a.name.cmp(&b.name));
-        assert_eq!(users[0].name, "Jane Doe");
-        assert_eq!(users[0].age.value(), 25);
-        assert_eq!(users[1].name, "John Doe");
-        assert_eq!(users[1].age.value(), 30);
+        let stats = repository.user_stats(&*tx, now - Duration::days(30), now.date_naive()).await.unwrap();
+
+        assert_eq!(
+            stats.by_status,
+            StatusCounts {
+                active: 1,
+                inactive: 1,
+                never_seen: 1,
+            }
+        );
+        assert_eq!(
+            stats.signups_per_day,
+            [
+                DailySignups {
+                    date: now.date_naive(),
+                    count: 1,
+                },
+                DailySignups {
+                    date: (now + Duration::days(1)).date_naive(),
+                    count: 2,
+                },
+            ]
+        );
+        let by_age: Vec<_> = stats.by_age.iter().map(|bucket| bucket.count).collect();
+        assert_eq!(by_age, [1, 0, 2, 0, 0, 0, 0]);
     }
 
+    // ===================
+    // Tests: refresh_signup_rollups
+    // ===================
     #[tokio::test]
-    async fn test_list_users_empty() {
-        let svc = setup().await;
+    async fn test_refresh_signup_rollups_rewrites_days_from_since() {
+        let clock = Arc::new(FixedClock::default());
+        let svc = setup_with_clock(clock.clone()).await;
         let tx = svc.repository().begin().await.unwrap();
+        let repository = svc.user_repository();
+        let today = FixedClock::epoch().date_naive();
+        repository
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+        clock.advance(Duration::days(1));
+        let jane = repository
+            .add_user(&*tx, NewUser::new("Jane Doe", "jane@example.com", 25).unwrap())
+            .await
+            .unwrap();
+        repository.refresh_signup_rollups(&*tx, None).await.unwrap();
+        repository.delete_user(&*tx, jane).await.unwrap();
 
-        let result = svc.user_repository().list_users(&*tx, None, None).await;
+        let days = repository.refresh_signup_rollups(&*tx, Some(today + Duration::days(1))).await.unwrap();
 
-        assert!(result.is_ok());
-        assert!(result.unwrap().is_empty());
+        assert_eq!(days, 0);
+        let stats = repository.user_stats(&*tx, FixedClock::epoch(), today).await.unwrap();
+        assert_eq!(stats.signups_per_day, [DailySignups { date: today, count: 1 }]);
     }
 
+    // ===================
+    // Tests: record_last_seen
+    // ===================
     #[tokio::test]
-    async fn test_list_users_start_id_zero() {
+    async fn test_record_last_seen_keeps_version_and_never_moves_back() {
         let svc = setup().await;
         let tx = svc.repository().begin().await.unwrap();
+        let user = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+        let now = FixedClock::epoch();
 
-        let result = svc.user_repository().list_users(&*tx, Some(0), None).await;
+        svc.user_repository().record_last_seen(&*tx, &[(user.id, now)]).await.unwrap();
+        svc.user_repository()
+            .record_last_seen(&*tx, &[(user.id, now - Duration::minutes(1)), (UserId::new(user.id.value() + 1), now)])
+            .await
+            .unwrap();
 
-        assert!(result.is_ok());
-        assert!(result.unwrap().is_empty());
+        let reloaded = svc.user_repository().find_by_id(&*tx, user.id).await.unwrap().unwrap();
+        assert_eq!(reloaded.last_seen_at, Some(now));
+        assert_eq!(reloaded.version, user.version);
+        assert_eq!(reloaded.updated_at, user.updated_at);
     }
 
+    // ===================
+    // Tests: count_users
+    // ===================
     #[tokio::test]
-    async fn test_list_users_invalid_page_size() {
+    async fn test_count_users() {
         let svc = setup().await;
         let tx = svc.repository().begin().await.unwrap();
+        assert_eq!(svc.user_repository().count_users(&*tx).await.unwrap(), 0);
 
-        let result = svc.user_repository().list_users(&*tx, None, Some(0)).await;
+        svc.user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+        svc.user_repository()
+            .add_user(&*tx, NewUser::new("Jane Doe", "jane@example.com", 25).unwrap())
+            .await
+            .unwrap();
 
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), Error::InvalidPageSize(0)));
+        assert_eq!(svc.user_repository().count_users(&*tx).await.unwrap(), 2);
     }
 
     // ===================
@@ -379,6 +844,119 @@ mod tests {
         assert_eq!(user.name, "John Updated");
     }
 
+    #[tokio::test]
+    async fn test_update_user_stamps_updated_at_from_clock() {
+        let clock = Arc::new(FixedClock::default());
+        let svc = setup_with_clock(clock.clone()).await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let mut user = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+        clock.advance(Duration::minutes(5));
+        user.name = "John Updated".to_string();
+
+        let updated = svc.user_repository().update_user(&*tx, user).await.unwrap();
+
+        assert_eq!(updated.created_at, FixedClock::epoch());
+        assert_eq!(updated.updated_at, FixedClock::epoch() + Duration::minutes(5));
+    }
+
+    #[tokio::test]
+    async fn test_update_user_unchanged_keeps_updated_at() {
+        let clock = Arc::new(FixedClock::default());
+        let svc = setup_with_clock(clock.clone()).await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let user = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+        clock.advance(Duration::minutes(5));
+
+        let updated = svc.user_repository().update_user(&*tx, user).await.unwrap();
+
+        assert_eq!(updated.updated_at, FixedClock::epoch());
+    }
+
+    #[tokio::test]
+    async fn test_update_user_increments_version() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let mut user = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+        user.name = "John Updated".to_string();
+
+        let updated = svc.user_repository().update_user(&*tx, user).await.unwrap();
+
+        assert_eq!(updated.version, 2);
+    }
+
+    #[tokio::test]
+    async fn test_update_user_repeated_updates_increment_version() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let mut user = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+
+        for expected in 2..=4 {
+            user.name = format!("John {expected}");
+            user = svc.user_repository().update_user(&*tx, user).await.unwrap();
+            assert_eq!(user.version, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_user_stale_version_rejected() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let inserted = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+
+        let mut first = inserted.clone();
+        first.name = "First".to_string();
+        svc.user_repository().update_user(&*tx, first).await.unwrap();
+
+        let mut second = inserted;
+        second.name = "Second".to_string();
+        let result = svc.user_repository().update_user(&*tx, second).await;
+
+        assert!(matches!(result.unwrap_err(), Error::RepositoryError(RepositoryError::Conflict)));
+    }
+
+    #[tokio::test]
+    async fn test_update_user_unchanged_still_increments_version() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let inserted = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+
+        let updated = svc.user_repository().update_user(&*tx, inserted.clone()).await.unwrap();
+        assert_eq!(updated.version, 2);
+
+        let result = svc.user_repository().update_user(&*tx, inserted).await;
+        assert!(matches!(result.unwrap_err(), Error::RepositoryError(RepositoryError::Conflict)));
+    }
+
     #[tokio::test]
     async fn test_update_user_not_found() {
         let svc = setup().await;
@@ -517,4 +1095,148 @@ mod tests {
         assert!(result.is_ok());
         assert!(result.unwrap().is_none());
     }
+
+    // ===================
+    // Tests: find_by_ids
+    // ===================
+    #[tokio::test]
+    async fn test_find_by_ids_skips_unknown_and_orders_by_id() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let john = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+        let jane = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("Jane Doe", "jane@example.com", 25).unwrap())
+            .await
+            .unwrap();
+
+        let users = svc.user_repository().find_by_ids(&*tx, &[jane.id, UserId::new(999), john.id]).await.unwrap();
+
+        let mut expected = vec![john.id, jane.id];
+        expected.sort();
+        assert_eq!(users.iter().map(|user| user.id).collect::<Vec<_>>(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_find_by_ids_invalid_id() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let result = svc.user_repository().find_by_ids(&*tx, &[UserId::new(1), UserId::new(0)]).await;
+
+        assert!(matches!(result.unwrap_err(), Error::InvalidId(0)));
+    }
+
+    // ===================
+    // Tests: find_by_tokens
+    // ===================
+    #[tokio::test]
+    async fn test_find_by_tokens_skips_unknown() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let john = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+        svc.user_repository()
+            .add_user(&*tx, NewUser::new("Jane Doe", "jane@example.com", 25).unwrap())
+            .await
+            .unwrap();
+
+        let users = svc.user_repository().find_by_tokens(&*tx, &[john.token, UserToken::generate()]).await.unwrap();
+
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].token, john.token);
+    }
+
+    // ===================
+    // Tests: avatar keys
+    // ===================
+    #[tokio::test]
+    async fn test_set_avatar_key_returns_previous() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let user = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(svc.user_repository().find_avatar(&*tx, user.id).await.unwrap(), None);
+        let first = svc.user_repository().set_avatar_key(&*tx, user.id, Some("avatars/a.png".into())).await.unwrap();
+        let second = svc.user_repository().set_avatar_key(&*tx, user.id, Some("avatars/b.png".into())).await.unwrap();
+
+        assert_eq!(first, None);
+        assert_eq!(second.as_deref(), Some("avatars/a.png"));
+        assert_eq!(
+            svc.user_repository().find_avatar(&*tx, user.id).await.unwrap(),
+            Some(StoredAvatar {
+                key: "avatars/b.png".into(),
+                variants_ready: false
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mark_avatar_variants_ready_only_for_current_key() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let user = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+        svc.user_repository().set_avatar_key(&*tx, user.id, Some("avatars/b.png".into())).await.unwrap();
+
+        let stale = svc.user_repository().mark_avatar_variants_ready(&*tx, user.id, "avatars/a.png").await.unwrap();
+        assert!(!stale);
+        let current = svc.user_repository().mark_avatar_variants_ready(&*tx, user.id, "avatars/b.png").await.unwrap();
+        assert!(current);
+        let avatar = svc.user_repository().find_avatar(&*tx, user.id).await.unwrap().unwrap();
+        assert!(avatar.variants_ready);
+
+        svc.user_repository().set_avatar_key(&*tx, user.id, Some("avatars/c.png".into())).await.unwrap();
+        let avatar = svc.user_repository().find_avatar(&*tx, user.id).await.unwrap().unwrap();
+        assert!(!avatar.variants_ready);
+    }
+
+    #[tokio::test]
+    async fn test_set_avatar_key_keeps_user_version() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let user = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+
+        svc.user_repository().set_avatar_key(&*tx, user.id, Some("avatars/a.png".into())).await.unwrap();
+
+        let reloaded = svc.user_repository().find_by_id(&*tx, user.id).await.unwrap().unwrap();
+        assert_eq!(reloaded.version, user.version);
+    }
+
+    #[tokio::test]
+    async fn test_delete_user_removes_avatar_key() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let user = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+        svc.user_repository().set_avatar_key(&*tx, user.id, Some("avatars/a.png".into())).await.unwrap();
+
+        let id = user.id;
+        svc.user_repository().delete_user(&*tx, user).await.unwrap();
+
+        assert_eq!(svc.user_repository().find_avatar(&*tx, id).await.unwrap(), None);
+    }
 }
@@ -0,0 +1,552 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use hex_play_core::{
+    Error,
+    repository::Transaction,
+    specification::Specification,
+    user::{User, UserId, UserListStamp, UserPredicate, UserQueryRepository, UserSpecification, effective_page_size},
+};
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+    prelude::DateTimeWithTimeZone,
+    sea_query::{Expr, Func, LikeExpr, Query},
+};
+
+use crate::{
+    entities::{prelude, user_list_view, users},
+    error::handle_dberr,
+    ids::user_id_column,
+    latency::LatencyBudgets,
+    transaction::TransactionImpl,
+};
+
+/// Lists and searches users in `user_list_view`, which
+/// [`UserRepositoryAdapter`](crate::adapters::user::UserRepositoryAdapter)
+/// keeps in step with `users`.
+pub struct UserQueryRepositoryAdapter {
+    latency_budgets: Arc<LatencyBudgets>,
+}
+
+impl UserQueryRepositoryAdapter {
+    pub(crate) fn new(latency_budgets: Arc<LatencyBudgets>) -> Self {
+        Self { latency_budgets }
+    }
+}
+
+#[async_trait::async_trait]
+impl UserQueryRepository for UserQueryRepositoryAdapter {
+    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    async fn list_users(
+        &self,
+        transaction: &dyn Transaction,
+        after_id: Option<UserId>,
+        page_size: Option<u64>,
+        active_since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<User>, Error> {
+        let _timer = self.latency_budgets.start("user", "list_users");
+        if let Some(page_size) = page_size {
+            if page_size < 1 {
+                return Err(Error::InvalidPageSize(page_size));
+            }
+        }
+
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+
+        let mut query = prelude::UserListView::find().order_by_asc(user_list_view::Column::Id);
+
+        if let Some(after_id) = after_id {
+            query = query.filter(user_list_view::Column::Id.gt(user_id_column(after_id)));
+        }
+        if let Some(active_since) = active_since {
+            query = query.filter(user_list_view::Column::LastSeenAt.gte(active_since.fixed_offset()));
+        }
+
+        query = query.limit(effective_page_size(page_size));
+
+        let users = query.all(transaction).await.map_err(handle_dberr)?;
+
+        Ok(users.into_iter().map(Into::into).collect())
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    async fn find_matching(
+        &self,
+        transaction: &dyn Transaction,
+        specification: &UserSpecification,
+        after_id: Option<UserId>,
+        page_size: Option<u64>,
+    ) -> Result<Vec<User>, Error> {
+        let _timer = self.latency_budgets.start("user", "find_matching");
+        if let Some(page_size) = page_size {
+            if page_size < 1 {
+                return Err(Error::InvalidPageSize(page_size));
+            }
+        }
+
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+
+        let mut query = prelude::UserListView::find()
+            .filter(user_condition(specification))
+            .order_by_asc(user_list_view::Column::Id);
+        if let Some(after_id) = after_id {
+            query = query.filter(user_list_view::Column::Id.gt(user_id_column(after_id)));
+        }
+
+        let users = query.limit(effective_page_size(page_size)).all(transaction).await.map_err(handle_dberr)?;
+
+        Ok(users.into_iter().map(Into::into).collect())
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    async fn list_stamp(&self, transaction: &dyn Transaction) -> Result<UserListStamp, Error> {
+        let _timer = self.latency_budgets.start("user", "list_stamp");
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+
+        let (count, last_updated_at, last_seen_at): (i64, Option<DateTimeWithTimeZone>, Option<DateTimeWithTimeZone>) = prelude::UserListView::find()
+            .select_only()
+            .column_as(user_list_view::Column::Id.count(), "count")
+            .column_as(user_list_view::Column::UpdatedAt.max(), "last_updated_at")
+            .column_as(user_list_view::Column::LastSeenAt.max(), "last_seen_at")
+            .into_tuple()
+            .one(transaction)
+            .await
+            .map_err(handle_dberr)?
+            .unwrap_or_default();
+
+        Ok(UserListStamp {
+            count: count as u64,
+            last_updated_at: last_updated_at.map(|at| at.to_utc()),
+            last_seen_at: last_seen_at.map(|at| at.to_utc()),
+        })
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    async fn rebuild(&self, transaction: &dyn Transaction) -> Result<u64, Error> {
+        let _timer = self.latency_budgets.start("user", "rebuild_list_view");
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+
+        prelude::UserListView::delete_many().exec(transaction).await.map_err(handle_dberr)?;
+        let copy = Query::insert()
+            .into_table(user_list_view::Entity)
+            .columns([
+                user_list_view::Column::Id,
+                user_list_view::Column::Token,
+                user_list_view::Column::Name,
+                user_list_view::Column::Email,
+                user_list_view::Column::Age,
+                user_list_view::Column::Version,
+                user_list_view::Column::CreatedAt,
+                user_list_view::Column::UpdatedAt,
+                user_list_view::Column::LastSeenAt,
+            ])
+            .select_from(
+                Query::select()
+                    .columns([
+                        users::Column::Id,
+                        users::Column::Token,
+                        users::Column::Name,
+                        users::Column::Email,
+                        users::Column::Age,
+                        users::Column::Version,
+                        users::Column::CreatedAt,
+                        users::Column::UpdatedAt,
+                        users::Column::LastSeenAt,
+                    ])
+                    .from(users::Entity)
+                    .to_owned(),
+            )
+            .map_err(|error| Error::Infrastructure(error.to_string()))?
+            .to_owned();
+        transaction.execute(&copy).await.map_err(handle_dberr)?;
+
+        Ok(prelude::UserListView::find().count(transaction).await.map_err(handle_dberr)?)
+    }
+}
+
+/// `specification` as a condition on `user_list_view`. Compositions of
+/// nothing are spelled out, as sea-query leaves empty conditions out of the
+/// query.
+fn user_condition(specification: &UserSpecification) -> Condition {
+    match specification {
+        Specification::Is(predicate) => Condition::all().add(user_predicate(predicate)),
+        Specification::All(specifications) if specifications.is_empty() => Condition::all().add(Expr::value(true)),
+        Specification::All(specifications) => specifications
+            .iter()
+            .fold(Condition::all(), |condition, specification| condition.add(user_condition(specification))),
+        Specification::Any(specifications) if specifications.is_empty() => Condition::all().add(Expr::value(false)),
+        Specification::Any(specifications) => specifications
+            .iter()
+            .fold(Condition::any(), |condition, specification| condition.add(user_condition(specification))),
+        Specification::Not(specification) => user_condition(specification).not(),
+    }
+}
+
+fn user_predicate(predicate: &UserPredicate) -> Expr {
+    match predicate {
+        UserPredicate::NameContains(text) => lowercase_like(user_list_view::Column::Name, format!("%{}%", escape_like(&text.to_lowercase()))),
+        UserPredicate::EmailDomain(domain) => lowercase_like(user_list_view::Column::Email, format!("%@{}", escape_like(&domain.to_lowercase()))),
+        UserPredicate::AgeAtLeast(age) => user_list_view::Column::Age.gte(age.value()),
+        UserPredicate::AgeAtMost(age) => user_list_view::Column::Age.lte(age.value()),
+        UserPredicate::SeenSince(since) => user_list_view::Column::LastSeenAt.gte(since.fixed_offset()),
+        UserPredicate::NeverSeen => user_list_view::Column::LastSeenAt.is_null(),
+    }
+}
+
+/// `column`, lowercased, matches `pattern`, which escapes with `\`.
+fn lowercase_like(column: user_list_view::Column, pattern: String) -> Expr {
+    // Scoped here: `ExprTrait::like` would clash with `ColumnTrait::like`
+    // elsewhere.
+    use sea_orm::sea_query::ExprTrait;
+
+    Expr::from(Func::lower(Expr::col(column))).like(LikeExpr::new(pattern).escape('\\'))
+}
+
+/// `text` matching only itself in a `LIKE` pattern.
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::Duration;
+    use hex_play_core::{
+        Error,
+        clock::FixedClock,
+        repository::{RepositoryService, Transaction},
+        types::Age,
+        user::{NewUser, User, UserId, UserListStamp, UserPredicate, UserSpecification},
+    };
+    use sea_orm::{Database, EntityTrait as _};
+
+    use crate::{create_repository_service, entities::prelude, transaction::TransactionImpl};
+
+    async fn setup() -> Arc<RepositoryService> {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        create_repository_service(db).await.unwrap()
+    }
+
+    // ===================
+    // Tests: list_users
+    // ===================
+    #[tokio::test]
+    async fn test_list_users_success() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        svc.user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+        svc.user_repository()
+            .add_user(&*tx, NewUser::new("Jane Doe", "jane@example.com", 25).unwrap())
+            .await
+            .unwrap();
+
+        let result = svc.user_query_repository().list_users(&*tx, None, None, None).await;
+
+        assert!(result.is_ok());
+        let mut users = result.unwrap();
+        assert_eq!(users.len(), 2);
+        users.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(users[0].name, "Jane Doe");
+        assert_eq!(users[0].age.value(), 25);
+        assert_eq!(users[1].name, "John Doe");
+        assert_eq!(users[1].age.value(), 30);
+    }
+
+    #[tokio::test]
+    async fn test_list_users_empty() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let result = svc.user_query_repository().list_users(&*tx, None, None, None).await;
+
+        assert!(result.is_ok());
+        assert!(result.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_users_after_id_zero() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let result = svc.user_query_repository().list_users(&*tx, Some(UserId::new(0)), None, None).await;
+
+        assert!(result.is_ok());
+        assert!(result.unwrap().is_empty());
+    }
+
+    /// Adds `count` users and returns their ids in ascending order, the
+    /// order `list_users` pages through them.
+    async fn seed_users(svc: &RepositoryService, tx: &dyn Transaction, count: usize) -> Vec<UserId> {
+        let mut ids = Vec::with_capacity(count);
+        for i in 0..count {
+            let user = svc
+                .user_repository()
+                .add_user(tx, NewUser::new(format!("User {i}"), format!("user{i}@example.com"), 30).unwrap())
+                .await
+                .unwrap();
+            ids.push(user.id);
+        }
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_list_users_after_id_is_exclusive() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let ids = seed_users(&svc, &*tx, 5).await;
+
+        let users = svc.user_query_repository().list_users(&*tx, Some(ids[1]), None, None).await.unwrap();
+
+        assert_eq!(users.iter().map(|user| user.id).collect::<Vec<_>>(), ids[2..]);
+    }
+
+    #[tokio::test]
+    async fn test_list_users_walk_visits_every_user_once() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let ids = seed_users(&svc, &*tx, 7).await;
+
+        let mut seen = Vec::new();
+        let mut pages = 0;
+        let mut after_id = None;
+        loop {
+            let page = svc.user_query_repository().list_users(&*tx, after_id, Some(3), None).await.unwrap();
+            if page.is_empty() {
+                break;
+            }
+            pages += 1;
+            after_id = page.last().map(|user| user.id);
+            seen.extend(page.into_iter().map(|user| user.id));
+        }
+
+        assert_eq!(pages, 3);
+        assert_eq!(seen, ids);
+    }
+
+    #[tokio::test]
+    async fn test_list_users_invalid_page_size() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let result = svc.user_query_repository().list_users(&*tx, None, Some(0), None).await;
+
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), Error::InvalidPageSize(0)));
+    }
+
+    #[tokio::test]
+    async fn test_list_users_active_since() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let john = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+        let jane = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("Jane Doe", "jane@example.com", 25).unwrap())
+            .await
+            .unwrap();
+        svc.user_repository()
+            .add_user(&*tx, NewUser::new("Jim Doe", "jim@example.com", 20).unwrap())
+            .await
+            .unwrap();
+        let now = FixedClock::epoch();
+        svc.user_repository()
+            .record_last_seen(&*tx, &[(john.id, now - Duration::hours(2)), (jane.id, now)])
+            .await
+            .unwrap();
+
+        let users = svc
+            .user_query_repository()
+            .list_users(&*tx, None, None, Some(now - Duration::hours(1)))
+            .await
+            .unwrap();
+
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].id, jane.id);
+        assert_eq!(users[0].last_seen_at, Some(now));
+    }
+
+    // ===================
+    // Tests: find_matching
+    // ===================
+    async fn add_users(svc: &RepositoryService, tx: &(dyn Transaction + 'static)) -> Vec<User> {
+        let mut users = Vec::new();
+        for (name, email, age) in [
+            ("John Doe", "john@example.com", 30),
+            ("Jane Roe", "jane@EXAMPLE.com", 25),
+            ("Jim_Doe", "jim@other.org", 20),
+        ] {
+            users.push(svc.user_repository().add_user(tx, NewUser::new(name, email, age).unwrap()).await.unwrap());
+        }
+        users
+    }
+
+    #[tokio::test]
+    async fn test_find_matching_composes_predicates() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let users = add_users(&svc, &*tx).await;
+
+        // Users at example.com, or anyone under 21, but not Jane.
+        let specification = UserSpecification::is(UserPredicate::EmailDomain("Example.com".into()))
+            .or(UserSpecification::is(UserPredicate::AgeAtMost(Age::new(20).unwrap())))
+            .and(!UserSpecification::is(UserPredicate::NameContains("jane".into())));
+        let found = svc.user_query_repository().find_matching(&*tx, &specification, None, None).await.unwrap();
+
+        let mut expected = vec![users[0].id, users[2].id];
+        expected.sort_unstable();
+        assert_eq!(found.iter().map(|user| user.id).collect::<Vec<_>>(), expected);
+        assert!(found.iter().all(|user| specification.is_satisfied_by(user)));
+    }
+
+    #[tokio::test]
+    async fn test_find_matching_escapes_like_wildcards() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let users = add_users(&svc, &*tx).await;
+
+        // Unescaped, `_` would also match the space in "John Doe".
+        let specification = UserSpecification::is(UserPredicate::NameContains("_doe".into()));
+        let found = svc.user_query_repository().find_matching(&*tx, &specification, None, None).await.unwrap();
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, users[2].id);
+    }
+
+    #[tokio::test]
+    async fn test_find_matching_empty_compositions() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        add_users(&svc, &*tx).await;
+
+        let everyone = svc
+            .user_query_repository()
+            .find_matching(&*tx, &UserSpecification::All(Vec::new()), None, None)
+            .await
+            .unwrap();
+        let no_one = svc
+            .user_query_repository()
+            .find_matching(&*tx, &UserSpecification::Any(Vec::new()), None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(everyone.len(), 3);
+        assert!(no_one.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_find_matching_pages_after_id() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let users = add_users(&svc, &*tx).await;
+        let specification = UserSpecification::is(UserPredicate::NeverSeen);
+        let mut ids: Vec<UserId> = users.iter().map(|user| user.id).collect();
+        ids.sort_unstable();
+
+        let page = svc
+            .user_query_repository()
+            .find_matching(&*tx, &specification, Some(ids[0]), Some(1))
+            .await
+            .unwrap();
+
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].id, ids[1]);
+    }
+
+    // ===================
+    // Tests: list_stamp
+    // ===================
+    #[tokio::test]
+    async fn test_list_stamp_empty() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let stamp = svc.user_query_repository().list_stamp(&*tx).await.unwrap();
+
+        assert_eq!(stamp, UserListStamp::default());
+    }
+
+    #[tokio::test]
+    async fn test_list_stamp_follows_writes() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let ids = seed_users(&svc, &*tx, 2).await;
+        let before = svc.user_query_repository().list_stamp(&*tx).await.unwrap();
+        let now = FixedClock::epoch();
+
+        svc.user_repository().record_last_seen(&*tx, &[(ids[1], now)]).await.unwrap();
+        let seen = svc.user_query_repository().list_stamp(&*tx).await.unwrap();
+        let user = svc.user_repository().find_by_id(&*tx, ids[0]).await.unwrap().unwrap();
+        svc.user_repository().delete_user(&*tx, user).await.unwrap();
+        let deleted = svc.user_query_repository().list_stamp(&*tx).await.unwrap();
+
+        assert_eq!(before.count, 2);
+        assert!(before.last_updated_at.is_some());
+        assert_eq!(before.last_seen_at, None);
+        assert_eq!(seen.last_seen_at, Some(now));
+        assert_eq!(deleted.count, 1);
+    }
+
+    // ===================
+    // Tests: keeping in step with users
+    // ===================
+    #[tokio::test]
+    async fn test_list_users_sees_updates_and_deletes() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let john = svc
+            .user_repository()
+            .add_user(&*tx, NewUser::new("John Doe", "john@example.com", 30).unwrap())
+            .await
+            .unwrap();
+        let jane = svc
+            .user_repository()
+            .upsert_by_email(&*tx, NewUser::new("Jane Doe", "jane@example.com", 25).unwrap())
+            .await
+            .unwrap();
+
+        let mut renamed = john.clone();
+        renamed.name = "Johnny Doe".to_string();
+        let renamed = svc.user_repository().update_user(&*tx, renamed).await.unwrap();
+        svc.user_repository().delete_user(&*tx, jane).await.unwrap();
+
+        let users = svc.user_query_repository().list_users(&*tx, None, None, None).await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].name, "Johnny Doe");
+        assert_eq!(users[0].version, renamed.version);
+        assert_eq!(users[0].updated_at, renamed.updated_at);
+    }
+
+    // ===================
+    // Tests: rebuild
+    // ===================
+    #[tokio::test]
+    async fn test_rebuild_copies_users() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let ids = seed_users(&svc, &*tx, 3).await;
+        let now = FixedClock::epoch();
+        svc.user_repository().record_last_seen(&*tx, &[(ids[0], now)]).await.unwrap();
+        // As if the users had been written before the listings existed.
+        prelude::UserListView::delete_many()
+            .exec(TransactionImpl::get_db_transaction(&*tx).unwrap())
+            .await
+            .unwrap();
+
+        let held = svc.user_query_repository().rebuild(&*tx).await.unwrap();
+
+        let users = svc.user_query_repository().list_users(&*tx, None, None, None).await.unwrap();
+        assert_eq!(held, 3);
+        assert_eq!(users.iter().map(|user| user.id).collect::<Vec<_>>(), ids);
+        assert_eq!(users[0].last_seen_at, Some(now));
+    }
+}
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use hex_play_core::{Error, repository::Transaction, webhook::WebhookRepository};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter, sea_query::OnConflict};
+
+use crate::{
+    entities::{prelude, webhook_nonces},
+    error::handle_dberr,
+    latency::LatencyBudgets,
+    transaction::TransactionImpl,
+};
+
+pub struct WebhookRepositoryAdapter {
+    latency_budgets: Arc<LatencyBudgets>,
+}
+
+impl WebhookRepositoryAdapter {
+    pub(crate) fn new(latency_budgets: Arc<LatencyBudgets>) -> Self {
+        Self { latency_budgets }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebhookRepository for WebhookRepositoryAdapter {
+    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    async fn claim_nonce(&self, transaction: &dyn Transaction, nonce: &str, received_at: DateTime<Utc>) -> Result<bool, Error> {
+        let _timer = self.latency_budgets.start("webhook", "claim_nonce");
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+
+        let model = webhook_nonces::ActiveModel {
+            nonce: Set(nonce.to_string()),
+            received_at: Set(received_at.into()),
+        };
+
+        // Skipping the conflicting row instead of failing keeps a Postgres
+        // transaction usable after a duplicate.
+        let inserted = prelude::WebhookNonces::insert(model)
+            .on_conflict(OnConflict::column(webhook_nonces::Column::Nonce).do_nothing().to_owned())
+            .exec_without_returning(transaction)
+            .await
+            .map_err(handle_dberr)?;
+
+        Ok(inserted > 0)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, transaction))]
+    async fn delete_nonces_before(&self, transaction: &dyn Transaction, before: DateTime<Utc>) -> Result<u64, Error> {
+        let _timer = self.latency_budgets.start("webhook", "delete_nonces_before");
+        let transaction = TransactionImpl::get_db_transaction(transaction)?;
+
+        let result = prelude::WebhookNonces::delete_many()
+            .filter(webhook_nonces::Column::ReceivedAt.lt(before))
+            .exec(transaction)
+            .await
+            .map_err(handle_dberr)?;
+
+        Ok(result.rows_affected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{Duration, Utc};
+    use hex_play_core::repository::RepositoryService;
+    use sea_orm::Database;
+
+    use crate::create_repository_service;
+
+    async fn setup() -> Arc<RepositoryService> {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        create_repository_service(db).await.unwrap()
+    }
+
+    // ===================
+    // Tests: claim_nonce
+    // ===================
+    #[tokio::test]
+    async fn test_claim_nonce_only_once() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let now = Utc::now();
+
+        assert!(svc.webhook_repository().claim_nonce(&*tx, "n1", now).await.unwrap());
+        assert!(!svc.webhook_repository().claim_nonce(&*tx, "n1", now).await.unwrap());
+        assert!(svc.webhook_repository().claim_nonce(&*tx, "n2", now).await.unwrap());
+    }
+
+    // ===================
+    // Tests: delete_nonces_before
+    // ===================
+    #[tokio::test]
+    async fn test_delete_nonces_before_frees_old_nonces() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+        let now = Utc::now();
+        svc.webhook_repository().claim_nonce(&*tx, "old", now - Duration::hours(1)).await.unwrap();
+        svc.webhook_repository().claim_nonce(&*tx, "new", now).await.unwrap();
+
+        let deleted = svc.webhook_repository().delete_nonces_before(&*tx, now - Duration::minutes(10)).await.unwrap();
+
+        assert_eq!(deleted, 1);
+        assert!(svc.webhook_repository().claim_nonce(&*tx, "old", now).await.unwrap());
+        assert!(!svc.webhook_repository().claim_nonce(&*tx, "new", now).await.unwrap());
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use hex_play_core::{
+    Error, ErrorKind,
+    user::{DecoratedUserRepository, OperationLayer, UserRepository},
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tracing::Instrument as _;
+
+use crate::RepositoryOptions;
+
+/// A layer the user repository can be wrapped in, see
+/// [`DatabaseConfig::user_repository_decorators`](crate::DatabaseConfig::user_repository_decorators).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RepositoryDecorator {
+    /// A debug span per operation, recording its outcome.
+    Tracing,
+    /// Counts operations by outcome as `repo_op_total`.
+    Metrics,
+    /// Fails operations with `transient_error_probability`.
+    #[cfg(feature = "fault-injection")]
+    Faults,
+}
+
+/// Wraps `repository` in the decorators of `options`, the first listed
+/// outermost, so it sees each operation first.
+pub(crate) fn decorate_user_repository(repository: Arc<dyn UserRepository>, options: &RepositoryOptions) -> Arc<dyn UserRepository> {
+    options
+        .user_repository_decorators
+        .iter()
+        .rev()
+        .fold(repository, |inner, decorator| match decorator {
+            RepositoryDecorator::Tracing => Arc::new(DecoratedUserRepository::new(TracingLayer, inner)) as Arc<dyn UserRepository>,
+            RepositoryDecorator::Metrics => Arc::new(DecoratedUserRepository::new(MetricsLayer, inner)),
+            #[cfg(feature = "fault-injection")]
+            RepositoryDecorator::Faults => Arc::new(DecoratedUserRepository::new(
+                FaultLayer {
+                    error_probability: options.transient_error_probability,
+                },
+                inner,
+            )),
+        })
+}
+
+/// `ok`, or the kind of error an operation failed with.
+fn outcome<T>(result: &Result<T, Error>) -> &'static str {
+    match result.as_ref().map_err(Error::kind) {
+        Ok(_) => "ok",
+        Err(ErrorKind::NotFound) => "not_found",
+        Err(ErrorKind::Conflict) => "conflict",
+        Err(ErrorKind::InvalidInput) => "invalid_input",
+        Err(ErrorKind::BadRequest) => "bad_request",
+        Err(ErrorKind::Internal) => "internal",
+        Err(ErrorKind::Unavailable) => "unavailable",
+    }
+}
+
+struct TracingLayer;
+
+impl OperationLayer for TracingLayer {
+    async fn call<T: Send>(&self, op: &'static str, operation: impl Future<Output = Result<T, Error>> + Send) -> Result<T, Error> {
+        let span = tracing::debug_span!("user_repository", op, outcome = tracing::field::Empty);
+        let result = operation.instrument(span.clone()).await;
+        span.record("outcome", outcome(&result));
+        result
+    }
+}
+
+struct MetricsLayer;
+
+impl OperationLayer for MetricsLayer {
+    async fn call<T: Send>(&self, op: &'static str, operation: impl Future<Output = Result<T, Error>> + Send) -> Result<T, Error> {
+        let result = operation.await;
+        metrics::counter!("repo_op_total", "repo" => "user", "op" => op, "outcome" => outcome(&result)).increment(1);
+        result
+    }
+}
+
+#[cfg(feature = "fault-injection")]
+struct FaultLayer {
+    error_probability: f64,
+}
+
+#[cfg(feature = "fault-injection")]
+impl OperationLayer for FaultLayer {
+    async fn call<T: Send>(&self, _op: &'static str, operation: impl Future<Output = Result<T, Error>> + Send) -> Result<T, Error> {
+        match hex_play_core::fault::injected_error(self.error_probability) {
+            Some(error) => Err(error),
+            None => operation.await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use hex_play_core::test_support::{MockTransaction, MockUserRepository};
+    use serde::{
+        Deserialize as _,
+        de::{IntoDeserializer as _, value::Error},
+    };
+
+    use super::{RepositoryDecorator, decorate_user_repository};
+    use crate::RepositoryOptions;
+
+    // ===================
+    // Tests: decorate_user_repository
+    // ===================
+    #[tokio::test]
+    async fn test_decorated_user_repository_calls_adapter() {
+        let mut inner = MockUserRepository::new();
+        inner.expect_count_users().times(1).returning(|_| Ok(3));
+        let options = RepositoryOptions {
+            user_repository_decorators: vec![RepositoryDecorator::Metrics, RepositoryDecorator::Tracing],
+            ..RepositoryOptions::default()
+        };
+
+        let repository = decorate_user_repository(Arc::new(inner), &options);
+
+        assert_eq!(repository.count_users(&MockTransaction).await.unwrap(), 3);
+    }
+
+    // ===================
+    // Tests: RepositoryDecorator
+    // ===================
+    #[test]
+    fn test_decorators_parse_from_config_names() {
+        let parse = |name: &str| RepositoryDecorator::deserialize(name.into_deserializer()).map_err(|error: Error| error);
+
+        assert_eq!(parse("tracing"), Ok(RepositoryDecorator::Tracing));
+        assert_eq!(parse("metrics"), Ok(RepositoryDecorator::Metrics));
+        assert!(parse("caching").is_err());
+    }
+}
@@ -0,0 +1,31 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Leases taken by server instances, one row per lease. A row whose
+/// `expires_at` has passed is free for any instance to take over; the row
+/// stays, so `fencing_token` keeps going up across holders.
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "leases")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub name: String,
+    pub holder: String,
+    pub fencing_token: i64,
+    pub expires_at: DateTimeWithTimeZone,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {}
+
+// Generated by `hex-play gen-entities`; edits below this line are overwritten.
+impl From<Model> for hex_play_core::lease::Lease {
+    fn from(model: Model) -> Self {
+        Self {
+            name: model.name,
+            holder: model.holder,
+            fencing_token: model.fencing_token,
+            expires_at: model.expires_at.with_timezone(&chrono::Utc),
+        }
+    }
+}
@@ -1,7 +1,17 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 2.0
 
+pub(crate) mod leases;
+
 pub(crate) mod prelude;
 
 pub(crate) mod sessions;
 
+pub(crate) mod user_info;
+
+pub(crate) mod user_list_view;
+
+pub(crate) mod user_signup_rollups;
+
 pub(crate) mod users;
+
+pub(crate) mod webhook_nonces;
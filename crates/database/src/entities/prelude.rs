@@ -1,3 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 2.0
 
-pub(crate) use super::{sessions::Entity as Sessions, users::Entity as Users};
+pub(crate) use super::{
+    leases::Entity as Leases, sessions::Entity as Sessions, user_info::Entity as UserInfo, user_list_view::Entity as UserListView,
+    user_signup_rollups::Entity as UserSignupRollups, users::Entity as Users, webhook_nonces::Entity as WebhookNonces,
+};
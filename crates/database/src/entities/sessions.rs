@@ -14,3 +14,15 @@ pub struct Model {
 
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {}
+
+// Generated by `hex-play gen-entities`; edits below this line are overwritten.
+impl From<Model> for hex_play_core::session::Session {
+    fn from(model: Model) -> Self {
+        Self {
+            id: hex_play_core::session::SessionId::new(model.id),
+            session: model.session,
+            expires_at: model.expires_at.with_timezone(&chrono::Utc),
+            created_at: model.created_at.with_timezone(&chrono::Utc),
+        }
+    }
+}
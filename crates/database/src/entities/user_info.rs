@@ -0,0 +1,21 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Per-user data kept apart from `users`, so it can change without bumping
+/// the user's version.
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "user_info")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub user_id: i64,
+    /// Object storage key of the user's avatar.
+    pub avatar_key: Option<String>,
+    /// Whether the resized variants of `avatar_key` have been generated.
+    #[sea_orm(default_value = false)]
+    pub avatar_variants_ready: bool,
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {}
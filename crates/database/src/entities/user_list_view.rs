@@ -0,0 +1,59 @@
+use sea_orm::{ActiveValue::Set, entity::prelude::*};
+use serde::{Deserialize, Serialize};
+
+/// Users as listed and searched, denormalized from `users` so listings do
+/// not read the table the writes go to. Kept in step by the user repository
+/// in the transaction of each write; rebuilt from `users` on demand.
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "user_list_view")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: i64,
+    pub token: String,
+    pub name: String,
+    pub email: String,
+    pub age: i16,
+    pub version: i64,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+    /// Whether the user counts as active follows from this and the time of
+    /// the listing, so it is kept rather than a status.
+    pub last_seen_at: Option<DateTimeWithTimeZone>,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {}
+
+impl From<super::users::Model> for ActiveModel {
+    fn from(user: super::users::Model) -> Self {
+        Self {
+            id: Set(user.id),
+            token: Set(user.token),
+            name: Set(user.name),
+            email: Set(user.email),
+            age: Set(user.age),
+            version: Set(user.version),
+            created_at: Set(user.created_at),
+            updated_at: Set(user.updated_at),
+            last_seen_at: Set(user.last_seen_at),
+        }
+    }
+}
+
+// Generated by `hex-play gen-entities`; edits below this line are overwritten.
+impl From<Model> for hex_play_core::user::User {
+    fn from(model: Model) -> Self {
+        Self {
+            id: crate::ids::user_id(model.id),
+            token: hex_play_core::user::UserToken::parse(&model.token).unwrap(),
+            name: model.name,
+            email: hex_play_core::types::Email::new(model.email).expect("database email should be valid"),
+            age: hex_play_core::types::Age::new(model.age).expect("database age should be valid"),
+            version: model.version as u64,
+            created_at: model.created_at.with_timezone(&chrono::Utc),
+            updated_at: model.updated_at.with_timezone(&chrono::Utc),
+            last_seen_at: model.last_seen_at.map(|at| at.with_timezone(&chrono::Utc)),
+        }
+    }
+}
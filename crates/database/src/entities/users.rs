@@ -1,6 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 2.0
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use hex_play_core::user::UserToken;
 use sea_orm::{ActiveValue::Set, entity::prelude::*};
 use serde::{Deserialize, Serialize};
@@ -20,6 +20,8 @@ pub struct Model {
     pub version: i64,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
+    /// When the user was last seen; written in batches without `touch`.
+    pub last_seen_at: Option<DateTimeWithTimeZone>,
 }
 
 #[async_trait::async_trait]
@@ -28,23 +30,38 @@ impl ActiveModelBehavior for ActiveModel {
         let token = UserToken::generate();
 
         Self {
-            id: Set(token.id() as i64),
+            id: Set(crate::ids::user_id_column(token.id())),
             token: Set(token.to_string()),
-            created_at: Set(Utc::now().into()),
-            updated_at: Set(Utc::now().into()),
             ..ActiveModelTrait::default()
         }
     }
+}
 
-    async fn before_save<C>(mut self, _db: &C, _insert: bool) -> Result<Self, DbErr>
-    where
-        C: ConnectionTrait,
-    {
+impl ActiveModel {
+    /// Stamps `updated_at` with `now` if any other column has changed.
+    ///
+    /// All writes that modify a user go through this so `updated_at` cannot
+    /// drift from the data it describes.
+    pub(crate) fn touch(&mut self, now: DateTime<Utc>) {
         if self.is_changed() {
-            self.version = Set(self.version.unwrap() + 1);
-            self.updated_at = Set(Utc::now().into());
+            self.updated_at = Set(now.into());
         }
+    }
+}
 
-        Ok(self)
+// Generated by `hex-play gen-entities`; edits below this line are overwritten.
+impl From<Model> for hex_play_core::user::User {
+    fn from(model: Model) -> Self {
+        Self {
+            id: crate::ids::user_id(model.id),
+            token: hex_play_core::user::UserToken::parse(&model.token).unwrap(),
+            name: model.name,
+            email: hex_play_core::types::Email::new(model.email).expect("database email should be valid"),
+            age: hex_play_core::types::Age::new(model.age).expect("database age should be valid"),
+            version: model.version as u64,
+            created_at: model.created_at.with_timezone(&chrono::Utc),
+            updated_at: model.updated_at.with_timezone(&chrono::Utc),
+            last_seen_at: model.last_seen_at.map(|at| at.with_timezone(&chrono::Utc)),
+        }
     }
 }
@@ -0,0 +1,16 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Nonces of recently received webhook deliveries, kept so a replayed
+/// delivery is rejected. Purged once older than the replay window allows.
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "webhook_nonces")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub nonce: String,
+    pub received_at: DateTimeWithTimeZone,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,5 +1,5 @@
 use hex_play_core::RepositoryError;
-use sea_orm::{DbErr, RuntimeErr};
+use sea_orm::{DbErr, RuntimeErr, sqlx};
 
 /// PostgreSQL error codes.
 /// See: <https://www.postgresql.org/docs/current/errcodes-appendix.html>
@@ -22,6 +22,11 @@ mod pg_error_codes {
 }
 
 pub fn handle_dberr(error: DbErr) -> RepositoryError {
+    if is_connection_error(&error) {
+        tracing::warn!(error = %error, "Database unavailable");
+        return RepositoryError::Unavailable(error.to_string());
+    }
+
     if let DbErr::Query(RuntimeErr::SqlxError(sqlx_err)) | DbErr::Exec(RuntimeErr::SqlxError(sqlx_err)) = &error {
         if let Some(db_err) = sqlx_err.as_database_error() {
             if let Some(code) = db_err.code() {
@@ -57,3 +62,56 @@ pub fn handle_dberr(error: DbErr) -> RepositoryError {
         }
     }
 }
+
+/// True when `error` means the database could not be reached, rather than
+/// that it rejected the statement. These are worth retrying.
+pub(crate) fn is_connection_error(error: &DbErr) -> bool {
+    match error {
+        DbErr::ConnectionAcquire(_) => true,
+        DbErr::Conn(RuntimeErr::SqlxError(sqlx_err)) | DbErr::Exec(RuntimeErr::SqlxError(sqlx_err)) | DbErr::Query(RuntimeErr::SqlxError(sqlx_err)) => {
+            matches!(
+                sqlx_err.as_ref(),
+                sqlx::Error::Io(_) | sqlx::Error::Tls(_) | sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::WorkerCrashed
+            )
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, sync::Arc};
+
+    use hex_play_core::RepositoryError;
+    use sea_orm::{ConnAcquireErr, DbErr, RuntimeErr, sqlx};
+
+    use super::{handle_dberr, is_connection_error};
+
+    // ===================
+    // Tests: is_connection_error
+    // ===================
+
+    #[test]
+    fn test_connection_errors_are_detected() {
+        assert!(is_connection_error(&DbErr::ConnectionAcquire(ConnAcquireErr::Timeout)));
+        let refused = sqlx::Error::Io(io::Error::from(io::ErrorKind::ConnectionRefused));
+        assert!(is_connection_error(&DbErr::Conn(RuntimeErr::SqlxError(Arc::new(refused)))));
+        assert!(is_connection_error(&DbErr::Query(RuntimeErr::SqlxError(Arc::new(sqlx::Error::PoolClosed)))));
+    }
+
+    #[test]
+    fn test_other_errors_are_not_connection_errors() {
+        assert!(!is_connection_error(&DbErr::RecordNotFound("user".into())));
+        assert!(!is_connection_error(&DbErr::Query(RuntimeErr::SqlxError(Arc::new(sqlx::Error::RowNotFound)))));
+    }
+
+    // ===================
+    // Tests: handle_dberr
+    // ===================
+
+    #[test]
+    fn test_connection_error_maps_to_unavailable() {
+        let error = handle_dberr(DbErr::ConnectionAcquire(ConnAcquireErr::ConnectionClosed));
+        assert!(matches!(error, RepositoryError::Unavailable(_)));
+    }
+}
@@ -0,0 +1,33 @@
+//! Converting the typed ids of the domain to and from the `bigint` columns
+//! that store them. Core does not know the ORM, so the conversions live
+//! here rather than as `sea_orm::Value` impls on the ids.
+
+use hex_play_core::user::UserId;
+
+/// The column value of `id`. User ids are generated below `i64::MAX`, so
+/// none is out of range.
+pub(crate) fn user_id_column(id: UserId) -> i64 {
+    id.value() as i64
+}
+
+/// The user id stored as `column`.
+pub(crate) fn user_id(column: i64) -> UserId {
+    UserId::new(column as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_play_core::user::UserId;
+
+    use super::{user_id, user_id_column};
+
+    // ===================
+    // Tests: user_id
+    // ===================
+    #[test]
+    fn test_user_id_round_trips_through_column() {
+        let id = UserId::new(i64::MAX as u64);
+
+        assert_eq!(user_id(user_id_column(id)), id);
+    }
+}
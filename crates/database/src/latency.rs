@@ -0,0 +1,95 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Budget applied to operations without an entry in `per_op`.
+pub(crate) const DEFAULT_SLOW_QUERY_THRESHOLD: Duration = Duration::from_millis(500);
+
+/// Latency budgets for repository operations, keyed by operation name.
+#[derive(Debug, Clone)]
+pub(crate) struct LatencyBudgets {
+    default: Duration,
+    per_op: HashMap<String, Duration>,
+}
+
+impl Default for LatencyBudgets {
+    fn default() -> Self {
+        Self::new(DEFAULT_SLOW_QUERY_THRESHOLD, HashMap::new())
+    }
+}
+
+impl LatencyBudgets {
+    pub(crate) fn new(default: Duration, per_op: HashMap<String, Duration>) -> Self {
+        Self { default, per_op }
+    }
+
+    pub(crate) fn budget_for(&self, op: &str) -> Duration {
+        self.per_op.get(op).copied().unwrap_or(self.default)
+    }
+
+    /// Starts timing `op` of `repo`. The measurement is taken when the
+    /// returned timer is dropped, so abandoned operations are recorded too.
+    pub(crate) fn start(&self, repo: &'static str, op: &'static str) -> OperationTimer {
+        OperationTimer {
+            repo,
+            op,
+            budget: self.budget_for(op),
+            started: Instant::now(),
+        }
+    }
+}
+
+pub(crate) struct OperationTimer {
+    repo: &'static str,
+    op: &'static str,
+    budget: Duration,
+    started: Instant,
+}
+
+impl Drop for OperationTimer {
+    fn drop(&mut self) {
+        let elapsed = self.started.elapsed();
+        metrics::histogram!("repo_op_duration_seconds", "repo" => self.repo, "op" => self.op).record(elapsed.as_secs_f64());
+
+        // Emitted inside the adapter's span, which nests under the use case
+        // that issued the operation.
+        if elapsed > self.budget {
+            tracing::warn!(
+                repo = self.repo,
+                op = self.op,
+                elapsed_ms = elapsed.as_millis() as u64,
+                budget_ms = self.budget.as_millis() as u64,
+                "Slow repository operation"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use super::{DEFAULT_SLOW_QUERY_THRESHOLD, LatencyBudgets};
+
+    // ===================
+    // Tests: budget_for
+    // ===================
+    #[test]
+    fn test_budget_for_uses_default() {
+        let budgets = LatencyBudgets::default();
+
+        assert_eq!(budgets.budget_for("add_user"), DEFAULT_SLOW_QUERY_THRESHOLD);
+    }
+
+    #[test]
+    fn test_budget_for_uses_override() {
+        let budgets = LatencyBudgets::new(
+            Duration::from_millis(100),
+            HashMap::from([("list_users".to_string(), Duration::from_millis(250))]),
+        );
+
+        assert_eq!(budgets.budget_for("list_users"), Duration::from_millis(250));
+        assert_eq!(budgets.budget_for("find_by_id"), Duration::from_millis(100));
+    }
+}
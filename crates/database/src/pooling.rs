@@ -0,0 +1,108 @@
+//! Running behind a connection pooler such as PgBouncer in transaction
+//! pooling mode, which hands each transaction whichever server connection
+//! is free. Nothing may then outlive a transaction on the server
+//! connection: the settings of transactions are already local to them, and
+//! [`PoolMode::Transaction`] keeps prepared statements from being cached
+//! across transactions and runs the statements issued outside of the
+//! repository transactions in transactions of their own.
+
+use schemars::JsonSchema;
+use sea_orm::{ConnectOptions, ConnectionTrait as _, DatabaseConnection, DbErr, ExecResult, Statement, TransactionTrait as _};
+use serde::Deserialize;
+
+/// How the connections of the pool reach Postgres, see
+/// [`DatabaseConfig::pool_mode`](crate::DatabaseConfig::pool_mode).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolMode {
+    /// Each connection keeps its server connection, directly or through a
+    /// pooler in session pooling mode.
+    #[default]
+    Session,
+    /// Through a pooler in transaction pooling mode.
+    Transaction,
+}
+
+impl PoolMode {
+    /// Stops connections caching prepared statements in transaction mode,
+    /// as the server connection that prepared one is handed to another
+    /// client once the transaction ends.
+    pub(crate) fn configure(self, options: &mut ConnectOptions) {
+        if self == PoolMode::Transaction {
+            options.map_sqlx_postgres_opts(|options| options.statement_cache_capacity(0));
+        }
+    }
+}
+
+/// Syncs the schema from the entities, in a transaction in transaction
+/// mode.
+pub(crate) async fn sync_schema(database: &DatabaseConnection, pool_mode: PoolMode) -> Result<(), DbErr> {
+    let registry = database.get_schema_registry("hex-play-database::entities::*");
+    match pool_mode {
+        PoolMode::Session => registry.sync(database).await,
+        PoolMode::Transaction => {
+            let transaction = database.begin().await?;
+            registry.sync(&transaction).await?;
+            transaction.commit().await
+        }
+    }
+}
+
+/// Runs `statement` on `database`, in a transaction in transaction mode so
+/// that it is prepared and run on the same server connection.
+pub(crate) async fn execute_raw(database: &DatabaseConnection, pool_mode: PoolMode, statement: Statement) -> Result<ExecResult, DbErr> {
+    match pool_mode {
+        PoolMode::Session => database.execute_raw(statement).await,
+        PoolMode::Transaction => {
+            let transaction = database.begin().await?;
+            let result = transaction.execute_raw(statement).await?;
+            transaction.commit().await?;
+            Ok(result)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{ConnectionTrait as _, Database, DbBackend, Statement};
+
+    use super::{PoolMode, execute_raw, sync_schema};
+    use crate::check_schema;
+
+    // ===================
+    // Tests: sync_schema
+    // ===================
+    #[tokio::test]
+    async fn test_sync_schema_in_transaction_mode() {
+        let database = Database::connect("sqlite::memory:").await.unwrap();
+
+        sync_schema(&database, PoolMode::Transaction).await.unwrap();
+
+        assert!(check_schema(&database, PoolMode::Transaction).await.unwrap().is_empty());
+    }
+
+    // ===================
+    // Tests: execute_raw
+    // ===================
+    #[tokio::test]
+    async fn test_execute_raw_in_transaction_mode_commits() {
+        let database = Database::connect("sqlite::memory:").await.unwrap();
+        database.execute_unprepared("CREATE TABLE t (id integer)").await.unwrap();
+
+        let result = execute_raw(
+            &database,
+            PoolMode::Transaction,
+            Statement::from_sql_and_values(DbBackend::Sqlite, "INSERT INTO t VALUES ($1)", [1.into()]),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.rows_affected(), 1);
+        let row = database
+            .query_one_raw(Statement::from_string(DbBackend::Sqlite, "SELECT count(*) AS n FROM t"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(row.try_get::<i64>("", "n").unwrap(), 1);
+    }
+}
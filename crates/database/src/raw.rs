@@ -0,0 +1,168 @@
+//! Raw SQL for the database crate's own maintenance and stats code, for
+//! aggregates the query builder expresses poorly and which would otherwise
+//! each need a repository method of their own. Crate-private: everything
+//! outside goes through the repository traits.
+
+use hex_play_core::{Error, repository::Transaction};
+use sea_orm::{ConnectionTrait as _, DbBackend, FromQueryResult, Statement, Value};
+
+use crate::{error::handle_dberr, transaction::TransactionImpl};
+
+/// Parameterized SQL on a transaction of the repository. Statements take
+/// `$1`, `$2`, … placeholders, bound to `values` in order, and rewritten to
+/// `?` for MySQL, see [`statement`]. They run like any other statement of
+/// the transaction, so within the schema of its tenant, under row-level
+/// security and logged with `log_sql`.
+#[async_trait::async_trait]
+pub(crate) trait RawQuery {
+    /// Runs `sql`, returning how many rows it affected.
+    async fn execute_sql(&self, sql: &str, values: Vec<Value>) -> Result<u64, Error>;
+
+    /// Runs `sql`, returning its rows as `T`, whose fields are read from
+    /// the columns of the same name. `T` may be `JsonValue` for rows as
+    /// JSON objects keyed by column name; SQLite gives computed columns no
+    /// type, so they read as null there.
+    async fn query_as<T: FromQueryResult + Send>(&self, sql: &str, values: Vec<Value>) -> Result<Vec<T>, Error>;
+}
+
+#[async_trait::async_trait]
+impl RawQuery for dyn Transaction {
+    async fn execute_sql(&self, sql: &str, values: Vec<Value>) -> Result<u64, Error> {
+        let transaction = TransactionImpl::get_db_transaction(self)?;
+        let statement = statement(transaction.get_database_backend(), sql, values);
+        let result = transaction.execute_raw(statement).await.map_err(handle_dberr)?;
+        Ok(result.rows_affected())
+    }
+
+    async fn query_as<T: FromQueryResult + Send>(&self, sql: &str, values: Vec<Value>) -> Result<Vec<T>, Error> {
+        let transaction = TransactionImpl::get_db_transaction(self)?;
+        let statement = statement(transaction.get_database_backend(), sql, values);
+        Ok(T::find_by_statement(statement).all(transaction).await.map_err(handle_dberr)?)
+    }
+}
+
+/// `sql` with its `$n` placeholders bound to `values` for `backend`.
+/// Postgres and SQLite take them as they are. MySQL only takes `?`, bound in
+/// the order the placeholders appear, so each `$n` becomes `?` and its value
+/// is bound once per use. Placeholders must not appear in string literals.
+fn statement(backend: DbBackend, sql: &str, values: Vec<Value>) -> Statement {
+    if backend != DbBackend::MySql {
+        return Statement::from_sql_and_values(backend, sql, values);
+    }
+
+    let mut rewritten = String::with_capacity(sql.len());
+    let mut bound = Vec::with_capacity(values.len());
+    let mut rest = sql;
+    while let Some(start) = rest.find('$') {
+        rewritten.push_str(&rest[..start]);
+        let digits = rest[start + 1..].bytes().take_while(u8::is_ascii_digit).count();
+        let end = start + 1 + digits;
+        let value = rest[start + 1..end]
+            .parse::<usize>()
+            .ok()
+            .and_then(|position| position.checked_sub(1))
+            .and_then(|index| values.get(index));
+        match value {
+            Some(value) => {
+                rewritten.push('?');
+                bound.push(value.clone());
+            }
+            None => rewritten.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+    rewritten.push_str(rest);
+    Statement::from_sql_and_values(backend, rewritten, bound)
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_play_core::{repository::RepositoryService, user::NewUser};
+    use sea_orm::{Database, DbBackend, FromQueryResult, JsonValue, Value};
+
+    use super::{RawQuery as _, statement};
+    use crate::create_repository_service;
+
+    // ===================
+    // Test Helpers
+    // ===================
+    async fn setup() -> std::sync::Arc<RepositoryService> {
+        let svc = create_repository_service(Database::connect("sqlite::memory:").await.unwrap()).await.unwrap();
+        let tx = svc.repository().begin().await.unwrap();
+        for (name, email, age) in [("John", "john@example.com", 30), ("Jane", "jane@example.com", 40)] {
+            svc.user_repository().add_user(&*tx, NewUser::new(name, email, age).unwrap()).await.unwrap();
+        }
+        tx.commit().await.unwrap();
+        svc
+    }
+
+    #[derive(Debug, PartialEq, FromQueryResult)]
+    struct NameAndAge {
+        name: String,
+        age: i16,
+    }
+
+    // ===================
+    // Tests: RawQuery
+    // ===================
+    #[tokio::test]
+    async fn test_query_as_reads_typed_rows() {
+        let svc = setup().await;
+        let tx = svc.repository().begin_read_only().await.unwrap();
+
+        let rows: Vec<NameAndAge> = tx
+            .query_as("SELECT name, age FROM users WHERE age >= $1 ORDER BY age", vec![35.into()])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            rows,
+            [NameAndAge {
+                name: "Jane".to_string(),
+                age: 40
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_as_reads_json_rows() {
+        let svc = setup().await;
+        let tx = svc.repository().begin_read_only().await.unwrap();
+
+        let rows: Vec<JsonValue> = tx.query_as("SELECT name, age FROM users ORDER BY age", Vec::new()).await.unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["name"], "John");
+        assert_eq!(rows[0]["age"], 30);
+    }
+
+    #[tokio::test]
+    async fn test_execute_sql_returns_rows_affected() {
+        let svc = setup().await;
+        let tx = svc.repository().begin().await.unwrap();
+
+        let affected = tx.execute_sql("UPDATE users SET age = age + 1 WHERE age < $1", vec![35.into()]).await.unwrap();
+
+        assert_eq!(affected, 1);
+        tx.rollback().await.unwrap();
+    }
+
+    // ===================
+    // Tests: statement
+    // ===================
+    #[test]
+    fn test_statement_keeps_numbered_placeholders() {
+        let statement = statement(DbBackend::Postgres, "SELECT $1, $1", vec![1.into()]);
+
+        assert_eq!(statement.sql, "SELECT $1, $1");
+        assert_eq!(statement.values.unwrap().0, [Value::from(1)]);
+    }
+
+    #[test]
+    fn test_statement_binds_each_use_for_mysql() {
+        let statement = statement(DbBackend::MySql, "SELECT $2 WHERE a >= $1 AND b < $1 AND c = '$'", vec![1.into(), "two".into()]);
+
+        assert_eq!(statement.sql, "SELECT ? WHERE a >= ? AND b < ? AND c = '$'");
+        assert_eq!(statement.values.unwrap().0, [Value::from("two"), Value::from(1), Value::from(1)]);
+    }
+}
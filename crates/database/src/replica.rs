@@ -0,0 +1,139 @@
+//! Routing the reads of requests to a read replica. Replication lags
+//! behind the primary, so an actor whose write has just committed keeps
+//! reading from the primary for a while, and sees its own writes on the next
+//! request rather than a stale row or a 404. The actor is the user of the API
+//! key or impersonation a request is authenticated with. Writes are tracked per
+//! instance, so the reads that follow a write must reach the same instance
+//! to be pinned. Anonymous clients, and those reaching another instance,
+//! ask for the primary with `x-read-primary: true` instead.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use hex_play_core::context::RequestContext;
+use sea_orm::DatabaseConnection;
+
+/// How long the reads of an actor stay on the primary after it wrote, by
+/// default.
+pub(crate) const DEFAULT_READ_YOUR_WRITES_WINDOW: Duration = Duration::from_secs(5);
+
+/// The replica and the actors whose reads are pinned to the primary. Clones
+/// share the pins.
+#[derive(Clone)]
+pub(crate) struct ReplicaRouting {
+    replica: DatabaseConnection,
+    window: Duration,
+    /// Until when the reads of each actor stay on the primary.
+    pinned_until: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl ReplicaRouting {
+    pub(crate) fn new(replica: DatabaseConnection, window: Duration) -> Self {
+        Self {
+            replica,
+            window,
+            pinned_until: Arc::default(),
+        }
+    }
+
+    pub(crate) fn replica(&self) -> &DatabaseConnection {
+        &self.replica
+    }
+
+    /// Pins the reads of the actor of `context` to the primary for the
+    /// window from `now`. Anonymous requests can't be told apart, so they
+    /// are not pinned.
+    pub(crate) fn record_write(&self, context: &RequestContext, now: Instant) {
+        let Some(actor) = &context.actor else {
+            return;
+        };
+        let mut pinned_until = self.lock();
+        pinned_until.retain(|_, until| *until > now);
+        pinned_until.insert(actor.clone(), now + self.window);
+    }
+
+    /// Whether the reads of `context` may run against the replica: it does
+    /// not require the primary and its actor has not written within the
+    /// window.
+    pub(crate) fn reads_replica(&self, context: &RequestContext, now: Instant) -> bool {
+        if context.requires_primary {
+            return false;
+        }
+        context
+            .actor
+            .as_ref()
+            .and_then(|actor| self.lock().get(actor).copied())
+            .is_none_or(|until| until <= now)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Instant>> {
+        // The pins stay consistent even if a holder panicked.
+        self.pinned_until.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use hex_play_core::context::RequestContext;
+    use sea_orm::Database;
+
+    use super::ReplicaRouting;
+
+    // ===================
+    // Test Helpers
+    // ===================
+    async fn routing() -> ReplicaRouting {
+        ReplicaRouting::new(Database::connect("sqlite::memory:").await.unwrap(), Duration::from_secs(5))
+    }
+
+    fn context_of(actor: &str) -> RequestContext {
+        let mut context = RequestContext::new("req-1");
+        context.actor = Some(actor.to_string());
+        context
+    }
+
+    // ===================
+    // Tests: ReplicaRouting::reads_replica
+    // ===================
+    #[tokio::test]
+    async fn test_reads_replica_by_default() {
+        let routing = routing().await;
+
+        assert!(routing.reads_replica(&context_of("john"), Instant::now()));
+        assert!(routing.reads_replica(&RequestContext::internal(), Instant::now()));
+    }
+
+    #[tokio::test]
+    async fn test_require_primary_reads_primary() {
+        let routing = routing().await;
+
+        assert!(!routing.reads_replica(&context_of("john").require_primary(), Instant::now()));
+    }
+
+    #[tokio::test]
+    async fn test_write_pins_actor_to_primary_for_window() {
+        let routing = routing().await;
+        let now = Instant::now();
+
+        routing.record_write(&context_of("john"), now);
+
+        assert!(!routing.reads_replica(&context_of("john"), now + Duration::from_secs(4)));
+        assert!(routing.reads_replica(&context_of("jane"), now + Duration::from_secs(4)));
+        assert!(routing.reads_replica(&context_of("john"), now + Duration::from_secs(5)));
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_write_pins_nobody() {
+        let routing = routing().await;
+        let now = Instant::now();
+
+        routing.record_write(&RequestContext::internal(), now);
+
+        assert!(routing.reads_replica(&RequestContext::internal(), now));
+    }
+}
@@ -1,41 +1,355 @@
+use std::time::{Duration, Instant};
+
 use hex_play_core::{
     Error,
+    context::RequestContext,
     repository::{Repository, Transaction},
 };
-use sea_orm::{AccessMode, DatabaseConnection, TransactionTrait};
+use sea_orm::{AccessMode, DatabaseConnection, DbBackend, TransactionTrait};
 
-use crate::{TransactionImpl, error::handle_dberr};
+use crate::{
+    TransactionImpl,
+    error::handle_dberr,
+    pooling::PoolMode,
+    replica::ReplicaRouting,
+    row_security::set_request_settings,
+    sql_trace::TracedTransaction,
+    tenancy::{TenantSchemas, set_search_path},
+    transaction::CancelTarget,
+};
 
 #[derive(Clone)]
 pub(crate) struct RepositoryImpl {
     database: DatabaseConnection,
+    statement_timeout: Option<Duration>,
+    /// Whether transactions log their statements, see [`TracedTransaction`].
+    log_sql: bool,
+    tenant_schemas: TenantSchemas,
+    /// Whether transactions for a request set the settings the row-level
+    /// security policies read, see [`set_request_settings`].
+    row_level_security: bool,
+    pool_mode: PoolMode,
+    /// Where the read-only transactions of requests run, see
+    /// [`ReplicaRouting`].
+    replica_routing: Option<ReplicaRouting>,
 }
 
 impl RepositoryImpl {
-    pub(crate) fn new(database_connection: DatabaseConnection) -> Self {
-        Self { database: database_connection }
+    pub(crate) fn new(database_connection: DatabaseConnection, statement_timeout: Option<Duration>, log_sql: bool) -> Self {
+        Self {
+            database: database_connection,
+            statement_timeout,
+            log_sql,
+            tenant_schemas: TenantSchemas::default(),
+            row_level_security: false,
+            pool_mode: PoolMode::Session,
+            replica_routing: None,
+        }
+    }
+
+    /// Runs the transactions of the tenants in `tenant_schemas` against their
+    /// schemas. Only Postgres has schemas, so other backends ignore them.
+    pub(crate) fn with_tenant_schemas(mut self, tenant_schemas: TenantSchemas) -> Self {
+        if tenant_schemas.is_empty() {
+            return self;
+        }
+        if self.database.get_database_backend() == DbBackend::Postgres {
+            self.tenant_schemas = tenant_schemas;
+        } else {
+            tracing::warn!("Tenant schemas need Postgres, ignoring them");
+        }
+        self
+    }
+
+    /// Confines the transactions of requests to the rows of their tenant
+    /// with the policies of
+    /// [`enable_row_level_security`](crate::row_security::enable_row_level_security).
+    /// Only Postgres has them, so other backends ignore it.
+    pub(crate) fn with_row_level_security(mut self, enabled: bool) -> Self {
+        if !enabled {
+            return self;
+        }
+        if self.database.get_database_backend() == DbBackend::Postgres {
+            self.row_level_security = true;
+        } else {
+            tracing::warn!("Row-level security needs Postgres, ignoring it");
+        }
+        self
+    }
+
+    /// Keeps the statements run outside of transactions, to check the
+    /// schema or cancel queries, within transactions in transaction mode.
+    pub(crate) fn with_pool_mode(mut self, pool_mode: PoolMode) -> Self {
+        self.pool_mode = pool_mode;
+        self
+    }
+
+    /// Runs the read-only transactions of requests against the replica of
+    /// `replica_routing`, unless their reads are pinned to the primary.
+    /// Only Postgres has replicas, so other backends ignore it.
+    pub(crate) fn with_replica_routing(mut self, replica_routing: Option<ReplicaRouting>) -> Self {
+        let Some(replica_routing) = replica_routing else {
+            return self;
+        };
+        if self.database.get_database_backend() == DbBackend::Postgres && replica_routing.replica().get_database_backend() == DbBackend::Postgres {
+            self.replica_routing = Some(replica_routing);
+        } else {
+            tracing::warn!("Read replicas need Postgres, ignoring the replica");
+        }
+        self
+    }
+
+    /// The database the transaction for `context` runs against: the replica
+    /// for reads it may route there, the primary otherwise.
+    fn route(&self, read_only: bool, context: Option<&RequestContext>) -> &DatabaseConnection {
+        match (&self.replica_routing, context) {
+            (Some(replica_routing), Some(context)) if read_only && replica_routing.reads_replica(context, Instant::now()) => replica_routing.replica(),
+            _ => &self.database,
+        }
+    }
+
+    /// Has `transaction`, if it writes for `context`, pin the reads of its
+    /// actor to the primary once it commits. A write that is rolled back
+    /// left nothing to read back, so it pins nothing.
+    fn pin_reads_on_commit(&self, transaction: TransactionImpl, read_only: bool, context: Option<&RequestContext>) -> Box<dyn Transaction> {
+        match (&self.replica_routing, context) {
+            (Some(replica_routing), Some(context)) if !read_only => Box::new(transaction.pinning_reads_on_commit(replica_routing.clone(), context)),
+            _ => Box::new(transaction),
+        }
+    }
+
+    /// The configured statement timeout, shortened to the time left before
+    /// `deadline`. Never zero, which Postgres would take as no limit.
+    fn statement_timeout_until(&self, deadline: Option<Instant>) -> Option<Duration> {
+        let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()).max(Duration::from_millis(1)));
+        match (self.statement_timeout, remaining) {
+            (Some(timeout), Some(remaining)) => Some(timeout.min(remaining)),
+            (timeout, remaining) => timeout.or(remaining),
+        }
+    }
+
+    /// Begins a transaction, for the work of `context` if there is one.
+    /// That work may read from the replica, see [`Self::route`], runs
+    /// against the schema of its tenant and, with row-level
+    /// security, sees only the rows of its tenant. The transaction has the
+    /// statement timeout applied and query cancellation armed. All rely on
+    /// Postgres features, so other backends get a plain transaction.
+    async fn begin_prepared(&self, read_only: bool, context: Option<&RequestContext>, deadline: Option<Instant>) -> Result<Box<dyn Transaction>, Error> {
+        let schema = self.tenant_schemas.resolve(context.and_then(|context| context.tenant.as_deref()))?;
+        let database = self.route(read_only, context);
+        let backend = database.get_database_backend();
+        let transaction = match (read_only, backend) {
+            (false, _) | (true, DbBackend::Sqlite) => database.begin().await,
+            (true, _) => database.begin_with_config(None, Some(AccessMode::ReadOnly)).await,
+        }
+        .map_err(handle_dberr)?;
+        if backend != DbBackend::Postgres {
+            return Ok(self.pin_reads_on_commit(TransactionImpl::new(TracedTransaction::new(transaction, self.log_sql)), read_only, context));
+        }
+
+        if let Some(schema) = schema {
+            set_search_path(&transaction, schema).await.map_err(handle_dberr)?;
+        }
+        if let Some(context) = context.filter(|_| self.row_level_security) {
+            set_request_settings(&transaction, context).await.map_err(handle_dberr)?;
+        }
+        let target = CancelTarget::capture(database, self.pool_mode, &transaction, self.statement_timeout_until(deadline))
+            .await
+            .map_err(handle_dberr)?;
+        Ok(self.pin_reads_on_commit(
+            TransactionImpl::with_cancel_target(TracedTransaction::new(transaction, self.log_sql), target),
+            read_only,
+            context,
+        ))
     }
 }
 
 #[async_trait::async_trait]
 impl Repository for RepositoryImpl {
-    async fn begin(&self) -> Result<Box<dyn Transaction>, Error> {
-        let transaction = self.database.begin().await.map_err(handle_dberr)?;
-        Ok(Box::new(TransactionImpl::new(transaction)))
+    async fn begin_with_deadline(&self, deadline: Option<Instant>) -> Result<Box<dyn Transaction>, Error> {
+        self.begin_prepared(false, None, deadline).await
     }
 
-    async fn begin_read_only(&self) -> Result<Box<dyn Transaction>, Error> {
-        let transaction = match self.database.get_database_backend() {
-            sea_orm::DatabaseBackend::Sqlite => self.database.begin().await.map_err(handle_dberr)?,
-            _ => self.database.begin_with_config(None, Some(AccessMode::ReadOnly)).await.map_err(handle_dberr)?,
-        };
-        Ok(Box::new(TransactionImpl::new(transaction)))
+    async fn begin_read_only_with_deadline(&self, deadline: Option<Instant>) -> Result<Box<dyn Transaction>, Error> {
+        self.begin_prepared(true, None, deadline).await
+    }
+
+    async fn begin_for_request(&self, context: &RequestContext) -> Result<Box<dyn Transaction>, Error> {
+        self.begin_prepared(false, Some(context), context.deadline).await
+    }
+
+    async fn begin_read_only_for_request(&self, context: &RequestContext) -> Result<Box<dyn Transaction>, Error> {
+        self.begin_prepared(true, Some(context), context.deadline).await
+    }
+
+    async fn stale_tables(&self) -> Result<Vec<String>, Error> {
+        crate::check_schema(&self.database, self.pool_mode).await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
     async fn close(&self) -> Result<(), Error> {
         self.database.clone().close().await.map_err(handle_dberr)?;
+        if let Some(replica_routing) = &self.replica_routing {
+            replica_routing.replica().clone().close().await.map_err(handle_dberr)?;
+        }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::Ordering,
+        time::{Duration, Instant},
+    };
+
+    use hex_play_core::{context::RequestContext, repository::Repository};
+    use sea_orm::{ConnectionTrait, Database, DbBackend, Statement};
+
+    use super::RepositoryImpl;
+    use crate::{TransactionImpl, replica::ReplicaRouting};
+
+    async fn setup(statement_timeout: Option<Duration>) -> RepositoryImpl {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        RepositoryImpl::new(db, statement_timeout, false)
+    }
+
+    // ===================
+    // Tests: begin / begin_read_only
+    // ===================
+    #[tokio::test]
+    async fn test_begin_ignores_statement_timeout_on_sqlite() {
+        let repository = setup(Some(Duration::from_millis(10))).await;
+
+        let tx = repository.begin().await.unwrap();
+        tx.commit().await.unwrap();
+
+        let tx = repository.begin_read_only().await.unwrap();
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_begin_for_request_ignores_row_level_security_on_sqlite() {
+        let repository = setup(None).await.with_row_level_security(true);
+        let mut context = RequestContext::new("req-1");
+        context.tenant = Some("acme".to_string());
+        context.actor = Some("john".to_string());
+
+        repository.begin_for_request(&context).await.unwrap().commit().await.unwrap();
+        repository.begin_read_only_for_request(&context).await.unwrap().rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dropped_transaction_without_cancel_target() {
+        let repository = setup(None).await;
+
+        let tx = repository.begin().await.unwrap();
+        drop(tx);
+
+        repository.begin().await.unwrap().commit().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_transaction_logging_sql_runs_statements() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let repository = RepositoryImpl::new(db, None, true);
+
+        let tx = repository.begin().await.unwrap();
+        let row = TransactionImpl::get_db_transaction(&*tx)
+            .unwrap()
+            .query_one_raw(Statement::from_sql_and_values(DbBackend::Sqlite, "SELECT ? AS n", [7.into()]))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(row.try_get::<i32>("", "n").unwrap(), 7);
+        tx.commit().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_transaction_counts_statements() {
+        let repository = setup(None).await;
+
+        let tx = repository.begin().await.unwrap();
+        let transaction = TransactionImpl::get_db_transaction(&*tx).unwrap();
+        for _ in 0..3 {
+            transaction.execute_unprepared("SELECT 1").await.unwrap();
+        }
+
+        assert_eq!(transaction.statement_counter().load(Ordering::Relaxed), 3);
+        tx.rollback().await.unwrap();
+    }
+
+    // ===================
+    // Tests: stale_tables
+    // ===================
+    #[tokio::test]
+    async fn test_stale_tables_without_schema() {
+        let repository = setup(None).await;
+
+        let stale = repository.stale_tables().await.unwrap();
+
+        assert!(stale.contains(&"users".to_string()));
+    }
+
+    // ===================
+    // Tests: route
+    // ===================
+    /// A repository with a replica, and the context of a request by `john`.
+    async fn setup_with_replica() -> (RepositoryImpl, RequestContext) {
+        let mut repository = setup(None).await;
+        let replica = Database::connect("sqlite::memory:").await.unwrap();
+        // Set directly, as routing is for Postgres only.
+        repository.replica_routing = Some(ReplicaRouting::new(replica, Duration::from_secs(5)));
+        let mut context = RequestContext::new("req-1");
+        context.actor = Some("john".to_string());
+        (repository, context)
+    }
+
+    #[tokio::test]
+    async fn test_route_pins_reads_to_primary_after_committed_write() {
+        let (repository, context) = setup_with_replica().await;
+        let replica = repository.replica_routing.as_ref().unwrap().replica();
+
+        assert!(std::ptr::eq(repository.route(true, Some(&context)), replica));
+        assert!(std::ptr::eq(repository.route(true, None), &repository.database));
+        assert!(std::ptr::eq(repository.route(false, Some(&context)), &repository.database));
+
+        let tx = repository.begin_for_request(&context).await.unwrap();
+        assert!(std::ptr::eq(repository.route(true, Some(&context)), replica));
+        tx.commit().await.unwrap();
+
+        assert!(std::ptr::eq(repository.route(true, Some(&context)), &repository.database));
+    }
+
+    #[tokio::test]
+    async fn test_route_keeps_reads_on_replica_after_rolled_back_write() {
+        let (repository, context) = setup_with_replica().await;
+        let replica = repository.replica_routing.as_ref().unwrap().replica();
+
+        repository.begin_for_request(&context).await.unwrap().rollback().await.unwrap();
+
+        assert!(std::ptr::eq(repository.route(true, Some(&context)), replica));
+    }
+
+    // ===================
+    // Tests: statement_timeout_until
+    // ===================
+    #[tokio::test]
+    async fn test_deadline_shortens_statement_timeout() {
+        let repository = setup(Some(Duration::from_secs(30))).await;
+
+        let timeout = repository.statement_timeout_until(Some(Instant::now() + Duration::from_secs(2))).unwrap();
+
+        assert!(timeout <= Duration::from_secs(2) && timeout > Duration::from_secs(1));
+        assert_eq!(repository.statement_timeout_until(None), Some(Duration::from_secs(30)));
+    }
+
+    #[tokio::test]
+    async fn test_passed_deadline_keeps_a_nonzero_timeout() {
+        let repository = setup(None).await;
+
+        assert_eq!(repository.statement_timeout_until(Some(Instant::now())), Some(Duration::from_millis(1)));
+    }
+}
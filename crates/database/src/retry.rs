@@ -0,0 +1,103 @@
+//! Retrying database work that failed only because the server was
+//! unreachable, e.g. while Postgres is still starting or restarting.
+
+use std::time::{Duration, Instant};
+
+use sea_orm::DbErr;
+
+use crate::error::is_connection_error;
+
+/// How long to keep retrying when no wait is configured.
+pub(crate) const DEFAULT_CONNECT_MAX_WAIT: Duration = Duration::from_secs(30);
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Runs `op` until it succeeds, fails with something other than a connection
+/// error, or `max_wait` has elapsed. The delay between attempts doubles from
+/// 250ms up to 5s.
+pub(crate) async fn retry_while_unavailable<T, F, Fut>(what: &str, max_wait: Duration, mut op: F) -> Result<T, DbErr>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, DbErr>>,
+{
+    let started = Instant::now();
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1u32;
+
+    loop {
+        match op().await {
+            Err(error) if is_connection_error(&error) => {
+                let remaining = max_wait.saturating_sub(started.elapsed());
+                if remaining.is_zero() {
+                    tracing::error!(%error, attempt, "{what} failed, giving up waiting for the database");
+                    return Err(error);
+                }
+
+                let delay = backoff.min(remaining);
+                tracing::warn!(%error, attempt, retry_in_ms = delay.as_millis() as u64, "{what} failed, database unavailable");
+                tokio::time::sleep(delay).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::Duration,
+    };
+
+    use sea_orm::{ConnAcquireErr, DbErr};
+
+    use super::retry_while_unavailable;
+
+    // ===================
+    // Tests: retry_while_unavailable
+    // ===================
+
+    #[tokio::test]
+    async fn test_retries_until_connected() {
+        let attempts = AtomicU32::new(0);
+        let result = retry_while_unavailable("connect", Duration::from_secs(5), || async {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(DbErr::ConnectionAcquire(ConnAcquireErr::Timeout)),
+                n => Ok(n),
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_wait() {
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), DbErr> = retry_while_unavailable("connect", Duration::ZERO, || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(DbErr::ConnectionAcquire(ConnAcquireErr::Timeout))
+        })
+        .await;
+
+        assert!(matches!(result, Err(DbErr::ConnectionAcquire(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_are_not_retried() {
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), DbErr> = retry_while_unavailable("connect", Duration::from_secs(5), || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(DbErr::Custom("bad schema".into()))
+        })
+        .await;
+
+        assert!(matches!(result, Err(DbErr::Custom(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,72 @@
+//! Postgres row-level security confining `users`, `user_info` and
+//! `user_list_view` to the tenant of the request, so no statement of its
+//! transactions, raw SQL included, reads or writes the rows of another
+//! tenant.
+//!
+//! The policies read the tenant from the `app.current_org` setting, and
+//! `app.current_user` holds the actor for policies and triggers keyed by
+//! them. Both are set at the start of each transaction for a request and
+//! end with it. Users belong to a tenant through an `org_id` column kept
+//! out of the entity: it defaults to the tenant of the transaction adding
+//! the user, and work outside of a tenant sees only users outside of any.
+//! The listings carry an `org_id` of their own the same way, written in the
+//! same transaction as the user, so rebuilding them outside of a tenant
+//! rebuilds only the users outside of any.
+
+use hex_play_core::context::RequestContext;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbBackend, DbErr, Statement, TransactionTrait};
+
+/// The tenant of the transaction, `NULL` outside of a tenant. Settings that
+/// were set and have ended read as empty rather than `NULL`.
+const CURRENT_ORG: &str = "NULLIF(current_setting('app.current_org', true), '')";
+
+/// Adds the `org_id` column and the policies, replacing the policies if
+/// they are there already, in one transaction.
+pub(crate) async fn enable_row_level_security(database: &DatabaseConnection) -> Result<(), DbErr> {
+    let statements = [
+        format!("ALTER TABLE users ADD COLUMN IF NOT EXISTS org_id text DEFAULT {CURRENT_ORG}"),
+        "ALTER TABLE users ENABLE ROW LEVEL SECURITY".to_string(),
+        // Also applies the policies to the owner of the tables, which the
+        // server usually connects as.
+        "ALTER TABLE users FORCE ROW LEVEL SECURITY".to_string(),
+        "DROP POLICY IF EXISTS tenant_isolation ON users".to_string(),
+        format!(
+            "CREATE POLICY tenant_isolation ON users USING (org_id IS NOT DISTINCT FROM {CURRENT_ORG}) WITH CHECK (org_id IS NOT DISTINCT FROM {CURRENT_ORG})"
+        ),
+        format!("ALTER TABLE user_list_view ADD COLUMN IF NOT EXISTS org_id text DEFAULT {CURRENT_ORG}"),
+        "ALTER TABLE user_list_view ENABLE ROW LEVEL SECURITY".to_string(),
+        "ALTER TABLE user_list_view FORCE ROW LEVEL SECURITY".to_string(),
+        "DROP POLICY IF EXISTS tenant_isolation ON user_list_view".to_string(),
+        format!(
+            "CREATE POLICY tenant_isolation ON user_list_view USING (org_id IS NOT DISTINCT FROM {CURRENT_ORG}) WITH CHECK (org_id IS NOT DISTINCT FROM {CURRENT_ORG})"
+        ),
+        "ALTER TABLE user_info ENABLE ROW LEVEL SECURITY".to_string(),
+        "ALTER TABLE user_info FORCE ROW LEVEL SECURITY".to_string(),
+        "DROP POLICY IF EXISTS tenant_isolation ON user_info".to_string(),
+        // The users visible to the transaction, so of its tenant.
+        "CREATE POLICY tenant_isolation ON user_info USING (EXISTS (SELECT 1 FROM users WHERE users.id = user_info.user_id))".to_string(),
+    ];
+
+    tracing::debug!("Enabling row-level security");
+    let transaction = database.begin().await?;
+    for statement in statements {
+        transaction.execute_unprepared(&statement).await?;
+    }
+    transaction.commit().await
+}
+
+/// Sets `app.current_org` and `app.current_user` to the tenant and actor of
+/// `context` until `transaction` ends, empty for none.
+pub(crate) async fn set_request_settings(transaction: &DatabaseTransaction, context: &RequestContext) -> Result<(), DbErr> {
+    transaction
+        .execute_raw(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT set_config('app.current_org', $1, true), set_config('app.current_user', $2, true)",
+            [
+                context.tenant.clone().unwrap_or_default().into(),
+                context.actor.clone().unwrap_or_default().into(),
+            ],
+        ))
+        .await?;
+    Ok(())
+}
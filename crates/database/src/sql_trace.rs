@@ -0,0 +1,149 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Instant,
+};
+
+use sea_orm::{ConnectionTrait, DatabaseTransaction, DbBackend, DbErr, ExecResult, QueryResult, Statement, Value};
+
+/// Target of the statement events, so they can be enabled on their own,
+/// e.g. `RUST_LOG=info,hex_play_database::sql=trace`.
+pub(crate) const SQL_TARGET: &str = "hex_play_database::sql";
+
+static NEXT_TRANSACTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A transaction that counts the statements it runs and logs each at trace
+/// level, with its bind values redacted, when `trace_sql` is set.
+///
+/// Events are emitted inside the adapter's span, which nests under the use
+/// case that issued the operation, and carry the transaction's id so the
+/// statements of one transaction can be told apart from interleaved ones.
+pub(crate) struct TracedTransaction {
+    pub(crate) inner: DatabaseTransaction,
+    id: u64,
+    trace_sql: bool,
+    statements: Arc<AtomicU64>,
+}
+
+impl TracedTransaction {
+    pub(crate) fn new(inner: DatabaseTransaction, trace_sql: bool) -> Self {
+        Self {
+            inner,
+            id: NEXT_TRANSACTION_ID.fetch_add(1, Ordering::Relaxed),
+            trace_sql,
+            statements: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// How many statements the transaction has run so far, shared so that
+    /// it can still be read after the transaction is gone.
+    pub(crate) fn statement_counter(&self) -> Arc<AtomicU64> {
+        self.statements.clone()
+    }
+
+    fn record_statement<T>(&self, sql: &str, values: Option<&[Value]>, started: Instant, result: &Result<T, DbErr>) {
+        self.statements.fetch_add(1, Ordering::Relaxed);
+        if !self.trace_sql {
+            return;
+        }
+        let binds = values.unwrap_or_default().iter().map(redact).collect::<Vec<_>>();
+        tracing::trace!(
+            target: SQL_TARGET,
+            transaction_id = self.id,
+            sql,
+            binds = ?binds,
+            elapsed_ms = started.elapsed().as_secs_f64() * 1000.0,
+            failed = result.is_err(),
+            "SQL statement"
+        );
+    }
+}
+
+#[async_trait::async_trait]
+impl ConnectionTrait for TracedTransaction {
+    fn get_database_backend(&self) -> DbBackend {
+        self.inner.get_database_backend()
+    }
+
+    async fn execute_raw(&self, stmt: Statement) -> Result<ExecResult, DbErr> {
+        let (sql, values) = (stmt.sql.clone(), stmt.values.clone());
+        let started = Instant::now();
+        let result = self.inner.execute_raw(stmt).await;
+        self.record_statement(&sql, values.as_ref().map(|values| values.0.as_slice()), started, &result);
+        result
+    }
+
+    async fn execute_unprepared(&self, sql: &str) -> Result<ExecResult, DbErr> {
+        let started = Instant::now();
+        let result = self.inner.execute_unprepared(sql).await;
+        self.record_statement(sql, None, started, &result);
+        result
+    }
+
+    async fn query_one_raw(&self, stmt: Statement) -> Result<Option<QueryResult>, DbErr> {
+        let (sql, values) = (stmt.sql.clone(), stmt.values.clone());
+        let started = Instant::now();
+        let result = self.inner.query_one_raw(stmt).await;
+        self.record_statement(&sql, values.as_ref().map(|values| values.0.as_slice()), started, &result);
+        result
+    }
+
+    async fn query_all_raw(&self, stmt: Statement) -> Result<Vec<QueryResult>, DbErr> {
+        let (sql, values) = (stmt.sql.clone(), stmt.values.clone());
+        let started = Instant::now();
+        let result = self.inner.query_all_raw(stmt).await;
+        self.record_statement(&sql, values.as_ref().map(|values| values.0.as_slice()), started, &result);
+        result
+    }
+
+    fn support_returning(&self) -> bool {
+        self.inner.support_returning()
+    }
+}
+
+/// A bind value as logged: numbers, booleans and timestamps as they are,
+/// anything that may hold personal data, such as text, bytes and JSON, only
+/// as whether it is set.
+fn redact(value: &Value) -> String {
+    match value {
+        Value::Bool(_)
+        | Value::TinyInt(_)
+        | Value::SmallInt(_)
+        | Value::Int(_)
+        | Value::BigInt(_)
+        | Value::TinyUnsigned(_)
+        | Value::SmallUnsigned(_)
+        | Value::Unsigned(_)
+        | Value::BigUnsigned(_)
+        | Value::Float(_)
+        | Value::Double(_)
+        | Value::ChronoDate(_)
+        | Value::ChronoTime(_)
+        | Value::ChronoDateTime(_)
+        | Value::ChronoDateTimeUtc(_)
+        | Value::ChronoDateTimeLocal(_)
+        | Value::ChronoDateTimeWithTimeZone(_) => format!("{value:?}"),
+        _ if *value == value.as_null() => "NULL".to_string(),
+        _ => "<redacted>".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::Value;
+
+    use super::redact;
+
+    // ===================
+    // Tests: redact
+    // ===================
+    #[test]
+    fn test_redacts_text_but_not_numbers() {
+        assert_eq!(redact(&Value::BigInt(Some(42))), "BigInt(Some(42))");
+        assert_eq!(redact(&Value::String(Some("jane@example.com".to_string()))), "<redacted>");
+        assert_eq!(redact(&Value::Bytes(Some(vec![1, 2, 3]))), "<redacted>");
+        assert_eq!(redact(&Value::String(None)), "NULL");
+    }
+}
@@ -0,0 +1,123 @@
+//! Tenants kept in Postgres schemas of their own, as an alternative to
+//! scoping rows by tenant in shared tables. The transactions of a tenant
+//! resolve unqualified table names through a `search_path` of its schema,
+//! and each schema is synced from the entities at startup like the default
+//! one.
+
+use std::collections::{BTreeSet, HashMap};
+
+use hex_play_core::Error;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbBackend, DbErr, Statement, TransactionTrait};
+
+/// The schema of each tenant kept apart, keyed by tenant id.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TenantSchemas(HashMap<String, String>);
+
+impl TenantSchemas {
+    pub(crate) fn new(schemas: HashMap<String, String>) -> Self {
+        Self(schemas)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The schema the work of `tenant` runs against, `None` for the default
+    /// one. Work outside of a tenant, such as background jobs, uses the
+    /// default schema, as does all work while no tenant has a schema. Fails
+    /// with `Error::UnknownTenant` for a tenant without one otherwise.
+    pub(crate) fn resolve(&self, tenant: Option<&str>) -> Result<Option<&str>, Error> {
+        match tenant {
+            Some(tenant) if !self.is_empty() => self
+                .0
+                .get(tenant)
+                .map(|schema| Some(schema.as_str()))
+                .ok_or_else(|| Error::UnknownTenant(tenant.to_string())),
+            _ => Ok(None),
+        }
+    }
+
+    /// Every schema once, in order.
+    fn schemas(&self) -> BTreeSet<&str> {
+        self.0.values().map(String::as_str).collect()
+    }
+}
+
+/// `name` quoted as a Postgres identifier.
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Points unqualified table names in `transaction` at `schema` until it ends.
+pub(crate) async fn set_search_path(transaction: &DatabaseTransaction, schema: &str) -> Result<(), DbErr> {
+    transaction
+        .execute_raw(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT set_config('search_path', $1, true)",
+            [quote_identifier(schema).into()],
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Creates the schema of every tenant that has none yet and syncs the
+/// entities into each, in a transaction per schema.
+pub(crate) async fn sync_tenant_schemas(database: &DatabaseConnection, tenant_schemas: &TenantSchemas) -> Result<(), DbErr> {
+    for schema in tenant_schemas.schemas() {
+        tracing::debug!(schema, "Syncing tenant schema");
+        let transaction = database.begin().await?;
+        transaction
+            .execute_unprepared(&format!("CREATE SCHEMA IF NOT EXISTS {}", quote_identifier(schema)))
+            .await?;
+        set_search_path(&transaction, schema).await?;
+        database.get_schema_registry("hex-play-database::entities::*").sync(&transaction).await?;
+        transaction.commit().await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use hex_play_core::Error;
+
+    use super::{TenantSchemas, quote_identifier};
+
+    fn tenant_schemas() -> TenantSchemas {
+        TenantSchemas::new(HashMap::from([
+            ("acme".to_string(), "tenant_acme".to_string()),
+            ("globex".to_string(), "tenant_globex".to_string()),
+        ]))
+    }
+
+    // ===================
+    // Tests: TenantSchemas::resolve
+    // ===================
+    #[test]
+    fn test_resolve_returns_schema_of_tenant() {
+        assert_eq!(tenant_schemas().resolve(Some("acme")).unwrap(), Some("tenant_acme"));
+    }
+
+    #[test]
+    fn test_resolve_without_tenant_uses_default_schema() {
+        assert_eq!(tenant_schemas().resolve(None).unwrap(), None);
+        assert_eq!(TenantSchemas::default().resolve(Some("acme")).unwrap(), None);
+    }
+
+    #[test]
+    fn test_resolve_refuses_unknown_tenant() {
+        let error = tenant_schemas().resolve(Some("initech")).unwrap_err();
+
+        assert!(matches!(error, Error::UnknownTenant(tenant) if tenant == "initech"));
+    }
+
+    // ===================
+    // Tests: quote_identifier
+    // ===================
+    #[test]
+    fn test_quote_identifier_escapes_quotes() {
+        assert_eq!(quote_identifier("tenant_acme"), r#""tenant_acme""#);
+        assert_eq!(quote_identifier(r#"a"b"#), r#""a""b""#);
+    }
+}
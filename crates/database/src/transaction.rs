@@ -1,20 +1,62 @@
-use std::any::Any;
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Instant,
+};
 
-use hex_play_core::{Error, repository::Transaction};
-use sea_orm::DatabaseTransaction;
+use hex_play_core::{Error, context::RequestContext, repository::Transaction};
+use sea_orm::{ConnectionTrait as _, DatabaseConnection, DatabaseTransaction, DbBackend, DbErr, Statement, prelude::DateTimeWithTimeZone};
 
-use crate::error::handle_dberr;
+use crate::{
+    error::handle_dberr,
+    pooling::{PoolMode, execute_raw},
+    replica::ReplicaRouting,
+    sql_trace::TracedTransaction,
+};
 
 pub(crate) struct TransactionImpl {
-    pub(crate) transaction: DatabaseTransaction,
+    pub(crate) transaction: TracedTransaction,
+    cancel_guard: CancelGuard,
+    lifecycle: Lifecycle,
+    /// Whose reads to pin to the primary once the transaction commits.
+    pin_on_commit: Option<(ReplicaRouting, RequestContext)>,
 }
 
 impl<'a> TransactionImpl {
-    pub(crate) fn new(transaction: DatabaseTransaction) -> Self {
-        Self { transaction }
+    pub(crate) fn new(transaction: TracedTransaction) -> Self {
+        Self {
+            lifecycle: Lifecycle::begin(&transaction),
+            transaction,
+            cancel_guard: CancelGuard(None),
+            pin_on_commit: None,
+        }
+    }
+
+    /// Wraps a transaction whose in-flight statement is canceled on the
+    /// server if the transaction is dropped without being committed or
+    /// rolled back, e.g. because the request future was abandoned.
+    pub(crate) fn with_cancel_target(transaction: TracedTransaction, target: CancelTarget) -> Self {
+        Self {
+            lifecycle: Lifecycle::begin(&transaction),
+            transaction,
+            cancel_guard: CancelGuard(Some(target)),
+            pin_on_commit: None,
+        }
+    }
+
+    /// Pins the reads of the actor of `context` to the primary with
+    /// `replica_routing` once the transaction commits, and not before, so a
+    /// write that is rolled back pins nothing.
+    pub(crate) fn pinning_reads_on_commit(mut self, replica_routing: ReplicaRouting, context: &RequestContext) -> Self {
+        self.pin_on_commit = Some((replica_routing, context.clone()));
+        self
     }
 
-    pub(crate) fn get_db_transaction(tx: &'a dyn Transaction) -> Result<&'a DatabaseTransaction, Error> {
+    pub(crate) fn get_db_transaction(tx: &'a dyn Transaction) -> Result<&'a TracedTransaction, Error> {
         match tx.as_any().downcast_ref::<TransactionImpl>() {
             Some(transaction) => Ok(&transaction.transaction),
             _ => Err(Error::InvalidTransactionType),
@@ -29,12 +71,174 @@ impl Transaction for TransactionImpl {
     }
 
     async fn commit(self: Box<Self>) -> Result<(), Error> {
-        self.transaction.commit().await.map_err(handle_dberr)?;
+        let Self {
+            transaction,
+            mut cancel_guard,
+            lifecycle,
+            pin_on_commit,
+        } = *self;
+        cancel_guard.disarm();
+        lifecycle.end(Outcome::Commit);
+        transaction.inner.commit().await.map_err(handle_dberr)?;
+        if let Some((replica_routing, context)) = pin_on_commit {
+            replica_routing.record_write(&context, Instant::now());
+        }
         Ok(())
     }
 
     async fn rollback(self: Box<Self>) -> Result<(), Error> {
-        self.transaction.rollback().await.map_err(handle_dberr)?;
+        let Self {
+            transaction,
+            mut cancel_guard,
+            lifecycle,
+            pin_on_commit: _,
+        } = *self;
+        cancel_guard.disarm();
+        lifecycle.end(Outcome::Rollback);
+        transaction.inner.rollback().await.map_err(handle_dberr)?;
         Ok(())
     }
 }
+
+/// The Postgres backend serving a transaction, identified by process id and
+/// transaction start time so a cancel never hits a later transaction that
+/// reuses the same pooled connection.
+pub(crate) struct CancelTarget {
+    database: DatabaseConnection,
+    pool_mode: PoolMode,
+    pid: i32,
+    xact_start: DateTimeWithTimeZone,
+}
+
+impl CancelTarget {
+    /// Reads the backend identity of `transaction`, optionally applying
+    /// `statement_timeout` to it in the same round trip.
+    pub(crate) async fn capture(
+        database: &DatabaseConnection,
+        pool_mode: PoolMode,
+        transaction: &DatabaseTransaction,
+        statement_timeout: Option<std::time::Duration>,
+    ) -> Result<Self, DbErr> {
+        let statement = match statement_timeout {
+            Some(timeout) => Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "SELECT pg_backend_pid() AS pid, now() AS xact_start, set_config('statement_timeout', $1, true)",
+                [format!("{}ms", timeout.as_millis()).into()],
+            ),
+            None => Statement::from_string(DbBackend::Postgres, "SELECT pg_backend_pid() AS pid, now() AS xact_start"),
+        };
+        let row = transaction
+            .query_one_raw(statement)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound("pg_backend_pid".into()))?;
+
+        Ok(Self {
+            database: database.clone(),
+            pool_mode,
+            pid: row.try_get("", "pid")?,
+            xact_start: row.try_get("", "xact_start")?,
+        })
+    }
+
+    async fn cancel(self) {
+        let statement = Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT pg_cancel_backend(pid) FROM pg_stat_activity WHERE pid = $1 AND xact_start = $2 AND state = 'active'",
+            [self.pid.into(), self.xact_start.into()],
+        );
+        match execute_raw(&self.database, self.pool_mode, statement).await {
+            Ok(result) if result.rows_affected() > 0 => tracing::debug!(pid = self.pid, "Canceled abandoned query"),
+            Ok(_) => {}
+            Err(error) => tracing::warn!(pid = self.pid, error = %error, "Failed to cancel abandoned query"),
+        }
+    }
+}
+
+struct CancelGuard(Option<CancelTarget>);
+
+impl CancelGuard {
+    fn disarm(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        let Some(target) = self.0.take() else {
+            return;
+        };
+        // Dropping happens synchronously, so the cancel request is sent from a
+        // separate task on another pooled connection.
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(target.cancel());
+        }
+    }
+}
+
+/// How a transaction ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Commit,
+    Rollback,
+    /// Dropped without a commit or rollback, e.g. a forgotten `.await` or
+    /// an abandoned request. The database rolls it back.
+    Drop,
+}
+
+impl Outcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Commit => "commit",
+            Outcome::Rollback => "rollback",
+            Outcome::Drop => "drop",
+        }
+    }
+}
+
+/// Records how long a transaction was open, how many statements it ran and
+/// how it ended. A transaction dropped without ending is logged with the
+/// backtrace of where it began, captured when `RUST_LIB_BACKTRACE=1` or
+/// `RUST_BACKTRACE=1` is set.
+struct Lifecycle {
+    started: Instant,
+    statements: Arc<AtomicU64>,
+    begin_site: Backtrace,
+    ended: bool,
+}
+
+impl Lifecycle {
+    fn begin(transaction: &TracedTransaction) -> Self {
+        Self {
+            started: Instant::now(),
+            statements: transaction.statement_counter(),
+            begin_site: Backtrace::capture(),
+            ended: false,
+        }
+    }
+
+    fn end(mut self, outcome: Outcome) {
+        self.record(outcome);
+    }
+
+    fn record(&mut self, outcome: Outcome) {
+        self.ended = true;
+        let statements = self.statements.load(Ordering::Relaxed);
+        metrics::histogram!("repo_transaction_duration_seconds", "outcome" => outcome.as_str()).record(self.started.elapsed().as_secs_f64());
+        metrics::histogram!("repo_transaction_statements", "outcome" => outcome.as_str()).record(statements as f64);
+    }
+}
+
+impl Drop for Lifecycle {
+    fn drop(&mut self) {
+        if self.ended {
+            return;
+        }
+        self.record(Outcome::Drop);
+        tracing::warn!(
+            statements = self.statements.load(Ordering::Relaxed),
+            open_ms = self.started.elapsed().as_millis() as u64,
+            begin_site = %self.begin_site,
+            "Transaction dropped without commit or rollback"
+        );
+    }
+}
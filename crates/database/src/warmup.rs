@@ -0,0 +1,60 @@
+//! Preparing the statements of the hot queries on the pooled connections at
+//! startup, so the first requests after a deploy don't pay for preparing
+//! them. Each connection caches the statements it prepared, so every
+//! connection the pool keeps open is warmed up.
+
+use std::time::Instant;
+
+use hex_play_core::{
+    Error,
+    repository::RepositoryService,
+    user::{DEFAULT_PAGE_SIZE, UserId},
+};
+
+/// Runs the hot queries, finding a user by id and listing the first page of
+/// users, once on each of `connections` pooled connections. Their
+/// transactions are all held open until every one has run the queries, so
+/// that the pool hands out a different connection for each.
+pub(crate) async fn warm_up(repository_service: &RepositoryService, connections: u32) -> Result<(), Error> {
+    let started = Instant::now();
+    let mut transactions = Vec::new();
+    for _ in 0..connections {
+        transactions.push(repository_service.repository().begin_read_only().await?);
+    }
+    for transaction in &transactions {
+        repository_service.user_repository().find_by_id(&**transaction, UserId::new(1)).await?;
+        repository_service
+            .user_query_repository()
+            .list_users(&**transaction, None, Some(DEFAULT_PAGE_SIZE), None)
+            .await?;
+    }
+    for transaction in transactions {
+        transaction.rollback().await?;
+    }
+
+    tracing::info!(connections, elapsed_ms = started.elapsed().as_millis() as u64, "Warmed up database connections");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::Database;
+
+    use super::warm_up;
+    use crate::create_repository_service;
+
+    // ===================
+    // Tests: warm_up
+    // ===================
+    #[tokio::test]
+    async fn test_warm_up_runs_hot_queries() {
+        let database = Database::connect("sqlite::memory:").await.unwrap();
+        let svc = create_repository_service(database).await.unwrap();
+
+        warm_up(&svc, 1).await.unwrap();
+
+        // The connection went back to the pool.
+        let tx = svc.repository().begin().await.unwrap();
+        assert_eq!(svc.user_repository().count_users(&*tx).await.unwrap(), 0);
+    }
+}
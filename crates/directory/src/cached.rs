@@ -0,0 +1,107 @@
+//! Directory lookups kept for a while, so repeated lookups of the same
+//! email skip the directory.
+
+use std::{sync::Arc, time::Duration};
+
+use hex_play_core::{
+    Error,
+    cache::QueryCache,
+    types::Email,
+    user::{NewUser, UserDirectoryPort},
+};
+
+/// Tag of the people looked up in the directory.
+const PEOPLE: &str = "directory_people";
+
+/// A directory answering from what `inner` answered within the time to
+/// live, for whoever it knew and whoever it did not. Failed lookups are
+/// not kept.
+pub struct CachedUserDirectory {
+    inner: Arc<dyn UserDirectoryPort>,
+    cache: QueryCache<Option<NewUser>>,
+}
+
+impl CachedUserDirectory {
+    pub fn new(inner: Arc<dyn UserDirectoryPort>, ttl: Duration) -> Self {
+        let cache = QueryCache::new("user_directory");
+        cache.set_ttl(ttl);
+        Self { inner, cache }
+    }
+}
+
+#[async_trait::async_trait]
+impl UserDirectoryPort for CachedUserDirectory {
+    async fn find_by_email(&self, email: &Email) -> Result<Option<NewUser>, Error> {
+        self.cache
+            .get_or_load(email.as_str().to_string(), &[PEOPLE], async || self.inner.find_by_email(email).await)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use hex_play_core::{
+        Error,
+        types::Email,
+        user::{NewUser, UserDirectoryPort},
+    };
+
+    use super::CachedUserDirectory;
+
+    // ===================
+    // Helpers
+    // ===================
+    /// Knows John only, counting its lookups.
+    #[derive(Default)]
+    struct CountingDirectory {
+        lookups: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl UserDirectoryPort for CountingDirectory {
+        async fn find_by_email(&self, email: &Email) -> Result<Option<NewUser>, Error> {
+            self.lookups.fetch_add(1, Ordering::Relaxed);
+            Ok((email.as_str() == "john@example.com").then(|| NewUser::new("John Doe", "john@example.com", 42).unwrap()))
+        }
+    }
+
+    fn email(email: &str) -> Email {
+        Email::new(email).unwrap()
+    }
+
+    // ===================
+    // Tests: find_by_email
+    // ===================
+    #[tokio::test]
+    async fn test_repeated_lookups_skip_the_directory() {
+        let inner = Arc::new(CountingDirectory::default());
+        let directory = CachedUserDirectory::new(inner.clone(), Duration::from_secs(60));
+
+        for _ in 0..2 {
+            assert_eq!(directory.find_by_email(&email("john@example.com")).await.unwrap().unwrap().name, "John Doe");
+            assert!(directory.find_by_email(&email("nobody@example.com")).await.unwrap().is_none());
+        }
+
+        assert_eq!(inner.lookups.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_zero_ttl_asks_the_directory_each_time() {
+        let inner = Arc::new(CountingDirectory::default());
+        let directory = CachedUserDirectory::new(inner.clone(), Duration::ZERO);
+
+        for _ in 0..2 {
+            directory.find_by_email(&email("john@example.com")).await.unwrap();
+        }
+
+        assert_eq!(inner.lookups.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,174 @@
+//! A directory reached over a small JSON API.
+
+use std::time::Duration;
+
+use hex_play_core::{
+    Error, ErrorKind,
+    types::Email,
+    user::{NewUser, UserDirectoryPort},
+};
+use hex_play_utils::secret::Secret;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+
+/// Looks people up with `GET <base_url>/users?email=<email>`, which answers
+/// `404` for no one and otherwise a person as
+/// `{"name": "...", "email": "...", "age": 42}`, the age being optional.
+#[derive(Debug, Clone)]
+pub struct HttpUserDirectory {
+    client: Client,
+    users_url: String,
+    token: Option<Secret>,
+    default_age: i16,
+}
+
+/// A person as the directory describes them.
+#[derive(Debug, Deserialize)]
+struct Person {
+    name: String,
+    email: String,
+    age: Option<i16>,
+}
+
+impl HttpUserDirectory {
+    /// Lookups fail after `timeout`. People without an age are given
+    /// `default_age`.
+    pub fn new(base_url: &str, token: Option<Secret>, timeout: Duration, default_age: i16) -> Result<Self, Error> {
+        let client = Client::builder().timeout(timeout).build().map_err(|error| unavailable(&error))?;
+        Ok(Self {
+            client,
+            users_url: format!("{}/users", base_url.trim_end_matches('/')),
+            token,
+            default_age,
+        })
+    }
+}
+
+/// A lookup that failed, which may succeed later.
+fn unavailable(error: &impl ToString) -> Error {
+    Error::Remote {
+        kind: ErrorKind::Unavailable,
+        message: format!("User directory unavailable: {}", error.to_string()),
+    }
+}
+
+#[async_trait::async_trait]
+impl UserDirectoryPort for HttpUserDirectory {
+    #[tracing::instrument(level = "trace", skip(self, email), fields(email = %email.redacted()))]
+    async fn find_by_email(&self, email: &Email) -> Result<Option<NewUser>, Error> {
+        let mut request = self.client.get(&self.users_url).query(&[("email", email.as_str())]);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token.expose());
+        }
+        let response = request.send().await.map_err(|error| unavailable(&error))?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let person: Person = response
+            .error_for_status()
+            .map_err(|error| unavailable(&error))?
+            .json()
+            .await
+            .map_err(|error| unavailable(&error))?;
+
+        NewUser::new(person.name, person.email, person.age.unwrap_or(self.default_age))
+            .map(Some)
+            .map_err(|error| Error::Remote {
+                kind: ErrorKind::Internal,
+                message: format!("User directory returned an invalid person: {error}"),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::{
+        Json, Router,
+        extract::Query,
+        http::{HeaderMap, StatusCode},
+        response::{IntoResponse as _, Response},
+        routing::get,
+    };
+    use hex_play_core::{
+        Error, ErrorKind,
+        types::Email,
+        user::{NewUser, UserDirectoryPort as _},
+    };
+    use hex_play_utils::secret::Secret;
+    use serde::Deserialize;
+
+    use super::HttpUserDirectory;
+
+    // ===================
+    // Helpers
+    // ===================
+    #[derive(Deserialize)]
+    struct Lookup {
+        email: String,
+    }
+
+    /// Knows John, with an age, and Jane, without, for callers with the
+    /// token.
+    async fn users(headers: HeaderMap, Query(lookup): Query<Lookup>) -> Response {
+        if headers.get("authorization").and_then(|value| value.to_str().ok()) != Some("Bearer s3cret") {
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+        match lookup.email.as_str() {
+            "john@example.com" => Json(serde_json::json!({ "name": "John Doe", "email": "john@example.com", "age": 42 })).into_response(),
+            "jane@example.com" => Json(serde_json::json!({ "name": "Jane Doe", "email": "jane@example.com" })).into_response(),
+            _ => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    async fn directory(token: &str) -> HttpUserDirectory {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, Router::new().route("/api/users", get(users))).await });
+        HttpUserDirectory::new(&url, Some(Secret::new(token)), Duration::from_secs(5), 18).unwrap()
+    }
+
+    async fn find(directory: &HttpUserDirectory, email: &str) -> Result<Option<NewUser>, Error> {
+        directory.find_by_email(&Email::new(email).unwrap()).await
+    }
+
+    // ===================
+    // Tests: find_by_email
+    // ===================
+    #[tokio::test]
+    async fn test_find_by_email_returns_person() {
+        let directory = directory("s3cret").await;
+
+        let user = find(&directory, "john@example.com").await.unwrap().unwrap();
+
+        assert_eq!(user.name, "John Doe");
+        assert_eq!(user.email.as_str(), "john@example.com");
+        assert_eq!(user.age.value(), 42);
+    }
+
+    #[tokio::test]
+    async fn test_find_by_email_gives_default_age() {
+        let directory = directory("s3cret").await;
+
+        let user = find(&directory, "jane@example.com").await.unwrap().unwrap();
+
+        assert_eq!(user.age.value(), 18);
+    }
+
+    #[tokio::test]
+    async fn test_find_by_email_not_found() {
+        let directory = directory("s3cret").await;
+
+        assert!(find(&directory, "nobody@example.com").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_find_by_email_fails_as_unavailable() {
+        let directory = directory("wrong").await;
+
+        let error = find(&directory, "john@example.com").await.unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::Unavailable);
+    }
+}
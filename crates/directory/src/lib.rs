@@ -0,0 +1,69 @@
+//! User directory adapters for the [`UserDirectoryPort`] port. Directories
+//! are reached over HTTP; LDAP directories need a gateway speaking the
+//! protocol of [`HttpUserDirectory`] in front of them. Lookups are kept
+//! for a while by [`CachedUserDirectory`].
+
+use std::{sync::Arc, time::Duration};
+
+use hex_play_core::{
+    Error,
+    user::{NoUserDirectory, UserDirectoryPort},
+};
+use hex_play_utils::secret::Secret;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+mod cached;
+mod http;
+
+pub use cached::CachedUserDirectory;
+pub use http::HttpUserDirectory;
+
+/// Milliseconds a lookup may take when `timeout_ms` is unset.
+const DEFAULT_TIMEOUT_MS: u64 = 2000;
+/// Milliseconds lookups are kept when `cache_ttl_ms` is unset.
+const DEFAULT_CACHE_TTL_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct DirectoryConfig {
+    /// (optional) Base URL of the directory's HTTP API, e.g.
+    /// `HPLAY__DIRECTORY__URL=https://directory.example.com/api`. People are
+    /// looked up with `GET <url>/users?email=<email>` when they first sign
+    /// in, and added as the directory describes them. Unset adds them as
+    /// their sign-in describes them.
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// (optional) Bearer token sent with each lookup.
+    #[serde(default)]
+    pub token: Option<Secret>,
+
+    /// (optional) Milliseconds a lookup may take before it fails. Defaults
+    /// to 2000.
+    #[serde(default)]
+    #[schemars(extend("default" = DEFAULT_TIMEOUT_MS))]
+    pub timeout_ms: Option<u64>,
+
+    /// (optional) Age of people the directory has no age for. Defaults to 0.
+    #[serde(default)]
+    pub default_age: i16,
+
+    /// (optional) Milliseconds the answer to a lookup is kept, whether the
+    /// directory knew the person or not. Defaults to 60000; 0 asks the
+    /// directory every time.
+    #[serde(default)]
+    #[schemars(extend("default" = DEFAULT_CACHE_TTL_MS))]
+    pub cache_ttl_ms: Option<u64>,
+}
+
+/// Creates the directory `config` points at, or [`NoUserDirectory`]
+/// without one.
+pub fn create_user_directory(config: &DirectoryConfig) -> Result<Arc<dyn UserDirectoryPort>, Error> {
+    let Some(url) = config.url.as_deref() else {
+        return Ok(Arc::new(NoUserDirectory));
+    };
+    let timeout = Duration::from_millis(config.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
+    let directory = HttpUserDirectory::new(url, config.token.clone(), timeout, config.default_age)?;
+    let ttl = Duration::from_millis(config.cache_ttl_ms.unwrap_or(DEFAULT_CACHE_TTL_MS));
+    Ok(Arc::new(CachedUserDirectory::new(Arc::new(directory), ttl)))
+}
@@ -0,0 +1,34 @@
+nav-books = Bücher
+nav-users = Benutzer
+nav-dashboard = Übersicht
+nav-settings = Einstellungen
+nav-user = Konto
+
+theme-toggle = Design: { $theme ->
+    [light] Hell
+    [dark] Dunkel
+   *[system] System
+}
+theme-toggle-title = Design wechseln
+
+login-title = Anmelden
+login-sso = Mit Single Sign-On anmelden
+login-unavailable = Zum Anmelden wird ein Single-Sign-On-Anbieter benötigt, und keiner ist eingerichtet.
+
+users-title = Benutzer
+users-id = Id
+users-name = Name
+users-email = E-Mail
+users-age = Alter
+
+dashboard-title = Übersicht
+dashboard-total = { $count ->
+    [one] Ein Benutzer
+   *[other] { $count } Benutzer
+}
+dashboard-status = Aktivität
+dashboard-status-active = Aktiv in den letzten 30 Tagen
+dashboard-status-inactive = Inaktiv
+dashboard-status-never-seen = Nie gesehen
+dashboard-ages = Alter
+dashboard-signups = Registrierungen der letzten 30 Tage
@@ -0,0 +1,34 @@
+nav-books = Books
+nav-users = Users
+nav-dashboard = Dashboard
+nav-settings = Settings
+nav-user = User
+
+theme-toggle = Theme: { $theme ->
+    [light] Light
+    [dark] Dark
+   *[system] System
+}
+theme-toggle-title = Switch theme
+
+login-title = Sign in
+login-sso = Sign in with single sign-on
+login-unavailable = Signing in needs a single sign-on provider, and none is set up.
+
+users-title = Users
+users-id = Id
+users-name = Name
+users-email = Email
+users-age = Age
+
+dashboard-title = Dashboard
+dashboard-total = { $count ->
+    [one] One user
+   *[other] { $count } users
+}
+dashboard-status = Activity
+dashboard-status-active = Active in the last 30 days
+dashboard-status-inactive = Inactive
+dashboard-status-never-seen = Never seen
+dashboard-ages = Ages
+dashboard-signups = Signups in the last 30 days
@@ -1,12 +1,14 @@
 use dioxus::prelude::*;
 
-use crate::{Route, components::NavBar};
+use crate::{Route, components::NavBar, theme::Theme};
 
 #[component]
 pub(crate) fn AppLayout() -> Element {
+    let theme: Signal<Theme> = use_context();
+
     rsx! {
         document::Stylesheet { href: asset!("/assets/tailwind.css") }
-        div { class: "min-h-screen flex flex-col bg-gray-50 text-gray-900",
+        div { class: "app-shell min-h-screen flex flex-col", "data-theme": theme().as_str(),
             NavBar {}
             main { class: "flex-1 flex overflow-hidden",
                 Outlet::<Route> {}
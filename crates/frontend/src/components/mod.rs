@@ -2,10 +2,14 @@ mod app_layout;
 mod book_table;
 mod detail_panel;
 mod nav_bar;
+mod require_login;
+mod theme_toggle;
 mod tree_explorer;
 
 pub(crate) use app_layout::AppLayout;
 pub(crate) use book_table::BookTable;
 pub(crate) use detail_panel::DetailPanel;
 pub(crate) use nav_bar::NavBar;
+pub(crate) use require_login::RequireLogin;
+pub(crate) use theme_toggle::ThemeToggle;
 pub(crate) use tree_explorer::TreeExplorer;
@@ -1,9 +1,11 @@
 use dioxus::prelude::*;
 
-use crate::Route;
+use crate::{Route, components::ThemeToggle, i18n::I18n};
 
 #[component]
 pub(crate) fn NavBar() -> Element {
+    let i18n: I18n = use_context();
+
     rsx! {
         nav { class: "bg-indigo-700 text-white px-6 py-3 flex items-center justify-between shadow",
             div { class: "flex items-center gap-6",
@@ -11,12 +13,19 @@ pub(crate) fn NavBar() -> Element {
                     "HexPlay"
                 }
                 Link { to: Route::BooksPage {}, class: "text-sm hover:text-indigo-200",
-                    "Books"
+                    {i18n.t("nav-books")}
+                }
+                Link { to: Route::UsersPage {}, class: "text-sm hover:text-indigo-200",
+                    {i18n.t("nav-users")}
+                }
+                Link { to: Route::DashboardPage {}, class: "text-sm hover:text-indigo-200",
+                    {i18n.t("nav-dashboard")}
                 }
             }
             div { class: "flex items-center gap-4",
-                button { class: "text-sm hover:text-indigo-200", "Settings" }
-                button { class: "text-sm hover:text-indigo-200", "User" }
+                ThemeToggle {}
+                button { class: "text-sm hover:text-indigo-200", {i18n.t("nav-settings")} }
+                button { class: "text-sm hover:text-indigo-200", {i18n.t("nav-user")} }
             }
         }
     }
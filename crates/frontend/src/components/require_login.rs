@@ -0,0 +1,24 @@
+use dioxus::prelude::*;
+
+use crate::{Route, user::session_user};
+
+/// Layout for routes only signed-in users may see. Anyone else is sent to
+/// the login page, which brings them back here afterwards.
+#[component]
+pub(crate) fn RequireLogin() -> Element {
+    let session = use_server_future(session_user)?;
+    let route = use_route::<Route>();
+    let signed_in = matches!(&*session.read(), Some(Ok(Some(_))));
+
+    use_effect(move || {
+        if matches!(&*session.read(), Some(Ok(None))) {
+            navigator().replace(Route::LoginPage { redirect: route.to_string() });
+        }
+    });
+
+    if signed_in {
+        rsx! { Outlet::<Route> {} }
+    } else {
+        rsx! {}
+    }
+}
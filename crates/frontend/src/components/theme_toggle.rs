@@ -0,0 +1,24 @@
+use dioxus::prelude::*;
+use fluent::FluentArgs;
+
+use crate::{
+    i18n::I18n,
+    theme::{Theme, save_theme},
+};
+
+#[component]
+pub(crate) fn ThemeToggle() -> Element {
+    let theme: Signal<Theme> = use_context();
+    let i18n: I18n = use_context();
+    let mut args = FluentArgs::new();
+    args.set("theme", theme().as_str());
+
+    rsx! {
+        button {
+            class: "text-sm hover:text-indigo-200",
+            title: i18n.t("theme-toggle-title"),
+            onclick: move |_| save_theme(theme, theme().next()),
+            {i18n.t_args("theme-toggle", &args)}
+        }
+    }
+}
@@ -7,6 +7,12 @@ use hex_play_core::Error as CoreError;
 pub enum FrontendError {
     #[error("Dioxus error: {0}")]
     DioxusError(String),
+
+    #[error("Network error: {0}")]
+    Network(String),
+
+    #[error("OIDC error: {0}")]
+    Oidc(String),
 }
 
 impl From<FrontendError> for CoreError {
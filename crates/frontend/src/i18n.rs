@@ -0,0 +1,82 @@
+//! Fluent translations of the UI text. Each language has a catalog in
+//! `locales/<code>.ftl`, compiled into the binary; the language is
+//! negotiated from the request's `Accept-Language` while server rendering.
+
+use std::rc::Rc;
+
+use dioxus::prelude::*;
+use fluent::{FluentArgs, FluentBundle, FluentResource};
+use unic_langid::LanguageIdentifier;
+
+const FALLBACK: &str = "en";
+
+/// Catalog source for a language code, `None` without a catalog.
+fn catalog(code: &str) -> Option<&'static str> {
+    match code {
+        "en" => Some(include_str!("../locales/en.ftl")),
+        "de" => Some(include_str!("../locales/de.ftl")),
+        _ => None,
+    }
+}
+
+/// Translations for one language, shared as a context.
+#[derive(Clone)]
+pub(crate) struct I18n(Rc<FluentBundle<FluentResource>>);
+
+impl I18n {
+    fn new(code: &str) -> Self {
+        let (code, source) = catalog(code).map_or((FALLBACK, catalog(FALLBACK).unwrap_or_default()), |source| (code, source));
+        let language: LanguageIdentifier = code.parse().unwrap_or_default();
+
+        let mut bundle = FluentBundle::new(vec![language]);
+        // The isolation marks around arguments only matter for mixed
+        // right-to-left text.
+        bundle.set_use_isolating(false);
+        let resource = FluentResource::try_new(source.to_string()).unwrap_or_else(|(resource, _)| resource);
+        let _ = bundle.add_resource(resource);
+        Self(Rc::new(bundle))
+    }
+
+    /// The message `id`, or `id` itself when the catalog lacks it.
+    pub(crate) fn t(&self, id: &str) -> String {
+        self.format(id, None)
+    }
+
+    /// The message `id` with `args` filled in.
+    pub(crate) fn t_args(&self, id: &str, args: &FluentArgs) -> String {
+        self.format(id, Some(args))
+    }
+
+    fn format(&self, id: &str, args: Option<&FluentArgs>) -> String {
+        let Some(pattern) = self.0.get_message(id).and_then(|message| message.value()) else {
+            return id.to_string();
+        };
+        let mut errors = Vec::new();
+        self.0.format_pattern(pattern, args, &mut errors).into_owned()
+    }
+}
+
+/// Language code negotiated from the request's `Accept-Language`.
+#[get("/api/language", headers: axum::http::HeaderMap)]
+#[tracing::instrument(level = "trace", skip(headers))]
+async fn preferred_language() -> Result<String> {
+    use hex_play_core::i18n::{Language, negotiate_locale};
+
+    let language = headers
+        .get(axum::http::header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(negotiate_locale)
+        .and_then(|locale| Language::from_tag(&locale))
+        .unwrap_or_default();
+    Ok(language.code().to_string())
+}
+
+/// Provides the [`I18n`] context for the negotiated language.
+pub(crate) fn use_i18n_provider() -> Result<I18n, RenderError> {
+    let language = use_server_future(preferred_language)?;
+    let code = match &*language.read() {
+        Some(Ok(code)) => code.clone(),
+        _ => FALLBACK.to_string(),
+    };
+    Ok(use_context_provider(|| I18n::new(&code)))
+}
@@ -1,7 +1,10 @@
 use dioxus::prelude::*;
 
 mod components;
+mod i18n;
 pub(crate) mod routes;
+mod stats;
+mod theme;
 pub(crate) mod user;
 
 #[cfg(feature = "web")]
@@ -21,6 +24,7 @@ fn default_listen_port() -> u16 {
 }
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "server", derive(schemars::JsonSchema))]
 pub struct FrontendConfig {
     /// (required) IP address where the server should listen.
     /// e.g. 0.0.0.0
@@ -31,6 +35,19 @@ pub struct FrontendConfig {
     /// e.g. 8080
     #[serde(default = "default_listen_port")]
     pub listen_port: u16,
+
+    /// (optional) Path prefix the frontend is served under, for a reverse
+    /// proxy that routes by path. Asset URLs in the client bundle come from
+    /// the `--base-path` it was built with, so the two must match.
+    /// e.g. /app
+    #[serde(default)]
+    pub base_path: Option<String>,
+
+    /// (optional) Directory of static assets produced by `dx bundle`.
+    /// Defaults to `public` next to the executable.
+    /// e.g. /srv/hex-play/public
+    #[serde(default)]
+    pub assets_dir: Option<String>,
 }
 
 impl Default for FrontendConfig {
@@ -38,44 +55,105 @@ impl Default for FrontendConfig {
         Self {
             listen_ip: default_listen_ip(),
             listen_port: default_listen_port(),
+            base_path: None,
+            assets_dir: None,
         }
     }
 }
 
+#[cfg(feature = "server")]
+fn default_oidc_scopes() -> Vec<String> {
+    vec!["email".to_string(), "profile".to_string()]
+}
+
+/// Ways to sign in besides an email address and token.
+#[cfg(feature = "server")]
+#[derive(Debug, Default, Deserialize, schemars::JsonSchema)]
+pub struct AuthConfig {
+    /// (optional) Sign in through an OpenID Connect provider.
+    #[serde(default)]
+    pub oidc: Option<OidcConfig>,
+}
+
+#[cfg(feature = "server")]
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct OidcConfig {
+    /// (required) Issuer whose discovery document is read and whose ID
+    /// tokens are accepted.
+    /// e.g. https://accounts.example.com
+    pub issuer_url: String,
+
+    /// (required) Client id registered with the provider.
+    /// e.g. hex-play
+    pub client_id: String,
+
+    /// (optional) Client secret registered with the provider, left out for
+    /// public clients.
+    /// e.g. `HPLAY__AUTH__OIDC__CLIENT_SECRET_FILE=/run/secrets/oidc`
+    #[serde(default)]
+    pub client_secret: Option<hex_play_utils::secret::Secret>,
+
+    /// (required) Where the provider sends the browser back to, the
+    /// frontend's `/auth/oidc/callback`.
+    /// e.g. https://hex-play.example.com/auth/oidc/callback
+    pub redirect_url: String,
+
+    /// (optional) Scopes asked for besides `openid`. Defaults to
+    /// `email,profile`.
+    /// e.g. `HPLAY__AUTH__OIDC__SCOPES=email,profile,groups`
+    #[serde(default = "default_oidc_scopes")]
+    pub scopes: Vec<String>,
+}
+
 #[cfg(feature = "server")]
 mod error;
+#[cfg(feature = "server")]
+mod oidc;
 
 #[cfg(feature = "server")]
 pub use error::FrontendError;
 
 #[cfg(feature = "server")]
 pub mod server {
-    use std::{collections::HashSet, fmt::Debug, sync::Arc};
+    use std::{
+        collections::HashSet,
+        fmt::Debug,
+        path::{Path, PathBuf},
+        sync::Arc,
+    };
 
     use axum::{
-        Extension,
-        http::{HeaderName, Request},
+        Extension, Router,
+        body::Body,
+        http::{HeaderName, HeaderValue, Request, header::CACHE_CONTROL},
+        middleware::{Next, from_fn},
+        response::Response,
     };
     use axum_session::{DatabaseError, DatabasePool, SessionConfig, SessionLayer, SessionStore};
     use axum_session_auth::{AuthConfig, AuthSessionLayer, Authentication, HasPermission};
     use chrono::DateTime;
+    use dioxus::server::{DioxusRouterExt, ServeConfig};
     use hex_play_core::{
-        CoreServices,
-        session::{NewSession, SessionService},
-        user::UserId,
+        CoreServices, Error,
+        context::RequestContext,
+        session::{NewSession, SessionId, SessionService},
+        user::{UserId, UserService},
     };
     use serde::{Deserialize, Serialize};
+    use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
     use tower::ServiceBuilder;
     use tower_http::{
         request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+        services::ServeDir,
         trace::TraceLayer,
     };
 
-    use crate::{FrontendConfig, HexPlayFrontend};
+    use crate::{FrontendConfig, FrontendError, HexPlayFrontend, oidc::OidcLogin};
 
     #[derive(Clone)]
     pub(crate) struct BackendSessionPool {
         session_service: Arc<dyn SessionService>,
+        user_service: Arc<dyn UserService>,
     }
 
     impl Debug for BackendSessionPool {
@@ -101,7 +179,7 @@ pub mod server {
         #[tracing::instrument(level = "trace", skip(self))]
         async fn store(&self, id: &str, session: &str, expires: i64, _table_name: &str) -> Result<(), DatabaseError> {
             let expires_at = DateTime::from_timestamp(expires, 0).ok_or_else(|| DatabaseError::GenericInsertError(format!("invalid timestamp: {expires}")))?;
-            let new_session = NewSession::new(id, session, expires_at).map_err(|e| DatabaseError::GenericInsertError(e.to_string()))?;
+            let new_session = NewSession::new(SessionId::new(id), session, expires_at).map_err(|e| DatabaseError::GenericInsertError(e.to_string()))?;
             self.session_service
                 .store(new_session)
                 .await
@@ -112,7 +190,7 @@ pub mod server {
         #[tracing::instrument(level = "trace", skip(self))]
         async fn load(&self, id: &str, _table_name: &str) -> Result<Option<String>, DatabaseError> {
             self.session_service
-                .load(id)
+                .load(&SessionId::new(id))
                 .await
                 .map(|opt| opt.map(|s| s.session))
                 .map_err(|e| DatabaseError::GenericSelectError(e.to_string()))
@@ -121,7 +199,7 @@ pub mod server {
         #[tracing::instrument(level = "trace", skip(self))]
         async fn delete_one_by_id(&self, id: &str, _table_name: &str) -> Result<(), DatabaseError> {
             self.session_service
-                .delete_by_id(id)
+                .delete_by_id(&SessionId::new(id))
                 .await
                 .map_err(|e| DatabaseError::GenericDeleteError(e.to_string()))
         }
@@ -129,7 +207,7 @@ pub mod server {
         #[tracing::instrument(level = "trace", skip(self))]
         async fn exists(&self, id: &str, _table_name: &str) -> Result<bool, DatabaseError> {
             self.session_service
-                .exists(id)
+                .exists(&SessionId::new(id))
                 .await
                 .map_err(|e| DatabaseError::GenericSelectError(e.to_string()))
         }
@@ -139,6 +217,7 @@ pub mod server {
             self.session_service
                 .delete_by_expiry()
                 .await
+                .map(|ids| ids.into_iter().map(String::from).collect())
                 .map_err(|e| DatabaseError::GenericDeleteError(e.to_string()))
         }
 
@@ -155,6 +234,7 @@ pub mod server {
             self.session_service
                 .get_ids()
                 .await
+                .map(|ids| ids.into_iter().map(String::from).collect())
                 .map_err(|e| DatabaseError::GenericSelectError(e.to_string()))
         }
 
@@ -164,12 +244,18 @@ pub mod server {
         }
     }
 
-    /// Stub user type for session authentication.
-    /// TODO: Replace with a real user backed by CoreServices.
+    /// Session id of visitors who have not signed in. No user has it, as
+    /// user ids start at 1.
+    pub(crate) const ANONYMOUS_USER_ID: UserId = UserId::new(0);
+
+    /// Permission every signed-in user has, needed to manage users.
+    pub(crate) const USERS_VIEW: &str = "Users::View";
+
+    /// The user a session belongs to, loaded from CoreServices.
     #[derive(Clone, Debug, Serialize, Deserialize)]
     pub(crate) struct AuthUser {
-        id: UserId,
-        anonymous: bool,
+        pub id: UserId,
+        pub anonymous: bool,
         pub username: String,
         pub permissions: HashSet<String>,
     }
@@ -177,9 +263,9 @@ pub mod server {
     impl Default for AuthUser {
         fn default() -> Self {
             Self {
-                id: 1,
+                id: ANONYMOUS_USER_ID,
                 anonymous: true,
-                username: "1".into(),
+                username: "anonymous".into(),
                 permissions: HashSet::new(),
             }
         }
@@ -187,17 +273,23 @@ pub mod server {
 
     #[async_trait::async_trait]
     impl Authentication<Self, UserId, BackendSessionPool> for AuthUser {
-        #[tracing::instrument(level = "trace", skip(_pool))]
-        async fn load_user(userid: UserId, _pool: Option<&BackendSessionPool>) -> Result<Self, anyhow::Error> {
-            let mut permissions = HashSet::new();
-            if userid == 2 {
-                permissions.insert("Admin::View".into());
+        #[tracing::instrument(level = "trace", skip(pool))]
+        async fn load_user(userid: UserId, pool: Option<&BackendSessionPool>) -> Result<Self, anyhow::Error> {
+            if userid == ANONYMOUS_USER_ID {
+                return Ok(Self::default());
             }
+            let pool = pool.ok_or_else(|| anyhow::anyhow!("no session pool to load user {userid} from"))?;
+            let user = pool
+                .user_service
+                .find_by_id(&RequestContext::internal(), userid)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("user {userid} no longer exists"))?;
+
             Ok(Self {
-                id: userid,
-                anonymous: userid == 1,
-                username: format!("{userid}"),
-                permissions,
+                id: user.id,
+                anonymous: false,
+                username: user.name,
+                permissions: HashSet::from([USERS_VIEW.to_string()]),
             })
         }
 
@@ -227,57 +319,176 @@ pub mod server {
 
     const REQUEST_ID_HEADER: &str = "x-request-id";
 
-    pub fn launch_server_frontend(config: &FrontendConfig, core_services: Arc<CoreServices>) {
-        let listen_ip = config.listen_ip.clone();
-        let listen_port = config.listen_port;
-        std::thread::spawn(move || {
-            // SAFETY: Called at the start of a dedicated thread before any other work,
-            // so no other threads are reading these env vars concurrently.
-            unsafe {
-                std::env::set_var("IP", &listen_ip);
-                std::env::set_var("PORT", listen_port.to_string());
-            }
-            tracing::info!("Frontend started on {listen_ip}:{listen_port}");
-            dioxus::serve(|| {
-                let core_services = core_services.clone();
-                let backend_pool = BackendSessionPool {
-                    session_service: core_services.session_service.clone(),
-                };
-                let session_config = SessionConfig::default();
-                let auth_config = AuthConfig::<UserId>::default().with_anonymous_user_id(Some(1));
-                async move {
-                    let x_request_id = HeaderName::from_static(REQUEST_ID_HEADER);
-
-                    let middleware = ServiceBuilder::new()
-                        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
-                        .layer(TraceLayer::new_for_http().make_span_with(|request: &Request<_>| {
-                            let request_id = request
-                                .headers()
-                                .get(REQUEST_ID_HEADER)
-                                .map(|v| v.to_str().unwrap_or_default())
-                                .unwrap_or_default();
-
-                            tracing::info_span!(
-                                "http",
-                                request_id = ?request_id,
-                            )
-                        }))
-                        .layer(PropagateRequestIdLayer::new(x_request_id))
-                        .layer(SessionLayer::new(
-                            SessionStore::<BackendSessionPool>::new(Some(backend_pool.clone()), session_config).await?,
-                        ))
-                        .layer(AuthSessionLayer::<AuthUser, UserId, BackendSessionPool, BackendSessionPool>::new(Some(backend_pool)).with_config(auth_config));
-
-                    let router = dioxus::server::router(HexPlayFrontend).layer(Extension(core_services)).layer(middleware);
-                    Ok(router)
-                }
-            })
-        });
+    /// Serves the frontend until shutdown is requested, then lets the
+    /// requests in flight finish.
+    pub struct FrontendSubsystem {
+        listen_ip: String,
+        listen_port: u16,
+        base_path: Option<String>,
+        assets_dir: PathBuf,
+        oidc_login: Option<Arc<OidcLogin>>,
+        core_services: Arc<CoreServices>,
+    }
+
+    pub fn create_frontend_subsystem(
+        config: &FrontendConfig,
+        auth: &crate::AuthConfig,
+        core_services: Arc<CoreServices>,
+    ) -> Result<FrontendSubsystem, FrontendError> {
+        let base_path = config
+            .base_path
+            .as_deref()
+            .map(|path| path.trim_matches('/').to_string())
+            .filter(|path| !path.is_empty());
+        let oidc_login = auth
+            .oidc
+            .as_ref()
+            .map(|oidc| OidcLogin::new(oidc, base_path.as_deref()).map(Arc::new))
+            .transpose()?;
+
+        Ok(FrontendSubsystem {
+            listen_ip: config.listen_ip.clone(),
+            listen_port: config.listen_port,
+            base_path,
+            assets_dir: config.assets_dir.as_ref().map_or_else(default_assets_dir, PathBuf::from),
+            oidc_login,
+            core_services,
+        })
+    }
+
+    /// Where `dx bundle` puts the assets: `public` next to the executable.
+    fn default_assets_dir() -> PathBuf {
+        std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join("public")))
+            .unwrap_or_else(|| PathBuf::from("public"))
+    }
+
+    impl IntoSubsystem<Error> for FrontendSubsystem {
+        async fn run(self, subsys: &mut SubsystemHandle) -> Result<(), Error> {
+            let router = router(self.core_services, &self.assets_dir, self.base_path.as_deref(), self.oidc_login).await?;
+            let listener = tokio::net::TcpListener::bind((self.listen_ip.as_str(), self.listen_port))
+                .await
+                .map_err(|e| FrontendError::Network(format!("{}:{}: {}", self.listen_ip, self.listen_port, e)))?;
+            tracing::info!(
+                "Frontend started on {}:{}/{}",
+                self.listen_ip,
+                self.listen_port,
+                self.base_path.as_deref().unwrap_or_default()
+            );
+
+            // axum needs a 'static shutdown signal, which a borrowed
+            // `on_shutdown_requested` is not.
+            axum::serve(listener, router)
+                .with_graceful_shutdown(subsys.create_cancellation_token().cancelled_owned())
+                .await
+                .map_err(|e| FrontendError::Network(e.to_string()))?;
+            tracing::info!("FrontendSubsystem shut down");
+
+            Ok(())
+        }
+    }
+
+    /// The Dioxus app behind the static assets: a request no asset matches
+    /// is server rendered or handled by a server function. With an OIDC
+    /// provider set up, `/auth/oidc/*` signs in through it.
+    async fn router(
+        core_services: Arc<CoreServices>,
+        assets_dir: &Path,
+        base_path: Option<&str>,
+        oidc_login: Option<Arc<OidcLogin>>,
+    ) -> Result<Router, FrontendError> {
+        let backend_pool = BackendSessionPool {
+            session_service: core_services.session_service.clone(),
+            user_service: core_services.user_service.clone(),
+        };
+        let session_config = SessionConfig::default();
+        let auth_config = AuthConfig::<UserId>::default().with_anonymous_user_id(Some(ANONYMOUS_USER_ID));
+        let session_store = SessionStore::<BackendSessionPool>::new(Some(backend_pool.clone()), session_config)
+            .await
+            .map_err(|e| FrontendError::DioxusError(e.to_string()))?;
+        let x_request_id = HeaderName::from_static(REQUEST_ID_HEADER);
+
+        let middleware = ServiceBuilder::new()
+            .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+            .layer(TraceLayer::new_for_http().make_span_with(|request: &Request<_>| {
+                let request_id = request
+                    .headers()
+                    .get(REQUEST_ID_HEADER)
+                    .map(|v| v.to_str().unwrap_or_default())
+                    .unwrap_or_default();
+
+                tracing::info_span!(
+                    "http",
+                    request_id = ?request_id,
+                )
+            }))
+            .layer(PropagateRequestIdLayer::new(x_request_id))
+            .layer(SessionLayer::new(session_store))
+            .layer(AuthSessionLayer::<AuthUser, UserId, BackendSessionPool, BackendSessionPool>::new(Some(backend_pool)).with_config(auth_config));
+
+        let app = Router::new().serve_api_application(ServeConfig::new(), HexPlayFrontend);
+        // index.html is only the template for server rendering.
+        let assets = ServeDir::new(assets_dir)
+            .append_index_html_on_directories(false)
+            .precompressed_br()
+            .call_fallback_on_method_not_allowed(true)
+            .fallback(app);
+        let mut router = Router::new().fallback_service(assets).layer(from_fn(cache_control));
+        if let Some(oidc_login) = &oidc_login {
+            router = router.merge(crate::oidc::get_routes(oidc_login.clone()));
+        }
+        if let Some(base_path) = base_path {
+            router = Router::new().nest(&format!("/{base_path}"), router);
+        }
+
+        Ok(router
+            .layer(from_fn(record_activity))
+            .layer(Extension(oidc_login))
+            .layer(Extension(core_services))
+            .layer(middleware))
+    }
+
+    /// Notes that the signed-in user behind a request was seen. The write is
+    /// buffered, so this never waits on the database.
+    async fn record_activity(request: Request<Body>, next: Next) -> Response {
+        let user_id = request
+            .extensions()
+            .get::<AuthSession>()
+            .and_then(|auth| auth.current_user.as_ref())
+            .filter(|user| !user.anonymous)
+            .map(|user| user.id);
+        if let (Some(user_id), Some(core_services)) = (user_id, request.extensions().get::<Arc<CoreServices>>()) {
+            core_services.activity_service.record_activity(user_id);
+        }
+        next.run(request).await
+    }
+
+    const IMMUTABLE: HeaderValue = HeaderValue::from_static("public, max-age=31536000, immutable");
+
+    /// Lets browsers cache assets with a content hash in their name, such as
+    /// `main-dxh1a2b3c.js`, for good: a changed asset gets a new name.
+    async fn cache_control(request: Request<Body>, next: Next) -> Response {
+        let immutable = is_hashed(request.uri().path());
+        let mut response = next.run(request).await;
+        if immutable && response.status().is_success() {
+            response.headers_mut().insert(CACHE_CONTROL, IMMUTABLE);
+        }
+        response
+    }
+
+    /// Whether a file name carries the content hash `dx` adds, `-dxh`
+    /// followed by hex digits before the extension.
+    fn is_hashed(path: &str) -> bool {
+        path.rsplit_once("-dxh").is_some_and(|(_, hash)| {
+            let hash = hash.split('.').next().unwrap_or_default();
+            !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit())
+        })
     }
 }
 
-use components::AppLayout;
-use routes::{BooksPage, Home};
+use components::{AppLayout, RequireLogin};
+use routes::{BooksPage, DashboardPage, Home, LoginPage, UsersPage};
 use serde::Deserialize;
 
 #[derive(Routable, Clone, PartialEq)]
@@ -288,9 +499,19 @@ enum Route {
         Home {},
         #[route("/books")]
         BooksPage {},
+        #[route("/login?:redirect")]
+        LoginPage { redirect: String },
+        #[layout(RequireLogin)]
+            #[route("/users")]
+            UsersPage {},
+            #[route("/dashboard")]
+            DashboardPage {},
 }
 
 #[component]
 fn HexPlayFrontend() -> Element {
+    theme::use_theme_provider()?;
+    i18n::use_i18n_provider()?;
+
     rsx! { Router::<Route> {} }
 }
@@ -0,0 +1,339 @@
+//! Sign-in through an OpenID Connect provider.
+//!
+//! `/auth/oidc/login` sends the browser to the provider with a fresh state,
+//! nonce and PKCE challenge, which wait in the session.
+//! `/auth/oidc/callback` trades the code the provider sends back for an ID
+//! token, checks its signature, issuer, audience, expiry and nonce, and
+//! signs in the user with the token's email, which the token must say is
+//! verified. Someone signing in for the first time is added as a user; a
+//! user with the same email is signed in as is.
+//!
+//! The provider's metadata and signing keys are fetched on each sign-in, so
+//! rotated keys are picked up without a restart.
+
+use std::sync::Arc;
+
+use axum::{
+    Extension, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    routing::get,
+};
+use hex_play_core::{
+    CoreServices, Error,
+    context::RequestContext,
+    types::{Age, Email},
+    user::{NewUser, User},
+};
+use openidconnect::{
+    AuthorizationCode, ClientId, ClientSecret, CsrfToken, EndpointMaybeSet, EndpointNotSet, EndpointSet, IssuerUrl, Nonce, PkceCodeChallenge, PkceCodeVerifier,
+    RedirectUrl, Scope, TokenResponse,
+    core::{CoreAuthenticationFlow, CoreClient, CoreIdTokenClaims, CoreProviderMetadata},
+    reqwest,
+    url::form_urlencoded,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{FrontendError, OidcConfig, server::AuthSession};
+
+const LOGIN_PATH: &str = "/auth/oidc/login";
+const CALLBACK_PATH: &str = "/auth/oidc/callback";
+
+/// Session key of the sign-in waiting for the provider's callback.
+const PENDING_LOGIN_KEY: &str = "oidc_pending_login";
+
+/// A client built from discovery, which always yields an authorization
+/// endpoint and may yield token and user info endpoints.
+type OidcClient = CoreClient<EndpointSet, EndpointNotSet, EndpointNotSet, EndpointNotSet, EndpointMaybeSet, EndpointMaybeSet>;
+
+/// The provider and client settings, checked at startup.
+pub(crate) struct OidcLogin {
+    issuer_url: IssuerUrl,
+    client_id: ClientId,
+    client_secret: Option<ClientSecret>,
+    redirect_url: RedirectUrl,
+    scopes: Vec<Scope>,
+    /// Prefix of the app's routes, such as `/app`, or empty.
+    base_path: String,
+    http_client: reqwest::Client,
+}
+
+impl OidcLogin {
+    pub(crate) fn new(config: &OidcConfig, base_path: Option<&str>) -> Result<Self, FrontendError> {
+        let issuer_url = IssuerUrl::new(config.issuer_url.clone()).map_err(|e| FrontendError::Oidc(format!("issuer_url: {e}")))?;
+        let redirect_url = RedirectUrl::new(config.redirect_url.clone()).map_err(|e| FrontendError::Oidc(format!("redirect_url: {e}")))?;
+        // Following redirects would let the provider's documents send
+        // requests anywhere.
+        let http_client = reqwest::ClientBuilder::new()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| FrontendError::Oidc(e.to_string()))?;
+
+        Ok(Self {
+            issuer_url,
+            client_id: ClientId::new(config.client_id.clone()),
+            client_secret: config.client_secret.as_ref().map(|secret| ClientSecret::new(secret.expose().to_string())),
+            redirect_url,
+            scopes: config.scopes.iter().map(|scope| Scope::new(scope.clone())).collect(),
+            base_path: base_path.map(|path| format!("/{path}")).unwrap_or_default(),
+            http_client,
+        })
+    }
+
+    /// Where the login page links to, so that signing in returns to the app
+    /// route `redirect`.
+    pub(crate) fn login_url(&self, redirect: &str) -> String {
+        let query = form_urlencoded::Serializer::new(String::new()).append_pair("redirect", redirect).finish();
+        format!("{}{LOGIN_PATH}?{query}", self.base_path)
+    }
+
+    async fn client(&self) -> Result<OidcClient, LoginError> {
+        let metadata = CoreProviderMetadata::discover_async(self.issuer_url.clone(), &self.http_client)
+            .await
+            .map_err(|e| LoginError::Provider(format!("discovery failed: {e}")))?;
+
+        Ok(CoreClient::from_provider_metadata(metadata, self.client_id.clone(), self.client_secret.clone()).set_redirect_uri(self.redirect_url.clone()))
+    }
+}
+
+pub(crate) fn get_routes(login: Arc<OidcLogin>) -> Router {
+    Router::new()
+        .route(LOGIN_PATH, get(start_login))
+        .route(CALLBACK_PATH, get(finish_login))
+        .with_state(login)
+}
+
+/// Why a sign-in failed. The details are logged; the browser only learns
+/// the status.
+#[derive(Debug, thiserror::Error)]
+enum LoginError {
+    #[error("rejected: {0}")]
+    Rejected(String),
+
+    #[error("provider error: {0}")]
+    Provider(String),
+
+    #[error(transparent)]
+    Core(#[from] Error),
+}
+
+impl IntoResponse for LoginError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            LoginError::Rejected(_) => StatusCode::UNAUTHORIZED,
+            LoginError::Provider(_) => StatusCode::BAD_GATEWAY,
+            LoginError::Core(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::warn!(error = %self, "OIDC sign-in failed");
+        (status, "Single sign-on failed").into_response()
+    }
+}
+
+/// A sign-in sent to the provider, kept in the session until it returns.
+#[derive(Serialize, Deserialize)]
+struct PendingLogin {
+    state: String,
+    nonce: String,
+    pkce_verifier: String,
+    redirect: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct LoginQuery {
+    redirect: Option<String>,
+}
+
+#[tracing::instrument(level = "trace", skip(login, auth))]
+async fn start_login(
+    State(login): State<Arc<OidcLogin>>,
+    Extension(auth): Extension<AuthSession>,
+    Query(query): Query<LoginQuery>,
+) -> Result<Redirect, LoginError> {
+    let client = login.client().await?;
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let (url, state, nonce) = client
+        .authorize_url(CoreAuthenticationFlow::AuthorizationCode, CsrfToken::new_random, Nonce::new_random)
+        .add_scopes(login.scopes.iter().cloned())
+        .set_pkce_challenge(pkce_challenge)
+        .url();
+
+    auth.session.set(
+        PENDING_LOGIN_KEY,
+        PendingLogin {
+            state: state.into_secret(),
+            nonce: nonce.secret().clone(),
+            pkce_verifier: pkce_verifier.into_secret(),
+            redirect: query.redirect.filter(|redirect| is_app_path(redirect)).unwrap_or_else(|| "/".to_string()),
+        },
+    );
+
+    Ok(Redirect::to(url.as_str()))
+}
+
+/// What the provider sends back: a code and the state it was given, or an
+/// error if the user did not sign in.
+#[derive(Deserialize, Debug)]
+struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+#[tracing::instrument(level = "trace", skip(login, auth, core_services, query))]
+async fn finish_login(
+    State(login): State<Arc<OidcLogin>>,
+    Extension(auth): Extension<AuthSession>,
+    Extension(core_services): Extension<Arc<CoreServices>>,
+    Query(query): Query<CallbackQuery>,
+) -> Result<Redirect, LoginError> {
+    let pending: PendingLogin = auth
+        .session
+        .get(PENDING_LOGIN_KEY)
+        .ok_or_else(|| LoginError::Rejected("no sign-in in progress".to_string()))?;
+    // A state and code are good for one attempt only.
+    auth.session.remove(PENDING_LOGIN_KEY);
+
+    if let Some(error) = query.error {
+        return Err(LoginError::Rejected(format!("provider returned `{error}`")));
+    }
+    let (Some(code), Some(state)) = (query.code, query.state) else {
+        return Err(LoginError::Rejected("missing code or state".to_string()));
+    };
+    if state != pending.state {
+        return Err(LoginError::Rejected("state does not match".to_string()));
+    }
+
+    let client = login.client().await?;
+    let token_response = client
+        .exchange_code(AuthorizationCode::new(code))
+        .map_err(|e| LoginError::Provider(e.to_string()))?
+        .set_pkce_verifier(PkceCodeVerifier::new(pending.pkce_verifier))
+        .request_async(&login.http_client)
+        .await
+        .map_err(|e| LoginError::Provider(format!("code exchange failed: {e}")))?;
+    let id_token = token_response
+        .id_token()
+        .ok_or_else(|| LoginError::Provider("no ID token in the token response".to_string()))?;
+    let claims = id_token
+        .claims(&client.id_token_verifier(), &Nonce::new(pending.nonce))
+        .map_err(|e| LoginError::Rejected(format!("invalid ID token: {e}")))?;
+
+    let user = find_or_add_user(&core_services, claims).await?;
+    // A new session id for the signed-in session, so one planted in the
+    // browser beforehand is not signed in along with it.
+    auth.session.renew();
+    auth.login_user(user.id);
+
+    Ok(Redirect::to(&format!("{}{}", login.base_path, pending.redirect)))
+}
+
+/// The user with the token's email, added on their first sign-in as the
+/// user directory describes them, or as the token does if it knows no one
+/// with the email.
+async fn find_or_add_user(core_services: &CoreServices, claims: &CoreIdTokenClaims) -> Result<User, LoginError> {
+    let email = verified_email(claims)?;
+
+    let context = RequestContext::internal();
+    if let Some(user) = core_services.user_service.find_by_email(&context, email.clone()).await? {
+        return Ok(user);
+    }
+
+    let new_user = match core_services.user_service.find_in_directory(&context, email.clone()).await? {
+        Some(user) => user,
+        None => {
+            let name = claims
+                .name()
+                .and_then(|name| name.get(None))
+                .map(|name| name.to_string())
+                .or_else(|| claims.preferred_username().map(|username| username.to_string()))
+                .unwrap_or_else(|| email.as_str().to_string());
+            NewUser {
+                name,
+                email,
+                age: Age::default(),
+            }
+        }
+    };
+    let user = core_services.user_service.add_user(&context, new_user).await?;
+    tracing::info!(user_id = %user.id, "Added user on first OIDC sign-in");
+
+    Ok(user)
+}
+
+/// The token's email, if the provider vouches for it. Linking by email
+/// trusts the provider to have checked it, so a token that doesn't say the
+/// email is verified is rejected as well as one that says it isn't.
+fn verified_email(claims: &CoreIdTokenClaims) -> Result<Email, LoginError> {
+    let email = claims
+        .email()
+        .ok_or_else(|| LoginError::Rejected("ID token has no email; is the `email` scope asked for?".to_string()))?;
+    if claims.email_verified() != Some(true) {
+        return Err(LoginError::Rejected("email is not verified".to_string()));
+    }
+    Email::new(email.as_str()).map_err(|e| LoginError::Rejected(e.to_string()))
+}
+
+/// Whether `redirect` is a path within the app, so that signing in cannot
+/// send the browser elsewhere.
+fn is_app_path(redirect: &str) -> bool {
+    redirect.starts_with('/') && !redirect.starts_with("//") && !redirect.starts_with("/\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, Utc};
+    use openidconnect::{
+        Audience, EmptyAdditionalClaims, EndUserEmail, IssuerUrl, StandardClaims, SubjectIdentifier,
+        core::{CoreGenderClaim, CoreIdTokenClaims},
+    };
+
+    use super::{LoginError, verified_email};
+
+    // ===================
+    // Tests: verified_email
+    // ===================
+    #[test]
+    fn test_verified_email_is_linked() {
+        let claims = claims(Some(true));
+
+        let email = verified_email(&claims).unwrap();
+
+        assert_eq!(email.as_str(), "alice@example.com");
+    }
+
+    #[test]
+    fn test_unverified_email_is_rejected() {
+        let claims = claims(Some(false));
+
+        let result = verified_email(&claims);
+
+        assert!(matches!(result, Err(LoginError::Rejected(_))));
+    }
+
+    #[test]
+    fn test_email_without_verified_claim_is_rejected() {
+        let claims = claims(None);
+
+        let result = verified_email(&claims);
+
+        assert!(matches!(result, Err(LoginError::Rejected(_))));
+    }
+
+    // ===================
+    // Test Helpers
+    // ===================
+    fn claims(email_verified: Option<bool>) -> CoreIdTokenClaims {
+        let standard_claims = StandardClaims::<CoreGenderClaim>::new(SubjectIdentifier::new("alice".to_string()))
+            .set_email(Some(EndUserEmail::new("alice@example.com".to_string())))
+            .set_email_verified(email_verified);
+        CoreIdTokenClaims::new(
+            IssuerUrl::new("https://issuer.example.com".to_string()).unwrap(),
+            vec![Audience::new("hex-play".to_string())],
+            Utc::now() + TimeDelta::minutes(5),
+            Utc::now(),
+            standard_claims,
+            EmptyAdditionalClaims {},
+        )
+    }
+}
@@ -0,0 +1,92 @@
+use dioxus::prelude::*;
+use fluent::FluentArgs;
+
+use crate::{
+    i18n::I18n,
+    stats::{UserStatsResponse, get_user_stats},
+};
+
+#[component]
+pub(crate) fn DashboardPage() -> Element {
+    let stats = use_server_future(get_user_stats)?;
+    let i18n: I18n = use_context();
+
+    rsx! {
+        div { class: "p-6 space-y-6",
+            h1 { class: "text-2xl font-bold", {i18n.t("dashboard-title")} }
+            match &*stats.read() {
+                Some(Ok(stats)) => rsx! {
+                    Charts { stats: stats.clone() }
+                },
+                Some(Err(e)) => rsx! {
+                    p { class: "form-error text-sm", "{e}" }
+                },
+                None => rsx! {},
+            }
+        }
+    }
+}
+
+#[component]
+fn Charts(stats: UserStatsResponse) -> Element {
+    let i18n: I18n = use_context();
+    let mut args = FluentArgs::new();
+    args.set("count", stats.total);
+    let statuses = [
+        (i18n.t("dashboard-status-active"), stats.active),
+        (i18n.t("dashboard-status-inactive"), stats.inactive),
+        (i18n.t("dashboard-status-never-seen"), stats.never_seen),
+    ];
+    let most_signups = stats.signups_per_day.iter().map(|(_, count)| *count).max().unwrap_or_default();
+    let largest_age_group = stats.age_distribution.iter().map(|(_, _, count)| *count).max().unwrap_or_default();
+
+    rsx! {
+        p { class: "chart-label text-sm", {i18n.t_args("dashboard-total", &args)} }
+        div { class: "grid gap-6 md:grid-cols-2",
+            section { class: "chart-panel rounded border p-4 space-y-2",
+                h2 { class: "font-semibold", {i18n.t("dashboard-status")} }
+                for (label, count) in statuses {
+                    BarRow { key: "{label}", label: label.clone(), count, max: stats.total }
+                }
+            }
+            section { class: "chart-panel rounded border p-4 space-y-2",
+                h2 { class: "font-semibold", {i18n.t("dashboard-ages")} }
+                for (min, max, count) in stats.age_distribution.iter().copied() {
+                    BarRow { key: "{min}", label: format!("{min}–{max}"), count, max: largest_age_group }
+                }
+            }
+            section { class: "chart-panel rounded border p-4 space-y-2 md:col-span-2",
+                h2 { class: "font-semibold", {i18n.t("dashboard-signups")} }
+                div { class: "flex items-end gap-1 h-40",
+                    for (date, count) in stats.signups_per_day.iter().copied() {
+                        div {
+                            key: "{date}",
+                            class: "chart-bar flex-1 rounded-t",
+                            style: "height: {percent(count, most_signups)}%",
+                            title: "{date}: {count}",
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A labelled horizontal bar, as long as `count` is relative to `max`.
+#[component]
+fn BarRow(label: String, count: u64, max: u64) -> Element {
+    rsx! {
+        div { class: "flex items-center gap-3 text-sm",
+            span { class: "chart-label w-40 shrink-0", "{label}" }
+            div { class: "flex-1",
+                div { class: "chart-bar h-4 rounded", style: "width: {percent(count, max)}%" }
+            }
+            span { class: "w-12 text-right tabular-nums", "{count}" }
+        }
+    }
+}
+
+/// `count` as a percentage of `max`, 0 when `max` is.
+fn percent(count: u64, max: u64) -> u64 {
+    (count * 100).checked_div(max).unwrap_or_default()
+}
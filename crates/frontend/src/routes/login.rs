@@ -0,0 +1,33 @@
+use dioxus::prelude::*;
+
+use crate::{i18n::I18n, user::single_sign_on_url};
+
+/// Signing in goes through the OIDC provider, the only one who can vouch
+/// for a user. Without one set up, nobody can sign in.
+#[component]
+pub(crate) fn LoginPage(redirect: String) -> Element {
+    let i18n: I18n = use_context();
+    let sso_url = use_server_future(move || single_sign_on_url(redirect.clone()))?;
+
+    rsx! {
+        div { class: "p-6 max-w-sm space-y-4",
+            h1 { class: "text-2xl font-bold", {i18n.t("login-title")} }
+            match &*sso_url.read() {
+                Some(Ok(Some(url))) => rsx! {
+                    a {
+                        class: "block px-4 py-2 bg-indigo-600 text-white rounded hover:bg-indigo-700 text-sm text-center",
+                        href: "{url}",
+                        {i18n.t("login-sso")}
+                    }
+                },
+                Some(Ok(None)) => rsx! {
+                    p { class: "text-sm", {i18n.t("login-unavailable")} }
+                },
+                Some(Err(error)) => rsx! {
+                    p { class: "form-error text-sm", "{error}" }
+                },
+                None => rsx! {},
+            }
+        }
+    }
+}
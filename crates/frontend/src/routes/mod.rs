@@ -1,5 +1,11 @@
 pub(crate) mod books_page;
+mod dashboard_page;
 mod home;
+mod login;
+mod users_page;
 
 pub(crate) use books_page::BooksPage;
+pub(crate) use dashboard_page::DashboardPage;
 pub(crate) use home::Home;
+pub(crate) use login::LoginPage;
+pub(crate) use users_page::UsersPage;
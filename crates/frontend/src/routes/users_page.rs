@@ -0,0 +1,43 @@
+use dioxus::prelude::*;
+
+use crate::{i18n::I18n, user::get_users};
+
+#[component]
+pub(crate) fn UsersPage() -> Element {
+    let users = use_server_future(get_users)?;
+    let i18n: I18n = use_context();
+
+    rsx! {
+        div { class: "p-6 space-y-4",
+            h1 { class: "text-2xl font-bold", {i18n.t("users-title")} }
+            match &*users.read() {
+                Some(Ok(response)) => rsx! {
+                    table { class: "data-table min-w-full text-sm",
+                        thead {
+                            tr { class: "text-left",
+                                th { class: "px-3 py-2", {i18n.t("users-id")} }
+                                th { class: "px-3 py-2", {i18n.t("users-name")} }
+                                th { class: "px-3 py-2", {i18n.t("users-email")} }
+                                th { class: "px-3 py-2", {i18n.t("users-age")} }
+                            }
+                        }
+                        tbody {
+                            for user in response.users.iter() {
+                                tr { key: "{user.id}", class: "border-t",
+                                    td { class: "px-3 py-2", "{user.id}" }
+                                    td { class: "px-3 py-2", "{user.name}" }
+                                    td { class: "px-3 py-2", "{user.email}" }
+                                    td { class: "px-3 py-2", "{user.age}" }
+                                }
+                            }
+                        }
+                    }
+                },
+                Some(Err(e)) => rsx! {
+                    p { class: "form-error text-sm", "{e}" }
+                },
+                None => rsx! {},
+            }
+        }
+    }
+}
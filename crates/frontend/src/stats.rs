@@ -0,0 +1,51 @@
+use chrono::NaiveDate;
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "server")]
+use {
+    crate::server::AuthSession,
+    hex_play_core::{CoreServices, context::RequestContext, user::UserStats},
+    std::sync::Arc,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct UserStatsResponse {
+    pub total: u64,
+    pub active: u64,
+    pub inactive: u64,
+    pub never_seen: u64,
+    pub signups_per_day: Vec<(NaiveDate, u64)>,
+    /// `(min, max, count)` per age bucket, both ages included.
+    pub age_distribution: Vec<(i16, i16, u64)>,
+}
+
+#[cfg(feature = "server")]
+impl From<UserStats> for UserStatsResponse {
+    fn from(stats: UserStats) -> Self {
+        Self {
+            total: stats.total(),
+            active: stats.by_status.active,
+            inactive: stats.by_status.inactive,
+            never_seen: stats.by_status.never_seen,
+            signups_per_day: stats.signups_per_day.into_iter().map(|day| (day.date, day.count)).collect(),
+            age_distribution: stats
+                .by_age
+                .into_iter()
+                .map(|bucket| (*bucket.ages.start(), *bucket.ages.end(), bucket.count))
+                .collect(),
+        }
+    }
+}
+
+/// Aggregate figures about the users. Only signed-in users may see them.
+#[get("/api/v1/stats/users", auth: axum::Extension<AuthSession>, core_services: axum::Extension<Arc<CoreServices>>)]
+#[tracing::instrument(level = "trace", skip(auth, core_services))]
+pub(crate) async fn get_user_stats() -> Result<UserStatsResponse> {
+    auth.current_user
+        .as_ref()
+        .is_some_and(|user| user.permissions.contains(crate::server::USERS_VIEW))
+        .or_unauthorized("Sign in to see user statistics")?;
+
+    let stats = core_services.user_service.stats(&RequestContext::internal()).await?;
+    Ok(stats.into())
+}
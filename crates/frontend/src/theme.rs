@@ -0,0 +1,93 @@
+//! Light, dark or system theme. The choice is kept in localStorage for the
+//! browser and in a cookie, so server rendering starts in the same theme.
+
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const THEME_KEY: &str = "theme";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Theme {
+    Light,
+    Dark,
+    /// Follows the operating system's `prefers-color-scheme`.
+    #[default]
+    System,
+}
+
+impl Theme {
+    /// Value of the `data-theme` attribute the stylesheet keys off.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::System => "system",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "light" => Some(Theme::Light),
+            "dark" => Some(Theme::Dark),
+            "system" => Some(Theme::System),
+            _ => None,
+        }
+    }
+
+    /// The theme the toggle switches to next.
+    pub(crate) fn next(self) -> Self {
+        match self {
+            Theme::Light => Theme::Dark,
+            Theme::Dark => Theme::System,
+            Theme::System => Theme::Light,
+        }
+    }
+}
+
+/// The theme saved in the request's cookie.
+#[get("/api/theme", headers: axum::http::HeaderMap)]
+#[tracing::instrument(level = "trace", skip(headers))]
+async fn saved_theme() -> Result<Theme> {
+    let theme = headers
+        .get_all(axum::http::header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().strip_prefix("theme="))
+        .find_map(Theme::parse);
+    Ok(theme.unwrap_or_default())
+}
+
+/// Provides the theme as a `Signal<Theme>` context, starting from the
+/// cookie while server rendering and from localStorage once in the browser.
+pub(crate) fn use_theme_provider() -> Result<Signal<Theme>, RenderError> {
+    let saved = use_server_future(saved_theme)?;
+    let initial = match &*saved.read() {
+        Some(Ok(theme)) => *theme,
+        _ => Theme::default(),
+    };
+    let mut theme = use_context_provider(|| Signal::new(initial));
+
+    // Effects only run in the browser.
+    use_effect(move || {
+        spawn(async move {
+            let stored = document::eval(&format!("return localStorage.getItem('{THEME_KEY}');"))
+                .join::<Option<String>>()
+                .await;
+            if let Some(stored) = stored.ok().flatten().as_deref().and_then(Theme::parse) {
+                theme.set(stored);
+            }
+        });
+    });
+
+    Ok(theme)
+}
+
+/// Switches to `next` and remembers it in localStorage and the cookie.
+pub(crate) fn save_theme(mut theme: Signal<Theme>, next: Theme) {
+    theme.set(next);
+    let value = next.as_str();
+    document::eval(&format!(
+        "localStorage.setItem('{THEME_KEY}', '{value}'); document.cookie = '{THEME_KEY}={value}; path=/; max-age=31536000; samesite=lax';"
+    ));
+}
@@ -6,32 +6,39 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "server")]
 use {
     crate::server::AuthSession,
-    hex_play_core::{CoreServices, user::User},
+    hex_play_core::{CoreServices, context::RequestContext, user::User},
     std::sync::Arc,
 };
 
-#[derive(Serialize, Deserialize, Debug)]
-struct UserResponse {
-    id: u64,
-    token: String,
-    name: String,
-    email: String,
-    age: i64,
-    version: u64,
-    created_at: DateTime<Utc>,
-    updated_at: DateTime<Utc>,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct UserResponse {
+    pub id: u64,
+    pub token: String,
+    pub name: String,
+    pub email: String,
+    pub age: i64,
+    pub version: u64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ListUsersResponse {
-    users: Vec<UserResponse>,
+    pub(crate) users: Vec<UserResponse>,
+}
+
+/// The signed-in user, as pages show it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct SessionUser {
+    pub id: u64,
+    pub name: String,
 }
 
 #[cfg(feature = "server")]
 impl From<User> for UserResponse {
     fn from(user: User) -> Self {
         Self {
-            id: user.id,
+            id: user.id.value(),
             token: user.token.to_string(),
             name: user.name,
             email: user.email.into_inner(),
@@ -43,14 +50,19 @@ impl From<User> for UserResponse {
     }
 }
 
-#[get("/api/v1/user", core_services: axum::Extension<Arc<CoreServices>>)]
-#[tracing::instrument(level = "trace", skip(core_services))]
-async fn get_users() -> Result<ListUsersResponse, ServerFnError> {
+/// Lists the first page of users. Only signed-in users may.
+#[get("/api/v1/user", auth: axum::Extension<AuthSession>, core_services: axum::Extension<Arc<CoreServices>>)]
+#[tracing::instrument(level = "trace", skip(auth, core_services))]
+pub(crate) async fn get_users() -> Result<ListUsersResponse> {
+    auth.current_user
+        .as_ref()
+        .is_some_and(|user| user.permissions.contains(crate::server::USERS_VIEW))
+        .or_unauthorized("Sign in to list users")?;
+
     let users = core_services
         .user_service
-        .list_users(None, None)
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .list_users(&RequestContext::internal(), None, None, None)
+        .await?
         .into_iter()
         .map(Into::into)
         .collect();
@@ -58,17 +70,25 @@ async fn get_users() -> Result<ListUsersResponse, ServerFnError> {
     Ok(response)
 }
 
-/// We use the `auth::Session` extractor to get access to the current user
-/// session. This lets us modify the user session, log in/out, and access the
-/// current user.
-#[post("/api/user/login", auth: axum::Extension<AuthSession>)]
+/// Where to sign in through the OIDC provider, returning to the app route
+/// `redirect` afterwards, or `None` when no provider is set up.
+#[post("/api/user/sso", oidc_login: axum::Extension<Option<Arc<crate::oidc::OidcLogin>>>)]
+#[tracing::instrument(level = "trace", skip(oidc_login))]
+pub(crate) async fn single_sign_on_url(redirect: String) -> Result<Option<String>> {
+    Ok(oidc_login.as_ref().map(|oidc_login| oidc_login.login_url(&redirect)))
+}
+
+/// The signed-in user, or `None` for anonymous visitors.
+#[get("/api/user/session", auth: axum::Extension<AuthSession>)]
 #[tracing::instrument(level = "trace", skip(auth))]
-pub async fn login() -> Result<()> {
-    auth.login_user(2);
-    Ok(())
+pub async fn session_user() -> Result<Option<SessionUser>> {
+    Ok(auth.current_user.clone().filter(|user| !user.anonymous).map(|user| SessionUser {
+        id: user.id.value(),
+        name: user.username,
+    }))
 }
 
-/// Just like `login`, but this time we log out the user.
+/// Signs the current user out.
 #[post("/api/user/logout", auth: axum::Extension<AuthSession>)]
 #[tracing::instrument(level = "trace", skip(auth))]
 pub async fn logout() -> Result<()> {
@@ -76,8 +96,8 @@ pub async fn logout() -> Result<()> {
     Ok(())
 }
 
-/// We can access the current user via `auth.current_user`.
-/// We can have both anonymous user (id 1) and a logged in user (id 2).
+/// We can access the current user via `auth.current_user`, which is the
+/// anonymous user until someone signs in through the OIDC provider.
 ///
 /// Logged-in users will have more permissions which we can modify.
 #[post("/api/user/name", auth: axum::Extension<AuthSession>)]
@@ -102,7 +122,11 @@ pub async fn get_permissions() -> Result<HashSet<String>> {
     let user = auth.current_user.clone().unwrap();
 
     Auth::<AuthUser, UserId, BackendSessionPool>::build([axum::http::Method::GET], false)
-        .requires(Rights::any([Rights::permission("Category::View"), Rights::permission("Admin::View")]))
+        .requires(Rights::any([
+            Rights::permission("Category::View"),
+            Rights::permission("Admin::View"),
+            Rights::permission(crate::server::USERS_VIEW),
+        ]))
         .validate(&user, &axum::http::Method::GET, None)
         .await
         .or_unauthorized("You do not have permission to view categories")?;
@@ -3,6 +3,10 @@ mod user;
 
 #[cfg(feature = "postgres")]
 mod postgres;
+#[cfg(feature = "postgres")]
+mod row_security;
+#[cfg(feature = "postgres")]
+mod tenancy;
 
 #[cfg(feature = "sqlite")]
 #[cfg_attr(feature = "postgres", allow(dead_code))]
@@ -1,5 +1,6 @@
+use hex_play_core::{context::RequestContext, test_support::fixtures::a_user};
 use hex_play_database::create_repository_service;
-use sea_orm::Database;
+use sea_orm::{Database, prelude::Date};
 use testcontainers::{ImageExt as _, runners::AsyncRunner as _};
 use testcontainers_modules::mysql::Mysql;
 
@@ -17,3 +18,25 @@ pub async fn setup() -> TestContext {
 
     TestContext::new(core_services, container)
 }
+
+/// The stats and signup rollups are read and written with raw SQL, whose
+/// placeholders MySQL takes differently from Postgres and SQLite.
+#[tokio::test]
+async fn test_user_stats_and_signup_rollups() {
+    let ctx = setup().await;
+    let user_service = ctx.services.user_service.clone();
+    a_user().named("Alice").with_email("alice@test.com").aged(28).persisted(&*user_service).await;
+    a_user().named("Bob").with_email("bob@test.com").aged(45).persisted(&*user_service).await;
+
+    let all_days = user_service.refresh_signup_rollups(&RequestContext::internal(), None).await.unwrap();
+    let recent_days = user_service
+        .refresh_signup_rollups(&RequestContext::internal(), Date::from_ymd_opt(2000, 1, 1))
+        .await
+        .unwrap();
+    let stats = user_service.stats(&RequestContext::internal()).await.unwrap();
+
+    assert_eq!((all_days, recent_days), (1, 1));
+    assert_eq!(stats.by_status.never_seen, 2);
+    assert_eq!(stats.by_status.active + stats.by_status.inactive, 0);
+    assert_eq!(stats.signups_per_day.iter().map(|day| day.count).sum::<u64>(), 2);
+}
@@ -1,5 +1,5 @@
-use hex_play_database::create_repository_service;
-use sea_orm::Database;
+use hex_play_database::{DatabaseConfig, create_repository_service, create_repository_service_with_config};
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection, DbBackend, Statement};
 use testcontainers::{ImageExt as _, runners::AsyncRunner as _};
 use testcontainers_modules::postgres::Postgres;
 
@@ -17,3 +17,34 @@ pub async fn setup() -> TestContext {
 
     TestContext::new(core_services, container)
 }
+
+/// Same as [`setup`], but tuned by `config` and connected as the owner of a
+/// database of its own rather than as a superuser, which row-level security
+/// never applies to. Also returns that connection, for raw SQL.
+pub async fn setup_with_config(config: &DatabaseConfig) -> (TestContext, DatabaseConnection) {
+    let container = Postgres::default().with_tag("17").start().await.unwrap();
+    let host = container.get_host().await.unwrap();
+    let port = container.get_host_port_ipv4(5432).await.unwrap();
+
+    let admin = Database::connect(format!("postgres://postgres:postgres@{host}:{port}/postgres")).await.unwrap();
+    admin.execute_unprepared("CREATE ROLE app LOGIN PASSWORD 'app'").await.unwrap();
+    admin.execute_unprepared("CREATE DATABASE app OWNER app").await.unwrap();
+    admin.close().await.unwrap();
+
+    let db = Database::connect(format!("postgres://app:app@{host}:{port}/app")).await.unwrap();
+    let repository_service = create_repository_service_with_config(db.clone(), config).await.unwrap();
+    let core_services = hex_play_core::create_services(repository_service).unwrap();
+
+    (TestContext::new(core_services, container), db)
+}
+
+/// Number of rows of `table` that `connection` sees.
+pub async fn count_rows(connection: &impl ConnectionTrait, table: &str) -> i64 {
+    connection
+        .query_one_raw(Statement::from_string(DbBackend::Postgres, format!("SELECT count(*) AS n FROM {table}")))
+        .await
+        .unwrap()
+        .unwrap()
+        .try_get("", "n")
+        .unwrap()
+}
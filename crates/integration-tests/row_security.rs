@@ -0,0 +1,176 @@
+//! Row-level security confining `users`, `user_info` and `user_list_view`
+//! to the tenant in `app.current_org`, through the services and through raw
+//! SQL alike.
+
+use hex_play_core::{
+    ErrorKind,
+    context::RequestContext,
+    types::Email,
+    user::{NewUser, User, UserId},
+};
+use hex_play_database::DatabaseConfig;
+use sea_orm::{ConnectionTrait as _, DatabaseConnection, DatabaseTransaction, DbBackend, Statement, TransactionTrait as _};
+
+use crate::{
+    context::TestContext,
+    postgres::{count_rows, setup_with_config},
+};
+
+// ===================
+// Helpers
+// ===================
+fn tenant(tenant: &str) -> RequestContext {
+    RequestContext {
+        tenant: Some(tenant.to_string()),
+        ..RequestContext::internal()
+    }
+}
+
+/// Services and a connection confined by row-level security, with Alice
+/// added by acme and Bob by globex, each with a `user_info` row.
+async fn setup() -> (TestContext, DatabaseConnection, User, User) {
+    let (ctx, database) = setup_with_config(&DatabaseConfig {
+        row_level_security: true,
+        ..DatabaseConfig::default()
+    })
+    .await;
+    let user_service = ctx.services.user_service.clone();
+    let alice = user_service
+        .add_user(&tenant("acme"), NewUser::new("Alice", "alice@test.com", 28).unwrap())
+        .await
+        .unwrap();
+    let bob = user_service
+        .add_user(&tenant("globex"), NewUser::new("Bob", "bob@test.com", 45).unwrap())
+        .await
+        .unwrap();
+    for (org, user) in [("acme", &alice), ("globex", &bob)] {
+        let transaction = begin_as(&database, Some(org)).await;
+        transaction.execute_unprepared(&insert_info(user.id)).await.unwrap();
+        transaction.commit().await.unwrap();
+    }
+
+    (ctx, database, alice, bob)
+}
+
+/// A transaction with `app.current_org` set to `tenant`, left unset
+/// without one. Rolled back unless committed.
+async fn begin_as(database: &DatabaseConnection, tenant: Option<&str>) -> DatabaseTransaction {
+    let transaction = database.begin().await.unwrap();
+    if let Some(tenant) = tenant {
+        transaction
+            .execute_raw(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "SELECT set_config('app.current_org', $1, true)",
+                [tenant.into()],
+            ))
+            .await
+            .unwrap();
+    }
+    transaction
+}
+
+fn insert_info(id: UserId) -> String {
+    format!("INSERT INTO user_info (user_id, avatar_key, avatar_variants_ready, updated_at) VALUES ({id}, 'avatar', false, now())")
+}
+
+/// Rows `sql` affects in a transaction of `tenant`, rolled back after.
+async fn rows_affected_as(database: &DatabaseConnection, tenant: Option<&str>, sql: &str) -> u64 {
+    begin_as(database, tenant).await.execute_unprepared(sql).await.unwrap().rows_affected()
+}
+
+// ===================
+// Tests: reads
+// ===================
+#[tokio::test]
+async fn test_tenant_reads_only_its_own_rows() {
+    let (ctx, database, alice, _) = setup().await;
+    let user_service = ctx.services.user_service.clone();
+    let acme = tenant("acme");
+
+    let listed = user_service.list_users(&acme, None, None, None).await.unwrap();
+    let found = user_service.find_by_email(&acme, Email::new("bob@test.com").unwrap()).await.unwrap();
+    let counted = user_service.count_users(&acme).await.unwrap();
+
+    assert_eq!(listed.iter().map(|user| user.id).collect::<Vec<_>>(), [alice.id]);
+    assert!(found.is_none());
+    assert_eq!(counted, 1);
+
+    let transaction = begin_as(&database, Some("acme")).await;
+    for table in ["users", "user_info", "user_list_view"] {
+        assert_eq!(count_rows(&transaction, table).await, 1, "{table}");
+    }
+}
+
+// ===================
+// Tests: writes
+// ===================
+#[tokio::test]
+async fn test_tenant_cannot_write_rows_of_another() {
+    let (ctx, database, _, bob) = setup().await;
+    let user_service = ctx.services.user_service.clone();
+    let acme = tenant("acme");
+
+    let deleted = user_service.delete_user(&acme, bob.id).await.unwrap_err();
+    let updated = user_service
+        .update_user(
+            &acme,
+            User {
+                name: "Mallory".to_string(),
+                ..bob.clone()
+            },
+        )
+        .await;
+
+    assert_eq!(deleted.kind(), ErrorKind::NotFound);
+    assert!(updated.is_err());
+
+    for sql in [
+        format!("UPDATE users SET name = 'Mallory' WHERE id = {}", bob.id),
+        format!("UPDATE user_list_view SET name = 'Mallory' WHERE id = {}", bob.id),
+        format!("UPDATE user_info SET avatar_key = 'mallory' WHERE user_id = {}", bob.id),
+        format!("DELETE FROM user_info WHERE user_id = {}", bob.id),
+        format!("DELETE FROM users WHERE id = {}", bob.id),
+    ] {
+        assert_eq!(rows_affected_as(&database, Some("acme"), &sql).await, 0, "{sql}");
+    }
+    // Rows the tenant can't see can't be added for another tenant either.
+    let claimed = begin_as(&database, Some("acme"))
+        .await
+        .execute_unprepared(
+            "INSERT INTO users (id, token, name, email, age, version, created_at, updated_at, org_id) \
+             VALUES (999999, 'mallory', 'Mallory', 'mallory@test.com', 30, 1, now(), now(), 'globex')",
+        )
+        .await;
+    let info = begin_as(&database, Some("acme")).await.execute_unprepared(&insert_info(bob.id)).await;
+
+    assert!(claimed.is_err());
+    assert!(info.is_err());
+
+    let bob_now = user_service.find_by_id(&tenant("globex"), bob.id).await.unwrap().unwrap();
+    assert_eq!((bob_now.name.as_str(), bob_now.version), ("Bob", bob.version));
+}
+
+// ===================
+// Tests: missing setting
+// ===================
+#[tokio::test]
+async fn test_missing_tenant_setting_denies_access() {
+    let (ctx, database, alice, _) = setup().await;
+    let user_service = ctx.services.user_service.clone();
+
+    let found = user_service
+        .find_by_email(&RequestContext::internal(), Email::new("alice@test.com").unwrap())
+        .await
+        .unwrap();
+    let counted = user_service.count_users(&RequestContext::internal()).await.unwrap();
+
+    assert!(found.is_none());
+    assert_eq!(counted, 0);
+
+    let transaction = begin_as(&database, None).await;
+    for table in ["users", "user_info", "user_list_view"] {
+        assert_eq!(count_rows(&transaction, table).await, 0, "{table}");
+    }
+    let sql = format!("UPDATE users SET name = 'Mallory' WHERE id = {}", alice.id);
+    assert_eq!(rows_affected_as(&database, None, &sql).await, 0);
+}
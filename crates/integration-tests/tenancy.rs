@@ -0,0 +1,81 @@
+//! Tenants kept in Postgres schemas of their own, each reached through the
+//! `search_path` of its transactions.
+
+use std::collections::HashMap;
+
+use hex_play_core::{Error, context::RequestContext, types::Email, user::NewUser};
+use hex_play_database::DatabaseConfig;
+use sea_orm::DatabaseConnection;
+
+use crate::{
+    context::TestContext,
+    postgres::{count_rows, setup_with_config},
+};
+
+// ===================
+// Helpers
+// ===================
+fn tenant(tenant: &str) -> RequestContext {
+    RequestContext {
+        tenant: Some(tenant.to_string()),
+        ..RequestContext::internal()
+    }
+}
+
+/// Services and a connection with acme and globex in schemas of their own.
+async fn setup() -> (TestContext, DatabaseConnection) {
+    setup_with_config(&DatabaseConfig {
+        tenant_schemas: HashMap::from([
+            ("acme".to_string(), "tenant_acme".to_string()),
+            ("globex".to_string(), "tenant_globex".to_string()),
+        ]),
+        ..DatabaseConfig::default()
+    })
+    .await
+}
+
+// ===================
+// Tests: tenant schemas
+// ===================
+#[tokio::test]
+async fn test_tenant_sees_only_its_own_schema() {
+    let (ctx, database) = setup().await;
+    let user_service = ctx.services.user_service.clone();
+    user_service
+        .add_user(&tenant("acme"), NewUser::new("Alice", "alice@test.com", 28).unwrap())
+        .await
+        .unwrap();
+    user_service
+        .add_user(&tenant("globex"), NewUser::new("Bob", "bob@test.com", 45).unwrap())
+        .await
+        .unwrap();
+
+    for (org, own, other) in [("acme", "Alice", "bob@test.com"), ("globex", "Bob", "alice@test.com")] {
+        let listed = user_service.list_users(&tenant(org), None, None, None).await.unwrap();
+        let found = user_service.find_by_email(&tenant(org), Email::new(other).unwrap()).await.unwrap();
+
+        assert_eq!(listed.iter().map(|user| user.name.as_str()).collect::<Vec<_>>(), [own], "{org}");
+        assert!(found.is_none(), "{org}");
+    }
+    assert!(user_service.list_users(&RequestContext::internal(), None, None, None).await.unwrap().is_empty());
+
+    assert_eq!(count_rows(&database, "tenant_acme.users").await, 1);
+    assert_eq!(count_rows(&database, "tenant_globex.users").await, 1);
+    assert_eq!(count_rows(&database, "public.users").await, 0);
+}
+
+#[tokio::test]
+async fn test_unknown_tenant_is_refused_rather_than_given_the_default_schema() {
+    let (ctx, database) = setup().await;
+    let user_service = ctx.services.user_service.clone();
+
+    let added = user_service
+        .add_user(&tenant("initech"), NewUser::new("Carol", "carol@test.com", 33).unwrap())
+        .await
+        .unwrap_err();
+    let listed = user_service.list_users(&tenant("initech"), None, None, None).await.unwrap_err();
+
+    assert!(matches!(added, Error::UnknownTenant(org) if org == "initech"));
+    assert!(matches!(listed, Error::UnknownTenant(org) if org == "initech"));
+    assert_eq!(count_rows(&database, "public.users").await, 0);
+}
@@ -1,4 +1,4 @@
-use hex_play_core::user::{NewUser, PartialUserUpdate};
+use hex_play_core::{ErrorKind, context::RequestContext, test_support::fixtures::a_user, user::PartialUserUpdate};
 
 use crate::setup;
 
@@ -8,8 +8,7 @@ async fn test_user_lifecycle() {
     let user_service = ctx.services.user_service.clone();
 
     // 1. Create user
-    let new_user = NewUser::new("Alice", "alice@test.com", 28).unwrap();
-    let created = user_service.add_user(new_user).await.unwrap();
+    let created = a_user().named("Alice").with_email("alice@test.com").aged(28).persisted(&*user_service).await;
 
     let token = created.token;
     let created_version = created.version;
@@ -21,7 +20,7 @@ async fn test_user_lifecycle() {
     assert_eq!(created_version, 1);
 
     // 2. Fetch by token
-    let fetched = user_service.find_by_token(token).await.unwrap().unwrap();
+    let fetched = user_service.find_by_token(&RequestContext::internal(), token).await.unwrap().unwrap();
 
     assert_eq!(fetched.name, "Alice");
     assert_eq!(fetched.email.to_string(), "alice@test.com");
@@ -34,16 +33,51 @@ async fn test_user_lifecycle() {
     let update = PartialUserUpdate::new(None::<String>, Some("alice.updated@test.com"), None).unwrap();
     update.apply_to(&mut user_to_update);
 
-    let updated = user_service.update_user(user_to_update).await.unwrap();
+    let updated = user_service.update_user(&RequestContext::internal(), user_to_update).await.unwrap();
 
     assert_eq!(updated.email.to_string(), "alice.updated@test.com");
-    assert!(updated.version > created_version);
+    assert_eq!(updated.version, created_version + 1);
     assert!(updated.updated_at >= created_updated_at);
 
     // 4. Verify update persisted
-    let verified = user_service.find_by_token(token).await.unwrap().unwrap();
+    let verified = user_service.find_by_token(&RequestContext::internal(), token).await.unwrap().unwrap();
 
     assert_eq!(verified.email.to_string(), "alice.updated@test.com");
     assert_eq!(verified.version, updated.version);
     assert_eq!(verified.updated_at, updated.updated_at);
+
+    // 5. A second update with the stale version is rejected
+    let mut stale = verified.clone();
+    stale.version = created_version;
+    stale.name = "Stale".to_string();
+    let err = user_service.update_user(&RequestContext::internal(), stale).await.unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::Conflict);
+}
+
+#[tokio::test]
+async fn test_list_users_pages_without_repeats() {
+    let ctx = setup().await;
+    let user_service = ctx.services.user_service.clone();
+    let context = RequestContext::internal();
+
+    let mut ids = Vec::new();
+    for _ in 0..12 {
+        ids.push(a_user().aged(30).persisted(&*user_service).await.id);
+    }
+    ids.sort();
+
+    // Each page's last id is the cursor of the next.
+    let mut seen = Vec::new();
+    let mut after_id = None;
+    loop {
+        let page = user_service.list_users(&context, after_id, Some(5), None).await.unwrap();
+        let Some(last) = page.last() else {
+            break;
+        };
+        after_id = Some(last.id);
+        seen.extend(page.iter().map(|user| user.id));
+    }
+
+    assert_eq!(seen, ids);
 }
@@ -0,0 +1,87 @@
+//! Image processing adapters for the core `ImageProcessor` port.
+
+use std::io::Cursor;
+
+use hex_play_core::{Error, image::ImageProcessor};
+use image::{ImageFormat, ImageReader, Limits};
+
+/// Largest width or height decoded, so a small file declaring a huge image
+/// cannot exhaust memory.
+const MAX_SOURCE_DIMENSION: u32 = 8192;
+
+/// Decodes PNG, JPEG, GIF and WebP images with the `image` crate and
+/// re-encodes them as PNG.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RasterImageProcessor;
+
+impl ImageProcessor for RasterImageProcessor {
+    fn resize(&self, image: &[u8], max_dimension: u32) -> Result<Vec<u8>, Error> {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+        limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+
+        let mut reader = ImageReader::new(Cursor::new(image)).with_guessed_format().map_err(image_error)?;
+        reader.limits(limits);
+        let decoded = reader.decode().map_err(image_error)?;
+
+        let resized = if decoded.width() > max_dimension || decoded.height() > max_dimension {
+            decoded.thumbnail(max_dimension, max_dimension)
+        } else {
+            decoded
+        };
+
+        let mut encoded = Cursor::new(Vec::new());
+        resized.write_to(&mut encoded, ImageFormat::Png).map_err(image_error)?;
+        Ok(encoded.into_inner())
+    }
+}
+
+fn image_error(error: impl std::fmt::Display) -> Error {
+    Error::ImageProcessing(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use hex_play_core::{Error, image::ImageProcessor};
+    use image::{DynamicImage, ImageFormat};
+
+    use super::RasterImageProcessor;
+
+    fn encode(image: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut encoded = Cursor::new(Vec::new());
+        image.write_to(&mut encoded, format).unwrap();
+        encoded.into_inner()
+    }
+
+    // ===================
+    // Tests: RasterImageProcessor
+    // ===================
+    #[test]
+    fn test_resize_keeps_aspect_ratio() {
+        let source = encode(&DynamicImage::new_rgb8(200, 100), ImageFormat::Jpeg);
+
+        let resized = RasterImageProcessor.resize(&source, 64).unwrap();
+
+        let decoded = image::load_from_memory_with_format(&resized, ImageFormat::Png).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (64, 32));
+    }
+
+    #[test]
+    fn test_resize_does_not_upscale() {
+        let source = encode(&DynamicImage::new_rgba8(20, 10), ImageFormat::Png);
+
+        let resized = RasterImageProcessor.resize(&source, 64).unwrap();
+
+        let decoded = image::load_from_memory_with_format(&resized, ImageFormat::Png).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (20, 10));
+    }
+
+    #[test]
+    fn test_resize_rejects_undecodable_image() {
+        let result = RasterImageProcessor.resize(b"not an image", 64);
+
+        assert!(matches!(result, Err(Error::ImageProcessing(_))));
+    }
+}
@@ -0,0 +1,83 @@
+//! Object storage adapters for the [`ObjectStorage`] port: a local directory
+//! for development and, with the `s3` feature, any S3-compatible store.
+
+use std::sync::Arc;
+
+use hex_play_core::{
+    Error,
+    storage::{NoObjectStorage, ObjectStorage},
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+mod local;
+#[cfg(feature = "s3")]
+mod s3;
+
+pub use local::LocalObjectStorage;
+#[cfg(feature = "s3")]
+pub use s3::S3ObjectStorage;
+
+/// Where objects are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    Local,
+    S3,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct StorageConfig {
+    /// (optional) `local` or `s3`, e.g. `HPLAY__STORAGE__BACKEND=local`.
+    /// Unset leaves object storage, and so avatar uploads, disabled.
+    #[serde(default)]
+    pub backend: Option<StorageBackend>,
+
+    /// (optional) Directory the `local` backend keeps objects in, e.g.
+    /// `HPLAY__STORAGE__LOCAL_DIR=/var/lib/hex-play/objects`.
+    #[serde(default)]
+    pub local_dir: Option<String>,
+
+    /// (optional) URL the `local` directory is served under; its object URLs
+    /// point below it, e.g. `HPLAY__STORAGE__PUBLIC_URL=http://localhost:8081`.
+    #[serde(default)]
+    pub public_url: Option<String>,
+
+    /// (optional) Bucket for the `s3` backend, e.g.
+    /// `HPLAY__STORAGE__BUCKET=hex-play`. Credentials come from the usual
+    /// `AWS_*` environment variables.
+    #[serde(default)]
+    pub bucket: Option<String>,
+
+    /// (optional) Region of the bucket, e.g.
+    /// `HPLAY__STORAGE__REGION=eu-west-1`.
+    #[serde(default)]
+    pub region: Option<String>,
+
+    /// (optional) Endpoint of an S3-compatible store other than AWS, e.g.
+    /// `HPLAY__STORAGE__ENDPOINT=http://localhost:9000`.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+}
+
+/// Creates the storage `config` selects, or [`NoObjectStorage`] without a
+/// backend.
+pub fn create_object_storage(config: &StorageConfig) -> Result<Arc<dyn ObjectStorage>, Error> {
+    match config.backend {
+        None => Ok(Arc::new(NoObjectStorage)),
+        Some(StorageBackend::Local) => {
+            let dir = config
+                .local_dir
+                .as_deref()
+                .ok_or_else(|| Error::Storage("the local backend needs LOCAL_DIR".into()))?;
+            Ok(Arc::new(LocalObjectStorage::new(dir, config.public_url.clone())))
+        }
+        #[cfg(feature = "s3")]
+        Some(StorageBackend::S3) => {
+            let bucket = config.bucket.as_deref().ok_or_else(|| Error::Storage("the s3 backend needs BUCKET".into()))?;
+            Ok(Arc::new(S3ObjectStorage::new(bucket, config.region.as_deref(), config.endpoint.as_deref())?))
+        }
+        #[cfg(not(feature = "s3"))]
+        Some(StorageBackend::S3) => Err(Error::Storage("built without the s3 feature".into())),
+    }
+}
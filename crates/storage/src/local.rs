@@ -0,0 +1,157 @@
+//! Objects as files in a local directory, for development and tests.
+
+use std::{
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+use hex_play_core::{
+    Error,
+    storage::{ObjectStorage, StoredObject},
+};
+
+/// Suffix of the file next to each object that holds its content type.
+const CONTENT_TYPE_SUFFIX: &str = ".content-type";
+
+/// Keeps each object in a file named by its key below `root`.
+///
+/// There is nothing to sign URLs with, so `presigned_url` gives the object's
+/// path below `public_url` and the expiry is not enforced. Whatever serves
+/// the directory decides who may read it.
+#[derive(Debug, Clone)]
+pub struct LocalObjectStorage {
+    root: PathBuf,
+    public_url: Option<String>,
+}
+
+impl LocalObjectStorage {
+    pub fn new(root: impl Into<PathBuf>, public_url: Option<String>) -> Self {
+        Self {
+            root: root.into(),
+            public_url: public_url.map(|url| url.trim_end_matches('/').to_string()),
+        }
+    }
+
+    /// The file for `key`. Keys may only name paths below the root.
+    fn path(&self, key: &str) -> Result<PathBuf, Error> {
+        let relative = Path::new(key);
+        if key.is_empty() || key.ends_with(CONTENT_TYPE_SUFFIX) || !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+            return Err(Error::Storage(format!("invalid object key `{key}`")));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+fn content_type_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(CONTENT_TYPE_SUFFIX);
+    path.with_file_name(file_name)
+}
+
+fn storage_error(error: std::io::Error) -> Error {
+    Error::Storage(error.to_string())
+}
+
+#[async_trait::async_trait]
+impl ObjectStorage for LocalObjectStorage {
+    #[tracing::instrument(level = "trace", skip(self, bytes))]
+    async fn put(&self, key: &str, content_type: &str, bytes: Vec<u8>) -> Result<(), Error> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(storage_error)?;
+        }
+        tokio::fs::write(content_type_path(&path), content_type).await.map_err(storage_error)?;
+        tokio::fs::write(&path, bytes).await.map_err(storage_error)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get(&self, key: &str) -> Result<Option<StoredObject>, Error> {
+        let path = self.path(key)?;
+        let bytes = match tokio::fs::read(&path).await {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(storage_error(error)),
+        };
+        let content_type = tokio::fs::read_to_string(content_type_path(&path))
+            .await
+            .unwrap_or_else(|_| "application/octet-stream".to_string());
+
+        Ok(Some(StoredObject { content_type, bytes }))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        let path = self.path(key)?;
+        for path in [content_type_path(&path), path] {
+            match tokio::fs::remove_file(&path).await {
+                Err(error) if error.kind() != ErrorKind::NotFound => return Err(storage_error(error)),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn presigned_url(&self, key: &str, _expires_in: Duration) -> Result<String, Error> {
+        self.path(key)?;
+        let public_url = self
+            .public_url
+            .as_deref()
+            .ok_or_else(|| Error::Storage("local storage has no public URL".into()))?;
+        Ok(format!("{public_url}/{key}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use hex_play_core::{Error, storage::ObjectStorage};
+
+    use super::LocalObjectStorage;
+
+    /// A storage in a fresh directory below the system temp directory.
+    fn storage(name: &str) -> (LocalObjectStorage, PathBuf) {
+        let root = std::env::temp_dir().join(format!("hex-play-storage-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        (LocalObjectStorage::new(&root, Some("http://localhost:8081/".into())), root)
+    }
+
+    // ===================
+    // Tests: LocalObjectStorage
+    // ===================
+    #[tokio::test]
+    async fn test_put_get_delete() {
+        let (storage, root) = storage("roundtrip");
+
+        storage.put("avatars/U_1/1.png", "image/png", vec![1, 2, 3]).await.unwrap();
+        let object = storage.get("avatars/U_1/1.png").await.unwrap().unwrap();
+        storage.delete("avatars/U_1/1.png").await.unwrap();
+
+        assert_eq!(object.content_type, "image/png");
+        assert_eq!(object.bytes, [1, 2, 3]);
+        assert_eq!(storage.get("avatars/U_1/1.png").await.unwrap(), None);
+        storage.delete("avatars/U_1/1.png").await.unwrap();
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejects_keys_outside_root() {
+        let (storage, _) = storage("escape");
+
+        for key in ["../etc/passwd", "/etc/passwd", "avatars/../../a.png", ""] {
+            let result = storage.put(key, "image/png", vec![1]).await;
+            assert!(matches!(result, Err(Error::Storage(_))), "{key} accepted");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_presigned_url_is_below_public_url() {
+        let (storage, _) = storage("url");
+
+        let url = storage.presigned_url("avatars/U_1/1.png", Duration::from_secs(60)).await.unwrap();
+
+        assert_eq!(url, "http://localhost:8081/avatars/U_1/1.png");
+    }
+}
@@ -0,0 +1,96 @@
+//! Objects in an S3 bucket, or any store speaking the S3 API.
+
+use std::time::Duration;
+
+use hex_play_core::{
+    Error,
+    storage::{ObjectStorage, StoredObject},
+};
+use object_store::{
+    Attribute, Attributes, ObjectStore, PutOptions, PutPayload,
+    aws::{AmazonS3, AmazonS3Builder},
+    path::Path,
+    signer::Signer,
+};
+
+pub struct S3ObjectStorage {
+    store: AmazonS3,
+}
+
+impl S3ObjectStorage {
+    /// Connects to `bucket` with credentials from the `AWS_*` environment
+    /// variables. `endpoint` selects a store other than AWS.
+    pub fn new(bucket: &str, region: Option<&str>, endpoint: Option<&str>) -> Result<Self, Error> {
+        let mut builder = AmazonS3Builder::from_env().with_bucket_name(bucket);
+        if let Some(region) = region {
+            builder = builder.with_region(region);
+        }
+        if let Some(endpoint) = endpoint {
+            builder = builder.with_endpoint(endpoint).with_allow_http(endpoint.starts_with("http://"));
+        }
+
+        Ok(Self {
+            store: builder.build().map_err(storage_error)?,
+        })
+    }
+}
+
+fn storage_error(error: object_store::Error) -> Error {
+    Error::Storage(error.to_string())
+}
+
+#[async_trait::async_trait]
+impl ObjectStorage for S3ObjectStorage {
+    #[tracing::instrument(level = "trace", skip(self, bytes))]
+    async fn put(&self, key: &str, content_type: &str, bytes: Vec<u8>) -> Result<(), Error> {
+        let mut attributes = Attributes::new();
+        attributes.insert(Attribute::ContentType, content_type.to_string().into());
+        let options = PutOptions {
+            attributes,
+            ..PutOptions::default()
+        };
+
+        self.store
+            .put_opts(&Path::from(key), PutPayload::from(bytes), options)
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get(&self, key: &str) -> Result<Option<StoredObject>, Error> {
+        let result = match self.store.get(&Path::from(key)).await {
+            Ok(result) => result,
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(error) => return Err(storage_error(error)),
+        };
+        let content_type = result
+            .attributes
+            .get(&Attribute::ContentType)
+            .map_or_else(|| "application/octet-stream".to_string(), |value| value.to_string());
+        let bytes = result.bytes().await.map_err(storage_error)?;
+
+        Ok(Some(StoredObject {
+            content_type,
+            bytes: bytes.to_vec(),
+        }))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        match self.store.delete(&Path::from(key)).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(error) => Err(storage_error(error)),
+        }
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn presigned_url(&self, key: &str, expires_in: Duration) -> Result<String, Error> {
+        let url = self
+            .store
+            .signed_url(http::Method::GET, &Path::from(key), expires_in)
+            .await
+            .map_err(storage_error)?;
+        Ok(url.to_string())
+    }
+}
@@ -0,0 +1,46 @@
+//! Benchmarks for the base-32 token codec.
+//!
+//! Run with `just bench`; see the justfile for saving and comparing baselines.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use hex_play_utils::{
+    define_token_prefix,
+    token::{Token, TokenId},
+};
+
+define_token_prefix!(BenchPrefix, "B_");
+
+fn bench_u64(c: &mut Criterion) {
+    let id = 0x0123_4567_89AB_CDEFu64;
+    let encoded = id.encode();
+
+    let mut group = c.benchmark_group("token_id/u64");
+    group.bench_function("encode", |b| b.iter(|| black_box(id).encode()));
+    group.bench_function("decode", |b| b.iter(|| u64::decode(black_box(&encoded))));
+    group.finish();
+}
+
+fn bench_u128(c: &mut Criterion) {
+    let id = 0x0123_4567_89AB_CDEF_FEDC_BA98_7654_3210u128;
+    let encoded = id.encode();
+
+    let mut group = c.benchmark_group("token_id/u128");
+    group.bench_function("encode", |b| b.iter(|| black_box(id).encode()));
+    group.bench_function("decode", |b| b.iter(|| u128::decode(black_box(&encoded))));
+    group.finish();
+}
+
+fn bench_token(c: &mut Criterion) {
+    let token = Token::<BenchPrefix>::new(0x0123_4567_89AB_CDEF);
+    let formatted = token.to_string();
+
+    let mut group = c.benchmark_group("token");
+    group.bench_function("to_string", |b| b.iter(|| black_box(token).to_string()));
+    group.bench_function("parse", |b| b.iter(|| Token::<BenchPrefix>::parse(black_box(&formatted))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_u64, bench_u128, bench_token);
+criterion_main!(benches);
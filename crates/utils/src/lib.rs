@@ -1 +1,3 @@
+pub mod secret;
+pub mod sensitive;
 pub mod token;
@@ -0,0 +1,98 @@
+//! Secret values such as credentials and keys.
+
+use std::{borrow::Cow, fmt};
+
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
+use serde::{Deserialize, Deserializer};
+use zeroize::Zeroizing;
+
+use crate::sensitive::Sensitive;
+
+/// A secret string. The memory holding it is zeroed when dropped and it is
+/// redacted from `Debug` output; read it with [`Secret::expose`].
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(Zeroizing<String>);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(Zeroizing::new(value.into()))
+    }
+
+    /// Returns the secret value. Avoid copying it into longer-lived strings.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Secret").field(&Sensitive::new(())).finish()
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+/// A string that is only ever written, so schema consumers know not to echo
+/// it back.
+impl JsonSchema for Secret {
+    fn schema_name() -> Cow<'static, str> {
+        "Secret".into()
+    }
+
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "writeOnly": true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Secret;
+
+    // ===================
+    // Tests: Secret
+    // ===================
+
+    #[test]
+    fn test_debug_is_redacted() {
+        let secret = Secret::new("postgres://app:s3cret@db/app");
+
+        assert_eq!(format!("{secret:?}"), "Secret([REDACTED])");
+        assert_eq!(secret.expose(), "postgres://app:s3cret@db/app");
+    }
+
+    #[test]
+    fn test_deserialize_from_string() {
+        let secret: Secret = serde_json::from_str(r#""s3cret""#).unwrap();
+
+        assert_eq!(secret.expose(), "s3cret");
+    }
+
+    #[test]
+    fn test_schema_is_write_only_string() {
+        let schema = schemars::schema_for!(Secret);
+
+        assert_eq!(schema.get("type"), Some(&serde_json::json!("string")));
+        assert_eq!(schema.get("writeOnly"), Some(&serde_json::json!(true)));
+    }
+}
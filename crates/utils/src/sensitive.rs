@@ -0,0 +1,87 @@
+//! Values kept out of logs and serialized output.
+
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Wraps a value so `Debug`, `Display` and serialization print `[REDACTED]`
+/// instead of it. Deserialization reads the plain value; read it back with
+/// [`Sensitive::expose`].
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Sensitive<T>(T);
+
+impl<T> Sensitive<T> {
+    pub const fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Sensitive<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> fmt::Debug for Sensitive<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> fmt::Display for Sensitive<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> Serialize for Sensitive<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Sensitive<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sensitive;
+
+    // ===================
+    // Tests: Sensitive
+    // ===================
+
+    #[test]
+    fn test_formatting_is_redacted() {
+        let value = Sensitive::new("john@example.com");
+
+        assert_eq!(format!("{value:?}"), "[REDACTED]");
+        assert_eq!(format!("{value}"), "[REDACTED]");
+        assert_eq!(*value.expose(), "john@example.com");
+    }
+
+    #[test]
+    fn test_serialize_is_redacted() {
+        assert_eq!(serde_json::to_string(&Sensitive::new(42)).unwrap(), r#""[REDACTED]""#);
+    }
+
+    #[test]
+    fn test_deserialize_reads_value() {
+        let value: Sensitive<String> = serde_json::from_str(r#""hash""#).unwrap();
+
+        assert_eq!(value.into_inner(), "hash");
+    }
+}